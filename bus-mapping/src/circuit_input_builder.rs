@@ -4,6 +4,7 @@
 mod access;
 mod block;
 mod call;
+mod chunk;
 mod execution;
 mod input_state_ref;
 #[cfg(test)]
@@ -13,16 +14,25 @@ mod transaction;
 use self::access::gen_state_access_trace;
 use crate::error::Error;
 use crate::evm::opcodes::{gen_associated_ops, gen_begin_tx_ops, gen_end_tx_ops};
-use crate::operation::{CallContextField, Operation, RWCounter, StartOp, RW};
+use crate::mpt::StateTrie;
+use crate::operation::{
+    AccountField, CallContextField, Operation, RWCounter, StartOp, TxReceiptField, RW,
+};
 use crate::rpc::GethClient;
 use crate::state_db::{self, CodeDB, StateDB};
 pub use access::{Access, AccessSet, AccessValue, CodeSource};
-pub use block::{Block, BlockContext};
+pub use block::{Block, BlockContext, BlockHead};
 pub use call::{Call, CallContext, CallKind};
+pub use chunk::Chunk;
 use core::fmt::Debug;
+use eth_types::evm_types::{Hardfork, ProgramCounter};
 use eth_types::sign_types::{pk_bytes_le, pk_bytes_swap_endianness, SignData};
 use eth_types::ToWord;
-use eth_types::{self, geth_types, Address, GethExecStep, GethExecTrace, Word};
+use eth_types::{
+    self,
+    geth_types::{self, block_header_rlp},
+    Address, BigEndianHash, GethExecStep, GethExecTrace, Word, H256,
+};
 use ethers_providers::JsonRpcClient;
 pub use execution::{
     CopyDataType, CopyEvent, CopyStep, ExecState, ExecStep, ExpEvent, ExpStep, NumberOrHash,
@@ -30,15 +40,17 @@ pub use execution::{
 pub use input_state_ref::CircuitInputStateRef;
 use itertools::Itertools;
 use log::warn;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 pub use transaction::{Transaction, TransactionContext};
 
 /// Circuit Setup Parameters
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CircuitsParams {
     /// Maximum number of rw operations in the state circuit (RwTable length /
     /// nummber of rows). This must be at least the number of rw operations
-    /// + 1, in order to allocate at least a Start row.
+    /// + 1, in order to allocate at least a Start row.  A block with more rw
+    /// operations can be proved in chunks, see [`Block::chunks`].
     pub max_rws: usize,
     // TODO: evm_rows: Maximum number of rows in the EVM Circuit
     /// Maximum number of txs in the Tx Circuit
@@ -49,10 +61,15 @@ pub struct CircuitsParams {
     pub max_copy_rows: usize,
     /// Maximum number of bytes supported in the Bytecode Circuit
     pub max_bytecode: usize,
+    /// Maximum number of rows in the MPT Circuit.  When 0, the MPT Circuit
+    /// contains as many rows as needed by the MPT updates.
+    pub max_mpt_rows: usize,
     // TODO: Rename for consistency
     /// Pad the keccak circuit with this number of invocations to a static
     /// capacity.  Number of keccak_f that the Keccak circuit will support.
     pub keccak_padding: Option<usize>,
+    /// Hardfork whose rules are used to process the block.
+    pub hardfork: Hardfork,
 }
 
 impl Default for CircuitsParams {
@@ -66,7 +83,9 @@ impl Default for CircuitsParams {
             // this lib tests
             max_copy_rows: 1000,
             max_bytecode: 512,
+            max_mpt_rows: 0,
             keccak_padding: None,
+            hardfork: Hardfork::default(),
         }
     }
 }
@@ -139,18 +158,19 @@ impl<'a> CircuitInputBuilder {
     ) -> Result<Transaction, Error> {
         let call_id = self.block_ctx.rwc.0;
 
-        self.block_ctx.call_map.insert(
+        self.block_ctx
+            .call_map
+            .insert(call_id, (self.block.txs.len(), 0));
+
+        Transaction::new(
             call_id,
-            (
-                eth_tx
-                    .transaction_index
-                    .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?
-                    .as_u64() as usize,
-                0,
-            ),
-        );
-
-        Transaction::new(call_id, &self.sdb, &mut self.code_db, eth_tx, is_success)
+            &self.sdb,
+            &mut self.code_db,
+            eth_tx,
+            self.block.number.low_u64(),
+            is_success,
+            self.block.circuits_params.hardfork,
+        )
     }
 
     /// Iterate over all generated CallContext RwCounterEndOfReversion
@@ -181,22 +201,119 @@ impl<'a> CircuitInputBuilder {
         eth_block: &EthBlock,
         geth_traces: &[eth_types::GethExecTrace],
     ) -> Result<(), Error> {
-        // accumulates gas across all txs in the block
-        for (tx_index, tx) in eth_block.transactions.iter().enumerate() {
-            let geth_trace = &geth_traces[tx_index];
-            self.handle_tx(tx, geth_trace, tx_index + 1 == eth_block.transactions.len())?;
+        self.handle_blocks([(eth_block, geth_traces)])
+    }
+
+    /// Handle a batch of consecutive blocks, each one with the traces of its
+    /// transactions, to generate all the associated operations in a single
+    /// witness.  The first block must be the one the builder was created
+    /// with, and the StateDB and CodeDB are carried over from each block to
+    /// the next one.
+    pub fn handle_blocks<'b>(
+        &mut self,
+        blocks: impl IntoIterator<Item = (&'b EthBlock, &'b [eth_types::GethExecTrace])>,
+    ) -> Result<(), Error> {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        let num_txs: usize = blocks
+            .iter()
+            .map(|(eth_block, _)| eth_block.transactions.len())
+            .sum();
+        // First rw counter of the transactions of each block, by number
+        let mut block_rwcs = BTreeMap::new();
+        for (index, (eth_block, geth_traces)) in blocks.into_iter().enumerate() {
+            if index > 0 {
+                self.block.next_block(eth_block)?;
+                // accumulates gas across all txs in each block
+                self.block_ctx.cumulative_gas_used = 0;
+            }
+            block_rwcs.insert(self.block.number.low_u64(), self.block_ctx.rwc.0);
+            for (tx_index, tx) in eth_block.transactions.iter().enumerate() {
+                let geth_trace = &geth_traces[tx_index];
+                self.handle_tx(tx, geth_trace, self.block.txs.len() + 1 == num_txs)?;
+            }
         }
         self.set_value_ops_call_context_rwc_eor();
-        self.set_end_block();
+        self.check_withdrawal_recipients(&block_rwcs)?;
+        self.set_withdrawals()?;
+        self.set_end_block()
+    }
+
+    /// Check that no transaction accesses the recipient of a withdrawal of an
+    /// earlier block of the batch.  The withdrawals of every block are applied
+    /// after the last transaction of the batch, which gives the same state as
+    /// applying them after the transactions of their block only when the
+    /// later transactions don't see their recipients.
+    fn check_withdrawal_recipients(&self, block_rwcs: &BTreeMap<u64, usize>) -> Result<(), Error> {
+        for (withdrawal, number) in self
+            .block
+            .withdrawals
+            .iter()
+            .zip(&self.block.withdrawal_blocks)
+        {
+            let later_rwc = match block_rwcs.range(number + 1..).next() {
+                Some((_, rwc)) => *rwc,
+                None => continue,
+            };
+            if let Some(op) = self
+                .block
+                .container
+                .account
+                .iter()
+                .find(|op| op.rwc().0 >= later_rwc && op.op().address == withdrawal.address)
+            {
+                let (access_number, _) = block_rwcs
+                    .iter()
+                    .rev()
+                    .find(|(_, rwc)| **rwc <= op.rwc().0)
+                    .expect("later block");
+                return Err(Error::WithdrawalRecipientAccess(
+                    withdrawal.address,
+                    *access_number,
+                ));
+            }
+        }
         Ok(())
     }
 
-    fn set_end_block(&mut self) {
+    /// Generate a Withdrawal step for each withdrawal of the batch, which
+    /// credits the withdrawn amount to the balance of the recipient.
+    fn set_withdrawals(&mut self) -> Result<(), Error> {
+        let mut dummy_tx = Transaction::dummy();
+        let mut dummy_tx_ctx = TransactionContext::default();
+        let mut state = self.state_ref(&mut dummy_tx, &mut dummy_tx_ctx);
+
+        for (index, withdrawal) in state.block.withdrawals.clone().iter().enumerate() {
+            let mut step = ExecStep {
+                exec_state: ExecState::Withdrawal,
+                // The program counter holds the id of the withdrawal, starting
+                // at 1.
+                pc: ProgramCounter(index + 1),
+                rwc: state.block_ctx.rwc,
+                ..ExecStep::default()
+            };
+            let balance_prev = state.sdb.get_account(&withdrawal.address).1.balance;
+            state.account_write(
+                &mut step,
+                withdrawal.address,
+                AccountField::Balance,
+                balance_prev + withdrawal.amount_wei(),
+                balance_prev,
+            )?;
+            state.block.block_steps.withdrawals.push(step);
+        }
+        Ok(())
+    }
+
+    fn set_end_block(&mut self) -> Result<(), Error> {
         let max_rws = self.block.circuits_params.max_rws;
         let mut end_block_not_last = self.block.block_steps.end_block_not_last.clone();
         let mut end_block_last = self.block.block_steps.end_block_last.clone();
         end_block_not_last.rwc = self.block_ctx.rwc;
         end_block_last.rwc = self.block_ctx.rwc;
+        // The program counter of EndBlock holds the number of withdrawals + 1
+        let pc = ProgramCounter(self.block.withdrawals.len() + 1);
+        end_block_not_last.pc = pc;
+        end_block_last.pc = pc;
 
         let mut dummy_tx = Transaction::dummy();
         let mut dummy_tx_ctx = TransactionContext::default();
@@ -209,6 +326,14 @@ impl<'a> CircuitInputBuilder {
                 CallContextField::TxId,
                 Word::from(state.block.txs.len() as u64),
             );
+            // Read the cumulative gas used by all the txs to check it against
+            // the block gas limit.
+            state.tx_receipt_read(
+                &mut end_block_last,
+                state.block.txs.len(),
+                TxReceiptField::CumulativeGasUsed,
+                state.block_ctx.cumulative_gas_used,
+            )?;
         }
 
         let mut push_op = |step: &mut ExecStep, rwc: RWCounter, rw: RW, op: StartOp| {
@@ -217,26 +342,28 @@ impl<'a> CircuitInputBuilder {
         };
 
         let total_rws = state.block_ctx.rwc.0 - 1;
-        // We need at least 1 extra Start row
-        #[allow(clippy::int_plus_one)]
-        {
-            assert!(
-                total_rws + 1 <= max_rws,
-                "total_rws + 1 <= max_rws, total_rws={}, max_rws={}",
+        // We need at least 1 extra Start row.  A block with more rws can only
+        // be proved in chunks (see `Block::chunks`), each one with its own
+        // Start rows.
+        if total_rws < max_rws {
+            push_op(&mut end_block_last, RWCounter(1), RW::READ, StartOp {});
+            push_op(
+                &mut end_block_last,
+                RWCounter(max_rws - total_rws),
+                RW::READ,
+                StartOp {},
+            );
+        } else {
+            log::debug!(
+                "block proved in chunks, total_rws={}, max_rws={}",
                 total_rws,
                 max_rws
             );
         }
-        push_op(&mut end_block_last, RWCounter(1), RW::READ, StartOp {});
-        push_op(
-            &mut end_block_last,
-            RWCounter(max_rws - total_rws),
-            RW::READ,
-            StartOp {},
-        );
 
         self.block.block_steps.end_block_not_last = end_block_not_last;
         self.block.block_steps.end_block_last = end_block_last;
+        Ok(())
     }
 
     /// Handle a transaction with its corresponding execution trace to generate
@@ -252,6 +379,8 @@ impl<'a> CircuitInputBuilder {
     ) -> Result<(), Error> {
         let mut tx = self.new_tx(eth_tx, !geth_trace.failed)?;
         let mut tx_ctx = TransactionContext::new(eth_tx, geth_trace, is_last_tx)?;
+        // In a batch, the id is the position of the tx in the whole batch
+        tx_ctx.id = self.block.txs.len() + 1;
 
         // TODO: Move into gen_associated_steps with
         // - execution_state: BeginTx
@@ -341,6 +470,8 @@ pub fn keccak_inputs_tx_circuit(
     // Keccak inputs from SignVerify Chip
     let sign_verify_inputs = keccak_inputs_sign_verify(&sign_datas);
     inputs.extend_from_slice(&sign_verify_inputs);
+    // Keccak inputs from the RLP Circuit, which hashes the signed messages
+    inputs.extend(txs.iter().map(|tx| tx.sign_message(chain_id)));
     // NOTE: We don't verify the Tx Hash in the circuit yet, so we don't have more
     // hash inputs.
     Ok(inputs)
@@ -395,11 +526,21 @@ impl<P: JsonRpcClient> BuilderClient<P> {
     }
 
     /// Step 1. Query geth for Block, Txs, TxExecTraces, history block hashes
-    /// and previous state root.
+    /// with their RLP encoded headers and previous state root.
+    #[allow(clippy::type_complexity)]
     pub async fn get_block(
         &self,
         block_num: u64,
-    ) -> Result<(EthBlock, Vec<eth_types::GethExecTrace>, Vec<Word>, Word), Error> {
+    ) -> Result<
+        (
+            EthBlock,
+            Vec<eth_types::GethExecTrace>,
+            Vec<Word>,
+            Vec<Vec<u8>>,
+            Word,
+        ),
+        Error,
+    > {
         let eth_block = self.cli.get_block_by_number(block_num.into()).await?;
         let geth_traces = self.cli.trace_block_by_number(block_num.into()).await?;
 
@@ -408,6 +549,7 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         let mut next_hash = eth_block.parent_hash;
         let mut prev_state_root: Option<Word> = None;
         let mut history_hashes = vec![Word::default(); n_blocks];
+        let mut history_headers = vec![Vec::new(); n_blocks];
         while n_blocks > 0 {
             n_blocks -= 1;
 
@@ -425,6 +567,7 @@ impl<P: JsonRpcClient> BuilderClient<P> {
                 .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?
                 .to_word();
             history_hashes[n_blocks] = block_hash;
+            history_headers[n_blocks] = block_header_rlp(&header);
 
             // continue
             next_hash = header.parent_hash;
@@ -434,6 +577,7 @@ impl<P: JsonRpcClient> BuilderClient<P> {
             eth_block,
             geth_traces,
             history_hashes,
+            history_headers,
             prev_state_root.unwrap_or_default(),
         ))
     }
@@ -458,12 +602,22 @@ impl<P: JsonRpcClient> BuilderClient<P> {
             let tx_access_trace = gen_state_access_trace(eth_block, tx, geth_trace)?;
             block_access_trace.extend(tx_access_trace);
         }
+        // The recipients of the withdrawals are credited after the txs
+        for withdrawal in geth_types::block_withdrawals(eth_block)? {
+            block_access_trace.push(Access::new(
+                None,
+                RW::WRITE,
+                AccessValue::Account {
+                    address: withdrawal.address,
+                },
+            ));
+        }
 
         Ok(AccessSet::from(block_access_trace))
     }
 
-    /// Step 3. Query geth for all accounts, storage keys, and codes from
-    /// Accesses
+    /// Step 3. Query geth for the proofs of all accounts and storage keys, and
+    /// the codes from Accesses
     pub async fn get_state(
         &self,
         block_num: u64,
@@ -482,23 +636,31 @@ impl<P: JsonRpcClient> BuilderClient<P> {
             let proof = self
                 .cli
                 .get_proof(address, keys, (block_num - 1).into())
-                .await
-                .unwrap();
+                .await?;
             proofs.push(proof);
         }
         let mut codes: HashMap<Address, Vec<u8>> = HashMap::new();
         for address in access_set.code {
-            let code = self
-                .cli
-                .get_code(address, (block_num - 1).into())
-                .await
-                .unwrap();
+            let code = self.cli.get_code(address, (block_num - 1).into()).await?;
             codes.insert(address, code);
         }
         Ok((proofs, codes))
     }
 
-    /// Step 4. Build a partial StateDB from step 3
+    /// Step 4. Build the partial state trie of the previous block from the
+    /// proofs of step 3
+    pub fn build_state_trie(
+        &self,
+        prev_state_root: Word,
+        proofs: &[eth_types::EIP1186ProofResponse],
+    ) -> Result<StateTrie, Error> {
+        Ok(StateTrie::from_proofs(
+            H256::from_uint(&prev_state_root),
+            proofs,
+        )?)
+    }
+
+    /// Step 5. Build a partial StateDB from step 3
     pub fn build_state_code_db(
         &self,
         proofs: Vec<eth_types::EIP1186ProofResponse>,
@@ -528,21 +690,22 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         (sdb, code_db)
     }
 
-    /// Step 5. For each step in TxExecTraces, gen the associated ops and state
+    /// Step 6. For each step in TxExecTraces, gen the associated ops and state
     /// circuit inputs
     pub fn gen_inputs_from_state(
         &self,
-        sdb: StateDB,
+        mut sdb: StateDB,
         code_db: CodeDB,
+        state_trie: StateTrie,
         eth_block: &EthBlock,
         geth_traces: &[eth_types::GethExecTrace],
         history_hashes: Vec<Word>,
-        prev_state_root: Word,
     ) -> Result<CircuitInputBuilder, Error> {
+        sdb.set_state_trie(state_trie.clone());
         let block = Block::new(
             self.chain_id,
             history_hashes,
-            prev_state_root,
+            state_trie,
             eth_block,
             self.circuits_params.clone(),
         )?;
@@ -562,19 +725,30 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         ),
         Error,
     > {
-        let (eth_block, geth_traces, history_hashes, prev_state_root) =
+        let (eth_block, geth_traces, history_hashes, history_headers, prev_state_root) =
             self.get_block(block_num).await?;
         let access_set = self.get_state_accesses(&eth_block, &geth_traces)?;
         let (proofs, codes) = self.get_state(block_num, access_set).await?;
+        let state_trie = self.build_state_trie(prev_state_root, &proofs)?;
         let (state_db, code_db) = self.build_state_code_db(proofs, codes);
-        let builder = self.gen_inputs_from_state(
+        let mut builder = self.gen_inputs_from_state(
             state_db,
             code_db,
+            state_trie,
             &eth_block,
             &geth_traces,
             history_hashes,
-            prev_state_root,
         )?;
+        builder.block.history_headers = history_headers;
+        // Cross-check the state root computed from the partial state trie
+        let state_root = builder.sdb.state_root()?;
+        if state_root != eth_block.state_root {
+            return Err(Error::StateRootMismatch(
+                block_num,
+                eth_block.state_root,
+                state_root,
+            ));
+        }
         Ok((builder, eth_block))
     }
 }
@@ -4,11 +4,18 @@ use super::{
     execution::ExecState, transaction::Transaction, CircuitsParams, CopyEvent, ExecStep, ExpEvent,
 };
 use crate::{
+    mpt::StateTrie,
     operation::{OperationContainer, RWCounter},
     Error,
 };
-use eth_types::{evm_unimplemented, Address, Hash, Word};
-use std::collections::HashMap;
+use eth_types::{
+    evm_types::Hardfork,
+    evm_unimplemented,
+    geth_types::{block_header_rlp, block_withdrawals, Withdrawal},
+    Address, Hash, ToWord, Word,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 /// Context of a [`Block`] which can mutate in a [`Transaction`].
 #[derive(Debug)]
@@ -20,7 +27,8 @@ pub struct BlockContext {
     /// in Block.txs and call_index is the index used in Transaction.
     /// calls).
     pub(crate) call_map: HashMap<usize, (usize, usize)>,
-    /// Total gas used by previous transactions in this block.
+    /// Total gas used by previous transactions in the current block of the
+    /// batch.
     pub(crate) cumulative_gas_used: u64,
 }
 
@@ -42,8 +50,11 @@ impl BlockContext {
 }
 
 /// Block-wise execution steps that don't belong to any Transaction.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BlockSteps {
+    /// Withdrawal steps, one per withdrawal of the block, that follow the last
+    /// transaction.
+    pub withdrawals: Vec<ExecStep>,
     /// EndBlock step that is repeated after the last transaction and before
     /// reaching the last EVM row.
     pub end_block_not_last: ExecStep,
@@ -51,15 +62,87 @@ pub struct BlockSteps {
     pub end_block_last: ExecStep,
 }
 
+/// Header fields of a block of the batch, which are the block context of its
+/// transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockHead {
+    /// chain id
+    pub chain_id: Word,
+    /// history hashes contains most recent 256 block hashes in history, where
+    /// the lastest one is at history_hashes[history_hashes.len() - 1].
+    pub history_hashes: Vec<Word>,
+    /// coinbase
+    pub coinbase: Address,
+    /// gas limit
+    pub gas_limit: u64,
+    /// number
+    pub number: Word,
+    /// time
+    pub timestamp: Word,
+    /// difficulty, or the randomness (`mix_hash`) after the Merge
+    pub difficulty: Word,
+    /// base fee
+    pub base_fee: Word,
+    /// Original block from geth
+    #[serde(with = "eth_types::geth_types::json_in_binary")]
+    pub eth_block: eth_types::Block<eth_types::Transaction>,
+}
+
+impl BlockHead {
+    /// Create a new block head.
+    pub fn new(
+        chain_id: Word,
+        history_hashes: Vec<Word>,
+        eth_block: &eth_types::Block<eth_types::Transaction>,
+        hardfork: Hardfork,
+    ) -> Result<Self, Error> {
+        if eth_block.base_fee_per_gas.is_none() {
+            // FIXME: resolve this once we have proper EIP-1559 support
+            evm_unimplemented!(
+                "This does not look like a EIP-1559 block - base_fee_per_gas defaults to zero"
+            );
+        }
+
+        Ok(Self {
+            chain_id,
+            history_hashes,
+            coinbase: eth_block
+                .author
+                .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?,
+            gas_limit: eth_block.gas_limit.low_u64(),
+            number: eth_block
+                .number
+                .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?
+                .low_u64()
+                .into(),
+            timestamp: eth_block.timestamp,
+            // After the Merge the DIFFICULTY opcode is PREVRANDAO (EIP-4399),
+            // which returns the `mix_hash` of the header.
+            difficulty: if hardfork.is_post_merge() {
+                eth_block.mix_hash.unwrap_or_default().to_word()
+            } else {
+                eth_block.difficulty
+            },
+            base_fee: eth_block.base_fee_per_gas.unwrap_or_default(),
+            eth_block: eth_block.clone(),
+        })
+    }
+}
+
 // TODO: Remove fields that are duplicated in`eth_block`
-/// Circuit Input related to a block.
-#[derive(Debug)]
+/// Circuit Input related to a block, or to a batch of consecutive blocks.  In
+/// a batch, the header fields are the ones of the last block handled so far,
+/// while the ones of every block are in `headers`.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Block {
     /// chain id
     pub chain_id: Word,
     /// history hashes contains most recent 256 block hashes in history, where
     /// the lastest one is at history_hashes[history_hashes.len() - 1].
     pub history_hashes: Vec<Word>,
+    /// RLP encoded headers of the blocks of the history hashes, aligned with
+    /// `history_hashes`.  Empty when the headers are not known.
+    pub history_headers: Vec<Vec<u8>>,
     /// coinbase
     pub coinbase: Address,
     /// time
@@ -72,12 +155,22 @@ pub struct Block {
     pub difficulty: Word,
     /// base fee
     pub base_fee: Word,
-    /// State root of the previous block
+    /// Header fields of the blocks of the batch, by number
+    pub headers: BTreeMap<u64, BlockHead>,
+    /// State root of the block previous to the batch
     pub prev_state_root: Word,
+    /// State trie of the block previous to the batch, with the paths of the
+    /// accounts and storage keys accessed in the batch.
+    pub state_trie: StateTrie,
     /// Container of operations done in this block.
     pub container: OperationContainer,
-    /// Transactions contained in the block
+    /// Transactions contained in the blocks of the batch
     pub txs: Vec<Transaction>,
+    /// Withdrawals of the blocks of the batch, in order, applied after the
+    /// transactions of the last block
+    pub withdrawals: Vec<Withdrawal>,
+    /// Numbers of the blocks of the withdrawals, aligned with `withdrawals`
+    pub withdrawal_blocks: Vec<u64>,
     /// Block-wise steps
     pub block_steps: BlockSteps,
     /// Copy events in this block.
@@ -90,6 +183,7 @@ pub struct Block {
     /// Circuits Setup Paramteres
     pub circuits_params: CircuitsParams,
     /// Original block from geth
+    #[serde(with = "eth_types::geth_types::json_in_binary")]
     pub eth_block: eth_types::Block<eth_types::Transaction>,
 }
 
@@ -98,36 +192,38 @@ impl Block {
     pub fn new(
         chain_id: Word,
         history_hashes: Vec<Word>,
-        prev_state_root: Word,
+        state_trie: StateTrie,
         eth_block: &eth_types::Block<eth_types::Transaction>,
         circuits_params: CircuitsParams,
     ) -> Result<Self, Error> {
-        if eth_block.base_fee_per_gas.is_none() {
-            // FIXME: resolve this once we have proper EIP-1559 support
-            evm_unimplemented!(
-                "This does not look like a EIP-1559 block - base_fee_per_gas defaults to zero"
-            );
-        }
+        let head = BlockHead::new(
+            chain_id,
+            history_hashes,
+            eth_block,
+            circuits_params.hardfork,
+        )?;
+        let withdrawals = block_withdrawals(eth_block).map_err(Error::EthTypeError)?;
+        let withdrawal_blocks = vec![head.number.low_u64(); withdrawals.len()];
 
         Ok(Self {
             chain_id,
-            history_hashes,
-            coinbase: eth_block
-                .author
-                .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?,
-            gas_limit: eth_block.gas_limit.low_u64(),
-            number: eth_block
-                .number
-                .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?
-                .low_u64()
-                .into(),
-            timestamp: eth_block.timestamp,
-            difficulty: eth_block.difficulty,
-            base_fee: eth_block.base_fee_per_gas.unwrap_or_default(),
-            prev_state_root,
+            history_hashes: head.history_hashes.clone(),
+            history_headers: Vec::new(),
+            coinbase: head.coinbase,
+            gas_limit: head.gas_limit,
+            number: head.number,
+            timestamp: head.timestamp,
+            difficulty: head.difficulty,
+            base_fee: head.base_fee,
+            headers: BTreeMap::from([(head.number.low_u64(), head)]),
+            prev_state_root: state_trie.root().to_word(),
+            state_trie,
             container: OperationContainer::new(),
             txs: Vec::new(),
+            withdrawals,
+            withdrawal_blocks,
             block_steps: BlockSteps {
+                withdrawals: Vec::new(),
                 end_block_not_last: ExecStep {
                     exec_state: ExecState::EndBlock,
                     ..ExecStep::default()
@@ -146,6 +242,62 @@ impl Block {
         })
     }
 
+    /// Continue the batch with `eth_block`, which must be the child of the
+    /// last block of the batch.  The header fields are replaced by the ones of
+    /// `eth_block`, the hash of its parent is appended to the history and its
+    /// withdrawals to the ones of the batch.
+    pub fn next_block(
+        &mut self,
+        eth_block: &eth_types::Block<eth_types::Transaction>,
+    ) -> Result<(), Error> {
+        let number = eth_block
+            .number
+            .ok_or(Error::EthTypeError(eth_types::Error::IncompleteBlock))?
+            .low_u64();
+        let is_child = number == self.number.low_u64() + 1
+            && self
+                .eth_block
+                .hash
+                .map_or(true, |hash| hash == eth_block.parent_hash);
+        if !is_child {
+            return Err(Error::NonConsecutiveBlock(number));
+        }
+
+        let mut history_hashes = self.history_hashes.clone();
+        if history_hashes.len() == 256 {
+            history_hashes.remove(0);
+        }
+        history_hashes.push(eth_block.parent_hash.to_word());
+        if !self.history_headers.is_empty() {
+            if self.history_headers.len() == 256 {
+                self.history_headers.remove(0);
+            }
+            self.history_headers.push(block_header_rlp(&self.eth_block));
+        }
+
+        let head = BlockHead::new(
+            self.chain_id,
+            history_hashes,
+            eth_block,
+            self.circuits_params.hardfork,
+        )?;
+        self.history_hashes = head.history_hashes.clone();
+        self.coinbase = head.coinbase;
+        self.gas_limit = head.gas_limit;
+        self.number = head.number;
+        self.timestamp = head.timestamp;
+        self.difficulty = head.difficulty;
+        self.base_fee = head.base_fee;
+        self.headers.insert(number, head);
+        let withdrawals = block_withdrawals(eth_block).map_err(Error::EthTypeError)?;
+        self.withdrawal_blocks
+            .extend(std::iter::repeat(number).take(withdrawals.len()));
+        self.withdrawals.extend(withdrawals);
+        self.eth_block = eth_block.clone();
+
+        Ok(())
+    }
+
     /// Return the list of transactions of this block.
     pub fn txs(&self) -> &[Transaction] {
         &self.txs
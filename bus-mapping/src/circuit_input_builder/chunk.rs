@@ -0,0 +1,209 @@
+//! Splitting of a block that doesn't fit in the circuits into chunks
+
+use super::{Block, CopyDataType, CopyEvent, NumberOrHash};
+use crate::{operation::RWCounter, state_db::CodeDB, Error};
+use eth_types::Hash;
+use std::{collections::HashSet, ops::Range};
+
+/// Number of rw operations done by EndBlock at the end of a chunk with
+/// transactions: the read of the id of the last tx and the read of its
+/// cumulative gas used.
+const END_BLOCK_RWS: usize = 2;
+
+/// Chunk of consecutive transactions of a [`Block`], proved in a witness of
+/// its own when the whole block exceeds the
+/// [`CircuitsParams`](super::CircuitsParams).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Index of the chunk in the block, starting at 0
+    pub index: usize,
+    /// Indexes in [`Block::txs`] of the transactions of the chunk
+    pub txs: Range<usize>,
+    /// Rw counter of the first operation of the chunk, which is 1 for the
+    /// first chunk and the write of the id of its first tx by the EndTx step
+    /// of the previous tx for the others.
+    pub rw_counter_start: RWCounter,
+    /// Rw counter of the first operation of the next chunk, or of the
+    /// EndBlock step for the last chunk, which also contains the withdrawals.
+    pub rw_counter_end: RWCounter,
+}
+
+/// Rows used in the circuits by a range of transactions
+#[derive(Debug, Default)]
+struct ChunkUsage {
+    rws: usize,
+    copy_rows: usize,
+    bytecodes: HashSet<Hash>,
+}
+
+impl ChunkUsage {
+    fn bytecode_rows(&self, code_db: &CodeDB) -> usize {
+        self.bytecodes
+            .iter()
+            .map(|hash| code_db.0.get(hash).map_or(0, |code| code.len() + 1))
+            .sum()
+    }
+}
+
+impl Block {
+    /// Split the transactions of the block into chunks, so that the rw
+    /// operations, the copy rows and the bytecodes of each chunk fit in the
+    /// circuits.  The chunks are filled greedily in order, and a single chunk
+    /// is returned when the whole block fits.
+    pub fn chunks(&self, code_db: &CodeDB) -> Result<Vec<Chunk>, Error> {
+        let params = &self.circuits_params;
+        let fits = |usage: &ChunkUsage| {
+            // At least one Start row is needed in the rw table
+            usage.rws + END_BLOCK_RWS < params.max_rws
+                && usage.copy_rows + 2 <= params.max_copy_rows
+                && usage.bytecode_rows(code_db) <= params.max_bytecode
+        };
+
+        // The operations of a tx start with the write of its id done by the
+        // EndTx step of the previous tx, right before its BeginTx step, and
+        // the withdrawals are done in the last chunk.
+        let end_block_rwc = self.block_steps.end_block_last.rwc;
+        let tx_rwc_start = |index: usize| match index {
+            0 => RWCounter(1),
+            _ => self
+                .txs
+                .get(index)
+                .map_or(end_block_rwc, |tx| RWCounter(tx.steps()[0].rwc.0 - 1)),
+        };
+
+        let mut chunks = Vec::new();
+        let mut usage = ChunkUsage::default();
+        let mut first_tx = 0;
+        for index in 0..self.txs.len() {
+            let tx_usage = self.tx_usage(index, tx_rwc_start(index), tx_rwc_start(index + 1));
+            let mut merged = ChunkUsage {
+                rws: usage.rws + tx_usage.rws,
+                copy_rows: usage.copy_rows + tx_usage.copy_rows,
+                bytecodes: &usage.bytecodes | &tx_usage.bytecodes,
+            };
+            if !fits(&merged) && index > first_tx {
+                chunks.push(Chunk {
+                    index: chunks.len(),
+                    txs: first_tx..index,
+                    rw_counter_start: tx_rwc_start(first_tx),
+                    rw_counter_end: tx_rwc_start(index),
+                });
+                first_tx = index;
+                merged = tx_usage;
+            }
+            if !fits(&merged) {
+                return Err(Error::OversizedTx(index));
+            }
+            usage = merged;
+        }
+        chunks.push(Chunk {
+            index: chunks.len(),
+            txs: first_tx..self.txs.len(),
+            rw_counter_start: tx_rwc_start(first_tx),
+            rw_counter_end: end_block_rwc,
+        });
+
+        Ok(chunks)
+    }
+
+    /// Rows used by the tx at `index`, whose operations have rw counters in
+    /// `rwc_start..rwc_end`.
+    fn tx_usage(&self, index: usize, rwc_start: RWCounter, rwc_end: RWCounter) -> ChunkUsage {
+        let copy_events: Vec<&CopyEvent> = self
+            .copy_events
+            .iter()
+            .filter(|event| (rwc_start.0..rwc_end.0).contains(&event.rw_counter_start.0))
+            .collect();
+        let copied_bytecodes = copy_events.iter().flat_map(|event| {
+            [
+                (event.src_type, &event.src_id),
+                (event.dst_type, &event.dst_id),
+            ]
+            .into_iter()
+            .filter_map(|(ty, id)| match (ty, id) {
+                (CopyDataType::Bytecode, NumberOrHash::Hash(hash)) => Some(*hash),
+                _ => None,
+            })
+        });
+        ChunkUsage {
+            rws: rwc_end.0 - rwc_start.0,
+            copy_rows: copy_events.iter().map(|event| event.bytes.len() * 2).sum(),
+            bytecodes: self.txs[index]
+                .calls()
+                .iter()
+                .map(|call| call.code_hash)
+                .chain(copied_bytecodes)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{circuit_input_builder::CircuitsParams, mock::BlockData};
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::{test_ctx::helpers::account_0_code_account_1_no_code, TestContext};
+
+    #[test]
+    fn chunks_at_tx_boundaries() {
+        let block: GethData = TestContext::<2, 3>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! {
+                PUSH1(0x20)
+                PUSH1(0)
+                PUSH1(0)
+                CALLDATACOPY
+                STOP
+            }),
+            |txs, accs| {
+                for tx in txs {
+                    tx.from(accs[1].address).to(accs[0].address);
+                }
+            },
+            |block, _txs| block,
+        )
+        .unwrap()
+        .into();
+        let params = CircuitsParams {
+            max_txs: 3,
+            ..Default::default()
+        };
+        let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), params)
+            .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        // The whole block fits in a single chunk
+        let chunks = builder.block.chunks(&builder.code_db).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].txs, 0..3);
+        assert_eq!(chunks[0].rw_counter_start.0, 1);
+        assert_eq!(
+            chunks[0].rw_counter_end,
+            builder.block.block_steps.end_block_last.rwc
+        );
+
+        // Each chunk can only hold the rws of 2 txs.  The txs following the
+        // first one also read the cumulative gas used by the previous tx.
+        let tx_rws = builder.block.txs[1].steps()[0].rwc.0 - 1;
+        builder.block.circuits_params.max_rws = 2 * tx_rws + 4;
+        let chunks = builder.block.chunks(&builder.code_db).unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.txs.clone())
+                .collect::<Vec<_>>(),
+            vec![0..2, 2..3]
+        );
+        assert_eq!(chunks[1].rw_counter_start, chunks[0].rw_counter_end);
+        assert_eq!(
+            chunks[1].rw_counter_start.0,
+            builder.block.txs[2].steps()[0].rwc.0 - 1
+        );
+
+        // A tx that doesn't fit in a chunk of its own
+        builder.block.circuits_params.max_rws = tx_rws;
+        assert!(builder.block.chunks(&builder.code_db).is_err());
+    }
+}
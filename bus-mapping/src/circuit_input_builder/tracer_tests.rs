@@ -7,7 +7,7 @@ use crate::geth_errors::{
 };
 use crate::operation::RWCounter;
 use crate::state_db::Account;
-use eth_types::evm_types::{stack::Stack, Gas, OpcodeId};
+use eth_types::evm_types::{stack::Stack, Gas, Hardfork, OpcodeId};
 use eth_types::{
     address, bytecode, geth_types::GethData, word, Bytecode, Hash, ToAddress, ToWord, Word,
 };
@@ -1317,6 +1317,40 @@ fn tracer_err_invalid_opcode() {
     );
 }
 
+#[test]
+fn tracer_err_unavailable_opcode() {
+    // PUSH0 is only defined from Shanghai
+    let code = bytecode! {
+        PUSH0
+        STOP
+    };
+    let block: GethData = TestContext::<2, 1>::new_with_hardfork(
+        None,
+        account_0_code_account_1_no_code(code),
+        tx_from_1_to_0,
+        |block, _tx| block.number(0xcafeu64),
+        Hardfork::Shanghai,
+    )
+    .unwrap()
+    .into();
+
+    let step = &block.geth_traces[0].struct_logs[0];
+    let next_step = block.geth_traces[0].struct_logs.get(1);
+    assert_eq!(step.op, OpcodeId::PUSH0);
+    assert_eq!(step.error, None);
+
+    // The builder follows the London rules, under which PUSH0 is invalid
+    let mut builder = CircuitInputBuilderTx::new(&block, step);
+    assert_eq!(
+        builder.state_ref().block.circuits_params.hardfork,
+        Hardfork::London
+    );
+    assert_eq!(
+        builder.state_ref().get_step_err(step, next_step).unwrap(),
+        Some(ExecError::InvalidOpcode)
+    );
+}
+
 #[test]
 fn test_tracer_err_write_protection() {
     // test write_protection error happens in sstore
@@ -1,10 +1,16 @@
 //! Transaction & TransactionContext utility module.
 
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-use eth_types::evm_types::Memory;
+use eth_types::evm_types::{
+    gas_utils::init_code_gas_cost, GasCost, Hardfork, Memory, MAX_INIT_CODE_SIZE,
+};
 use eth_types::Signature;
-use eth_types::{geth_types, Address, GethExecTrace, Word};
+use eth_types::{
+    geth_types::{self, TxType},
+    AccessList, Address, GethExecTrace, Word,
+};
 use ethers_core::utils::get_contract_address;
 
 use crate::{
@@ -17,8 +23,9 @@ use super::{call::ReversionGroup, Call, CallContext, CallKind, CodeSource, ExecS
 #[derive(Debug, Default)]
 /// Context of a [`Transaction`] which can mutate in an [`ExecStep`].
 pub struct TransactionContext {
-    /// Unique identifier of transaction of the block. The value is `index + 1`.
-    id: usize,
+    /// Unique identifier of transaction of the batch. The value is `index + 1`,
+    /// where `index` is the position of the transaction in the batch.
+    pub(crate) id: usize,
     /// The index of logs made in the transaction.
     pub(crate) log_id: usize,
     /// Identifier if this transaction is last one of the block or not.
@@ -178,15 +185,23 @@ impl TransactionContext {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Result of the parsing of an Ethereum Transaction.
 pub struct Transaction {
+    /// Type of the transaction envelope
+    pub tx_type: TxType,
     /// Nonce
     pub nonce: u64,
     /// Gas
     pub gas: u64,
     /// Gas price
     pub gas_price: Word,
+    /// Max fee per gas (EIP-1559)
+    pub max_fee_per_gas: Word,
+    /// Max priority fee per gas (EIP-1559)
+    pub max_priority_fee_per_gas: Word,
+    /// Access list (EIP-2930)
+    pub access_list: AccessList,
     /// From / Caller Address
     pub from: Address,
     /// To / Callee Address
@@ -197,6 +212,15 @@ pub struct Transaction {
     pub input: Vec<u8>,
     /// Signature
     pub signature: Signature,
+    /// Number of the block of the batch that contains the transaction
+    pub block_num: u64,
+    /// Whether the transaction is invalid: its nonce doesn't match the
+    /// caller's one, the caller can't pay for the gas fee and the value, its
+    /// gas limit doesn't cover the intrinsic gas, or (from Shanghai) it
+    /// creates a contract with an init code larger than
+    /// [`MAX_INIT_CODE_SIZE`].  An invalid transaction is skipped without
+    /// touching the state and gets a failed receipt.
+    pub is_invalid: bool,
     /// Calls made in the transaction
     pub(crate) calls: Vec<Call>,
     /// Execution steps
@@ -206,13 +230,18 @@ pub struct Transaction {
 impl From<&Transaction> for geth_types::Transaction {
     fn from(tx: &Transaction) -> geth_types::Transaction {
         geth_types::Transaction {
+            tx_type: tx.tx_type,
             from: tx.from,
             to: Some(tx.to),
             nonce: Word::from(tx.nonce),
             gas_limit: Word::from(tx.gas),
             value: tx.value,
             gas_price: tx.gas_price,
+            gas_fee_cap: tx.max_fee_per_gas,
+            gas_tip_cap: tx.max_priority_fee_per_gas,
             call_data: tx.input.clone().into(),
+            access_list: Some(tx.access_list.clone()),
+            block_number: tx.block_num,
             v: tx.signature.v,
             r: tx.signature.r,
             s: tx.signature.s,
@@ -225,9 +254,13 @@ impl Transaction {
     /// Create a dummy Transaction with zero values
     pub fn dummy() -> Self {
         Self {
+            tx_type: TxType::default(),
             nonce: 0,
             gas: 0,
             gas_price: Word::zero(),
+            max_fee_per_gas: Word::zero(),
+            max_priority_fee_per_gas: Word::zero(),
+            access_list: AccessList::default(),
             from: Address::zero(),
             to: Address::zero(),
             value: Word::zero(),
@@ -237,6 +270,8 @@ impl Transaction {
                 s: Word::zero(),
                 v: 0,
             },
+            block_num: 0,
+            is_invalid: false,
             calls: Vec::new(),
             steps: Vec::new(),
         }
@@ -248,13 +283,56 @@ impl Transaction {
         sdb: &StateDB,
         code_db: &mut CodeDB,
         eth_tx: &eth_types::Transaction,
+        block_num: u64,
         is_success: bool,
+        hardfork: Hardfork,
     ) -> Result<Self, Error> {
-        let (found, _) = sdb.get_account(&eth_tx.from);
+        let (found, caller) = sdb.get_account(&eth_tx.from);
         if !found {
             return Err(Error::AccountNotFound(eth_tx.from));
         }
 
+        // Nodes reject a nonce or a gas limit above u64 when decoding the
+        // transaction, so such a transaction is never part of a block.
+        if eth_tx.nonce > Word::from(u64::MAX) {
+            return Err(Error::TxFieldOverflow(eth_tx.hash, "nonce"));
+        }
+        if eth_tx.gas > Word::from(u64::MAX) {
+            return Err(Error::TxFieldOverflow(eth_tx.hash, "gas"));
+        }
+
+        let is_create = eth_tx.to.is_none();
+        let access_list = eth_tx.access_list.clone().unwrap_or_default();
+        let intrinsic_gas = intrinsic_gas(is_create, &eth_tx.input, &access_list, hardfork);
+        let is_init_code_too_large = is_create
+            && hardfork >= Hardfork::Shanghai
+            && eth_tx.input.len() as u64 > MAX_INIT_CODE_SIZE;
+        let tx_type = eth_tx
+            .transaction_type
+            .map(|tx_type| TxType::try_from(tx_type.as_u64()))
+            .transpose()?
+            .unwrap_or_default();
+        let gas_price = eth_tx.gas_price.unwrap_or_default();
+        let max_fee_per_gas = eth_tx.max_fee_per_gas.unwrap_or_default();
+        // Like geth's `buyGas`, the balance of the caller must cover the gas
+        // limit at the max fee per gas of an EIP-1559 transaction, even if
+        // it is only charged at the effective gas price.
+        let balance_check_gas_price = if tx_type == TxType::Eip1559 {
+            max_fee_per_gas
+        } else {
+            gas_price
+        };
+        let is_balance_sufficient = balance_check_gas_price
+            .checked_mul(eth_tx.gas)
+            .and_then(|gas_fee| gas_fee.checked_add(eth_tx.value))
+            .map_or(false, |cost| caller.balance >= cost);
+        let is_invalid = eth_tx.nonce != caller.nonce
+            || !is_balance_sufficient
+            || eth_tx.gas < Word::from(intrinsic_gas)
+            || is_init_code_too_large;
+        // An invalid transaction is never executed, so it always fails.
+        let is_success = is_success && !is_invalid;
+
         let call = if let Some(address) = eth_tx.to {
             // Contract Call / Transfer
             let (found, account) = sdb.get_account(&address);
@@ -298,9 +376,13 @@ impl Transaction {
         };
 
         Ok(Self {
+            tx_type,
             nonce: eth_tx.nonce.as_u64(),
             gas: eth_tx.gas.as_u64(),
-            gas_price: eth_tx.gas_price.unwrap_or_default(),
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas: eth_tx.max_priority_fee_per_gas.unwrap_or_default(),
+            access_list,
             from: eth_tx.from,
             to: eth_tx
                 .to
@@ -314,6 +396,8 @@ impl Transaction {
                 r: eth_tx.r,
                 s: eth_tx.s,
             },
+            block_num,
+            is_invalid,
         })
     }
 
@@ -322,6 +406,12 @@ impl Transaction {
         self.calls[0].is_create()
     }
 
+    /// Return the intrinsic gas of this [`Transaction`], which is charged
+    /// before executing any code under the rules of `hardfork`.
+    pub fn intrinsic_gas(&self, hardfork: Hardfork) -> u64 {
+        intrinsic_gas(self.is_create(), &self.input, &self.access_list, hardfork)
+    }
+
     /// Return the list of execution steps of this transaction.
     pub fn steps(&self) -> &[ExecStep] {
         &self.steps
@@ -362,3 +452,80 @@ impl Transaction {
         self.steps.is_empty()
     }
 }
+
+/// Intrinsic gas of a transaction, made of the base cost, the call data cost
+/// (4 for byte == 0, 16 otherwise) and the cost of the addresses and storage
+/// keys of the access list (EIP-2930).  From Shanghai, a contract creation
+/// also pays for each word of its init code (EIP-3860).
+fn intrinsic_gas(
+    is_create: bool,
+    input: &[u8],
+    access_list: &AccessList,
+    hardfork: Hardfork,
+) -> u64 {
+    let call_data_gas_cost = input
+        .iter()
+        .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 });
+    let base_gas_cost = if is_create {
+        GasCost::CREATION_TX.as_u64()
+    } else {
+        GasCost::TX.as_u64()
+    };
+    let init_code_cost = if is_create && hardfork >= Hardfork::Shanghai {
+        init_code_gas_cost(input.len() as u64)
+    } else {
+        0
+    };
+    let access_list_gas_cost = access_list.0.iter().fold(0, |acc, item| {
+        acc + GasCost::ACCESS_LIST_ADDRESS.as_u64()
+            + GasCost::ACCESS_LIST_STORAGE_KEY.as_u64() * item.storage_keys.len() as u64
+    });
+    base_gas_cost + call_data_gas_cost + access_list_gas_cost + init_code_cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_db::Account;
+    use eth_types::U256;
+    use mock::MOCK_ACCOUNTS;
+
+    fn new_tx(nonce: Word, gas: Word) -> Result<Transaction, Error> {
+        let mut sdb = StateDB::new();
+        sdb.set_account(&MOCK_ACCOUNTS[0], Account::zero());
+        sdb.set_account(&MOCK_ACCOUNTS[1], Account::zero());
+        let eth_tx = eth_types::Transaction {
+            from: MOCK_ACCOUNTS[0],
+            to: Some(MOCK_ACCOUNTS[1]),
+            nonce,
+            gas,
+            ..Default::default()
+        };
+        Transaction::new(
+            1,
+            &sdb,
+            &mut CodeDB::new(),
+            &eth_tx,
+            1,
+            true,
+            Hardfork::default(),
+        )
+    }
+
+    #[test]
+    fn tx_field_above_u64_is_rejected() {
+        let above_u64 = U256::from(u64::MAX) + 1;
+        assert!(matches!(
+            new_tx(above_u64, Word::from(21000)),
+            Err(Error::TxFieldOverflow(_, "nonce"))
+        ));
+        assert!(matches!(
+            new_tx(Word::zero(), above_u64),
+            Err(Error::TxFieldOverflow(_, "gas"))
+        ));
+        // A tx whose fields fit in a u64 is built, with its invalidity decided
+        // as usual
+        let tx = new_tx(Word::from(u64::MAX), Word::from(u64::MAX)).unwrap();
+        assert!(tx.is_invalid);
+    }
+}
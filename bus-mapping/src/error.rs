@@ -2,7 +2,9 @@
 
 use core::fmt::{Display, Formatter, Result as FmtResult};
 use eth_types::{evm_types::OpcodeId, Address, GethExecStep, Word, H256};
+use ethers_core::utils::rlp::DecoderError;
 use ethers_providers::ProviderError;
+use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
 
 use crate::geth_errors::{
@@ -15,6 +17,13 @@ use crate::geth_errors::{
 pub enum Error {
     /// Serde de/serialization error.
     SerdeError(serde_json::error::Error),
+    /// Binary de/serialization error.
+    BincodeError(bincode::Error),
+    /// I/O error.
+    IoError(std::io::Error),
+    /// Serialized data in a version of the format that is not supported, see
+    /// [`crate::serialization`].
+    UnsupportedFormatVersion(u32),
     /// JSON-RPC related error.
     JSONRpcError(ProviderError),
     /// OpcodeId is not a call type.
@@ -38,6 +47,26 @@ pub enum Error {
     EthTypeError(eth_types::Error),
     /// EVM Execution error
     ExecutionError(ExecError),
+    /// Merkle Patricia Trie error
+    TrieError(TrieError),
+    /// Block of a batch that isn't the child of the previous block of the
+    /// batch, identified by its number.
+    NonConsecutiveBlock(u64),
+    /// State root of a block, identified by its number, that doesn't match
+    /// the one computed from the state trie: (number, block root, computed
+    /// root)
+    StateRootMismatch(u64, H256, H256),
+    /// Transaction, identified by its index in the block, that doesn't fit in
+    /// the circuits even in a chunk of its own.
+    OversizedTx(usize),
+    /// Transaction, identified by its hash, with a nonce or gas limit that
+    /// doesn't fit in a u64, which no valid block can include (EIP-2681).
+    TxFieldOverflow(H256, &'static str),
+    /// Recipient of a withdrawal of a block of a batch that a transaction of a
+    /// later block accesses, identified by the number of that block.  The
+    /// withdrawals of a batch are applied after its last transaction, so the
+    /// access wouldn't see the withdrawn amount: (recipient, number)
+    WithdrawalRecipientAccess(Address, u64),
     /// Internal Code error
     InternalError(&'static str),
 }
@@ -48,12 +77,24 @@ impl From<eth_types::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IoError(err)
+    }
+}
+
 impl From<ProviderError> for Error {
     fn from(err: ProviderError) -> Self {
         Error::JSONRpcError(err)
     }
 }
 
+impl From<TrieError> for Error {
+    fn from(err: TrieError) -> Self {
+        Error::TrieError(err)
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         write!(f, "{:?}", self)
@@ -63,7 +104,7 @@ impl Display for Error {
 impl StdError for Error {}
 
 /// Out of Gas errors by opcode
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OogError {
     /// Out of Gas for opcodes which have non-zero constant gas cost
     Constant,
@@ -73,8 +114,8 @@ pub enum OogError {
     /// Out of Gas for CREATE, RETURN, REVERT, which have dynamic memory
     /// expansion gas cost
     DynamicMemoryExpansion,
-    /// Out of Gas for CALLDATACOPY, CODECOPY, RETURNDATACOPY, which copy a
-    /// specified chunk of memory
+    /// Out of Gas for CALLDATACOPY, CODECOPY, RETURNDATACOPY, MCOPY, which copy
+    /// a specified chunk of memory
     MemoryCopy,
     /// Out of Gas for BALANCE, EXTCODESIZE, EXTCODEHASH, which possibly touch
     /// an extra account
@@ -109,7 +150,7 @@ pub enum OogError {
 }
 
 /// EVM Execution Error
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecError {
     /// Invalid Opcode
     InvalidOpcode,
@@ -119,8 +160,8 @@ pub enum ExecError {
     StackUnderflow,
     /// Out of Gas
     OutOfGas(OogError),
-    /// For SSTORE, LOG0, LOG1, LOG2, LOG3, LOG4, CREATE, CALL, CREATE2,
-    /// SELFDESTRUCT
+    /// For SSTORE, TSTORE, LOG0, LOG1, LOG2, LOG3, LOG4, CREATE, CALL,
+    /// CREATE2, SELFDESTRUCT
     WriteProtection,
     /// For CALL, CALLCODE, DELEGATECALL, STATICCALL
     Depth,
@@ -141,6 +182,23 @@ pub enum ExecError {
     MaxCodeSizeExceeded,
 }
 
+/// Merkle Patricia Trie Error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrieError {
+    /// Node that is only known by its hash in a partial trie
+    NodeNotFound(H256),
+    /// Node or value with an invalid RLP encoding
+    InvalidEncoding(DecoderError),
+    /// Proof of an account whose values don't match the ones in the trie
+    InvalidProof(Address),
+}
+
+impl From<DecoderError> for TrieError {
+    fn from(err: DecoderError) -> Self {
+        TrieError::InvalidEncoding(err)
+    }
+}
+
 // TODO: Move to impl block.
 pub(crate) fn get_step_reported_error(op: &OpcodeId, error: &str) -> ExecError {
     if error == GETH_ERR_OUT_OF_GAS || error == GETH_ERR_GAS_UINT_OVERFLOW {
@@ -152,9 +210,10 @@ pub(crate) fn get_step_reported_error(op: &OpcodeId, error: &str) -> ExecError {
             OpcodeId::CREATE | OpcodeId::RETURN | OpcodeId::REVERT => {
                 OogError::DynamicMemoryExpansion
             }
-            OpcodeId::CALLDATACOPY | OpcodeId::CODECOPY | OpcodeId::RETURNDATACOPY => {
-                OogError::MemoryCopy
-            }
+            OpcodeId::CALLDATACOPY
+            | OpcodeId::CODECOPY
+            | OpcodeId::RETURNDATACOPY
+            | OpcodeId::MCOPY => OogError::MemoryCopy,
             OpcodeId::BALANCE | OpcodeId::EXTCODESIZE | OpcodeId::EXTCODEHASH => {
                 OogError::AccountAccess
             }
@@ -10,7 +10,7 @@ use crate::{
 };
 use core::fmt::Debug;
 use eth_types::{
-    evm_types::{GasCost, MAX_REFUND_QUOTIENT_OF_GAS_USED},
+    evm_types::{GasCost, Hardfork},
     evm_unimplemented, GethExecStep, ToAddress, ToWord, Word,
 };
 use keccak256::EMPTY_HASH;
@@ -20,6 +20,7 @@ pub use self::sha3::sha3_tests::{gen_sha3_code, MemoryKind};
 
 mod address;
 mod balance;
+mod blockctx;
 mod calldatacopy;
 mod calldataload;
 mod calldatasize;
@@ -37,6 +38,7 @@ mod extcodehash;
 mod extcodesize;
 mod gasprice;
 mod logs;
+mod mcopy;
 mod mload;
 mod mstore;
 mod number;
@@ -51,6 +53,8 @@ mod sstore;
 mod stackonlyop;
 mod stop;
 mod swap;
+mod tload;
+mod tstore;
 
 mod error_invalid_jump;
 mod error_oog_call;
@@ -61,6 +65,7 @@ mod memory_expansion_test;
 use self::sha3::Sha3;
 use address::Address;
 use balance::Balance;
+use blockctx::BlockCtx;
 use calldatacopy::Calldatacopy;
 use calldataload::Calldataload;
 use calldatasize::Calldatasize;
@@ -79,6 +84,7 @@ use extcodehash::Extcodehash;
 use extcodesize::Extcodesize;
 use gasprice::GasPrice;
 use logs::Log;
+use mcopy::Mcopy;
 use mload::Mload;
 use mstore::Mstore;
 use origin::Origin;
@@ -91,6 +97,8 @@ use sstore::Sstore;
 use stackonlyop::StackOnlyOpcode;
 use stop::Stop;
 use swap::Swap;
+use tload::Tload;
+use tstore::Tstore;
 
 /// Generic opcode trait which defines the logic of the
 /// [`Operation`](crate::operation::Operation) that should be generated for one
@@ -173,15 +181,15 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::RETURNDATASIZE => Returndatasize::gen_associated_ops,
         OpcodeId::RETURNDATACOPY => Returndatacopy::gen_associated_ops,
         OpcodeId::EXTCODEHASH => Extcodehash::gen_associated_ops,
-        OpcodeId::BLOCKHASH => StackOnlyOpcode::<1, 1>::gen_associated_ops,
-        OpcodeId::COINBASE => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::TIMESTAMP => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::NUMBER => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::DIFFICULTY => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::GASLIMIT => StackOnlyOpcode::<0, 1>::gen_associated_ops,
+        OpcodeId::BLOCKHASH => BlockCtx::<1>::gen_associated_ops,
+        OpcodeId::COINBASE => BlockCtx::<0>::gen_associated_ops,
+        OpcodeId::TIMESTAMP => BlockCtx::<0>::gen_associated_ops,
+        OpcodeId::NUMBER => BlockCtx::<0>::gen_associated_ops,
+        OpcodeId::DIFFICULTY => BlockCtx::<0>::gen_associated_ops,
+        OpcodeId::GASLIMIT => BlockCtx::<0>::gen_associated_ops,
         OpcodeId::CHAINID => StackOnlyOpcode::<0, 1>::gen_associated_ops,
         OpcodeId::SELFBALANCE => Selfbalance::gen_associated_ops,
-        OpcodeId::BASEFEE => StackOnlyOpcode::<0, 1>::gen_associated_ops,
+        OpcodeId::BASEFEE => BlockCtx::<0>::gen_associated_ops,
         OpcodeId::POP => StackOnlyOpcode::<1, 0>::gen_associated_ops,
         OpcodeId::MLOAD => Mload::gen_associated_ops,
         OpcodeId::MSTORE => Mstore::<false>::gen_associated_ops,
@@ -194,6 +202,10 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::MSIZE => StackOnlyOpcode::<0, 1>::gen_associated_ops,
         OpcodeId::GAS => StackOnlyOpcode::<0, 1>::gen_associated_ops,
         OpcodeId::JUMPDEST => Dummy::gen_associated_ops,
+        OpcodeId::TLOAD => Tload::gen_associated_ops,
+        OpcodeId::TSTORE => Tstore::gen_associated_ops,
+        OpcodeId::MCOPY => Mcopy::gen_associated_ops,
+        OpcodeId::PUSH0 => StackOnlyOpcode::<0, 1>::gen_associated_ops,
         OpcodeId::DUP1 => Dup::<1>::gen_associated_ops,
         OpcodeId::DUP2 => Dup::<2>::gen_associated_ops,
         OpcodeId::DUP3 => Dup::<3>::gen_associated_ops,
@@ -341,21 +353,39 @@ pub fn gen_begin_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
             (call.is_persistent as usize).into(),
         ),
         (CallContextField::IsSuccess, call.is_success.to_word()),
+        (
+            CallContextField::IsInvalidTx,
+            (state.tx.is_invalid as usize).into(),
+        ),
     ] {
         state.call_context_write(&mut exec_step, call.call_id, field, value);
     }
 
-    // Increase caller's nonce
+    // Increase caller's nonce, which is left untouched for an invalid tx
     let caller_address = call.caller_address;
     let nonce_prev = state.sdb.get_account(&caller_address).1.nonce;
     state.account_write(
         &mut exec_step,
         caller_address,
         AccountField::Nonce,
-        nonce_prev + 1,
+        nonce_prev + (!state.tx.is_invalid) as u64,
         nonce_prev,
     )?;
 
+    // An invalid tx is skipped: we only read the caller's balance to prove
+    // the validity check, and go to EndTx.
+    if state.tx.is_invalid {
+        let caller_balance = state.sdb.get_account(&caller_address).1.balance;
+        state.account_read(
+            &mut exec_step,
+            caller_address,
+            AccountField::Balance,
+            caller_balance,
+            caller_balance,
+        )?;
+        return Ok(exec_step);
+    }
+
     // Add caller and callee into access list
     for address in [call.caller_address, call.address] {
         state.sdb.add_account_to_access_list(address);
@@ -368,18 +398,23 @@ pub fn gen_begin_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
         )?;
     }
 
+    // From Shanghai the coinbase is warm at the beginning of the tx (EIP-3651)
+    let hardfork = state.block.circuits_params.hardfork;
+    if hardfork >= Hardfork::Shanghai {
+        let coinbase = state.block.coinbase;
+        let is_warm_prev = coinbase == call.caller_address || coinbase == call.address;
+        state.sdb.add_account_to_access_list(coinbase);
+        state.tx_accesslist_account_write(
+            &mut exec_step,
+            state.tx_ctx.id(),
+            coinbase,
+            true,
+            is_warm_prev,
+        )?;
+    }
+
     // Calculate intrinsic gas cost
-    let call_data_gas_cost = state
-        .tx
-        .input
-        .iter()
-        .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 });
-    let intrinsic_gas_cost = if state.tx.is_create() {
-        GasCost::CREATION_TX.as_u64()
-    } else {
-        GasCost::TX.as_u64()
-    } + call_data_gas_cost;
-    exec_step.gas_cost = GasCost(intrinsic_gas_cost);
+    exec_step.gas_cost = GasCost(state.tx.intrinsic_gas(hardfork));
 
     // Transfer with fee
     state.transfer_with_fee(
@@ -506,6 +541,12 @@ pub fn gen_end_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Erro
         CallContextField::IsPersistent,
         Word::from(call.is_persistent as u8),
     );
+    state.call_context_read(
+        &mut exec_step,
+        call.call_id,
+        CallContextField::IsInvalidTx,
+        Word::from(state.tx.is_invalid as u8),
+    );
 
     let refund = state.sdb.refund();
     state.push_op(
@@ -518,15 +559,20 @@ pub fn gen_end_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Erro
         },
     );
 
-    let effective_refund =
-        refund.min((state.tx.gas - exec_step.gas_left.0) / MAX_REFUND_QUOTIENT_OF_GAS_USED as u64);
+    let max_refund_quotient = state.block.circuits_params.hardfork.max_refund_quotient();
+    let effective_refund = refund.min((state.tx.gas - exec_step.gas_left.0) / max_refund_quotient);
     let (found, caller_account) = state.sdb.get_account(&call.caller_address);
     if !found {
         return Err(Error::AccountNotFound(call.caller_address));
     }
     let caller_balance_prev = caller_account.balance;
-    let caller_balance =
-        caller_balance_prev + state.tx.gas_price * (exec_step.gas_left.0 + effective_refund);
+    // The caller of an invalid tx hasn't paid for the gas, so nothing is
+    // refunded.
+    let caller_balance = if state.tx.is_invalid {
+        caller_balance_prev
+    } else {
+        caller_balance_prev + state.tx.gas_price * (exec_step.gas_left.0 + effective_refund)
+    };
     state.account_write(
         &mut exec_step,
         call.caller_address,
@@ -567,7 +613,14 @@ pub fn gen_end_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Erro
         log_id as u64,
     )?;
 
-    if state.tx_ctx.id() > 1 {
+    // The cumulative gas is per block, so it restarts from 0 at the first tx
+    // of each block of the batch.
+    let is_first_tx_of_block = state
+        .block
+        .txs
+        .last()
+        .map_or(true, |prev_tx| prev_tx.block_num != state.tx.block_num);
+    if !is_first_tx_of_block {
         // query pre tx cumulative gas
         state.tx_receipt_read(
             &mut exec_step,
@@ -640,7 +693,15 @@ fn dummy_gen_selfdestruct_ops(
     let value = sender_account.balance;
     state.transfer(&mut exec_step, sender, receiver, value)?;
 
-    if state.call()?.is_persistent {
+    // From Cancun, an account which isn't created in the same tx only sends
+    // its balance (EIP-6780).
+    let is_created_in_tx = state
+        .tx
+        .calls()
+        .iter()
+        .any(|call| call.is_create() && call.address == sender);
+    let hardfork = state.block.circuits_params.hardfork;
+    if state.call()?.is_persistent && (!hardfork.is_selfdestruct_restricted() || is_created_in_tx) {
         state.sdb.destruct_account(sender);
     }
 
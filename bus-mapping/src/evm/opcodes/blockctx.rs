@@ -0,0 +1,32 @@
+use super::{stackonlyop::StackOnlyOpcode, Opcode};
+use crate::circuit_input_builder::{CircuitInputStateRef, ExecStep};
+use crate::operation::CallContextField;
+use crate::Error;
+use eth_types::GethExecStep;
+
+/// Placeholder structure used to implement [`Opcode`] trait over it
+/// corresponding to the opcodes that read the context of the block of the
+/// transaction: take N words and return one, like [`StackOnlyOpcode`], and
+/// then read the TxId, whose block number selects the block context in a
+/// batch of blocks.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct BlockCtx<const N_POP: usize>;
+
+impl<const N_POP: usize> Opcode for BlockCtx<N_POP> {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let mut exec_steps = StackOnlyOpcode::<N_POP, 1>::gen_associated_ops(state, geth_steps)?;
+
+        // CallContext read of the TxId
+        state.call_context_read(
+            &mut exec_steps[0],
+            state.call()?.call_id,
+            CallContextField::TxId,
+            state.tx_ctx.id().into(),
+        );
+
+        Ok(exec_steps)
+    }
+}
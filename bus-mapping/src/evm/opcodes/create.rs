@@ -2,7 +2,7 @@ use crate::circuit_input_builder::{CircuitInputStateRef, ExecStep};
 use crate::evm::Opcode;
 use crate::operation::{AccountField, AccountOp, CallContextField, TxAccessListAccountOp, RW};
 use crate::Error;
-use eth_types::{evm_types::gas_utils::memory_expansion_gas_cost, GethExecStep, ToWord, Word};
+use eth_types::{GethExecStep, ToWord, Word};
 use keccak256::EMPTY_HASH;
 
 #[derive(Debug, Copy, Clone)]
@@ -19,7 +19,6 @@ impl<const IS_CREATE2: bool> Opcode for DummyCreate<IS_CREATE2> {
         let offset = geth_step.stack.nth_last(1)?.as_usize();
         let length = geth_step.stack.nth_last(2)?.as_usize();
 
-        let curr_memory_word_size = (state.call_ctx()?.memory.len() as u64) / 32;
         if length != 0 {
             state
                 .call_ctx_mut()?
@@ -127,12 +126,11 @@ impl<const IS_CREATE2: bool> Opcode for DummyCreate<IS_CREATE2> {
             call.value,
         )?;
 
-        let memory_expansion_gas_cost =
-            memory_expansion_gas_cost(curr_memory_word_size, next_memory_word_size);
-
         // EIP-150: all but one 64th of the caller's gas is sent to the callee.
-        let caller_gas_left =
-            (geth_step.gas.0 - geth_step.gas_cost.0 - memory_expansion_gas_cost) / 64;
+        // The gas cost of the step already includes the memory expansion, the
+        // hashing of the init code for CREATE2 and, from Shanghai, the init
+        // code words (EIP-3860).
+        let caller_gas_left = (geth_step.gas.0 - geth_step.gas_cost.0) / 64;
 
         for (field, value) in [
             (
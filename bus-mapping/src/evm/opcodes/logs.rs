@@ -86,23 +86,29 @@ fn gen_log_step(
         Word::from(state.call()?.is_persistent as u8),
     );
 
-    if state.call()?.is_persistent {
-        state.tx_log_write(
-            &mut exec_step,
-            state.tx_ctx.id(),
-            state.tx_ctx.log_id + 1,
-            TxLogField::Address,
-            0,
-            state.call()?.address.to_word(),
-        )?;
-    }
-
     // generates topic operation dynamically
     let topic_count = match exec_step.exec_state {
         ExecState::Op(op_id) => op_id.postfix().expect("opcode with postfix") as usize,
         _ => panic!("currently only handle successful log state"),
     };
 
+    if state.call()?.is_persistent {
+        for (field, value) in [
+            (TxLogField::Address, state.call()?.address.to_word()),
+            (TxLogField::TopicLength, Word::from(topic_count)),
+            (TxLogField::DataLength, msize),
+        ] {
+            state.tx_log_write(
+                &mut exec_step,
+                state.tx_ctx.id(),
+                state.tx_ctx.log_id + 1,
+                field,
+                0,
+                value,
+            )?;
+        }
+    }
+
     for i in 0..topic_count {
         let topic = geth_step.stack.nth_last(2 + i)?;
         state.stack_read(
@@ -360,19 +366,42 @@ mod log_tests {
         // TODO: handle is_persistent = false conditions
         if is_persistent {
             assert_eq!(
-                [6].map(|idx| &builder.block.container.tx_log
-                    [step.bus_mapping_instance[idx].as_usize()])
+                [6, 7, 8]
+                    .map(|idx| &builder.block.container.tx_log
+                        [step.bus_mapping_instance[idx].as_usize()])
                     .map(|operation| (operation.rw(), operation.op())),
-                [(
-                    RW::WRITE,
-                    &TxLogOp {
-                        tx_id: 1,
-                        log_id: step.log_id + 1,
-                        field: TxLogField::Address,
-                        index: 0,
-                        value: callee_address.to_word(),
-                    }
-                ),]
+                [
+                    (
+                        RW::WRITE,
+                        &TxLogOp {
+                            tx_id: 1,
+                            log_id: step.log_id + 1,
+                            field: TxLogField::Address,
+                            index: 0,
+                            value: callee_address.to_word(),
+                        }
+                    ),
+                    (
+                        RW::WRITE,
+                        &TxLogOp {
+                            tx_id: 1,
+                            log_id: step.log_id + 1,
+                            field: TxLogField::TopicLength,
+                            index: 0,
+                            value: Word::from(topic_count),
+                        }
+                    ),
+                    (
+                        RW::WRITE,
+                        &TxLogOp {
+                            tx_id: 1,
+                            log_id: step.log_id + 1,
+                            field: TxLogField::DataLength,
+                            index: 0,
+                            value: Word::from(msize),
+                        }
+                    ),
+                ]
             );
         }
 
@@ -385,7 +414,7 @@ mod log_tests {
             ));
         }
         assert_eq!(
-            (1..1 + topic_count)
+            (3..3 + topic_count)
                 .map(|idx| &builder.block.container.tx_log[idx])
                 .map(|op| (op.rw(), op.op().clone()))
                 .collect::<Vec<(RW, TxLogOp)>>(),
@@ -424,7 +453,7 @@ mod log_tests {
             },
         );
         assert_eq!(
-            ((1 + topic_count)..msize + 1 + topic_count)
+            ((3 + topic_count)..msize + 3 + topic_count)
                 .map(|idx| &builder.block.container.tx_log[idx])
                 .map(|op| (op.rw(), op.op().clone()))
                 .collect::<Vec<(RW, TxLogOp)>>(),
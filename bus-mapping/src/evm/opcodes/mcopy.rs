@@ -0,0 +1,194 @@
+use super::Opcode;
+use crate::circuit_input_builder::{
+    CircuitInputStateRef, CopyDataType, CopyEvent, ExecStep, NumberOrHash,
+};
+use crate::Error;
+use eth_types::GethExecStep;
+
+/// Placeholder structure used to implement [`Opcode`] trait over it
+/// corresponding to the [`OpcodeId::MCOPY`](crate::evm::OpcodeId::MCOPY)
+/// `OpcodeId`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Mcopy;
+
+impl Opcode for Mcopy {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let exec_steps = vec![gen_mcopy_step(state, geth_step)?];
+
+        // The copy event reads the source bytes before the memory is updated.
+        let copy_event = gen_copy_event(state, geth_step)?;
+
+        // reconstruction
+        let dst_offset = geth_step.stack.nth_last(0)?.as_usize();
+        let src_offset = geth_step.stack.nth_last(1)?.as_usize();
+        let length = geth_step.stack.nth_last(2)?.as_usize();
+        if length != 0 {
+            let memory = &mut state.call_ctx_mut()?.memory;
+            memory.extend_at_least(std::cmp::max(src_offset, dst_offset) + length);
+            memory
+                .0
+                .copy_within(src_offset..src_offset + length, dst_offset);
+        }
+
+        state.push_copy(copy_event);
+        Ok(exec_steps)
+    }
+}
+
+fn gen_mcopy_step(
+    state: &mut CircuitInputStateRef,
+    geth_step: &GethExecStep,
+) -> Result<ExecStep, Error> {
+    let mut exec_step = state.new_step(geth_step)?;
+
+    let dst_offset = geth_step.stack.nth_last(0)?;
+    let src_offset = geth_step.stack.nth_last(1)?;
+    let length = geth_step.stack.nth_last(2)?;
+
+    // stack reads
+    state.stack_read(
+        &mut exec_step,
+        geth_step.stack.nth_last_filled(0),
+        dst_offset,
+    )?;
+    state.stack_read(
+        &mut exec_step,
+        geth_step.stack.nth_last_filled(1),
+        src_offset,
+    )?;
+    state.stack_read(&mut exec_step, geth_step.stack.nth_last_filled(2), length)?;
+
+    Ok(exec_step)
+}
+
+fn gen_copy_steps(
+    state: &mut CircuitInputStateRef,
+    exec_step: &mut ExecStep,
+    src_addr: u64,
+    dst_addr: u64,
+    bytes_left: u64,
+) -> Result<Vec<(u8, bool)>, Error> {
+    let memory = &state.call_ctx()?.memory;
+    let bytes: Vec<u8> = (src_addr..src_addr + bytes_left)
+        .map(|addr| memory.0.get(addr as usize).copied().unwrap_or_default())
+        .collect();
+
+    // Read all the source bytes before writing any of them, so that
+    // overlapping ranges are copied correctly.
+    for (idx, byte) in bytes.iter().enumerate() {
+        state.memory_read(exec_step, (src_addr + idx as u64).into(), *byte)?;
+    }
+    for (idx, byte) in bytes.iter().enumerate() {
+        state.memory_write(exec_step, (dst_addr + idx as u64).into(), *byte)?;
+    }
+
+    Ok(bytes.into_iter().map(|byte| (byte, false)).collect())
+}
+
+fn gen_copy_event(
+    state: &mut CircuitInputStateRef,
+    geth_step: &GethExecStep,
+) -> Result<CopyEvent, Error> {
+    let rw_counter_start = state.block_ctx.rwc;
+
+    let dst_offset = geth_step.stack.nth_last(0)?.as_u64();
+    let src_offset = geth_step.stack.nth_last(1)?.as_u64();
+    let length = geth_step.stack.nth_last(2)?.as_u64();
+
+    let mut exec_step = state.new_step(geth_step)?;
+    let copy_steps = gen_copy_steps(state, &mut exec_step, src_offset, dst_offset, length)?;
+
+    let call_id = state.call()?.call_id;
+    Ok(CopyEvent {
+        src_type: CopyDataType::Memory,
+        src_id: NumberOrHash::Number(call_id),
+        src_addr: src_offset,
+        src_addr_end: src_offset + length,
+        dst_type: CopyDataType::Memory,
+        dst_id: NumberOrHash::Number(call_id),
+        dst_addr: dst_offset,
+        log_id: None,
+        rw_counter_start,
+        bytes: copy_steps,
+    })
+}
+
+#[cfg(test)]
+mod mcopy_tests {
+    use crate::{circuit_input_builder::CircuitsParams, mock::BlockData};
+    use eth_types::{bytecode, evm_types::Hardfork, geth_types::GethData, Word};
+    use mock::test_ctx::{helpers::*, TestContext};
+    use pretty_assertions::assert_eq;
+
+    /// Returns the bytes copied by `MCOPY` from a memory holding 0, 1, 2, ...
+    /// up to 63.
+    fn copied_bytes(dst_offset: u64, src_offset: u64, length: u64) -> Vec<u8> {
+        let memory: Vec<u8> = (0..64).collect();
+        let code = bytecode! {
+            PUSH32(Word::from_big_endian(&memory[..32]))
+            PUSH1(0x00)
+            MSTORE
+            PUSH32(Word::from_big_endian(&memory[32..]))
+            PUSH1(0x20)
+            MSTORE
+            PUSH32(length)
+            PUSH32(src_offset)
+            PUSH32(dst_offset)
+            MCOPY
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new_with_hardfork(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(0xcafeu64),
+            Hardfork::Cancun,
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block.clone(),
+            CircuitsParams {
+                hardfork: Hardfork::Cancun,
+                ..Default::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let copy_events = &builder.block.copy_events;
+        assert_eq!(copy_events.len(), 1);
+        assert_eq!(copy_events[0].src_addr, src_offset);
+        assert_eq!(copy_events[0].dst_addr, dst_offset);
+        copy_events[0].bytes.iter().map(|(byte, _)| *byte).collect()
+    }
+
+    #[test]
+    fn mcopy_opcode_impl_overlapping() {
+        // The bytes are copied as they were before the copy, whether the
+        // destination is after the source or before it.
+        assert_eq!(
+            copied_bytes(0x10, 0x00, 0x30),
+            (0x00..0x30).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            copied_bytes(0x00, 0x10, 0x30),
+            (0x10..0x40).collect::<Vec<u8>>()
+        );
+    }
+
+    #[test]
+    fn mcopy_opcode_impl_beyond_memory() {
+        // The bytes beyond the memory are read as zeros
+        let mut expected: Vec<u8> = (0x30..0x40).collect();
+        expected.extend([0; 0x10]);
+        assert_eq!(copied_bytes(0x00, 0x30, 0x20), expected);
+    }
+}
@@ -4,10 +4,10 @@ mod number_tests {
         circuit_input_builder::ExecState,
         evm::OpcodeId,
         mock::BlockData,
-        operation::{StackOp, RW},
+        operation::{CallContextField, CallContextOp, StackOp, RW},
         Error,
     };
-    use eth_types::{bytecode, evm_types::StackAddress, geth_types::GethData};
+    use eth_types::{bytecode, evm_types::StackAddress, geth_types::GethData, Word};
     use mock::test_ctx::{helpers::*, TestContext};
     use pretty_assertions::assert_eq;
 
@@ -50,6 +50,22 @@ mod number_tests {
             )
         );
 
+        // The TxId selects the context of the block of the tx
+        let call_id = builder.block.txs()[0].calls()[0].call_id;
+        let op_tx_id =
+            &builder.block.container.call_context[step.bus_mapping_instance[1].as_usize()];
+        assert_eq!(
+            (op_tx_id.rw(), op_tx_id.op()),
+            (
+                RW::READ,
+                &CallContextOp {
+                    call_id,
+                    field: CallContextField::TxId,
+                    value: Word::one(),
+                }
+            )
+        );
+
         Ok(())
     }
 }
@@ -0,0 +1,209 @@
+use super::Opcode;
+use crate::circuit_input_builder::{CircuitInputStateRef, ExecStep};
+use crate::operation::CallContextField;
+use crate::{
+    operation::{TransientStorageOp, RW},
+    Error,
+};
+use eth_types::{GethExecStep, ToWord, Word};
+
+/// Placeholder structure used to implement [`Opcode`] trait over it
+/// corresponding to the [`OpcodeId::TLOAD`](crate::evm::OpcodeId::TLOAD)
+/// `OpcodeId`.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Tload;
+
+impl Opcode for Tload {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let mut exec_step = state.new_step(geth_step)?;
+
+        let call_id = state.call()?.call_id;
+        let contract_addr = state.call()?.address;
+
+        state.call_context_read(
+            &mut exec_step,
+            call_id,
+            CallContextField::TxId,
+            Word::from(state.tx_ctx.id()),
+        );
+
+        state.call_context_read(
+            &mut exec_step,
+            call_id,
+            CallContextField::CalleeAddress,
+            contract_addr.to_word(),
+        );
+
+        // First stack read
+        let key = geth_step.stack.last()?;
+        let stack_position = geth_step.stack.last_filled();
+
+        // Manage first stack read at latest stack position
+        state.stack_read(&mut exec_step, stack_position, key)?;
+
+        // Transient storage read.  The trace doesn't include transient storage,
+        // so the value is taken from the transient storage of the tx in the
+        // StateDB.
+        let value = *state.sdb.get_transient_storage(&contract_addr, &key);
+        state.push_op(
+            &mut exec_step,
+            RW::READ,
+            TransientStorageOp::new(state.tx_ctx.id(), contract_addr, key, value, value),
+        );
+
+        // First stack write
+        state.stack_write(&mut exec_step, stack_position, value)?;
+
+        Ok(vec![exec_step])
+    }
+}
+
+#[cfg(test)]
+mod tload_tests {
+    use super::*;
+    use crate::{
+        circuit_input_builder::{CircuitInputBuilder, CircuitsParams, ExecState},
+        mock::BlockData,
+        operation::StackOp,
+    };
+    use eth_types::{
+        bytecode,
+        evm_types::{Hardfork, OpcodeId, StackAddress},
+        geth_types::GethData,
+        Bytecode,
+    };
+    use mock::{
+        test_ctx::{helpers::*, TestContext},
+        MOCK_ACCOUNTS,
+    };
+    use pretty_assertions::assert_eq;
+
+    /// Returns the circuit input of a block with `NTX` txs calling `code`.
+    fn build_block<const NTX: usize>(code: Bytecode) -> CircuitInputBuilder {
+        let block: GethData = TestContext::<2, NTX>::new_with_hardfork(
+            None,
+            account_0_code_account_1_no_code(code),
+            |txs, accs| {
+                for tx in txs {
+                    tx.from(accs[1].address).to(accs[0].address);
+                }
+            },
+            |block, _tx| block.number(0xcafeu64),
+            Hardfork::Cancun,
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block.clone(),
+            CircuitsParams {
+                max_txs: NTX,
+                hardfork: Hardfork::Cancun,
+                ..Default::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        builder
+    }
+
+    /// Returns the transient storage operation of the `TLOAD` of the tx at
+    /// `tx_index`.
+    fn tload_op(builder: &CircuitInputBuilder, tx_index: usize) -> (RW, TransientStorageOp) {
+        let step = builder.block.txs()[tx_index]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::TLOAD))
+            .unwrap();
+        let operation =
+            &builder.block.container.transient_storage[step.bus_mapping_instance[3].as_usize()];
+        (operation.rw(), operation.op().clone())
+    }
+
+    #[test]
+    fn tload_opcode_impl_after_tstore() {
+        let code = bytecode! {
+            // Write 0x6f to transient storage slot 0
+            PUSH1(0x6fu64)
+            PUSH1(0x00u64)
+            TSTORE
+            // Load transient storage slot 0
+            PUSH1(0x00u64)
+            TLOAD
+            STOP
+        };
+        let builder = build_block::<1>(code);
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::TLOAD))
+            .unwrap();
+        assert_eq!(
+            [2, 4]
+                .map(|idx| &builder.block.container.stack[step.bus_mapping_instance[idx].as_usize()])
+                .map(|operation| (operation.rw(), operation.op())),
+            [
+                (
+                    RW::READ,
+                    &StackOp::new(1, StackAddress::from(1023), Word::zero())
+                ),
+                (
+                    RW::WRITE,
+                    &StackOp::new(1, StackAddress::from(1023), Word::from(0x6fu64))
+                )
+            ]
+        );
+        assert_eq!(
+            tload_op(&builder, 0),
+            (
+                RW::READ,
+                TransientStorageOp::new(
+                    1,
+                    MOCK_ACCOUNTS[0],
+                    Word::zero(),
+                    Word::from(0x6fu64),
+                    Word::from(0x6fu64),
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn tload_opcode_impl_cleared_across_txs() {
+        // Each tx loads the slot before writing it, which reads zero since the
+        // transient storage of the previous tx is discarded.
+        let code = bytecode! {
+            PUSH1(0x00u64)
+            TLOAD
+            POP
+            PUSH1(0x6fu64)
+            PUSH1(0x00u64)
+            TSTORE
+            STOP
+        };
+        let builder = build_block::<2>(code);
+
+        for tx_index in 0..2 {
+            assert_eq!(
+                tload_op(&builder, tx_index),
+                (
+                    RW::READ,
+                    TransientStorageOp::new(
+                        tx_index + 1,
+                        MOCK_ACCOUNTS[0],
+                        Word::zero(),
+                        Word::zero(),
+                        Word::zero(),
+                    )
+                )
+            );
+        }
+    }
+}
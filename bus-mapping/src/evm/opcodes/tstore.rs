@@ -0,0 +1,216 @@
+use super::Opcode;
+use crate::circuit_input_builder::{CircuitInputStateRef, ExecStep};
+use crate::operation::CallContextField;
+use crate::{
+    operation::{TransientStorageOp, RW},
+    Error,
+};
+use eth_types::{GethExecStep, ToWord, Word};
+
+/// Placeholder structure used to implement [`Opcode`] trait over it
+/// corresponding to the [`OpcodeId::TSTORE`](crate::evm::OpcodeId::TSTORE)
+/// `OpcodeId`.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Tstore;
+
+impl Opcode for Tstore {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let mut exec_step = state.new_step(geth_step)?;
+
+        let contract_addr = state.call()?.address;
+
+        state.call_context_read(
+            &mut exec_step,
+            state.call()?.call_id,
+            CallContextField::TxId,
+            Word::from(state.tx_ctx.id()),
+        );
+        state.call_context_read(
+            &mut exec_step,
+            state.call()?.call_id,
+            CallContextField::IsStatic,
+            Word::from(state.call()?.is_static as u8),
+        );
+
+        state.call_context_read(
+            &mut exec_step,
+            state.call()?.call_id,
+            CallContextField::RwCounterEndOfReversion,
+            Word::from(state.call()?.rw_counter_end_of_reversion),
+        );
+
+        state.call_context_read(
+            &mut exec_step,
+            state.call()?.call_id,
+            CallContextField::IsPersistent,
+            Word::from(state.call()?.is_persistent as u8),
+        );
+
+        state.call_context_read(
+            &mut exec_step,
+            state.call()?.call_id,
+            CallContextField::CalleeAddress,
+            contract_addr.to_word(),
+        );
+
+        let key = geth_step.stack.nth_last(0)?;
+        let key_stack_position = geth_step.stack.nth_last_filled(0);
+        let value = geth_step.stack.nth_last(1)?;
+        let value_stack_position = geth_step.stack.nth_last_filled(1);
+
+        state.stack_read(&mut exec_step, key_stack_position, key)?;
+        state.stack_read(&mut exec_step, value_stack_position, value)?;
+
+        let value_prev = *state.sdb.get_transient_storage(&contract_addr, &key);
+
+        state.push_op_reversible(
+            &mut exec_step,
+            RW::WRITE,
+            TransientStorageOp::new(state.tx_ctx.id(), contract_addr, key, value, value_prev),
+        )?;
+
+        Ok(vec![exec_step])
+    }
+}
+
+#[cfg(test)]
+mod tstore_tests {
+    use super::*;
+    use crate::{
+        circuit_input_builder::{CircuitInputBuilder, CircuitsParams, ExecState},
+        error::ExecError,
+        mock::BlockData,
+    };
+    use eth_types::{
+        bytecode,
+        evm_types::{Hardfork, OpcodeId},
+        geth_types::GethData,
+        Bytecode,
+    };
+    use mock::{test_ctx::TestContext, MOCK_ACCOUNTS};
+    use pretty_assertions::assert_eq;
+
+    /// Returns the circuit input of a tx to a contract which calls a contract
+    /// holding `callee_code` with `call_op`.
+    fn build_block(call_op: OpcodeId, callee_code: Bytecode) -> CircuitInputBuilder {
+        let mut code = bytecode! {
+            PUSH1(0x00u64) // retLength
+            PUSH1(0x00u64) // retOffset
+            PUSH1(0x00u64) // argsLength
+            PUSH1(0x00u64) // argsOffset
+        };
+        if call_op == OpcodeId::CALL {
+            code.push(1, Word::zero()); // value
+        }
+        code.push(20, MOCK_ACCOUNTS[1].to_word())
+            .push(32, Word::from(50_000u64))
+            .write_op(call_op)
+            .write_op(OpcodeId::STOP);
+
+        let block: GethData = TestContext::<3, 1>::new_with_hardfork(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(10u64.pow(19)))
+                    .code(code);
+                accs[1]
+                    .address(MOCK_ACCOUNTS[1])
+                    .balance(Word::from(10u64.pow(19)))
+                    .code(callee_code);
+                accs[2]
+                    .address(MOCK_ACCOUNTS[2])
+                    .balance(Word::from(10u64.pow(19)));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[2].address).to(accs[0].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+            Hardfork::Cancun,
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block.clone(),
+            CircuitsParams {
+                hardfork: Hardfork::Cancun,
+                ..Default::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    fn tstore_opcode_impl_reverted_by_failing_call() {
+        let callee_code = bytecode! {
+            PUSH1(0x6fu64)
+            PUSH1(0x00u64)
+            TSTORE
+            PUSH1(0x00u64)
+            PUSH1(0x00u64)
+            REVERT
+        };
+        let builder = build_block(OpcodeId::CALL, callee_code);
+
+        // The write is undone when the call reverts
+        assert_eq!(
+            builder
+                .block
+                .container
+                .transient_storage
+                .iter()
+                .map(|operation| (operation.rw(), operation.op().clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    RW::WRITE,
+                    TransientStorageOp::new(
+                        1,
+                        MOCK_ACCOUNTS[1],
+                        Word::zero(),
+                        Word::from(0x6fu64),
+                        Word::zero(),
+                    )
+                ),
+                (
+                    RW::WRITE,
+                    TransientStorageOp::new(
+                        1,
+                        MOCK_ACCOUNTS[1],
+                        Word::zero(),
+                        Word::zero(),
+                        Word::from(0x6fu64),
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn tstore_opcode_impl_write_protection() {
+        let callee_code = bytecode! {
+            PUSH1(0x6fu64)
+            PUSH1(0x00u64)
+            TSTORE
+            STOP
+        };
+        let builder = build_block(OpcodeId::STATICCALL, callee_code);
+
+        let step = builder.block.txs()[0]
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::TSTORE))
+            .unwrap();
+        assert_eq!(step.error, Some(ExecError::WriteProtection));
+        assert!(builder.block.container.transient_storage.is_empty());
+    }
+}
@@ -2,9 +2,13 @@
 
 use crate::{
     circuit_input_builder::{Block, CircuitInputBuilder, CircuitsParams},
+    mpt::StateTrie,
+    operation::AccountField,
     state_db::{self, CodeDB, StateDB},
 };
-use eth_types::{geth_types::GethData, Word};
+use eth_types::{geth_types::GethData, ToWord, Word};
+
+pub mod server;
 
 /// BlockData is a type that contains all the information from a block required
 /// to build the circuit inputs.
@@ -14,6 +18,8 @@ pub struct BlockData {
     pub sdb: StateDB,
     /// CodeDB
     pub code_db: CodeDB,
+    /// State trie with the accounts of the StateDB
+    pub state_trie: StateTrie,
     /// chain id
     pub chain_id: Word,
     /// history hashes contains most recent 256 block hashes in history, where
@@ -37,7 +43,7 @@ impl BlockData {
             Block::new(
                 self.chain_id,
                 self.history_hashes.clone(),
-                Word::default(),
+                self.state_trie.clone(),
                 &self.eth_block,
                 self.circuits_params.clone(),
             )
@@ -51,6 +57,7 @@ impl BlockData {
     ) -> Self {
         let mut sdb = StateDB::new();
         let mut code_db = CodeDB::new();
+        let mut state_trie = StateTrie::default();
 
         sdb.set_account(
             &geth_data.eth_block.author.expect("Block.author"),
@@ -65,6 +72,20 @@ impl BlockData {
 
         for account in geth_data.accounts {
             let code_hash = code_db.insert(account.code.to_vec());
+            for (field, value) in [
+                (AccountField::Nonce, account.nonce),
+                (AccountField::Balance, account.balance),
+                (AccountField::CodeHash, code_hash.to_word()),
+            ] {
+                state_trie
+                    .set_account_field(&account.address, field, value)
+                    .expect("state trie of the mock accounts is complete");
+            }
+            for (key, value) in account.storage.iter() {
+                state_trie
+                    .set_storage(&account.address, key, *value)
+                    .expect("state trie of the mock accounts is complete");
+            }
             sdb.set_account(
                 &account.address,
                 state_db::Account {
@@ -79,6 +100,7 @@ impl BlockData {
         Self {
             sdb,
             code_db,
+            state_trie,
             chain_id: geth_data.chain_id,
             history_hashes: geth_data.history_hashes,
             eth_block: geth_data.eth_block,
@@ -0,0 +1,489 @@
+//! In-process mock of the geth JSON-RPC server, to test the
+//! [`BuilderClient`](crate::circuit_input_builder::BuilderClient) end to end
+//! without a node.  It serves the block of a [`GethData`], usually built with
+//! the `TestContext` of the `mock` crate, with the subset of the methods of
+//! [`GethClient`](crate::rpc::GethClient).
+
+use super::BlockData;
+use crate::{
+    error::TrieError,
+    state_db::{CodeDB, StateDB},
+};
+use eth_types::{
+    geth_types::{block_header_rlp, GethData},
+    Address, BigEndianHash, Bytes, EIP1186ProofResponse, GethExecTrace, Hash, StorageProof, Word,
+    H256, U64,
+};
+use ethers_core::utils::keccak256;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+type EthBlock = eth_types::Block<eth_types::Transaction>;
+
+/// JSON-RPC error returned by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Chain served by the [`MockGethServer`]: the block of a [`GethData`] with
+/// its traces, preceded by up to 256 empty blocks, and the state of the
+/// accounts of the [`GethData`] as the state before the block.
+#[derive(Debug)]
+pub struct MockGeth {
+    chain_id: Word,
+    /// Ancestors of the block, followed by the block
+    blocks: Vec<EthBlock>,
+    geth_traces: Vec<GethExecTrace>,
+    sdb: StateDB,
+    code_db: CodeDB,
+    /// Error messages returned by methods instead of their result
+    errors: HashMap<String, String>,
+}
+
+impl MockGeth {
+    /// Create the chain of the block of `geth_data`.  The hashes of its
+    /// ancestors are the `history_hashes` of `geth_data`, or the hashes of
+    /// their headers for the ancestors not in `history_hashes`, and the
+    /// parent hash of the block is set accordingly.  The state root of the
+    /// block is the one of the state after its txs.
+    pub fn new(geth_data: GethData) -> Self {
+        let block_data = BlockData::new_from_geth_data(geth_data.clone());
+        let mut block = geth_data.eth_block;
+        let number = block.number.expect("Block.number").as_u64();
+        let state_root = block_data.sdb.state_trie().root();
+
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&block, &geth_data.geth_traces)
+            .expect("the txs of the block can be handled");
+        block.state_root = builder
+            .sdb
+            .state_root()
+            .expect("state trie of the mock accounts is complete");
+
+        let mut blocks = Vec::new();
+        let mut parent_hash = Hash::zero();
+        for ancestor in number - std::cmp::min(256, number)..number {
+            let mut header = EthBlock {
+                parent_hash,
+                author: block.author,
+                state_root,
+                number: Some(ancestor.into()),
+                gas_limit: block.gas_limit,
+                timestamp: block.timestamp,
+                base_fee_per_gas: block.base_fee_per_gas,
+                ..Default::default()
+            };
+            // The latest history hash is the hash of the parent of the block
+            let history_index =
+                (geth_data.history_hashes.len() + ancestor as usize).checked_sub(number as usize);
+            let hash = match history_index {
+                Some(index) => H256::from_uint(&geth_data.history_hashes[index]),
+                None => H256(keccak256(block_header_rlp(&header))),
+            };
+            header.hash = Some(hash);
+            blocks.push(header);
+            parent_hash = hash;
+        }
+        block.parent_hash = parent_hash;
+        if block.hash.map_or(true, |hash| hash.is_zero()) {
+            block.hash = Some(H256(keccak256(block_header_rlp(&block))));
+        }
+        blocks.push(block);
+
+        Self {
+            chain_id: geth_data.chain_id,
+            blocks,
+            geth_traces: geth_data.geth_traces,
+            sdb: block_data.sdb,
+            code_db: block_data.code_db,
+            errors: HashMap::new(),
+        }
+    }
+
+    /// Make the calls of `method` fail with `message`.
+    pub fn with_error(mut self, method: &str, message: &str) -> Self {
+        self.errors.insert(method.to_string(), message.to_string());
+        self
+    }
+
+    /// The block of the [`GethData`], last in the chain
+    fn head(&self) -> &EthBlock {
+        self.blocks.last().expect("the chain contains the block")
+    }
+
+    fn block_by_number(&self, block_num: Value) -> Result<Option<&EthBlock>, RpcError> {
+        let number = match block_num.as_str() {
+            Some("latest" | "pending" | "safe" | "finalized") => return Ok(Some(self.head())),
+            Some("earliest") => U64::zero(),
+            _ => U64::deserialize(&block_num).map_err(|_| {
+                RpcError::new(-32602, format!("invalid block number {}", block_num))
+            })?,
+        };
+        Ok(self
+            .blocks
+            .iter()
+            .find(|block| block.number == Some(number)))
+    }
+
+    fn block_by_hash(&self, hash: Hash) -> Option<&EthBlock> {
+        self.blocks.iter().find(|block| block.hash == Some(hash))
+    }
+
+    /// Traces of `block`, in the format of `debug_traceBlockBy*`
+    fn traces(&self, block: Option<&EthBlock>) -> Result<Value, RpcError> {
+        match block {
+            Some(block) if block.hash == self.head().hash => Ok(Value::Array(
+                self.geth_traces
+                    .iter()
+                    .map(|trace| json!({ "result": trace }))
+                    .collect(),
+            )),
+            Some(_) => Ok(json!([])),
+            None => Err(RpcError::new(-32000, "block not found")),
+        }
+    }
+
+    fn code(&self, address: &Address) -> Bytes {
+        let (_, account) = self.sdb.get_account(address);
+        self.code_db
+            .0
+            .get(&account.code_hash)
+            .cloned()
+            .unwrap_or_default()
+            .into()
+    }
+
+    fn proof(&self, address: Address, keys: Vec<Word>) -> Result<EIP1186ProofResponse, RpcError> {
+        let internal = |err: TrieError| RpcError::new(-32000, format!("{:?}", err));
+        let trie = self.sdb.state_trie();
+        let account = trie
+            .account(&address)
+            .map_err(internal)?
+            .unwrap_or_default();
+        let account_proof = trie.account_proof(&address).map_err(internal)?;
+        let storage_proof = keys
+            .into_iter()
+            .map(|key| {
+                // The storage proof of the trie starts with the account proof
+                let proof = trie.storage_proof(&address, &key).map_err(internal)?;
+                Ok(StorageProof {
+                    key,
+                    value: trie.storage(&address, &key).map_err(internal)?,
+                    proof: proof[account_proof.len()..]
+                        .iter()
+                        .cloned()
+                        .map(Bytes::from)
+                        .collect(),
+                })
+            })
+            .collect::<Result<_, RpcError>>()?;
+        Ok(EIP1186ProofResponse {
+            address,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce,
+            storage_hash: account.storage_root,
+            account_proof: account_proof.into_iter().map(Bytes::from).collect(),
+            storage_proof,
+        })
+    }
+
+    /// Result of the call of `method` with `params`.  The state is always the
+    /// state before the block, whatever the requested block.
+    fn handle(&self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        if let Some(message) = self.errors.get(method) {
+            return Err(RpcError::new(-32000, message.as_str()));
+        }
+        let result = match method {
+            "eth_chainId" => json!(U64::from(self.chain_id.as_u64())),
+            "eth_coinbase" => json!(self.head().author.unwrap_or_default()),
+            "eth_getBlockByNumber" => json!(self.block_by_number(param(params, 0)?)?),
+            "eth_getBlockByHash" => json!(self.block_by_hash(param(params, 0)?)),
+            "debug_traceBlockByNumber" => self.traces(self.block_by_number(param(params, 0)?)?)?,
+            "debug_traceBlockByHash" => self.traces(self.block_by_hash(param(params, 0)?))?,
+            "eth_getCode" => json!(self.code(&param(params, 0)?)),
+            "eth_getProof" => json!(self.proof(param(params, 0)?, param(params, 1)?)?),
+            "miner_start" | "miner_stop" => Value::Null,
+            _ => {
+                return Err(RpcError::new(
+                    -32601,
+                    format!("the method {} does not exist/is not available", method),
+                ))
+            }
+        };
+        Ok(result)
+    }
+
+    /// Response to the JSON-RPC request `body`
+    fn response(&self, body: &[u8]) -> Value {
+        #[derive(Deserialize)]
+        struct Request {
+            id: Value,
+            method: String,
+            #[serde(default)]
+            params: Value,
+        }
+
+        let (id, result) = match serde_json::from_slice::<Request>(body) {
+            Ok(request) => {
+                let params = match request.params {
+                    Value::Array(params) => params,
+                    Value::Null => Vec::new(),
+                    params => vec![params],
+                };
+                (request.id, self.handle(&request.method, &params))
+            }
+            Err(err) => (Value::Null, Err(RpcError::new(-32700, err.to_string()))),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        }
+    }
+
+    /// Serve one HTTP request on `stream`, closing the connection after the
+    /// response.
+    fn serve(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidData, "invalid content length")
+                    })?;
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let response = serde_json::to_vec(&self.response(&body))?;
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.len()
+        )?;
+        stream.write_all(&response)?;
+        stream.flush()
+    }
+}
+
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    params
+        .get(index)
+        .cloned()
+        .and_then(|param| serde_json::from_value(param).ok())
+        .ok_or_else(|| RpcError::new(-32602, format!("invalid parameter {}", index)))
+}
+
+/// HTTP JSON-RPC server of a [`MockGeth`], running in a thread on a local
+/// port until it's dropped.
+#[derive(Debug)]
+pub struct MockGethServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockGethServer {
+    /// Start serving `geth` on a free local port.
+    pub fn start(geth: MockGeth) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let shutdown = shutdown.clone();
+            move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Err(err) = stream.and_then(|stream| geth.serve(stream)) {
+                        log::warn!("mock geth server: {}", err);
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            addr,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    /// URL of the server
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for MockGethServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the server waiting for a connection
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use crate::{
+        circuit_input_builder::{BuilderClient, CircuitsParams},
+        rpc::GethClient,
+        Error,
+    };
+    use eth_types::{bytecode, evm_types::OpcodeId};
+    use ethers_providers::{Http, JsonRpcClient};
+    use mock::test_ctx::{helpers::*, TestContext};
+    use url::Url;
+
+    const BLOCK_NUM: u64 = 0xcafe;
+
+    /// Block with a transaction that writes the storage and whose trace has
+    /// many steps with memory, to get a large `debug_traceBlockByNumber`
+    /// response.
+    fn geth_data() -> GethData {
+        let mut code = bytecode! {
+            PUSH1(0x2a)
+            PUSH2(0x0400)
+            MSTORE
+            PUSH1(0x2a)
+            PUSH1(0x00)
+            SSTORE
+        };
+        for _ in 0..128 {
+            code.push(32, Word::MAX);
+            code.write_op(OpcodeId::POP);
+        }
+        code.write_op(OpcodeId::STOP);
+        TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block.number(BLOCK_NUM),
+        )
+        .unwrap()
+        .into()
+    }
+
+    async fn builder_client(server: &MockGethServer) -> BuilderClient<Http> {
+        let transport = Http::new(Url::parse(&server.url()).unwrap());
+        BuilderClient::new(GethClient::new(transport), CircuitsParams::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn gen_inputs_from_mock_geth() {
+        let geth_data = geth_data();
+        let server = MockGethServer::start(MockGeth::new(geth_data.clone())).unwrap();
+        let cli = builder_client(&server).await;
+
+        let (_, _, history_hashes, _, prev_state_root) = cli.get_block(BLOCK_NUM).await.unwrap();
+        assert_eq!(history_hashes.len(), 256);
+        let (builder, eth_block) = cli.gen_inputs(BLOCK_NUM).await.unwrap();
+        assert_eq!(eth_block.parent_hash, H256::from_uint(&history_hashes[255]));
+
+        let block_data = BlockData::new_from_geth_data(geth_data.clone());
+        assert_eq!(
+            H256::from_uint(&prev_state_root),
+            block_data.sdb.state_trie().root()
+        );
+        let mut expected = block_data.new_circuit_input_builder();
+        expected
+            .handle_block(&geth_data.eth_block, &geth_data.geth_traces)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&builder.block.txs).unwrap(),
+            serde_json::to_value(&expected.block.txs).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&builder.block.container).unwrap(),
+            serde_json::to_value(&expected.block.container).unwrap()
+        );
+        assert_eq!(
+            builder.sdb.state_root().unwrap(),
+            expected.sdb.state_root().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn gen_inputs_state_root_mismatch() {
+        let mut mock_geth = MockGeth::new(geth_data());
+        let state_root = mock_geth.head().state_root;
+        mock_geth.blocks.last_mut().unwrap().state_root = H256::zero();
+        let server = MockGethServer::start(mock_geth).unwrap();
+        let cli = builder_client(&server).await;
+
+        match cli.gen_inputs(BLOCK_NUM).await {
+            Err(Error::StateRootMismatch(block_num, block_root, computed_root)) => {
+                assert_eq!(block_num, BLOCK_NUM);
+                assert_eq!(block_root, H256::zero());
+                assert_eq!(computed_root, state_root);
+            }
+            res => panic!("unexpected result {:?}", res.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn mock_geth_errors() {
+        let server = MockGethServer::start(
+            MockGeth::new(geth_data()).with_error("debug_traceBlockByNumber", "tracing failed"),
+        )
+        .unwrap();
+        let cli = builder_client(&server).await;
+
+        assert!(matches!(
+            cli.gen_inputs(BLOCK_NUM).await,
+            Err(Error::JSONRpcError(_))
+        ));
+        // Unknown block
+        assert!(matches!(
+            cli.get_block(BLOCK_NUM + 1).await,
+            Err(Error::JSONRpcError(_))
+        ));
+        // Unknown method
+        let transport = Http::new(Url::parse(&server.url()).unwrap());
+        assert!(transport.request::<_, Value>("eth_foo", ()).await.is_err());
+    }
+}
@@ -0,0 +1,1032 @@
+//! Merkle Patricia Tries of the Ethereum state, used to generate the witness
+//! of the state trie updates proved by the MPT circuit.
+//!
+//! The tries are usually partial: they are built from the nodes of the proofs
+//! returned by `eth_getProof` for the keys accessed in a block, so the
+//! subtries that are not in any proof are only known by their hash.  Reading
+//! or updating a key whose path goes through one of these subtries fails with
+//! [`TrieError::NodeNotFound`].
+
+use crate::{error::TrieError, operation::AccountField, state_db::CODE_HASH_ZERO};
+use eth_types::{Address, BigEndianHash, EIP1186ProofResponse, Hash, ToBigEndian, Word, H256};
+use ethers_core::utils::{
+    keccak256,
+    rlp::{self, Rlp, RlpStream},
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashMap};
+
+lazy_static! {
+    /// Root of the empty trie, which is the hash of the RLP encoding of the
+    /// empty string.
+    pub static ref EMPTY_ROOT: Hash = H256(keccak256([0x80]));
+}
+
+/// Node of a Merkle Patricia Trie, with its paths stored as nibbles.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum Node {
+    #[default]
+    Empty,
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Vec<u8>,
+    },
+    /// Node that is only known by its hash
+    Hash(Hash),
+}
+
+impl Node {
+    fn encode(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        match self {
+            Node::Empty => {
+                stream.append_empty_data();
+            }
+            Node::Leaf { path, value } => {
+                stream
+                    .begin_list(2)
+                    .append(&encode_path(path, true))
+                    .append(value);
+            }
+            Node::Extension { path, child } => {
+                stream.begin_list(2).append(&encode_path(path, false));
+                child.append_reference(&mut stream);
+            }
+            Node::Branch { children, value } => {
+                stream.begin_list(17);
+                for child in children.iter() {
+                    child.append_reference(&mut stream);
+                }
+                stream.append(value);
+            }
+            Node::Hash(_) => unreachable!("nodes known by their hash can't be encoded"),
+        }
+        stream.out().to_vec()
+    }
+
+    fn hash(&self) -> Hash {
+        match self {
+            Node::Hash(hash) => *hash,
+            node => H256(keccak256(node.encode())),
+        }
+    }
+
+    /// Append the reference to the node from its parent, which is the node
+    /// itself when its encoding is shorter than a hash.
+    fn append_reference(&self, stream: &mut RlpStream) {
+        match self {
+            Node::Empty => {
+                stream.append_empty_data();
+            }
+            Node::Hash(hash) => {
+                stream.append(hash);
+            }
+            node => {
+                let encoded = node.encode();
+                if encoded.len() < 32 {
+                    stream.append_raw(&encoded, 1);
+                } else {
+                    stream.append(&H256(keccak256(encoded)));
+                }
+            }
+        }
+    }
+
+    fn decode(rlp: &Rlp) -> Result<Self, rlp::DecoderError> {
+        if rlp.is_data() {
+            let data = rlp.data()?;
+            return match data.len() {
+                0 => Ok(Node::Empty),
+                32 => Ok(Node::Hash(H256::from_slice(data))),
+                _ => Err(rlp::DecoderError::Custom("invalid node reference")),
+            };
+        }
+        match rlp.item_count()? {
+            2 => {
+                let (path, is_leaf) = decode_path(rlp.at(0)?.data()?)?;
+                Ok(if is_leaf {
+                    Node::Leaf {
+                        path,
+                        value: rlp.at(1)?.data()?.to_vec(),
+                    }
+                } else {
+                    Node::Extension {
+                        path,
+                        child: Box::new(Node::decode(&rlp.at(1)?)?),
+                    }
+                })
+            }
+            17 => {
+                let mut children = Box::<[Node; 16]>::default();
+                for (i, child) in children.iter_mut().enumerate() {
+                    *child = Node::decode(&rlp.at(i)?)?;
+                }
+                Ok(Node::Branch {
+                    children,
+                    value: rlp.at(16)?.data()?.to_vec(),
+                })
+            }
+            _ => Err(rlp::DecoderError::RlpIncorrectListLen),
+        }
+    }
+}
+
+/// Hex-prefix encoding of a path of nibbles
+fn encode_path(path: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut nibbles = if path.len() % 2 == 1 {
+        vec![flag + 1]
+    } else {
+        vec![flag, 0]
+    };
+    nibbles.extend_from_slice(path);
+    nibbles
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect()
+}
+
+/// Decode a hex-prefix encoded path, returning its nibbles and whether it's
+/// the path of a leaf.
+fn decode_path(bytes: &[u8]) -> Result<(Vec<u8>, bool), rlp::DecoderError> {
+    let nibbles = to_nibbles(bytes);
+    let flag = match nibbles.first() {
+        Some(flag) if *flag < 4 => *flag,
+        _ => return Err(rlp::DecoderError::Custom("invalid node path")),
+    };
+    let skip = if flag % 2 == 1 { 1 } else { 2 };
+    Ok((nibbles[skip.min(nibbles.len())..].to_vec(), flag >= 2))
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xf])
+        .collect()
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Returns `node` behind an extension with `path`, if `path` is not empty.
+fn with_prefix(path: &[u8], node: Node) -> Node {
+    if path.is_empty() {
+        node
+    } else {
+        Node::Extension {
+            path: path.to_vec(),
+            child: Box::new(node),
+        }
+    }
+}
+
+/// Returns `node` with `path` prepended to its own path, merging it into a
+/// single node if it has one.
+fn join_path(path: &[u8], node: Node) -> Node {
+    match node {
+        Node::Empty => Node::Empty,
+        Node::Leaf {
+            path: node_path,
+            value,
+        } => Node::Leaf {
+            path: [path, &node_path].concat(),
+            value,
+        },
+        Node::Extension {
+            path: node_path,
+            child,
+        } => Node::Extension {
+            path: [path, &node_path].concat(),
+            child,
+        },
+        node => with_prefix(path, node),
+    }
+}
+
+/// Merkle Patricia Trie whose nodes may be only known by their hash.  Keys
+/// are used as given, so secure tries hash them before accessing the trie.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Trie {
+    root: Node,
+    /// RLP encoded nodes by hash, which are decoded when their path is
+    /// accessed.
+    nodes: HashMap<Hash, Vec<u8>>,
+}
+
+impl Trie {
+    /// Create a partial trie with `root`, whose known nodes are `nodes`.
+    pub fn from_nodes(root: Hash, nodes: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        Self {
+            root: if root == *EMPTY_ROOT {
+                Node::Empty
+            } else {
+                Node::Hash(root)
+            },
+            nodes: nodes
+                .into_iter()
+                .map(|node| (H256(keccak256(&node)), node))
+                .collect(),
+        }
+    }
+
+    /// Root hash of the trie
+    pub fn root(&self) -> Hash {
+        self.root.hash()
+    }
+
+    /// Get the value at `key`, if there is one.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, TrieError> {
+        self.get_node(&self.root, &to_nibbles(key))
+    }
+
+    /// Set the value at `key`.  An empty value removes the key from the trie.
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Result<(), TrieError> {
+        let path = to_nibbles(key);
+        let root = self.root.clone();
+        self.root = if value.is_empty() {
+            self.remove_node(root, &path)?
+        } else {
+            self.insert_node(root, &path, value)?
+        };
+        Ok(())
+    }
+
+    /// RLP encoded nodes in the path from the root to `key`, which prove the
+    /// value at `key` or that there is no value.  Nodes embedded in their
+    /// parent are not included, and the proof of an empty trie is the empty
+    /// node.
+    pub fn proof(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, TrieError> {
+        let mut proof = Vec::new();
+        self.prove_node(&self.root, &to_nibbles(key), true, &mut proof)?;
+        Ok(proof)
+    }
+
+    fn resolve(&self, hash: Hash) -> Result<Node, TrieError> {
+        let node = self.nodes.get(&hash).ok_or(TrieError::NodeNotFound(hash))?;
+        Ok(Node::decode(&Rlp::new(node))?)
+    }
+
+    fn get_node(&self, node: &Node, path: &[u8]) -> Result<Option<Vec<u8>>, TrieError> {
+        match node {
+            Node::Empty => Ok(None),
+            Node::Leaf {
+                path: leaf_path,
+                value,
+            } => Ok((leaf_path == path).then(|| value.clone())),
+            Node::Extension {
+                path: extension_path,
+                child,
+            } => match path.strip_prefix(extension_path.as_slice()) {
+                Some(path) => self.get_node(child, path),
+                None => Ok(None),
+            },
+            Node::Branch { children, value } => match path.split_first() {
+                Some((nibble, path)) => self.get_node(&children[*nibble as usize], path),
+                None => Ok((!value.is_empty()).then(|| value.clone())),
+            },
+            Node::Hash(hash) => self.get_node(&self.resolve(*hash)?, path),
+        }
+    }
+
+    fn prove_node(
+        &self,
+        node: &Node,
+        path: &[u8],
+        is_root: bool,
+        proof: &mut Vec<Vec<u8>>,
+    ) -> Result<(), TrieError> {
+        let node = match node {
+            Node::Hash(hash) => Cow::Owned(self.resolve(*hash)?),
+            node => Cow::Borrowed(node),
+        };
+        let encoded = node.encode();
+        if is_root || encoded.len() >= 32 {
+            proof.push(encoded);
+        }
+        match node.as_ref() {
+            Node::Extension {
+                path: extension_path,
+                child,
+            } => {
+                if let Some(path) = path.strip_prefix(extension_path.as_slice()) {
+                    self.prove_node(child, path, false, proof)?;
+                }
+            }
+            Node::Branch { children, .. } => {
+                if let Some((nibble, path)) = path.split_first() {
+                    self.prove_node(&children[*nibble as usize], path, false, proof)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn insert_node(&self, node: Node, path: &[u8], value: Vec<u8>) -> Result<Node, TrieError> {
+        Ok(match node {
+            Node::Empty => Node::Leaf {
+                path: path.to_vec(),
+                value,
+            },
+            Node::Leaf {
+                path: leaf_path,
+                value: leaf_value,
+            } => {
+                if leaf_path == path {
+                    Node::Leaf {
+                        path: leaf_path,
+                        value,
+                    }
+                } else {
+                    // Split the leaf into a branch with both values
+                    let common = common_prefix_len(&leaf_path, path);
+                    let branch = Node::Branch {
+                        children: Default::default(),
+                        value: Vec::new(),
+                    };
+                    let branch = self.insert_node(branch, &leaf_path[common..], leaf_value)?;
+                    let branch = self.insert_node(branch, &path[common..], value)?;
+                    with_prefix(&path[..common], branch)
+                }
+            }
+            Node::Extension {
+                path: extension_path,
+                child,
+            } => {
+                let common = common_prefix_len(&extension_path, path);
+                if common == extension_path.len() {
+                    Node::Extension {
+                        child: Box::new(self.insert_node(*child, &path[common..], value)?),
+                        path: extension_path,
+                    }
+                } else {
+                    // Split the extension at the first nibble that differs
+                    let mut children = Box::<[Node; 16]>::default();
+                    children[extension_path[common] as usize] =
+                        with_prefix(&extension_path[common + 1..], *child);
+                    let branch = Node::Branch {
+                        children,
+                        value: Vec::new(),
+                    };
+                    let branch = self.insert_node(branch, &path[common..], value)?;
+                    with_prefix(&path[..common], branch)
+                }
+            }
+            Node::Branch {
+                mut children,
+                value: branch_value,
+            } => match path.split_first() {
+                Some((nibble, path)) => {
+                    let child = std::mem::take(&mut children[*nibble as usize]);
+                    children[*nibble as usize] = self.insert_node(child, path, value)?;
+                    Node::Branch {
+                        children,
+                        value: branch_value,
+                    }
+                }
+                None => Node::Branch { children, value },
+            },
+            Node::Hash(hash) => self.insert_node(self.resolve(hash)?, path, value)?,
+        })
+    }
+
+    fn remove_node(&self, node: Node, path: &[u8]) -> Result<Node, TrieError> {
+        Ok(match node {
+            Node::Empty => Node::Empty,
+            Node::Leaf {
+                path: leaf_path,
+                value,
+            } => {
+                if leaf_path == path {
+                    Node::Empty
+                } else {
+                    Node::Leaf {
+                        path: leaf_path,
+                        value,
+                    }
+                }
+            }
+            Node::Extension {
+                path: extension_path,
+                child,
+            } => match path.strip_prefix(extension_path.as_slice()) {
+                Some(path) => join_path(&extension_path, self.remove_node(*child, path)?),
+                None => Node::Extension {
+                    path: extension_path,
+                    child,
+                },
+            },
+            Node::Branch {
+                mut children,
+                mut value,
+            } => {
+                match path.split_first() {
+                    Some((nibble, path)) => {
+                        let child = std::mem::take(&mut children[*nibble as usize]);
+                        children[*nibble as usize] = self.remove_node(child, path)?;
+                    }
+                    None => value.clear(),
+                }
+                // A branch with a single child or value is replaced by it
+                let non_empty: Vec<_> = children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| !matches!(child, Node::Empty))
+                    .map(|(nibble, _)| nibble)
+                    .collect();
+                match (non_empty.as_slice(), value.is_empty()) {
+                    ([], true) => Node::Empty,
+                    ([], false) => Node::Leaf {
+                        path: Vec::new(),
+                        value,
+                    },
+                    ([nibble], true) => {
+                        let child = match std::mem::take(&mut children[*nibble]) {
+                            Node::Hash(hash) => self.resolve(hash)?,
+                            child => child,
+                        };
+                        join_path(&[*nibble as u8], child)
+                    }
+                    _ => Node::Branch { children, value },
+                }
+            }
+            Node::Hash(hash) => self.remove_node(self.resolve(hash)?, path)?,
+        })
+    }
+}
+
+/// Account in the state trie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrieAccount {
+    /// Nonce
+    pub nonce: Word,
+    /// Balance
+    pub balance: Word,
+    /// Root of the storage trie
+    pub storage_root: Hash,
+    /// Code hash
+    pub code_hash: Hash,
+}
+
+impl Default for TrieAccount {
+    fn default() -> Self {
+        Self {
+            nonce: Word::zero(),
+            balance: Word::zero(),
+            storage_root: *EMPTY_ROOT,
+            code_hash: *CODE_HASH_ZERO,
+        }
+    }
+}
+
+impl TrieAccount {
+    /// Return if the account is empty, in which case it's not in the trie.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append(&self.nonce)
+            .append(&self.balance)
+            .append(&self.storage_root)
+            .append(&self.code_hash);
+        stream.out().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, rlp::DecoderError> {
+        let rlp = Rlp::new(bytes);
+        Ok(Self {
+            nonce: rlp.val_at(0)?,
+            balance: rlp.val_at(1)?,
+            storage_root: rlp.val_at(2)?,
+            code_hash: rlp.val_at(3)?,
+        })
+    }
+}
+
+/// Verify the `proof` of `key` in the trie with `root`, returning the value at
+/// `key` if there is one.  The proof is invalid if it misses a node of the
+/// path to `key`, in which case [`TrieError::NodeNotFound`] is returned.
+pub fn verify_proof(
+    root: Hash,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, TrieError> {
+    Trie::from_nodes(root, proof.to_vec()).get(key)
+}
+
+/// Roots and proofs of the state trie before and after the update of a key.
+/// The proofs of storage keys contain the path to the account in the state
+/// trie followed by the path to the key in the storage trie of the account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrieUpdate {
+    /// Root before the update
+    pub old_root: Hash,
+    /// Root after the update
+    pub new_root: Hash,
+    /// Proof of the key before the update
+    pub old_nodes: Vec<Vec<u8>>,
+    /// Proof of the key after the update
+    pub new_nodes: Vec<Vec<u8>>,
+}
+
+/// The Ethereum state trie, with the storage tries of its accounts.  Both
+/// are secure tries, whose keys are the hashes of the addresses and storage
+/// keys.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateTrie {
+    accounts: Trie,
+    storage: HashMap<Address, Trie>,
+}
+
+impl StateTrie {
+    /// Create a partial state trie with `root` from the `eth_getProof`
+    /// responses of some accounts, checking that the proofs match the values
+    /// in the responses.
+    pub fn from_proofs(root: Hash, proofs: &[EIP1186ProofResponse]) -> Result<Self, TrieError> {
+        let mut trie = Self {
+            accounts: Trie::from_nodes(
+                root,
+                proofs
+                    .iter()
+                    .flat_map(|proof| proof.account_proof.iter().map(|node| node.to_vec())),
+            ),
+            storage: HashMap::new(),
+        };
+        for proof in proofs {
+            // The values of a non-existing account are zero, but its code hash
+            // and storage hash depend on the node that returns them.
+            let account = trie.account(&proof.address)?;
+            let storage_root = match account {
+                Some(account) => {
+                    if (account.nonce, account.balance, account.code_hash)
+                        != (proof.nonce, proof.balance, proof.code_hash)
+                        || account.storage_root != proof.storage_hash
+                    {
+                        return Err(TrieError::InvalidProof(proof.address));
+                    }
+                    account.storage_root
+                }
+                None => {
+                    if !proof.nonce.is_zero() || !proof.balance.is_zero() {
+                        return Err(TrieError::InvalidProof(proof.address));
+                    }
+                    *EMPTY_ROOT
+                }
+            };
+            let storage = Trie::from_nodes(
+                storage_root,
+                proof
+                    .storage_proof
+                    .iter()
+                    .flat_map(|storage_proof| storage_proof.proof.iter().map(|node| node.to_vec())),
+            );
+            for storage_proof in &proof.storage_proof {
+                if storage_value(&storage, &storage_proof.key)? != storage_proof.value {
+                    return Err(TrieError::InvalidProof(proof.address));
+                }
+            }
+            trie.storage.insert(proof.address, storage);
+        }
+        Ok(trie)
+    }
+
+    /// Root hash of the state trie
+    pub fn root(&self) -> Hash {
+        self.accounts.root()
+    }
+
+    /// Get the account at `address`, if it exists.
+    pub fn account(&self, address: &Address) -> Result<Option<TrieAccount>, TrieError> {
+        self.accounts
+            .get(&keccak256(address))?
+            .map(|value| TrieAccount::decode(&value))
+            .transpose()
+            .map_err(TrieError::from)
+    }
+
+    /// Get the storage value of the account at `address` at `key`.
+    pub fn storage(&self, address: &Address, key: &Word) -> Result<Word, TrieError> {
+        match self.storage_trie(address)? {
+            Some(storage) => storage_value(&storage, key),
+            None => Ok(Word::zero()),
+        }
+    }
+
+    /// Proof of the account at `address`
+    pub fn account_proof(&self, address: &Address) -> Result<Vec<Vec<u8>>, TrieError> {
+        self.accounts.proof(&keccak256(address))
+    }
+
+    /// Proof of the storage of the account at `address` at `key`, which
+    /// starts with the proof of the account.  The proof of a non-existing
+    /// account doesn't contain storage trie nodes.
+    pub fn storage_proof(&self, address: &Address, key: &Word) -> Result<Vec<Vec<u8>>, TrieError> {
+        let mut proof = self.account_proof(address)?;
+        if let Some(storage) = self.storage_trie(address)? {
+            proof.extend(storage.proof(&keccak256(key.to_be_bytes()))?);
+        }
+        Ok(proof)
+    }
+
+    /// Set the `field` of the account at `address`, creating the account if
+    /// it doesn't exist.  A zero code hash is the code hash of a non-existing
+    /// account, so it's set as the hash of the empty code.
+    pub fn set_account_field(
+        &mut self,
+        address: &Address,
+        field: AccountField,
+        value: Word,
+    ) -> Result<TrieUpdate, TrieError> {
+        let old_root = self.root();
+        let old_nodes = self.account_proof(address)?;
+
+        let mut account = self.account(address)?.unwrap_or_default();
+        match field {
+            AccountField::Nonce => account.nonce = value,
+            AccountField::Balance => account.balance = value,
+            AccountField::CodeHash => {
+                account.code_hash = if value.is_zero() {
+                    *CODE_HASH_ZERO
+                } else {
+                    H256::from_uint(&value)
+                }
+            }
+        }
+        self.set_account(address, &account)?;
+
+        Ok(TrieUpdate {
+            old_root,
+            new_root: self.root(),
+            old_nodes,
+            new_nodes: self.account_proof(address)?,
+        })
+    }
+
+    /// Set the storage of the account at `address` at `key`, creating the
+    /// account if it doesn't exist.
+    pub fn set_storage(
+        &mut self,
+        address: &Address,
+        key: &Word,
+        value: Word,
+    ) -> Result<TrieUpdate, TrieError> {
+        let old_root = self.root();
+        let old_nodes = self.storage_proof(address, key)?;
+
+        self.update_storage(address, key, value)?;
+
+        Ok(TrieUpdate {
+            old_root,
+            new_root: self.root(),
+            old_nodes,
+            new_nodes: self.storage_proof(address, key)?,
+        })
+    }
+
+    /// Set the account at `address`.  An empty account is removed from the
+    /// trie, but its storage trie is kept.
+    pub fn set_account(
+        &mut self,
+        address: &Address,
+        account: &TrieAccount,
+    ) -> Result<(), TrieError> {
+        let value = if account.is_empty() {
+            Vec::new()
+        } else {
+            account.encode()
+        };
+        self.accounts.insert(&keccak256(address), value)
+    }
+
+    /// Set the storage of the account at `address` at `key` like
+    /// [`Self::set_storage`], without generating the proofs of the update.
+    pub fn update_storage(
+        &mut self,
+        address: &Address,
+        key: &Word,
+        value: Word,
+    ) -> Result<(), TrieError> {
+        let mut account = self.account(address)?.unwrap_or_default();
+        let storage = self
+            .storage
+            .entry(*address)
+            .or_insert_with(|| Trie::from_nodes(account.storage_root, []));
+        // Zero values are removed from the trie
+        let value = if value.is_zero() {
+            Vec::new()
+        } else {
+            rlp::encode(&value).to_vec()
+        };
+        storage.insert(&keccak256(key.to_be_bytes()), value)?;
+        account.storage_root = storage.root();
+        self.set_account(address, &account)
+    }
+
+    /// Remove the account at `address` with its storage.
+    pub fn remove_account(&mut self, address: &Address) -> Result<(), TrieError> {
+        self.storage.remove(address);
+        self.accounts.insert(&keccak256(address), Vec::new())
+    }
+
+    /// Verify the `proof` of the account at `address` in the state trie with
+    /// `root`, returning the account if it exists.
+    pub fn verify_account_proof(
+        root: Hash,
+        address: &Address,
+        proof: &[Vec<u8>],
+    ) -> Result<Option<TrieAccount>, TrieError> {
+        verify_proof(root, &keccak256(address), proof)?
+            .map(|value| TrieAccount::decode(&value))
+            .transpose()
+            .map_err(TrieError::from)
+    }
+
+    /// Verify the `proof` of the storage of the account at `address` at `key`
+    /// in the state trie with `root`, as returned by
+    /// [`Self::storage_proof`], returning the storage value.
+    pub fn verify_storage_proof(
+        root: Hash,
+        address: &Address,
+        key: &Word,
+        proof: &[Vec<u8>],
+    ) -> Result<Word, TrieError> {
+        match Self::verify_account_proof(root, address, proof)? {
+            Some(account) => {
+                storage_value(&Trie::from_nodes(account.storage_root, proof.to_vec()), key)
+            }
+            None => Ok(Word::zero()),
+        }
+    }
+
+    /// Storage trie of the account at `address`, if it exists.
+    fn storage_trie(&self, address: &Address) -> Result<Option<Cow<'_, Trie>>, TrieError> {
+        Ok(self.account(address)?.map(|account| {
+            match self.storage.get(address) {
+                Some(storage) => Cow::Borrowed(storage),
+                // The storage of an account without proofs is unknown
+                None => Cow::Owned(Trie::from_nodes(account.storage_root, [])),
+            }
+        }))
+    }
+}
+
+fn storage_value(storage: &Trie, key: &Word) -> Result<Word, TrieError> {
+    Ok(storage
+        .get(&keccak256(key.to_be_bytes()))?
+        .map(|value| rlp::decode(&value))
+        .transpose()?
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::{address, word, StorageProof};
+    use pretty_assertions::assert_eq;
+
+    fn trie(entries: &[(&str, &str)]) -> Trie {
+        let mut trie = Trie::default();
+        for (key, value) in entries {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec())
+                .unwrap();
+        }
+        trie
+    }
+
+    #[test]
+    fn trie_root() {
+        assert_eq!(Trie::default().root(), *EMPTY_ROOT);
+        assert_eq!(
+            trie(&[
+                ("do", "verb"),
+                ("horse", "stallion"),
+                ("doge", "coin"),
+                ("dog", "puppy"),
+            ])
+            .root(),
+            H256::from_uint(&word!(
+                "0x5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+            ))
+        );
+        assert_eq!(
+            trie(&[
+                ("doe", "reindeer"),
+                ("dog", "puppy"),
+                ("dogglesworth", "cat")
+            ])
+            .root(),
+            H256::from_uint(&word!(
+                "0x8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+            ))
+        );
+    }
+
+    #[test]
+    fn trie_remove() {
+        let entries = [
+            ("do", "verb"),
+            ("horse", "stallion"),
+            ("doge", "coin"),
+            ("dog", "puppy"),
+            ("dogglesworth", "cat"),
+        ];
+        for removed in 0..entries.len() {
+            let mut trie = trie(&entries);
+            trie.insert(entries[removed].0.as_bytes(), Vec::new())
+                .unwrap();
+
+            let mut remaining = entries.to_vec();
+            remaining.remove(removed);
+            assert_eq!(trie.root(), self::trie(&remaining).root());
+            assert_eq!(trie.get(entries[removed].0.as_bytes()), Ok(None));
+        }
+    }
+
+    #[test]
+    fn partial_trie() {
+        let entries: Vec<_> = (0..64u64)
+            .map(|i| (keccak256(i.to_be_bytes()), i))
+            .collect();
+        let mut trie = Trie::default();
+        for (key, i) in &entries {
+            trie.insert(key, rlp::encode(i).to_vec()).unwrap();
+        }
+
+        // Build a partial trie from the proofs of some keys
+        let (proved, unproved) = entries.split_at(8);
+        let mut partial = Trie::from_nodes(
+            trie.root(),
+            proved.iter().flat_map(|(key, _)| trie.proof(key).unwrap()),
+        );
+        assert_eq!(partial.root(), trie.root());
+        for (key, i) in proved {
+            assert_eq!(partial.get(key), Ok(Some(rlp::encode(i).to_vec())));
+            assert_eq!(partial.proof(key), trie.proof(key));
+
+            let mut proof = trie.proof(key).unwrap();
+            assert_eq!(
+                verify_proof(trie.root(), key, &proof),
+                Ok(Some(rlp::encode(i).to_vec()))
+            );
+            proof.pop();
+            assert!(matches!(
+                verify_proof(trie.root(), key, &proof),
+                Err(TrieError::NodeNotFound(_))
+            ));
+        }
+        assert!(matches!(
+            partial.get(&unproved[0].0),
+            Err(TrieError::NodeNotFound(_))
+        ));
+
+        // Updating the proved keys gives the same root as in the full trie
+        for (key, i) in proved {
+            for trie in [&mut trie, &mut partial] {
+                if i % 2 == 0 {
+                    trie.insert(key, Vec::new()).unwrap();
+                } else {
+                    trie.insert(key, vec![0xff; 40]).unwrap();
+                }
+            }
+            assert_eq!(partial.root(), trie.root());
+        }
+    }
+
+    #[test]
+    fn state_trie_updates() {
+        let address = address!("0x00000000000000000000000000000000000000aa");
+        let key = Word::from(0x1234);
+        let mut trie = StateTrie::default();
+
+        let update = trie
+            .set_account_field(&address, AccountField::Balance, Word::from(100))
+            .unwrap();
+        assert_eq!(update.old_root, *EMPTY_ROOT);
+        assert_eq!(update.old_nodes, vec![vec![0x80]]);
+        assert_eq!(update.new_root, trie.root());
+        assert_eq!(update.new_nodes, trie.account_proof(&address).unwrap());
+
+        let update = trie.set_storage(&address, &key, Word::from(5)).unwrap();
+        assert_eq!(update.old_nodes.len(), 2);
+        assert_eq!(update.old_nodes[1], vec![0x80]);
+        assert_eq!(update.new_nodes.len(), 2);
+        assert_eq!(trie.storage(&address, &key), Ok(Word::from(5)));
+        let account = trie.account(&address).unwrap().unwrap();
+        assert_eq!(account.balance, Word::from(100));
+        assert_eq!(account.storage_root, H256(keccak256(&update.new_nodes[1])));
+        assert_eq!(
+            StateTrie::verify_storage_proof(update.new_root, &address, &key, &update.new_nodes),
+            Ok(Word::from(5))
+        );
+        assert_eq!(
+            StateTrie::verify_account_proof(update.old_root, &address, &update.old_nodes),
+            Ok(Some(TrieAccount {
+                balance: Word::from(100),
+                ..TrieAccount::default()
+            }))
+        );
+
+        // Clearing the account leaves an empty state trie
+        trie.set_storage(&address, &key, Word::zero()).unwrap();
+        trie.set_account_field(&address, AccountField::Balance, Word::zero())
+            .unwrap();
+        assert_eq!(trie.account(&address), Ok(None));
+        assert_eq!(trie.root(), *EMPTY_ROOT);
+    }
+
+    #[test]
+    fn state_trie_from_proofs() {
+        let addresses = [
+            address!("0x00000000000000000000000000000000000000aa"),
+            address!("0x00000000000000000000000000000000000000bb"),
+            address!("0x00000000000000000000000000000000000000cc"),
+        ];
+        let mut trie = StateTrie::default();
+        for (i, address) in addresses.iter().enumerate() {
+            trie.set_account_field(address, AccountField::Nonce, Word::from(i + 1))
+                .unwrap();
+            for key in 0..4u64 {
+                trie.set_storage(address, &Word::from(key), Word::from(key + 1))
+                    .unwrap();
+            }
+        }
+
+        let proof_response = |trie: &StateTrie, address: &Address, keys: &[u64]| {
+            let account = trie.account(address).unwrap().unwrap_or_default();
+            EIP1186ProofResponse {
+                address: *address,
+                balance: account.balance,
+                code_hash: account.code_hash,
+                nonce: account.nonce,
+                storage_hash: account.storage_root,
+                account_proof: trie
+                    .account_proof(address)
+                    .unwrap()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                storage_proof: keys
+                    .iter()
+                    .map(|key| StorageProof {
+                        key: Word::from(*key),
+                        value: trie.storage(address, &Word::from(*key)).unwrap(),
+                        proof: trie
+                            .storage_trie(address)
+                            .unwrap()
+                            .unwrap()
+                            .proof(&keccak256(Word::from(*key).to_be_bytes()))
+                            .unwrap()
+                            .into_iter()
+                            .map(Into::into)
+                            .collect(),
+                    })
+                    .collect(),
+            }
+        };
+        let proofs = vec![
+            proof_response(&trie, &addresses[0], &[0, 1]),
+            proof_response(&trie, &addresses[1], &[]),
+        ];
+        let mut partial = StateTrie::from_proofs(trie.root(), &proofs).unwrap();
+        assert_eq!(
+            partial.storage(&addresses[0], &Word::from(1)),
+            Ok(Word::from(2))
+        );
+
+        // Same updates in the full and the partial tries
+        for trie in [&mut trie, &mut partial] {
+            trie.set_storage(&addresses[0], &Word::from(0), Word::zero())
+                .unwrap();
+            trie.set_storage(&addresses[0], &Word::from(1), Word::from(7))
+                .unwrap();
+            trie.set_account_field(&addresses[1], AccountField::Balance, Word::from(1))
+                .unwrap();
+        }
+        assert_eq!(partial.root(), trie.root());
+        assert!(matches!(
+            partial.storage(&addresses[2], &Word::zero()),
+            Err(TrieError::NodeNotFound(_))
+        ));
+
+        // A proof with values that don't match the trie is rejected
+        let mut invalid = proof_response(&trie, &addresses[1], &[]);
+        invalid.nonce = Word::from(100);
+        assert_eq!(
+            StateTrie::from_proofs(trie.root(), &[invalid]).unwrap_err(),
+            TrieError::InvalidProof(addresses[1])
+        );
+    }
+}
@@ -1,10 +1,11 @@
 use super::{
     AccountDestructedOp, AccountOp, CallContextOp, MemoryOp, Op, OpEnum, Operation, RWCounter,
-    StackOp, StartOp, StorageOp, Target, TxAccessListAccountOp, TxAccessListAccountStorageOp,
-    TxLogOp, TxReceiptOp, TxRefundOp, RW,
+    StackOp, StartOp, StorageOp, Target, TransientStorageOp, TxAccessListAccountOp,
+    TxAccessListAccountStorageOp, TxLogOp, TxReceiptOp, TxRefundOp, RW,
 };
 use crate::exec_trace::OperationRef;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 /// The `OperationContainer` is meant to store all of the [`Operation`]s that an
 /// [`ExecStep`](crate::circuit_input_builder::ExecStep) performs during its
@@ -20,7 +21,7 @@ use itertools::Itertools;
 /// they have specified.
 /// That serves as a way to get an input with which is easy to work with in
 /// order to construct the State proof.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OperationContainer {
     /// Operations of MemoryOp
     pub memory: Vec<Operation<MemoryOp>>,
@@ -34,6 +35,8 @@ pub struct OperationContainer {
     pub tx_access_list_account_storage: Vec<Operation<TxAccessListAccountStorageOp>>,
     /// Operations of TxRefundOp
     pub tx_refund: Vec<Operation<TxRefundOp>>,
+    /// Operations of TransientStorageOp
+    pub transient_storage: Vec<Operation<TransientStorageOp>>,
     /// Operations of AccountOp
     pub account: Vec<Operation<AccountOp>>,
     /// Operations of AccountDestructedOp
@@ -65,6 +68,7 @@ impl OperationContainer {
             tx_access_list_account: Vec::new(),
             tx_access_list_account_storage: Vec::new(),
             tx_refund: Vec::new(),
+            transient_storage: Vec::new(),
             account: Vec::new(),
             account_destructed: Vec::new(),
             call_context: Vec::new(),
@@ -143,6 +147,14 @@ impl OperationContainer {
                 });
                 OperationRef::from((Target::TxRefund, self.tx_refund.len() - 1))
             }
+            OpEnum::TransientStorage(op) => {
+                self.transient_storage.push(if reversible {
+                    Operation::new_reversible(rwc, rw, op)
+                } else {
+                    Operation::new(rwc, rw, op)
+                });
+                OperationRef::from((Target::TransientStorage, self.transient_storage.len() - 1))
+            }
             OpEnum::Account(op) => {
                 self.account.push(if reversible {
                     Operation::new_reversible(rwc, rw, op)
@@ -10,6 +10,8 @@ pub use ethers_core::types::BlockNumber;
 use ethers_providers::JsonRpcClient;
 use serde::Serialize;
 
+pub mod fixture;
+
 /// Serialize a type.
 ///
 /// # Panics
@@ -0,0 +1,231 @@
+//! JSON-RPC providers that record the responses of a node into a fixture
+//! file and replay them offline, so that the users of the
+//! [`GethClient`](super::GethClient), like the
+//! [`BuilderClient`](crate::circuit_input_builder::BuilderClient), can be
+//! tested without a running geth.
+
+use crate::Error;
+use async_trait::async_trait;
+use ethers_providers::{JsonRpcClient, ProviderError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::Mutex,
+};
+
+/// JSON-RPC call with its response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcCall {
+    /// Method of the call
+    pub method: String,
+    /// Parameters of the call
+    pub params: Value,
+    /// Result returned by the node
+    pub result: Value,
+}
+
+/// Responses of a node to a sequence of JSON-RPC calls
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Calls in the order they were first made
+    pub calls: Vec<RpcCall>,
+}
+
+impl Fixture {
+    /// Returns the result of the call of `method` with `params`.
+    pub fn get(&self, method: &str, params: &Value) -> Option<&Value> {
+        self.calls
+            .iter()
+            .find(|call| call.method == method && &call.params == params)
+            .map(|call| &call.result)
+    }
+
+    /// Add a call, unless a call of `method` with the same `params` was
+    /// already recorded.
+    pub fn insert(&mut self, method: &str, params: Value, result: Value) {
+        if self.get(method, &params).is_none() {
+            self.calls.push(RpcCall {
+                method: method.to_string(),
+                params,
+                result,
+            });
+        }
+    }
+
+    /// Read a fixture file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(Error::SerdeError)
+    }
+
+    /// Write a fixture file, creating its directory if needed
+    pub fn store(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self).map_err(Error::SerdeError)
+    }
+}
+
+/// Provider that forwards the calls to another provider, and records them
+/// along with their responses.
+#[derive(Debug)]
+pub struct RecordingProvider<P: JsonRpcClient> {
+    inner: P,
+    fixture: Mutex<Fixture>,
+}
+
+impl<P: JsonRpcClient> RecordingProvider<P> {
+    /// Create a new `RecordingProvider` forwarding the calls to `inner`.
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            fixture: Mutex::new(Fixture::default()),
+        }
+    }
+
+    /// Returns the calls recorded so far.
+    pub fn fixture(&self) -> Fixture {
+        self.fixture.lock().expect("poisoned fixture").clone()
+    }
+
+    /// Write the calls recorded so far to a fixture file.
+    pub fn store(&self, path: &Path) -> Result<(), Error> {
+        self.fixture().store(path)
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> JsonRpcClient for RecordingProvider<P> {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let params_value = serde_json::to_value(&params)?;
+        let result: Value = self
+            .inner
+            .request(method, params)
+            .await
+            .map_err(Into::<ProviderError>::into)?;
+        self.fixture
+            .lock()
+            .expect("poisoned fixture")
+            .insert(method, params_value, result.clone());
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+/// Provider that serves the responses of a [`Fixture`], and fails on calls
+/// that were not recorded.
+#[derive(Debug, Clone)]
+pub struct ReplayProvider {
+    fixture: Fixture,
+}
+
+impl ReplayProvider {
+    /// Create a new `ReplayProvider` serving the responses of `fixture`.
+    pub fn new(fixture: Fixture) -> Self {
+        Self { fixture }
+    }
+
+    /// Create a new `ReplayProvider` serving the responses of a fixture
+    /// file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(Self::new(Fixture::load(path)?))
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ReplayProvider {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let params = serde_json::to_value(&params)?;
+        let result = self.fixture.get(method, &params).ok_or_else(|| {
+            ProviderError::CustomError(format!(
+                "call {} with params {} not in the fixture",
+                method, params
+            ))
+        })?;
+        Ok(R::deserialize(result)?)
+    }
+}
+
+#[cfg(test)]
+mod fixture_tests {
+    use super::*;
+    use crate::rpc::{BlockNumber, GethClient};
+    use eth_types::{address, Address};
+    use serde_json::json;
+
+    /// Provider that answers the calls with their method and parameters,
+    /// except for `eth_getCode`
+    #[derive(Debug)]
+    struct EchoProvider;
+
+    #[async_trait]
+    impl JsonRpcClient for EchoProvider {
+        type Error = ProviderError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            if method == "eth_getCode" {
+                return Ok(serde_json::from_value(json!("0x6000"))?);
+            }
+            Ok(serde_json::from_value(
+                json!({ "method": method, "params": params }),
+            )?)
+        }
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let addr: Address = address!("0x00000000000000000000000000000000000000fe");
+        let recorder = RecordingProvider::new(EchoProvider);
+        let code = GethClient::new(&recorder)
+            .get_code(addr, BlockNumber::Number(1.into()))
+            .await
+            .unwrap();
+        let echo: Value = recorder.request("eth_foo", [1, 2]).await.unwrap();
+        // Repeated calls are only recorded once
+        let _: Value = recorder.request("eth_foo", [1, 2]).await.unwrap();
+        assert_eq!(recorder.fixture().calls.len(), 2);
+
+        let path =
+            std::env::temp_dir().join(format!("bus_mapping_fixture_{}.json", std::process::id()));
+        recorder.store(&path).unwrap();
+        let replay = ReplayProvider::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            GethClient::new(&replay)
+                .get_code(addr, BlockNumber::Number(1.into()))
+                .await
+                .unwrap(),
+            code
+        );
+        let replayed: Value = replay.request("eth_foo", [1, 2]).await.unwrap();
+        assert_eq!(replayed, echo);
+        // Calls that were not recorded fail
+        assert!(replay.request::<_, Value>("eth_foo", [1, 3]).await.is_err());
+        assert!(GethClient::new(&replay)
+            .get_code(addr, BlockNumber::Number(2.into()))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Versioned on-disk format of the circuit inputs, so that the witness of a
+//! block can be generated once, next to the node that traces it, and proved
+//! later on another machine.  It's used for the output of the
+//! [`CircuitInputBuilder`](crate::circuit_input_builder::CircuitInputBuilder)
+//! as well as for the witness block of the circuits.
+//!
+//! The data is preceded by the [`FORMAT_VERSION`] it was written with, and is
+//! encoded either in JSON, which is meant for debugging, or in a compact
+//! binary form with bincode.
+
+use crate::Error;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+/// Version of the format, which must be increased on any change to the
+/// serialized types.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Encoding of the serialized data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// JSON, for debugging
+    Json,
+    /// Compact binary form
+    Binary,
+}
+
+impl Format {
+    /// Format of a file by its extension: JSON for `.json` files and binary
+    /// otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext == "json" => Self::Json,
+            _ => Self::Binary,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+
+#[derive(Deserialize)]
+struct VersionedJson {
+    version: u32,
+    data: serde_json::Value,
+}
+
+fn check_version(version: u32) -> Result<(), Error> {
+    if version == FORMAT_VERSION {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedFormatVersion(version))
+    }
+}
+
+/// Write `data` to `writer` in `format`.
+pub fn to_writer<T: Serialize>(writer: impl Write, format: Format, data: &T) -> Result<(), Error> {
+    let versioned = Versioned {
+        version: FORMAT_VERSION,
+        data,
+    };
+    match format {
+        Format::Json => serde_json::to_writer(writer, &versioned).map_err(Error::SerdeError),
+        Format::Binary => bincode::serialize_into(writer, &versioned).map_err(Error::BincodeError),
+    }
+}
+
+/// Read data written by [`to_writer`] in `format` from `reader`.
+pub fn from_reader<T: DeserializeOwned>(mut reader: impl Read, format: Format) -> Result<T, Error> {
+    match format {
+        Format::Json => {
+            let versioned: VersionedJson =
+                serde_json::from_reader(reader).map_err(Error::SerdeError)?;
+            check_version(versioned.version)?;
+            serde_json::from_value(versioned.data).map_err(Error::SerdeError)
+        }
+        Format::Binary => {
+            // The version comes first, so that it's checked before the data is
+            // decoded.
+            let version: u32 =
+                bincode::deserialize_from(&mut reader).map_err(Error::BincodeError)?;
+            check_version(version)?;
+            bincode::deserialize_from(reader).map_err(Error::BincodeError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod serialization_tests {
+    use super::*;
+    use crate::{circuit_input_builder::Block, mock::BlockData};
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::test_ctx::{helpers::*, TestContext};
+
+    #[test]
+    fn block_roundtrip() {
+        let code = bytecode! {
+            PUSH1(0x20)
+            PUSH1(0x00)
+            PUSH1(0x00)
+            CALLDATACOPY
+            PUSH1(0x03)
+            PUSH1(0x02)
+            EXP
+            PUSH1(0x00)
+            SSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .input(vec![0xab; 0x20].into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        assert!(!builder.block.copy_events.is_empty());
+        assert!(!builder.block.exp_events.is_empty());
+
+        let expected = serde_json::to_value(&builder.block).unwrap();
+        for format in [Format::Json, Format::Binary] {
+            let mut bytes = Vec::new();
+            to_writer(&mut bytes, format, &builder.block).unwrap();
+            let decoded: Block = from_reader(bytes.as_slice(), format).unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let versioned = Versioned {
+            version: FORMAT_VERSION + 1,
+            data: &0xcafeu64,
+        };
+        let json = serde_json::to_vec(&versioned).unwrap();
+        let binary = bincode::serialize(&versioned).unwrap();
+        for (bytes, format) in [(json, Format::Json), (binary, Format::Binary)] {
+            assert!(matches!(
+                from_reader::<u64>(bytes.as_slice(), format),
+                Err(Error::UnsupportedFormatVersion(version)) if version == FORMAT_VERSION + 1
+            ));
+        }
+    }
+}
@@ -1,6 +1,10 @@
 //! Implementation of an in-memory key-value database to represent the
 //! Ethereum State Trie.
 
+use crate::{
+    error::TrieError,
+    mpt::{StateTrie, TrieAccount},
+};
 use eth_types::{Address, Hash, Word, H256, U256};
 use ethers_core::utils::keccak256;
 use lazy_static::lazy_static;
@@ -9,7 +13,7 @@ use std::collections::{HashMap, HashSet};
 lazy_static! {
     static ref ACCOUNT_ZERO: Account = Account::zero();
     static ref VALUE_ZERO: Word = Word::zero();
-    static ref CODE_HASH_ZERO: Hash = H256(keccak256(&[]));
+    pub(crate) static ref CODE_HASH_ZERO: Hash = H256(keccak256(&[]));
 }
 
 /// Memory storage for contract code by code hash.
@@ -70,11 +74,20 @@ impl Account {
 }
 
 /// In-memory key-value database that represents the Ethereum State Trie.
+/// The committed state is also kept in a (usually partial) [`StateTrie`], to
+/// compute the state root and the proofs of its accounts and storage keys.
 #[derive(Debug, Clone, Default)]
 pub struct StateDB {
     state: HashMap<Address, Account>,
-
-    // Fields with transaction lifespan, will be clear in `clear_access_list_and_refund`.
+    // State trie of the committed state, updated in `set_account` and
+    // `commit_tx` with the accounts and storage keys touched since then.
+    trie: StateTrie,
+    touched_accounts: HashSet<Address>,
+    touched_storage: HashSet<(Address, Word)>,
+    // Error of a failed update of `trie`, after which its root is unknown.
+    trie_error: Option<TrieError>,
+
+    // Fields with transaction lifespan, will be clear in `commit_tx`.
     access_list_account: HashSet<Address>,
     access_list_account_storage: HashSet<(Address, U256)>,
     // `dirty_storage` contains writes during current transaction.
@@ -86,6 +99,9 @@ pub struct StateDB {
     // Accounts that have been through `SELFDESTRUCT` under the situation that `is_persistent` is
     // `true`. These accounts will be reset once `commit_tx` is called.
     destructed_account: HashSet<Address>,
+    // `transient_storage` contains the EIP-1153 transient storage of the current transaction,
+    // which is discarded when the transaction finishes.
+    transient_storage: HashMap<(Address, Word), Word>,
     refund: u64,
 }
 
@@ -94,17 +110,33 @@ impl StateDB {
     pub fn new() -> Self {
         Self {
             state: HashMap::new(),
+            trie: StateTrie::default(),
+            touched_accounts: HashSet::new(),
+            touched_storage: HashSet::new(),
+            trie_error: None,
             access_list_account: HashSet::new(),
             access_list_account_storage: HashSet::new(),
             dirty_storage: HashMap::new(),
             destructed_account: HashSet::new(),
+            transient_storage: HashMap::new(),
             refund: 0,
         }
     }
 
     /// Set an [`Account`] at `addr` in the StateDB.
     pub fn set_account(&mut self, addr: &Address, acc: Account) {
+        self.touched_accounts.insert(*addr);
+        self.touched_storage
+            .extend(acc.storage.keys().map(|key| (*addr, *key)));
         self.state.insert(*addr, acc);
+        self.update_state_trie(HashSet::new());
+    }
+
+    /// Replace the state trie of the committed state by `trie`, which must be
+    /// the (usually partial) state trie of the accounts in the StateDB.
+    pub fn set_state_trie(&mut self, trie: StateTrie) {
+        self.trie = trie;
+        self.trie_error = None;
     }
 
     /// Get a reference to the [`Account`] at `addr`.  Returns false and a zero
@@ -120,6 +152,7 @@ impl StateDB {
     /// [`Account`] is not found in the state, a zero one will be inserted
     /// and returned along with false.
     pub fn get_account_mut(&mut self, addr: &Address) -> (bool, &mut Account) {
+        self.touched_accounts.insert(*addr);
         let found = if self.state.contains_key(addr) {
             true
         } else {
@@ -159,6 +192,7 @@ impl StateDB {
     /// be inserted at `key` in its storage, and the value will be returned
     /// along with false.
     pub fn get_storage_mut(&mut self, addr: &Address, key: &Word) -> (bool, &mut Word) {
+        self.touched_storage.insert((*addr, *key));
         let (_, acc) = self.get_account_mut(addr);
         let found = if acc.storage.contains_key(key) {
             true
@@ -225,6 +259,19 @@ impl StateDB {
         debug_assert!(exist);
     }
 
+    /// Get a reference to the transient storage value of `addr` at `key`.
+    /// Transient storage is zero unless written in the current transaction.
+    pub fn get_transient_storage(&self, addr: &Address, key: &Word) -> &Word {
+        self.transient_storage
+            .get(&(*addr, *key))
+            .unwrap_or(&VALUE_ZERO)
+    }
+
+    /// Set transient storage value at `addr` and `key`.
+    pub fn set_transient_storage(&mut self, addr: &Address, key: &Word, value: &Word) {
+        self.transient_storage.insert((*addr, *key), *value);
+    }
+
     /// Set account as self destructed.
     pub fn destruct_account(&mut self, addr: Address) {
         self.destructed_account.insert(addr);
@@ -240,8 +287,75 @@ impl StateDB {
         self.refund = value;
     }
 
-    /// Clear access list and refund, and commit dirty storage.
-    /// It should be invoked before processing
+    /// State trie of the committed state
+    pub fn state_trie(&self) -> &StateTrie {
+        &self.trie
+    }
+
+    /// Root of the state trie of the committed state.  Returns an error if
+    /// the trie misses some nodes required to apply the committed changes.
+    pub fn state_root(&self) -> Result<Hash, TrieError> {
+        match &self.trie_error {
+            Some(err) => Err(err.clone()),
+            None => Ok(self.trie.root()),
+        }
+    }
+
+    /// Proof of the committed account at `addr` in the state trie.
+    pub fn account_proof(&self, addr: &Address) -> Result<Vec<Vec<u8>>, TrieError> {
+        self.state_root()?;
+        self.trie.account_proof(addr)
+    }
+
+    /// Proof of the committed storage of the account at `addr` at `key`, which
+    /// starts with the proof of the account.
+    pub fn storage_proof(&self, addr: &Address, key: &Word) -> Result<Vec<Vec<u8>>, TrieError> {
+        self.state_root()?;
+        self.trie.storage_proof(addr, key)
+    }
+
+    /// Apply the `destructed` accounts and the accounts and storage keys
+    /// touched since the last update to the state trie.  If this fails, the
+    /// error is kept and the trie is not updated anymore.
+    fn update_state_trie(&mut self, destructed: HashSet<Address>) {
+        let accounts = std::mem::take(&mut self.touched_accounts);
+        let storage = std::mem::take(&mut self.touched_storage);
+        if self.trie_error.is_none() {
+            if let Err(err) = self.apply_to_state_trie(destructed, accounts, storage) {
+                self.trie_error = Some(err);
+            }
+        }
+    }
+
+    fn apply_to_state_trie(
+        &mut self,
+        destructed: HashSet<Address>,
+        accounts: HashSet<Address>,
+        storage: HashSet<(Address, Word)>,
+    ) -> Result<(), TrieError> {
+        for addr in &destructed {
+            self.trie.remove_account(addr)?;
+        }
+        for addr in accounts {
+            let acc = self.state.get(&addr).unwrap_or(&*ACCOUNT_ZERO);
+            let account = TrieAccount {
+                nonce: acc.nonce,
+                balance: acc.balance,
+                code_hash: acc.code_hash,
+                ..self.trie.account(&addr)?.unwrap_or_default()
+            };
+            self.trie.set_account(&addr, &account)?;
+        }
+        for (addr, key) in storage {
+            let (_, value) = self.get_committed_storage(&addr, &key);
+            let value = *value;
+            self.trie.update_storage(&addr, &key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Clear access list, transient storage and refund, and commit dirty
+    /// storage and the state trie.  It should be invoked before processing
     /// with new transaction with the same [`StateDB`].
     pub fn commit_tx(&mut self) {
         self.access_list_account = HashSet::new();
@@ -251,17 +365,21 @@ impl StateDB {
             *ptr = value;
         }
         self.dirty_storage = HashMap::new();
-        for addr in self.destructed_account.clone() {
-            let (_, account) = self.get_account_mut(&addr);
+        self.transient_storage = HashMap::new();
+        let destructed = std::mem::take(&mut self.destructed_account);
+        for addr in destructed.iter() {
+            let (_, account) = self.get_account_mut(addr);
             *account = ACCOUNT_ZERO.clone();
         }
         self.refund = 0;
+        self.update_state_trie(destructed);
     }
 }
 
 #[cfg(test)]
 mod statedb_tests {
     use super::*;
+    use crate::operation::AccountField;
     use eth_types::address;
 
     #[test]
@@ -318,4 +436,87 @@ mod statedb_tests {
         assert!(found);
         assert_eq!(value, &Word::from(102));
     }
+
+    #[test]
+    fn statedb_state_root() {
+        let addr_a = address!("0x0000000000000000000000000000000000000001");
+        let addr_b = address!("0x0000000000000000000000000000000000000002");
+        let key = Word::from(2);
+        let mut statedb = StateDB::new();
+        let mut trie = StateTrie::default();
+        assert_eq!(statedb.state_root(), Ok(trie.root()));
+
+        // Accounts set in the StateDB are in the trie
+        statedb.set_account(
+            &addr_a,
+            Account {
+                nonce: Word::from(1),
+                balance: Word::from(100),
+                storage: HashMap::from([(key, Word::from(101))]),
+                code_hash: *CODE_HASH_ZERO,
+            },
+        );
+        trie.set_account_field(&addr_a, AccountField::Nonce, Word::from(1))
+            .unwrap();
+        trie.set_account_field(&addr_a, AccountField::Balance, Word::from(100))
+            .unwrap();
+        trie.set_storage(&addr_a, &key, Word::from(101)).unwrap();
+        assert_eq!(statedb.state_root(), Ok(trie.root()));
+
+        // Changes are applied to the trie when the tx is committed
+        let prev_root = trie.root();
+        statedb.get_account_mut(&addr_b).1.balance = Word::from(50);
+        statedb.set_storage(&addr_a, &key, &Word::zero());
+        assert_eq!(statedb.state_root(), Ok(prev_root));
+        statedb.commit_tx();
+        trie.set_account_field(&addr_b, AccountField::Balance, Word::from(50))
+            .unwrap();
+        trie.set_storage(&addr_a, &key, Word::zero()).unwrap();
+        assert_eq!(statedb.state_root(), Ok(trie.root()));
+
+        let proof = statedb.storage_proof(&addr_a, &key).unwrap();
+        assert_eq!(
+            StateTrie::verify_storage_proof(trie.root(), &addr_a, &key, &proof),
+            Ok(Word::zero())
+        );
+        let proof = statedb.account_proof(&addr_b).unwrap();
+        assert_eq!(
+            StateTrie::verify_account_proof(trie.root(), &addr_b, &proof)
+                .unwrap()
+                .map(|account| account.balance),
+            Some(Word::from(50))
+        );
+
+        // Destructed accounts are removed from the trie
+        statedb.destruct_account(addr_a);
+        statedb.commit_tx();
+        trie.remove_account(&addr_a).unwrap();
+        assert_eq!(statedb.state_root(), Ok(trie.root()));
+        assert_eq!(trie.account(&addr_a), Ok(None));
+
+        // Accounts recreated after the destruction is committed are kept
+        statedb.get_account_mut(&addr_a).1.balance = Word::from(10);
+        statedb.commit_tx();
+        trie.set_account_field(&addr_a, AccountField::Balance, Word::from(10))
+            .unwrap();
+        assert_eq!(statedb.state_root(), Ok(trie.root()));
+        assert_eq!(statedb.get_account(&addr_a).1.balance, Word::from(10));
+    }
+
+    #[test]
+    fn statedb_transient_storage() {
+        let addr = address!("0x0000000000000000000000000000000000000001");
+        let key = Word::from(2);
+        let mut statedb = StateDB::new();
+
+        // Transient storage starts at zero and doesn't touch the state
+        assert_eq!(statedb.get_transient_storage(&addr, &key), &Word::zero());
+        statedb.set_transient_storage(&addr, &key, &Word::from(101));
+        assert_eq!(statedb.get_transient_storage(&addr, &key), &Word::from(101));
+        assert!(!statedb.get_storage(&addr, &key).0);
+
+        // Transient storage is discarded when the tx is committed
+        statedb.commit_tx();
+        assert_eq!(statedb.get_transient_storage(&addr, &key), &Word::zero());
+    }
 }
@@ -37,6 +37,7 @@ mod tests {
         let circuit = PiTestCircuit::<Fr, MAX_TXS, MAX_CALLDATA>(PiCircuit::<Fr>::new(
             MAX_TXS,
             MAX_CALLDATA,
+            0,
             randomness,
             rand_rpi,
             public_data,
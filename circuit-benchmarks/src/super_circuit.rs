@@ -5,7 +5,7 @@ mod tests {
     use ark_std::{end_timer, start_timer};
     use bus_mapping::circuit_input_builder::CircuitsParams;
     use eth_types::geth_types::GethData;
-    use eth_types::{address, bytecode, Word};
+    use eth_types::{address, bytecode, evm_types::Hardfork, Word};
     use ethers_signers::LocalWallet;
     use ethers_signers::Signer;
     use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof};
@@ -80,7 +80,9 @@ mod tests {
             max_rws: 256,
             max_copy_rows: 256,
             max_bytecode: 512,
+            max_mpt_rows: 0,
             keccak_padding: None,
+            hardfork: Hardfork::default(),
         };
         let (_, circuit, instance, _) =
             SuperCircuit::<_, MAX_TXS, MAX_CALLDATA, 0x100>::build(block, circuits_params).unwrap();
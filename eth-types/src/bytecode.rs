@@ -236,7 +236,9 @@ impl FromStr for OpcodeWithData {
     #[allow(clippy::manual_range_contains)]
     fn from_str(op: &str) -> Result<Self, Self::Err> {
         let err = || Error::InvalidAsmError(op.to_string());
-        if let Some(push) = op.strip_prefix("PUSH") {
+        if op == "PUSH0" {
+            Ok(OpcodeWithData::Opcode(OpcodeId::PUSH0))
+        } else if let Some(push) = op.strip_prefix("PUSH") {
             let n_value: Vec<_> = push.splitn(3, ['(', ')']).collect();
             let n = n_value[0].parse::<u8>().map_err(|_| err())?;
             if n < 1 || n > 32 {
@@ -386,6 +388,8 @@ mod tests {
             PUSH1(5)
             PUSH2(0xa)
             MUL
+            PUSH0
+            ADD
             STOP
         };
         let mut code2 = Bytecode::default();
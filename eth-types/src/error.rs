@@ -33,6 +33,10 @@ pub enum Error {
     WordToMemAddr,
     /// Signature parsing error.
     Signature(libsecp256k1::Error),
+    /// Transaction type that is not supported (EIP-2718).
+    UnsupportedTxType(u64),
+    /// Error while parsing the name of a `Hardfork`.
+    UnknownHardfork(String),
 }
 
 impl From<libsecp256k1::Error> for Error {
@@ -4,12 +4,14 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 pub mod gas_utils;
+pub mod hardfork;
 pub mod memory;
 pub mod opcode_ids;
 pub mod stack;
 pub mod storage;
 
 pub use {
+    hardfork::Hardfork,
     memory::{Memory, MemoryAddress},
     opcode_ids::OpcodeId,
     stack::{Stack, StackAddress},
@@ -66,6 +68,10 @@ impl fmt::Debug for Gas {
 pub const MAX_REFUND_QUOTIENT_OF_GAS_USED: usize = 5;
 /// Gas stipend when CALL or CALLCODE is attached with value.
 pub const GAS_STIPEND_CALL_WITH_VALUE: u64 = 2300;
+/// Maximum size of a deployed contract code (EIP-170)
+pub const MAX_CODE_SIZE: u64 = 24576;
+/// Maximum size of the init code of a contract creation (EIP-3860)
+pub const MAX_INIT_CODE_SIZE: u64 = 2 * MAX_CODE_SIZE;
 
 /// Defines the gas consumption.
 #[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -122,6 +128,10 @@ impl GasCost {
     pub const TX: Self = Self(21000);
     /// Constant cost for a creation transaction
     pub const CREATION_TX: Self = Self(53000);
+    /// Cost per address of the access list of a transaction (EIP-2930)
+    pub const ACCESS_LIST_ADDRESS: Self = Self(2400);
+    /// Cost per storage key of the access list of a transaction (EIP-2930)
+    pub const ACCESS_LIST_STORAGE_KEY: Self = Self(1900);
     /// Constant cost for calling with non-zero value
     pub const CALL_WITH_VALUE: Self = Self(9000);
     /// Constant cost for turning empty account into non-empty account
@@ -134,6 +144,8 @@ impl GasCost {
     pub const MEMORY_EXPANSION_LINEAR_COEFF: Self = Self(3);
     /// Constant gas for LOG[0-4] op codes
     pub const LOG: Self = Self(375);
+    /// Cost per word of the init code of a contract creation (EIP-3860)
+    pub const INIT_CODE_WORD_COST: Self = Self(2);
 }
 
 impl GasCost {
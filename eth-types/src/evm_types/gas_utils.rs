@@ -28,6 +28,12 @@ pub fn memory_copier_gas_cost(
         + memory_expansion_gas_cost(curr_memory_word_size, next_memory_word_size)
 }
 
+/// Calculate EIP 3860 gas cost of the init code of a contract creation, which
+/// is charged per word by creation txs, CREATE and CREATE2.
+pub fn init_code_gas_cost(init_code_length: u64) -> u64 {
+    GasCost::INIT_CODE_WORD_COST.as_u64() * ((init_code_length + 31) / 32)
+}
+
 /// Calculate EIP 150 gas passed to callee.
 pub fn eip150_gas(gas_left: u64, gas_specified: Word) -> u64 {
     let capped_gas = gas_left - gas_left / 64;
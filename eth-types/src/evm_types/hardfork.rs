@@ -0,0 +1,145 @@
+//! Hardforks supported by the zkEVM
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use strum_macros::EnumIter;
+
+use super::{GasCost, MAX_REFUND_QUOTIENT_OF_GAS_USED};
+use crate::Error;
+
+/// Ethereum hardfork whose rules are used to build and prove a block.  Only
+/// the forks from London onwards are supported, and the variants are ordered
+/// by activation so that `hardfork >= Hardfork::Shanghai` reads as "Shanghai
+/// rules are active".
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    EnumIter,
+)]
+pub enum Hardfork {
+    /// London (EIP-1559, EIP-3198, EIP-3529, EIP-3541)
+    #[default]
+    London,
+    /// Paris, also known as the Merge (EIP-3675, EIP-4399)
+    Paris,
+    /// Shanghai (EIP-3651, EIP-3855, EIP-3860, EIP-4895)
+    Shanghai,
+    /// Cancun (EIP-1153, EIP-4788, EIP-4844, EIP-5656, EIP-6780, EIP-7516)
+    Cancun,
+}
+
+impl Hardfork {
+    /// Returns `true` if the block is produced by the beacon chain, in which
+    /// case the `DIFFICULTY` opcode returns the `PREVRANDAO` value taken from
+    /// the `mix_hash` field of the header.
+    pub fn is_post_merge(&self) -> bool {
+        *self >= Self::Paris
+    }
+
+    /// Returns `true` if `eip` is part of the rules of `self`.  EIPs which are
+    /// not listed on any of the supported hardforks are never enabled.
+    pub fn is_eip_enabled(&self, eip: u64) -> bool {
+        let activation = match eip {
+            1559 | 3198 | 3529 | 3541 => Self::London,
+            3675 | 4399 => Self::Paris,
+            3651 | 3855 | 3860 | 4895 => Self::Shanghai,
+            1153 | 4788 | 4844 | 5656 | 6780 | 7516 => Self::Cancun,
+            _ => return false,
+        };
+        *self >= activation
+    }
+
+    /// Returns the divisor of the gas used by a transaction giving its
+    /// maximum refund, which EIP-3529 raised from 2 to 5.
+    pub fn max_refund_quotient(&self) -> u64 {
+        if self.is_eip_enabled(3529) {
+            MAX_REFUND_QUOTIENT_OF_GAS_USED as u64
+        } else {
+            2
+        }
+    }
+
+    /// Returns the refund for clearing a storage slot, which EIP-3529 lowered
+    /// from 15000 to 4800.
+    pub fn sstore_clears_schedule(&self) -> GasCost {
+        if self.is_eip_enabled(3529) {
+            GasCost::SSTORE_CLEARS_SCHEDULE
+        } else {
+            GasCost(15000)
+        }
+    }
+
+    /// Returns `true` if `SELFDESTRUCT` only deletes the accounts created in
+    /// the same transaction, and otherwise only sends their balance
+    /// (EIP-6780).
+    pub fn is_selfdestruct_restricted(&self) -> bool {
+        self.is_eip_enabled(6780)
+    }
+}
+
+impl fmt::Display for Hardfork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Hardfork {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "London" => Self::London,
+            "Paris" | "Merge" => Self::Paris,
+            "Shanghai" => Self::Shanghai,
+            "Cancun" => Self::Cancun,
+            _ => return Err(Error::UnknownHardfork(s.to_string())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod hardfork_tests {
+    use super::*;
+
+    #[test]
+    fn hardfork_order_and_parsing() {
+        assert!(Hardfork::London < Hardfork::Paris);
+        assert!(Hardfork::Shanghai < Hardfork::Cancun);
+        assert!(!Hardfork::London.is_post_merge());
+        assert!(Hardfork::Shanghai.is_post_merge());
+        assert_eq!(Hardfork::from_str("Merge").unwrap(), Hardfork::Paris);
+        assert_eq!(
+            Hardfork::from_str(&Hardfork::Cancun.to_string()).unwrap(),
+            Hardfork::Cancun
+        );
+        assert!(Hardfork::from_str("Berlin").is_err());
+    }
+
+    #[test]
+    fn hardfork_eips() {
+        assert!(Hardfork::London.is_eip_enabled(3198));
+        assert!(!Hardfork::Paris.is_eip_enabled(3860));
+        assert!(Hardfork::Shanghai.is_eip_enabled(3651));
+        assert!(Hardfork::Cancun.is_eip_enabled(3855));
+        assert!(!Hardfork::Cancun.is_eip_enabled(2));
+    }
+
+    #[test]
+    fn hardfork_rules() {
+        for hardfork in [Hardfork::London, Hardfork::Shanghai] {
+            assert_eq!(hardfork.max_refund_quotient(), 5);
+            assert_eq!(hardfork.sstore_clears_schedule().as_u64(), 4800);
+            assert!(!hardfork.is_selfdestruct_restricted());
+        }
+        assert!(Hardfork::Cancun.is_selfdestruct_restricted());
+    }
+}
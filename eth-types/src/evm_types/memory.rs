@@ -4,11 +4,11 @@ use crate::{DebugByte, ToBigEndian, Word};
 use core::ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Range, Sub, SubAssign};
 use core::str::FromStr;
 use itertools::Itertools;
-use serde::{Serialize, Serializer};
+use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
 use std::fmt;
 
 /// Represents a `MemoryAddress` of the EVM.
-#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct MemoryAddress(pub usize);
 
 impl fmt::Debug for MemoryAddress {
@@ -248,13 +248,18 @@ impl<A: Into<MemoryAddress>> IndexMut<A> for Memory {
     }
 }
 
+/// Serializes the memory in chunks of 32 bytes in hex, as returned by geth, so
+/// that it deserializes back in a [`GethExecStep`](crate::GethExecStep).
 impl Serialize for Memory {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let encoded = hex::encode(&self.0);
-        serializer.serialize_str(encoded.as_str())
+        let mut ser = serializer.serialize_seq(Some((self.0.len() + 31) / 32))?;
+        for chunk in self.0.chunks(32) {
+            ser.serialize_element(&hex::encode(chunk))?;
+        }
+        ser.end()
     }
 }
 
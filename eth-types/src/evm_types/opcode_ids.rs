@@ -1,5 +1,8 @@
 //! Doc this
-use crate::{error::Error, evm_types::GasCost};
+use crate::{
+    error::Error,
+    evm_types::{GasCost, Hardfork},
+};
 use core::fmt::Debug;
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -9,7 +12,7 @@ use std::str::FromStr;
 use strum_macros::EnumIter;
 
 /// Opcode enum. One-to-one corresponding to an `u8` value.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Hash, EnumIter)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, EnumIter)]
 pub enum OpcodeId {
     /// `STOP`
     STOP,
@@ -94,8 +97,16 @@ pub enum OpcodeId {
     MSIZE,
     /// `JUMPDEST`
     JUMPDEST,
+    /// `TLOAD`
+    TLOAD,
+    /// `TSTORE`
+    TSTORE,
+    /// `MCOPY`
+    MCOPY,
 
     // PUSHn
+    /// `PUSH0`
+    PUSH0,
     /// `PUSH1`
     PUSH1,
     /// `PUSH2`
@@ -316,7 +327,8 @@ pub enum OpcodeId {
 }
 
 impl OpcodeId {
-    /// Returns `true` if the `OpcodeId` is a `PUSHn`.
+    /// Returns `true` if the `OpcodeId` is a `PUSHn` with immediate data, that
+    /// is `PUSH1` to `PUSH32`.  `PUSH0` is not included.
     pub fn is_push(&self) -> bool {
         self.as_u8() >= Self::PUSH1.as_u8() && self.as_u8() <= Self::PUSH32.as_u8()
     }
@@ -357,6 +369,25 @@ impl OpcodeId {
     pub fn is_call_or_create(&self) -> bool {
         self.is_call() || self.is_create()
     }
+
+    /// Returns the first [`Hardfork`] in which the `OpcodeId` is defined.
+    /// Every opcode we support was already defined in London, except for
+    /// `PUSH0` (EIP-3855) which is introduced in Shanghai, and `TLOAD` and
+    /// `TSTORE` (EIP-1153) and `MCOPY` (EIP-5656) which are introduced in
+    /// Cancun.
+    pub fn activation_fork(&self) -> Hardfork {
+        match self {
+            OpcodeId::PUSH0 => Hardfork::Shanghai,
+            OpcodeId::TLOAD | OpcodeId::TSTORE | OpcodeId::MCOPY => Hardfork::Cancun,
+            _ => Hardfork::London,
+        }
+    }
+
+    /// Returns `true` if the `OpcodeId` is defined under the rules of
+    /// `hardfork`.  Otherwise executing it fails as an invalid opcode.
+    pub fn is_available(&self, hardfork: Hardfork) -> bool {
+        hardfork >= self.activation_fork()
+    }
 }
 
 impl OpcodeId {
@@ -403,6 +434,10 @@ impl OpcodeId {
             OpcodeId::PC => 0x58u8,
             OpcodeId::MSIZE => 0x59u8,
             OpcodeId::JUMPDEST => 0x5bu8,
+            OpcodeId::TLOAD => 0x5cu8,
+            OpcodeId::TSTORE => 0x5du8,
+            OpcodeId::MCOPY => 0x5eu8,
+            OpcodeId::PUSH0 => 0x5fu8,
             OpcodeId::PUSH1 => 0x60u8,
             OpcodeId::PUSH2 => 0x61u8,
             OpcodeId::PUSH3 => 0x62u8,
@@ -581,6 +616,10 @@ impl OpcodeId {
             OpcodeId::MSIZE => GasCost::QUICK,
             OpcodeId::GAS => GasCost::QUICK,
             OpcodeId::JUMPDEST => GasCost::ONE,
+            OpcodeId::TLOAD => GasCost::WARM_ACCESS,
+            OpcodeId::TSTORE => GasCost::WARM_ACCESS,
+            OpcodeId::MCOPY => GasCost::FASTEST,
+            OpcodeId::PUSH0 => GasCost::QUICK,
             OpcodeId::PUSH1 => GasCost::FASTEST,
             OpcodeId::PUSH2 => GasCost::FASTEST,
             OpcodeId::PUSH3 => GasCost::FASTEST,
@@ -736,6 +775,10 @@ impl OpcodeId {
             OpcodeId::MSIZE => (1, 1024),
             OpcodeId::GAS => (1, 1024),
             OpcodeId::JUMPDEST => (0, 1024),
+            OpcodeId::TLOAD => (0, 1023),
+            OpcodeId::TSTORE => (0, 1022),
+            OpcodeId::MCOPY => (0, 1021),
+            OpcodeId::PUSH0 => (1, 1024),
             OpcodeId::PUSH1 => (1, 1024),
             OpcodeId::PUSH2 => (1, 1024),
             OpcodeId::PUSH3 => (1, 1024),
@@ -830,6 +873,7 @@ impl OpcodeId {
                 | OpcodeId::RETURNDATACOPY
                 | OpcodeId::CODECOPY
                 | OpcodeId::EXTCODECOPY
+                | OpcodeId::MCOPY
         )
     }
 
@@ -911,6 +955,10 @@ impl From<u8> for OpcodeId {
             0x58u8 => OpcodeId::PC,
             0x59u8 => OpcodeId::MSIZE,
             0x5bu8 => OpcodeId::JUMPDEST,
+            0x5cu8 => OpcodeId::TLOAD,
+            0x5du8 => OpcodeId::TSTORE,
+            0x5eu8 => OpcodeId::MCOPY,
+            0x5fu8 => OpcodeId::PUSH0,
             0x60u8 => OpcodeId::PUSH1,
             0x61u8 => OpcodeId::PUSH2,
             0x62u8 => OpcodeId::PUSH3,
@@ -1064,6 +1112,10 @@ impl FromStr for OpcodeId {
             "PC" => OpcodeId::PC,
             "MSIZE" => OpcodeId::MSIZE,
             "JUMPDEST" => OpcodeId::JUMPDEST,
+            "TLOAD" => OpcodeId::TLOAD,
+            "TSTORE" => OpcodeId::TSTORE,
+            "MCOPY" => OpcodeId::MCOPY,
+            "PUSH0" => OpcodeId::PUSH0,
             "PUSH1" => OpcodeId::PUSH1,
             "PUSH2" => OpcodeId::PUSH2,
             "PUSH3" => OpcodeId::PUSH3,
@@ -1131,7 +1183,6 @@ impl FromStr for OpcodeId {
             "RETURN" => OpcodeId::RETURN,
             "REVERT" => OpcodeId::REVERT,
             "INVALID" => OpcodeId::INVALID(0xfe),
-            "PUSH0" => OpcodeId::INVALID(0x5f),
             "SHA3" | "KECCAK256" => OpcodeId::SHA3,
             "ADDRESS" => OpcodeId::ADDRESS,
             "BALANCE" => OpcodeId::BALANCE,
@@ -1187,6 +1238,22 @@ impl FromStr for OpcodeId {
     }
 }
 
+impl Serialize for OpcodeId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            // Invalid opcodes are serialized as reported by geth, so that they
+            // deserialize back.
+            OpcodeId::INVALID(b) => {
+                serializer.serialize_str(&format!("opcode 0x{:x} not defined", b))
+            }
+            op => serializer.serialize_str(&op.to_string()),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for OpcodeId {
     fn deserialize<D>(deserializer: D) -> Result<OpcodeId, D::Error>
     where
@@ -1221,6 +1288,15 @@ mod opcode_ids_tests {
         ));
     }
 
+    #[test]
+    fn serde_roundtrip() {
+        for byte in 0..=u8::MAX {
+            let op = OpcodeId::from(byte);
+            let json = serde_json::to_string(&op).unwrap();
+            assert_eq!(serde_json::from_str::<OpcodeId>(&json).unwrap(), op);
+        }
+    }
+
     #[test]
     fn postfix() {
         assert_eq!(OpcodeId::PUSH1.postfix(), Some(1));
@@ -1235,5 +1311,34 @@ mod opcode_ids_tests {
         assert_eq!(OpcodeId::PUSH10.data_len(), 10);
         assert_eq!(OpcodeId::LOG2.data_len(), 0);
         assert_eq!(OpcodeId::CALLCODE.data_len(), 0);
+        assert_eq!(OpcodeId::PUSH0.data_len(), 0);
+    }
+
+    #[test]
+    fn push0() {
+        assert_eq!(OpcodeId::from(0x5f), OpcodeId::PUSH0);
+        assert_eq!(OpcodeId::from_str("PUSH0").unwrap(), OpcodeId::PUSH0);
+        assert!(!OpcodeId::PUSH0.is_push());
+        assert!(!OpcodeId::PUSH0.is_available(Hardfork::London));
+        assert!(OpcodeId::PUSH0.is_available(Hardfork::Shanghai));
+    }
+
+    #[test]
+    fn transient_storage() {
+        assert_eq!(OpcodeId::from(0x5c), OpcodeId::TLOAD);
+        assert_eq!(OpcodeId::from(0x5d), OpcodeId::TSTORE);
+        assert_eq!(OpcodeId::from_str("TLOAD").unwrap(), OpcodeId::TLOAD);
+        assert_eq!(OpcodeId::from_str("TSTORE").unwrap(), OpcodeId::TSTORE);
+        assert!(!OpcodeId::TLOAD.is_available(Hardfork::Shanghai));
+        assert!(OpcodeId::TLOAD.is_available(Hardfork::Cancun));
+        assert!(OpcodeId::TSTORE.is_available(Hardfork::Cancun));
+    }
+
+    #[test]
+    fn mcopy() {
+        assert_eq!(OpcodeId::from(0x5e), OpcodeId::MCOPY);
+        assert_eq!(OpcodeId::from_str("MCOPY").unwrap(), OpcodeId::MCOPY);
+        assert!(!OpcodeId::MCOPY.is_available(Hardfork::Shanghai));
+        assert!(OpcodeId::MCOPY.is_available(Hardfork::Cancun));
     }
 }
@@ -3,21 +3,27 @@
 use crate::{
     sign_types::{biguint_to_32bytes_le, ct_option_ok_or, recover_pk, SignData, SECP256K1_Q},
     AccessList, Address, Block, Bytes, Error, GethExecTrace, Hash, ToBigEndian, ToLittleEndian,
-    Word, U64,
+    ToWord, Word, U64,
+};
+use ethers_core::{
+    types::{
+        transaction::eip2718::TypedTransaction, Eip1559TransactionRequest,
+        Eip2930TransactionRequest, Signature, TransactionRequest,
+    },
+    utils::rlp::{Encodable, RlpStream},
 };
-use ethers_core::types::TransactionRequest;
 use ethers_signers::{LocalWallet, Signer};
 use halo2_proofs::halo2curves::{group::ff::PrimeField, secp256k1};
 use num::Integer;
 use num_bigint::BigUint;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::serde_as;
 use sha3::{Digest, Keccak256};
 use std::collections::HashMap;
 
 /// Definition of all of the data related to an account.
 #[serde_as]
-#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize)]
+#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Account {
     /// Address
     pub address: Address,
@@ -28,7 +34,10 @@ pub struct Account {
     /// EVM Code
     pub code: Bytes,
     /// Storage
-    #[serde(serialize_with = "serde_account_storage")]
+    #[serde(
+        serialize_with = "serde_account_storage",
+        deserialize_with = "de_account_storage"
+    )]
     pub storage: HashMap<Word, Word>,
 }
 
@@ -53,6 +62,48 @@ fn serde_account_storage<S: Serializer>(
         .serialize(serializer)
 }
 
+fn de_account_storage<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Word, Word>, D::Error> {
+    Ok(HashMap::<Hash, Hash>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, v)| (k.to_word(), v.to_word()))
+        .collect())
+}
+
+/// Serde of the types from geth with optional or flattened fields, like
+/// [`Block`], which only deserialize from self-describing formats.  The other
+/// formats, like bincode, get them as a JSON string.  Use it with
+/// `#[serde(with = "eth_types::geth_types::json_in_binary")]`.
+pub mod json_in_binary {
+    use serde::{de, de::DeserializeOwned, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize `value`, as a JSON string if the format is not human readable.
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            value.serialize(serializer)
+        } else {
+            serde_json::to_string(value)
+                .map_err(ser::Error::custom)?
+                .serialize(serializer)
+        }
+    }
+
+    /// Deserialize a value serialized by [`serialize`].
+    pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        if deserializer.is_human_readable() {
+            T::deserialize(deserializer)
+        } else {
+            serde_json::from_str(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+        }
+    }
+}
+
 /// Definition of all of the constants related to an Ethereum block and
 /// chain to be used as setup for the external tracer.
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
@@ -69,6 +120,8 @@ pub struct BlockConstants {
     pub gas_limit: Word,
     /// base fee
     pub base_fee: Word,
+    /// mix hash, which holds the `PREVRANDAO` value after the Merge
+    pub mix_hash: Hash,
 }
 
 impl<TX> TryFrom<&Block<TX>> for BlockConstants {
@@ -82,6 +135,7 @@ impl<TX> TryFrom<&Block<TX>> for BlockConstants {
             difficulty: block.difficulty,
             gas_limit: block.gas_limit,
             base_fee: block.base_fee_per_gas.ok_or(Error::IncompleteBlock)?,
+            mix_hash: block.mix_hash.unwrap_or_default(),
         })
     }
 }
@@ -95,6 +149,7 @@ impl BlockConstants {
         difficulty: Word,
         gas_limit: Word,
         base_fee: Word,
+        mix_hash: Hash,
     ) -> BlockConstants {
         BlockConstants {
             coinbase,
@@ -103,6 +158,150 @@ impl BlockConstants {
             difficulty,
             gas_limit,
             base_fee,
+            mix_hash,
+        }
+    }
+}
+
+/// Withdrawal of a validator of the beacon chain, as defined in EIP-4895.  The
+/// withdrawals of a block are applied after its transactions, crediting
+/// `amount` Gwei to `address` without consuming gas.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    /// Monotonically increasing index of the withdrawal
+    pub index: U64,
+    /// Index of the validator
+    pub validator_index: U64,
+    /// Recipient of the withdrawal
+    pub address: Address,
+    /// Amount of the withdrawal, in Gwei
+    pub amount: U64,
+}
+
+impl Withdrawal {
+    /// Return the amount of the withdrawal in Wei.
+    pub fn amount_wei(&self) -> Word {
+        Word::from(self.amount.as_u64()) * Word::exp10(9)
+    }
+
+    /// Return the RLP encoding of the withdrawal, `rlp([index,
+    /// validator_index, address, amount])`, which is its value in the
+    /// withdrawals trie of the block.
+    pub fn rlp(&self) -> Vec<u8> {
+        self.rlp_bytes().to_vec()
+    }
+}
+
+impl Encodable for Withdrawal {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream
+            .begin_list(4)
+            .append(&self.index)
+            .append(&self.validator_index)
+            .append(&self.address)
+            .append(&self.amount);
+    }
+}
+
+/// Returns the withdrawals of a post-Shanghai block, which are not part of the
+/// block type of `ethers`.  Blocks without withdrawals return an empty list.
+pub fn block_withdrawals<TX>(block: &Block<TX>) -> Result<Vec<Withdrawal>, Error> {
+    block
+        .other
+        .get_deserialized("withdrawals")
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(Error::SerdeError)
+}
+
+/// Returns the withdrawals root of the header of a post-Shanghai block, if
+/// any.
+pub fn block_withdrawals_root<TX>(block: &Block<TX>) -> Result<Option<Hash>, Error> {
+    block
+        .other
+        .get_deserialized("withdrawalsRoot")
+        .transpose()
+        .map_err(Error::SerdeError)
+}
+
+/// Returns the RLP encoding of the header of a block, whose keccak hash is the
+/// block hash.  The base fee is only encoded when the block has one, and the
+/// withdrawals root when the block is post-Shanghai.
+pub fn block_header_rlp<TX>(block: &Block<TX>) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    stream.begin_unbounded_list();
+    stream
+        .append(&block.parent_hash)
+        .append(&block.uncles_hash)
+        .append(&block.author.unwrap_or_default())
+        .append(&block.state_root)
+        .append(&block.transactions_root)
+        .append(&block.receipts_root)
+        .append(&block.logs_bloom.unwrap_or_default().as_bytes())
+        .append(&block.difficulty)
+        .append(&block.number.unwrap_or_default())
+        .append(&block.gas_limit)
+        .append(&block.gas_used)
+        .append(&block.timestamp)
+        .append(&block.extra_data.to_vec())
+        .append(&block.mix_hash.unwrap_or_default())
+        .append(&block.nonce.unwrap_or_default().as_bytes());
+    if let Some(base_fee) = block.base_fee_per_gas {
+        stream.append(&base_fee);
+    }
+    if let Ok(Some(withdrawals_root)) = block_withdrawals_root(block) {
+        stream.append(&withdrawals_root);
+    }
+    stream.finalize_unbounded_list();
+    stream.out().to_vec()
+}
+
+/// Type of a transaction envelope, as defined in EIP-2718.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxType {
+    /// Legacy transaction, signed with EIP-155 replay protection
+    #[default]
+    Legacy = 0,
+    /// EIP-2930 transaction, with an access list
+    Eip2930 = 1,
+    /// EIP-1559 transaction, with a priority fee and a max fee per gas
+    Eip1559 = 2,
+}
+
+impl TryFrom<u64> for TxType {
+    type Error = Error;
+
+    fn try_from(tx_type: u64) -> Result<Self, Self::Error> {
+        match tx_type {
+            0 => Ok(Self::Legacy),
+            1 => Ok(Self::Eip2930),
+            2 => Ok(Self::Eip1559),
+            _ => Err(Error::UnsupportedTxType(tx_type)),
+        }
+    }
+}
+
+impl TxType {
+    /// Return the recovery id of a signature of a transaction of this type
+    /// from its `v`: legacy transactions use the EIP-155 `v`, `recovery_id +
+    /// 35 + 2 * chain_id`, and typed transactions use the y parity.
+    pub fn recovery_id(&self, v: u64, chain_id: u64) -> Option<u8> {
+        match self {
+            Self::Legacy => v.checked_sub(35 + chain_id * 2),
+            Self::Eip2930 | Self::Eip1559 => Some(v),
+        }
+        .filter(|recovery_id| *recovery_id <= 1)
+        .map(|recovery_id| recovery_id as u8)
+    }
+
+    /// Return the `v` of a signature of a transaction of this type from the
+    /// `v` of the same signature with EIP-155 replay protection, as returned
+    /// by the signers of `ethers`.
+    pub fn sig_v_from_eip155(&self, v: u64, chain_id: u64) -> u64 {
+        match self {
+            Self::Legacy => v,
+            Self::Eip2930 | Self::Eip1559 => v - 35 - chain_id * 2,
         }
     }
 }
@@ -110,6 +309,8 @@ impl BlockConstants {
 /// Definition of all of the constants related to an Ethereum transaction.
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct Transaction {
+    /// Type of the transaction envelope
+    pub tx_type: TxType,
     /// Sender address
     pub from: Address,
     /// Recipient address (None for contract creation)
@@ -122,9 +323,9 @@ pub struct Transaction {
     pub value: Word,
     /// Gas Price
     pub gas_price: Word,
-    /// Gas fee cap
+    /// Gas fee cap (max fee per gas)
     pub gas_fee_cap: Word,
-    /// Gas tip cap
+    /// Gas tip cap (max priority fee per gas)
     pub gas_tip_cap: Word,
     /// The compiled code of a contract OR the first 4 bytes of the hash of the
     /// invoked method signature and encoded parameters. For details see
@@ -132,8 +333,11 @@ pub struct Transaction {
     pub call_data: Bytes,
     /// Access list
     pub access_list: Option<AccessList>,
+    /// Number of the block that includes the transaction (0 when pending)
+    pub block_number: u64,
 
-    /// "v" value of the transaction signature
+    /// "v" value of the transaction signature: the EIP-155 `v` for legacy
+    /// transactions and the y parity for typed transactions
     pub v: u64,
     /// "r" value of the transaction signature
     pub r: Word,
@@ -150,13 +354,14 @@ impl From<&Transaction> for crate::Transaction {
             gas: tx.gas_limit,
             value: tx.value,
             gas_price: Some(tx.gas_price),
-            max_priority_fee_per_gas: Some(tx.gas_fee_cap),
-            max_fee_per_gas: Some(tx.gas_tip_cap),
+            max_priority_fee_per_gas: Some(tx.gas_tip_cap),
+            max_fee_per_gas: Some(tx.gas_fee_cap),
             input: tx.call_data.clone(),
             access_list: tx.access_list.clone(),
             v: tx.v.into(),
             r: tx.r,
             s: tx.s,
+            transaction_type: Some(U64::from(tx.tx_type as u64)),
             ..Default::default()
         }
     }
@@ -165,16 +370,21 @@ impl From<&Transaction> for crate::Transaction {
 impl From<&crate::Transaction> for Transaction {
     fn from(tx: &crate::Transaction) -> Transaction {
         Transaction {
+            tx_type: tx
+                .transaction_type
+                .map(|tx_type| TxType::try_from(tx_type.as_u64()).expect("unsupported tx type"))
+                .unwrap_or_default(),
             from: tx.from,
             to: tx.to,
             nonce: tx.nonce,
             gas_limit: tx.gas,
             value: tx.value,
             gas_price: tx.gas_price.unwrap_or_default(),
-            gas_fee_cap: tx.max_priority_fee_per_gas.unwrap_or_default(),
-            gas_tip_cap: tx.max_fee_per_gas.unwrap_or_default(),
+            gas_fee_cap: tx.max_fee_per_gas.unwrap_or_default(),
+            gas_tip_cap: tx.max_priority_fee_per_gas.unwrap_or_default(),
             call_data: tx.input.clone(),
             access_list: tx.access_list.clone(),
+            block_number: tx.block_number.unwrap_or_default().as_u64(),
             v: tx.v.as_u64(),
             r: tx.r,
             s: tx.s,
@@ -196,6 +406,69 @@ impl From<&Transaction> for TransactionRequest {
 }
 
 impl Transaction {
+    /// Return the unsigned transaction of this Transaction with the envelope
+    /// of its type.
+    pub fn typed_tx(&self, chain_id: u64) -> TypedTransaction {
+        let access_list = self.access_list.clone().unwrap_or_default();
+        match self.tx_type {
+            TxType::Legacy | TxType::Eip2930 => {
+                let mut req = TransactionRequest::new()
+                    .nonce(self.nonce)
+                    .gas_price(self.gas_price)
+                    .gas(self.gas_limit)
+                    .value(self.value)
+                    .data(self.call_data.clone())
+                    .chain_id(chain_id);
+                if let Some(to) = self.to {
+                    req = req.to(to);
+                }
+                if self.tx_type == TxType::Legacy {
+                    TypedTransaction::Legacy(req)
+                } else {
+                    TypedTransaction::Eip2930(Eip2930TransactionRequest::new(req, access_list))
+                }
+            }
+            TxType::Eip1559 => {
+                let mut req = Eip1559TransactionRequest::new()
+                    .nonce(self.nonce)
+                    .max_priority_fee_per_gas(self.gas_tip_cap)
+                    .max_fee_per_gas(self.gas_fee_cap)
+                    .gas(self.gas_limit)
+                    .value(self.value)
+                    .data(self.call_data.clone())
+                    .access_list(access_list)
+                    .chain_id(chain_id);
+                if let Some(to) = self.to {
+                    req = req.to(to);
+                }
+                TypedTransaction::Eip1559(req)
+            }
+        }
+    }
+
+    /// Return the message signed by this Transaction:
+    /// - Legacy (EIP-155): `rlp([nonce, gas_price, gas, to, value, data,
+    ///   chain_id, 0, 0])`
+    /// - EIP-2930: `0x01 || rlp([chain_id, nonce, gas_price, gas, to, value,
+    ///   data, access_list])`
+    /// - EIP-1559: `0x02 || rlp([chain_id, nonce, max_priority_fee_per_gas,
+    ///   max_fee_per_gas, gas, to, value, data, access_list])`
+    pub fn sign_message(&self, chain_id: u64) -> Vec<u8> {
+        self.typed_tx(chain_id).rlp().to_vec()
+    }
+
+    /// Return the encoding of this signed Transaction, with the envelope of
+    /// its type, whose keccak hash is the transaction hash.  It's the value of
+    /// the transaction in the transactions trie of a block.
+    pub fn signed_rlp(&self, chain_id: u64) -> Vec<u8> {
+        let signature = Signature {
+            r: self.r,
+            s: self.s,
+            v: self.v,
+        };
+        self.typed_tx(chain_id).rlp_signed(&signature).to_vec()
+    }
+
     /// Return the SignData associated with this Transaction.
     pub fn sign_data(&self, chain_id: u64) -> Result<SignData, Error> {
         let sig_r_le = self.r.to_le_bytes();
@@ -208,18 +481,16 @@ impl Transaction {
             secp256k1::Fq::from_repr(sig_s_le),
             Error::Signature(libsecp256k1::Error::InvalidSignature),
         )?;
-        // msg = rlp([nonce, gasPrice, gas, to, value, data, sig_v, r, s])
-        let req: TransactionRequest = self.into();
-        let msg = req.chain_id(chain_id).rlp();
+        let msg = self.sign_message(chain_id);
         let msg_hash: [u8; 32] = Keccak256::digest(&msg)
             .as_slice()
             .to_vec()
             .try_into()
             .expect("hash length isn't 32 bytes");
         let v = self
-            .v
-            .checked_sub(35 + chain_id * 2)
-            .ok_or(Error::Signature(libsecp256k1::Error::InvalidSignature))? as u8;
+            .tx_type
+            .recovery_id(self.v, chain_id)
+            .ok_or(Error::Signature(libsecp256k1::Error::InvalidSignature))?;
         let pk = recover_pk(v, &self.r, &self.s, &msg_hash)?;
         // msg_hash = msg_hash % q
         let msg_hash = BigUint::from_bytes_be(msg_hash.as_slice());
@@ -238,7 +509,7 @@ impl Transaction {
 }
 
 /// GethData is a type that contains all the information of a Ethereum block
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GethData {
     /// chain id
     pub chain_id: Word,
@@ -260,11 +531,105 @@ impl GethData {
             let wallet = wallets.get(&tx.from).unwrap();
             assert_eq!(Word::from(wallet.chain_id()), self.chain_id);
             let geth_tx: Transaction = (&*tx).into();
-            let req: TransactionRequest = (&geth_tx).into();
-            let sig = wallet.sign_transaction_sync(&req.chain_id(self.chain_id.as_u64()).into());
-            tx.v = U64::from(sig.v);
+            let chain_id = self.chain_id.as_u64();
+            let sig = wallet.sign_transaction_sync(&geth_tx.typed_tx(chain_id));
+            tx.v = U64::from(geth_tx.tx_type.sig_v_from_eip155(sig.v, chain_id));
             tx.r = sig.r;
             tx.s = sig.s;
         }
     }
 }
+
+#[cfg(test)]
+mod geth_types_tests {
+    use super::*;
+    use crate::Transaction;
+
+    #[test]
+    fn block_withdrawals() {
+        let mut block = Block::<Transaction>::default();
+        block.other = serde_json::from_value(serde_json::json!({
+            "withdrawalsRoot":
+                "0x7a4ecf19774d15cf9c15adf0dd8e8a250c128b26c9e2ab2a08d6c9c8ffbd104f",
+            "withdrawals": [
+                {
+                    "index": "0x0",
+                    "validatorIndex": "0x5",
+                    "address": "0x00000000000000000000000000000000000000aa",
+                    "amount": "0x2"
+                },
+            ],
+        }))
+        .unwrap();
+
+        let withdrawals = super::block_withdrawals(&block).unwrap();
+        assert_eq!(
+            withdrawals,
+            vec![Withdrawal {
+                index: 0.into(),
+                validator_index: 5.into(),
+                address: Address::from_low_u64_be(0xaa),
+                amount: 2.into(),
+            }]
+        );
+        assert_eq!(withdrawals[0].amount_wei(), Word::from(2_000_000_000u64));
+        assert!(block_withdrawals_root(&block).unwrap().is_some());
+
+        // The withdrawals root is the last field of the header
+        let header = block_header_rlp(&block);
+        let root = block_withdrawals_root(&block).unwrap().unwrap();
+        assert_eq!(header[header.len() - 32..], root.to_fixed_bytes());
+
+        // Blocks before Shanghai have no withdrawals
+        let block = Block::<Transaction>::default();
+        assert!(super::block_withdrawals(&block).unwrap().is_empty());
+        assert!(block_withdrawals_root(&block).unwrap().is_none());
+    }
+
+    #[test]
+    fn geth_data_serde() {
+        use crate::{
+            evm_types::{Gas, GasCost, Memory, OpcodeId, ProgramCounter, Stack, Storage},
+            GethExecStep,
+        };
+
+        let step = GethExecStep {
+            pc: ProgramCounter(3),
+            op: OpcodeId::SSTORE,
+            gas: Gas(21000),
+            gas_cost: GasCost(20000),
+            refund: Gas(0),
+            depth: 1,
+            error: None,
+            stack: Stack(vec![Word::from(0x20), Word::zero()]),
+            memory: Memory::from(vec![Word::from(0x40), Word::MAX]),
+            storage: Storage(HashMap::from([(Word::zero(), Word::from(0x20))])),
+        };
+        let geth_data = GethData {
+            chain_id: Word::from(1337),
+            history_hashes: vec![Word::from(0xff)],
+            eth_block: Block::default(),
+            geth_traces: vec![GethExecTrace {
+                gas: Gas(41000),
+                failed: false,
+                return_value: String::new(),
+                struct_logs: vec![step],
+            }],
+            accounts: vec![Account {
+                address: Address::from_low_u64_be(0xaa),
+                nonce: Word::one(),
+                balance: Word::from(100),
+                code: Bytes::from(vec![0x60, 0x00]),
+                storage: HashMap::from([(Word::one(), Word::from(2))]),
+            }],
+        };
+
+        let json = serde_json::to_string(&geth_data).unwrap();
+        let decoded: GethData = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.chain_id, geth_data.chain_id);
+        assert_eq!(decoded.history_hashes, geth_data.history_hashes);
+        assert_eq!(decoded.eth_block, geth_data.eth_block);
+        assert_eq!(decoded.geth_traces, geth_data.geth_traces);
+        assert_eq!(decoded.accounts, geth_data.accounts);
+    }
+}
@@ -237,7 +237,7 @@ impl<F: Field> ToScalar<F> for usize {
 }
 
 /// Struct used to define the storage proof
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StorageProof {
     /// Storage key
     pub key: U256,
@@ -248,7 +248,7 @@ pub struct StorageProof {
 }
 
 /// Struct used to define the result of `eth_getProof` call
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EIP1186ProofResponse {
     /// Account address
@@ -297,6 +297,7 @@ pub struct GethExecStep {
     pub pc: ProgramCounter,
     pub op: OpcodeId,
     pub gas: Gas,
+    #[serde(rename = "gasCost")]
     pub gas_cost: GasCost,
     pub refund: Gas,
     pub depth: u16,
@@ -0,0 +1,1179 @@
+//! EVM interpreter written in Rust, which traces the transactions of a
+//! [`TraceConfig`] like the Go library of `geth-utils` does: the transactions
+//! are applied with the rules of go-ethereum v1.10.18 along with the
+//! adjustments of `gethutil.Trace` for the later hardforks, and their steps
+//! are recorded like geth's `StructLogger` does, so that both give the same
+//! traces.  Cancun, which `gethutil.Trace` doesn't support, is only traced
+//! here, with the transient storage (EIP-1153), `MCOPY` (EIP-5656) and the
+//! `SELFDESTRUCT` (EIP-6780) rules.
+
+mod interpreter;
+mod logger;
+mod precompiles;
+mod state;
+
+use crate::TraceConfig;
+use eth_types::{
+    evm_types::{gas_utils::init_code_gas_cost, Gas, GasCost, Hardfork, MAX_INIT_CODE_SIZE},
+    geth_types::{Transaction, TxType},
+    Address, Error, GethExecTrace, ToWord, Word,
+};
+use ethers_core::utils::{get_contract_address, hex, to_checksum};
+use interpreter::{CallKind, Evm, Message};
+use state::State;
+
+/// Gas of each byte of call data that is zero
+const TX_DATA_ZERO_GAS: u64 = 4;
+/// Gas of each byte of call data that is not zero (EIP-2028)
+const TX_DATA_NON_ZERO_GAS: u64 = 16;
+
+/// Failure of a transaction before its execution
+enum TxError {
+    /// The transaction is invalid, and is kept in the block as a no-op.
+    Invalid,
+    /// The transaction can't be applied, which fails the whole trace.
+    Fatal(String),
+}
+
+/// Creates a trace for the specified config with the interpreter.
+pub(crate) fn trace(config: &TraceConfig) -> Result<Vec<GethExecTrace>, Error> {
+    apply_transactions(config)
+        .map_err(|err| Error::TracingError(format!("Failed to run Trace, err: {}", err)))
+}
+
+fn apply_transactions(config: &TraceConfig) -> Result<Vec<GethExecTrace>, String> {
+    let block_gas_limit = config.block_constants.gas_limit.low_u64();
+    let txs_gas_limit = config
+        .transactions
+        .iter()
+        .fold(0u64, |sum, tx| sum.wrapping_add(tx.gas_limit.low_u64()));
+    if txs_gas_limit > block_gas_limit {
+        return Err(format!(
+            "txs total gas: {} Exceeds block gas limit: {}",
+            txs_gas_limit, block_gas_limit
+        ));
+    }
+
+    // The empty accounts are dropped right away, like geth does
+    let mut state = State::new(&config.accounts);
+    state.finalise();
+
+    let mut traces = Vec::with_capacity(config.transactions.len());
+    for (i, tx) in config.transactions.iter().enumerate() {
+        let snapshot = state.snapshot();
+        let trace = match apply_transaction(config, &mut state, tx) {
+            Ok(trace) => trace,
+            Err(TxError::Invalid) => {
+                state.revert(snapshot);
+                GethExecTrace {
+                    gas: Gas(0),
+                    failed: true,
+                    return_value: String::new(),
+                    struct_logs: Vec::new(),
+                }
+            }
+            Err(TxError::Fatal(err)) => {
+                return Err(format!(
+                    "Failed to apply config.Transactions[{}]: {}",
+                    i, err
+                ))
+            }
+        };
+        traces.push(trace);
+    }
+    Ok(traces)
+}
+
+/// Apply `tx` to `state` like geth's `StateTransition` does, with a gas
+/// price used as tip cap, and as fee cap unless `tx` is an EIP-1559 one.
+fn apply_transaction(
+    config: &TraceConfig,
+    state: &mut State,
+    tx: &Transaction,
+) -> Result<GethExecTrace, TxError> {
+    let block = &config.block_constants;
+    let gas_price = tx.gas_price;
+    let mut gas_limit = tx.gas_limit.low_u64();
+    let gas_fee = |gas: u64| gas_price.checked_mul(Word::from(gas));
+    // Like geth's `buyGas`, the balance must cover the gas limit at the fee
+    // cap.
+    let gas_fee_cap = if tx.tx_type == TxType::Eip1559 {
+        tx.gas_fee_cap
+    } else {
+        gas_price
+    };
+    let max_gas_fee = |gas: u64| gas_fee_cap.checked_mul(Word::from(gas));
+
+    // The init code of a creation tx is limited in size and charged per word
+    // from Shanghai (EIP-3860), out of the gas limit of the message.
+    let mut init_code_gas = 0;
+    if config.hardfork >= Hardfork::Shanghai && tx.to.is_none() {
+        if tx.call_data.len() as u64 > MAX_INIT_CODE_SIZE {
+            return Err(TxError::Invalid);
+        }
+        init_code_gas = init_code_gas_cost(tx.call_data.len() as u64);
+        if gas_limit < init_code_gas {
+            return Err(TxError::Invalid);
+        }
+        let cost = max_gas_fee(gas_limit).and_then(|fee| fee.checked_add(tx.value));
+        if cost.map_or(true, |cost| state.balance(&tx.from) < cost) {
+            return Err(TxError::Invalid);
+        }
+        gas_limit -= init_code_gas;
+    }
+
+    let nonce = state.nonce(&tx.from);
+    if nonce != tx.nonce.low_u64() {
+        return Err(TxError::Invalid);
+    }
+    if !state.code(&tx.from).is_empty() {
+        return Err(TxError::Fatal(format!(
+            "sender not an eoa: address {}, codehash: {:?}",
+            to_checksum(&tx.from, None),
+            state.code_hash(&tx.from)
+        )));
+    }
+    if !gas_price.is_zero() && gas_price < block.base_fee {
+        return Err(TxError::Fatal(format!(
+            "max fee per gas less than block base fee: address {}, maxFeePerGas: {} baseFee: {}",
+            to_checksum(&tx.from, None),
+            gas_price,
+            block.base_fee
+        )));
+    }
+    // Buy the gas
+    let gas_cost = gas_fee(gas_limit);
+    let balance_check = max_gas_fee(gas_limit).and_then(|cost| cost.checked_add(tx.value));
+    match (gas_cost, balance_check) {
+        (Some(gas_cost), Some(balance_check)) if state.balance(&tx.from) >= balance_check => {
+            state.sub_balance(tx.from, gas_cost)
+        }
+        _ => return Err(TxError::Invalid),
+    }
+
+    let intrinsic_gas =
+        intrinsic_gas(tx).ok_or_else(|| TxError::Fatal("gas uint64 overflow".to_string()))?;
+    if gas_limit < intrinsic_gas {
+        return Err(TxError::Invalid);
+    }
+    let gas = gas_limit - intrinsic_gas;
+    if !tx.value.is_zero() && state.balance(&tx.from) < tx.value {
+        return Err(TxError::Invalid);
+    }
+
+    // Access list (EIP-2929, EIP-2930 and EIP-3651 from Shanghai)
+    state.warm_address(tx.from);
+    if let Some(to) = tx.to {
+        state.warm_address(to);
+    }
+    for precompile in 1..=9u64 {
+        state.warm_address(Address::from_low_u64_be(precompile));
+    }
+    for item in tx.access_list.iter().flat_map(|list| list.0.iter()) {
+        state.warm_address(item.address);
+        for key in item.storage_keys.iter() {
+            state.warm_slot(item.address, key.to_word());
+        }
+    }
+    if config.hardfork >= Hardfork::Shanghai {
+        state.warm_address(block.coinbase);
+    }
+
+    let msg = match tx.to {
+        None => Message::Create {
+            caller: tx.from,
+            address: get_contract_address(tx.from, nonce),
+            init_code: tx.call_data.clone(),
+            gas,
+            value: tx.value,
+        },
+        Some(to) => {
+            state.set_nonce(tx.from, nonce + 1);
+            Message::Call {
+                kind: CallKind::Call,
+                caller: tx.from,
+                address: to,
+                code_address: to,
+                value: tx.value,
+                input: tx.call_data.clone(),
+                gas,
+                is_static: false,
+            }
+        }
+    };
+    let (outcome, struct_logs) = Evm::new(config, state, tx.from, gas_price).execute(msg);
+
+    // Refund the gas left and pay the coinbase.  The refund is capped on the
+    // whole gas used, including the init code charge.
+    let gas_used = gas_limit + init_code_gas - outcome.gas;
+    let refund = (gas_used / config.hardfork.max_refund_quotient()).min(state.refund());
+    let gas_used = gas_used - refund;
+    // The gas bought excludes the init code charge, which is paid here.
+    state.add_balance(tx.from, gas_price * (gas_limit + init_code_gas - gas_used));
+    state.sub_balance(tx.from, gas_price * init_code_gas);
+    if !gas_price.is_zero() {
+        state.add_balance(block.coinbase, (gas_price - block.base_fee) * gas_used);
+    } else if init_code_gas > 0 {
+        // The tip is negative for a creation tx without gas price when there
+        // is a base fee, which geth takes from the coinbase on the gas it
+        // charges on top of the message for the init code.
+        let message_gas_used = gas_limit - outcome.gas;
+        let message_refund =
+            (message_gas_used / config.hardfork.max_refund_quotient()).min(state.refund());
+        let extra_gas = gas_used - (message_gas_used - message_refund);
+        state.sub_balance(
+            block.coinbase,
+            block.base_fee.saturating_mul(Word::from(extra_gas)),
+        );
+    }
+    state.finalise();
+
+    Ok(GethExecTrace {
+        gas: Gas(gas_used),
+        failed: outcome.error.is_some(),
+        return_value: hex::encode(&outcome.ret),
+        struct_logs,
+    })
+}
+
+/// Returns the gas charged before the execution of `tx`, or `None` if it
+/// doesn't fit in 64 bits.
+fn intrinsic_gas(tx: &Transaction) -> Option<u64> {
+    let mut gas = if tx.to.is_none() {
+        GasCost::CREATION_TX.as_u64()
+    } else {
+        GasCost::TX.as_u64()
+    };
+    for byte in tx.call_data.iter() {
+        gas = gas.checked_add(if *byte == 0 {
+            TX_DATA_ZERO_GAS
+        } else {
+            TX_DATA_NON_ZERO_GAS
+        })?;
+    }
+    for item in tx.access_list.iter().flat_map(|list| list.0.iter()) {
+        gas = gas.checked_add(GasCost::ACCESS_LIST_ADDRESS.as_u64())?;
+        gas = gas.checked_add(
+            (item.storage_keys.len() as u64)
+                .checked_mul(GasCost::ACCESS_LIST_STORAGE_KEY.as_u64())?,
+        )?;
+    }
+    Some(gas)
+}
+
+/// Returns `size` bytes of `data` from `start`, right padded with zeros.
+fn get_data(data: &[u8], start: u64, size: u64) -> Vec<u8> {
+    let len = data.len() as u64;
+    let start = start.min(len);
+    let end = start.saturating_add(size).min(len);
+    let mut bytes = data[start as usize..end as usize].to_vec();
+    bytes.resize(size as usize, 0);
+    bytes
+}
+
+#[cfg(all(test, feature = "geth"))]
+mod evm_tests {
+    use super::*;
+    use crate::{geth_trace, LoggerConfig};
+    use eth_types::{
+        bytecode,
+        evm_types::OpcodeId,
+        geth_types::{Account, BlockConstants, TxType},
+        Bytecode, Bytes, Hash, ToBigEndian, H256, U64,
+    };
+    use ethers_core::types::transaction::eip2930::{AccessList, AccessListItem};
+
+    fn sender() -> Address {
+        Address::from_low_u64_be(0xcafe111)
+    }
+
+    fn coinbase() -> Address {
+        Address::from_low_u64_be(0xc014ba5e)
+    }
+
+    /// Address of the contract called by the `i`-th transaction
+    fn contract(i: usize) -> Address {
+        Address::from_low_u64_be(0xc0de00 + i as u64)
+    }
+
+    /// Address of a contract called by the other contracts
+    fn callee(i: usize) -> Address {
+        Address::from_low_u64_be(0xca11ee00 + i as u64)
+    }
+
+    fn add_account(config: &mut TraceConfig, address: Address, balance: Word, code: Bytes) {
+        config.accounts.insert(
+            address,
+            Account {
+                address,
+                balance,
+                code,
+                ..Account::default()
+            },
+        );
+    }
+
+    fn push_tx(config: &mut TraceConfig, to: Option<Address>, call_data: Bytes) {
+        let nonce = config
+            .transactions
+            .iter()
+            .filter(|tx| tx.from == sender())
+            .count();
+        config.transactions.push(Transaction {
+            from: sender(),
+            to,
+            nonce: Word::from(nonce),
+            gas_limit: Word::from(1_000_000),
+            gas_price: Word::from(10),
+            call_data,
+            ..Transaction::default()
+        });
+    }
+
+    /// Returns a config with a transaction from a funded sender to a
+    /// contract holding each of `codes`.
+    fn trace_config(hardfork: Hardfork, codes: Vec<Bytecode>) -> TraceConfig {
+        let mut config = TraceConfig {
+            chain_id: Word::from(1337),
+            history_hashes: (0..4).map(|i| Word::from(0xb10c0 + i)).collect(),
+            block_constants: BlockConstants {
+                coinbase: coinbase(),
+                timestamp: Word::from(0x1234),
+                number: U64::from(0x10),
+                difficulty: Word::from(0x20000),
+                gas_limit: Word::from(30_000_000),
+                base_fee: Word::from(7),
+                mix_hash: Hash::repeat_byte(0x42),
+            },
+            logger_config: LoggerConfig::enable_memory(),
+            hardfork,
+            ..TraceConfig::default()
+        };
+        add_account(
+            &mut config,
+            sender(),
+            Word::from(10).pow(Word::from(20)),
+            Bytes::default(),
+        );
+        for (i, code) in codes.into_iter().enumerate() {
+            add_account(&mut config, contract(i), Word::from(1000), code.into());
+            push_tx(&mut config, Some(contract(i)), Bytes::default());
+        }
+        config
+    }
+
+    /// Store `bytes` in memory at `offset` with `MSTORE`s of 32 bytes.
+    fn mstore_bytes(code: &mut Bytecode, offset: usize, bytes: &[u8]) {
+        for (i, chunk) in bytes.chunks(32).enumerate() {
+            let mut word = [0u8; 32];
+            word[..chunk.len()].copy_from_slice(chunk);
+            code.append(&bytecode! {
+                PUSH32(Word::from_big_endian(&word))
+                PUSH32(offset + 32 * i)
+                MSTORE
+            });
+        }
+    }
+
+    /// Call `address` with 100000 gas, `value` and the first `in_size` bytes
+    /// of memory as input.
+    fn call(
+        code: &mut Bytecode,
+        address: Address,
+        value: u64,
+        in_size: u64,
+        out_offset: u64,
+        out_size: u64,
+    ) {
+        code.append(&bytecode! {
+            PUSH32(out_size)
+            PUSH32(out_offset)
+            PUSH32(in_size)
+            PUSH1(0)
+            PUSH32(value)
+            PUSH32(address.to_word())
+            PUSH32(100_000)
+            CALL
+        });
+    }
+
+    /// Call `address` with `input` stored in memory, and copy its output
+    /// to memory.
+    fn call_with_input(code: &mut Bytecode, address: Address, input: &[u8], gas: u64) {
+        mstore_bytes(code, 0, input);
+        code.append(&bytecode! {
+            PUSH1(0x80)
+            PUSH2(0x400)
+            PUSH32(input.len())
+            PUSH1(0)
+            PUSH1(0)
+            PUSH32(address.to_word())
+            PUSH32(gas)
+            CALL
+            RETURNDATASIZE
+        });
+    }
+
+    fn assert_same_traces(config: &TraceConfig) {
+        match (geth_trace(config), trace(config)) {
+            (Ok(expected), Ok(traces)) => {
+                assert_eq!(traces.len(), expected.len());
+                for (i, (trace, expected)) in traces.iter().zip(expected.iter()).enumerate() {
+                    for (step, expected_step) in trace.struct_logs.iter().zip(&expected.struct_logs)
+                    {
+                        assert_eq!(step, expected_step, "tx {}", i);
+                    }
+                    assert_eq!(trace, expected, "tx {}", i);
+                }
+            }
+            (Err(Error::TracingError(expected)), Err(Error::TracingError(err))) => {
+                assert_eq!(err, expected)
+            }
+            (expected, result) => panic!("geth: {:?}, interpreter: {:?}", expected, result),
+        }
+    }
+
+    #[test]
+    fn arithmetic_ops() {
+        let min = Word::one() << 255;
+        let values = [
+            Word::zero(),
+            Word::one(),
+            Word::from(7),
+            Word::from(31),
+            Word::from(255),
+            Word::from(256),
+            Word::from(u64::MAX),
+            min,
+            min + 1,
+            Word::MAX,
+        ];
+        let mut code = Bytecode::default();
+        for op in [
+            OpcodeId::ADD,
+            OpcodeId::MUL,
+            OpcodeId::SUB,
+            OpcodeId::DIV,
+            OpcodeId::SDIV,
+            OpcodeId::MOD,
+            OpcodeId::SMOD,
+            OpcodeId::EXP,
+            OpcodeId::SIGNEXTEND,
+            OpcodeId::LT,
+            OpcodeId::GT,
+            OpcodeId::SLT,
+            OpcodeId::SGT,
+            OpcodeId::EQ,
+            OpcodeId::AND,
+            OpcodeId::OR,
+            OpcodeId::XOR,
+            OpcodeId::BYTE,
+            OpcodeId::SHL,
+            OpcodeId::SHR,
+            OpcodeId::SAR,
+        ] {
+            for a in values {
+                for b in values {
+                    code.push(32, b)
+                        .push(32, a)
+                        .write_op(op)
+                        .write_op(OpcodeId::POP);
+                }
+            }
+        }
+        for op in [OpcodeId::ADDMOD, OpcodeId::MULMOD] {
+            for a in values {
+                for n in values {
+                    code.push(32, n)
+                        .push(32, Word::MAX - 2)
+                        .push(32, a)
+                        .write_op(op)
+                        .write_op(OpcodeId::POP);
+                }
+            }
+        }
+        for a in values {
+            code.push(32, a)
+                .write_op(OpcodeId::ISZERO)
+                .write_op(OpcodeId::NOT)
+                .write_op(OpcodeId::POP);
+        }
+        let mut config = trace_config(Hardfork::London, vec![code]);
+        config.logger_config = LoggerConfig::default();
+        config.transactions[0].gas_limit = Word::from(5_000_000);
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn stack_and_control_flow() {
+        let mut code = Bytecode::default();
+        for i in 1..=16u64 {
+            code.push(1, Word::from(i));
+        }
+        for i in 0..16 {
+            code.write_op(OpcodeId::from(OpcodeId::DUP1.as_u8() + i))
+                .write_op(OpcodeId::from(OpcodeId::SWAP1.as_u8() + i));
+        }
+        // A jump taken, another one not taken, and a truncated PUSH2
+        let jumps = Bytecode::from_raw_unchecked(vec![
+            0x60, 0x00, 0x60, 0x0a, 0x57, 0x60, 0x01, 0x60, 0x0b, 0x57, 0x00, 0x5b, 0x58, 0x60,
+            0x11, 0x56, 0x00, 0x5b, 0x61, 0x01,
+        ]);
+        let config = trace_config(Hardfork::London, vec![code, jumps]);
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn memory_and_data_ops() {
+        let code = bytecode! {
+            CALLDATASIZE
+            PUSH1(0)
+            PUSH1(0x20)
+            CALLDATACOPY
+            PUSH1(0x45)
+            CALLDATALOAD
+            PUSH32(Word::from(u64::MAX) + 1)
+            CALLDATALOAD
+            PUSH1(0xff)
+            PUSH1(0x3f)
+            MSTORE8
+            PUSH32(Word::MAX - 0x1234)
+            PUSH2(0x100)
+            MSTORE
+            PUSH2(0x101)
+            MLOAD
+            PUSH1(0x40)
+            PUSH1(0x10)
+            SHA3
+            PUSH1(0)
+            PUSH1(0)
+            SHA3
+            PUSH1(0x30)
+            PUSH1(2)
+            PUSH2(0x200)
+            CODECOPY
+            PUSH1(0x40)
+            PUSH2(0x1000)
+            PUSH2(0x240)
+            CODECOPY
+            PUSH1(0)
+            PUSH32(Word::MAX)
+            PUSH32(Word::MAX)
+            CODECOPY
+            MSIZE
+            PUSH1(0x20)
+            PUSH1(0x10)
+            RETURN
+        };
+        let mut config = trace_config(Hardfork::London, vec![code]);
+        config.transactions[0].call_data = (0..0x50u8).collect::<Vec<_>>().into();
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn storage_ops() {
+        let code = bytecode! {
+            // Slot set at the beginning of the tx
+            PUSH1(0)
+            PUSH1(1)
+            SSTORE
+            PUSH1(1)
+            PUSH1(1)
+            SSTORE
+            PUSH1(2)
+            PUSH1(1)
+            SSTORE
+            PUSH1(0)
+            PUSH1(1)
+            SSTORE
+            // Slot empty at the beginning of the tx
+            PUSH1(1)
+            PUSH1(5)
+            SSTORE
+            PUSH1(2)
+            PUSH1(5)
+            SSTORE
+            PUSH1(0)
+            PUSH1(5)
+            SSTORE
+            // No-op writes, reads of cold and warm slots
+            PUSH1(3)
+            PUSH1(3)
+            SSTORE
+            PUSH1(2)
+            SLOAD
+            PUSH1(2)
+            SLOAD
+            PUSH1(9)
+            SLOAD
+            PUSH1(7)
+            PUSH1(2)
+            SSTORE
+        };
+        let mut config = trace_config(Hardfork::London, vec![code]);
+        config
+            .accounts
+            .get_mut(&contract(0))
+            .unwrap()
+            .storage
+            .extend([(1, 1), (2, 2), (3, 3), (4, 0)].map(|(k, v)| (Word::from(k), Word::from(v))));
+        // The second tx starts from the storage written by the first one,
+        // and the third one has access lists.
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        config.transactions[2].tx_type = TxType::Eip2930;
+        config.transactions[2].access_list = Some(AccessList(vec![
+            AccessListItem {
+                address: contract(0),
+                storage_keys: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(9)],
+            },
+            AccessListItem {
+                address: callee(0),
+                storage_keys: vec![],
+            },
+        ]));
+        // Not enough gas left for SSTORE
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        config.transactions[3].gas_limit = Word::from(21_000 + 2_300 + 6);
+        config.logger_config.disable_stack = true;
+        assert_same_traces(&config);
+
+        config.logger_config = LoggerConfig {
+            disable_storage: true,
+            ..LoggerConfig::default()
+        };
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn call_ops() {
+        let returner = bytecode! {
+            PUSH1(0x2a)
+            PUSH1(0)
+            SSTORE
+            CALLER
+            PUSH1(0)
+            MSTORE
+            CALLVALUE
+            PUSH1(0x20)
+            MSTORE
+            ADDRESS
+            PUSH1(0x40)
+            MSTORE
+            CALLDATASIZE
+            PUSH1(0x60)
+            MSTORE
+            PUSH1(0x80)
+            PUSH1(0)
+            RETURN
+        };
+        let reverter = bytecode! {
+            PUSH1(0xaa)
+            PUSH1(0)
+            MSTORE
+            PUSH1(0x20)
+            PUSH1(0)
+            REVERT
+        };
+        let mut code = Bytecode::default();
+        call(&mut code, callee(0), 5, 0x10, 0x100, 0x80);
+        code.append(&bytecode! {
+            RETURNDATASIZE
+            PUSH1(0x20)
+            PUSH1(0x10)
+            PUSH2(0x200)
+            RETURNDATACOPY
+            PUSH1(0x80)
+            PUSH2(0x300)
+            PUSH1(4)
+            PUSH1(0)
+            PUSH1(7)
+            PUSH32(callee(0).to_word())
+            GAS
+            CALLCODE
+        });
+        for op in [OpcodeId::DELEGATECALL, OpcodeId::STATICCALL] {
+            code.append(&bytecode! {
+                PUSH1(0x80)
+                PUSH2(0x300)
+                PUSH1(4)
+                PUSH1(0)
+                PUSH32(callee(0).to_word())
+                PUSH3(50_000)
+            });
+            code.write_op(op);
+        }
+        call(&mut code, callee(1), 0, 0, 0x400, 0x20);
+        // Calls to empty accounts and to a precompile, with and without value,
+        // and with a value above the balance
+        call(&mut code, callee(9), 1, 0, 0, 0);
+        call(&mut code, callee(8), 0, 0, 0, 0);
+        call(&mut code, Address::from_low_u64_be(4), 0, 0x20, 0x500, 0x20);
+        call(&mut code, callee(7), 1_000_000, 0, 0, 0);
+        // Returndata copied out of bounds
+        code.append(&bytecode! {
+            PUSH1(1)
+            PUSH1(0)
+            PUSH1(0)
+            RETURNDATACOPY
+        });
+        let mut config = trace_config(Hardfork::London, vec![code]);
+        add_account(&mut config, callee(0), Word::zero(), returner.into());
+        add_account(&mut config, callee(1), Word::zero(), reverter.into());
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn nested_calls() {
+        // Each callee calls the next one with all its gas, until the depth
+        // limit or the gas runs out
+        let recursive = bytecode! {
+            PUSH1(0)
+            SLOAD
+            PUSH1(1)
+            ADD
+            PUSH1(0)
+            SSTORE
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            PUSH1(0)
+            ADDRESS
+            GAS
+            CALL
+        };
+        let mut config = trace_config(Hardfork::London, vec![recursive]);
+        config.logger_config = LoggerConfig::default();
+        config.transactions[0].gas_limit = Word::from(10_000_000);
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn precompiles() {
+        let mut ecrecover_input = Vec::new();
+        ecrecover_input.extend_from_slice(
+            &hex::decode("456e9aea5e197a1f1af7a3e85a3212fa4049a3ba34c2289b4c860fc0b0c64ef3")
+                .unwrap(),
+        );
+        ecrecover_input.extend_from_slice(&Word::from(28).to_be_bytes());
+        ecrecover_input.extend_from_slice(&hex::decode(
+            "9242685bf161793cc25603c231bc2f568eb630ea16aa137d2664ac80388256084f8ae3bd7535248d0bd448298cc2e2071e56992d0774dc340c368ae950852ada",
+        ).unwrap());
+        let mut bad_ecrecover_input = ecrecover_input.clone();
+        bad_ecrecover_input[63] = 29;
+
+        let modexp_input = [
+            Word::from(1).to_be_bytes().to_vec(),
+            Word::from(32).to_be_bytes().to_vec(),
+            Word::from(32).to_be_bytes().to_vec(),
+            vec![3],
+            (Word::MAX - 0x1000).to_be_bytes().to_vec(),
+            (Word::MAX - 0x3000).to_be_bytes().to_vec(),
+        ]
+        .concat();
+
+        // Generators of G1 and G2 and their pairing with the negated G1
+        let g1 = [Word::from(1), Word::from(2)]
+            .iter()
+            .flat_map(|w| w.to_be_bytes())
+            .collect::<Vec<_>>();
+        let g1_neg = [
+            Word::from(1),
+            Word::from_dec_str(
+                "21888242871839275222246405745257275088696311157297823662689037894645226208581",
+            )
+            .unwrap(),
+        ]
+        .iter()
+        .flat_map(|w| w.to_be_bytes())
+        .collect::<Vec<_>>();
+        let g2 = [
+            "11559732032986387107991004021392285783925812861821192530917403151452391805634",
+            "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+            "4082367875863433681332203403145435568316851327593401208105741076214120093531",
+            "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+        ]
+        .iter()
+        .flat_map(|n| Word::from_dec_str(n).unwrap().to_be_bytes())
+        .collect::<Vec<_>>();
+        let pairing_input = [g1.clone(), g2.clone(), g1_neg, g2].concat();
+        let not_on_curve = [Word::from(1), Word::from(3)]
+            .iter()
+            .flat_map(|w| w.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        let mut blake2f_input = vec![0, 0, 0, 12];
+        blake2f_input.extend((0..208).map(|i| i as u8));
+        blake2f_input.push(1);
+        let mut bad_blake2f_input = blake2f_input.clone();
+        bad_blake2f_input[212] = 2;
+
+        let calls: Vec<(u64, Vec<u8>, u64)> = vec![
+            (1, ecrecover_input, 100_000),
+            (1, bad_ecrecover_input, 100_000),
+            (1, vec![], 2_000),
+            (2, b"abc".to_vec(), 100_000),
+            (2, vec![0; 100], 70),
+            (3, b"abc".to_vec(), 100_000),
+            (4, (0..100).collect(), 100_000),
+            (5, modexp_input.clone(), 100_000),
+            (5, modexp_input[..100].to_vec(), 100_000),
+            (5, modexp_input, 100),
+            (6, [g1.clone(), g1.clone()].concat(), 100_000),
+            (6, [g1.clone(), not_on_curve.clone()].concat(), 100_000),
+            (
+                7,
+                [g1.clone(), Word::from(3).to_be_bytes().to_vec()].concat(),
+                100_000,
+            ),
+            (7, [not_on_curve, vec![0; 32]].concat(), 100_000),
+            (8, pairing_input.clone(), 200_000),
+            (8, pairing_input[..192].to_vec(), 200_000),
+            (8, pairing_input[..100].to_vec(), 200_000),
+            (8, vec![], 200_000),
+            (9, blake2f_input.clone(), 100_000),
+            (9, bad_blake2f_input, 100_000),
+            (9, blake2f_input[1..].to_vec(), 100_000),
+        ];
+        let codes = calls
+            .into_iter()
+            .map(|(precompile, input, gas)| {
+                let mut code = Bytecode::default();
+                call_with_input(&mut code, Address::from_low_u64_be(precompile), &input, gas);
+                code
+            })
+            .collect();
+        let config = trace_config(Hardfork::London, codes);
+        assert_same_traces(&config);
+    }
+
+    /// Returns init code returning `size` bytes of memory whose first byte is
+    /// `first_byte`, and then reverting if `revert` is set.
+    fn init_code(first_byte: u8, size: u64, revert: bool) -> Vec<u8> {
+        let mut code = bytecode! {
+            PUSH1(first_byte)
+            PUSH1(0)
+            MSTORE8
+            PUSH32(size)
+            PUSH1(0)
+        };
+        code.write_op(if revert {
+            OpcodeId::REVERT
+        } else {
+            OpcodeId::RETURN
+        });
+        code.to_vec()
+    }
+
+    fn create(code: &mut Bytecode, init_code: &[u8], value: u64, salt: Option<u64>) {
+        mstore_bytes(code, 0, init_code);
+        if let Some(salt) = salt {
+            code.push(32, Word::from(salt));
+        }
+        code.push(32, Word::from(init_code.len()))
+            .push(1, Word::zero())
+            .push(32, Word::from(value))
+            .write_op(if salt.is_some() {
+                OpcodeId::CREATE2
+            } else {
+                OpcodeId::CREATE
+            })
+            .write_op(OpcodeId::DUP1)
+            .write_op(OpcodeId::EXTCODESIZE)
+            .write_op(OpcodeId::RETURNDATASIZE);
+    }
+
+    #[test]
+    fn create_ops() {
+        let mut code = Bytecode::default();
+        create(&mut code, &init_code(0x60, 0x20, false), 1, None);
+        create(&mut code, &init_code(0x60, 0x20, false), 0, Some(1));
+        // Address collision
+        create(&mut code, &init_code(0x60, 0x20, false), 0, Some(1));
+        create(&mut code, &init_code(0x60, 0x20, true), 0, None);
+        // Code starting with 0xEF (EIP-3541) and too large (EIP-170)
+        create(&mut code, &init_code(0xef, 0x20, false), 0, None);
+        create(&mut code, &init_code(0x60, 0x6001, false), 0, None);
+        // Failing init code and insufficient balance
+        create(&mut code, &[0xfe], 0, Some(2));
+        create(&mut code, &init_code(0x60, 0x20, false), 1_000_000, None);
+        let mut config = trace_config(Hardfork::London, vec![code]);
+        config.transactions[0].gas_limit = Word::from(10_000_000);
+        // Not enough gas to store the code
+        let mut code = Bytecode::default();
+        create(&mut code, &init_code(0x60, 1000, false), 0, None);
+        add_account(&mut config, contract(1), Word::zero(), code.into());
+        push_tx(&mut config, Some(contract(1)), Bytes::default());
+        config.transactions[1].gas_limit = Word::from(150_000);
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn creation_txs() {
+        for hardfork in [Hardfork::London, Hardfork::Shanghai] {
+            let mut config = trace_config(hardfork, vec![]);
+            push_tx(&mut config, None, init_code(0x60, 0x20, false).into());
+            push_tx(&mut config, None, init_code(0x60, 0x20, true).into());
+            push_tx(&mut config, None, vec![0xfe].into());
+            push_tx(&mut config, None, init_code(0xef, 0x20, false).into());
+            // Creation tx without gas price, with init code too large from
+            // Shanghai, and with too little gas for its init code
+            push_tx(&mut config, None, vec![0; 0x200].into());
+            config.transactions[4].gas_price = Word::zero();
+            push_tx(&mut config, None, vec![0; 0xc001].into());
+            config.transactions[5].gas_limit = Word::from(2_000_000);
+            push_tx(&mut config, None, vec![1; 0x100].into());
+            config.transactions[6].gas_limit = Word::from(53_000 + 0x100 * 16);
+            // Init code with a refund above the cap
+            let refund = bytecode! {
+                PUSH1(1)
+                PUSH1(0)
+                SSTORE
+                PUSH1(0)
+                PUSH1(0)
+                SSTORE
+            };
+            push_tx(&mut config, None, refund.to_vec().into());
+            assert_same_traces(&config);
+        }
+    }
+
+    #[test]
+    fn create_init_code_rules() {
+        // go-ethereum doesn't apply EIP-3860 to CREATE and CREATE2, so the
+        // Shanghai costs are checked against the London ones.
+        let init_code = init_code(0x60, 0x20, false);
+        let mut code = Bytecode::default();
+        create(&mut code, &init_code, 0, None);
+        create(&mut code, &init_code, 0, Some(1));
+        let create_costs = |hardfork| {
+            let traces = trace(&trace_config(hardfork, vec![code.clone()])).unwrap();
+            traces[0]
+                .struct_logs
+                .iter()
+                .filter(|step| matches!(step.op, OpcodeId::CREATE | OpcodeId::CREATE2))
+                .map(|step| step.gas_cost.0)
+                .collect::<Vec<_>>()
+        };
+        let init_code_gas =
+            (init_code.len() as u64 + 31) / 32 * GasCost::INIT_CODE_WORD_COST.as_u64();
+        let london_costs = create_costs(Hardfork::London);
+        assert_eq!(london_costs.len(), 2);
+        for (cost, london_cost) in create_costs(Hardfork::Shanghai).iter().zip(london_costs) {
+            assert_eq!(*cost, london_cost + init_code_gas);
+        }
+
+        // Init code above the size limit
+        let code = bytecode! {
+            PUSH32(MAX_INIT_CODE_SIZE + 1)
+            PUSH1(0)
+            PUSH1(0)
+            CREATE
+        };
+        let traces = trace(&trace_config(Hardfork::London, vec![code.clone()])).unwrap();
+        assert!(!traces[0].failed);
+        let traces = trace(&trace_config(Hardfork::Shanghai, vec![code])).unwrap();
+        assert!(traces[0].failed);
+        let step = traces[0].struct_logs.last().unwrap();
+        assert_eq!(step.op, OpcodeId::CREATE);
+        assert_eq!(step.error.as_deref(), Some("out of gas"));
+    }
+
+    #[test]
+    fn cancun_ops() {
+        // geth doesn't support Cancun, so the traces are checked directly
+        let tstore = bytecode! {
+            PUSH1(0)
+            TLOAD
+            PUSH1(0x6f)
+            PUSH1(0)
+            TSTORE
+            PUSH1(0)
+            TLOAD
+            STOP
+        };
+        let mcopy = bytecode! {
+            PUSH32(Word::MAX - 1)
+            PUSH1(0)
+            MSTORE
+            PUSH1(32)
+            PUSH1(0)
+            PUSH1(1)
+            MCOPY
+            PUSH1(1)
+            MLOAD
+            STOP
+        };
+        let mut config = trace_config(Hardfork::Cancun, vec![tstore, mcopy]);
+        // The transient storage is cleared at the end of each tx
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        let traces = trace(&config).unwrap();
+        for trace in [&traces[0], &traces[2]] {
+            let stack = &trace.struct_logs.last().unwrap().stack;
+            assert_eq!(stack.0, vec![Word::zero(), Word::from(0x6f)]);
+        }
+        let mcopy = &traces[1].struct_logs[6];
+        assert_eq!(mcopy.op, OpcodeId::MCOPY);
+        // Copy of one word expanding the memory by one word
+        assert_eq!(mcopy.gas_cost.0, 3 + 3 + 3);
+        let stack = &traces[1].struct_logs.last().unwrap().stack;
+        assert_eq!(stack.0, vec![Word::MAX - 1]);
+    }
+
+    #[test]
+    fn self_destruct() {
+        let code = bytecode! {
+            PUSH32(callee(0).to_word())
+            SELFDESTRUCT
+        };
+        let suicide = bytecode! {
+            ADDRESS
+            SELFDESTRUCT
+        };
+        let mut config = trace_config(Hardfork::London, vec![code, suicide]);
+        // The contracts are gone in the next txs
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        let check = bytecode! {
+            PUSH32(contract(0).to_word())
+            EXTCODESIZE
+            PUSH32(contract(1).to_word())
+            BALANCE
+            PUSH32(callee(0).to_word())
+            BALANCE
+        };
+        add_account(&mut config, contract(2), Word::zero(), check.into());
+        push_tx(&mut config, Some(contract(2)), Bytes::default());
+        assert_same_traces(&config);
+    }
+
+    /// Returns code running the opcodes reading the environment.
+    fn env_code() -> Bytecode {
+        let mut code = bytecode! {
+            ADDRESS
+            BALANCE
+            ORIGIN
+            CALLER
+            CALLVALUE
+            CALLDATASIZE
+            CODESIZE
+            GASPRICE
+            COINBASE
+            BALANCE
+            TIMESTAMP
+            NUMBER
+            DIFFICULTY
+            GASLIMIT
+            CHAINID
+            SELFBALANCE
+            BASEFEE
+            PC
+            GAS
+            MSIZE
+        };
+        for address in [sender(), callee(0), callee(9), contract(0)] {
+            code.append(&bytecode! {
+                PUSH32(address.to_word())
+                EXTCODESIZE
+                PUSH32(address.to_word())
+                EXTCODEHASH
+                PUSH32(address.to_word())
+                BALANCE
+                PUSH1(0x40)
+                PUSH1(0)
+                PUSH1(0)
+                PUSH32(address.to_word())
+                EXTCODECOPY
+            });
+        }
+        for number in [0x10, 0xf, 0xc, 0x11, u64::MAX] {
+            code.push(32, Word::from(number))
+                .write_op(OpcodeId::BLOCKHASH);
+        }
+        code
+    }
+
+    #[test]
+    fn env_ops() {
+        for hardfork in [Hardfork::London, Hardfork::Paris, Hardfork::Shanghai] {
+            let mut config = trace_config(hardfork, vec![env_code()]);
+            add_account(
+                &mut config,
+                callee(0),
+                Word::from(3),
+                bytecode! { STOP }.into(),
+            );
+            config.transactions[0].value = Word::from(77);
+            config.transactions[0].call_data = vec![1, 2, 3].into();
+            assert_same_traces(&config);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut overflow = Bytecode::default();
+        for _ in 0..=1024 {
+            overflow.push(1, Word::one());
+        }
+        let codes = vec![
+            bytecode! { ADD },
+            overflow,
+            bytecode! { PUSH32(Word::MAX) MLOAD },
+            bytecode! { PUSH4(0xffffffffu64) MLOAD },
+            bytecode! { PUSH1(3) JUMP },
+            bytecode! { PUSH1(0x5b) PUSH1(1) JUMP },
+            Bytecode::from_raw_unchecked(vec![0x0c]),
+            Bytecode::from_raw_unchecked(vec![0x60, 0x01, 0xfe]),
+            Bytecode::from_raw_unchecked(vec![0x5f, 0x5c]),
+            bytecode! { PUSH1(1) PUSH1(0) PUSH1(0) RETURNDATACOPY },
+            bytecode! { JUMPDEST PUSH1(0) JUMP },
+        ];
+        for hardfork in [Hardfork::London, Hardfork::Shanghai] {
+            let mut config = trace_config(hardfork, codes.clone());
+            config.transactions[10].gas_limit = Word::from(21_000 + 100);
+            assert_same_traces(&config);
+        }
+    }
+
+    #[test]
+    fn invalid_txs() {
+        let mut config = trace_config(Hardfork::London, vec![bytecode! { STOP }; 2]);
+        // Wrong nonce, gas limit below the intrinsic gas, too expensive gas
+        // and too large value
+        config.transactions[1].nonce = Word::from(5);
+        push_tx(&mut config, Some(contract(0)), vec![1; 100].into());
+        config.transactions[2].gas_limit = Word::from(21_100);
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        config.transactions[3].gas_price = Word::MAX;
+        push_tx(&mut config, Some(contract(0)), Bytes::default());
+        config.transactions[4].value = Word::from(10).pow(Word::from(20));
+        // Value transfers to an account and without gas price
+        push_tx(&mut config, Some(callee(0)), Bytes::default());
+        config.transactions[5].value = Word::from(12345);
+        config.transactions[5].gas_price = Word::zero();
+        for tx in config.transactions[2..].iter_mut() {
+            tx.nonce = Word::one();
+        }
+        assert_same_traces(&config);
+    }
+
+    #[test]
+    fn failing_configs() {
+        // Sender with code
+        let mut config = trace_config(Hardfork::London, vec![bytecode! { STOP }]);
+        config.accounts.get_mut(&sender()).unwrap().code = vec![0].into();
+        assert_same_traces(&config);
+        // Gas price below the base fee
+        let mut config = trace_config(Hardfork::London, vec![bytecode! { STOP }]);
+        config.transactions[0].gas_price = Word::from(5);
+        assert_same_traces(&config);
+        // Gas limits above the gas limit of the block
+        let mut config = trace_config(Hardfork::London, vec![bytecode! { STOP }; 2]);
+        config.transactions[1].gas_limit = Word::from(29_500_000);
+        assert_same_traces(&config);
+        // Cancun is only supported by the interpreter
+        let config = trace_config(Hardfork::Cancun, vec![bytecode! { STOP }]);
+        assert!(geth_trace(&config).is_err());
+        assert!(trace(&config).is_ok());
+    }
+}
@@ -0,0 +1,1364 @@
+//! Interpreter executing the calls and contract creations of a transaction.
+//! The frames of the nested calls are kept in a stack instead of recursing,
+//! so that the depth of the calls is only limited by the gas.
+
+use super::{get_data, logger::StructLogger, precompiles, state::State};
+use crate::TraceConfig;
+use eth_types::{
+    evm_types::{
+        gas_utils::eip150_gas, GasCost, OpcodeId, GAS_STIPEND_CALL_WITH_VALUE, MAX_CODE_SIZE,
+        MAX_INIT_CODE_SIZE,
+    },
+    Address, Bytes, GethExecStep, ToAddress, ToBigEndian, ToWord, Word, U512,
+};
+use ethers_core::utils::{get_contract_address, get_create2_address, keccak256};
+use std::fmt;
+
+/// Maximum depth of the calls
+const CALL_CREATE_DEPTH: usize = 1024;
+/// Maximum number of items in the stack
+const STACK_LIMIT: usize = 1024;
+/// Gas below which `SSTORE` fails (EIP-2200)
+const SSTORE_SENTRY_GAS: u64 = 2300;
+/// Largest memory size whose expansion gas fits in 64 bits
+const MAX_MEMORY_SIZE: u64 = 0x1FFFFFFFE0;
+
+/// Error stopping the execution of a frame, displayed like in geth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum VmError {
+    OutOfGas,
+    GasUintOverflow,
+    StackUnderflow { len: usize, required: usize },
+    StackOverflow { len: usize, limit: usize },
+    InvalidOpcode(OpcodeId),
+    InvalidJump,
+    WriteProtection,
+    ReturnDataOutOfBounds,
+    ExecutionReverted,
+    Depth,
+    InsufficientBalance,
+    ContractAddressCollision,
+    MaxCodeSizeExceeded,
+    InvalidCode,
+    CodeStoreOutOfGas,
+    Precompile(&'static str),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfGas => write!(f, "out of gas"),
+            Self::GasUintOverflow => write!(f, "gas uint64 overflow"),
+            Self::StackUnderflow { len, required } => {
+                write!(f, "stack underflow ({} <=> {})", len, required)
+            }
+            Self::StackOverflow { len, limit } => {
+                write!(f, "stack limit reached {} ({})", len, limit)
+            }
+            Self::InvalidOpcode(op) => write!(f, "invalid opcode: {}", geth_opcode_name(op)),
+            Self::InvalidJump => write!(f, "invalid jump destination"),
+            Self::WriteProtection => write!(f, "write protection"),
+            Self::ReturnDataOutOfBounds => write!(f, "return data out of bounds"),
+            Self::ExecutionReverted => write!(f, "execution reverted"),
+            Self::Depth => write!(f, "max call depth exceeded"),
+            Self::InsufficientBalance => write!(f, "insufficient balance for transfer"),
+            Self::ContractAddressCollision => write!(f, "contract address collision"),
+            Self::MaxCodeSizeExceeded => write!(f, "max code size exceeded"),
+            Self::InvalidCode => write!(f, "invalid code: must not begin with 0xef"),
+            Self::CodeStoreOutOfGas => write!(f, "contract creation code storage out of gas"),
+            Self::Precompile(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Returns the name geth gives to an undefined opcode.
+fn geth_opcode_name(op: &OpcodeId) -> String {
+    match op.as_u8() {
+        0xfe => "INVALID".to_string(),
+        byte => format!("opcode {:#x} not defined", byte),
+    }
+}
+
+/// Kind of message call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+}
+
+/// Message starting a frame
+#[derive(Debug)]
+pub(super) enum Message {
+    Call {
+        kind: CallKind,
+        caller: Address,
+        /// Address of the account whose storage and balance are used
+        address: Address,
+        /// Address of the account whose code is run
+        code_address: Address,
+        value: Word,
+        input: Bytes,
+        gas: u64,
+        is_static: bool,
+    },
+    Create {
+        caller: Address,
+        address: Address,
+        init_code: Bytes,
+        gas: u64,
+        value: Word,
+    },
+}
+
+/// Result of a message
+#[derive(Debug)]
+pub(super) struct Outcome {
+    pub(super) ret: Vec<u8>,
+    /// Gas left, returned to the caller
+    pub(super) gas: u64,
+    pub(super) error: Option<VmError>,
+}
+
+impl Outcome {
+    fn failure(gas: u64, error: VmError) -> Self {
+        Self {
+            ret: Vec::new(),
+            gas,
+            error: Some(error),
+        }
+    }
+}
+
+/// Call or creation made by a frame, waiting for its result.
+#[derive(Debug)]
+enum Pending {
+    Call { ret_offset: Word, ret_size: Word },
+    Create { address: Address },
+}
+
+/// Execution context of the code of a call or contract creation
+#[derive(Debug)]
+pub(super) struct Frame {
+    is_create: bool,
+    caller: Address,
+    pub(super) address: Address,
+    value: Word,
+    input: Bytes,
+    code: Bytes,
+    jumpdests: Vec<bool>,
+    pub(super) pc: usize,
+    gas: u64,
+    pub(super) stack: Vec<Word>,
+    pub(super) memory: Vec<u8>,
+    /// Gas paid so far for the expansion of the memory
+    memory_gas: u64,
+    return_data: Vec<u8>,
+    read_only: bool,
+    /// Depth of the frame, starting at 1
+    pub(super) depth: usize,
+    /// Snapshot of the state to revert to if the frame fails
+    snapshot: usize,
+    pending: Option<Pending>,
+}
+
+impl Frame {
+    fn pop(&mut self) -> Word {
+        self.stack.pop().expect("stack validated before execution")
+    }
+
+    fn back(&self, n: usize) -> Word {
+        self.stack[self.stack.len() - 1 - n]
+    }
+
+    fn push(&mut self, value: Word) {
+        self.stack.push(value);
+    }
+
+    /// Returns the part of the memory, which was already expanded to cover
+    /// it, at `offset` with `size`.
+    fn memory_slice(&self, offset: Word, size: Word) -> &[u8] {
+        if size.is_zero() {
+            return &[];
+        }
+        let offset = offset.low_u64() as usize;
+        &self.memory[offset..offset + size.low_u64() as usize]
+    }
+
+    /// Write `data` to the memory at `offset`, which was already expanded to
+    /// cover `size` bytes.
+    fn memory_set(&mut self, offset: Word, size: u64, data: &[u8]) {
+        if size == 0 {
+            return;
+        }
+        let offset = offset.low_u64() as usize;
+        self.memory[offset..offset + size as usize].copy_from_slice(&data[..size as usize]);
+    }
+}
+
+/// Returns a map of the positions of `code` holding a `JUMPDEST`, which
+/// excludes the data of the `PUSH`s.
+fn analyse_jumpdests(code: &[u8]) -> Vec<bool> {
+    let mut jumpdests = vec![false; code.len()];
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = OpcodeId::from(code[pc]);
+        if op == OpcodeId::JUMPDEST {
+            jumpdests[pc] = true;
+        }
+        pc += 1 + op.data_len();
+    }
+    jumpdests
+}
+
+/// Result of entering a message
+enum Enter {
+    Frame(Box<Frame>),
+    Done(Outcome),
+}
+
+/// Reason for the interpreter loop to stop
+enum Exit {
+    /// The frame makes a call or a creation
+    Call(Message),
+    /// The frame is done, with its return data and error if any
+    Halt(Vec<u8>, Option<VmError>),
+}
+
+/// Effect of an executed opcode on the control flow
+enum Control {
+    Continue,
+    Jump(usize),
+    Call(Message),
+    Stop(Vec<u8>),
+    Revert(Vec<u8>),
+}
+
+/// EVM running the message of a transaction on a [`State`]
+pub(super) struct Evm<'a> {
+    config: &'a TraceConfig,
+    state: &'a mut State,
+    origin: Address,
+    gas_price: Word,
+    logger: StructLogger,
+    /// Gas passed to the callee, computed along with the gas cost of the call
+    call_gas_temp: u64,
+}
+
+impl<'a> Evm<'a> {
+    pub(super) fn new(
+        config: &'a TraceConfig,
+        state: &'a mut State,
+        origin: Address,
+        gas_price: Word,
+    ) -> Self {
+        Self {
+            config,
+            state,
+            origin,
+            gas_price,
+            logger: StructLogger::new(&config.logger_config),
+            call_gas_temp: 0,
+        }
+    }
+
+    /// Execute `msg` along with the messages it makes, and return its outcome
+    /// with the steps of the execution.
+    pub(super) fn execute(mut self, msg: Message) -> (Outcome, Vec<GethExecStep>) {
+        let mut frames: Vec<Box<Frame>> = Vec::new();
+        let mut next = self.enter(msg, 0);
+        let outcome = loop {
+            let frame = match next {
+                Enter::Frame(frame) => {
+                    frames.push(frame);
+                    frames.last_mut().expect("frame just pushed")
+                }
+                Enter::Done(outcome) => match frames.last_mut() {
+                    Some(caller) => {
+                        Self::resume(caller, outcome);
+                        caller
+                    }
+                    None => break outcome,
+                },
+            };
+            next = match self.run(frame) {
+                Exit::Call(msg) => {
+                    let depth = frames.len();
+                    self.enter(msg, depth)
+                }
+                Exit::Halt(ret, error) => {
+                    let frame = frames.pop().expect("running frame");
+                    Enter::Done(self.exit(*frame, ret, error))
+                }
+            };
+        };
+        (outcome, self.logger.into_steps())
+    }
+
+    /// Start a message made at `depth`, which either runs code in a new frame
+    /// or is done right away.
+    fn enter(&mut self, msg: Message, depth: usize) -> Enter {
+        match msg {
+            Message::Call {
+                kind,
+                caller,
+                address,
+                code_address,
+                value,
+                input,
+                gas,
+                is_static,
+            } => {
+                if depth > CALL_CREATE_DEPTH {
+                    return Enter::Done(Outcome::failure(gas, VmError::Depth));
+                }
+                if matches!(kind, CallKind::Call | CallKind::CallCode)
+                    && self.state.balance(&caller) < value
+                {
+                    return Enter::Done(Outcome::failure(gas, VmError::InsufficientBalance));
+                }
+                let snapshot = self.state.snapshot();
+                if kind == CallKind::Call {
+                    self.state.transfer(caller, address, value);
+                }
+                if precompiles::is_precompile(&code_address) {
+                    return Enter::Done(match precompiles::run(&code_address, &input, gas) {
+                        Ok((ret, gas)) => Outcome {
+                            ret,
+                            gas,
+                            error: None,
+                        },
+                        Err(error) => {
+                            self.state.revert(snapshot);
+                            Outcome::failure(0, error)
+                        }
+                    });
+                }
+                let code = self.state.code(&code_address);
+                if code.is_empty() {
+                    return Enter::Done(Outcome {
+                        ret: Vec::new(),
+                        gas,
+                        error: None,
+                    });
+                }
+                Enter::Frame(self.new_frame(
+                    false,
+                    caller,
+                    address,
+                    value,
+                    input,
+                    code,
+                    gas,
+                    is_static || kind == CallKind::StaticCall,
+                    depth,
+                    snapshot,
+                ))
+            }
+            Message::Create {
+                caller,
+                address,
+                init_code,
+                gas,
+                value,
+            } => {
+                if depth > CALL_CREATE_DEPTH {
+                    return Enter::Done(Outcome::failure(gas, VmError::Depth));
+                }
+                if self.state.balance(&caller) < value {
+                    return Enter::Done(Outcome::failure(gas, VmError::InsufficientBalance));
+                }
+                let nonce = self.state.nonce(&caller);
+                self.state.set_nonce(caller, nonce + 1);
+                // The address stays warm even if the creation fails
+                self.state.warm_address(address);
+                if self.state.nonce(&address) != 0 || !self.state.code(&address).is_empty() {
+                    return Enter::Done(Outcome::failure(0, VmError::ContractAddressCollision));
+                }
+                let snapshot = self.state.snapshot();
+                self.state.create_account(address);
+                self.state.set_nonce(address, 1);
+                self.state.transfer(caller, address, value);
+                let frame = self.new_frame(
+                    true,
+                    caller,
+                    address,
+                    value,
+                    Bytes::default(),
+                    init_code,
+                    gas,
+                    false,
+                    depth,
+                    snapshot,
+                );
+                if frame.code.is_empty() {
+                    return Enter::Done(self.exit(*frame, Vec::new(), None));
+                }
+                Enter::Frame(frame)
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_frame(
+        &self,
+        is_create: bool,
+        caller: Address,
+        address: Address,
+        value: Word,
+        input: Bytes,
+        code: Bytes,
+        gas: u64,
+        read_only: bool,
+        depth: usize,
+        snapshot: usize,
+    ) -> Box<Frame> {
+        Box::new(Frame {
+            is_create,
+            caller,
+            address,
+            value,
+            input,
+            jumpdests: analyse_jumpdests(&code),
+            code,
+            pc: 0,
+            gas,
+            stack: Vec::new(),
+            memory: Vec::new(),
+            memory_gas: 0,
+            return_data: Vec::new(),
+            read_only,
+            depth: depth + 1,
+            snapshot,
+            pending: None,
+        })
+    }
+
+    /// End `frame` with its return data and error, and return the outcome
+    /// of its message.
+    fn exit(&mut self, frame: Frame, ret: Vec<u8>, mut error: Option<VmError>) -> Outcome {
+        let mut gas = frame.gas;
+        if frame.is_create && error.is_none() {
+            let deposit_gas = ret.len() as u64 * GasCost::CODE_DEPOSIT_BYTE_COST.as_u64();
+            if ret.len() as u64 > MAX_CODE_SIZE {
+                error = Some(VmError::MaxCodeSizeExceeded);
+            } else if ret.first() == Some(&0xef) {
+                error = Some(VmError::InvalidCode);
+            } else if gas < deposit_gas {
+                error = Some(VmError::CodeStoreOutOfGas);
+            } else {
+                gas -= deposit_gas;
+                self.state.set_code(frame.address, Bytes::from(ret.clone()));
+            }
+        }
+        if let Some(err) = &error {
+            self.state.revert(frame.snapshot);
+            if *err != VmError::ExecutionReverted {
+                gas = 0;
+            }
+        }
+        Outcome { ret, gas, error }
+    }
+
+    /// Resume `frame` with the outcome of the message it made.
+    fn resume(frame: &mut Frame, outcome: Outcome) {
+        let success = outcome.error.is_none();
+        let reverted = outcome.error == Some(VmError::ExecutionReverted);
+        match frame.pending.take().expect("frame waiting for a message") {
+            Pending::Call {
+                ret_offset,
+                ret_size,
+            } => {
+                frame.push(Word::from(success as u64));
+                if success || reverted {
+                    let size = (outcome.ret.len() as u64).min(ret_size.low_u64());
+                    frame.memory_set(ret_offset, size, &outcome.ret);
+                }
+                frame.return_data = outcome.ret;
+            }
+            Pending::Create { address } => {
+                frame.push(if success {
+                    address.to_word()
+                } else {
+                    Word::zero()
+                });
+                frame.return_data = if reverted { outcome.ret } else { Vec::new() };
+            }
+        }
+        frame.gas += outcome.gas;
+        frame.pc += 1;
+    }
+
+    /// Run `frame` until it halts or makes a message.
+    fn run(&mut self, frame: &mut Frame) -> Exit {
+        loop {
+            let pc = frame.pc;
+            let op = OpcodeId::from(frame.code.get(pc).copied().unwrap_or_default());
+            let gas = frame.gas;
+            let mut cost = 0;
+            let result = self.step(frame, op, &mut cost);
+            let control = match result {
+                Ok(control) => control,
+                Err(StepError::BeforeLog(error)) => {
+                    // The steps failing before their execution are logged with
+                    // their error
+                    self.logger
+                        .capture_state(self.state, frame, op, gas, cost, Some(&error));
+                    return Exit::Halt(Vec::new(), Some(error));
+                }
+                Err(StepError::Execution(error)) => return Exit::Halt(Vec::new(), Some(error)),
+            };
+            match control {
+                Control::Continue => frame.pc += op.data_len() + 1,
+                Control::Jump(dest) => frame.pc = dest,
+                Control::Call(msg) => return Exit::Call(msg),
+                Control::Stop(ret) => return Exit::Halt(ret, None),
+                Control::Revert(ret) => return Exit::Halt(ret, Some(VmError::ExecutionReverted)),
+            }
+        }
+    }
+
+    /// Returns `true` if `op` is defined under the rules of the hardfork.
+    fn is_defined(&self, op: OpcodeId) -> bool {
+        !matches!(op, OpcodeId::INVALID(_)) && op.is_available(self.config.hardfork)
+    }
+
+    /// Charge the gas of `op`, log it and execute it, with the gas charged
+    /// so far in `cost`.
+    fn step(
+        &mut self,
+        frame: &mut Frame,
+        op: OpcodeId,
+        cost: &mut u64,
+    ) -> Result<Control, StepError> {
+        let gas = frame.gas;
+        if !self.is_defined(op) {
+            self.logger
+                .capture_state(self.state, frame, op, gas, 0, None);
+            return Err(StepError::Execution(VmError::InvalidOpcode(op)));
+        }
+        let (min_sp, max_sp) = op.valid_stack_ptr_range();
+        let (min_len, max_len) = (STACK_LIMIT - max_sp as usize, STACK_LIMIT - min_sp as usize);
+        let len = frame.stack.len();
+        if len < min_len {
+            return Err(StepError::BeforeLog(VmError::StackUnderflow {
+                len,
+                required: min_len,
+            }));
+        }
+        if len > max_len {
+            return Err(StepError::BeforeLog(VmError::StackOverflow {
+                len,
+                limit: max_len,
+            }));
+        }
+        *cost = constant_gas(op);
+        if frame.gas < *cost {
+            return Err(StepError::BeforeLog(VmError::OutOfGas));
+        }
+        frame.gas -= *cost;
+        let mut memory_size = 0;
+        if has_dynamic_gas(op) {
+            memory_size = memory_size_of(op, frame).map_err(StepError::BeforeLog)?;
+            let dynamic_cost = self
+                .dynamic_gas(frame, op, memory_size)
+                .map_err(|_| StepError::BeforeLog(VmError::OutOfGas))?;
+            *cost += dynamic_cost;
+            if frame.gas < dynamic_cost {
+                return Err(StepError::BeforeLog(VmError::OutOfGas));
+            }
+            frame.gas -= dynamic_cost;
+        }
+        self.logger
+            .capture_state(self.state, frame, op, gas, *cost, None);
+        if memory_size as usize > frame.memory.len() {
+            frame.memory.resize(memory_size as usize, 0);
+        }
+        self.execute_op(frame, op).map_err(StepError::Execution)
+    }
+
+    /// Returns the gas of `op` that depends on its operands and the state,
+    /// besides its constant gas.
+    fn dynamic_gas(
+        &mut self,
+        frame: &mut Frame,
+        op: OpcodeId,
+        memory_size: u64,
+    ) -> Result<u64, VmError> {
+        use OpcodeId::*;
+        let gas = match op {
+            SHA3 => {
+                let words_gas = words_gas(frame.back(1), GasCost::COPY_SHA3)?;
+                safe_add(memory_gas(frame, memory_size)?, words_gas)?
+            }
+            EXP => {
+                let exponent_bytes = (frame.back(1).bits() as u64 + 7) / 8;
+                10 + 50 * exponent_bytes
+            }
+            BALANCE | EXTCODESIZE | EXTCODEHASH => {
+                self.account_access_gas(frame.back(0).to_address())
+            }
+            CALLDATACOPY | CODECOPY | RETURNDATACOPY | MCOPY => {
+                let words_gas = words_gas(frame.back(2), GasCost::COPY)?;
+                safe_add(memory_gas(frame, memory_size)?, words_gas)?
+            }
+            EXTCODECOPY => {
+                let words_gas = words_gas(frame.back(3), GasCost::COPY)?;
+                let gas = safe_add(memory_gas(frame, memory_size)?, words_gas)?;
+                safe_add(gas, self.account_access_gas(frame.back(0).to_address()))?
+            }
+            MLOAD | MSTORE | MSTORE8 | RETURN | REVERT => memory_gas(frame, memory_size)?,
+            CREATE | CREATE2 => {
+                let size = frame.back(2);
+                let mut gas = memory_gas(frame, memory_size)?;
+                if op == CREATE2 {
+                    gas = safe_add(gas, words_gas(size, GasCost::COPY_SHA3)?)?;
+                }
+                // From Shanghai, the init code is limited in size and charged
+                // per word (EIP-3860).  Like in geth, a too large init code
+                // fails as out of gas.
+                if self.config.hardfork.is_eip_enabled(3860) {
+                    if size > Word::from(MAX_INIT_CODE_SIZE) {
+                        return Err(VmError::GasUintOverflow);
+                    }
+                    gas = safe_add(gas, words_gas(size, GasCost::INIT_CODE_WORD_COST)?)?;
+                }
+                gas
+            }
+            SLOAD => {
+                let key = frame.back(0);
+                if self.state.is_warm_slot(&frame.address, &key) {
+                    GasCost::WARM_ACCESS.as_u64()
+                } else {
+                    self.state.warm_slot(frame.address, key);
+                    GasCost::COLD_SLOAD.as_u64()
+                }
+            }
+            SSTORE => self.sstore_gas(frame)?,
+            LOG0 | LOG1 | LOG2 | LOG3 | LOG4 => {
+                let size = frame.back(1);
+                if size.bits() > 64 {
+                    return Err(VmError::GasUintOverflow);
+                }
+                let topics = op.postfix().expect("LOG has a postfix") as u64;
+                let gas = safe_add(memory_gas(frame, memory_size)?, GasCost::LOG.as_u64())?;
+                let gas = safe_add(gas, topics * GasCost::LOG.as_u64())?;
+                let data_gas = size
+                    .low_u64()
+                    .checked_mul(8)
+                    .ok_or(VmError::GasUintOverflow)?;
+                safe_add(gas, data_gas)?
+            }
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => self.call_gas(frame, op, memory_size)?,
+            SELFDESTRUCT => {
+                let beneficiary = frame.back(0).to_address();
+                let mut gas = 0;
+                if !self.state.is_warm_address(&beneficiary) {
+                    self.state.warm_address(beneficiary);
+                    gas += GasCost::COLD_ACCOUNT_ACCESS.as_u64();
+                }
+                if self.state.is_empty(&beneficiary)
+                    && !self.state.balance(&frame.address).is_zero()
+                {
+                    gas += GasCost::NEW_ACCOUNT.as_u64();
+                }
+                gas
+            }
+            _ => 0,
+        };
+        Ok(gas)
+    }
+
+    /// Returns the extra gas of an access to `address` if it's cold, and
+    /// warms it (EIP-2929).
+    fn account_access_gas(&mut self, address: Address) -> u64 {
+        if self.state.is_warm_address(&address) {
+            return 0;
+        }
+        self.state.warm_address(address);
+        GasCost::COLD_ACCOUNT_ACCESS.as_u64() - GasCost::WARM_ACCESS.as_u64()
+    }
+
+    /// Returns the gas of `SSTORE` and updates the refund counter (EIP-2200
+    /// with EIP-2929 and EIP-3529).
+    fn sstore_gas(&mut self, frame: &Frame) -> Result<u64, VmError> {
+        if frame.gas <= SSTORE_SENTRY_GAS {
+            return Err(VmError::OutOfGas);
+        }
+        let (key, value) = (frame.back(0), frame.back(1));
+        let address = frame.address;
+        let mut cold_gas = 0;
+        if !self.state.is_warm_slot(&address, &key) {
+            self.state.warm_slot(address, key);
+            cold_gas = GasCost::COLD_SLOAD.as_u64();
+        }
+        let current = self.state.storage(&address, &key);
+        if current == value {
+            return Ok(cold_gas + GasCost::WARM_ACCESS.as_u64());
+        }
+        let original = self.state.committed_storage(&address, &key);
+        let clears_schedule = self.config.hardfork.sstore_clears_schedule().as_u64();
+        if original == current {
+            if original.is_zero() {
+                return Ok(cold_gas + GasCost::SSTORE_SET.as_u64());
+            }
+            if value.is_zero() {
+                self.state.add_refund(clears_schedule);
+            }
+            return Ok(cold_gas + GasCost::SSTORE_RESET.as_u64());
+        }
+        if !original.is_zero() {
+            if current.is_zero() {
+                self.state.sub_refund(clears_schedule);
+            } else if value.is_zero() {
+                self.state.add_refund(clears_schedule);
+            }
+        }
+        if original == value {
+            let refund = if original.is_zero() {
+                GasCost::SSTORE_SET.as_u64()
+            } else {
+                GasCost::SSTORE_RESET.as_u64()
+            };
+            self.state
+                .add_refund(refund - GasCost::WARM_ACCESS.as_u64());
+        }
+        Ok(cold_gas + GasCost::WARM_ACCESS.as_u64())
+    }
+
+    /// Returns the gas of a call, including the gas passed to the callee
+    /// which is kept in `call_gas_temp`.
+    fn call_gas(
+        &mut self,
+        frame: &mut Frame,
+        op: OpcodeId,
+        memory_size: u64,
+    ) -> Result<u64, VmError> {
+        let address = frame.back(1).to_address();
+        let mut available_gas = frame.gas;
+        let mut cold_gas = 0;
+        if !self.state.is_warm_address(&address) {
+            self.state.warm_address(address);
+            cold_gas = GasCost::COLD_ACCOUNT_ACCESS.as_u64() - GasCost::WARM_ACCESS.as_u64();
+            if available_gas < cold_gas {
+                return Err(VmError::OutOfGas);
+            }
+            available_gas -= cold_gas;
+        }
+        let transfers_value =
+            matches!(op, OpcodeId::CALL | OpcodeId::CALLCODE) && !frame.back(2).is_zero();
+        let mut gas = 0;
+        if op == OpcodeId::CALL && transfers_value && self.state.is_empty(&address) {
+            gas += GasCost::NEW_ACCOUNT.as_u64();
+        }
+        if transfers_value {
+            gas += GasCost::CALL_WITH_VALUE.as_u64();
+        }
+        gas = safe_add(gas, memory_gas(frame, memory_size)?)?;
+        self.call_gas_temp = eip150_gas(available_gas.wrapping_sub(gas), frame.back(0));
+        gas = safe_add(gas, self.call_gas_temp)?;
+        Ok(gas + cold_gas)
+    }
+
+    /// Execute `op`, whose gas is already charged.
+    fn execute_op(&mut self, frame: &mut Frame, op: OpcodeId) -> Result<Control, VmError> {
+        use OpcodeId::*;
+        let config = self.config;
+        let block = &config.block_constants;
+        match op {
+            STOP => return Ok(Control::Stop(Vec::new())),
+            ADD => binary_op(frame, |a, b| a.overflowing_add(b).0),
+            MUL => binary_op(frame, |a, b| a.overflowing_mul(b).0),
+            SUB => binary_op(frame, |a, b| a.overflowing_sub(b).0),
+            DIV => binary_op(frame, |a, b| a.checked_div(b).unwrap_or_default()),
+            SDIV => binary_op(frame, sdiv),
+            MOD => binary_op(frame, |a, b| a.checked_rem(b).unwrap_or_default()),
+            SMOD => binary_op(frame, smod),
+            ADDMOD => {
+                let (a, b, n) = (frame.pop(), frame.pop(), frame.pop());
+                frame.push(if n.is_zero() {
+                    Word::zero()
+                } else {
+                    u512_low((U512::from(a) + U512::from(b)) % U512::from(n))
+                });
+            }
+            MULMOD => {
+                let (a, b, n) = (frame.pop(), frame.pop(), frame.pop());
+                frame.push(if n.is_zero() {
+                    Word::zero()
+                } else {
+                    u512_low(a.full_mul(b) % U512::from(n))
+                });
+            }
+            EXP => binary_op(frame, |base, exponent| base.overflowing_pow(exponent).0),
+            SIGNEXTEND => binary_op(frame, signextend),
+            LT => binary_op(frame, |a, b| (a < b).to_word()),
+            GT => binary_op(frame, |a, b| (a > b).to_word()),
+            SLT => binary_op(frame, |a, b| (a ^ sign_bit() < b ^ sign_bit()).to_word()),
+            SGT => binary_op(frame, |a, b| (a ^ sign_bit() > b ^ sign_bit()).to_word()),
+            EQ => binary_op(frame, |a, b| (a == b).to_word()),
+            ISZERO => {
+                let a = frame.pop();
+                frame.push(a.is_zero().to_word());
+            }
+            AND => binary_op(frame, |a, b| a & b),
+            OR => binary_op(frame, |a, b| a | b),
+            XOR => binary_op(frame, |a, b| a ^ b),
+            NOT => {
+                let a = frame.pop();
+                frame.push(!a);
+            }
+            BYTE => binary_op(frame, |index, value| {
+                if index < Word::from(32) {
+                    Word::from(value.byte(31 - index.as_usize()))
+                } else {
+                    Word::zero()
+                }
+            }),
+            SHL => binary_op(frame, |shift, value| {
+                if shift < Word::from(256) {
+                    value << shift.as_usize()
+                } else {
+                    Word::zero()
+                }
+            }),
+            SHR => binary_op(frame, |shift, value| {
+                if shift < Word::from(256) {
+                    value >> shift.as_usize()
+                } else {
+                    Word::zero()
+                }
+            }),
+            SAR => binary_op(frame, sar),
+            SHA3 => {
+                let (offset, size) = (frame.pop(), frame.pop());
+                let hash = keccak256(frame.memory_slice(offset, size));
+                frame.push(Word::from_big_endian(&hash));
+            }
+            ADDRESS => frame.push(frame.address.to_word()),
+            BALANCE => {
+                let address = frame.pop().to_address();
+                frame.push(self.state.balance(&address));
+            }
+            ORIGIN => frame.push(self.origin.to_word()),
+            CALLER => frame.push(frame.caller.to_word()),
+            CALLVALUE => frame.push(frame.value),
+            CALLDATALOAD => {
+                let offset = frame.pop();
+                frame.push(if offset.bits() > 64 {
+                    Word::zero()
+                } else {
+                    Word::from_big_endian(&get_data(&frame.input, offset.low_u64(), 32))
+                });
+            }
+            CALLDATASIZE => frame.push(Word::from(frame.input.len())),
+            CALLDATACOPY => {
+                let (memory_offset, data_offset, size) = (frame.pop(), frame.pop(), frame.pop());
+                let data = get_data(&frame.input, saturating_u64(data_offset), size.low_u64());
+                frame.memory_set(memory_offset, size.low_u64(), &data);
+            }
+            CODESIZE => frame.push(Word::from(frame.code.len())),
+            CODECOPY => {
+                let (memory_offset, code_offset, size) = (frame.pop(), frame.pop(), frame.pop());
+                let data = get_data(&frame.code, saturating_u64(code_offset), size.low_u64());
+                frame.memory_set(memory_offset, size.low_u64(), &data);
+            }
+            GASPRICE => frame.push(self.gas_price),
+            EXTCODESIZE => {
+                let address = frame.pop().to_address();
+                frame.push(Word::from(self.state.code(&address).len()));
+            }
+            EXTCODECOPY => {
+                let address = frame.pop().to_address();
+                let (memory_offset, code_offset, size) = (frame.pop(), frame.pop(), frame.pop());
+                let code = self.state.code(&address);
+                let data = get_data(&code, saturating_u64(code_offset), size.low_u64());
+                frame.memory_set(memory_offset, size.low_u64(), &data);
+            }
+            RETURNDATASIZE => frame.push(Word::from(frame.return_data.len())),
+            RETURNDATACOPY => {
+                let (memory_offset, data_offset, size) = (frame.pop(), frame.pop(), frame.pop());
+                if data_offset.bits() > 64 {
+                    return Err(VmError::ReturnDataOutOfBounds);
+                }
+                let end = data_offset.overflowing_add(size).0;
+                if end.bits() > 64 || (frame.return_data.len() as u64) < end.low_u64() {
+                    return Err(VmError::ReturnDataOutOfBounds);
+                }
+                let data = frame.return_data[data_offset.as_usize()..end.as_usize()].to_vec();
+                frame.memory_set(memory_offset, size.low_u64(), &data);
+            }
+            EXTCODEHASH => {
+                let address = frame.pop().to_address();
+                frame.push(if self.state.is_empty(&address) {
+                    Word::zero()
+                } else {
+                    self.state.code_hash(&address).to_word()
+                });
+            }
+            BLOCKHASH => {
+                let number = frame.pop();
+                let upper = block.number.as_u64();
+                let lower = upper.saturating_sub(256);
+                frame.push(
+                    if number.bits() <= 64 && (lower..upper).contains(&number.low_u64()) {
+                        self.block_hash(number.low_u64())
+                    } else {
+                        Word::zero()
+                    },
+                );
+            }
+            COINBASE => frame.push(block.coinbase.to_word()),
+            TIMESTAMP => frame.push(block.timestamp),
+            NUMBER => frame.push(Word::from(block.number.as_u64())),
+            DIFFICULTY => frame.push(if config.hardfork.is_post_merge() {
+                block.mix_hash.to_word()
+            } else {
+                block.difficulty
+            }),
+            GASLIMIT => frame.push(Word::from(block.gas_limit.low_u64())),
+            CHAINID => frame.push(config.chain_id),
+            SELFBALANCE => frame.push(self.state.balance(&frame.address)),
+            BASEFEE => frame.push(block.base_fee),
+            POP => {
+                frame.pop();
+            }
+            MLOAD => {
+                let offset = frame.pop();
+                let value = Word::from_big_endian(frame.memory_slice(offset, Word::from(32)));
+                frame.push(value);
+            }
+            MSTORE => {
+                let (offset, value) = (frame.pop(), frame.pop());
+                let mut bytes = [0; 32];
+                value.to_big_endian(&mut bytes);
+                frame.memory_set(offset, 32, &bytes);
+            }
+            MSTORE8 => {
+                let (offset, value) = (frame.pop(), frame.pop());
+                frame.memory_set(offset, 1, &[value.low_u64() as u8]);
+            }
+            SLOAD => {
+                let key = frame.pop();
+                frame.push(self.state.storage(&frame.address, &key));
+            }
+            SSTORE => {
+                if frame.read_only {
+                    return Err(VmError::WriteProtection);
+                }
+                let (key, value) = (frame.pop(), frame.pop());
+                self.state.set_storage(frame.address, key, value);
+            }
+            TLOAD => {
+                let key = frame.pop();
+                frame.push(self.state.transient_storage(&frame.address, &key));
+            }
+            TSTORE => {
+                if frame.read_only {
+                    return Err(VmError::WriteProtection);
+                }
+                let (key, value) = (frame.pop(), frame.pop());
+                self.state.set_transient_storage(frame.address, key, value);
+            }
+            JUMP => {
+                let dest = frame.pop();
+                return Ok(Control::Jump(valid_jump(frame, dest)?));
+            }
+            JUMPI => {
+                let (dest, condition) = (frame.pop(), frame.pop());
+                if !condition.is_zero() {
+                    return Ok(Control::Jump(valid_jump(frame, dest)?));
+                }
+            }
+            PC => frame.push(Word::from(frame.pc)),
+            MSIZE => frame.push(Word::from(frame.memory.len())),
+            GAS => frame.push(Word::from(frame.gas)),
+            JUMPDEST => {}
+            PUSH0 => frame.push(Word::zero()),
+            MCOPY => {
+                let (dest_offset, offset, size) = (frame.pop(), frame.pop(), frame.pop());
+                let data = frame.memory_slice(offset, size).to_vec();
+                frame.memory_set(dest_offset, size.low_u64(), &data);
+            }
+            LOG0 | LOG1 | LOG2 | LOG3 | LOG4 => {
+                if frame.read_only {
+                    return Err(VmError::WriteProtection);
+                }
+                let topics = op.postfix().expect("LOG has a postfix") as usize;
+                for _ in 0..2 + topics {
+                    frame.pop();
+                }
+            }
+            CREATE | CREATE2 => {
+                if frame.read_only {
+                    return Err(VmError::WriteProtection);
+                }
+                let (value, offset, size) = (frame.pop(), frame.pop(), frame.pop());
+                let init_code = frame.memory_slice(offset, size).to_vec();
+                let address = if op == CREATE {
+                    get_contract_address(frame.address, self.state.nonce(&frame.address))
+                } else {
+                    let salt = frame.pop();
+                    get_create2_address(
+                        frame.address,
+                        salt.to_be_bytes().to_vec(),
+                        init_code.clone(),
+                    )
+                };
+                // All but one 64th of the gas is passed to the creation (EIP-150)
+                let gas = frame.gas - frame.gas / 64;
+                frame.gas -= gas;
+                frame.pending = Some(Pending::Create { address });
+                return Ok(Control::Call(Message::Create {
+                    caller: frame.address,
+                    address,
+                    init_code: Bytes::from(init_code),
+                    gas,
+                    value,
+                }));
+            }
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => {
+                frame.pop();
+                let code_address = frame.pop().to_address();
+                let value = if matches!(op, CALL | CALLCODE) {
+                    frame.pop()
+                } else {
+                    Word::zero()
+                };
+                let (args_offset, args_size) = (frame.pop(), frame.pop());
+                let (ret_offset, ret_size) = (frame.pop(), frame.pop());
+                if op == CALL && frame.read_only && !value.is_zero() {
+                    return Err(VmError::WriteProtection);
+                }
+                let mut gas = self.call_gas_temp;
+                if !value.is_zero() {
+                    gas += GAS_STIPEND_CALL_WITH_VALUE;
+                }
+                let input = Bytes::from(frame.memory_slice(args_offset, args_size).to_vec());
+                let (kind, caller, address, value) = match op {
+                    CALL => (CallKind::Call, frame.address, code_address, value),
+                    CALLCODE => (CallKind::CallCode, frame.address, frame.address, value),
+                    DELEGATECALL => (
+                        CallKind::DelegateCall,
+                        frame.caller,
+                        frame.address,
+                        frame.value,
+                    ),
+                    _ => (CallKind::StaticCall, frame.address, code_address, value),
+                };
+                frame.pending = Some(Pending::Call {
+                    ret_offset,
+                    ret_size,
+                });
+                return Ok(Control::Call(Message::Call {
+                    kind,
+                    caller,
+                    address,
+                    code_address,
+                    value,
+                    input,
+                    gas,
+                    is_static: frame.read_only,
+                }));
+            }
+            RETURN | REVERT => {
+                let (offset, size) = (frame.pop(), frame.pop());
+                let ret = frame.memory_slice(offset, size).to_vec();
+                return Ok(if op == RETURN {
+                    Control::Stop(ret)
+                } else {
+                    Control::Revert(ret)
+                });
+            }
+            SELFDESTRUCT => {
+                if frame.read_only {
+                    return Err(VmError::WriteProtection);
+                }
+                let beneficiary = frame.pop().to_address();
+                let balance = self.state.balance(&frame.address);
+                // From Cancun, an account which isn't created in the same
+                // transaction only sends its balance (EIP-6780).
+                if config.hardfork.is_selfdestruct_restricted()
+                    && !self.state.is_created(&frame.address)
+                {
+                    self.state.transfer(frame.address, beneficiary, balance);
+                } else {
+                    self.state.add_balance(beneficiary, balance);
+                    self.state.self_destruct(frame.address);
+                }
+                return Ok(Control::Stop(Vec::new()));
+            }
+            op if op.is_push() => {
+                let start = frame.pc + 1;
+                let data = get_data(&frame.code, start as u64, op.data_len() as u64);
+                frame.push(Word::from_big_endian(&data));
+            }
+            op if op.is_dup() => {
+                let n = op.postfix().expect("DUP has a postfix") as usize;
+                frame.push(frame.back(n - 1));
+            }
+            op if op.is_swap() => {
+                let n = op.postfix().expect("SWAP has a postfix") as usize;
+                let top = frame.stack.len() - 1;
+                frame.stack.swap(top, top - n);
+            }
+            op => return Err(VmError::InvalidOpcode(op)),
+        }
+        Ok(Control::Continue)
+    }
+
+    /// Returns the hash of the block `number`, taken from the history
+    /// hashes, or zero if it's not there.
+    fn block_hash(&self, number: u64) -> Word {
+        let current = self.config.block_constants.number.as_u64();
+        let history = &self.config.history_hashes;
+        if current > number && current - number <= 256 {
+            let back = (current - number) as usize;
+            if back <= history.len() {
+                return history[history.len() - back];
+            }
+        }
+        Word::zero()
+    }
+}
+
+/// Error of a step, which is logged unless it happens during the execution
+/// of the opcode.
+enum StepError {
+    BeforeLog(VmError),
+    Execution(VmError),
+}
+
+/// Returns the constant gas of `op`, which doesn't include the base cost of
+/// `EXP` that geth charges as part of its dynamic gas.
+fn constant_gas(op: OpcodeId) -> u64 {
+    match op {
+        OpcodeId::EXP => 0,
+        op => op.constant_gas_cost().as_u64(),
+    }
+}
+
+/// Returns `true` if the gas of `op` depends on its operands or the state.
+fn has_dynamic_gas(op: OpcodeId) -> bool {
+    use OpcodeId::*;
+    matches!(
+        op,
+        SHA3 | EXP
+            | BALANCE
+            | EXTCODESIZE
+            | EXTCODEHASH
+            | CALLDATACOPY
+            | CODECOPY
+            | EXTCODECOPY
+            | RETURNDATACOPY
+            | MCOPY
+            | MLOAD
+            | MSTORE
+            | MSTORE8
+            | SLOAD
+            | SSTORE
+            | LOG0
+            | LOG1
+            | LOG2
+            | LOG3
+            | LOG4
+            | CREATE
+            | CREATE2
+            | CALL
+            | CALLCODE
+            | DELEGATECALL
+            | STATICCALL
+            | RETURN
+            | REVERT
+            | SELFDESTRUCT
+    )
+}
+
+/// Returns the size, rounded up to words, the memory needs to be expanded to
+/// for `op`.
+fn memory_size_of(op: OpcodeId, frame: &Frame) -> Result<u64, VmError> {
+    use OpcodeId::*;
+    let access = |offset: usize, size: usize| memory_access(frame.back(offset), frame.back(size));
+    let size = match op {
+        SHA3 | RETURN | REVERT | LOG0 | LOG1 | LOG2 | LOG3 | LOG4 => access(0, 1)?,
+        CALLDATACOPY | CODECOPY | RETURNDATACOPY => access(0, 2)?,
+        // The memory is expanded to cover both the source and the destination
+        MCOPY => access(0, 2)?.max(access(1, 2)?),
+        EXTCODECOPY => access(1, 3)?,
+        MLOAD | MSTORE => memory_access_u64(frame.back(0), 32)?,
+        MSTORE8 => memory_access_u64(frame.back(0), 1)?,
+        CREATE | CREATE2 => access(1, 2)?,
+        CALL | CALLCODE => access(5, 6)?.max(access(3, 4)?),
+        DELEGATECALL | STATICCALL => access(4, 5)?.max(access(2, 3)?),
+        _ => return Ok(0),
+    };
+    to_word_size(size)
+        .checked_mul(32)
+        .ok_or(VmError::GasUintOverflow)
+}
+
+/// Returns the end of the memory access at `offset` with `size`, or zero
+/// if `size` is zero.
+fn memory_access(offset: Word, size: Word) -> Result<u64, VmError> {
+    if size.bits() > 64 {
+        return Err(VmError::GasUintOverflow);
+    }
+    memory_access_u64(offset, size.low_u64())
+}
+
+fn memory_access_u64(offset: Word, size: u64) -> Result<u64, VmError> {
+    if size == 0 {
+        return Ok(0);
+    }
+    if offset.bits() > 64 {
+        return Err(VmError::GasUintOverflow);
+    }
+    offset
+        .low_u64()
+        .checked_add(size)
+        .ok_or(VmError::GasUintOverflow)
+}
+
+/// Returns the gas of the expansion of the memory of `frame` to
+/// `memory_size`, and records it as paid.
+fn memory_gas(frame: &mut Frame, memory_size: u64) -> Result<u64, VmError> {
+    if memory_size == 0 {
+        return Ok(0);
+    }
+    if memory_size > MAX_MEMORY_SIZE {
+        return Err(VmError::GasUintOverflow);
+    }
+    if memory_size <= frame.memory.len() as u64 {
+        return Ok(0);
+    }
+    let words = to_word_size(memory_size);
+    let total = words * GasCost::MEMORY_EXPANSION_LINEAR_COEFF.as_u64()
+        + words * words / GasCost::MEMORY_EXPANSION_QUAD_DENOMINATOR.as_u64();
+    let gas = total - frame.memory_gas;
+    frame.memory_gas = total;
+    Ok(gas)
+}
+
+/// Returns the gas of `size` bytes at `per_word` gas per word.
+fn words_gas(size: Word, per_word: GasCost) -> Result<u64, VmError> {
+    if size.bits() > 64 {
+        return Err(VmError::GasUintOverflow);
+    }
+    to_word_size(size.low_u64())
+        .checked_mul(per_word.as_u64())
+        .ok_or(VmError::GasUintOverflow)
+}
+
+fn to_word_size(size: u64) -> u64 {
+    if size > u64::MAX - 31 {
+        return u64::MAX / 32 + 1;
+    }
+    (size + 31) / 32
+}
+
+fn safe_add(a: u64, b: u64) -> Result<u64, VmError> {
+    a.checked_add(b).ok_or(VmError::GasUintOverflow)
+}
+
+fn saturating_u64(value: Word) -> u64 {
+    if value.bits() > 64 {
+        u64::MAX
+    } else {
+        value.low_u64()
+    }
+}
+
+/// Returns the destination of a jump if it's a `JUMPDEST`.
+fn valid_jump(frame: &Frame, dest: Word) -> Result<usize, VmError> {
+    if dest.bits() > 64 {
+        return Err(VmError::InvalidJump);
+    }
+    let dest = dest.low_u64() as usize;
+    if frame.jumpdests.get(dest).copied().unwrap_or_default() {
+        Ok(dest)
+    } else {
+        Err(VmError::InvalidJump)
+    }
+}
+
+fn binary_op(frame: &mut Frame, f: impl FnOnce(Word, Word) -> Word) {
+    let (a, b) = (frame.pop(), frame.pop());
+    frame.push(f(a, b));
+}
+
+fn u512_low(value: U512) -> Word {
+    let mut bytes = [0; 64];
+    value.to_big_endian(&mut bytes);
+    Word::from_big_endian(&bytes[32..])
+}
+
+fn sign_bit() -> Word {
+    Word::one() << 255
+}
+
+fn is_negative(value: Word) -> bool {
+    value.bit(255)
+}
+
+fn negate(value: Word) -> Word {
+    (!value).overflowing_add(Word::one()).0
+}
+
+fn abs(value: Word) -> Word {
+    if is_negative(value) {
+        negate(value)
+    } else {
+        value
+    }
+}
+
+fn sdiv(a: Word, b: Word) -> Word {
+    if b.is_zero() {
+        return Word::zero();
+    }
+    let quotient = abs(a) / abs(b);
+    if is_negative(a) != is_negative(b) {
+        negate(quotient)
+    } else {
+        quotient
+    }
+}
+
+fn smod(a: Word, b: Word) -> Word {
+    if b.is_zero() {
+        return Word::zero();
+    }
+    let remainder = abs(a) % abs(b);
+    if is_negative(a) {
+        negate(remainder)
+    } else {
+        remainder
+    }
+}
+
+fn signextend(byte_index: Word, value: Word) -> Word {
+    if byte_index >= Word::from(31) {
+        return value;
+    }
+    let bit = byte_index.as_usize() * 8 + 7;
+    let mask = (Word::one() << (bit + 1)) - 1;
+    if value.bit(bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
+
+fn sar(shift: Word, value: Word) -> Word {
+    let negative = is_negative(value);
+    if shift >= Word::from(256) {
+        return if negative { Word::MAX } else { Word::zero() };
+    }
+    let shift = shift.as_usize();
+    if negative {
+        !((!value) >> shift)
+    } else {
+        value >> shift
+    }
+}
@@ -0,0 +1,96 @@
+//! Logger recording the steps of the execution like geth's `StructLogger`.
+
+use super::{
+    interpreter::{Frame, VmError},
+    state::State,
+};
+use crate::LoggerConfig;
+use eth_types::{
+    evm_types::{Gas, GasCost, Hardfork, Memory, OpcodeId, ProgramCounter, Stack, Storage},
+    Address, GethExecStep, Word,
+};
+use std::collections::HashMap;
+
+/// Logger of the steps of a transaction
+#[derive(Debug)]
+pub(super) struct StructLogger {
+    config: LoggerConfig,
+    /// Slots read or written so far by each contract, which aren't reverted
+    storage: HashMap<Address, HashMap<Word, Word>>,
+    steps: Vec<GethExecStep>,
+}
+
+impl StructLogger {
+    pub(super) fn new(config: &LoggerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            storage: HashMap::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Record the step of `frame` running `op`, before its execution.
+    pub(super) fn capture_state(
+        &mut self,
+        state: &State,
+        frame: &Frame,
+        op: OpcodeId,
+        gas: u64,
+        cost: u64,
+        error: Option<&VmError>,
+    ) {
+        let stack = &frame.stack;
+        let mut storage = HashMap::new();
+        if !self.config.disable_storage && matches!(op, OpcodeId::SLOAD | OpcodeId::SSTORE) {
+            let slot = match op {
+                OpcodeId::SLOAD if !stack.is_empty() => {
+                    let key = stack[stack.len() - 1];
+                    Some((key, state.storage(&frame.address, &key)))
+                }
+                OpcodeId::SSTORE if stack.len() >= 2 => {
+                    Some((stack[stack.len() - 1], stack[stack.len() - 2]))
+                }
+                _ => None,
+            };
+            if let Some((key, value)) = slot {
+                let contract_storage = self.storage.entry(frame.address).or_default();
+                contract_storage.insert(key, value);
+                storage = contract_storage.clone();
+            }
+        }
+        self.steps.push(GethExecStep {
+            pc: ProgramCounter(frame.pc),
+            op: reported_opcode(op),
+            gas: Gas(gas),
+            gas_cost: GasCost(cost),
+            refund: Gas(state.refund()),
+            depth: frame.depth as u16,
+            error: error.map(ToString::to_string),
+            stack: Stack(if self.config.disable_stack {
+                Vec::new()
+            } else {
+                stack.clone()
+            }),
+            memory: Memory(if self.config.enable_memory {
+                frame.memory.clone()
+            } else {
+                Vec::new()
+            }),
+            storage: Storage(storage),
+        });
+    }
+
+    pub(super) fn into_steps(self) -> Vec<GethExecStep> {
+        self.steps
+    }
+}
+
+/// Returns `op` as reported by geth, which doesn't know the opcodes
+/// introduced after Shanghai.
+fn reported_opcode(op: OpcodeId) -> OpcodeId {
+    if op.activation_fork() > Hardfork::Shanghai {
+        OpcodeId::INVALID(op.as_u8())
+    } else {
+        op
+    }
+}
@@ -0,0 +1,341 @@
+//! Precompiled contracts at the addresses 0x01 to 0x09, as defined since
+//! Istanbul with the gas costs of EIP-2565 for `modexp`.
+
+use super::{get_data, interpreter::VmError};
+use eth_types::{
+    sign_types::{pk_bytes_le, pk_bytes_swap_endianness, recover_pk, SECP256K1_Q},
+    Address, ToWord, Word,
+};
+use ethers_core::utils::keccak256;
+use halo2_proofs::{
+    arithmetic::{CurveAffine, FieldExt},
+    halo2curves::{
+        bn256::{Bn256, Fq, Fq2, Fr, G1Affine, G2Affine, G2Prepared, Gt, G2},
+        group::{ff::Field, prime::PrimeCurveAffine, Curve, Group},
+        pairing::{MillerLoopResult, MultiMillerLoop},
+        Coordinates,
+    },
+};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// Returns `true` if `address` is the address of a precompiled contract.
+pub(super) fn is_precompile(address: &Address) -> bool {
+    let id = address.to_word();
+    !id.is_zero() && id <= Word::from(9)
+}
+
+/// Run the precompiled contract at `address` with `gas`, and return its
+/// output along with the gas left.
+pub(super) fn run(address: &Address, input: &[u8], gas: u64) -> Result<(Vec<u8>, u64), VmError> {
+    let (required_gas, run): (u64, fn(&[u8]) -> Result<Vec<u8>, &'static str>) =
+        match address.to_low_u64_be() {
+            0x01 => (3000, ecrecover),
+            0x02 => (60 + 12 * words(input), sha256),
+            0x03 => (600 + 120 * words(input), ripemd160),
+            0x04 => (15 + 3 * words(input), identity),
+            0x05 => (modexp_gas(input), modexp),
+            0x06 => (150, bn256_add),
+            0x07 => (6000, bn256_mul),
+            0x08 => (45000 + 34000 * (input.len() / 192) as u64, bn256_pairing),
+            0x09 => (blake2f_gas(input), blake2f),
+            _ => unreachable!("not a precompiled contract: {:?}", address),
+        };
+    if gas < required_gas {
+        return Err(VmError::OutOfGas);
+    }
+    let output = run(input).map_err(VmError::Precompile)?;
+    Ok((output, gas - required_gas))
+}
+
+/// Returns the number of words of `input`, rounded up.
+fn words(input: &[u8]) -> u64 {
+    (input.len() as u64 + 31) / 32
+}
+
+/// Returns `bytes` left padded with zeros to `len`.
+fn left_pad(bytes: &[u8], len: usize) -> Vec<u8> {
+    let mut padded = vec![0; len.saturating_sub(bytes.len())];
+    padded.extend_from_slice(bytes);
+    padded
+}
+
+fn ecrecover(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let input = get_data(input, 0, 128);
+    let r = Word::from_big_endian(&input[64..96]);
+    let s = Word::from_big_endian(&input[96..128]);
+    let v = input[63].wrapping_sub(27);
+    let q = Word::from_big_endian(&SECP256K1_Q.to_bytes_be());
+    let valid = |value: Word| !value.is_zero() && value < q;
+    // The signatures that can't be recovered return nothing, without failing
+    if input[32..63].iter().any(|byte| *byte != 0) || v > 1 || !valid(r) || !valid(s) {
+        return Ok(vec![]);
+    }
+    let msg_hash: [u8; 32] = input[..32].try_into().expect("input of 128 bytes");
+    Ok(match recover_pk(v, &r, &s, &msg_hash) {
+        Ok(pk) => {
+            let pk = pk_bytes_swap_endianness(&pk_bytes_le(&pk));
+            left_pad(&keccak256(pk)[12..], 32)
+        }
+        Err(_) => vec![],
+    })
+}
+
+fn identity(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    Ok(input.to_vec())
+}
+
+fn sha256(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    Ok(Sha256::digest(input).to_vec())
+}
+
+fn ripemd160(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    Ok(left_pad(&ripemd::Ripemd160::digest(input), 32))
+}
+
+/// Returns the lengths of the base, exponent and modulus of the input of
+/// `modexp`.
+fn modexp_lens(input: &[u8]) -> (BigUint, BigUint, BigUint) {
+    let len = |offset| BigUint::from_bytes_be(&get_data(input, offset, 32));
+    (len(0), len(32), len(64))
+}
+
+fn modexp_gas(input: &[u8]) -> u64 {
+    let (base_len, exp_len, mod_len) = modexp_lens(input);
+    let input = input.get(96..).unwrap_or_default();
+    let word = BigUint::from(32u8);
+    // Head of the exponent, with its length compared to the whole input like
+    // geth does
+    let exp_head = if BigUint::from(input.len()) <= base_len {
+        BigUint::default()
+    } else {
+        let base_len = u64::try_from(&base_len).expect("base length below the input length");
+        let head_len = if exp_len > word {
+            32
+        } else {
+            u64::try_from(&exp_len).expect("exponent length of at most 32")
+        };
+        BigUint::from_bytes_be(&get_data(input, base_len, head_len))
+    };
+    let msb = exp_head.bits().saturating_sub(1);
+    let mut adj_exp_len = if exp_len > word {
+        (exp_len - word) * 8u8
+    } else {
+        BigUint::default()
+    };
+    adj_exp_len += msb;
+    let words = (base_len.max(mod_len) + 7u8) / 8u8;
+    let gas = &words * &words * adj_exp_len.max(BigUint::from(1u8)) / 3u8;
+    u64::try_from(&gas).unwrap_or(u64::MAX).max(200)
+}
+
+fn modexp(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let (base_len, exp_len, mod_len) = modexp_lens(input);
+    // The lengths are truncated to 64 bits, which only matters for inputs
+    // that can't be paid for
+    let truncate = |len: BigUint| len.iter_u64_digits().next().unwrap_or_default();
+    let (base_len, exp_len, mod_len) = (truncate(base_len), truncate(exp_len), truncate(mod_len));
+    let input = input.get(96..).unwrap_or_default();
+    if base_len == 0 && mod_len == 0 {
+        return Ok(vec![]);
+    }
+    let base = BigUint::from_bytes_be(&get_data(input, 0, base_len));
+    let exp = BigUint::from_bytes_be(&get_data(input, base_len, exp_len));
+    let modulus = BigUint::from_bytes_be(&get_data(input, base_len.wrapping_add(exp_len), mod_len));
+    if modulus == BigUint::default() {
+        return Ok(vec![0; mod_len as usize]);
+    }
+    Ok(left_pad(
+        &base.modpow(&exp, &modulus).to_bytes_be(),
+        mod_len as usize,
+    ))
+}
+
+/// Decode a big-endian element of the base field of BN254.
+fn bn256_fq(bytes: &[u8]) -> Result<Fq, &'static str> {
+    let mut repr: [u8; 32] = bytes.try_into().expect("32 bytes");
+    repr.reverse();
+    Option::from(Fq::from_bytes(&repr)).ok_or("bn256: coordinate exceeds modulus")
+}
+
+/// Decode a point of G1 from its 64 bytes encoding, where `(0, 0)` is the
+/// point at infinity.
+fn bn256_g1(bytes: &[u8]) -> Result<G1Affine, &'static str> {
+    let x = bn256_fq(&bytes[..32])?;
+    let y = bn256_fq(&bytes[32..64])?;
+    if bytes.iter().all(|byte| *byte == 0) {
+        return Ok(G1Affine::identity());
+    }
+    Option::from(G1Affine::from_xy(x, y)).ok_or("bn256: malformed point")
+}
+
+/// Decode a point of G2 from its 128 bytes encoding, where the imaginary
+/// part of each coordinate comes first.
+fn bn256_g2(bytes: &[u8]) -> Result<G2Affine, &'static str> {
+    let x = Fq2 {
+        c0: bn256_fq(&bytes[32..64])?,
+        c1: bn256_fq(&bytes[..32])?,
+    };
+    let y = Fq2 {
+        c0: bn256_fq(&bytes[96..128])?,
+        c1: bn256_fq(&bytes[64..96])?,
+    };
+    if bytes.iter().all(|byte| *byte == 0) {
+        return Ok(G2Affine::identity());
+    }
+    let point: G2Affine = Option::from(G2Affine::from_xy(x, y)).ok_or("bn256: malformed point")?;
+    // The points of the curve that are out of the subgroup are rejected too
+    let point_proj = G2::from(point);
+    if !bool::from((point_proj * -Fr::one() + point_proj).is_identity()) {
+        return Err("bn256: malformed point");
+    }
+    Ok(point)
+}
+
+/// Encode a point of G1 in 64 bytes.
+fn bn256_g1_bytes(point: G1Affine) -> Vec<u8> {
+    let mut bytes = vec![0; 64];
+    if let Some(coordinates) = Option::<Coordinates<_>>::from(point.coordinates()) {
+        for (chunk, coordinate) in bytes.chunks_mut(32).zip([coordinates.x(), coordinates.y()]) {
+            chunk.copy_from_slice(&coordinate.to_bytes());
+            chunk.reverse();
+        }
+    }
+    bytes
+}
+
+fn bn256_add(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let input = get_data(input, 0, 128);
+    let a = bn256_g1(&input[..64])?;
+    let b = bn256_g1(&input[64..])?;
+    Ok(bn256_g1_bytes((a + b).to_affine()))
+}
+
+fn bn256_mul(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let input = get_data(input, 0, 96);
+    let point = bn256_g1(&input[..64])?;
+    let mut scalar = [0; 64];
+    Word::from_big_endian(&input[64..]).to_little_endian(&mut scalar[..32]);
+    Ok(bn256_g1_bytes(
+        (point * Fr::from_bytes_wide(&scalar)).to_affine(),
+    ))
+}
+
+fn bn256_pairing(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if input.len() % 192 != 0 {
+        return Err("bad elliptic curve pairing size");
+    }
+    let mut pairs = Vec::new();
+    for chunk in input.chunks(192) {
+        let p = bn256_g1(&chunk[..64])?;
+        let q = bn256_g2(&chunk[64..])?;
+        // The pairs with a point at infinity don't change the product
+        if !bool::from(p.is_identity()) && !bool::from(q.is_identity()) {
+            pairs.push((p, G2Prepared::from(q)));
+        }
+    }
+    let terms: Vec<_> = pairs.iter().map(|(p, q)| (p, q)).collect();
+    let success = Bn256::multi_miller_loop(&terms).final_exponentiation() == Gt::identity();
+    Ok(left_pad(&[success as u8], 32))
+}
+
+/// Length of the input of `blake2f`
+const BLAKE2F_INPUT_LEN: usize = 213;
+
+fn blake2f_gas(input: &[u8]) -> u64 {
+    if input.len() != BLAKE2F_INPUT_LEN {
+        return 0;
+    }
+    u32::from_be_bytes(input[..4].try_into().expect("4 bytes")) as u64
+}
+
+fn blake2f(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if input.len() != BLAKE2F_INPUT_LEN {
+        return Err("invalid input length");
+    }
+    let last_block = match input[212] {
+        0 => false,
+        1 => true,
+        _ => return Err("invalid final flag"),
+    };
+    let rounds = u32::from_be_bytes(input[..4].try_into().expect("4 bytes"));
+    let read_u64 =
+        |offset: usize| u64::from_le_bytes(input[offset..offset + 8].try_into().expect("8 bytes"));
+    let mut h = [0; 8];
+    for (i, h) in h.iter_mut().enumerate() {
+        *h = read_u64(4 + 8 * i);
+    }
+    let mut m = [0; 16];
+    for (i, m) in m.iter_mut().enumerate() {
+        *m = read_u64(68 + 8 * i);
+    }
+    let t = [read_u64(196), read_u64(204)];
+    blake2b_compress(rounds, &mut h, &m, t, last_block);
+    Ok(h.iter().flat_map(|h| h.to_le_bytes()).collect())
+}
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Compression function F of BLAKE2b (RFC 7693), with a number of rounds
+/// given by the caller (EIP-152).
+fn blake2b_compress(rounds: u32, h: &mut [u64; 8], m: &[u64; 16], t: [u64; 2], last_block: bool) {
+    let mut v = [0; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if last_block {
+        v[14] = !v[14];
+    }
+    for round in 0..rounds as usize {
+        let s = &BLAKE2B_SIGMA[round % 10];
+        for (i, [a, b, c, d]) in [
+            [0, 4, 8, 12],
+            [1, 5, 9, 13],
+            [2, 6, 10, 14],
+            [3, 7, 11, 15],
+            [0, 5, 10, 15],
+            [1, 6, 11, 12],
+            [2, 7, 8, 13],
+            [3, 4, 9, 14],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (x, y) = (m[s[2 * i]], m[s[2 * i + 1]]);
+            v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+            v[d] = (v[d] ^ v[a]).rotate_right(32);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(24);
+            v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+            v[d] = (v[d] ^ v[a]).rotate_right(16);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(63);
+        }
+    }
+    for (i, h) in h.iter_mut().enumerate() {
+        *h ^= v[i] ^ v[i + 8];
+    }
+}
@@ -0,0 +1,350 @@
+//! World state of the traced transactions, with the journal used to revert
+//! the changes of the calls that fail.
+
+use eth_types::{geth_types, Address, Bytes, Word, H256};
+use ethers_core::utils::keccak256;
+use std::collections::{HashMap, HashSet};
+
+/// Account of the [`State`].  The accounts that aren't in the state are
+/// empty.
+#[derive(Debug, Clone, Default)]
+struct Account {
+    nonce: u64,
+    balance: Word,
+    code: Bytes,
+    storage: HashMap<Word, Word>,
+}
+
+impl Account {
+    fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code.is_empty()
+    }
+}
+
+/// Change of the state, with the value it replaced.
+#[derive(Debug)]
+enum JournalEntry {
+    Created {
+        address: Address,
+        prev: Account,
+    },
+    Nonce {
+        address: Address,
+        prev: u64,
+    },
+    Balance {
+        address: Address,
+        prev: Word,
+    },
+    Code {
+        address: Address,
+        prev: Bytes,
+    },
+    Storage {
+        address: Address,
+        key: Word,
+        prev: Word,
+    },
+    TransientStorage {
+        address: Address,
+        key: Word,
+        prev: Word,
+    },
+    AccessedAddress(Address),
+    AccessedSlot(Address, Word),
+    Refund(u64),
+    Destructed(Address),
+}
+
+/// State of the accounts, along with the access lists (EIP-2929), the
+/// transient storage (EIP-1153) and the refund counter of the current
+/// transaction.
+#[derive(Debug, Default)]
+pub(super) struct State {
+    accounts: HashMap<Address, Account>,
+    /// Values at the beginning of the transaction of the slots it has written
+    committed: HashMap<(Address, Word), Word>,
+    transient_storage: HashMap<(Address, Word), Word>,
+    /// Accounts created in the transaction
+    created: HashSet<Address>,
+    destructed: HashSet<Address>,
+    accessed_addresses: HashSet<Address>,
+    accessed_slots: HashSet<(Address, Word)>,
+    refund: u64,
+    journal: Vec<JournalEntry>,
+}
+
+impl State {
+    /// Create the state holding `accounts`.
+    pub(super) fn new(accounts: &HashMap<Address, geth_types::Account>) -> Self {
+        let accounts = accounts
+            .iter()
+            .map(|(address, account)| {
+                let account = Account {
+                    nonce: account.nonce.low_u64(),
+                    balance: account.balance,
+                    code: account.code.clone(),
+                    storage: account
+                        .storage
+                        .iter()
+                        .filter(|(_, value)| !value.is_zero())
+                        .map(|(key, value)| (*key, *value))
+                        .collect(),
+                };
+                (*address, account)
+            })
+            .collect();
+        Self {
+            accounts,
+            ..Self::default()
+        }
+    }
+
+    fn account_mut(&mut self, address: Address) -> &mut Account {
+        self.accounts.entry(address).or_default()
+    }
+
+    pub(super) fn nonce(&self, address: &Address) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |account| account.nonce)
+    }
+
+    pub(super) fn balance(&self, address: &Address) -> Word {
+        self.accounts
+            .get(address)
+            .map_or_else(Word::zero, |account| account.balance)
+    }
+
+    pub(super) fn code(&self, address: &Address) -> Bytes {
+        self.accounts
+            .get(address)
+            .map(|account| account.code.clone())
+            .unwrap_or_default()
+    }
+
+    pub(super) fn code_hash(&self, address: &Address) -> H256 {
+        H256(keccak256(self.code(address)))
+    }
+
+    /// Returns `true` if the account has no nonce, balance nor code (EIP-161).
+    pub(super) fn is_empty(&self, address: &Address) -> bool {
+        self.accounts.get(address).map_or(true, Account::is_empty)
+    }
+
+    pub(super) fn storage(&self, address: &Address, key: &Word) -> Word {
+        self.accounts
+            .get(address)
+            .and_then(|account| account.storage.get(key).copied())
+            .unwrap_or_default()
+    }
+
+    /// Returns the value of the slot at the beginning of the transaction.
+    pub(super) fn committed_storage(&self, address: &Address, key: &Word) -> Word {
+        self.committed
+            .get(&(*address, *key))
+            .copied()
+            .unwrap_or_else(|| self.storage(address, key))
+    }
+
+    pub(super) fn transient_storage(&self, address: &Address, key: &Word) -> Word {
+        self.transient_storage
+            .get(&(*address, *key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(super) fn set_nonce(&mut self, address: Address, nonce: u64) {
+        let account = self.account_mut(address);
+        let prev = account.nonce;
+        account.nonce = nonce;
+        self.journal.push(JournalEntry::Nonce { address, prev });
+    }
+
+    fn set_balance(&mut self, address: Address, balance: Word) {
+        let account = self.account_mut(address);
+        let prev = account.balance;
+        account.balance = balance;
+        self.journal.push(JournalEntry::Balance { address, prev });
+    }
+
+    pub(super) fn add_balance(&mut self, address: Address, value: Word) {
+        let balance = self.balance(&address).saturating_add(value);
+        self.set_balance(address, balance);
+    }
+
+    pub(super) fn sub_balance(&mut self, address: Address, value: Word) {
+        let balance = self.balance(&address).saturating_sub(value);
+        self.set_balance(address, balance);
+    }
+
+    pub(super) fn transfer(&mut self, from: Address, to: Address, value: Word) {
+        self.sub_balance(from, value);
+        self.add_balance(to, value);
+    }
+
+    pub(super) fn set_code(&mut self, address: Address, code: Bytes) {
+        let prev = std::mem::replace(&mut self.account_mut(address).code, code);
+        self.journal.push(JournalEntry::Code { address, prev });
+    }
+
+    pub(super) fn set_storage(&mut self, address: Address, key: Word, value: Word) {
+        let storage = &mut self.account_mut(address).storage;
+        let prev = if value.is_zero() {
+            storage.remove(&key)
+        } else {
+            storage.insert(key, value)
+        }
+        .unwrap_or_default();
+        self.committed.entry((address, key)).or_insert(prev);
+        self.journal
+            .push(JournalEntry::Storage { address, key, prev });
+    }
+
+    pub(super) fn set_transient_storage(&mut self, address: Address, key: Word, value: Word) {
+        let prev = if value.is_zero() {
+            self.transient_storage.remove(&(address, key))
+        } else {
+            self.transient_storage.insert((address, key), value)
+        }
+        .unwrap_or_default();
+        self.journal
+            .push(JournalEntry::TransientStorage { address, key, prev });
+    }
+
+    /// Create a new account at `address`, which keeps the balance of the
+    /// account it replaces.
+    pub(super) fn create_account(&mut self, address: Address) {
+        let prev = self.accounts.remove(&address).unwrap_or_default();
+        self.accounts.insert(
+            address,
+            Account {
+                balance: prev.balance,
+                ..Account::default()
+            },
+        );
+        self.created.insert(address);
+        self.journal.push(JournalEntry::Created { address, prev });
+    }
+
+    /// Returns `true` if the account at `address` was created in the
+    /// transaction.
+    pub(super) fn is_created(&self, address: &Address) -> bool {
+        self.created.contains(address)
+    }
+
+    /// Mark the account for deletion at the end of the transaction, and
+    /// clear its balance.
+    pub(super) fn self_destruct(&mut self, address: Address) {
+        if self.destructed.insert(address) {
+            self.journal.push(JournalEntry::Destructed(address));
+        }
+        self.set_balance(address, Word::zero());
+    }
+
+    pub(super) fn is_warm_address(&self, address: &Address) -> bool {
+        self.accessed_addresses.contains(address)
+    }
+
+    pub(super) fn warm_address(&mut self, address: Address) {
+        if self.accessed_addresses.insert(address) {
+            self.journal.push(JournalEntry::AccessedAddress(address));
+        }
+    }
+
+    pub(super) fn is_warm_slot(&self, address: &Address, key: &Word) -> bool {
+        self.accessed_slots.contains(&(*address, *key))
+    }
+
+    pub(super) fn warm_slot(&mut self, address: Address, key: Word) {
+        self.warm_address(address);
+        if self.accessed_slots.insert((address, key)) {
+            self.journal.push(JournalEntry::AccessedSlot(address, key));
+        }
+    }
+
+    pub(super) fn refund(&self) -> u64 {
+        self.refund
+    }
+
+    pub(super) fn add_refund(&mut self, gas: u64) {
+        self.journal.push(JournalEntry::Refund(self.refund));
+        self.refund += gas;
+    }
+
+    pub(super) fn sub_refund(&mut self, gas: u64) {
+        self.journal.push(JournalEntry::Refund(self.refund));
+        self.refund = self
+            .refund
+            .checked_sub(gas)
+            .expect("refund counter below zero");
+    }
+
+    /// Returns an identifier of the current state to revert to.
+    pub(super) fn snapshot(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Revert the changes made since `snapshot` was taken.
+    pub(super) fn revert(&mut self, snapshot: usize) {
+        for entry in self.journal.drain(snapshot..).rev() {
+            match entry {
+                JournalEntry::Created { address, prev } => {
+                    self.created.remove(&address);
+                    self.accounts.insert(address, prev);
+                }
+                JournalEntry::Nonce { address, prev } => {
+                    self.accounts.entry(address).or_default().nonce = prev;
+                }
+                JournalEntry::Balance { address, prev } => {
+                    self.accounts.entry(address).or_default().balance = prev;
+                }
+                JournalEntry::Code { address, prev } => {
+                    self.accounts.entry(address).or_default().code = prev;
+                }
+                JournalEntry::Storage { address, key, prev } => {
+                    let storage = &mut self.accounts.entry(address).or_default().storage;
+                    if prev.is_zero() {
+                        storage.remove(&key);
+                    } else {
+                        storage.insert(key, prev);
+                    }
+                }
+                JournalEntry::TransientStorage { address, key, prev } => {
+                    if prev.is_zero() {
+                        self.transient_storage.remove(&(address, key));
+                    } else {
+                        self.transient_storage.insert((address, key), prev);
+                    }
+                }
+                JournalEntry::AccessedAddress(address) => {
+                    self.accessed_addresses.remove(&address);
+                }
+                JournalEntry::AccessedSlot(address, key) => {
+                    self.accessed_slots.remove(&(address, key));
+                }
+                JournalEntry::Refund(prev) => self.refund = prev,
+                JournalEntry::Destructed(address) => {
+                    self.destructed.remove(&address);
+                }
+            }
+        }
+    }
+
+    /// End the transaction: the self-destructed and the empty accounts are
+    /// deleted, and the journal, access lists, transient storage and refund
+    /// counter are reset.
+    pub(super) fn finalise(&mut self) {
+        for address in self.destructed.drain() {
+            self.accounts.remove(&address);
+        }
+        self.accounts.retain(|_, account| !account.is_empty());
+        self.committed.clear();
+        self.transient_storage.clear();
+        self.created.clear();
+        self.accessed_addresses.clear();
+        self.accessed_slots.clear();
+        self.refund = 0;
+        self.journal.clear();
+    }
+}
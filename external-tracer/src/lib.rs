@@ -1,6 +1,17 @@
-//! This module generates traces by connecting to an external tracer
+//! This module generates traces by connecting to an external tracer, which is
+//! either geth through the Go library built by `geth-utils` (feature `geth`,
+//! enabled by default), or an EVM interpreter written in Rust that gives the
+//! same traces (feature `rust-tracer`).  When both features are enabled,
+//! geth traces the hardforks it supports and the interpreter traces Cancun.
+
+#[cfg(not(any(feature = "geth", feature = "rust-tracer")))]
+compile_error!("either feature `geth` or `rust-tracer` must be enabled");
+
+#[cfg(feature = "rust-tracer")]
+mod evm;
 
 use eth_types::{
+    evm_types::Hardfork,
     geth_types::{Account, BlockConstants, Transaction},
     Address, Error, GethExecTrace, Word,
 };
@@ -23,6 +34,8 @@ pub struct TraceConfig {
     pub transactions: Vec<Transaction>,
     /// logger
     pub logger_config: LoggerConfig,
+    /// hardfork whose rules are used to execute the transactions
+    pub hardfork: Hardfork,
 }
 
 /// Configuration structure for `logger.Config`
@@ -63,7 +76,31 @@ impl LoggerConfig {
 }
 
 /// Creates a trace for the specified config
+#[cfg(all(feature = "geth", feature = "rust-tracer"))]
+pub fn trace(config: &TraceConfig) -> Result<Vec<GethExecTrace>, Error> {
+    // The go-ethereum version used by geth-utils doesn't implement Cancun
+    if config.hardfork >= Hardfork::Cancun {
+        evm::trace(config)
+    } else {
+        geth_trace(config)
+    }
+}
+
+/// Creates a trace for the specified config
+#[cfg(not(feature = "geth"))]
+pub fn trace(config: &TraceConfig) -> Result<Vec<GethExecTrace>, Error> {
+    evm::trace(config)
+}
+
+/// Creates a trace for the specified config
+#[cfg(not(feature = "rust-tracer"))]
 pub fn trace(config: &TraceConfig) -> Result<Vec<GethExecTrace>, Error> {
+    geth_trace(config)
+}
+
+/// Creates a trace for the specified config with geth
+#[cfg(feature = "geth")]
+fn geth_trace(config: &TraceConfig) -> Result<Vec<GethExecTrace>, Error> {
     // Get the trace
     let trace_string = geth_utils::trace(&serde_json::to_string(&config).unwrap()).map_err(
         |error| match error {
@@ -74,3 +111,64 @@ pub fn trace(config: &TraceConfig) -> Result<Vec<GethExecTrace>, Error> {
     let trace = serde_json::from_str(&trace_string).map_err(Error::SerdeError)?;
     Ok(trace)
 }
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+    use eth_types::{
+        bytecode,
+        evm_types::{GasCost, OpcodeId},
+        geth_types::TxType,
+        AccessList, H256,
+    };
+    use ethers_core::types::transaction::eip2930::AccessListItem;
+
+    #[test]
+    fn access_list_storage_keys() {
+        let sender = Address::from_low_u64_be(0xfe);
+        let contract = Address::from_low_u64_be(0xff);
+        let code = bytecode! {
+            PUSH1(1)
+            SLOAD
+            PUSH1(2)
+            SLOAD
+            STOP
+        };
+        let mut config = TraceConfig {
+            block_constants: BlockConstants {
+                gas_limit: Word::from(1_000_000),
+                ..BlockConstants::default()
+            },
+            ..TraceConfig::default()
+        };
+        config.accounts.insert(
+            contract,
+            Account {
+                address: contract,
+                code: code.into(),
+                ..Account::default()
+            },
+        );
+        config.transactions.push(Transaction {
+            tx_type: TxType::Eip2930,
+            from: sender,
+            to: Some(contract),
+            gas_limit: Word::from(100_000),
+            access_list: Some(AccessList(vec![AccessListItem {
+                address: contract,
+                storage_keys: vec![H256::from_low_u64_be(1)],
+            }])),
+            ..Transaction::default()
+        });
+
+        let traces = trace(&config).unwrap();
+        let sload_costs: Vec<_> = traces[0]
+            .struct_logs
+            .iter()
+            .filter(|step| step.op == OpcodeId::SLOAD)
+            .map(|step| step.gas_cost)
+            .collect();
+        // The slot of the access list is warm, and the other one cold
+        assert_eq!(sload_costs, vec![GasCost::WARM_ACCESS, GasCost::COLD_SLOAD]);
+    }
+}
@@ -43,6 +43,18 @@ impl<F: FieldExt> IsZeroConfig<F> {
     pub fn expr(&self) -> Expression<F> {
         self.is_zero_expression.clone()
     }
+
+    /// Returns the is_zero expression at `rotation`, when the value is the
+    /// advice column `value`.  The `is_zero_expression` only exposes the
+    /// current row.
+    pub fn expr_at(
+        &self,
+        meta: &mut VirtualCells<'_, F>,
+        value: Column<Advice>,
+        rotation: Rotation,
+    ) -> Expression<F> {
+        1.expr() - meta.query_advice(value, rotation) * meta.query_advice(self.value_inv, rotation)
+    }
 }
 
 /// Wrapper arround [`IsZeroConfig`] for which [`Chip`] is implemented.
@@ -1,11 +1,14 @@
 package gethutil
 
 import (
+	"errors"
 	"fmt"
 	"math/big"
+	"time"
 
 	"github.com/ethereum/go-ethereum/common"
 	"github.com/ethereum/go-ethereum/common/hexutil"
+	cmath "github.com/ethereum/go-ethereum/common/math"
 	"github.com/ethereum/go-ethereum/core"
 	"github.com/ethereum/go-ethereum/core/rawdb"
 	"github.com/ethereum/go-ethereum/core/state"
@@ -88,6 +91,7 @@ type Block struct {
 	Difficulty *hexutil.Big   `json:"difficulty"`
 	GasLimit   *hexutil.Big   `json:"gas_limit"`
 	BaseFee    *hexutil.Big   `json:"base_fee"`
+	MixHash    common.Hash    `json:"mix_hash"`
 }
 
 type Account struct {
@@ -98,6 +102,7 @@ type Account struct {
 }
 
 type Transaction struct {
+	TxType     string          `json:"tx_type"`
 	From       common.Address  `json:"from"`
 	To         *common.Address `json:"to"`
 	Nonce      hexutil.Uint64  `json:"nonce"`
@@ -109,7 +114,7 @@ type Transaction struct {
 	CallData   hexutil.Bytes   `json:"call_data"`
 	AccessList []struct {
 		Address     common.Address `json:"address"`
-		StorageKeys []common.Hash  `json:"storage_keys"`
+		StorageKeys []common.Hash  `json:"storageKeys"`
 	} `json:"access_list"`
 }
 
@@ -122,9 +127,56 @@ type TraceConfig struct {
 	Accounts      map[common.Address]Account `json:"accounts"`
 	Transactions  []Transaction              `json:"transactions"`
 	LoggerConfig  *logger.Config             `json:"logger_config"`
+	Hardfork      string                     `json:"hardfork"`
+}
+
+// hardforks lists the supported hardforks by activation, as serialized by
+// eth_types::evm_types::Hardfork.
+var hardforks = map[string]int{
+	"London":   0,
+	"Paris":    1,
+	"Shanghai": 2,
+	"Cancun":   3,
+}
+
+const (
+	// maxInitCodeSize is the maximum size of the init code of a contract
+	// creation (EIP-3860).
+	maxInitCodeSize = 2 * params.MaxCodeSize
+	// initCodeWordGas is the gas charged per word of init code (EIP-3860).
+	initCodeWordGas = 2
+)
+
+// errMaxInitCodeSizeExceeded is returned for a creation tx whose init code is
+// larger than maxInitCodeSize.
+var errMaxInitCodeSizeExceeded = errors.New("max initcode size exceeded")
+
+// shanghaiStateDB warms the coinbase at the beginning of each transaction
+// (EIP-3651), which the go-ethereum version we depend on doesn't do.
+type shanghaiStateDB struct {
+	*state.StateDB
+	coinbase common.Address
+}
+
+func (s *shanghaiStateDB) PrepareAccessList(sender common.Address, dst *common.Address, precompiles []common.Address, list types.AccessList) {
+	s.StateDB.PrepareAccessList(sender, dst, precompiles, list)
+	s.StateDB.AddAddressToAccessList(s.coinbase)
 }
 
 func Trace(config TraceConfig) ([]*ExecutionResult, error) {
+	if config.Hardfork == "" {
+		config.Hardfork = "London"
+	}
+	fork, ok := hardforks[config.Hardfork]
+	if !ok {
+		return nil, fmt.Errorf("Unknown hardfork: %s", config.Hardfork)
+	}
+	isMerge := fork >= hardforks["Paris"]
+	isShanghai := fork >= hardforks["Shanghai"]
+	if fork > hardforks["Shanghai"] {
+		return nil, fmt.Errorf("Hardfork %s is not supported by the tracer", config.Hardfork)
+	}
+
 	chainConfig := params.ChainConfig{
 		ChainID:             toBigInt(config.ChainID),
 		HomesteadBlock:      big.NewInt(0),
@@ -147,8 +199,10 @@ func Trace(config TraceConfig) ([]*ExecutionResult, error) {
 	blockGasLimit := toBigInt(config.Block.GasLimit).Uint64()
 	messages := make([]types.Message, len(config.Transactions))
 	for i, tx := range config.Transactions {
-		// If gas price is specified directly, the tx is treated as legacy type.
-		if tx.GasPrice != nil {
+		// If gas price is specified directly, the tx is treated as legacy type,
+		// unless it's an EIP-1559 tx whose balance is checked against its fee
+		// cap like in `buyGas`.
+		if tx.GasPrice != nil && (tx.TxType != "Eip1559" || tx.GasFeeCap == nil) {
 			tx.GasFeeCap = tx.GasPrice
 			tx.GasTipCap = tx.GasPrice
 		}
@@ -196,6 +250,20 @@ func Trace(config TraceConfig) ([]*ExecutionResult, error) {
 		BaseFee:     toBigInt(config.Block.BaseFee),
 		GasLimit:    blockGasLimit,
 	}
+	// After the Merge the DIFFICULTY opcode returns PREVRANDAO (EIP-4399)
+	if isMerge {
+		random := config.Block.MixHash
+		blockCtx.Random = &random
+	}
+
+	// PUSH0 (EIP-3855) is enabled as an extra EIP on top of London.
+	var extraEips []int
+	if isShanghai {
+		if !vm.ValidEip(3855) {
+			return nil, errors.New("EIP-3855 is not supported by go-ethereum")
+		}
+		extraEips = append(extraEips, 3855)
+	}
 
 	// Setup state db with accounts from argument
 	stateDB, _ := state.New(common.Hash{}, state.NewDatabase(rawdb.NewMemoryDatabase()), nil)
@@ -210,16 +278,77 @@ func Trace(config TraceConfig) ([]*ExecutionResult, error) {
 		}
 	}
 	stateDB.Finalise(true)
+	var evmStateDB vm.StateDB = stateDB
+	if isShanghai {
+		evmStateDB = &shanghaiStateDB{StateDB: stateDB, coinbase: blockCtx.Coinbase}
+	}
 
 	// Run the transactions with tracing enabled.
 	executionResults := make([]*ExecutionResult, len(config.Transactions))
 	for i, message := range messages {
-		tracer := logger.NewStructLogger(config.LoggerConfig)
-		evm := vm.NewEVM(blockCtx, core.NewEVMTxContext(message), stateDB, &chainConfig, vm.Config{Debug: true, Tracer: tracer, NoBaseFee: true})
+		tracer := &txTracer{StructLogger: logger.NewStructLogger(config.LoggerConfig)}
+		vmConfig := vm.Config{Debug: true, Tracer: tracer, NoBaseFee: true, ExtraEips: extraEips}
+
+		// From Shanghai the init code of a creation tx is limited in size
+		// and charged per word (EIP-3860).  The charge is taken out of the
+		// gas limit before applying the message, and added back afterwards.
+		var initCodeGas uint64
+		var err error
+		if isShanghai && message.To() == nil {
+			message, initCodeGas, err = applyInitCodeRules(stateDB, message)
+		}
+		var intrinsicGas uint64
+		if err == nil && initCodeGas > 0 {
+			intrinsicGas, err = core.IntrinsicGas(message.Data(), message.AccessList(), true, true, true)
+			// A failed creation, e.g. on an address collision, uses all the
+			// gas left after the intrinsic gas.
+			if err == nil && message.Gas() > intrinsicGas {
+				tracer.gasUsed = message.Gas() - intrinsicGas
+			}
+		}
 
-		result, err := core.ApplyMessage(evm, message, new(core.GasPool).AddGas(message.Gas()))
+		snapshot := stateDB.Snapshot()
+		var result *core.ExecutionResult
+		if err == nil {
+			evm := vm.NewEVM(blockCtx, core.NewEVMTxContext(message), evmStateDB, &chainConfig, vmConfig)
+			result, err = core.ApplyMessage(evm, message, new(core.GasPool).AddGas(message.Gas()))
+		}
 		if err != nil {
-			return nil, fmt.Errorf("Failed to apply config.Transactions[%d]: %w", i, err)
+			if !isInvalidTxErr(err) {
+				return nil, fmt.Errorf("Failed to apply config.Transactions[%d]: %w", i, err)
+			}
+			// Invalid transactions are kept in the block as no-ops, so we
+			// revert any partial state change (e.g. the gas bought before
+			// the intrinsic gas check) and return an empty failed trace.
+			stateDB.RevertToSnapshot(snapshot)
+			executionResults[i] = &ExecutionResult{
+				Gas:        0,
+				Failed:     true,
+				StructLogs: []StructLogRes{},
+			}
+			continue
+		}
+		if initCodeGas > 0 {
+			// go-ethereum capped the refund on the gas used without the init
+			// code charge, so it is applied again on the whole gas used, and
+			// the difference is charged to the sender.
+			usedGas := intrinsicGas + tracer.gasUsed + initCodeGas
+			refund := usedGas / params.RefundQuotientEIP3529
+			if stateDB.GetRefund() < refund {
+				refund = stateDB.GetRefund()
+			}
+			extraGas := new(big.Int).SetUint64(usedGas - refund - result.UsedGas)
+			result.UsedGas = usedGas - refund
+			stateDB.SubBalance(message.From(), new(big.Int).Mul(extraGas, message.GasPrice()))
+			tip := cmath.BigMin(message.GasTipCap(), new(big.Int).Sub(message.GasFeeCap(), blockCtx.BaseFee))
+			stateDB.AddBalance(blockCtx.Coinbase, new(big.Int).Mul(extraGas, tip))
+		}
+		if isShanghai {
+			for _, log := range tracer.StructLogs() {
+				if log.Op == vm.CREATE || log.Op == vm.CREATE2 {
+					return nil, fmt.Errorf("Failed to apply config.Transactions[%d]: %s doesn't apply EIP-3860 in this go-ethereum version, use the rust-tracer feature", i, log.Op)
+				}
+			}
 		}
 		stateDB.Finalise(true)
 
@@ -233,3 +362,69 @@ func Trace(config TraceConfig) ([]*ExecutionResult, error) {
 
 	return executionResults, nil
 }
+
+// applyInitCodeRules checks the init code of a creation message against the
+// EIP-3860 limit, and returns the message with its gas limit reduced by the
+// init code charge, along with the charge.  The balance check is done on the
+// original gas limit since go-ethereum only sees the reduced one.
+//
+// The refund cap is fixed up after applying the message, from the gas used by
+// the creation that txTracer records.  CREATE and CREATE2 can't be charged
+// for their init code with this go-ethereum version, so Trace fails on them
+// from Shanghai.
+func applyInitCodeRules(stateDB *state.StateDB, message types.Message) (types.Message, uint64, error) {
+	if len(message.Data()) > maxInitCodeSize {
+		return message, 0, errMaxInitCodeSizeExceeded
+	}
+	initCodeGas := initCodeWordGas * toWordSize(uint64(len(message.Data())))
+	if message.Gas() < initCodeGas {
+		return message, 0, core.ErrIntrinsicGas
+	}
+	cost := new(big.Int).Mul(new(big.Int).SetUint64(message.Gas()), message.GasFeeCap())
+	cost.Add(cost, message.Value())
+	if stateDB.GetBalance(message.From()).Cmp(cost) < 0 {
+		return message, 0, core.ErrInsufficientFunds
+	}
+	return types.NewMessage(
+		message.From(),
+		message.To(),
+		message.Nonce(),
+		message.Value(),
+		message.Gas()-initCodeGas,
+		message.GasPrice(),
+		message.GasFeeCap(),
+		message.GasTipCap(),
+		message.Data(),
+		message.AccessList(),
+		message.IsFake(),
+	), initCodeGas, nil
+}
+
+// txTracer is a StructLogger which records the gas used by the top-level
+// call, before the refund.
+type txTracer struct {
+	*logger.StructLogger
+	gasUsed uint64
+}
+
+func (t *txTracer) CaptureEnd(output []byte, gasUsed uint64, d time.Duration, err error) {
+	t.gasUsed = gasUsed
+	t.StructLogger.CaptureEnd(output, gasUsed, d, err)
+}
+
+func toWordSize(size uint64) uint64 {
+	return (size + 31) / 32
+}
+
+// isInvalidTxErr returns whether err comes from the validity checks done
+// before executing a transaction: nonce mismatch, insufficient balance to
+// cover the gas fee and value, gas limit below the intrinsic gas, and init code
+// above the size limit.
+func isInvalidTxErr(err error) bool {
+	return errors.Is(err, core.ErrNonceTooLow) ||
+		errors.Is(err, core.ErrNonceTooHigh) ||
+		errors.Is(err, core.ErrInsufficientFunds) ||
+		errors.Is(err, core.ErrInsufficientFundsForTransfer) ||
+		errors.Is(err, core.ErrIntrinsicGas) ||
+		errors.Is(err, errMaxInitCodeSizeExceeded)
+}
@@ -96,6 +96,10 @@ if [ -n "$STEP_GENDATA" ]; then
 fi
 
 if [ -n "$STEP_TESTS" ]; then
+    if [ "$RPC_FIXTURES" = "record" ]; then
+        mkdir -p fixtures
+        cp gendata_output.json fixtures/
+    fi
     for testname in $ARG_TESTS; do
         echo "+ Running test group $testname"
 	cargo test --profile release --test $(echo $testname | sed -e 's/::/ /g') --all-features -- --nocapture
@@ -1,6 +1,7 @@
 use crate::{get_client, GenDataOutput};
 use bus_mapping::circuit_input_builder::{BuilderClient, CircuitInputBuilder, CircuitsParams};
 use bus_mapping::mock::BlockData;
+use eth_types::evm_types::Hardfork;
 use eth_types::geth_types::GethData;
 use halo2_proofs::plonk::{
     create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ProvingKey, VerifyingKey,
@@ -51,7 +52,9 @@ const CIRCUITS_PARAMS: CircuitsParams = CircuitsParams {
     max_calldata: MAX_CALLDATA,
     max_bytecode: MAX_BYTECODE,
     max_copy_rows: MAX_COPY_ROWS,
+    max_mpt_rows: 0,
     keccak_padding: None,
+    hardfork: Hardfork::London,
 };
 
 /// EVM Circuit degree
@@ -18,6 +18,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env::{self, VarError};
 use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::sync::Once;
 use std::time::Duration;
 use url::Url;
@@ -36,6 +37,9 @@ pub const CONTRACTS: &[(&str, &str)] = &[
 ];
 /// Path to gen_blockchain_data output file
 pub const GENDATA_OUTPUT_PATH: &str = "gendata_output.json";
+/// Path to the JSON-RPC responses of geth0 recorded by the tests, along with
+/// the gen_blockchain_data output they were recorded with
+pub const FIXTURES_PATH: &str = "fixtures";
 
 const GETH0_URL_DEFAULT: &str = "http://localhost:8545";
 
@@ -49,6 +53,38 @@ lazy_static! {
     };
 }
 
+/// Source of the JSON-RPC responses used by the tests, selected with the
+/// `RPC_FIXTURES` env var.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Query geth0, when `RPC_FIXTURES` is not set
+    Live,
+    /// Query geth0 and record its responses into [`FIXTURES_PATH`], with
+    /// `RPC_FIXTURES=record`
+    Record,
+    /// Replay the responses recorded in [`FIXTURES_PATH`] without geth0, with
+    /// `RPC_FIXTURES=replay`
+    Replay,
+}
+
+lazy_static! {
+    /// Source of the JSON-RPC responses used by the tests
+    pub static ref FIXTURE_MODE: FixtureMode = match env::var("RPC_FIXTURES") {
+        Ok(val) if val == "record" => FixtureMode::Record,
+        Ok(val) if val == "replay" => FixtureMode::Replay,
+        Ok(val) => panic!("Invalid RPC_FIXTURES env var: {}", val),
+        Err(VarError::NotPresent) => FixtureMode::Live,
+        Err(e) => panic!("Error in RPC_FIXTURES env var: {:?}", e),
+    };
+}
+
+/// Path of the fixture recorded by the test `name` of the test group `group`
+pub fn fixture_path(group: &str, name: &str) -> PathBuf {
+    Path::new(FIXTURES_PATH)
+        .join(group)
+        .join(format!("{}.json", name))
+}
+
 static LOG_INIT: Once = Once::new();
 
 /// Initialize log
@@ -107,9 +143,14 @@ pub struct GenDataOutput {
 }
 
 impl GenDataOutput {
-    /// Load [`GenDataOutput`] from the json file.
+    /// Load [`GenDataOutput`] from the json file, or from its copy in
+    /// [`FIXTURES_PATH`] when replaying the recorded responses.
     pub fn load() -> Self {
-        serde_json::from_reader(File::open(GENDATA_OUTPUT_PATH).expect("cannot read file"))
+        let path = match *FIXTURE_MODE {
+            FixtureMode::Replay => Path::new(FIXTURES_PATH).join(GENDATA_OUTPUT_PATH),
+            _ => PathBuf::from(GENDATA_OUTPUT_PATH),
+        };
+        serde_json::from_reader(File::open(path).expect("cannot read file"))
             .expect("cannot deserialize json from file")
     }
 
@@ -1,7 +1,17 @@
 #![cfg(feature = "circuit_input_builder")]
 
-use bus_mapping::circuit_input_builder::{BuilderClient, CircuitsParams};
-use integration_tests::{get_client, log_init, GenDataOutput};
+use bus_mapping::{
+    circuit_input_builder::{BuilderClient, CircuitsParams},
+    rpc::{
+        fixture::{RecordingProvider, ReplayProvider},
+        GethClient,
+    },
+};
+use eth_types::evm_types::Hardfork;
+use ethers::providers::JsonRpcClient;
+use integration_tests::{
+    fixture_path, get_client, log_init, FixtureMode, GenDataOutput, FIXTURE_MODE,
+};
 use lazy_static::lazy_static;
 use log::trace;
 
@@ -9,8 +19,7 @@ lazy_static! {
     pub static ref GEN_DATA: GenDataOutput = GenDataOutput::load();
 }
 
-async fn test_circuit_input_builder_block(block_num: u64) {
-    let cli = get_client();
+async fn test_circuit_input_builder_block<P: JsonRpcClient>(cli: GethClient<P>, block_num: u64) {
     let cli = BuilderClient::new(
         cli,
         CircuitsParams {
@@ -19,50 +28,74 @@ async fn test_circuit_input_builder_block(block_num: u64) {
             max_calldata: 4000,
             max_bytecode: 4000,
             max_copy_rows: 16384,
+            max_mpt_rows: 0,
             keccak_padding: None,
+            hardfork: Hardfork::default(),
         },
     )
     .await
     .unwrap();
 
     // 1. Query geth for Block, Txs and TxExecTraces
-    let (eth_block, geth_trace, history_hashes, prev_state_root) =
+    let (eth_block, geth_trace, history_hashes, _history_headers, prev_state_root) =
         cli.get_block(block_num).await.unwrap();
 
     // 2. Get State Accesses from TxExecTraces
     let access_set = cli.get_state_accesses(&eth_block, &geth_trace).unwrap();
     trace!("AccessSet: {:#?}", access_set);
 
-    // 3. Query geth for all accounts, storage keys, and codes from Accesses
+    // 3. Query geth for the proofs of all accounts and storage keys, and the
+    // codes from Accesses
     let (proofs, codes) = cli.get_state(block_num, access_set).await.unwrap();
 
-    // 4. Build a partial StateDB from step 3
+    // 4. Build the partial state trie of the previous block from step 3
+    let state_trie = cli.build_state_trie(prev_state_root, &proofs).unwrap();
+
+    // 5. Build a partial StateDB from step 3
     let (state_db, code_db) = cli.build_state_code_db(proofs, codes);
     trace!("StateDB: {:#?}", state_db);
 
-    // 5. For each step in TxExecTraces, gen the associated ops and state
+    // 6. For each step in TxExecTraces, gen the associated ops and state
     // circuit inputs
     let builder = cli
         .gen_inputs_from_state(
             state_db,
             code_db,
+            state_trie,
             &eth_block,
             &geth_trace,
             history_hashes,
-            prev_state_root,
         )
         .unwrap();
 
     trace!("CircuitInputBuilder: {:#?}", builder);
 }
 
+/// Run the test of `block_num` with the JSON-RPC responses of
+/// [`FIXTURE_MODE`], recorded in the fixture of the test `name`.
+async fn test_circuit_input_builder_block_fixture(name: &str, block_num: u64) {
+    let path = fixture_path("circuit_input_builder", name);
+    match *FIXTURE_MODE {
+        FixtureMode::Live => test_circuit_input_builder_block(get_client(), block_num).await,
+        FixtureMode::Record => {
+            let provider = RecordingProvider::new(get_client().0);
+            test_circuit_input_builder_block(GethClient::new(&provider), block_num).await;
+            provider.store(&path).unwrap();
+        }
+        FixtureMode::Replay => {
+            let provider = ReplayProvider::load(&path).unwrap();
+            test_circuit_input_builder_block(GethClient::new(provider), block_num).await;
+        }
+    }
+}
+
 macro_rules! declare_tests {
     ($test_name:ident, $block_tag:expr) => {
         #[tokio::test]
         async fn $test_name() {
             log_init();
             let block_num = GEN_DATA.blocks.get($block_tag).unwrap();
-            test_circuit_input_builder_block(*block_num).await;
+            test_circuit_input_builder_block_fixture(stringify!($test_name), *block_num).await;
         }
     };
 }
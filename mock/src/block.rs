@@ -1,7 +1,7 @@
 //! Mock Block definition and builder related methods.
 
 use crate::{MockTransaction, MOCK_BASEFEE, MOCK_CHAIN_ID, MOCK_DIFFICULTY, MOCK_GASLIMIT};
-use eth_types::{Address, Block, Bytes, Hash, Transaction, Word, H64, U64};
+use eth_types::{geth_types::Withdrawal, Address, Block, Bytes, Hash, Transaction, Word, H64, U64};
 use ethers_core::types::Bloom;
 use ethers_core::types::OtherFields;
 
@@ -32,6 +32,7 @@ pub struct MockBlock {
     size: Word,
     mix_hash: Hash,
     nonce: H64,
+    withdrawals: Vec<Withdrawal>,
     // This field is handled here as we assume that all block txs have the same ChainId.
     // Also, the field is stored in the block_table since we don't have a chain_config
     // structure/table.
@@ -63,6 +64,7 @@ impl Default for MockBlock {
             size: Word::zero(),
             mix_hash: Hash::zero(),
             nonce: H64::zero(),
+            withdrawals: Vec::new(),
             chain_id: *MOCK_CHAIN_ID,
         }
     }
@@ -70,6 +72,7 @@ impl Default for MockBlock {
 
 impl From<MockBlock> for Block<Transaction> {
     fn from(mut mock: MockBlock) -> Self {
+        let other = mock.other_fields();
         Block {
             hash: mock.hash.or_else(|| Some(Hash::default())),
             parent_hash: mock.parent_hash,
@@ -97,13 +100,14 @@ impl From<MockBlock> for Block<Transaction> {
             mix_hash: Some(mock.mix_hash),
             nonce: Some(mock.nonce),
             base_fee_per_gas: Some(mock.base_fee_per_gas),
-            other: OtherFields::default(),
+            other,
         }
     }
 }
 
 impl From<MockBlock> for Block<()> {
     fn from(mock: MockBlock) -> Self {
+        let other = mock.other_fields();
         Block {
             hash: mock.hash.or_else(|| Some(Hash::default())),
             parent_hash: mock.parent_hash,
@@ -127,12 +131,21 @@ impl From<MockBlock> for Block<()> {
             mix_hash: Some(mock.mix_hash),
             nonce: Some(mock.nonce),
             base_fee_per_gas: Some(mock.base_fee_per_gas),
-            other: OtherFields::default(),
+            other,
         }
     }
 }
 
 impl MockBlock {
+    /// Fields of the block which are not part of the `ethers` block type.
+    fn other_fields(&self) -> OtherFields {
+        if self.withdrawals.is_empty() {
+            return OtherFields::default();
+        }
+        serde_json::from_value(serde_json::json!({ "withdrawals": self.withdrawals }))
+            .expect("withdrawals are serializable")
+    }
+
     /// TODO: This should be computed based on the fields of the block by
     /// default unless `Some(hash)` is specified on build process.
     pub fn hash(&mut self, hash: Hash) -> &mut Self {
@@ -269,6 +282,12 @@ impl MockBlock {
         self
     }
 
+    /// Set withdrawals field for the MockBlock.
+    pub fn withdrawals<I: IntoIterator<Item = Withdrawal>>(&mut self, withdrawals: I) -> &mut Self {
+        self.withdrawals.extend(withdrawals);
+        self
+    }
+
     /// Set chain_id field for the MockBlock.
     pub fn chain_id(&mut self, chain_id: Word) -> &mut Self {
         self.chain_id = chain_id;
@@ -3,14 +3,11 @@
 use super::{MOCK_ACCOUNTS, MOCK_CHAIN_ID, MOCK_GASPRICE};
 use eth_types::word;
 use eth_types::{
-    geth_types::Transaction as GethTransaction, AccessList, Address, Bytes, Hash, Transaction,
-    Word, U64,
+    geth_types::{Transaction as GethTransaction, TxType},
+    AccessList, Address, Bytes, Hash, Transaction, Word, U64,
 };
+use ethers_core::rand::{CryptoRng, RngCore};
 use ethers_core::types::OtherFields;
-use ethers_core::{
-    rand::{CryptoRng, RngCore},
-    types::TransactionRequest,
-};
 use ethers_signers::{LocalWallet, Signer};
 use lazy_static::lazy_static;
 use rand::SeedableRng;
@@ -301,27 +298,22 @@ impl MockTransaction {
     /// Consumes the mutable ref to the MockTransaction returning the structure
     /// by value.
     pub fn build(&mut self) -> Self {
-        let tx = TransactionRequest::new()
-            .from(self.from.address())
-            .to(self.to.clone().unwrap_or_default().address())
-            .nonce(self.nonce)
-            .value(self.value)
-            .data(self.input.clone())
-            .gas(self.gas)
-            .gas_price(self.gas_price)
-            .chain_id(self.chain_id.low_u64());
+        let chain_id = self.chain_id.low_u64();
+        let tx_type =
+            TxType::try_from(self.transaction_type.as_u64()).expect("unsupported transaction type");
 
         match (self.v, self.r, self.s) {
             (None, None, None) => {
                 // Compute sig params and set them in case we have a wallet as `from` attr.
                 if self.from.is_wallet() && self.hash.is_none() {
+                    let tx = GethTransaction::from(self.to_owned()).typed_tx(chain_id);
                     let sig = self
                         .from
                         .as_wallet()
-                        .with_chain_id(self.chain_id.low_u64())
-                        .sign_transaction_sync(&tx.into());
+                        .with_chain_id(chain_id)
+                        .sign_transaction_sync(&tx);
                     // Set sig parameters
-                    self.sig_data((sig.v, sig.r, sig.s));
+                    self.sig_data((tx_type.sig_v_from_eip155(sig.v, chain_id), sig.r, sig.s));
                 }
             }
             (Some(_), Some(_), Some(_)) => (),
@@ -0,0 +1,139 @@
+//! Circuit configuration of the prover, which determines the layout of the
+//! SuperCircuit and therefore its proving and verifying keys.
+//!
+//! The SuperCircuit uses [`MOCK_RANDOMNESS`] as the value of its challenges
+//! instead of deriving them from the transcript, so a prover who knows it can
+//! satisfy the random linear combinations of the circuit with other values.
+//! The proofs are NOT sound, and the prover is only suitable for testing the
+//! proving pipeline and benchmarking.
+
+use anyhow::{ensure, Result};
+use bus_mapping::circuit_input_builder::CircuitsParams;
+use clap::Args;
+use eth_types::evm_types::Hardfork;
+use halo2_proofs::halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+use zkevm_circuits::{super_circuit::SuperCircuit, witness::Block};
+
+/// Maximum number of txs of a block, fixed when the prover is compiled
+pub const MAX_TXS: usize = 4;
+/// Maximum number of bytes from all txs calldata of a block, fixed when the
+/// prover is compiled
+pub const MAX_CALLDATA: usize = 512;
+/// Randomness used for the challenges of the circuit, which makes the proofs
+/// unsound
+pub const MOCK_RANDOMNESS: u64 = 0x100;
+/// Rows at the end of the circuit that are reserved for the blinding factors
+const NUM_BLINDING_ROWS: usize = 64;
+
+/// SuperCircuit proved by the prover
+pub type ProverCircuit = SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA, MOCK_RANDOMNESS>;
+
+/// Configuration of the SuperCircuit.  Two blocks proved with the same
+/// configuration share the same proving and verifying keys, because every
+/// sub-circuit is padded to a size which only depends on the configuration.
+#[derive(Args, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProverConfig {
+    /// Degree of the circuit, which has 2^degree rows
+    #[clap(long, default_value = "20")]
+    pub degree: u32,
+    /// Maximum number of txs of a block, which must be the one the prover is
+    /// compiled with
+    #[clap(long, default_value_t = MAX_TXS)]
+    pub max_txs: usize,
+    /// Maximum number of bytes from all txs calldata of a block, which must be
+    /// the one the prover is compiled with
+    #[clap(long, default_value_t = MAX_CALLDATA)]
+    pub max_calldata: usize,
+    /// Maximum number of rw operations of a block
+    #[clap(long, default_value = "5888")]
+    pub max_rws: usize,
+    /// Maximum number of rows of the Copy Circuit
+    #[clap(long, default_value = "5888")]
+    pub max_copy_rows: usize,
+    /// Maximum number of bytes of the bytecodes used by a block
+    #[clap(long, default_value = "5000")]
+    pub max_bytecode: usize,
+    /// Maximum number of rows of the MPT Circuit
+    #[clap(long, default_value = "20000")]
+    pub max_mpt_rows: usize,
+    /// Hardfork whose rules are used to process the blocks
+    #[clap(long, default_value = "London")]
+    pub hardfork: Hardfork,
+}
+
+impl ProverConfig {
+    /// Number of rows available to the sub-circuits
+    pub fn usable_rows(&self) -> usize {
+        (1 << self.degree) - NUM_BLINDING_ROWS
+    }
+
+    /// Returns an error if the configuration isn't the one of
+    /// [`ProverCircuit`] or a sub-circuit doesn't fit in the circuit.
+    pub fn check(&self) -> Result<()> {
+        for (name, value, compiled) in [
+            ("max_txs", self.max_txs, MAX_TXS),
+            ("max_calldata", self.max_calldata, MAX_CALLDATA),
+        ] {
+            ensure!(
+                value == compiled,
+                "{} = {} isn't supported, the prover is compiled with {}",
+                name,
+                value,
+                compiled
+            );
+        }
+        for (name, rows) in [
+            ("max_rws", self.max_rws),
+            ("max_copy_rows", self.max_copy_rows),
+            ("max_bytecode", self.max_bytecode),
+            ("max_mpt_rows", self.max_mpt_rows),
+        ] {
+            ensure!(
+                rows <= self.usable_rows(),
+                "{} = {} doesn't fit in a circuit of degree {}",
+                name,
+                rows,
+                self.degree
+            );
+        }
+        Ok(())
+    }
+
+    /// Parameters used to build the witness of a block
+    pub fn circuits_params(&self) -> CircuitsParams {
+        CircuitsParams {
+            max_rws: self.max_rws,
+            max_txs: self.max_txs,
+            max_calldata: self.max_calldata,
+            max_bytecode: self.max_bytecode,
+            max_copy_rows: self.max_copy_rows,
+            max_mpt_rows: self.max_mpt_rows,
+            keccak_padding: Some(self.usable_rows()),
+            hardfork: self.hardfork,
+        }
+    }
+
+    /// Pad the sub-circuits of `block` whose size otherwise depends on the
+    /// block to the rows of the circuit.
+    pub fn pad_block(&self, block: &mut Block<Fr>) {
+        // The EVM Circuit uses one row more than its steps
+        block.evm_circuit_pad_to = self.usable_rows() - 1;
+        block.exp_circuit_pad_to = self.usable_rows();
+    }
+
+    /// Identifier of the configuration, used to name the cached keys.
+    pub fn id(&self) -> String {
+        format!(
+            "k{}_txs{}_calldata{}_rws{}_copy{}_bytecode{}_mpt{}_{}",
+            self.degree,
+            self.max_txs,
+            self.max_calldata,
+            self.max_rws,
+            self.max_copy_rows,
+            self.max_bytecode,
+            self.max_mpt_rows,
+            self.hardfork
+        )
+    }
+}
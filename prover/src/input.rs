@@ -0,0 +1,96 @@
+//! Blocks to prove, either read from a file or fetched from a node.
+
+use crate::config::ProverConfig;
+use anyhow::{bail, Context, Result};
+use bus_mapping::{
+    circuit_input_builder::{BuilderClient, CircuitInputBuilder},
+    mock::BlockData,
+    rpc::GethClient,
+    serialization::{self, Format},
+};
+use clap::Args;
+use eth_types::geth_types::GethData;
+use ethers_providers::Http;
+use halo2_proofs::halo2curves::bn256::Fr;
+use std::{fs::File, io::BufReader, path::PathBuf};
+use url::Url;
+use zkevm_circuits::witness::{block_convert, Block};
+
+/// Block to prove
+#[derive(Args, Debug, Clone)]
+pub struct Input {
+    /// JSON file with the block, its execution traces and the accounts it
+    /// accesses
+    #[clap(long, conflicts_with_all = &["witness_block", "rpc", "block"])]
+    pub witness: Option<PathBuf>,
+    /// Witness block written by the `witness` command, in JSON when the file
+    /// has the `.json` extension and in binary otherwise
+    #[clap(long, conflicts_with_all = &["rpc", "block"])]
+    pub witness_block: Option<PathBuf>,
+    /// JSON-RPC URL of a geth node to fetch the block from
+    #[clap(long, requires = "block")]
+    pub rpc: Option<Url>,
+    /// Number of the block to fetch from the node
+    #[clap(long, requires = "rpc")]
+    pub block: Option<u64>,
+}
+
+impl Input {
+    /// Returns the circuit input builder with the block processed using the
+    /// parameters of `config`.
+    async fn builder(&self, config: &ProverConfig) -> Result<CircuitInputBuilder> {
+        let circuits_params = config.circuits_params();
+        match (&self.witness, &self.rpc, self.block) {
+            (Some(path), _, _) => {
+                let file = File::open(path)
+                    .with_context(|| format!("cannot open witness {}", path.display()))?;
+                let geth_data: GethData = serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| format!("cannot parse witness {}", path.display()))?;
+                let mut builder =
+                    BlockData::new_from_geth_data_with_params(geth_data.clone(), circuits_params)
+                        .new_circuit_input_builder();
+                builder.handle_block(&geth_data.eth_block, &geth_data.geth_traces)?;
+                Ok(builder)
+            }
+            (None, Some(url), Some(block_num)) => {
+                log::info!("fetching block {} from {}", block_num, url);
+                let cli =
+                    BuilderClient::new(GethClient::new(Http::new(url.clone())), circuits_params)
+                        .await?;
+                let (builder, _) = cli.gen_inputs(block_num).await?;
+                Ok(builder)
+            }
+            _ => bail!("either --witness, --witness-block or --rpc and --block must be given"),
+        }
+    }
+
+    /// Returns the witness block to prove with the circuit of `config`.
+    pub async fn witness_block(&self, config: &ProverConfig) -> Result<Block<Fr>> {
+        if let Some(path) = &self.witness_block {
+            let file = File::open(path)
+                .with_context(|| format!("cannot open witness block {}", path.display()))?;
+            let block: Block<Fr> =
+                serialization::from_reader(BufReader::new(file), Format::from_path(path))
+                    .with_context(|| format!("cannot parse witness block {}", path.display()))?;
+            if block.circuits_params != config.circuits_params() {
+                bail!(
+                    "the witness block {} was built with other circuit parameters: {:?}",
+                    path.display(),
+                    block.circuits_params
+                );
+            }
+            return Ok(block);
+        }
+
+        let builder = self.builder(config).await?;
+        let chunks = builder.block.chunks(&builder.code_db)?;
+        if chunks.len() > 1 {
+            bail!(
+                "the block needs {} chunks with max_rws = {}",
+                chunks.len(),
+                config.max_rws
+            );
+        }
+        Ok(block_convert(&builder.block, &builder.code_db)?)
+    }
+}
@@ -0,0 +1,127 @@
+//! KZG parameters and SuperCircuit keys, cached on disk.
+
+use crate::config::{ProverCircuit, ProverConfig};
+use anyhow::{bail, Context, Result};
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, G1Affine},
+    plonk::{keygen_pk, keygen_vk, ProvingKey, VerifyingKey},
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    SerdeFormat,
+};
+use rand::rngs::OsRng;
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// Locations of the KZG parameters and of the cached keys
+#[derive(clap::Args, Debug, Clone)]
+pub struct KeyCache {
+    /// KZG parameters file.  When it doesn't exist, keygen and prove generate
+    /// parameters with an insecure setup and write them to it, which is only
+    /// suitable for testing, while verify fails.
+    #[clap(long, default_value = "kzg_bn254.params")]
+    pub params: PathBuf,
+    /// Directory of the proving and verifying keys, one pair per
+    /// configuration
+    #[clap(long, default_value = "keys")]
+    pub keys_dir: PathBuf,
+}
+
+impl KeyCache {
+    /// Load the KZG parameters for circuits of `degree`, generating them if
+    /// the parameters file doesn't exist.
+    pub fn load_or_setup_params(&self, degree: u32) -> Result<ParamsKZG<Bn256>> {
+        if !self.params.exists() {
+            log::warn!(
+                "generating KZG params of degree {} with an insecure setup into {}",
+                degree,
+                self.params.display()
+            );
+            let params = ParamsKZG::<Bn256>::setup(degree, OsRng);
+            params.write(&mut BufWriter::new(File::create(&self.params)?))?;
+        }
+        self.load_params(degree)
+    }
+
+    /// Load the KZG parameters for circuits of `degree`, which fails if the
+    /// parameters file doesn't exist.
+    pub fn load_params(&self, degree: u32) -> Result<ParamsKZG<Bn256>> {
+        if !self.params.exists() {
+            bail!(
+                "no KZG params in {}, run keygen first",
+                self.params.display()
+            );
+        }
+        log::info!("loading KZG params from {}", self.params.display());
+        let mut reader = BufReader::new(File::open(&self.params)?);
+        let mut params = ParamsKZG::<Bn256>::read(&mut reader)
+            .with_context(|| format!("cannot read params {}", self.params.display()))?;
+        if params.k() < degree {
+            bail!(
+                "params of degree {} are too small for circuits of degree {}",
+                params.k(),
+                degree
+            );
+        }
+        params.downsize(degree);
+        Ok(params)
+    }
+
+    fn key_path(&self, config: &ProverConfig, ext: &str) -> PathBuf {
+        self.keys_dir
+            .join(format!("super_circuit_{}.{}", config.id(), ext))
+    }
+
+    /// Load the proving key of `config`, or generate it from `circuit` and
+    /// cache it along with the verifying key.
+    pub fn proving_key(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        config: &ProverConfig,
+        circuit: &ProverCircuit,
+    ) -> Result<ProvingKey<G1Affine>> {
+        let pk_path = self.key_path(config, "pk");
+        if pk_path.exists() {
+            log::info!("loading proving key from {}", pk_path.display());
+            let mut reader = BufReader::new(File::open(&pk_path)?);
+            return ProvingKey::read::<_, ProverCircuit>(&mut reader, SerdeFormat::RawBytes)
+                .with_context(|| format!("cannot read proving key {}", pk_path.display()));
+        }
+
+        log::info!("generating keys for {}", config.id());
+        let vk = keygen_vk(params, circuit)?;
+        let pk = keygen_pk(params, vk, circuit)?;
+        fs::create_dir_all(&self.keys_dir)?;
+        write_key(&pk_path, |writer| pk.write(writer, SerdeFormat::RawBytes))?;
+        write_key(&self.key_path(config, "vk"), |writer| {
+            pk.get_vk().write(writer, SerdeFormat::RawBytes)
+        })?;
+        Ok(pk)
+    }
+
+    /// Load the cached verifying key of `config`.
+    pub fn verifying_key(&self, config: &ProverConfig) -> Result<VerifyingKey<G1Affine>> {
+        let vk_path = self.key_path(config, "vk");
+        if !vk_path.exists() {
+            bail!(
+                "no verifying key for {} in {}, run keygen first",
+                config.id(),
+                self.keys_dir.display()
+            );
+        }
+        let mut reader = BufReader::new(File::open(&vk_path)?);
+        VerifyingKey::read::<_, ProverCircuit>(&mut reader, SerdeFormat::RawBytes)
+            .with_context(|| format!("cannot read verifying key {}", vk_path.display()))
+    }
+}
+
+fn write_key(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> Result<()> {
+    log::info!("writing {}", path.display());
+    write(&mut BufWriter::new(File::create(path)?))
+        .with_context(|| format!("cannot write key {}", path.display()))
+}
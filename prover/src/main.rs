@@ -0,0 +1,230 @@
+//! Command line prover of the SuperCircuit: generates the keys of a circuit
+//! configuration, proves blocks and verifies their proofs.
+//!
+//! The challenges of the SuperCircuit are a fixed mock randomness, so the
+//! proofs are not sound (see [`config`]): the prover is only for testing.
+
+mod config;
+mod input;
+mod keys;
+mod proof;
+
+use anyhow::{bail, Result};
+use bus_mapping::serialization::{self, Format};
+use clap::{Parser, Subcommand};
+use config::{ProverCircuit, ProverConfig, MOCK_RANDOMNESS};
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, verify_proof, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use input::Input;
+use keys::KeyCache;
+use proof::ProofFile;
+use rand::rngs::OsRng;
+use std::{fs::File, io::BufWriter, path::PathBuf};
+use zkevm_circuits::{util::SubCircuit, witness::Block};
+
+/// zkEVM SuperCircuit prover.  Its proofs are NOT sound, because the circuit
+/// challenges are a fixed mock randomness: only use it for testing.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate and cache the keys of a configuration, using a block to build
+    /// the circuit
+    Keygen {
+        #[clap(flatten)]
+        config: ProverConfig,
+        #[clap(flatten)]
+        cache: KeyCache,
+        #[clap(flatten)]
+        input: Input,
+    },
+    /// Build the witness of a block and write it, to be proved later with
+    /// `--witness-block`
+    Witness {
+        #[clap(flatten)]
+        config: ProverConfig,
+        #[clap(flatten)]
+        input: Input,
+        /// Witness block file to write, in JSON when it has the `.json`
+        /// extension and in binary otherwise
+        #[clap(long, short, default_value = "witness.bin")]
+        output: PathBuf,
+    },
+    /// Prove a block and write the proof with its instance values
+    Prove {
+        #[clap(flatten)]
+        config: ProverConfig,
+        #[clap(flatten)]
+        cache: KeyCache,
+        #[clap(flatten)]
+        input: Input,
+        /// Proof file to write
+        #[clap(long, short, default_value = "proof.json")]
+        output: PathBuf,
+    },
+    /// Verify a proof file
+    Verify {
+        #[clap(flatten)]
+        cache: KeyCache,
+        /// Proof file to verify
+        proof: PathBuf,
+    },
+}
+
+/// Build the SuperCircuit of the witness `block` and its instance values.
+fn build_circuit(
+    config: &ProverConfig,
+    mut block: Block<Fr>,
+) -> Result<(ProverCircuit, Vec<Vec<Fr>>)> {
+    let calldata_len: usize = block.txs.iter().map(|tx| tx.call_data.len()).sum();
+    if block.txs.len() > config.max_txs || calldata_len > config.max_calldata {
+        bail!(
+            "the block has {} txs with {} bytes of calldata, more than max_txs = {} or \
+            max_calldata = {}",
+            block.txs.len(),
+            calldata_len,
+            config.max_txs,
+            config.max_calldata
+        );
+    }
+    block.randomness = Fr::from(MOCK_RANDOMNESS);
+    config.pad_block(&mut block);
+    let (_, rows_needed) = ProverCircuit::min_num_rows_block(&block);
+    if rows_needed > config.usable_rows() {
+        bail!(
+            "the block needs {} rows, more than a circuit of degree {}",
+            rows_needed,
+            config.degree
+        );
+    }
+
+    let circuit = ProverCircuit::new_from_block(&block);
+    let instance = circuit.instance();
+    Ok((circuit, instance))
+}
+
+fn prove(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: ProverCircuit,
+    instance: &[Vec<Fr>],
+) -> Result<Vec<u8>> {
+    let instance: Vec<&[Fr]> = instance.iter().map(|column| column.as_slice()).collect();
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        _,
+        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+        ProverCircuit,
+    >(params, pk, &[circuit], &[&instance], OsRng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+fn verify(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instance: &[Vec<Fr>],
+) -> Result<()> {
+    let instance: Vec<&[Fr]> = instance.iter().map(|column| column.as_slice()).collect();
+    let verifier_params = params.verifier_params();
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+    verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(
+        verifier_params,
+        vk,
+        SingleStrategy::new(params),
+        &[&instance],
+        &mut transcript,
+    )?;
+    Ok(())
+}
+
+async fn run(command: Command) -> Result<()> {
+    log::warn!(
+        "the circuit challenges are the mock randomness {:#x}: proofs are NOT sound",
+        MOCK_RANDOMNESS
+    );
+    match command {
+        Command::Keygen {
+            config,
+            cache,
+            input,
+        } => {
+            config.check()?;
+            let block = input.witness_block(&config).await?;
+            let (circuit, _) = build_circuit(&config, block)?;
+            let params = cache.load_or_setup_params(config.degree)?;
+            cache.proving_key(&params, &config, &circuit)?;
+        }
+        Command::Witness {
+            config,
+            input,
+            output,
+        } => {
+            config.check()?;
+            let block = input.witness_block(&config).await?;
+            let writer = BufWriter::new(File::create(&output)?);
+            serialization::to_writer(writer, Format::from_path(&output), &block)?;
+            log::info!("witness block written to {}", output.display());
+        }
+        Command::Prove {
+            config,
+            cache,
+            input,
+            output,
+        } => {
+            config.check()?;
+            let block = input.witness_block(&config).await?;
+            let number = block.eth_block.number.unwrap_or_default();
+            let (circuit, instance) = build_circuit(&config, block)?;
+            let params = cache.load_or_setup_params(config.degree)?;
+            let pk = cache.proving_key(&params, &config, &circuit)?;
+
+            log::info!("proving block {}", number);
+            let proof = prove(&params, &pk, circuit, &instance)?;
+            ProofFile::new(config, &instance, &proof).write(&output)?;
+            log::info!("proof written to {}", output.display());
+        }
+        Command::Verify { cache, proof } => {
+            let proof_file = ProofFile::read(&proof)?;
+            proof_file.config.check()?;
+            let params = cache.load_params(proof_file.config.degree)?;
+            let vk = cache.verifying_key(&proof_file.config)?;
+            verify(&params, &vk, &proof_file.proof()?, &proof_file.instance()?)?;
+            log::info!("proof {} is valid", proof.display());
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    run(Args::parse().command).await
+}
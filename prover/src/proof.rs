@@ -0,0 +1,113 @@
+//! Proof file, with the configuration and the instance values needed to
+//! verify the proof.
+
+use crate::config::ProverConfig;
+use anyhow::{anyhow, Context, Result};
+use halo2_proofs::halo2curves::{bn256::Fr, group::ff::PrimeField};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// Proof of a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofFile {
+    /// Configuration of the circuit
+    pub config: ProverConfig,
+    /// Values of the instance columns, in hex of their little-endian
+    /// representation
+    pub instance: Vec<Vec<String>>,
+    /// Proof, in hex
+    pub proof: String,
+}
+
+impl ProofFile {
+    /// Create the proof file contents of `proof`.
+    pub fn new(config: ProverConfig, instance: &[Vec<Fr>], proof: &[u8]) -> Self {
+        Self {
+            config,
+            instance: instance
+                .iter()
+                .map(|column| column.iter().map(|v| hex::encode(v.to_repr())).collect())
+                .collect(),
+            proof: hex::encode(proof),
+        }
+    }
+
+    /// Values of the instance columns
+    pub fn instance(&self) -> Result<Vec<Vec<Fr>>> {
+        self.instance
+            .iter()
+            .map(|column| column.iter().map(|v| decode_field(v)).collect())
+            .collect()
+    }
+
+    /// Proof bytes
+    pub fn proof(&self) -> Result<Vec<u8>> {
+        hex::decode(&self.proof).context("invalid proof hex")
+    }
+
+    /// Read a proof file
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("cannot open proof {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("cannot parse proof {}", path.display()))
+    }
+
+    /// Write a proof file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("cannot create proof {}", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+fn decode_field(value: &str) -> Result<Fr> {
+    let mut repr = <Fr as PrimeField>::Repr::default();
+    let bytes = hex::decode(value).context("invalid instance hex")?;
+    if bytes.len() != repr.as_ref().len() {
+        return Err(anyhow!("invalid instance value {}", value));
+    }
+    repr.as_mut().copy_from_slice(&bytes);
+    Option::from(Fr::from_repr(repr)).ok_or_else(|| anyhow!("invalid instance value {}", value))
+}
+
+#[cfg(test)]
+mod proof_tests {
+    use super::*;
+    use crate::config::{MAX_CALLDATA, MAX_TXS};
+    use eth_types::evm_types::Hardfork;
+
+    #[test]
+    fn proof_file_roundtrip() {
+        let config = ProverConfig {
+            degree: 18,
+            max_txs: MAX_TXS,
+            max_calldata: MAX_CALLDATA,
+            max_rws: 1000,
+            max_copy_rows: 1000,
+            max_bytecode: 512,
+            max_mpt_rows: 1000,
+            hardfork: Hardfork::Shanghai,
+        };
+        let instance = vec![
+            vec![Fr::from(1), -Fr::one()],
+            vec![],
+            vec![Fr::from(u64::MAX)],
+        ];
+        let proof_file = ProofFile::new(config, &instance, &[0xde, 0xad, 0xbe, 0xef]);
+
+        let json = serde_json::to_string(&proof_file).unwrap();
+        let decoded: ProofFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, proof_file);
+        assert_eq!(decoded.instance().unwrap(), instance);
+        assert_eq!(decoded.proof().unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+
+        // Values out of the field are rejected
+        assert!(decode_field(&"ff".repeat(32)).is_err());
+    }
+}
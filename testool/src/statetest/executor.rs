@@ -1,5 +1,6 @@
 use super::{AccountMatch, StateTest, StateTestResult};
 use crate::config::TestSuite;
+use crate::utils::TEST_FORK;
 use bus_mapping::circuit_input_builder::{CircuitInputBuilder, CircuitsParams};
 use bus_mapping::mock::BlockData;
 use eth_types::{geth_types, Address, Bytes, GethExecTrace, U256, U64};
@@ -131,9 +132,11 @@ fn into_traceconfig(st: StateTest) -> (String, TraceConfig, StateTestResult) {
                 difficulty: st.env.current_difficulty,
                 gas_limit: U256::from(st.env.current_gas_limit),
                 base_fee: U256::one(),
+                mix_hash: st.env.current_random.unwrap_or_default(),
             },
 
             transactions: vec![geth_types::Transaction {
+                tx_type: geth_types::TxType::Legacy,
                 from: st.from,
                 to: st.to,
                 nonce: st.nonce,
@@ -144,11 +147,13 @@ fn into_traceconfig(st: StateTest) -> (String, TraceConfig, StateTestResult) {
                 gas_tip_cap: U256::zero(),
                 call_data: st.data,
                 access_list: None,
+                block_number: st.env.current_number,
                 v: sig.v,
                 r: sig.r,
                 s: sig.s,
             }],
             accounts: st.pre,
+            hardfork: TEST_FORK.hardfork(),
             ..Default::default()
         },
         st.result,
@@ -225,6 +230,7 @@ pub fn run_test(
         difficulty: trace_config.block_constants.difficulty,
         gas_limit: trace_config.block_constants.gas_limit,
         base_fee_per_gas: Some(trace_config.block_constants.base_fee),
+        mix_hash: Some(trace_config.block_constants.mix_hash),
         transactions,
         ..eth_types::Block::default()
     };
@@ -251,7 +257,9 @@ pub fn run_test(
             max_calldata: 5000,
             max_bytecode: 5000,
             max_copy_rows: 55000,
+            max_mpt_rows: 0,
             keccak_padding: None,
+            hardfork: TEST_FORK.hardfork(),
         };
         let block_data = BlockData::new_from_geth_data_with_params(geth_data, circuits_params);
 
@@ -283,7 +291,9 @@ pub fn run_test(
             max_rws: 256,
             max_copy_rows: 256,
             max_bytecode: 512,
+            max_mpt_rows: 0,
             keccak_padding: None,
+            hardfork: TEST_FORK.hardfork(),
         };
         let (k, circuit, instance, _builder) =
             SuperCircuit::<Fr, MAX_TXS, MAX_CALLDATA, 0x100>::build(geth_data, circuits_params)
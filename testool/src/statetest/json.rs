@@ -26,6 +26,7 @@ struct TestEnv {
     current_number: String,
     current_timestamp: String,
     previous_hash: String,
+    current_random: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -206,6 +207,11 @@ impl<'a> JsonStateTestBuilder<'a> {
             current_number: parse::parse_u64(&env.current_number)?,
             current_timestamp: parse::parse_u64(&env.current_timestamp)?,
             previous_hash: parse::parse_hash(&env.previous_hash)?,
+            current_random: env
+                .current_random
+                .as_deref()
+                .map(parse::parse_hash)
+                .transpose()?,
         })
     }
 
@@ -385,6 +391,7 @@ mod test {
                 previous_hash: H256::from_str(
                     "0x5e20a0453cecd065ea59c37ac63e079ee08998b6045136a8ce6635c7912ec0b6",
                 )?,
+                current_random: None,
             },
             secret_key: Bytes::from(hex::decode(
                 "45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
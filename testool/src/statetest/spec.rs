@@ -12,6 +12,7 @@ pub struct Env {
     pub current_number: u64,
     pub current_timestamp: u64,
     pub previous_hash: H256,
+    pub current_random: Option<H256>,
 }
 
 #[derive(PartialEq, Eq, Default, Debug, Clone)]
@@ -261,6 +262,7 @@ impl StateTest {
                 current_number: 1,
                 current_timestamp: 1,
                 previous_hash: H256::default(),
+                current_random: None,
             },
             secret_key,
             from,
@@ -215,6 +215,11 @@ impl<'a> YamlStateTestBuilder<'a> {
             current_number: Self::parse_u64(&yaml["currentNumber"])?,
             current_timestamp: Self::parse_u64(&yaml["currentTimestamp"])?,
             previous_hash: Self::parse_hash(&yaml["previousHash"])?,
+            current_random: if yaml["currentRandom"].is_badvalue() {
+                None
+            } else {
+                Some(Self::parse_hash(&yaml["currentRandom"])?)
+            },
         })
     }
 
@@ -598,6 +603,7 @@ arith:
                 previous_hash: H256::from_slice(&hex::decode(
                     "5e20a0453cecd065ea59c37ac63e079ee08998b6045136a8ce6635c7912ec0b6",
                 )?),
+                current_random: None,
             },
             secret_key: Bytes::from(hex::decode(
                 "45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
@@ -1,13 +1,14 @@
 use std::str::FromStr;
 
 use anyhow::{bail, Result};
-use eth_types::{bytecode::OpcodeWithData, Bytecode, GethExecTrace, U256};
+use eth_types::{bytecode::OpcodeWithData, evm_types::Hardfork, Bytecode, GethExecTrace, U256};
 use log::{error, info};
 use prettytable::Table;
 use std::process::Command;
 
 #[derive(Debug, Eq, PartialEq, PartialOrd)]
 pub enum MainnetFork {
+    Shanghai = 15,
     Merge = 14,
     GrayGlacier = 13,
     ArrowGlacier = 12,
@@ -31,7 +32,8 @@ impl FromStr for MainnetFork {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
-            "Merge" => Self::Merge,
+            "Shanghai" => Self::Shanghai,
+            "Merge" | "Paris" => Self::Merge,
             "Gray Glacier" => Self::GrayGlacier,
             "Arrow Glacier" => Self::ArrowGlacier,
             "Altair" => Self::Altair,
@@ -51,6 +53,16 @@ impl FromStr for MainnetFork {
 }
 
 impl MainnetFork {
+    /// Returns the [`Hardfork`] whose rules are used to run the tests of
+    /// `self`.  Only the forks from London onwards are supported.
+    pub fn hardfork(&self) -> Hardfork {
+        match self {
+            Self::Shanghai => Hardfork::Shanghai,
+            Self::Merge => Hardfork::Paris,
+            _ => Hardfork::London,
+        }
+    }
+
     pub fn in_network_range(expect: &[String]) -> Result<bool, anyhow::Error> {
         let in_network = if expect.is_empty() {
             true
@@ -683,7 +683,7 @@ impl<F: Field> BytecodeCircuitConfig<F> {
     /// load fixed tables
     pub(crate) fn load_aux_tables(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         // push table: BYTE -> NUM_PUSHED:
-        // [0, OpcodeId::PUSH1] -> 0
+        // [0, OpcodeId::PUSH1] -> 0 (PUSH0 has no push data)
         // [OpcodeId::PUSH1, OpcodeId::PUSH32] -> [1..32]
         // [OpcodeId::PUSH32, 256] -> 0
         layouter.assign_region(
@@ -910,6 +910,12 @@ mod tests {
                     OpcodeId::PUSH32.as_u8(),
                     OpcodeId::ADD.as_u8(),
                 ]),
+                unroll(vec![
+                    OpcodeId::PUSH0.as_u8(),
+                    OpcodeId::PUSH1.as_u8(),
+                    OpcodeId::PUSH0.as_u8(),
+                    OpcodeId::ADD.as_u8(),
+                ]),
             ],
             true,
         );
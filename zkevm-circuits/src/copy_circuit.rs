@@ -7,6 +7,7 @@ use eth_types::Field;
 use eth_types::Word;
 use gadgets::{
     binary_number::BinaryNumberChip,
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
     less_than::{LtChip, LtConfig, LtInstruction},
     util::{and, not, or, Expr},
 };
@@ -63,6 +64,10 @@ pub struct CopyCircuitConfig<F> {
     pub value: Column<Advice>,
     /// Whether the row is padding.
     pub is_pad: Column<Advice>,
+    /// Whether the row belongs to a copy event that reads and writes the memory
+    /// of the same call (MCOPY).  In such a copy event all the memory reads
+    /// happen before the memory writes.
+    pub is_memory_copy: Column<Advice>,
     /// In case of a bytecode tag, this denotes whether or not the copied byte
     /// is an opcode or push data byte.
     pub is_code: Column<Advice>,
@@ -75,6 +80,8 @@ pub struct CopyCircuitConfig<F> {
     /// Since `src_addr` and `src_addr_end` are u64, 8 bytes are sufficient for
     /// the Lt chip.
     pub addr_lt_addr_end: LtConfig<F, 8>,
+    /// IsZero chip to check: id of the read row == id of the write row.
+    pub is_id_unchanged: IsZeroConfig<F>,
     // External tables
     /// TxTable
     pub tx_table: TxTable,
@@ -121,6 +128,7 @@ impl<F: Field> SubCircuitConfig<F> for CopyCircuitConfig<F> {
         let value = meta.advice_column_in(SecondPhase);
         let is_code = meta.advice_column();
         let is_pad = meta.advice_column();
+        let is_memory_copy = meta.advice_column();
         let is_first = copy_table.is_first;
         let id = copy_table.id;
         let addr = copy_table.addr;
@@ -138,6 +146,13 @@ impl<F: Field> SubCircuitConfig<F> for CopyCircuitConfig<F> {
             |meta| meta.query_advice(src_addr_end, Rotation::cur()),
         );
 
+        let is_id_unchanged = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_step),
+            |meta| meta.query_advice(id, Rotation::cur()) - meta.query_advice(id, Rotation::next()),
+            meta.advice_column_in(SecondPhase),
+        );
+
         meta.create_gate("verify row", |meta| {
             let mut cb = BaseConstraintBuilder::default();
 
@@ -203,8 +218,13 @@ impl<F: Field> SubCircuitConfig<F> for CopyCircuitConfig<F> {
                 ]),
                 not::expr(meta.query_advice(is_pad, Rotation::cur())),
             ]);
+            // The rw counters of a memory copy are constrained in the step gate,
+            // since all of its reads happen before its writes.
             cb.condition(
-                not::expr(meta.query_advice(is_last, Rotation::cur())),
+                and::expr([
+                    not::expr(meta.query_advice(is_last, Rotation::cur())),
+                    not::expr(meta.query_advice(is_memory_copy, Rotation::cur())),
+                ]),
                 |cb| {
                     cb.require_equal(
                         "rows[0].rw_counter + rw_diff == rows[1].rw_counter",
@@ -216,6 +236,11 @@ impl<F: Field> SubCircuitConfig<F> for CopyCircuitConfig<F> {
                         meta.query_advice(rwc_inc_left, Rotation::cur()) - rw_diff.clone(),
                         meta.query_advice(rwc_inc_left, Rotation::next()),
                     );
+                },
+            );
+            cb.condition(
+                not::expr(meta.query_advice(is_last, Rotation::cur())),
+                |cb| {
                     cb.require_equal(
                         "rows[0].rlc_acc == rows[1].rlc_acc",
                         meta.query_advice(rlc_acc, Rotation::cur()),
@@ -309,6 +334,83 @@ impl<F: Field> SubCircuitConfig<F> for CopyCircuitConfig<F> {
             cb.gate(meta.query_selector(q_step))
         });
 
+        meta.create_gate("verify memory copy step (q_step == 1)", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+
+            let is_memory_copy_cur = meta.query_advice(is_memory_copy, Rotation::cur());
+            cb.require_equal(
+                "is_memory_copy == (Memory to Memory with the same id)",
+                is_memory_copy_cur.clone(),
+                and::expr([
+                    tag.value_equals(CopyDataType::Memory, Rotation::cur())(meta),
+                    tag.value_equals(CopyDataType::Memory, Rotation::next())(meta),
+                    is_id_unchanged.expr(),
+                ]),
+            );
+            cb.require_equal(
+                "is_memory_copy is the same for the read and write rows",
+                is_memory_copy_cur.clone(),
+                meta.query_advice(is_memory_copy, Rotation::next()),
+            );
+
+            // For a memory copy of n bytes starting at rw counter c, the i-th read
+            // is at c + i and the i-th write is at c + n + i.  Every row thus
+            // shares rw_counter + rwc_inc_left == c + 2n.
+            cb.condition(is_memory_copy_cur.clone(), |cb| {
+                cb.require_zero(
+                    "is_pad == 0 for memory copy",
+                    meta.query_advice(is_pad, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "rows[0].rw_counter + rows[0].rwc_inc_left == rows[1].rw_counter + rows[1].rwc_inc_left",
+                    meta.query_advice(rw_counter, Rotation::cur())
+                        + meta.query_advice(rwc_inc_left, Rotation::cur()),
+                    meta.query_advice(rw_counter, Rotation::next())
+                        + meta.query_advice(rwc_inc_left, Rotation::next()),
+                );
+                cb.require_equal(
+                    "write row rwc_inc_left == bytes_left",
+                    meta.query_advice(rwc_inc_left, Rotation::next()),
+                    meta.query_advice(bytes_left, Rotation::cur()),
+                );
+            });
+            cb.condition(
+                and::expr([
+                    is_memory_copy_cur.clone(),
+                    meta.query_advice(is_first, Rotation::cur()),
+                ]),
+                |cb| {
+                    cb.require_equal(
+                        "rwc_inc_left == 2 * bytes_left for first step",
+                        meta.query_advice(rwc_inc_left, Rotation::cur()),
+                        2.expr() * meta.query_advice(bytes_left, Rotation::cur()),
+                    );
+                },
+            );
+            cb.condition(
+                and::expr([
+                    is_memory_copy_cur,
+                    not::expr(meta.query_advice(is_last, Rotation::next())),
+                ]),
+                |cb| {
+                    cb.require_equal(
+                        "rows[0].rw_counter + 1 == rows[2].rw_counter",
+                        meta.query_advice(rw_counter, Rotation::cur()) + 1.expr(),
+                        meta.query_advice(rw_counter, Rotation(2)),
+                    );
+                    cb.require_equal(
+                        "rows[1].rw_counter + rows[1].rwc_inc_left == rows[2].rw_counter + rows[2].rwc_inc_left",
+                        meta.query_advice(rw_counter, Rotation::next())
+                            + meta.query_advice(rwc_inc_left, Rotation::next()),
+                        meta.query_advice(rw_counter, Rotation(2))
+                            + meta.query_advice(rwc_inc_left, Rotation(2)),
+                    );
+                },
+            );
+
+            cb.gate(meta.query_selector(q_step))
+        });
+
         meta.create_gate("verify_step (q_step == 0)", |meta| {
             let mut cb = BaseConstraintBuilder::default();
 
@@ -411,9 +513,11 @@ impl<F: Field> SubCircuitConfig<F> for CopyCircuitConfig<F> {
             is_last,
             value,
             is_pad,
+            is_memory_copy,
             is_code,
             q_enable,
             addr_lt_addr_end,
+            is_id_unchanged,
             copy_table,
             tx_table,
             rw_table,
@@ -430,9 +534,13 @@ impl<F: Field> CopyCircuitConfig<F> {
         offset: &mut usize,
         tag_chip: &BinaryNumberChip<F, CopyDataType, 3>,
         lt_chip: &LtChip<F, 8>,
+        is_id_unchanged_chip: &IsZeroChip<F>,
         challenges: Challenges<Value<F>>,
         copy_event: &CopyEvent,
     ) -> Result<(), Error> {
+        let is_memory_copy = Value::known(F::from(copy_event.is_memory_copy()));
+        let id_diff = number_or_hash_to_field(&copy_event.src_id, challenges.evm_word())
+            - number_or_hash_to_field(&copy_event.dst_id, challenges.evm_word());
         for (step_idx, (tag, table_row, circuit_row)) in
             CopyTable::assignments(copy_event, challenges)
                 .iter()
@@ -479,10 +587,18 @@ impl<F: Field> CopyCircuitConfig<F> {
                 )?;
             }
 
+            // is_memory_copy
+            region.assign_advice(
+                || format!("is_memory_copy at row: {}", *offset),
+                self.is_memory_copy,
+                *offset,
+                || is_memory_copy,
+            )?;
+
             //tag
             tag_chip.assign(region, *offset, tag)?;
 
-            // lt chip
+            // lt chip and is_zero chip
             if is_read {
                 lt_chip.assign(
                     region,
@@ -490,6 +606,7 @@ impl<F: Field> CopyCircuitConfig<F> {
                     F::from(copy_event.src_addr + u64::try_from(step_idx).unwrap() / 2u64),
                     F::from(copy_event.src_addr_end),
                 )?;
+                is_id_unchanged_chip.assign(region, *offset, id_diff)?;
             }
 
             *offset += 1;
@@ -516,6 +633,7 @@ impl<F: Field> CopyCircuitConfig<F> {
 
         let tag_chip = BinaryNumberChip::construct(self.copy_table.tag);
         let lt_chip = LtChip::construct(self.addr_lt_addr_end);
+        let is_id_unchanged_chip = IsZeroChip::construct(self.is_id_unchanged.clone());
 
         layouter.assign_region(
             || "assign copy table",
@@ -527,17 +645,39 @@ impl<F: Field> CopyCircuitConfig<F> {
                         &mut offset,
                         &tag_chip,
                         &lt_chip,
+                        &is_id_unchanged_chip,
                         challenges,
                         copy_event,
                     )?;
                 }
 
                 for _ in 0..max_copy_rows - copy_rows_needed - 2 {
-                    self.assign_padding_row(&mut region, &mut offset, false, &tag_chip, &lt_chip)?;
+                    self.assign_padding_row(
+                        &mut region,
+                        &mut offset,
+                        false,
+                        &tag_chip,
+                        &lt_chip,
+                        &is_id_unchanged_chip,
+                    )?;
                 }
 
-                self.assign_padding_row(&mut region, &mut offset, true, &tag_chip, &lt_chip)?;
-                self.assign_padding_row(&mut region, &mut offset, true, &tag_chip, &lt_chip)?;
+                self.assign_padding_row(
+                    &mut region,
+                    &mut offset,
+                    true,
+                    &tag_chip,
+                    &lt_chip,
+                    &is_id_unchanged_chip,
+                )?;
+                self.assign_padding_row(
+                    &mut region,
+                    &mut offset,
+                    true,
+                    &tag_chip,
+                    &lt_chip,
+                    &is_id_unchanged_chip,
+                )?;
 
                 Ok(())
             },
@@ -551,6 +691,7 @@ impl<F: Field> CopyCircuitConfig<F> {
         is_last_two: bool,
         tag_chip: &BinaryNumberChip<F, CopyDataType, 3>,
         lt_chip: &LtChip<F, 8>,
+        is_id_unchanged_chip: &IsZeroChip<F>,
     ) -> Result<(), Error> {
         if !is_last_two {
             // q_enable
@@ -636,6 +777,13 @@ impl<F: Field> CopyCircuitConfig<F> {
             *offset,
             || Value::known(F::zero()),
         )?;
+        // is_memory_copy
+        region.assign_advice(
+            || format!("assign is_memory_copy {}", *offset),
+            self.is_memory_copy,
+            *offset,
+            || Value::known(F::zero()),
+        )?;
         // rw_counter
         region.assign_advice(
             || format!("assign rw_counter {}", *offset),
@@ -654,6 +802,8 @@ impl<F: Field> CopyCircuitConfig<F> {
         tag_chip.assign(region, *offset, &CopyDataType::Padding)?;
         // Assign LT gadget
         lt_chip.assign(region, *offset, F::zero(), F::one())?;
+        // Assign IsZero gadget
+        is_id_unchanged_chip.assign(region, *offset, Value::known(F::zero()))?;
 
         *offset += 1;
 
@@ -887,13 +1037,19 @@ pub mod dev {
 
 #[cfg(test)]
 mod tests {
-    use super::dev::test_copy_circuit_from_block;
+    use super::dev::{test_copy_circuit, test_copy_circuit_from_block};
+    use super::ExternalData;
     use crate::evm_circuit::test::rand_bytes;
     use crate::evm_circuit::witness::block_convert;
+    use crate::table::RwTableTag;
+    use crate::witness::{Rw, RwMap};
     use bus_mapping::evm::{gen_sha3_code, MemoryKind};
     use bus_mapping::{
-        circuit_input_builder::{CircuitInputBuilder, CircuitsParams},
+        circuit_input_builder::{
+            CircuitInputBuilder, CircuitsParams, CopyDataType, CopyEvent, NumberOrHash,
+        },
         mock::BlockData,
+        operation::RWCounter,
     };
     use eth_types::{bytecode, geth_types::GethData, ToWord, Word};
     use halo2_proofs::dev::VerifyFailure;
@@ -901,6 +1057,7 @@ mod tests {
     use mock::test_ctx::helpers::account_0_code_account_1_no_code;
     use mock::{TestContext, MOCK_ACCOUNTS};
     use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
 
     fn gen_calldatacopy_data() -> CircuitInputBuilder {
         let length = 0x0fffusize;
@@ -1067,6 +1224,63 @@ mod tests {
         assert_eq!(test_copy_circuit_from_block(10, block), Ok(()));
     }
 
+    /// Builds the copy event of an MCOPY of 8 bytes from memory address 0 to
+    /// the overlapping memory address 4, together with the memory reads and
+    /// writes it does.
+    fn gen_mcopy_data() -> (CopyEvent, ExternalData) {
+        let call_id = 1;
+        let (src_addr, dst_addr, length) = (0u64, 4u64, 8u64);
+        let rw_counter_start = 1;
+        let bytes: Vec<u8> = (1..=length as u8).collect();
+
+        let reads = bytes.iter().enumerate().map(|(idx, byte)| Rw::Memory {
+            rw_counter: rw_counter_start + idx,
+            is_write: false,
+            call_id,
+            memory_address: src_addr + idx as u64,
+            byte: *byte,
+        });
+        let writes = bytes.iter().enumerate().map(|(idx, byte)| Rw::Memory {
+            rw_counter: rw_counter_start + length as usize + idx,
+            is_write: true,
+            call_id,
+            memory_address: dst_addr + idx as u64,
+            byte: *byte,
+        });
+        let rws = RwMap(HashMap::from([(
+            RwTableTag::Memory,
+            reads.chain(writes).collect(),
+        )]));
+
+        let copy_event = CopyEvent {
+            src_type: CopyDataType::Memory,
+            src_id: NumberOrHash::Number(call_id),
+            src_addr,
+            src_addr_end: src_addr + length,
+            dst_type: CopyDataType::Memory,
+            dst_id: NumberOrHash::Number(call_id),
+            dst_addr,
+            log_id: None,
+            rw_counter_start: RWCounter(rw_counter_start),
+            bytes: bytes.into_iter().map(|byte| (byte, false)).collect(),
+        };
+        let external_data = ExternalData {
+            max_rws: 64,
+            rws,
+            ..Default::default()
+        };
+        (copy_event, external_data)
+    }
+
+    #[test]
+    fn copy_circuit_valid_mcopy_overlapping() {
+        let (copy_event, external_data) = gen_mcopy_data();
+        assert_eq!(
+            test_copy_circuit::<Fr>(10, vec![copy_event], 64, external_data),
+            Ok(())
+        );
+    }
+
     #[test]
     fn copy_circuit_invalid_calldatacopy() {
         let mut builder = gen_calldatacopy_data();
@@ -1147,6 +1361,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn copy_circuit_invalid_mcopy_overlapping() {
+        let (mut copy_event, external_data) = gen_mcopy_data();
+
+        // modify first byte of the copy event
+        copy_event.bytes[0].0 = copy_event.bytes[0].0.wrapping_add(1);
+
+        assert_error_matches(
+            test_copy_circuit::<Fr>(10, vec![copy_event], 64, external_data),
+            vec!["Memory lookup", "Memory lookup"],
+        );
+    }
+
     fn assert_error_matches(result: Result<(), Vec<VerifyFailure>>, names: Vec<&str>) {
         let errors = result.expect_err("result is not an error");
         assert_eq!(errors.len(), names.len(), "{:?}", errors);
@@ -13,13 +13,16 @@ pub(crate) mod util;
 
 pub mod table;
 
-use crate::table::{BlockTable, BytecodeTable, CopyTable, ExpTable, KeccakTable, RwTable, TxTable};
+use crate::table::{
+    BlockTable, BytecodeTable, CopyTable, ExpTable, KeccakTable, RwTable, TxTable, WithdrawalTable,
+};
 use crate::util::{log2_ceil, Challenges, SubCircuit, SubCircuitConfig};
 pub use crate::witness;
 use bus_mapping::evm::OpcodeId;
-use eth_types::Field;
+use eth_types::{evm_types::Hardfork, Field};
 use execution::ExecutionConfig;
 use itertools::Itertools;
+use log::error;
 use strum::IntoEnumIterator;
 use table::FixedTableTag;
 use witness::Block;
@@ -38,6 +41,8 @@ pub struct EvmCircuitConfig<F> {
     copy_table: CopyTable,
     keccak_table: KeccakTable,
     exp_table: ExpTable,
+    withdrawal_table: WithdrawalTable,
+    hardfork: Hardfork,
 }
 
 /// Circuit configuration arguments
@@ -58,6 +63,10 @@ pub struct EvmCircuitConfigArgs<F: Field> {
     pub keccak_table: KeccakTable,
     /// ExpTable
     pub exp_table: ExpTable,
+    /// WithdrawalTable
+    pub withdrawal_table: WithdrawalTable,
+    /// Hardfork whose rules are used to build the fixed table
+    pub hardfork: Hardfork,
 }
 
 impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
@@ -76,6 +85,8 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
             copy_table,
             keccak_table,
             exp_table,
+            withdrawal_table,
+            hardfork,
         }: Self::ConfigArgs,
     ) -> Self {
         let fixed_table = [(); 4].map(|_| meta.fixed_column());
@@ -92,6 +103,7 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
             &copy_table,
             &keccak_table,
             &exp_table,
+            &withdrawal_table,
         ));
 
         Self {
@@ -105,12 +117,22 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
             copy_table,
             keccak_table,
             exp_table,
+            withdrawal_table,
+            hardfork,
         }
     }
 }
 
 impl<F: Field> EvmCircuitConfig<F> {
-    /// Load fixed table
+    /// Returns the config with its fixed table built for `hardfork`.  The
+    /// constraints don't depend on the hardfork, which only selects the rows
+    /// of the fixed table, so this lets a circuit configured without
+    /// parameters prove the blocks of any hardfork.
+    pub(crate) fn with_hardfork(self, hardfork: Hardfork) -> Self {
+        Self { hardfork, ..self }
+    }
+
+    /// Load fixed table, built for the hardfork of the config
     pub fn load_fixed_table(
         &self,
         layouter: &mut impl Layouter<F>,
@@ -120,7 +142,11 @@ impl<F: Field> EvmCircuitConfig<F> {
             || "fixed table",
             |mut region| {
                 for (offset, row) in std::iter::once([F::zero(); 4])
-                    .chain(fixed_table_tags.iter().flat_map(|tag| tag.build()))
+                    .chain(
+                        fixed_table_tags
+                            .iter()
+                            .flat_map(|tag| tag.build(self.hardfork)),
+                    )
                     .enumerate()
                 {
                     for (column, value) in self.fixed_table.iter().zip_eq(row) {
@@ -197,6 +223,9 @@ impl<F: Field> EvmCircuit<F> {
                     num_rows += step.execution_state.get_step_height();
                 }
             }
+            for step in &block.withdrawal_steps {
+                num_rows += step.execution_state.get_step_height();
+            }
             num_rows += 1; // EndBlock
         } else {
             num_rows += block.evm_circuit_pad_to;
@@ -217,7 +246,7 @@ impl<F: Field> SubCircuit<F> for EvmCircuit<F> {
         let num_rows_required_for_execution_steps: usize = Self::get_num_rows_required(block);
         let num_rows_required_for_fixed_table: usize = detect_fixed_table_tags(block)
             .iter()
-            .map(|tag| tag.build::<F>().count())
+            .map(|tag| tag.build::<F>(block.circuits_params.hardfork).count())
             .sum();
         (
             std::cmp::max(
@@ -236,6 +265,13 @@ impl<F: Field> SubCircuit<F> for EvmCircuit<F> {
         layouter: &mut impl Layouter<F>,
     ) -> Result<(), Error> {
         let block = self.block.as_ref().unwrap();
+        if block.circuits_params.hardfork != config.hardfork {
+            error!(
+                "block built for {} but the EVM circuit is configured for {}",
+                block.circuits_params.hardfork, config.hardfork
+            );
+            return Err(Error::Synthesis);
+        }
 
         config.load_fixed_table(layouter, self.fixed_table_tags.clone())?;
         config.load_byte_table(layouter)?;
@@ -326,6 +362,7 @@ pub mod test {
             let copy_table = CopyTable::construct(meta, q_copy_table);
             let keccak_table = KeccakTable::construct(meta);
             let exp_table = ExpTable::construct(meta);
+            let withdrawal_table = WithdrawalTable::construct(meta);
             let challenges = Challenges::construct(meta);
             let challenges_expr = challenges.exprs(meta);
 
@@ -341,6 +378,8 @@ pub mod test {
                         copy_table,
                         keccak_table,
                         exp_table,
+                        withdrawal_table,
+                        hardfork: Hardfork::default(),
                     },
                 ),
                 challenges,
@@ -356,6 +395,7 @@ pub mod test {
 
             let (config, challenges) = config;
             let challenges = challenges.values(&mut layouter);
+            let config = config.with_hardfork(block.circuits_params.hardfork);
 
             config.tx_table.load(
                 &mut layouter,
@@ -363,7 +403,9 @@ pub mod test {
                 block.circuits_params.max_txs,
                 &challenges,
             )?;
-            block.rws.check_rw_counter_sanity();
+            block
+                .rws
+                .check_rw_counter_sanity(block.context.chunk.rw_counter_start);
             config.rw_table.load(
                 &mut layouter,
                 &block.rws.table_assignments(),
@@ -381,6 +423,11 @@ pub mod test {
                 .keccak_table
                 .dev_load(&mut layouter, &block.sha3_inputs, &challenges)?;
             config.exp_table.load(&mut layouter, block)?;
+            config.withdrawal_table.load(
+                &mut layouter,
+                &block.withdrawals,
+                &block.withdrawal_blocks,
+            )?;
 
             self.synthesize_sub(&config, &challenges, &mut layouter)
         }
@@ -418,7 +465,7 @@ pub mod test {
         let num_rows_required_for_rw_table: usize = block.circuits_params.max_rws;
         let num_rows_required_for_fixed_table: usize = detect_fixed_table_tags(block)
             .iter()
-            .map(|tag| tag.build::<F>().count())
+            .map(|tag| tag.build::<F>(block.circuits_params.hardfork).count())
             .sum();
         let num_rows_required_for_bytecode_table: usize = block
             .bytecodes
@@ -487,11 +534,24 @@ pub mod test {
 mod evm_circuit_stats {
     use super::test::*;
     use super::*;
-    use crate::{evm_circuit::step::ExecutionState, witness::block_convert};
-    use bus_mapping::{circuit_input_builder::CircuitsParams, mock::BlockData};
-    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData};
+    use crate::{
+        evm_circuit::step::ExecutionState,
+        pi_circuit::public_data_convert,
+        witness::{block_convert, block_convert_chunks},
+    };
+    use bus_mapping::{circuit_input_builder::CircuitsParams, mock::BlockData, Error};
+    use eth_types::{
+        bytecode,
+        evm_types::OpcodeId,
+        geth_types::{GethData, Withdrawal},
+        Address, Word, H256,
+    };
     use halo2_proofs::halo2curves::bn256::Fr;
-    use mock::test_ctx::{helpers::*, TestContext};
+    use mock::{
+        eth,
+        test_ctx::{helpers::*, TestContext},
+        MOCK_ACCOUNTS,
+    };
     use strum::IntoEnumIterator;
 
     fn get_empty_witness_block() -> Block<Fr> {
@@ -521,6 +581,223 @@ mod evm_circuit_stats {
         run_test_circuit(block).unwrap();
     }
 
+    #[test]
+    pub fn evm_circuit_batch_of_blocks() {
+        let code = bytecode! {
+            NUMBER
+            TIMESTAMP
+            COINBASE
+            GASLIMIT
+            BASEFEE
+            DIFFICULTY
+            PUSH2(0xcafe)
+            BLOCKHASH
+            STOP
+        };
+        // Both blocks share the accounts, and each one has a tx from a
+        // different sender, so that the traces of the second block don't
+        // depend on the state changes of the first one.  The hash of the
+        // block n is n.
+        let block_ctx = |number: u64, sender: usize| -> GethData {
+            TestContext::<3, 1>::new(
+                Some((0xcafd..number).map(Word::from).collect()),
+                |accs| {
+                    accs[0]
+                        .address(MOCK_ACCOUNTS[0])
+                        .balance(eth(10))
+                        .code(code.clone());
+                    accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+                    accs[2].address(MOCK_ACCOUNTS[2]).balance(eth(10));
+                },
+                |mut txs, accs| {
+                    txs[0].from(accs[sender].address).to(accs[0].address);
+                },
+                |block, _txs| {
+                    block
+                        .number(number)
+                        .hash(H256::from_low_u64_be(number))
+                        .parent_hash(H256::from_low_u64_be(number - 1))
+                        .timestamp(Word::from(number * 12))
+                        .author(MOCK_ACCOUNTS[2 + sender])
+                },
+            )
+            .unwrap()
+            .into()
+        };
+        let block1 = block_ctx(0xcafe, 1);
+        let block2 = block_ctx(0xcaff, 2);
+
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block1.clone(),
+            CircuitsParams {
+                max_txs: 2,
+                ..CircuitsParams::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_blocks([
+                (&block1.eth_block, block1.geth_traces.as_slice()),
+                (&block2.eth_block, block2.geth_traces.as_slice()),
+            ])
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(
+            block
+                .txs
+                .iter()
+                .map(|tx| tx.block_number)
+                .collect::<Vec<_>>(),
+            vec![0xcafe, 0xcaff]
+        );
+        run_test_circuit(block).unwrap();
+    }
+
+    #[test]
+    pub fn evm_circuit_batch_with_withdrawals() {
+        // Each block of the batch has a withdrawal to `recipient` and a tx
+        // from a different sender, like in `evm_circuit_batch_of_blocks`.
+        let block_ctx = |number: u64, sender: usize, recipient: Address| -> GethData {
+            TestContext::<3, 1>::new(
+                Some((0xcafd..number).map(Word::from).collect()),
+                |accs| {
+                    accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                    accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+                    accs[2].address(MOCK_ACCOUNTS[2]).balance(eth(10));
+                },
+                |mut txs, accs| {
+                    txs[0].from(accs[sender].address).to(accs[0].address);
+                },
+                |block, _txs| {
+                    block
+                        .number(number)
+                        .hash(H256::from_low_u64_be(number))
+                        .parent_hash(H256::from_low_u64_be(number - 1))
+                        .timestamp(Word::from(number * 12))
+                        .author(MOCK_ACCOUNTS[2 + sender])
+                        .withdrawals([Withdrawal {
+                            index: number.into(),
+                            validator_index: 7.into(),
+                            address: recipient,
+                            amount: 1.into(),
+                        }])
+                },
+            )
+            .unwrap()
+            .into()
+        };
+        let handle_blocks = |block1: GethData, block2: GethData| {
+            let mut builder = BlockData::new_from_geth_data_with_params(
+                block1.clone(),
+                CircuitsParams {
+                    max_txs: 2,
+                    ..CircuitsParams::default()
+                },
+            )
+            .new_circuit_input_builder();
+            builder
+                .handle_blocks([
+                    (&block1.eth_block, block1.geth_traces.as_slice()),
+                    (&block2.eth_block, block2.geth_traces.as_slice()),
+                ])
+                .map(|_| builder)
+        };
+
+        let recipient = Address::repeat_byte(0xaa);
+        let builder = handle_blocks(
+            block_ctx(0xcafe, 1, recipient),
+            block_ctx(0xcaff, 2, recipient),
+        )
+        .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(block.withdrawal_blocks, vec![0xcafe, 0xcaff]);
+        run_test_circuit(block).unwrap();
+
+        // The sender of the second block would pay with a balance that
+        // doesn't have the withdrawal of the first block yet.
+        let result = handle_blocks(
+            block_ctx(0xcafe, 1, MOCK_ACCOUNTS[2]),
+            block_ctx(0xcaff, 2, recipient),
+        );
+        assert!(matches!(
+            result,
+            Err(Error::WithdrawalRecipientAccess(address, 0xcaff)) if address == MOCK_ACCOUNTS[2]
+        ));
+    }
+
+    #[test]
+    pub fn evm_circuit_block_in_chunks() {
+        let block: GethData = TestContext::<2, 3>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! {
+                PUSH1(0x20)
+                PUSH1(0)
+                PUSH1(0)
+                CALLDATACOPY
+                STOP
+            }),
+            |txs, accs| {
+                for tx in txs {
+                    tx.from(accs[1].address).to(accs[0].address);
+                }
+            },
+            |block, _txs| block,
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block.clone(),
+            CircuitsParams {
+                max_txs: 3,
+                ..CircuitsParams::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+
+        // Each chunk can only hold the rws of 2 txs
+        let tx_rws = builder.block.txs()[1].steps()[0].rwc.0 - 1;
+        builder.block.circuits_params.max_rws = 2 * tx_rws + 4;
+        let chunks = block_convert_chunks::<Fr>(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].context.chunk.first_tx_id, 3);
+
+        // The chunks are linked by their state roots and rw commitments
+        assert_eq!(chunks[0].prev_state_root, block.prev_state_root);
+        assert_eq!(chunks[1].prev_state_root, chunks[0].mpt_updates.new_root());
+        assert_eq!(
+            chunks[1].mpt_updates.new_root(),
+            block.mpt_updates.new_root()
+        );
+        assert_eq!(
+            chunks[1].context.chunk.prev_rw_commitment,
+            chunks[0].context.chunk.rw_commitment
+        );
+        // The PublicInputs circuit opens the previous rw commitment to find
+        // where the chunk starts
+        let public_data = public_data_convert(&chunks[1]);
+        assert_eq!(
+            public_data.prev_rw_commitment(),
+            chunks[0].context.chunk.rw_commitment
+        );
+        assert_eq!(
+            public_data.rw_commitment(),
+            chunks[1].context.chunk.rw_commitment
+        );
+        assert_eq!(
+            public_data.rw_counter_start(),
+            chunks[1].context.chunk.rw_counter_start
+        );
+        assert_eq!(public_data.first_tx_id(), 3);
+
+        for chunk in chunks {
+            run_test_circuit(chunk).unwrap();
+        }
+    }
+
     /// This function prints to stdout a table with all the implemented states
     /// and their responsible opcodes with the following stats:
     /// - height: number of rows in the EVM circuit used by the execution state
@@ -1,33 +1,52 @@
 use crate::{
     evm_circuit::{
         execution::ExecutionGadget,
-        param::N_BYTES_GAS,
+        param::{N_BYTES_CALLDATASIZE, N_BYTES_GAS},
         step::ExecutionState,
         util::{
+            and,
             common_gadget::TransferWithGasFeeGadget,
             constraint_builder::{
                 ConstraintBuilder, ReversionInfo, StepStateTransition,
                 Transition::{Delta, To},
             },
-            math_gadget::{IsEqualGadget, IsZeroGadget, MulWordByU64Gadget, RangeCheckGadget},
-            not, select, CachedRegion, Cell, Word,
+            math_gadget::{
+                AddWordsGadget, IsEqualGadget, IsZeroGadget, LtGadget, LtWordGadget,
+                MulWordByU64Gadget,
+            },
+            memory_gadget::MemoryWordSizeGadget,
+            not, or, select, CachedRegion, Cell, Word,
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::{AccountFieldTag, CallContextFieldTag, TxFieldTag as TxContextFieldTag},
+    table::{
+        AccountFieldTag, BlockContextFieldTag, CallContextFieldTag, TxFieldTag as TxContextFieldTag,
+    },
     util::Expr,
 };
-use eth_types::{evm_types::GasCost, Field, ToLittleEndian, ToScalar};
+use eth_types::{
+    evm_types::{GasCost, MAX_INIT_CODE_SIZE},
+    geth_types::TxType,
+    Field, ToLittleEndian, ToScalar,
+};
 use halo2_proofs::circuit::Value;
 use halo2_proofs::plonk::Error;
 
 #[derive(Clone, Debug)]
 pub(crate) struct BeginTxGadget<F> {
     tx_id: Cell<F>,
+    is_first_tx: IsEqualGadget<F>,
+    rw_counter_start: Cell<F>,
+    first_tx_id: Cell<F>,
     tx_nonce: Cell<F>,
     tx_gas: Cell<F>,
     tx_gas_price: Word<F>,
     mul_gas_fee_by_gas: MulWordByU64Gadget<F>,
+    tx_type: Cell<F>,
+    is_eip1559_tx: IsEqualGadget<F>,
+    tx_max_fee_per_gas: Word<F>,
+    balance_check_gas_price: Word<F>,
+    mul_max_gas_fee_by_gas: MulWordByU64Gadget<F>,
     tx_caller_address: Cell<F>,
     tx_caller_address_is_zero: IsZeroGadget<F>,
     tx_callee_address: Cell<F>,
@@ -35,8 +54,26 @@ pub(crate) struct BeginTxGadget<F> {
     tx_value: Word<F>,
     tx_call_data_length: Cell<F>,
     tx_call_data_gas_cost: Cell<F>,
+    tx_access_list_addresses_len: Cell<F>,
+    tx_access_list_storage_keys_len: Cell<F>,
+    is_eip3651: Cell<F>,
+    is_eip3860: Cell<F>,
+    init_code_word_size: MemoryWordSizeGadget<F>,
+    is_init_code_too_large: LtGadget<F, N_BYTES_CALLDATASIZE>,
+    tx_block_number: Cell<F>,
+    coinbase: Cell<F>,
+    is_coinbase_caller: IsEqualGadget<F>,
+    is_coinbase_callee: IsEqualGadget<F>,
+    is_tx_invalid: Cell<F>,
+    caller_nonce: Cell<F>,
+    is_nonce_match: IsEqualGadget<F>,
+    caller_balance: Word<F>,
+    is_max_gas_fee_not_overflow: IsZeroGadget<F>,
+    total_eth_cost: AddWordsGadget<F, 2, false>,
+    is_balance_not_enough: LtWordGadget<F>,
+    is_insufficient_balance: Cell<F>,
+    is_gas_not_enough: LtGadget<F, N_BYTES_GAS>,
     reversion_info: ReversionInfo<F>,
-    sufficient_gas_left: RangeCheckGadget<F, N_BYTES_GAS>,
     transfer_with_gas_fee: TransferWithGasFeeGadget<F>,
     phase2_code_hash: Cell<F>,
     is_empty_code_hash: IsEqualGadget<F>,
@@ -65,8 +102,15 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             CallContextFieldTag::IsSuccess,
             reversion_info.is_persistent(),
         );
+        let is_tx_invalid = cb.query_bool();
+        cb.call_context_lookup(
+            1.expr(),
+            Some(call_id.expr()),
+            CallContextFieldTag::IsInvalidTx,
+            is_tx_invalid.expr(),
+        );
 
-        let [tx_nonce, tx_gas, tx_caller_address, tx_callee_address, tx_is_create, tx_call_data_length, tx_call_data_gas_cost] =
+        let [tx_nonce, tx_gas, tx_caller_address, tx_callee_address, tx_is_create, tx_call_data_length, tx_call_data_gas_cost, tx_type, tx_access_list_addresses_len, tx_access_list_storage_keys_len] =
             [
                 TxContextFieldTag::Nonce,
                 TxContextFieldTag::Gas,
@@ -75,6 +119,9 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
                 TxContextFieldTag::IsCreate,
                 TxContextFieldTag::CallDataLength,
                 TxContextFieldTag::CallDataGasCost,
+                TxContextFieldTag::TxType,
+                TxContextFieldTag::AccessListAddressesLen,
+                TxContextFieldTag::AccessListStorageKeysLen,
             ]
             .map(|field_tag| cb.tx_context(tx_id.expr(), field_tag, None));
         let tx_caller_address_is_zero = IsZeroGadget::construct(cb, tx_caller_address.expr());
@@ -83,181 +130,370 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             tx_caller_address_is_zero.expr(),
             false.expr(),
         );
-        let [tx_gas_price, tx_value] = [TxContextFieldTag::GasPrice, TxContextFieldTag::Value]
-            .map(|field_tag| cb.tx_context_as_word(tx_id.expr(), field_tag, None));
+        let [tx_gas_price, tx_max_fee_per_gas, tx_value] = [
+            TxContextFieldTag::GasPrice,
+            TxContextFieldTag::MaxFeePerGas,
+            TxContextFieldTag::Value,
+        ]
+        .map(|field_tag| cb.tx_context_as_word(tx_id.expr(), field_tag, None));
 
-        // Add first BeginTx step constraint to have tx_id == 1
+        // The first BeginTx step executes the first tx of the chunk of the
+        // block.  In the chunks that follow the first one, the first rw is the
+        // write of tx_id done by the EndTx step of the previous tx.
+        let [rw_counter_start, first_tx_id] = [
+            BlockContextFieldTag::RwCounterStart,
+            BlockContextFieldTag::FirstTxId,
+        ]
+        .map(|tag| {
+            let cell = cb.query_cell();
+            cb.block_lookup(tag.expr(), None, cell.expr());
+            cell
+        });
+        let is_first_tx = IsEqualGadget::construct(cb, tx_id.expr(), 1.expr());
         cb.step_first(|cb| {
-            cb.require_equal("tx_id is initialized to be 1", tx_id.expr(), 1.expr());
+            cb.require_equal(
+                "tx_id is initialized to be the first tx of the chunk",
+                tx_id.expr(),
+                first_tx_id.expr(),
+            );
+            cb.require_equal(
+                "rw_counter is initialized to be the start of the chunk",
+                call_id.expr(),
+                rw_counter_start.expr() + not::expr(is_first_tx.expr()),
+            );
         });
 
         // Increase caller's nonce.
-        // (tx caller's nonce always increases even tx ends with error)
+        // (tx caller's nonce always increases even tx ends with error, but it's
+        // left untouched when the tx is invalid)
+        let caller_nonce = cb.query_cell();
         cb.account_write(
             tx_caller_address.expr(),
             AccountFieldTag::Nonce,
-            tx_nonce.expr() + 1.expr(),
-            tx_nonce.expr(),
+            caller_nonce.expr() + 1.expr() - is_tx_invalid.expr(),
+            caller_nonce.expr(),
             None,
         );
+        let is_nonce_match = IsEqualGadget::construct(cb, tx_nonce.expr(), caller_nonce.expr());
 
-        // TODO: Implement EIP 1559 (currently it only supports legacy
-        // transaction format)
-        // Calculate transaction gas fee
+        // Calculate transaction gas fee, charged at the (effective) gas price
         let mul_gas_fee_by_gas =
             MulWordByU64Gadget::construct(cb, tx_gas_price.clone(), tx_gas.expr());
 
-        // TODO: Take gas cost of access list (EIP 2930) into consideration.
-        // Use intrinsic gas
+        // Like geth's `buyGas`, the caller's balance must cover the gas limit
+        // at the max fee per gas of an EIP-1559 tx, and at the gas price of
+        // the other txs.
+        let is_eip1559_tx =
+            IsEqualGadget::construct(cb, tx_type.expr(), (TxType::Eip1559 as u64).expr());
+        let balance_check_gas_price = cb.query_word_rlc();
+        cb.require_equal(
+            "balance_check_gas_price = is_eip1559_tx ? max_fee_per_gas : gas_price",
+            balance_check_gas_price.expr(),
+            select::expr(
+                is_eip1559_tx.expr(),
+                tx_max_fee_per_gas.expr(),
+                tx_gas_price.expr(),
+            ),
+        );
+        let mul_max_gas_fee_by_gas =
+            MulWordByU64Gadget::construct(cb, balance_check_gas_price.clone(), tx_gas.expr());
+
+        // From Shanghai, the init code of a creation tx is charged per word
+        // and its size is limited (EIP-3860).
+        let is_eip3860 = cb.is_eip_enabled(3860);
+        let is_create_eip3860 = and::expr([tx_is_create.expr(), is_eip3860.expr()]);
+        let init_code_word_size = MemoryWordSizeGadget::construct(cb, tx_call_data_length.expr());
+        let is_init_code_too_large =
+            LtGadget::construct(cb, MAX_INIT_CODE_SIZE.expr(), tx_call_data_length.expr());
+
+        // Use intrinsic gas, which includes the cost of the access list
+        // (EIP-2930)
         let intrinsic_gas_cost = select::expr(
             tx_is_create.expr(),
             GasCost::CREATION_TX.expr(),
             GasCost::TX.expr(),
-        ) + tx_call_data_gas_cost.expr();
+        ) + tx_call_data_gas_cost.expr()
+            + GasCost::ACCESS_LIST_ADDRESS.expr() * tx_access_list_addresses_len.expr()
+            + GasCost::ACCESS_LIST_STORAGE_KEY.expr() * tx_access_list_storage_keys_len.expr()
+            + is_create_eip3860.clone()
+                * GasCost::INIT_CODE_WORD_COST.expr()
+                * init_code_word_size.expr();
 
         // Check gas_left is sufficient
+        let is_gas_not_enough = LtGadget::construct(cb, tx_gas.expr(), intrinsic_gas_cost.clone());
         let gas_left = tx_gas.expr() - intrinsic_gas_cost;
-        let sufficient_gas_left = RangeCheckGadget::construct(cb, gas_left.clone());
 
-        // Prepare access list of caller and callee
-        cb.account_access_list_write(
-            tx_id.expr(),
-            tx_caller_address.expr(),
-            1.expr(),
-            0.expr(),
-            None,
-        );
-        cb.account_access_list_write(
-            tx_id.expr(),
-            tx_callee_address.expr(),
-            1.expr(),
-            0.expr(),
-            None,
-        );
-
-        // TODO: If value is 0, skip transfer, just like callop.
-        // Transfer value from caller to callee
-        let transfer_with_gas_fee = TransferWithGasFeeGadget::construct(
+        // Check the caller's balance covers the max gas fee and the value,
+        // where an overflow of the max gas fee or of the sum means that it
+        // can't.
+        let is_max_gas_fee_not_overflow =
+            IsZeroGadget::construct(cb, mul_max_gas_fee_by_gas.overflow());
+        let caller_balance = cb.query_word_rlc();
+        let total_eth_cost_sum = cb.query_word_rlc();
+        let total_eth_cost = AddWordsGadget::construct(
             cb,
-            tx_caller_address.expr(),
-            tx_callee_address.expr(),
-            tx_value.clone(),
-            mul_gas_fee_by_gas.product().clone(),
-            &mut reversion_info,
+            [tx_value.clone(), mul_max_gas_fee_by_gas.product().clone()],
+            total_eth_cost_sum,
         );
+        let is_balance_not_enough =
+            LtWordGadget::construct(cb, &caller_balance, total_eth_cost.sum());
+        let is_insufficient_balance = cb.copy(or::expr([
+            not::expr(is_max_gas_fee_not_overflow.expr()),
+            total_eth_cost.carry().as_ref().unwrap().expr(),
+            is_balance_not_enough.expr(),
+        ]));
 
-        // TODO: Handle creation transaction
-        // TODO: Handle precompiled
+        // A tx is invalid when its nonce doesn't match the caller's one, the
+        // caller can't afford it, it can't pay for the intrinsic gas, or its
+        // init code is too large.
+        cb.require_equal(
+            "is_tx_invalid is correct",
+            is_tx_invalid.expr(),
+            or::expr([
+                not::expr(is_nonce_match.expr()),
+                is_insufficient_balance.expr(),
+                is_gas_not_enough.expr(),
+                is_create_eip3860 * is_init_code_too_large.expr(),
+            ]),
+        );
 
-        // Read code_hash of callee
-        let phase2_code_hash = cb.query_cell_phase2();
-        cb.condition(not::expr(tx_is_create.expr()), |cb| {
+        // An invalid tx is skipped: the state is left untouched, the receipt
+        // is failed and no gas is used.
+        cb.condition(is_tx_invalid.expr(), |cb| {
             cb.account_read(
-                tx_callee_address.expr(),
-                AccountFieldTag::CodeHash,
-                phase2_code_hash.expr(),
+                tx_caller_address.expr(),
+                AccountFieldTag::Balance,
+                caller_balance.expr(),
             );
-        });
-
-        let is_empty_code_hash =
-            IsEqualGadget::construct(cb, phase2_code_hash.expr(), cb.empty_hash_rlc());
-
-        cb.condition(is_empty_code_hash.expr(), |cb| {
-            cb.require_equal(
-                "Tx to account with empty code should be persistent",
+            cb.require_zero(
+                "Invalid tx is not persistent",
                 reversion_info.is_persistent(),
-                1.expr(),
             );
             cb.require_equal(
-                "Go to EndTx when Tx to account with empty code",
+                "Go to EndTx when Tx is invalid",
                 cb.next.execution_state_selector([ExecutionState::EndTx]),
                 1.expr(),
             );
 
             cb.require_step_state_transition(StepStateTransition {
-                // 10 reads and writes:
+                // 7 reads and writes:
                 //   - Write CallContext TxId
                 //   - Write CallContext RwCounterEndOfReversion
                 //   - Write CallContext IsPersistent
                 //   - Write CallContext IsSuccess
+                //   - Write CallContext IsInvalidTx
                 //   - Write Account Nonce
-                //   - Write TxAccessListAccount
-                //   - Write TxAccessListAccount
-                //   - Write Account Balance
-                //   - Write Account Balance
-                //   - Read Account CodeHash
-                rw_counter: Delta(10.expr()),
+                //   - Read Account Balance
+                rw_counter: Delta(7.expr()),
                 call_id: To(call_id.expr()),
+                gas_left: To(tx_gas.expr()),
+                log_id: To(0.expr()),
                 ..StepStateTransition::any()
             });
         });
 
-        cb.condition(1.expr() - is_empty_code_hash.expr(), |cb| {
-            // Setup first call's context.
-            for (field_tag, value) in [
-                (CallContextFieldTag::Depth, 1.expr()),
-                (CallContextFieldTag::CallerAddress, tx_caller_address.expr()),
-                (CallContextFieldTag::CalleeAddress, tx_callee_address.expr()),
-                (CallContextFieldTag::CallDataOffset, 0.expr()),
-                (
-                    CallContextFieldTag::CallDataLength,
-                    tx_call_data_length.expr(),
-                ),
-                (CallContextFieldTag::Value, tx_value.expr()),
-                (CallContextFieldTag::IsStatic, 0.expr()),
-                (CallContextFieldTag::LastCalleeId, 0.expr()),
-                (CallContextFieldTag::LastCalleeReturnDataOffset, 0.expr()),
-                (CallContextFieldTag::LastCalleeReturnDataLength, 0.expr()),
-                (CallContextFieldTag::IsRoot, 1.expr()),
-                (CallContextFieldTag::IsCreate, tx_is_create.expr()),
-                (CallContextFieldTag::CodeHash, phase2_code_hash.expr()),
-            ] {
-                cb.call_context_lookup(true.expr(), Some(call_id.expr()), field_tag, value);
-            }
+        let is_tx_valid = not::expr(is_tx_invalid.expr());
+        cb.condition(is_tx_valid.clone(), |cb| {
+            // Prepare access list of caller and callee
+            cb.account_access_list_write(
+                tx_id.expr(),
+                tx_caller_address.expr(),
+                1.expr(),
+                0.expr(),
+                None,
+            );
+            cb.account_access_list_write(
+                tx_id.expr(),
+                tx_callee_address.expr(),
+                1.expr(),
+                0.expr(),
+                None,
+            );
+        });
 
-            cb.require_step_state_transition(StepStateTransition {
-                // 22-23 reads and writes:
-                //   - Write CallContext TxId
-                //   - Write CallContext RwCounterEndOfReversion
-                //   - Write CallContext IsPersistent
-                //   - Write CallContext IsSuccess
-                //   - Write Account Nonce
-                //   - Write TxAccessListAccount
-                //   - Write TxAccessListAccount
-                //   - Write Account Balance
-                //   - Write Account Balance
-                //   - Read Account CodeHash (only if tx is not create)
-                //   - Write CallContext Depth
-                //   - Write CallContext CallerAddress
-                //   - Write CallContext CalleeAddress
-                //   - Write CallContext CallDataOffset
-                //   - Write CallContext CallDataLength
-                //   - Write CallContext Value
-                //   - Write CallContext IsStatic
-                //   - Write CallContext LastCalleeId
-                //   - Write CallContext LastCalleeReturnDataOffset
-                //   - Write CallContext LastCalleeReturnDataLength
-                //   - Write CallContext IsRoot
-                //   - Write CallContext IsCreate
-                //   - Write CallContext CodeHash
-                rw_counter: Delta(22.expr() + (1.expr() - tx_is_create.expr())),
-                call_id: To(call_id.expr()),
-                is_root: To(true.expr()),
-                is_create: To(tx_is_create.expr()),
-                code_hash: To(phase2_code_hash.expr()),
-                gas_left: To(gas_left),
-                reversible_write_counter: To(2.expr()),
-                log_id: To(0.expr()),
-                ..StepStateTransition::new_context()
-            });
+        // From Shanghai, the coinbase is also warm at the beginning of the tx
+        // (EIP-3651).  It's already warm if it's the caller or the callee.
+        // In a batch of blocks, the coinbase is the one of the block of the tx.
+        let is_eip3651 = cb.is_eip_enabled(3651);
+        let tx_block_number = cb.tx_context(tx_id.expr(), TxContextFieldTag::BlockNumber, None);
+        let coinbase = cb.query_cell();
+        cb.block_lookup(
+            BlockContextFieldTag::Coinbase.expr(),
+            Some(tx_block_number.expr()),
+            coinbase.expr(),
+        );
+        let is_coinbase_caller =
+            IsEqualGadget::construct(cb, coinbase.expr(), tx_caller_address.expr());
+        let is_coinbase_callee =
+            IsEqualGadget::construct(cb, coinbase.expr(), tx_callee_address.expr());
+        cb.condition(and::expr([is_tx_valid.clone(), is_eip3651.expr()]), |cb| {
+            cb.account_access_list_write(
+                tx_id.expr(),
+                coinbase.expr(),
+                1.expr(),
+                or::expr([is_coinbase_caller.expr(), is_coinbase_callee.expr()]),
+                None,
+            );
+        });
+
+        // TODO: If value is 0, skip transfer, just like callop.
+        // Transfer value from caller to callee
+        let transfer_with_gas_fee = cb.condition(is_tx_valid.clone(), |cb| {
+            // The gas price of a valid tx is at most its max fee per gas, so
+            // its gas fee doesn't overflow either.
+            cb.require_zero("gas fee doesn't overflow", mul_gas_fee_by_gas.overflow());
+            TransferWithGasFeeGadget::construct(
+                cb,
+                tx_caller_address.expr(),
+                tx_callee_address.expr(),
+                tx_value.clone(),
+                mul_gas_fee_by_gas.product().clone(),
+                &mut reversion_info,
+            )
+        });
+        cb.condition(is_tx_valid.clone(), |cb| {
+            cb.require_equal(
+                "caller_balance is the balance before the transfer",
+                caller_balance.expr(),
+                transfer_with_gas_fee.sender().balance_prev().expr(),
+            );
         });
 
+        // TODO: Handle creation transaction
+        // TODO: Handle precompiled
+
+        // Read code_hash of callee
+        let phase2_code_hash = cb.query_cell_phase2();
+        cb.condition(
+            and::expr([is_tx_valid.clone(), not::expr(tx_is_create.expr())]),
+            |cb| {
+                cb.account_read(
+                    tx_callee_address.expr(),
+                    AccountFieldTag::CodeHash,
+                    phase2_code_hash.expr(),
+                );
+            },
+        );
+
+        let is_empty_code_hash =
+            IsEqualGadget::construct(cb, phase2_code_hash.expr(), cb.empty_hash_rlc());
+
+        cb.condition(
+            and::expr([is_tx_valid.clone(), is_empty_code_hash.expr()]),
+            |cb| {
+                cb.require_equal(
+                    "Tx to account with empty code should be persistent",
+                    reversion_info.is_persistent(),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "Go to EndTx when Tx to account with empty code",
+                    cb.next.execution_state_selector([ExecutionState::EndTx]),
+                    1.expr(),
+                );
+
+                cb.require_step_state_transition(StepStateTransition {
+                    // 11-12 reads and writes:
+                    //   - Write CallContext TxId
+                    //   - Write CallContext RwCounterEndOfReversion
+                    //   - Write CallContext IsPersistent
+                    //   - Write CallContext IsSuccess
+                    //   - Write CallContext IsInvalidTx
+                    //   - Write Account Nonce
+                    //   - Write TxAccessListAccount
+                    //   - Write TxAccessListAccount
+                    //   - Write TxAccessListAccount (only from Shanghai)
+                    //   - Write Account Balance
+                    //   - Write Account Balance
+                    //   - Read Account CodeHash
+                    rw_counter: Delta(11.expr() + is_eip3651.expr()),
+                    call_id: To(call_id.expr()),
+                    ..StepStateTransition::any()
+                });
+            },
+        );
+
+        cb.condition(
+            and::expr([is_tx_valid, not::expr(is_empty_code_hash.expr())]),
+            |cb| {
+                // Setup first call's context.
+                for (field_tag, value) in [
+                    (CallContextFieldTag::Depth, 1.expr()),
+                    (CallContextFieldTag::CallerAddress, tx_caller_address.expr()),
+                    (CallContextFieldTag::CalleeAddress, tx_callee_address.expr()),
+                    (CallContextFieldTag::CallDataOffset, 0.expr()),
+                    (
+                        CallContextFieldTag::CallDataLength,
+                        tx_call_data_length.expr(),
+                    ),
+                    (CallContextFieldTag::Value, tx_value.expr()),
+                    (CallContextFieldTag::IsStatic, 0.expr()),
+                    (CallContextFieldTag::LastCalleeId, 0.expr()),
+                    (CallContextFieldTag::LastCalleeReturnDataOffset, 0.expr()),
+                    (CallContextFieldTag::LastCalleeReturnDataLength, 0.expr()),
+                    (CallContextFieldTag::IsRoot, 1.expr()),
+                    (CallContextFieldTag::IsCreate, tx_is_create.expr()),
+                    (CallContextFieldTag::CodeHash, phase2_code_hash.expr()),
+                ] {
+                    cb.call_context_lookup(true.expr(), Some(call_id.expr()), field_tag, value);
+                }
+
+                cb.require_step_state_transition(StepStateTransition {
+                    // 23-25 reads and writes:
+                    //   - Write CallContext TxId
+                    //   - Write CallContext RwCounterEndOfReversion
+                    //   - Write CallContext IsPersistent
+                    //   - Write CallContext IsSuccess
+                    //   - Write CallContext IsInvalidTx
+                    //   - Write Account Nonce
+                    //   - Write TxAccessListAccount
+                    //   - Write TxAccessListAccount
+                    //   - Write TxAccessListAccount (only from Shanghai)
+                    //   - Write Account Balance
+                    //   - Write Account Balance
+                    //   - Read Account CodeHash (only if tx is not create)
+                    //   - Write CallContext Depth
+                    //   - Write CallContext CallerAddress
+                    //   - Write CallContext CalleeAddress
+                    //   - Write CallContext CallDataOffset
+                    //   - Write CallContext CallDataLength
+                    //   - Write CallContext Value
+                    //   - Write CallContext IsStatic
+                    //   - Write CallContext LastCalleeId
+                    //   - Write CallContext LastCalleeReturnDataOffset
+                    //   - Write CallContext LastCalleeReturnDataLength
+                    //   - Write CallContext IsRoot
+                    //   - Write CallContext IsCreate
+                    //   - Write CallContext CodeHash
+                    rw_counter: Delta(
+                        23.expr() + (1.expr() - tx_is_create.expr()) + is_eip3651.expr(),
+                    ),
+                    call_id: To(call_id.expr()),
+                    is_root: To(true.expr()),
+                    is_create: To(tx_is_create.expr()),
+                    code_hash: To(phase2_code_hash.expr()),
+                    gas_left: To(gas_left),
+                    reversible_write_counter: To(2.expr()),
+                    log_id: To(0.expr()),
+                    ..StepStateTransition::new_context()
+                });
+            },
+        );
+
         Self {
             tx_id,
+            is_first_tx,
+            rw_counter_start,
+            first_tx_id,
             tx_nonce,
             tx_gas,
             tx_gas_price,
             mul_gas_fee_by_gas,
+            tx_type,
+            is_eip1559_tx,
+            tx_max_fee_per_gas,
+            balance_check_gas_price,
+            mul_max_gas_fee_by_gas,
             tx_caller_address,
             tx_caller_address_is_zero,
             tx_callee_address,
@@ -265,8 +501,26 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             tx_value,
             tx_call_data_length,
             tx_call_data_gas_cost,
+            tx_access_list_addresses_len,
+            tx_access_list_storage_keys_len,
+            is_eip3651,
+            is_eip3860,
+            init_code_word_size,
+            is_init_code_too_large,
+            tx_block_number,
+            coinbase,
+            is_coinbase_caller,
+            is_coinbase_callee,
+            is_tx_invalid,
+            caller_nonce,
+            is_nonce_match,
+            caller_balance,
+            is_max_gas_fee_not_overflow,
+            total_eth_cost,
+            is_balance_not_enough,
+            is_insufficient_balance,
+            is_gas_not_enough,
             reversion_info,
-            sufficient_gas_left,
             transfer_with_gas_fee,
             phase2_code_hash,
             is_empty_code_hash,
@@ -282,17 +536,66 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
         call: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        let gas_fee = tx.gas_price * tx.gas;
-        let [caller_balance_pair, callee_balance_pair] =
-            [step.rw_indices[7], step.rw_indices[8]].map(|idx| block.rws[idx].account_value_pair());
-        let callee_code_hash = if tx.is_create {
+        let hardfork = block.circuits_params.hardfork;
+        let is_eip3651 = hardfork.is_eip_enabled(3651);
+        let is_eip3860 = hardfork.is_eip_enabled(3860);
+        // The coinbase access list write shifts the following rws by one.
+        let rw_offset = is_eip3651 as usize;
+
+        // The multiplications only overflow for invalid txs, whose gas fee
+        // isn't charged.
+        let (gas_fee, _) = tx.gas_price.overflowing_mul(tx.gas.into());
+        let balance_check_gas_price = if tx.tx_type == TxType::Eip1559 {
+            tx.max_fee_per_gas
+        } else {
+            tx.gas_price
+        };
+        let (max_gas_fee, is_max_gas_fee_overflow) =
+            balance_check_gas_price.overflowing_mul(tx.gas.into());
+        let (_, caller_nonce) = block.rws[step.rw_indices[5]].account_value_pair();
+        let caller_balance = if tx.is_invalid {
+            block.rws[step.rw_indices[6]].account_value_pair().0
+        } else {
+            let [caller_balance_pair, callee_balance_pair] = [
+                step.rw_indices[8 + rw_offset],
+                step.rw_indices[9 + rw_offset],
+            ]
+            .map(|idx| block.rws[idx].account_value_pair());
+            self.transfer_with_gas_fee.assign(
+                region,
+                offset,
+                caller_balance_pair,
+                callee_balance_pair,
+                tx.value,
+                gas_fee,
+            )?;
+            caller_balance_pair.1
+        };
+        let callee_code_hash = if tx.is_invalid {
+            Default::default()
+        } else if tx.is_create {
             call.code_hash
         } else {
-            block.rws[step.rw_indices[9]].account_value_pair().0
+            block.rws[step.rw_indices[10 + rw_offset]]
+                .account_value_pair()
+                .0
         };
 
         self.tx_id
             .assign(region, offset, Value::known(F::from(tx.id as u64)))?;
+        self.is_first_tx
+            .assign(region, offset, F::from(tx.id as u64), F::one())?;
+        let chunk = &block.context.chunk;
+        self.rw_counter_start.assign(
+            region,
+            offset,
+            Value::known(F::from(chunk.rw_counter_start as u64)),
+        )?;
+        self.first_tx_id.assign(
+            region,
+            offset,
+            Value::known(F::from(chunk.first_tx_id as u64)),
+        )?;
         self.tx_nonce
             .assign(region, offset, Value::known(F::from(tx.nonce)))?;
         self.tx_gas
@@ -301,23 +604,44 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             .assign(region, offset, Some(tx.gas_price.to_le_bytes()))?;
         self.mul_gas_fee_by_gas
             .assign(region, offset, tx.gas_price, tx.gas, gas_fee)?;
-        let caller_address = tx
-            .caller_address
-            .to_scalar()
-            .expect("unexpected Address -> Scalar conversion failure");
+        self.tx_type
+            .assign(region, offset, Value::known(F::from(tx.tx_type as u64)))?;
+        self.is_eip1559_tx.assign(
+            region,
+            offset,
+            F::from(tx.tx_type as u64),
+            F::from(TxType::Eip1559 as u64),
+        )?;
+        self.tx_max_fee_per_gas
+            .assign(region, offset, Some(tx.max_fee_per_gas.to_le_bytes()))?;
+        self.balance_check_gas_price.assign(
+            region,
+            offset,
+            Some(balance_check_gas_price.to_le_bytes()),
+        )?;
+        self.mul_max_gas_fee_by_gas.assign(
+            region,
+            offset,
+            balance_check_gas_price,
+            tx.gas,
+            max_gas_fee,
+        )?;
+        let [caller_address, callee_address, coinbase] = [
+            tx.caller_address,
+            tx.callee_address,
+            block.context.get(tx.block_number).coinbase,
+        ]
+        .map(|address| {
+            address
+                .to_scalar()
+                .expect("unexpected Address -> Scalar conversion failure")
+        });
         self.tx_caller_address
             .assign(region, offset, Value::known(caller_address))?;
         self.tx_caller_address_is_zero
             .assign(region, offset, caller_address)?;
-        self.tx_callee_address.assign(
-            region,
-            offset,
-            Value::known(
-                tx.callee_address
-                    .to_scalar()
-                    .expect("unexpected Address -> Scalar conversion failure"),
-            ),
-        )?;
+        self.tx_callee_address
+            .assign(region, offset, Value::known(callee_address))?;
         self.tx_is_create
             .assign(region, offset, Value::known(F::from(tx.is_create as u64)))?;
         self.tx_call_data_length.assign(
@@ -330,21 +654,99 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             offset,
             Value::known(F::from(tx.call_data_gas_cost)),
         )?;
-        self.reversion_info.assign(
+        self.tx_access_list_addresses_len.assign(
             region,
             offset,
-            call.rw_counter_end_of_reversion,
-            call.is_persistent,
+            Value::known(F::from(tx.access_list_addresses_len)),
+        )?;
+        self.tx_access_list_storage_keys_len.assign(
+            region,
+            offset,
+            Value::known(F::from(tx.access_list_storage_keys_len)),
+        )?;
+        self.is_eip3651
+            .assign(region, offset, Value::known(F::from(is_eip3651 as u64)))?;
+        self.is_eip3860
+            .assign(region, offset, Value::known(F::from(is_eip3860 as u64)))?;
+        let init_code_word_size =
+            self.init_code_word_size
+                .assign(region, offset, tx.call_data_length as u64)?;
+        self.is_init_code_too_large.assign(
+            region,
+            offset,
+            F::from(MAX_INIT_CODE_SIZE),
+            F::from(tx.call_data_length as u64),
+        )?;
+        self.tx_block_number
+            .assign(region, offset, Value::known(F::from(tx.block_number)))?;
+        self.coinbase
+            .assign(region, offset, Value::known(coinbase))?;
+        self.is_coinbase_caller
+            .assign(region, offset, coinbase, caller_address)?;
+        self.is_coinbase_callee
+            .assign(region, offset, coinbase, callee_address)?;
+        self.is_tx_invalid
+            .assign(region, offset, Value::known(F::from(tx.is_invalid as u64)))?;
+        self.caller_nonce.assign(
+            region,
+            offset,
+            Value::known(
+                caller_nonce
+                    .to_scalar()
+                    .expect("unexpected U256 -> Scalar conversion failure"),
+            ),
+        )?;
+        self.is_nonce_match.assign(
+            region,
+            offset,
+            F::from(tx.nonce),
+            caller_nonce
+                .to_scalar()
+                .expect("unexpected U256 -> Scalar conversion failure"),
         )?;
-        self.sufficient_gas_left
-            .assign(region, offset, F::from(tx.gas - step.gas_cost))?;
-        self.transfer_with_gas_fee.assign(
+        let max_gas_fee_overflow =
+            (balance_check_gas_price.full_mul(tx.gas.into()) >> 256).low_u64();
+        self.is_max_gas_fee_not_overflow
+            .assign(region, offset, F::from(max_gas_fee_overflow))?;
+        self.caller_balance
+            .assign(region, offset, Some(caller_balance.to_le_bytes()))?;
+        let (total_eth_cost, is_total_eth_cost_overflow) = tx.value.overflowing_add(max_gas_fee);
+        self.total_eth_cost
+            .assign(region, offset, [tx.value, max_gas_fee], total_eth_cost)?;
+        self.is_balance_not_enough
+            .assign(region, offset, caller_balance, total_eth_cost)?;
+        self.is_insufficient_balance.assign(
             region,
             offset,
-            caller_balance_pair,
-            callee_balance_pair,
-            tx.value,
-            gas_fee,
+            Value::known(F::from(
+                (is_max_gas_fee_overflow
+                    || is_total_eth_cost_overflow
+                    || caller_balance < total_eth_cost) as u64,
+            )),
+        )?;
+        let intrinsic_gas_cost = if tx.is_create {
+            GasCost::CREATION_TX.as_u64()
+        } else {
+            GasCost::TX.as_u64()
+        } + tx.call_data_gas_cost
+            + GasCost::ACCESS_LIST_ADDRESS.as_u64() * tx.access_list_addresses_len
+            + GasCost::ACCESS_LIST_STORAGE_KEY.as_u64() * tx.access_list_storage_keys_len
+            + if tx.is_create && is_eip3860 {
+                GasCost::INIT_CODE_WORD_COST.as_u64() * init_code_word_size
+            } else {
+                0
+            };
+        self.is_gas_not_enough.assign(
+            region,
+            offset,
+            F::from(tx.gas),
+            F::from(intrinsic_gas_cost),
+        )?;
+        self.reversion_info.assign(
+            region,
+            offset,
+            call.rw_counter_end_of_reversion,
+            call.is_persistent,
         )?;
         self.phase2_code_hash
             .assign(region, offset, region.word_rlc(callee_code_hash))?;
@@ -360,11 +762,17 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::evm_circuit::test::{rand_bytes, run_test_circuit_geth_data_default};
-    use bus_mapping::evm::OpcodeId;
+    use crate::evm_circuit::test::{
+        rand_bytes, run_test_circuit_geth_data, run_test_circuit_geth_data_default,
+    };
+    use bus_mapping::{circuit_input_builder::CircuitsParams, evm::OpcodeId};
     use eth_types::{
-        self, bytecode, evm_types::GasCost, geth_types::GethData, word, Bytecode, Word,
+        self, bytecode,
+        evm_types::{GasCost, Hardfork, MAX_INIT_CODE_SIZE},
+        geth_types::{GethData, TxType},
+        word, AccessList, Bytecode, Word, H256,
     };
+    use ethers_core::types::transaction::eip2930::AccessListItem;
     use halo2_proofs::halo2curves::bn256::Fr;
     use mock::{eth, gwei, TestContext, MOCK_ACCOUNTS};
 
@@ -558,6 +966,236 @@ mod test {
         assert_eq!(run_test_circuit_geth_data_default::<Fr>(block), Ok(()));
     }
 
+    fn test_invalid_tx(nonce: u64, gas: u64, caller_balance: Word) {
+        let block: GethData = TestContext::<2, 2>::new(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(caller_balance);
+            },
+            |mut txs, _accs| {
+                txs[0]
+                    .from(MOCK_ACCOUNTS[1])
+                    .to(MOCK_ACCOUNTS[0])
+                    .nonce(Word::from(nonce))
+                    .gas_price(gwei(2))
+                    .gas(Word::from(gas))
+                    .value(eth(1));
+                // The invalid tx is skipped, so the caller's nonce is still 0
+                txs[1]
+                    .from(MOCK_ACCOUNTS[1])
+                    .to(MOCK_ACCOUNTS[0])
+                    .nonce(Word::zero())
+                    .gas_price(gwei(2))
+                    .gas(Word::from(21000));
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        assert_eq!(
+            run_test_circuit_geth_data::<Fr>(
+                block,
+                CircuitsParams {
+                    max_txs: 2,
+                    ..Default::default()
+                }
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn begin_tx_invalid_nonce() {
+        test_invalid_tx(1, 21000, eth(10));
+    }
+
+    #[test]
+    fn begin_tx_not_enough_balance() {
+        test_invalid_tx(0, 21000, eth(1));
+    }
+
+    #[test]
+    fn begin_tx_not_enough_intrinsic_gas() {
+        test_invalid_tx(0, 20999, eth(10));
+    }
+
+    fn test_typed_tx(
+        tx_type: TxType,
+        gas: u64,
+        max_fee_per_gas: Word,
+        caller_balance: Word,
+        access_list: AccessList,
+    ) {
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(caller_balance);
+            },
+            |mut txs, _accs| {
+                txs[0]
+                    .transaction_type(tx_type as u64)
+                    .from(MOCK_ACCOUNTS[1])
+                    .to(MOCK_ACCOUNTS[0])
+                    .gas_price(gwei(2))
+                    .max_priority_fee_per_gas(gwei(2))
+                    .max_fee_per_gas(max_fee_per_gas)
+                    .gas(Word::from(gas))
+                    .value(eth(1))
+                    .access_list(access_list);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        assert_eq!(run_test_circuit_geth_data_default::<Fr>(block), Ok(()));
+    }
+
+    fn access_list() -> AccessList {
+        AccessList(vec![AccessListItem {
+            address: MOCK_ACCOUNTS[2],
+            storage_keys: vec![H256::zero(), H256::from_low_u64_be(1)],
+        }])
+    }
+
+    #[test]
+    fn begin_tx_access_list() {
+        let intrinsic_gas = GasCost::TX.as_u64()
+            + GasCost::ACCESS_LIST_ADDRESS.as_u64()
+            + 2 * GasCost::ACCESS_LIST_STORAGE_KEY.as_u64();
+        // The gas limit covers the access list
+        test_typed_tx(
+            TxType::Eip2930,
+            intrinsic_gas,
+            gwei(2),
+            eth(10),
+            access_list(),
+        );
+        // The gas limit doesn't cover the access list
+        test_typed_tx(
+            TxType::Eip2930,
+            intrinsic_gas - 1,
+            gwei(2),
+            eth(10),
+            access_list(),
+        );
+    }
+
+    #[test]
+    fn begin_tx_eip1559_balance_check() {
+        let gas = GasCost::TX.as_u64();
+        // The balance covers the gas limit at the max fee per gas
+        test_typed_tx(
+            TxType::Eip1559,
+            gas,
+            gwei(4),
+            eth(1) + gwei(4) * gas,
+            AccessList::default(),
+        );
+        // The balance covers the gas limit at the gas price, but not at the
+        // max fee per gas
+        test_typed_tx(
+            TxType::Eip1559,
+            gas,
+            gwei(4),
+            eth(1) + gwei(4) * gas - Word::one(),
+            AccessList::default(),
+        );
+        // The gas limit at the max fee per gas overflows, so no balance covers
+        // it
+        test_typed_tx(
+            TxType::Eip1559,
+            gas,
+            Word::MAX,
+            Word::MAX,
+            AccessList::default(),
+        );
+    }
+
+    #[test]
+    fn begin_tx_shanghai_warm_coinbase() {
+        let block: GethData = TestContext::<2, 1>::new_with_hardfork(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(eth(10))
+                    .code(code_with_return());
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+            },
+            |mut txs, _accs| {
+                txs[0]
+                    .from(MOCK_ACCOUNTS[1])
+                    .to(MOCK_ACCOUNTS[0])
+                    .gas_price(gwei(2))
+                    .gas(gas(&[]))
+                    .value(eth(1));
+            },
+            |block, _tx| block.number(0xcafeu64),
+            Hardfork::Shanghai,
+        )
+        .unwrap()
+        .into();
+
+        assert_eq!(
+            run_test_circuit_geth_data::<Fr>(
+                block,
+                CircuitsParams {
+                    hardfork: Hardfork::Shanghai,
+                    ..Default::default()
+                }
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn serial_begin_tx_init_code_too_large() {
+        let init_code = vec![0u8; MAX_INIT_CODE_SIZE as usize + 1];
+        let block: GethData = TestContext::<2, 2>::new_with_hardfork(
+            None,
+            |accs| {
+                accs[0].address(MOCK_ACCOUNTS[0]).balance(eth(10));
+                accs[1].address(MOCK_ACCOUNTS[1]).balance(eth(10));
+            },
+            |mut txs, _accs| {
+                txs[0]
+                    .from(MOCK_ACCOUNTS[1])
+                    .gas_price(gwei(2))
+                    .gas(Word::from(300000))
+                    .input(init_code.into());
+                // The invalid tx is skipped, so the caller's nonce is still 0
+                txs[1]
+                    .from(MOCK_ACCOUNTS[1])
+                    .to(MOCK_ACCOUNTS[0])
+                    .nonce(Word::zero())
+                    .gas_price(gwei(2))
+                    .gas(Word::from(21000));
+            },
+            |block, _tx| block.number(0xcafeu64),
+            Hardfork::Shanghai,
+        )
+        .unwrap()
+        .into();
+
+        assert_eq!(
+            run_test_circuit_geth_data::<Fr>(
+                block,
+                CircuitsParams {
+                    max_txs: 2,
+                    max_calldata: MAX_INIT_CODE_SIZE as usize + 1,
+                    hardfork: Hardfork::Shanghai,
+                    ..Default::default()
+                }
+            ),
+            Ok(())
+        );
+    }
+
     // TODO: Enable this test once we have support for contract deployment from
     // BeginTx.
     #[ignore]
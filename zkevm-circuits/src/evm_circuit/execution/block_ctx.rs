@@ -6,22 +6,24 @@ use crate::{
         util::{
             common_gadget::SameContextGadget,
             constraint_builder::{ConstraintBuilder, StepStateTransition, Transition::Delta},
-            from_bytes, CachedRegion, RandomLinearCombination,
+            from_bytes, CachedRegion, Cell, RandomLinearCombination,
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::BlockContextFieldTag,
+    table::{BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag},
     util::Expr,
 };
 use bus_mapping::evm::OpcodeId;
 use eth_types::Field;
 use eth_types::ToLittleEndian;
-use halo2_proofs::plonk::Error;
+use halo2_proofs::{circuit::Value, plonk::Error};
 
 #[derive(Clone, Debug)]
 pub(crate) struct BlockCtxGadget<F, const N_BYTES: usize> {
     same_context: SameContextGadget<F>,
     value: RandomLinearCombination<F, N_BYTES>,
+    tx_id: Cell<F>,
+    block_number: Cell<F>,
 }
 
 impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
@@ -31,6 +33,11 @@ impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
         // Push the const generic parameter N_BYTES value to the stack
         cb.stack_push(value.expr());
 
+        // The context is the one of the block of the tx, which in a batch of
+        // blocks is given by the tx table.
+        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
+        let block_number = cb.tx_context(tx_id.expr(), TxContextFieldTag::BlockNumber, None);
+
         // Get op's FieldTag
         let opcode = cb.query_cell();
         let blockctx_tag = BlockContextFieldTag::Coinbase.expr()
@@ -43,11 +50,11 @@ impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
         } else {
             from_bytes::expr(&value.cells)
         };
-        cb.block_lookup(blockctx_tag, None, value_expr);
+        cb.block_lookup(blockctx_tag, Some(block_number.expr()), value_expr);
 
         // State transition
         let step_state_transition = StepStateTransition {
-            rw_counter: Delta(1.expr()),
+            rw_counter: Delta(2.expr()),
             program_counter: Delta(1.expr()),
             stack_pointer: Delta((-1).expr()),
             gas_left: Delta(-OpcodeId::TIMESTAMP.constant_gas_cost().expr()),
@@ -58,8 +65,26 @@ impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
         Self {
             same_context,
             value,
+            tx_id,
+            block_number,
         }
     }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        tx: &Transaction,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        self.same_context.assign_exec_step(region, offset, step)?;
+        self.tx_id
+            .assign(region, offset, Value::known(F::from(tx.id as u64)))?;
+        self.block_number
+            .assign(region, offset, Value::known(F::from(tx.block_number)))?;
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -83,13 +108,11 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU64Gadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.value_u64
-            .same_context
-            .assign_exec_step(region, offset, step)?;
+        self.value_u64.assign_exec_step(region, offset, tx, step)?;
 
         let value = block.rws[step.rw_indices[0]].stack_value();
 
@@ -124,13 +147,11 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU160Gadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.value_u160
-            .same_context
-            .assign_exec_step(region, offset, step)?;
+        self.value_u160.assign_exec_step(region, offset, tx, step)?;
 
         let value = block.rws[step.rw_indices[0]].stack_value();
 
@@ -169,13 +190,11 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU256Gadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.value_u256
-            .same_context
-            .assign_exec_step(region, offset, step)?;
+        self.value_u256.assign_exec_step(region, offset, tx, step)?;
 
         let value = block.rws[step.rw_indices[0]].stack_value();
 
@@ -12,7 +12,7 @@ use crate::{
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::BlockContextFieldTag,
+    table::{BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag},
     util::Expr,
 };
 use bus_mapping::evm::OpcodeId;
@@ -24,6 +24,7 @@ use halo2_proofs::{circuit::Value, plonk::Error};
 pub(crate) struct BlockHashGadget<F> {
     same_context: SameContextGadget<F>,
     block_number: RandomLinearCombination<F, N_BYTES_U64>,
+    tx_id: Cell<F>,
     current_block_number: Cell<F>,
     block_hash: Word<F>,
     block_lt: LtGadget<F, N_BYTES_U64>,
@@ -39,10 +40,17 @@ impl<F: Field> ExecutionGadget<F> for BlockHashGadget<F> {
         let block_number = cb.query_word_rlc();
         cb.stack_pop(block_number.expr());
 
-        let current_block_number = cb.query_cell();
+        let block_hash = cb.query_word_rlc();
+        cb.stack_push(block_hash.expr());
+
+        // The current block is the one of the tx, which in a batch of blocks
+        // is given by the tx table.
+        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
+        let current_block_number =
+            cb.tx_context(tx_id.expr(), TxContextFieldTag::BlockNumber, None);
         cb.block_lookup(
             BlockContextFieldTag::Number.expr(),
-            None,
+            Some(current_block_number.expr()),
             current_block_number.expr(),
         );
 
@@ -58,7 +66,6 @@ impl<F: Field> ExecutionGadget<F> for BlockHashGadget<F> {
             257.expr() + from_bytes::expr(&block_number.cells),
         );
 
-        let block_hash = cb.query_word_rlc();
         cb.condition(block_lt.expr() * diff_lt.expr(), |cb| {
             cb.block_lookup(
                 BlockContextFieldTag::BlockHash.expr(),
@@ -70,10 +77,8 @@ impl<F: Field> ExecutionGadget<F> for BlockHashGadget<F> {
             cb.require_zero("invalid range", block_hash.expr());
         });
 
-        cb.stack_push(block_hash.expr());
-
         let step_state_transition = StepStateTransition {
-            rw_counter: Delta(2.expr()),
+            rw_counter: Delta(3.expr()),
             program_counter: Delta(1.expr()),
             gas_left: Delta(-OpcodeId::BLOCKHASH.constant_gas_cost().expr()),
             ..Default::default()
@@ -84,6 +89,7 @@ impl<F: Field> ExecutionGadget<F> for BlockHashGadget<F> {
         Self {
             same_context,
             block_number,
+            tx_id,
             current_block_number,
             block_hash,
             block_lt,
@@ -96,7 +102,7 @@ impl<F: Field> ExecutionGadget<F> for BlockHashGadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
@@ -114,17 +120,11 @@ impl<F: Field> ExecutionGadget<F> for BlockHashGadget<F> {
         )?;
         let block_number: F = block_number.to_scalar().unwrap();
 
-        let current_block_number = block.context.number;
-        self.current_block_number.assign(
-            region,
-            offset,
-            Value::known(
-                current_block_number
-                    .to_scalar()
-                    .expect("unexpected U256 -> Scalar conversion failure"),
-            ),
-        )?;
-        let current_block_number: F = current_block_number.to_scalar().unwrap();
+        self.tx_id
+            .assign(region, offset, Value::known(F::from(tx.id as u64)))?;
+        let current_block_number = F::from(tx.block_number);
+        self.current_block_number
+            .assign(region, offset, Value::known(current_block_number))?;
 
         self.block_hash.assign(
             region,
@@ -1,15 +1,17 @@
 use crate::{
     evm_circuit::{
         execution::ExecutionGadget,
+        param::N_BYTES_GAS,
         step::ExecutionState,
         util::{
+            and,
             constraint_builder::{ConstraintBuilder, StepStateTransition, Transition::Same},
-            math_gadget::{IsEqualGadget, IsZeroGadget},
+            math_gadget::{IsEqualGadget, IsZeroGadget, LtGadget},
             not, CachedRegion, Cell,
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::{CallContextFieldTag, TxContextFieldTag},
+    table::{BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag, TxReceiptFieldTag},
     util::Expr,
 };
 use eth_types::Field;
@@ -20,8 +22,14 @@ pub(crate) struct EndBlockGadget<F> {
     total_txs: Cell<F>,
     total_txs_is_max_txs: IsEqualGadget<F>,
     is_empty_block: IsZeroGadget<F>,
+    rw_counter_start: Cell<F>,
+    is_last_chunk: Cell<F>,
     max_rws: Cell<F>,
     max_txs: Cell<F>,
+    block_number: Cell<F>,
+    gas_limit: Cell<F>,
+    cumulative_gas_used: Cell<F>,
+    gas_limit_exceeded: LtGadget<F, N_BYTES_GAS>,
 }
 
 const EMPTY_BLOCK_N_RWS: u64 = 0;
@@ -36,45 +44,116 @@ impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
         let max_rws = cb.query_copy_cell();
         let total_txs = cb.query_cell();
         let total_txs_is_max_txs = IsEqualGadget::construct(cb, total_txs.expr(), max_txs.expr());
-        // Note that rw_counter starts at 1
-        let is_empty_block =
-            IsZeroGadget::construct(cb, cb.curr.state.rw_counter.clone().expr() - 1.expr());
+        // The program counter holds the id of the withdrawal following the last
+        // one, so it starts at 1 in a block without withdrawals.
+        // When the block is split in chunks, the rw_counter starts at the
+        // first rw of the chunk, and the withdrawals are only done in the last
+        // chunk.
+        let [rw_counter_start, is_last_chunk] = [
+            BlockContextFieldTag::RwCounterStart,
+            BlockContextFieldTag::IsLastChunk,
+        ]
+        .map(|tag| {
+            let cell = cb.query_cell();
+            cb.block_lookup(tag.expr(), None, cell.expr());
+            cell
+        });
+        let program_counter = cb.curr.state.program_counter.expr();
+        let rw_counter = cb.curr.state.rw_counter.expr();
+        cb.step_first(|cb| {
+            cb.require_equal(
+                "program_counter is initialized to be 1",
+                program_counter.clone(),
+                1.expr(),
+            );
+            cb.require_equal(
+                "rw_counter is initialized to be the start of the chunk",
+                rw_counter.clone(),
+                rw_counter_start.expr(),
+            );
+        });
+        let num_withdrawals = program_counter - 1.expr();
+        // Note that each withdrawal does 1 rw_table lookup.
+        let is_empty_block = IsZeroGadget::construct(
+            cb,
+            rw_counter.clone() - rw_counter_start.expr() - num_withdrawals.clone(),
+        );
         // If the block is empty, we do 0 rw_table lookups
-        // If the block is not empty, we will do 1 call_context lookup
-        let total_rws = not::expr(is_empty_block.expr())
-            * (cb.curr.state.rw_counter.clone().expr() - 1.expr() + 1.expr());
+        // If the block is not empty, we will do 1 call_context lookup and 1
+        // tx_receipt lookup
+        let total_rws =
+            rw_counter - rw_counter_start.expr() + not::expr(is_empty_block.expr()) * 2.expr();
 
         // 1. Constraint total_rws and total_txs witness values depending on the empty
         // block case.
+        let cumulative_gas_used = cb.query_cell();
+        let block_number = cb.query_cell();
         cb.condition(is_empty_block.expr(), |cb| {
             // 1a.
             cb.require_equal("total_txs is 0 in empty block", total_txs.expr(), 0.expr());
+            cb.require_zero(
+                "cumulative_gas_used is 0 in empty block",
+                cumulative_gas_used.expr(),
+            );
         });
         cb.condition(not::expr(is_empty_block.expr()), |cb| {
             // 1b. total_txs matches the tx_id that corresponds to the final step.
             cb.call_context_lookup(0.expr(), None, CallContextFieldTag::TxId, total_txs.expr());
-        });
-
-        // 2. If total_txs == max_txs, we know we have covered all txs from the
-        // tx_table. If not, we need to check that the rest of txs in the
-        // table are padding.
-        cb.condition(not::expr(total_txs_is_max_txs.expr()), |cb| {
-            // Verify that there are at most total_txs meaningful txs in the tx_table, by
-            // showing that the Tx following the last processed one has
-            // CallerAddress = 0x0 (which means padding tx).
+            // 1c. cumulative_gas_used is the one in the receipt of the last tx.
+            cb.tx_receipt_lookup(
+                0.expr(),
+                total_txs.expr(),
+                TxReceiptFieldTag::CumulativeGasUsed,
+                cumulative_gas_used.expr(),
+            );
+            // 1d. The last tx belongs to the last block of the batch.
             cb.tx_context_lookup(
-                total_txs.expr() + 1.expr(),
-                TxContextFieldTag::CallerAddress,
+                total_txs.expr(),
+                TxContextFieldTag::BlockNumber,
                 None,
-                0.expr(),
+                block_number.expr(),
             );
-            // Since every tx lookup done in the EVM circuit must succeed
-            // and uses a unique tx_id, we know that at
-            // least there are total_tx meaningful txs in
-            // the tx_table. We conclude that the number of
-            // meaningful txs in the tx_table is total_tx.
         });
 
+        // The gas used by all the txs in the block must fit in the block gas
+        // limit.  In a batch of blocks, the gas used by the previous blocks is
+        // checked against their gas limit by EndTx.
+        let gas_limit = cb.query_cell();
+        cb.block_lookup(
+            BlockContextFieldTag::GasLimit.expr(),
+            Some(block_number.expr()),
+            gas_limit.expr(),
+        );
+        let gas_limit_exceeded =
+            LtGadget::construct(cb, gas_limit.expr(), cumulative_gas_used.expr());
+        cb.require_zero(
+            "cumulative_gas_used <= gas_limit",
+            gas_limit_exceeded.expr(),
+        );
+
+        // 2. If total_txs == max_txs, we know we have covered all txs from the
+        // tx_table. If not, we need to check that the rest of txs in the
+        // table are padding.  The txs of the following chunks are not padding.
+        cb.condition(
+            and::expr([is_last_chunk.expr(), not::expr(total_txs_is_max_txs.expr())]),
+            |cb| {
+                // Verify that there are at most total_txs meaningful txs in the tx_table, by
+                // showing that the Tx following the last processed one has
+                // CallerAddress = 0x0 (which means padding tx).
+                cb.tx_context_lookup(
+                    total_txs.expr() + 1.expr(),
+                    TxContextFieldTag::CallerAddress,
+                    None,
+                    0.expr(),
+                );
+                // Since every tx lookup done in the EVM circuit must succeed
+                // and uses a unique tx_id, we know that at
+                // least there are total_tx meaningful txs in
+                // the tx_table. We conclude that the number of
+                // meaningful txs in the tx_table is total_tx.
+            },
+        );
+
         // 3. Verify rw_counter counts to the same number of meaningful rows in
         // rw_table to ensure there is no malicious insertion.
         // Verify that there are at most total_rws meaningful entries in the rw_table
@@ -90,11 +169,29 @@ impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
         //     // TODO: Handle reward to coinbase.  Depends on spec:
         //     // https://github.com/privacy-scaling-explorations/zkevm-specs/issues/290
         // });
+
+        // 4. Verify that there are exactly num_withdrawals withdrawals in the
+        // withdrawal table, by showing that the one following the last
+        // withdrawal is the padding withdrawal, with zero fields.  The
+        // PublicInputs circuit places it after the withdrawals of the batch,
+        // whose amount is never zero.  The withdrawals are only done in the
+        // last chunk.
+        cb.condition(is_last_chunk.expr(), |cb| {
+            cb.withdrawal_lookup(
+                num_withdrawals + 1.expr(),
+                0.expr(),
+                0.expr(),
+                0.expr(),
+                0.expr(),
+            );
+        });
+
         cb.not_step_last(|cb| {
-            // Propagate rw_counter and call_id all the way down.
+            // Propagate rw_counter, call_id and program_counter all the way down.
             cb.require_step_state_transition(StepStateTransition {
                 rw_counter: Same,
                 call_id: Same,
+                program_counter: Same,
                 ..StepStateTransition::any()
             });
         });
@@ -105,6 +202,12 @@ impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
             total_txs,
             total_txs_is_max_txs,
             is_empty_block,
+            rw_counter_start,
+            is_last_chunk,
+            block_number,
+            gas_limit,
+            cumulative_gas_used,
+            gas_limit_exceeded,
         }
     }
 
@@ -117,18 +220,63 @@ impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.is_empty_block
-            .assign(region, offset, F::from(step.rw_counter as u64 - 1))?;
+        let chunk = &block.context.chunk;
+        let num_withdrawals = step.program_counter as usize - 1;
+        self.is_empty_block.assign(
+            region,
+            offset,
+            F::from((step.rw_counter - chunk.rw_counter_start - num_withdrawals) as u64),
+        )?;
+        self.rw_counter_start.assign(
+            region,
+            offset,
+            Value::known(F::from(chunk.rw_counter_start as u64)),
+        )?;
+        self.is_last_chunk.assign(
+            region,
+            offset,
+            Value::known(F::from(chunk.is_last() as u64)),
+        )?;
         let max_rws = F::from(block.circuits_params.max_rws as u64);
         let max_rws_assigned = self.max_rws.assign(region, offset, Value::known(max_rws))?;
 
-        let total_txs = F::from(block.txs.len() as u64);
+        // The last tx executed in the chunk of the block
+        let last_tx = block.txs.iter().rfind(|tx| !tx.steps.is_empty());
+        let total_txs = F::from(last_tx.map_or(0, |tx| tx.id) as u64);
         let max_txs = F::from(block.circuits_params.max_txs as u64);
         self.total_txs
             .assign(region, offset, Value::known(total_txs))?;
         self.total_txs_is_max_txs
             .assign(region, offset, total_txs, max_txs)?;
         let max_txs_assigned = self.max_txs.assign(region, offset, Value::known(max_txs))?;
+
+        let block_number = match last_tx {
+            Some(tx) => tx.block_number,
+            None => block.context.last().number.low_u64(),
+        };
+        let gas_limit = block.context.get(block_number).gas_limit;
+        // The cumulative gas used is read from the receipt of the last tx by
+        // the second rw of the last EndBlock step, after its TxId.  The other
+        // EndBlock steps do the same lookups at the same rw_counter.
+        let cumulative_gas_used = match last_tx {
+            None => 0,
+            Some(_) => block.rws[block.end_block_last.rw_indices[1]].receipt_value(),
+        };
+        self.block_number
+            .assign(region, offset, Value::known(F::from(block_number)))?;
+        self.gas_limit
+            .assign(region, offset, Value::known(F::from(gas_limit)))?;
+        self.cumulative_gas_used.assign(
+            region,
+            offset,
+            Value::known(F::from(cumulative_gas_used)),
+        )?;
+        self.gas_limit_exceeded.assign(
+            region,
+            offset,
+            F::from(gas_limit),
+            F::from(cumulative_gas_used),
+        )?;
         // When rw_indices is not empty, we're at the last row (at a fixed offset),
         // where we need to access the max_rws and max_txs constant.
         if !step.rw_indices.is_empty() {
@@ -141,13 +289,17 @@ impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::{test_circuits_witness_block, BytecodeTestConfig};
+    use crate::{
+        test_util::{test_circuits_witness_block, BytecodeTestConfig},
+        witness::Block,
+    };
     use bus_mapping::{circuit_input_builder::CircuitsParams, mock::BlockData};
     use eth_types::bytecode;
     use eth_types::geth_types::GethData;
+    use halo2_proofs::halo2curves::bn256::Fr;
     use mock::TestContext;
 
-    fn test_circuit(evm_circuit_pad_to: usize) {
+    fn witness_block() -> Block<Fr> {
         let bytecode = bytecode! {
             PUSH1(0)
             STOP
@@ -163,7 +315,11 @@ mod test {
             .unwrap();
 
         // build a witness block from trace result
-        let mut block = crate::witness::block_convert(&builder.block, &builder.code_db).unwrap();
+        crate::witness::block_convert(&builder.block, &builder.code_db).unwrap()
+    }
+
+    fn test_circuit(evm_circuit_pad_to: usize) {
+        let mut block = witness_block();
         block.evm_circuit_pad_to = evm_circuit_pad_to;
 
         // finish required tests using this witness block
@@ -186,4 +342,20 @@ mod test {
     fn end_block_padding() {
         test_circuit(50);
     }
+
+    // Test where the gas used by the txs doesn't fit in the block gas limit
+    #[test]
+    fn end_block_gas_limit_exceeded() {
+        let mut block = witness_block();
+        let gas_used: u64 = block
+            .txs
+            .iter()
+            .map(|tx| tx.gas - tx.steps.last().unwrap().gas_left)
+            .sum();
+        for ctx in block.context.ctxs.values_mut() {
+            ctx.gas_limit = gas_used - 1;
+        }
+
+        assert!(test_circuits_witness_block(block, BytecodeTestConfig::default()).is_err());
+    }
 }
@@ -7,24 +7,21 @@ use crate::{
             common_gadget::UpdateBalanceGadget,
             constraint_builder::{
                 ConstraintBuilder, StepStateTransition,
-                Transition::{Delta, Same},
+                Transition::{Delta, Same, To},
             },
             math_gadget::{
-                AddWordsGadget, ConstantDivisionGadget, IsEqualGadget, MinMaxGadget,
+                AddWordsGadget, ConstantDivisionGadget, IsEqualGadget, LtGadget, MinMaxGadget,
                 MulWordByU64Gadget,
             },
-            CachedRegion, Cell,
+            not, or, CachedRegion, Cell,
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::{
-        BlockContextFieldTag, CallContextFieldTag, RwTableTag, TxContextFieldTag, TxReceiptFieldTag,
-    },
+    table::{BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag, TxReceiptFieldTag},
     util::Expr,
 };
 use eth_types::{evm_types::MAX_REFUND_QUOTIENT_OF_GAS_USED, Field, ToScalar};
 use halo2_proofs::{circuit::Value, plonk::Error};
-use strum::EnumCount;
 
 #[derive(Clone, Debug)]
 pub(crate) struct EndTxGadget<F> {
@@ -35,6 +32,7 @@ pub(crate) struct EndTxGadget<F> {
     effective_refund: MinMaxGadget<F, N_BYTES_GAS>,
     mul_gas_price_by_refund: MulWordByU64Gadget<F>,
     tx_caller_address: Cell<F>,
+    tx_block_number: Cell<F>,
     gas_fee_refund: UpdateBalanceGadget<F, 2, true>,
     sub_gas_price_by_base_fee: AddWordsGadget<F, 2, true>,
     mul_effective_tip_by_gas_used: MulWordByU64Gadget<F>,
@@ -42,7 +40,12 @@ pub(crate) struct EndTxGadget<F> {
     coinbase_reward: UpdateBalanceGadget<F, 2, true>,
     current_cumulative_gas_used: Cell<F>,
     is_first_tx: IsEqualGadget<F>,
+    prev_tx_block_number: Cell<F>,
+    is_same_block_as_prev_tx: IsEqualGadget<F>,
+    gas_limit: Cell<F>,
+    tx_gas_exceeds_remaining: LtGadget<F, N_BYTES_GAS>,
     is_persistent: Cell<F>,
+    is_tx_invalid: Cell<F>,
 }
 
 impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
@@ -53,10 +56,14 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
     fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
         let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
         let is_persistent = cb.call_context(None, CallContextFieldTag::IsPersistent);
+        let is_tx_invalid = cb.call_context(None, CallContextFieldTag::IsInvalidTx);
 
-        let [tx_gas, tx_caller_address] =
-            [TxContextFieldTag::Gas, TxContextFieldTag::CallerAddress]
-                .map(|field_tag| cb.tx_context(tx_id.expr(), field_tag, None));
+        let [tx_gas, tx_caller_address, tx_block_number] = [
+            TxContextFieldTag::Gas,
+            TxContextFieldTag::CallerAddress,
+            TxContextFieldTag::BlockNumber,
+        ]
+        .map(|field_tag| cb.tx_context(tx_id.expr(), field_tag, None));
         let tx_gas_price = cb.tx_context_as_word(tx_id.expr(), TxContextFieldTag::GasPrice, None);
 
         // Calculate effective gas to refund
@@ -70,11 +77,19 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
         cb.tx_refund_read(tx_id.expr(), refund.expr());
         let effective_refund = MinMaxGadget::construct(cb, max_refund.quotient(), refund.expr());
 
-        // Add effective_refund * tx_gas_price back to caller's balance
+        // Add effective_refund * tx_gas_price back to caller's balance, unless
+        // the tx is invalid, in which case the caller hasn't paid for the gas.
         let mul_gas_price_by_refund = MulWordByU64Gadget::construct(
             cb,
             tx_gas_price.clone(),
-            effective_refund.min() + cb.curr.state.gas_left.expr(),
+            not::expr(is_tx_invalid.expr())
+                * (effective_refund.min() + cb.curr.state.gas_left.expr()),
+        );
+        // BeginTx checked that the gas fee of a valid tx doesn't overflow, so
+        // neither does its refund.
+        cb.require_zero(
+            "gas fee refund doesn't overflow",
+            mul_gas_price_by_refund.overflow(),
         );
         let gas_fee_refund = UpdateBalanceGadget::construct(
             cb,
@@ -90,13 +105,17 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             (BlockContextFieldTag::Coinbase, coinbase.expr()),
             (BlockContextFieldTag::BaseFee, base_fee.expr()),
         ] {
-            cb.block_lookup(tag.expr(), None, value);
+            cb.block_lookup(tag.expr(), Some(tx_block_number.expr()), value);
         }
         let effective_tip = cb.query_word_rlc();
         let sub_gas_price_by_base_fee =
             AddWordsGadget::construct(cb, [effective_tip.clone(), base_fee], tx_gas_price);
         let mul_effective_tip_by_gas_used =
             MulWordByU64Gadget::construct(cb, effective_tip, gas_used.clone());
+        cb.require_zero(
+            "coinbase reward doesn't overflow",
+            mul_effective_tip_by_gas_used.overflow(),
+        );
         let coinbase_reward = UpdateBalanceGadget::construct(
             cb,
             coinbase.expr(),
@@ -118,17 +137,34 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             cb.curr.state.log_id.expr(),
         );
 
+        // The cumulative gas used is per block, so it starts again from 0 at
+        // the first tx of each block of a batch.
         let is_first_tx = IsEqualGadget::construct(cb, tx_id.expr(), 1.expr());
+        let prev_tx_block_number = cb.query_cell();
+        cb.condition(not::expr(is_first_tx.expr()), |cb| {
+            cb.tx_context_lookup(
+                tx_id.expr() - 1.expr(),
+                TxContextFieldTag::BlockNumber,
+                None,
+                prev_tx_block_number.expr(),
+            );
+        });
+        let is_same_block_as_prev_tx =
+            IsEqualGadget::construct(cb, prev_tx_block_number.expr(), tx_block_number.expr());
+        let is_first_tx_of_block = or::expr([
+            is_first_tx.expr(),
+            not::expr(is_same_block_as_prev_tx.expr()),
+        ]);
 
         let current_cumulative_gas_used = cb.query_cell();
-        cb.condition(is_first_tx.expr(), |cb| {
+        cb.condition(is_first_tx_of_block.clone(), |cb| {
             cb.require_zero(
-                "current_cumulative_gas_used is zero when tx is first tx",
+                "current_cumulative_gas_used is zero when tx is first tx of the block",
                 current_cumulative_gas_used.expr(),
             );
         });
 
-        cb.condition(1.expr() - is_first_tx.expr(), |cb| {
+        cb.condition(not::expr(is_first_tx_of_block.clone()), |cb| {
             cb.tx_receipt_lookup(
                 0.expr(),
                 tx_id.expr() - 1.expr(),
@@ -144,6 +180,29 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             gas_used + current_cumulative_gas_used.expr(),
         );
 
+        // The gas limit of a valid tx must fit in the gas left in the block:
+        // the gas used by previous txs plus the tx gas limit must not exceed
+        // the block gas limit.  The sum is compared instead of the gas left,
+        // which would wrap around in the field when the gas used by previous
+        // txs exceeds the block gas limit.
+        let gas_limit = cb.query_cell();
+        cb.block_lookup(
+            BlockContextFieldTag::GasLimit.expr(),
+            Some(tx_block_number.expr()),
+            gas_limit.expr(),
+        );
+        let tx_gas_exceeds_remaining = LtGadget::construct(
+            cb,
+            gas_limit.expr(),
+            current_cumulative_gas_used.expr() + tx_gas.expr(),
+        );
+        cb.condition(not::expr(is_tx_invalid.expr()), |cb| {
+            cb.require_zero(
+                "current_cumulative_gas_used + tx_gas <= gas_limit",
+                tx_gas_exceeds_remaining.expr(),
+            );
+        });
+
         cb.condition(
             cb.next.execution_state_selector([ExecutionState::BeginTx]),
             |cb| {
@@ -155,20 +214,24 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
                 );
 
                 cb.require_step_state_transition(StepStateTransition {
-                    rw_counter: Delta(10.expr() - is_first_tx.expr()),
+                    rw_counter: Delta(11.expr() - is_first_tx_of_block.clone()),
                     ..StepStateTransition::any()
                 });
             },
         );
 
         cb.condition(
-            cb.next.execution_state_selector([ExecutionState::EndBlock]),
+            cb.next
+                .execution_state_selector([ExecutionState::Withdrawal, ExecutionState::EndBlock]),
             |cb| {
                 cb.require_step_state_transition(StepStateTransition {
-                    rw_counter: Delta(9.expr() - is_first_tx.expr()),
+                    rw_counter: Delta(10.expr() - is_first_tx_of_block),
                     // We propagate call_id so that EndBlock can get the last tx_id
                     // in order to count processed txs.
                     call_id: Same,
+                    // The program counter of Withdrawal and EndBlock holds the
+                    // id of the next withdrawal.
+                    program_counter: To(1.expr()),
                     ..StepStateTransition::any()
                 });
             },
@@ -182,6 +245,7 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             effective_refund,
             mul_gas_price_by_refund,
             tx_caller_address,
+            tx_block_number,
             gas_fee_refund,
             sub_gas_price_by_base_fee,
             mul_effective_tip_by_gas_used,
@@ -189,7 +253,12 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             coinbase_reward,
             current_cumulative_gas_used,
             is_first_tx,
+            prev_tx_block_number,
+            is_same_block_as_prev_tx,
+            gas_limit,
+            tx_gas_exceeds_remaining,
             is_persistent,
+            is_tx_invalid,
         }
     }
 
@@ -203,9 +272,16 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
         step: &ExecStep,
     ) -> Result<(), Error> {
         let gas_used = tx.gas - step.gas_left;
-        let (refund, _) = block.rws[step.rw_indices[2]].tx_refund_value_pair();
+        let context = block.context.get(tx.block_number);
+        let prev_tx_block_number = if tx.id == 1 {
+            0
+        } else {
+            block.txs[tx.id - 2].block_number
+        };
+        let is_first_tx_of_block = tx.id == 1 || prev_tx_block_number != tx.block_number;
+        let (refund, _) = block.rws[step.rw_indices[3]].tx_refund_value_pair();
         let [(caller_balance, caller_balance_prev), (coinbase_balance, coinbase_balance_prev)] =
-            [step.rw_indices[3], step.rw_indices[4]].map(|idx| block.rws[idx].account_value_pair());
+            [step.rw_indices[4], step.rw_indices[5]].map(|idx| block.rws[idx].account_value_pair());
 
         self.tx_id
             .assign(region, offset, Value::known(F::from(tx.id as u64)))?;
@@ -221,12 +297,17 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             F::from(refund),
         )?;
         let effective_refund = refund.min(max_refund as u64);
-        let gas_fee_refund = tx.gas_price * (effective_refund + step.gas_left);
+        let refund_gas = if tx.is_invalid {
+            0
+        } else {
+            effective_refund + step.gas_left
+        };
+        let gas_fee_refund = tx.gas_price * refund_gas;
         self.mul_gas_price_by_refund.assign(
             region,
             offset,
             tx.gas_price,
-            effective_refund + step.gas_left,
+            refund_gas,
             gas_fee_refund,
         )?;
         self.tx_caller_address.assign(
@@ -238,6 +319,8 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
                     .expect("unexpected Address -> Scalar conversion failure"),
             ),
         )?;
+        self.tx_block_number
+            .assign(region, offset, Value::known(F::from(tx.block_number)))?;
         self.gas_fee_refund.assign(
             region,
             offset,
@@ -245,11 +328,11 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             vec![gas_fee_refund],
             caller_balance,
         )?;
-        let effective_tip = tx.gas_price - block.context.base_fee;
+        let effective_tip = tx.gas_price - context.base_fee;
         self.sub_gas_price_by_base_fee.assign(
             region,
             offset,
-            [effective_tip, block.context.base_fee],
+            [effective_tip, context.base_fee],
             tx.gas_price,
         )?;
         self.mul_effective_tip_by_gas_used.assign(
@@ -263,8 +346,7 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             region,
             offset,
             Value::known(
-                block
-                    .context
+                context
                     .coinbase
                     .to_scalar()
                     .expect("unexpected Address -> Scalar conversion failure"),
@@ -278,16 +360,12 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             coinbase_balance,
         )?;
 
-        let current_cumulative_gas_used: u64 = if tx.id == 1 {
+        let current_cumulative_gas_used: u64 = if is_first_tx_of_block {
             0
         } else {
-            // first transaction needs TxReceiptFieldTag::COUNT(3) lookups to tx receipt,
-            // while later transactions need 4 (with one extra cumulative gas read) lookups
-            let rw = &block.rws[(
-                RwTableTag::TxReceipt,
-                (tx.id - 2) * (TxReceiptFieldTag::COUNT + 1) + 2,
-            )];
-            rw.receipt_value()
+            // The cumulative gas of the previous tx is read after the
+            // PostStateOrStatus and LogLength writes.
+            block.rws[step.rw_indices[8]].receipt_value()
         };
 
         self.current_cumulative_gas_used.assign(
@@ -297,11 +375,32 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
         )?;
         self.is_first_tx
             .assign(region, offset, F::from(tx.id as u64), F::one())?;
+        self.prev_tx_block_number.assign(
+            region,
+            offset,
+            Value::known(F::from(prev_tx_block_number)),
+        )?;
+        self.is_same_block_as_prev_tx.assign(
+            region,
+            offset,
+            F::from(prev_tx_block_number),
+            F::from(tx.block_number),
+        )?;
+        self.gas_limit
+            .assign(region, offset, Value::known(F::from(context.gas_limit)))?;
+        self.tx_gas_exceeds_remaining.assign(
+            region,
+            offset,
+            F::from(context.gas_limit),
+            F::from(current_cumulative_gas_used) + F::from(tx.gas),
+        )?;
         self.is_persistent.assign(
             region,
             offset,
             Value::known(F::from(call.is_persistent as u64)),
         )?;
+        self.is_tx_invalid
+            .assign(region, offset, Value::known(F::from(tx.is_invalid as u64)))?;
 
         Ok(())
     }
@@ -309,23 +408,49 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::evm_circuit::test::run_test_circuit_geth_data;
-    use bus_mapping::circuit_input_builder::CircuitsParams;
+    use crate::{
+        evm_circuit::test::run_test_circuit_geth_data,
+        test_util::{test_circuits_witness_block, BytecodeTestConfig},
+    };
+    use bus_mapping::{circuit_input_builder::CircuitsParams, mock::BlockData};
     use eth_types::{self, bytecode, geth_types::GethData};
     use halo2_proofs::halo2curves::bn256::Fr;
     use mock::{eth, test_ctx::helpers::account_0_code_account_1_no_code, TestContext};
 
+    fn params() -> CircuitsParams {
+        CircuitsParams {
+            max_txs: 4,
+            ..Default::default()
+        }
+    }
+
     fn test_ok(block: GethData) {
-        assert_eq!(
-            run_test_circuit_geth_data::<Fr>(
-                block,
-                CircuitsParams {
-                    max_txs: 4,
-                    ..Default::default()
-                }
-            ),
-            Ok(())
-        );
+        assert_eq!(run_test_circuit_geth_data::<Fr>(block, params()), Ok(()));
+    }
+
+    fn multiple_txs() -> GethData {
+        // Get the execution steps from the external tracer
+        TestContext::<2, 3>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            |mut txs, accs| {
+                txs[0]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .value(eth(1));
+                txs[1]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .value(eth(1));
+                txs[2]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .value(eth(1));
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into()
     }
 
     #[test]
@@ -345,29 +470,27 @@ mod test {
         // )]);
 
         // Multiple txs
-        test_ok(
-            // Get the execution steps from the external tracer
-            TestContext::<2, 3>::new(
-                None,
-                account_0_code_account_1_no_code(bytecode! { STOP }),
-                |mut txs, accs| {
-                    txs[0]
-                        .to(accs[0].address)
-                        .from(accs[1].address)
-                        .value(eth(1));
-                    txs[1]
-                        .to(accs[0].address)
-                        .from(accs[1].address)
-                        .value(eth(1));
-                    txs[2]
-                        .to(accs[0].address)
-                        .from(accs[1].address)
-                        .value(eth(1));
-                },
-                |block, _tx| block.number(0xcafeu64),
-            )
-            .unwrap()
-            .into(),
-        );
+        test_ok(multiple_txs());
+    }
+
+    // Test where the gas used by the previous txs reaches the block gas limit,
+    // so that the gas limits of the following txs don't fit in it.
+    #[test]
+    fn end_tx_block_gas_limit_exceeded() {
+        let block = multiple_txs();
+        let mut builder = BlockData::new_from_geth_data_with_params(block.clone(), params())
+            .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let mut block =
+            crate::witness::block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        let first_tx = &block.txs[0];
+        let gas_limit = first_tx.gas - first_tx.steps.last().unwrap().gas_left;
+        for ctx in block.context.ctxs.values_mut() {
+            ctx.gas_limit = gas_limit;
+        }
+
+        assert!(test_circuits_witness_block(block, BytecodeTestConfig::default()).is_err());
     }
 }
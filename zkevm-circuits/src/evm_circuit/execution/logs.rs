@@ -63,14 +63,28 @@ impl<F: Field> ExecutionGadget<F> for LogGadget<F> {
         let is_persistent = cb.call_context(None, CallContextFieldTag::IsPersistent);
         cb.require_boolean("is_persistent is bool", is_persistent.expr());
 
+        let opcode = cb.query_cell();
+        let topic_count = opcode.expr() - OpcodeId::LOG0.as_u8().expr();
+
+        // check memory copy
+        let memory_address = MemoryAddressGadget::construct(cb, mstart, msize);
+
+        // The numbers of topics and data bytes are written with the address,
+        // for the RLP encoding of the receipt.
         cb.condition(is_persistent.expr(), |cb| {
-            cb.tx_log_lookup(
-                tx_id.expr(),
-                cb.curr.state.log_id.expr() + 1.expr(),
-                TxLogFieldTag::Address,
-                0.expr(),
-                contract_address.expr(),
-            );
+            for (field_tag, value) in [
+                (TxLogFieldTag::Address, contract_address.expr()),
+                (TxLogFieldTag::TopicLength, topic_count.clone()),
+                (TxLogFieldTag::DataLength, memory_address.length()),
+            ] {
+                cb.tx_log_lookup(
+                    tx_id.expr(),
+                    cb.curr.state.log_id.expr() + 1.expr(),
+                    field_tag,
+                    0.expr(),
+                    value,
+                );
+            }
         });
 
         // constrain topics in logs
@@ -91,9 +105,6 @@ impl<F: Field> ExecutionGadget<F> for LogGadget<F> {
             });
         }
 
-        let opcode = cb.query_cell();
-        let topic_count = opcode.expr() - OpcodeId::LOG0.as_u8().expr();
-
         // TOPIC_COUNT == Non zero topic selector count
         cb.require_equal(
             " sum of topic selectors = topic_count ",
@@ -114,9 +125,6 @@ impl<F: Field> ExecutionGadget<F> for LogGadget<F> {
             }
         }
 
-        // check memory copy
-        let memory_address = MemoryAddressGadget::construct(cb, mstart, msize);
-
         // Calculate the next memory size and the gas cost for this memory
         // access
         let memory_expansion = MemoryExpansionGadget::construct(cb, [memory_address.address()]);
@@ -209,7 +217,7 @@ impl<F: Field> ExecutionGadget<F> for LogGadget<F> {
 
         let is_persistent = call.is_persistent as u64;
         let mut topic_stack_entry = if topic_count > 0 {
-            step.rw_indices[6 + call.is_persistent as usize]
+            step.rw_indices[6 + 3 * call.is_persistent as usize]
         } else {
             // if topic_count == 0, this value will be no used anymore
             (RwTableTag::Stack, 0usize)
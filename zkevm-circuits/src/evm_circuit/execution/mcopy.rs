@@ -0,0 +1,266 @@
+use crate::{
+    evm_circuit::{
+        execution::ExecutionGadget,
+        param::N_BYTES_MEMORY_WORD_SIZE,
+        step::ExecutionState,
+        util::{
+            common_gadget::SameContextGadget,
+            constraint_builder::{
+                ConstraintBuilder, StepStateTransition,
+                Transition::{Delta, To},
+            },
+            memory_gadget::{MemoryAddressGadget, MemoryCopierGasGadget, MemoryExpansionGadget},
+            CachedRegion, Cell,
+        },
+        witness::{Block, Call, ExecStep, Transaction},
+    },
+    util::Expr,
+};
+use bus_mapping::{circuit_input_builder::CopyDataType, evm::OpcodeId};
+use eth_types::{evm_types::GasCost, Field, ToScalar};
+use gadgets::util::not;
+use halo2_proofs::{circuit::Value, plonk::Error};
+
+#[derive(Clone, Debug)]
+pub(crate) struct McopyGadget<F> {
+    same_context: SameContextGadget<F>,
+    /// The memory range that is written to.
+    dst_memory_addr: MemoryAddressGadget<F>,
+    /// The memory range that is read from.
+    src_memory_addr: MemoryAddressGadget<F>,
+    /// Opcode MCOPY has a dynamic gas cost:
+    /// gas_code = static_gas * minimum_word_size + memory_expansion_cost
+    /// where the memory expansion covers both the source and the destination.
+    memory_expansion: MemoryExpansionGadget<F, 2, N_BYTES_MEMORY_WORD_SIZE>,
+    /// Opcode MCOPY needs to copy data within memory. We account for the
+    /// copying costs using the memory copier gas gadget.
+    memory_copier_gas: MemoryCopierGasGadget<F, { GasCost::COPY }>,
+    /// RW inverse counter from the copy table at the start of related copy
+    /// steps.
+    copy_rwc_inc: Cell<F>,
+}
+
+impl<F: Field> ExecutionGadget<F> for McopyGadget<F> {
+    const NAME: &'static str = "MCOPY";
+
+    const EXECUTION_STATE: ExecutionState = ExecutionState::MCOPY;
+
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        let opcode = cb.query_cell();
+
+        let dest_offset = cb.query_cell_phase2();
+        let src_offset = cb.query_cell_phase2();
+        let length = cb.query_word_rlc();
+
+        // 1. Pop dest_offset, offset, length from stack
+        cb.stack_pop(dest_offset.expr());
+        cb.stack_pop(src_offset.expr());
+        cb.stack_pop(length.expr());
+
+        // 2. Construct the memory addresses of the source and the destination,
+        // which share the same length.
+        let dst_memory_addr = MemoryAddressGadget::construct(cb, dest_offset, length.clone());
+        let src_memory_addr = MemoryAddressGadget::construct(cb, src_offset, length);
+
+        // Calculate the next memory size and the gas cost for this memory
+        // access. This also accounts for the dynamic gas required to copy bytes
+        // within memory.
+        let memory_expansion = MemoryExpansionGadget::construct(
+            cb,
+            [src_memory_addr.address(), dst_memory_addr.address()],
+        );
+        let memory_copier_gas = MemoryCopierGasGadget::construct(
+            cb,
+            dst_memory_addr.length(),
+            memory_expansion.gas_cost(),
+        );
+
+        // 3. Copy from the memory of the current call to itself. The copy
+        // circuit does all the reads before the writes, so that overlapping
+        // ranges are handled correctly.
+        let copy_rwc_inc = cb.query_cell();
+        cb.condition(dst_memory_addr.has_length(), |cb| {
+            cb.copy_table_lookup(
+                cb.curr.state.call_id.expr(),
+                CopyDataType::Memory.expr(),
+                cb.curr.state.call_id.expr(),
+                CopyDataType::Memory.expr(),
+                src_memory_addr.offset(),
+                src_memory_addr.address(),
+                dst_memory_addr.offset(),
+                dst_memory_addr.length(),
+                0.expr(), // for MCOPY rlc_acc is 0
+                copy_rwc_inc.expr(),
+            );
+        });
+        cb.condition(not::expr(dst_memory_addr.has_length()), |cb| {
+            cb.require_zero(
+                "if no bytes to copy, copy table rwc inc == 0",
+                copy_rwc_inc.expr(),
+            );
+        });
+
+        // State transition
+        let step_state_transition = StepStateTransition {
+            rw_counter: Delta(cb.rw_counter_offset()),
+            program_counter: Delta(1.expr()),
+            stack_pointer: Delta(3.expr()),
+            gas_left: Delta(
+                -(OpcodeId::MCOPY.constant_gas_cost().expr() + memory_copier_gas.gas_cost()),
+            ),
+            memory_word_size: To(memory_expansion.next_memory_word_size()),
+            ..Default::default()
+        };
+        let same_context = SameContextGadget::construct(cb, opcode, step_state_transition);
+
+        Self {
+            same_context,
+            dst_memory_addr,
+            src_memory_addr,
+            memory_expansion,
+            memory_copier_gas,
+            copy_rwc_inc,
+        }
+    }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        _tx: &Transaction,
+        _call: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        self.same_context.assign_exec_step(region, offset, step)?;
+
+        let [dest_offset, src_offset, length] =
+            [0, 1, 2].map(|i| block.rws[step.rw_indices[i as usize]].stack_value());
+
+        // assign the source and destination memory offsets.
+        let dst_memory_address =
+            self.dst_memory_addr
+                .assign(region, offset, dest_offset, length)?;
+        let src_memory_address = self
+            .src_memory_addr
+            .assign(region, offset, src_offset, length)?;
+
+        // assign to gadgets handling memory expansion cost and copying cost.
+        let (_, memory_expansion_cost) = self.memory_expansion.assign(
+            region,
+            offset,
+            step.memory_word_size(),
+            [src_memory_address, dst_memory_address],
+        )?;
+        self.memory_copier_gas
+            .assign(region, offset, length.as_u64(), memory_expansion_cost)?;
+
+        // rw_counter always increases by `length` reads and `length` writes
+        let copy_rwc_inc = length + length;
+        self.copy_rwc_inc.assign(
+            region,
+            offset,
+            Value::known(
+                copy_rwc_inc
+                    .to_scalar()
+                    .expect("unexpected U256 -> Scalar conversion failure"),
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{evm_circuit::test::rand_word, test_util::run_test_circuits_with_params};
+    use bus_mapping::circuit_input_builder::CircuitsParams;
+    use eth_types::{bytecode, evm_types::Hardfork, Word};
+    use mock::{test_ctx::helpers::*, TestContext};
+
+    fn test_ok(dst_offset: u64, src_offset: u64, length: u64) {
+        // Fill the first 64 bytes of memory, then copy
+        let bytecode = bytecode! {
+            PUSH32(rand_word())
+            PUSH1(0x00)
+            MSTORE
+            PUSH32(rand_word())
+            PUSH1(0x20)
+            MSTORE
+            PUSH32(length)
+            PUSH32(src_offset)
+            PUSH32(dst_offset)
+            #[start]
+            MCOPY
+            STOP
+        };
+
+        let ctx = TestContext::<2, 1>::new_with_hardfork(
+            None,
+            account_0_code_account_1_no_code(bytecode),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .gas(Word::from(1_000_000u64));
+            },
+            |block, _tx| block.number(0xcafeu64),
+            Hardfork::Cancun,
+        )
+        .unwrap();
+
+        assert_eq!(
+            run_test_circuits_with_params(
+                ctx,
+                None,
+                CircuitsParams {
+                    hardfork: Hardfork::Cancun,
+                    ..Default::default()
+                }
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn mcopy_gadget_simple() {
+        test_ok(0x40, 0x00, 0x20);
+        test_ok(0x00, 0x20, 0x20);
+    }
+
+    #[test]
+    fn mcopy_gadget_overlapping_forward() {
+        // The destination starts inside the source
+        test_ok(0x10, 0x00, 0x30);
+        test_ok(0x01, 0x00, 0x3f);
+    }
+
+    #[test]
+    fn mcopy_gadget_overlapping_backward() {
+        // The source starts inside the destination
+        test_ok(0x00, 0x10, 0x30);
+        test_ok(0x00, 0x01, 0x3f);
+    }
+
+    #[test]
+    fn mcopy_gadget_zero_length() {
+        test_ok(0x40, 0x00, 0x00);
+        // The offsets are ignored when nothing is copied
+        test_ok(0xffffff, 0xffffff, 0x00);
+    }
+
+    #[test]
+    fn mcopy_gadget_memory_expansion() {
+        // Expansion from the destination and from the source
+        test_ok(0x60, 0x00, 0x40);
+        test_ok(0x00, 0x50, 0x50);
+        test_ok(0x101, 0x102, 0x17);
+    }
+
+    #[test]
+    fn mcopy_gadget_out_of_gas() {
+        // The memory expansion costs more than the gas limit of the tx
+        test_ok(0xffffff, 0x00, 0x20);
+        test_ok(0x00, 0xffffff, 0x20);
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
         util::{
             common_gadget::SameContextGadget,
             constraint_builder::{ConstraintBuilder, StepStateTransition, Transition::Delta},
-            sum, CachedRegion, Cell, Word,
+            math_gadget::IsZeroGadget,
+            not, select, sum, CachedRegion, Cell, Word,
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
@@ -18,6 +19,7 @@ use halo2_proofs::{circuit::Value, plonk::Error};
 #[derive(Clone, Debug)]
 pub(crate) struct PushGadget<F> {
     same_context: SameContextGadget<F>,
+    is_push0: IsZeroGadget<F>,
     value: Word<F>,
     selectors: [Cell<F>; 31],
 }
@@ -29,6 +31,8 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
 
     fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
         let opcode = cb.query_cell();
+        // PUSH0 (EIP-3855) pushes 0 without reading any byte of the code.
+        let is_push0 = IsZeroGadget::construct(cb, opcode.expr() - OpcodeId::PUSH0.expr());
 
         let value = cb.query_word_rlc();
         // Query selectors for each opcode_lookup
@@ -52,7 +56,9 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
             let index = cb.curr.state.program_counter.expr() + opcode.expr()
                 - (OpcodeId::PUSH1.as_u8() - 1 + idx as u8).expr();
             if idx == 0 {
-                cb.opcode_lookup_at(index, byte.expr(), 0.expr())
+                cb.condition(not::expr(is_push0.expr()), |cb| {
+                    cb.opcode_lookup_at(index, byte.expr(), 0.expr())
+                });
             } else {
                 cb.condition(selectors[idx - 1].expr(), |cb| {
                     cb.opcode_lookup_at(index, byte.expr(), 0.expr())
@@ -80,9 +86,18 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
             );
         }
 
+        // PUSH0 doesn't push any byte, so the selectors are all 0 and the
+        // value is 0.
+        cb.require_zero(
+            "Constrain byte0 == 0 for PUSH0",
+            value.cells[0].expr() * is_push0.expr(),
+        );
+
         // Deduce the number of additional bytes to push than PUSH1. Note that
-        // num_additional_pushed = n - 1 where n is the suffix number of PUSH*.
-        let num_additional_pushed = opcode.expr() - OpcodeId::PUSH1.as_u64().expr();
+        // num_additional_pushed = n - 1 where n is the suffix number of PUSH*,
+        // and it's 0 for PUSH0.
+        let num_additional_pushed =
+            not::expr(is_push0.expr()) * (opcode.expr() - OpcodeId::PUSH1.as_u64().expr());
         // Sum of selectors needs to be exactly the number of additional bytes
         // that needs to be pushed.
         cb.require_equal(
@@ -100,13 +115,18 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
             rw_counter: Delta(1.expr()),
             program_counter: Delta(opcode.expr() - (OpcodeId::PUSH1.as_u64() - 2).expr()),
             stack_pointer: Delta((-1).expr()),
-            gas_left: Delta(-OpcodeId::PUSH1.constant_gas_cost().expr()),
+            gas_left: Delta(-select::expr(
+                is_push0.expr(),
+                OpcodeId::PUSH0.constant_gas_cost().expr(),
+                OpcodeId::PUSH1.constant_gas_cost().expr(),
+            )),
             ..Default::default()
         };
         let same_context = SameContextGadget::construct(cb, opcode, step_state_transition);
 
         Self {
             same_context,
+            is_push0,
             value,
             selectors,
         }
@@ -124,17 +144,22 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
         self.same_context.assign_exec_step(region, offset, step)?;
 
         let opcode = step.opcode.unwrap();
+        self.is_push0.assign(
+            region,
+            offset,
+            F::from(opcode.as_u64()) - F::from(OpcodeId::PUSH0.as_u64()),
+        )?;
 
         let value = block.rws[step.rw_indices[0]].stack_value();
         self.value
             .assign(region, offset, Some(value.to_le_bytes()))?;
 
-        let num_additional_pushed = opcode.postfix().expect("opcode with postfix") - 1;
+        let num_additional_pushed = opcode.data_len().saturating_sub(1);
         for (idx, selector) in self.selectors.iter().enumerate() {
             selector.assign(
                 region,
                 offset,
-                Value::known(F::from((idx < num_additional_pushed as usize) as u64)),
+                Value::known(F::from((idx < num_additional_pushed) as u64)),
             )?;
         }
 
@@ -144,10 +169,17 @@ impl<F: Field> ExecutionGadget<F> for PushGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::{evm_circuit::test::rand_bytes, test_util::run_test_circuits};
+    use crate::{
+        evm_circuit::test::rand_bytes,
+        test_util::{run_test_circuits, run_test_circuits_with_params},
+    };
+    use bus_mapping::circuit_input_builder::CircuitsParams;
     use eth_types::bytecode;
-    use eth_types::evm_types::OpcodeId;
-    use mock::TestContext;
+    use eth_types::evm_types::{Hardfork, OpcodeId};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
 
     fn test_ok(opcode: OpcodeId, bytes: &[u8]) {
         assert!(bytes.len() == opcode.data_len());
@@ -189,6 +221,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn push_gadget_push0() {
+        let bytecode = bytecode! {
+            PUSH0
+            PUSH1(1)
+            ADD
+            PUSH0
+            STOP
+        };
+
+        assert_eq!(
+            run_test_circuits_with_params(
+                TestContext::<2, 1>::new_with_hardfork(
+                    None,
+                    account_0_code_account_1_no_code(bytecode),
+                    tx_from_1_to_0,
+                    |block, _txs| block,
+                    Hardfork::Shanghai,
+                )
+                .unwrap(),
+                None,
+                CircuitsParams {
+                    hardfork: Hardfork::Shanghai,
+                    ..Default::default()
+                },
+            ),
+            Ok(())
+        );
+    }
+
     #[test]
     #[ignore]
     fn push_gadget_rand() {
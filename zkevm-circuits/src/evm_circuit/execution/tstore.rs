@@ -0,0 +1,247 @@
+use crate::{
+    evm_circuit::{
+        execution::ExecutionGadget,
+        step::ExecutionState,
+        util::{
+            common_gadget::SameContextGadget,
+            constraint_builder::{
+                ConstraintBuilder, ReversionInfo, StepStateTransition, Transition::Delta,
+            },
+            CachedRegion, Cell,
+        },
+        witness::{Block, Call, ExecStep, Transaction},
+    },
+    table::CallContextFieldTag,
+    util::Expr,
+};
+use bus_mapping::evm::OpcodeId;
+use eth_types::{Field, ToScalar};
+use halo2_proofs::{circuit::Value, plonk::Error};
+
+#[derive(Clone, Debug)]
+pub(crate) struct TstoreGadget<F> {
+    same_context: SameContextGadget<F>,
+    tx_id: Cell<F>,
+    is_static: Cell<F>,
+    reversion_info: ReversionInfo<F>,
+    callee_address: Cell<F>,
+    phase2_key: Cell<F>,
+    phase2_value: Cell<F>,
+    phase2_value_prev: Cell<F>,
+}
+
+impl<F: Field> ExecutionGadget<F> for TstoreGadget<F> {
+    const NAME: &'static str = "TSTORE";
+
+    const EXECUTION_STATE: ExecutionState = ExecutionState::TSTORE;
+
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        let opcode = cb.query_cell();
+
+        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
+
+        // constrain not in static call
+        let is_static = cb.call_context(None, CallContextFieldTag::IsStatic);
+        cb.require_zero("is_static is false", is_static.expr());
+
+        let mut reversion_info = cb.reversion_info_read(None);
+        let callee_address = cb.call_context(None, CallContextFieldTag::CalleeAddress);
+
+        let phase2_key = cb.query_cell_phase2();
+        // Pop the key from the stack
+        cb.stack_pop(phase2_key.expr());
+
+        let phase2_value = cb.query_cell_phase2();
+        // Pop the value from the stack
+        cb.stack_pop(phase2_value.expr());
+
+        let phase2_value_prev = cb.query_cell_phase2();
+        cb.transient_storage_write(
+            tx_id.expr(),
+            callee_address.expr(),
+            phase2_key.expr(),
+            phase2_value.expr(),
+            phase2_value_prev.expr(),
+            Some(&mut reversion_info),
+        );
+
+        // Transient storage is always warm and has no refunds, so TSTORE has a
+        // constant gas cost.
+        let step_state_transition = StepStateTransition {
+            rw_counter: Delta(8.expr()),
+            program_counter: Delta(1.expr()),
+            stack_pointer: Delta(2.expr()),
+            reversible_write_counter: Delta(1.expr()),
+            gas_left: Delta(-OpcodeId::TSTORE.constant_gas_cost().expr()),
+            ..Default::default()
+        };
+        let same_context = SameContextGadget::construct(cb, opcode, step_state_transition);
+
+        Self {
+            same_context,
+            tx_id,
+            is_static,
+            reversion_info,
+            callee_address,
+            phase2_key,
+            phase2_value,
+            phase2_value_prev,
+        }
+    }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        tx: &Transaction,
+        call: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        self.same_context.assign_exec_step(region, offset, step)?;
+
+        self.tx_id
+            .assign(region, offset, Value::known(F::from(tx.id as u64)))?;
+        self.is_static
+            .assign(region, offset, Value::known(F::from(call.is_static as u64)))?;
+        self.reversion_info.assign(
+            region,
+            offset,
+            call.rw_counter_end_of_reversion,
+            call.is_persistent,
+        )?;
+        self.callee_address.assign(
+            region,
+            offset,
+            Value::known(
+                call.callee_address
+                    .to_scalar()
+                    .expect("unexpected Address -> Scalar conversion failure"),
+            ),
+        )?;
+
+        let [key, value] =
+            [step.rw_indices[5], step.rw_indices[6]].map(|idx| block.rws[idx].stack_value());
+        self.phase2_key
+            .assign(region, offset, region.word_rlc(key))?;
+        self.phase2_value
+            .assign(region, offset, region.word_rlc(value))?;
+
+        let (_, value_prev) = block.rws[step.rw_indices[7]].transient_storage_value_pair();
+        self.phase2_value_prev
+            .assign(region, offset, region.word_rlc(value_prev))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_util::run_test_circuits_with_params;
+    use bus_mapping::{circuit_input_builder::CircuitsParams, evm::OpcodeId};
+    use eth_types::{bytecode, evm_types::Hardfork, Bytecode, ToWord, Word};
+    use mock::{TestContext, MOCK_ACCOUNTS};
+
+    /// Run a tx to a contract holding `code`, which may call the contract
+    /// holding `callee_code`.
+    fn test_ok(code: Bytecode, callee_code: Bytecode) {
+        let ctx = TestContext::<3, 1>::new_with_hardfork(
+            None,
+            |accs| {
+                accs[0]
+                    .address(MOCK_ACCOUNTS[0])
+                    .balance(Word::from(10u64.pow(19)))
+                    .code(code);
+                accs[1]
+                    .address(MOCK_ACCOUNTS[1])
+                    .balance(Word::from(10u64.pow(19)))
+                    .code(callee_code);
+                accs[2]
+                    .address(MOCK_ACCOUNTS[2])
+                    .balance(Word::from(10u64.pow(19)));
+            },
+            |mut txs, accs| {
+                txs[0].from(accs[2].address).to(accs[0].address);
+            },
+            |block, _txs| block,
+            Hardfork::Cancun,
+        )
+        .unwrap();
+        assert_eq!(
+            run_test_circuits_with_params(
+                ctx,
+                None,
+                CircuitsParams {
+                    hardfork: Hardfork::Cancun,
+                    ..Default::default()
+                }
+            ),
+            Ok(())
+        );
+    }
+
+    /// Returns code calling `MOCK_ACCOUNTS[1]` with `call_op`.
+    fn caller(call_op: OpcodeId) -> Bytecode {
+        let mut code = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+        };
+        if call_op == OpcodeId::CALL {
+            code.push(1, Word::zero()); // value
+        }
+        code.push(20, MOCK_ACCOUNTS[1].to_word())
+            .push(32, Word::from(50_000u64))
+            .write_op(call_op)
+            .write_op(OpcodeId::STOP);
+        code
+    }
+
+    #[test]
+    fn tstore_gadget_simple() {
+        // The second TSTORE overwrites the first one, and the root call is
+        // either persistent (STOP) or reverted (REVERT).
+        let tstores = bytecode! {
+            PUSH1(0x6f)
+            PUSH1(0x00)
+            TSTORE
+            PUSH1(0x70)
+            PUSH1(0x00)
+            TSTORE
+        };
+        for halt in [
+            bytecode! { STOP },
+            bytecode! { PUSH1(0x00) PUSH1(0x00) REVERT },
+        ] {
+            let mut code = tstores.clone();
+            code.append(&halt);
+            test_ok(code, Bytecode::default());
+        }
+    }
+
+    #[test]
+    fn tstore_gadget_reverted_by_failing_call() {
+        let callee_code = bytecode! {
+            PUSH1(0x6f)
+            PUSH1(0x00)
+            TSTORE
+            PUSH1(0x00)
+            PUSH1(0x00)
+            REVERT
+        };
+        test_ok(caller(OpcodeId::CALL), callee_code);
+    }
+
+    #[test]
+    fn tstore_gadget_write_protection() {
+        // TSTORE fails with ErrorWriteProtection in a static call
+        let callee_code = bytecode! {
+            PUSH1(0x6f)
+            PUSH1(0x00)
+            TSTORE
+            STOP
+        };
+        test_ok(caller(OpcodeId::STATICCALL), callee_code);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    evm_circuit::{
+        execution::ExecutionGadget,
+        step::ExecutionState,
+        util::{
+            common_gadget::UpdateBalanceGadget,
+            constraint_builder::{
+                ConstraintBuilder, StepStateTransition,
+                Transition::{Delta, Same},
+            },
+            from_bytes, sum, CachedRegion, Cell, Word,
+        },
+        witness::{Block, Call, ExecStep, Transaction},
+    },
+    table::BlockContextFieldTag,
+    util::Expr,
+};
+use eth_types::{Field, ToLittleEndian, ToScalar};
+use halo2_proofs::{circuit::Value, plonk::Error};
+
+/// Number of Wei in a Gwei, the unit of the withdrawal amounts.
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+#[derive(Clone, Debug)]
+pub(crate) struct WithdrawalGadget<F> {
+    rw_counter_start: Cell<F>,
+    block_number: Cell<F>,
+    validator_index: Cell<F>,
+    address: Cell<F>,
+    amount: Cell<F>,
+    amount_wei: Word<F>,
+    recipient_balance: UpdateBalanceGadget<F, 2, true>,
+}
+
+impl<F: Field> ExecutionGadget<F> for WithdrawalGadget<F> {
+    const NAME: &'static str = "Withdrawal";
+
+    const EXECUTION_STATE: ExecutionState = ExecutionState::Withdrawal;
+
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        // The program counter holds the id of the withdrawal, which starts at
+        // 1 and increases by 1 for each withdrawal step.
+        let id = cb.curr.state.program_counter.expr();
+        // The withdrawals are done in the last chunk of the block, and are only
+        // first in a block without txs.
+        let rw_counter_start = cb.query_cell();
+        cb.block_lookup(
+            BlockContextFieldTag::RwCounterStart.expr(),
+            None,
+            rw_counter_start.expr(),
+        );
+        let rw_counter = cb.curr.state.rw_counter.expr();
+        cb.step_first(|cb| {
+            cb.require_equal("withdrawal id is initialized to be 1", id.clone(), 1.expr());
+            cb.require_equal(
+                "rw_counter is initialized to be the start of the chunk",
+                rw_counter,
+                rw_counter_start.expr(),
+            );
+        });
+
+        // The withdrawals of every block of the batch are done after the last
+        // tx, so the number of the block only tells them apart in the table.
+        let [block_number, validator_index, address, amount] = [(); 4].map(|_| cb.query_cell());
+        cb.withdrawal_lookup(
+            id,
+            block_number.expr(),
+            validator_index.expr(),
+            address.expr(),
+            amount.expr(),
+        );
+
+        // The amount of the withdrawal is in Gwei while balances are in Wei.
+        // amount * 10^9 < 2^64 * 2^30 fits in the 16 lower bytes of the word.
+        let amount_wei = cb.query_word_rlc();
+        cb.require_equal(
+            "amount_wei == amount * 10^9",
+            from_bytes::expr(&amount_wei.cells[..16]),
+            amount.expr() * WEI_PER_GWEI.expr(),
+        );
+        cb.require_zero(
+            "amount_wei fits in 16 bytes",
+            sum::expr(&amount_wei.cells[16..]),
+        );
+
+        // Credit the amount to the recipient, which can't be reverted
+        let recipient_balance =
+            UpdateBalanceGadget::construct(cb, address.expr(), vec![amount_wei.clone()], None);
+
+        // Withdrawal is followed by another Withdrawal or by EndBlock, which
+        // takes the id of the next withdrawal.  The call_id is propagated so
+        // that EndBlock can get the last tx_id.
+        cb.require_step_state_transition(StepStateTransition {
+            rw_counter: Delta(cb.rw_counter_offset()),
+            call_id: Same,
+            program_counter: Delta(1.expr()),
+            ..StepStateTransition::any()
+        });
+
+        Self {
+            rw_counter_start,
+            block_number,
+            validator_index,
+            address,
+            amount,
+            amount_wei,
+            recipient_balance,
+        }
+    }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        _: &Transaction,
+        _: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        let index = step.program_counter as usize - 1;
+        let withdrawal = &block.withdrawals[index];
+
+        self.rw_counter_start.assign(
+            region,
+            offset,
+            Value::known(F::from(block.context.chunk.rw_counter_start as u64)),
+        )?;
+
+        self.block_number.assign(
+            region,
+            offset,
+            Value::known(F::from(block.withdrawal_blocks[index])),
+        )?;
+        self.validator_index.assign(
+            region,
+            offset,
+            Value::known(F::from(withdrawal.validator_index.as_u64())),
+        )?;
+        self.address.assign(
+            region,
+            offset,
+            Value::known(
+                withdrawal
+                    .address
+                    .to_scalar()
+                    .expect("unexpected Address -> Scalar conversion failure"),
+            ),
+        )?;
+        self.amount.assign(
+            region,
+            offset,
+            Value::known(F::from(withdrawal.amount.as_u64())),
+        )?;
+        let amount_wei = withdrawal.amount_wei();
+        self.amount_wei
+            .assign(region, offset, Some(amount_wei.to_le_bytes()))?;
+
+        let (balance, balance_prev) = block.rws[step.rw_indices[0]].account_value_pair();
+        self.recipient_balance
+            .assign(region, offset, balance_prev, vec![amount_wei], balance)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evm_circuit::{test::run_test_circuit, witness::block_convert};
+    use bus_mapping::{circuit_input_builder::CircuitsParams, mock::BlockData};
+    use eth_types::{
+        address, bytecode,
+        geth_types::{GethData, Withdrawal},
+    };
+    use halo2_proofs::{dev::VerifyFailure, halo2curves::bn256::Fr};
+    use mock::{test_ctx::helpers::account_0_code_account_1_no_code, TestContext};
+
+    fn withdrawals() -> Vec<Withdrawal> {
+        vec![
+            Withdrawal {
+                index: 0.into(),
+                validator_index: 7.into(),
+                address: address!("0x00000000000000000000000000000000000000aa"),
+                amount: 32_000_000_000u64.into(),
+            },
+            Withdrawal {
+                index: 1.into(),
+                validator_index: 8.into(),
+                address: address!("0x00000000000000000000000000000000000000bb"),
+                amount: 1.into(),
+            },
+            // The same recipient can be credited more than once
+            Withdrawal {
+                index: 2.into(),
+                validator_index: 9.into(),
+                address: address!("0x00000000000000000000000000000000000000aa"),
+                amount: 5.into(),
+            },
+        ]
+    }
+
+    fn test_withdrawals<const NTX: usize>(
+        withdrawals: Vec<Withdrawal>,
+        tamper: impl FnOnce(&mut crate::witness::Block<Fr>),
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let block: GethData = TestContext::<2, NTX>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode! { STOP }),
+            |txs, accs| {
+                for tx in txs {
+                    tx.from(accs[1].address).to(accs[0].address);
+                }
+            },
+            |block, _txs| block.withdrawals(withdrawals),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data_with_params(
+            block.clone(),
+            CircuitsParams {
+                max_txs: NTX.max(1),
+                ..Default::default()
+            },
+        )
+        .new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let mut block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        tamper(&mut block);
+        run_test_circuit(block)
+    }
+
+    #[test]
+    fn withdrawal_after_txs() {
+        assert_eq!(test_withdrawals::<2>(withdrawals(), |_| {}), Ok(()));
+    }
+
+    #[test]
+    fn withdrawal_empty_block() {
+        assert_eq!(test_withdrawals::<0>(withdrawals(), |_| {}), Ok(()));
+    }
+
+    #[test]
+    fn withdrawal_none() {
+        assert_eq!(test_withdrawals::<1>(vec![], |_| {}), Ok(()));
+    }
+
+    #[test]
+    fn withdrawal_invalid_amount() {
+        // The credited amount doesn't match the one in the withdrawal table
+        assert!(test_withdrawals::<1>(withdrawals(), |block| {
+            block.withdrawals[1].amount = 2.into()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn withdrawal_invalid_order() {
+        // The withdrawals are applied in a different order than in the table
+        assert!(test_withdrawals::<1>(withdrawals(), |block| {
+            block.withdrawals.swap(0, 1);
+            block.withdrawal_steps.swap(0, 1);
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn withdrawal_missing() {
+        // The withdrawal table has a withdrawal that is not done
+        assert!(test_withdrawals::<1>(withdrawals(), |block| {
+            block.withdrawals.push(Withdrawal {
+                index: 3.into(),
+                validator_index: 10.into(),
+                address: address!("0x00000000000000000000000000000000000000cc"),
+                amount: 7.into(),
+            });
+            block.withdrawal_blocks.push(block.withdrawal_blocks[0]);
+        })
+        .is_err());
+    }
+}
@@ -33,6 +33,7 @@ pub(crate) const LOOKUP_CONFIG: &[(Table, usize)] = &[
     (Table::Copy, 1),
     (Table::Keccak, 1),
     (Table::Exp, 1),
+    (Table::Withdrawal, 1),
 ];
 
 /// Maximum number of bytes that an integer can fit in field without wrapping
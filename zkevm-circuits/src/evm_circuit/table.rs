@@ -2,7 +2,7 @@ use crate::evm_circuit::step::ExecutionState;
 use crate::impl_expr;
 pub use crate::table::TxContextFieldTag;
 use bus_mapping::evm::OpcodeId;
-use eth_types::Field;
+use eth_types::{evm_types::Hardfork, Field};
 use gadgets::util::Expr;
 use halo2_proofs::plonk::Expression;
 use strum::IntoEnumIterator;
@@ -26,11 +26,19 @@ pub enum FixedTableTag {
     Pow2,
     ConstantGasCost,
     OpcodeStack,
+    EipActivation,
 }
 impl_expr!(FixedTableTag);
 
+/// EIPs whose activation is read by the gadgets from the fixed table, so that
+/// the same constraints can prove blocks of different hardforks.
+pub(crate) const CONSTRAINED_EIPS: [u64; 2] = [3651, 3860];
+
 impl FixedTableTag {
-    pub fn build<F: Field>(&self) -> Box<dyn Iterator<Item = [F; 4]>> {
+    /// Returns the rows of the table for `self` under the rules of
+    /// `hardfork`.  The opcode related tables only contain the opcodes that
+    /// are available in `hardfork`.
+    pub fn build<F: Field>(&self, hardfork: Hardfork) -> Box<dyn Iterator<Item = [F; 4]>> {
         let tag = F::from(*self as u64);
         match self {
             Self::Zero => Box::new((0..1).map(move |_| [tag, F::zero(), F::zero(), F::zero()])),
@@ -77,6 +85,7 @@ impl FixedTableTag {
                     execution_state
                         .responsible_opcodes()
                         .into_iter()
+                        .filter(move |opcode| opcode.is_available(hardfork))
                         .map(move |opcode| {
                             [
                                 tag,
@@ -97,6 +106,7 @@ impl FixedTableTag {
             })),
             Self::ConstantGasCost => Box::new(
                 OpcodeId::iter()
+                    .filter(move |opcode| opcode.is_available(hardfork))
                     .filter(move |opcode| opcode.constant_gas_cost().0 > 0)
                     .map(move |opcode| {
                         [
@@ -109,6 +119,7 @@ impl FixedTableTag {
             ),
             Self::OpcodeStack => Box::new(
                 OpcodeId::iter()
+                    .filter(move |opcode| opcode.is_available(hardfork))
                     .filter(move |opcode| opcode.constant_gas_cost().0 > 0)
                     .map(move |opcode| {
                         [
@@ -119,6 +130,14 @@ impl FixedTableTag {
                         ]
                     }),
             ),
+            Self::EipActivation => Box::new(CONSTRAINED_EIPS.into_iter().map(move |eip| {
+                [
+                    tag,
+                    F::from(eip),
+                    F::from(hardfork.is_eip_enabled(eip) as u64),
+                    F::zero(),
+                ]
+            })),
         }
     }
 }
@@ -133,6 +152,7 @@ pub(crate) enum Table {
     Copy,
     Keccak,
     Exp,
+    Withdrawal,
 }
 
 #[derive(Clone, Debug)]
@@ -282,6 +302,20 @@ pub(crate) enum Lookup<F> {
         exponent_lo_hi: [Expression<F>; 2],
         exponentiation_lo_hi: [Expression<F>; 2],
     },
+    /// Lookup to withdrawal table, which contains the withdrawals of the
+    /// blocks of this batch.
+    Withdrawal {
+        /// Id of the withdrawal, starting at 1.
+        id: Expression<F>,
+        /// Number of the block of the withdrawal.
+        block_number: Expression<F>,
+        /// Index of the validator.
+        validator_index: Expression<F>,
+        /// Recipient of the withdrawal.
+        address: Expression<F>,
+        /// Amount of the withdrawal, in Gwei.
+        amount: Expression<F>,
+    },
     /// Conditional lookup enabled by the first element.
     Conditional(Expression<F>, Box<Lookup<F>>),
 }
@@ -301,6 +335,7 @@ impl<F: Field> Lookup<F> {
             Self::CopyTable { .. } => Table::Copy,
             Self::KeccakTable { .. } => Table::Keccak,
             Self::ExpTable { .. } => Table::Exp,
+            Self::Withdrawal { .. } => Table::Withdrawal,
             Self::Conditional(_, lookup) => lookup.table(),
         }
     }
@@ -412,6 +447,19 @@ impl<F: Field> Lookup<F> {
                 exponentiation_lo_hi[0].clone(),
                 exponentiation_lo_hi[1].clone(),
             ],
+            Self::Withdrawal {
+                id,
+                block_number,
+                validator_index,
+                address,
+                amount,
+            } => vec![
+                id.clone(),
+                block_number.clone(),
+                validator_index.clone(),
+                address.clone(),
+                amount.clone(),
+            ],
             Self::Conditional(condition, lookup) => lookup
                 .input_exprs()
                 .into_iter()
@@ -359,6 +359,10 @@ impl<F: Field> TransferWithGasFeeGadget<F> {
         Self { sender, receiver }
     }
 
+    pub(crate) fn sender(&self) -> &UpdateBalanceGadget<F, 3, false> {
+        &self.sender
+    }
+
     pub(crate) fn assign(
         &self,
         region: &mut CachedRegion<'_, '_, F>,
@@ -2,7 +2,7 @@ use crate::{
     evm_circuit::{
         param::STACK_CAPACITY,
         step::{ExecutionState, Step},
-        table::{FixedTableTag, Lookup, RwValues},
+        table::{FixedTableTag, Lookup, RwValues, CONSTRAINED_EIPS},
         util::{Cell, RandomLinearCombination, Word},
     },
     table::{
@@ -558,6 +558,21 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
         );
     }
 
+    /// Returns a boolean cell which is 1 if `eip` is enabled in the hardfork
+    /// the fixed table is built for.  `eip` must be in [`CONSTRAINED_EIPS`].
+    pub(crate) fn is_eip_enabled(&mut self, eip: u64) -> Cell<F> {
+        debug_assert!(CONSTRAINED_EIPS.contains(&eip), "unconstrained EIP-{}", eip);
+        let is_enabled = self.query_bool();
+        self.add_lookup(
+            "EIP activation",
+            Lookup::Fixed {
+                tag: FixedTableTag::EipActivation.expr(),
+                values: [eip.expr(), is_enabled.expr(), 0.expr()],
+            },
+        );
+        is_enabled
+    }
+
     // Opcode
 
     pub(crate) fn opcode_lookup(&mut self, opcode: Expression<F>, is_code: Expression<F>) {
@@ -682,6 +697,28 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
         );
     }
 
+    // Withdrawal
+
+    pub(crate) fn withdrawal_lookup(
+        &mut self,
+        id: Expression<F>,
+        block_number: Expression<F>,
+        validator_index: Expression<F>,
+        address: Expression<F>,
+        amount: Expression<F>,
+    ) {
+        self.add_lookup(
+            "Withdrawal lookup",
+            Lookup::Withdrawal {
+                id,
+                block_number,
+                validator_index,
+                address,
+                amount,
+            },
+        );
+    }
+
     // Rw
 
     /// Add a Lookup::Rw without increasing the rw_counter_offset, which is
@@ -857,6 +894,58 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
         );
     }
 
+    // Transient Storage
+
+    pub(crate) fn transient_storage_read(
+        &mut self,
+        tx_id: Expression<F>,
+        account_address: Expression<F>,
+        key: Expression<F>,
+        value: Expression<F>,
+    ) {
+        self.rw_lookup(
+            "TransientStorage read",
+            false.expr(),
+            RwTableTag::TransientStorage,
+            RwValues::new(
+                tx_id,
+                account_address,
+                0.expr(),
+                key,
+                value.clone(),
+                value,
+                0.expr(),
+                0.expr(),
+            ),
+        );
+    }
+
+    pub(crate) fn transient_storage_write(
+        &mut self,
+        tx_id: Expression<F>,
+        account_address: Expression<F>,
+        key: Expression<F>,
+        value: Expression<F>,
+        value_prev: Expression<F>,
+        reversion_info: Option<&mut ReversionInfo<F>>,
+    ) {
+        self.reversible_write(
+            "TransientStorage write",
+            RwTableTag::TransientStorage,
+            RwValues::new(
+                tx_id,
+                account_address,
+                0.expr(),
+                key,
+                value,
+                value_prev,
+                0.expr(),
+                0.expr(),
+            ),
+            reversion_info,
+        );
+    }
+
     // Tx Refund
 
     pub(crate) fn tx_refund_read(&mut self, tx_id: Expression<F>, value: Expression<F>) {
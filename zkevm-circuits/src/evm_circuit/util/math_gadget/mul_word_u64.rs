@@ -12,12 +12,14 @@ use halo2_proofs::{
 };
 
 /// Construction of 256-bit product by 256-bit multiplicand * 64-bit multiplier,
-/// which disallows overflow.
+/// where the product is taken modulo 2^256 and the bits above it are exposed
+/// by `overflow`, which callers that disallow overflow require to be zero.
 #[derive(Clone, Debug)]
 pub(crate) struct MulWordByU64Gadget<F> {
     multiplicand: util::Word<F>,
     product: util::Word<F>,
     carry_lo: [util::Cell<F>; 8],
+    carry_hi: [util::Cell<F>; 8],
 }
 
 impl<F: Field> MulWordByU64Gadget<F> {
@@ -30,6 +32,7 @@ impl<F: Field> MulWordByU64Gadget<F> {
             multiplicand,
             product: cb.query_word_rlc(),
             carry_lo: cb.query_bytes(),
+            carry_hi: cb.query_bytes(),
         };
 
         let multiplicand_lo = from_bytes::expr(&gadget.multiplicand.cells[..16]);
@@ -39,6 +42,7 @@ impl<F: Field> MulWordByU64Gadget<F> {
         let product_hi = from_bytes::expr(&gadget.product.cells[16..]);
 
         let carry_lo = from_bytes::expr(&gadget.carry_lo[..8]);
+        let carry_hi = from_bytes::expr(&gadget.carry_hi[..8]);
 
         cb.require_equal(
             "multiplicand_lo ⋅ multiplier == carry_lo ⋅ 2^128 + product_lo",
@@ -47,9 +51,9 @@ impl<F: Field> MulWordByU64Gadget<F> {
         );
 
         cb.require_equal(
-            "multiplicand_hi ⋅ multiplier + carry_lo == product_hi",
+            "multiplicand_hi ⋅ multiplier + carry_lo == carry_hi ⋅ 2^128 + product_hi",
             multiplicand_hi * multiplier.expr() + carry_lo,
-            product_hi,
+            carry_hi * pow_of_two_expr(128) + product_hi,
         );
 
         gadget
@@ -68,17 +72,20 @@ impl<F: Field> MulWordByU64Gadget<F> {
         self.product
             .assign(region, offset, Some(product.to_le_bytes()))?;
 
-        let (multiplicand_lo, _) = split_u256(&multiplicand);
-        let (product_lo, _) = split_u256(&product);
+        let (multiplicand_lo, multiplicand_hi) = split_u256(&multiplicand);
+        let (product_lo, product_hi) = split_u256(&product);
 
         let carry_lo = (multiplicand_lo * multiplier - product_lo) >> 128;
-        for (cell, byte) in self.carry_lo.iter().zip(
-            u64::try_from(carry_lo)
-                .map_err(|_| Error::Synthesis)?
-                .to_le_bytes()
-                .iter(),
-        ) {
-            cell.assign(region, offset, Value::known(F::from(*byte as u64)))?;
+        let carry_hi = (multiplicand_hi * multiplier + carry_lo - product_hi) >> 128;
+        for (cells, carry) in [(&self.carry_lo, carry_lo), (&self.carry_hi, carry_hi)] {
+            for (cell, byte) in cells.iter().zip(
+                u64::try_from(carry)
+                    .map_err(|_| Error::Synthesis)?
+                    .to_le_bytes()
+                    .iter(),
+            ) {
+                cell.assign(region, offset, Value::known(F::from(*byte as u64)))?;
+            }
         }
 
         Ok(())
@@ -87,6 +94,12 @@ impl<F: Field> MulWordByU64Gadget<F> {
     pub(crate) fn product(&self) -> &util::Word<F> {
         &self.product
     }
+
+    /// The product bits above 2^256, divided by 2^256, which is zero when the
+    /// multiplication doesn't overflow.
+    pub(crate) fn overflow(&self) -> Expression<F> {
+        from_bytes::expr(&self.carry_hi)
+    }
 }
 
 #[cfg(test)]
@@ -99,12 +112,14 @@ mod tests {
     use halo2_proofs::plonk::Error;
 
     #[derive(Clone)]
-    /// MulWordByU64TestContainer: require(product = a*(b as u64))
+    /// MulWordByU64TestContainer: require(product = a*(b as u64) mod 2^256,
+    /// overflow = a*(b as u64) >> 256)
     struct MulWordByU64TestContainer<F> {
         mulwords_u64_gadget: MulWordByU64Gadget<F>,
         a: util::Word<F>,
         b: Cell<F>,
         product: util::Word<F>,
+        overflow: Cell<F>,
     }
 
     impl<F: Field> MathGadgetContainer<F> for MulWordByU64TestContainer<F> {
@@ -112,12 +127,24 @@ mod tests {
             let a = cb.query_word_rlc();
             let b = cb.query_cell();
             let product = cb.query_word_rlc();
+            let overflow = cb.query_cell();
             let mulwords_u64_gadget = MulWordByU64Gadget::<F>::construct(cb, a.clone(), b.expr());
+            cb.require_equal(
+                "product is correct",
+                mulwords_u64_gadget.product().expr(),
+                product.expr(),
+            );
+            cb.require_equal(
+                "overflow is correct",
+                mulwords_u64_gadget.overflow(),
+                overflow.expr(),
+            );
             MulWordByU64TestContainer {
                 mulwords_u64_gadget,
                 a,
                 b,
                 product,
+                overflow,
             }
         }
 
@@ -129,12 +156,16 @@ mod tests {
             let a = witnesses[0];
             let b = u64::from_le_bytes(witnesses[1].to_le_bytes()[..8].try_into().unwrap());
             let product = witnesses[2];
+            // The overflow is expected to be zero unless it's given.
+            let overflow = witnesses.get(3).map_or(0, |overflow| overflow.as_u64());
             let offset = 0;
 
             self.a.assign(region, offset, Some(a.to_le_bytes()))?;
             self.b.assign(region, offset, Value::known(F::from(b)))?;
             self.product
                 .assign(region, offset, Some(product.to_le_bytes()))?;
+            self.overflow
+                .assign(region, offset, Value::known(F::from(overflow)))?;
             self.mulwords_u64_gadget.assign(region, 0, a, b, product)?;
 
             Ok(())
@@ -185,6 +216,28 @@ mod tests {
             vec![WORD_LOW_MAX, Word::from(2), WORD_LOW_MAX << 1],
             true,
         );
+        // high_max * 2 = high_max << 1 with an overflow of 1
+        try_test!(
+            MulWordByU64TestContainer<Fr>,
+            vec![
+                WORD_HIGH_MAX,
+                Word::from(2),
+                WORD_HIGH_MAX << 1,
+                Word::from(1)
+            ],
+            true,
+        );
+        // max * u64::MAX = 2^256 - u64::MAX with an overflow of u64::MAX - 1
+        try_test!(
+            MulWordByU64TestContainer<Fr>,
+            vec![
+                Word::MAX,
+                Word::from(u64::MAX),
+                Word::MAX - Word::from(u64::MAX) + 1,
+                Word::from(u64::MAX - 1)
+            ],
+            true,
+        );
     }
 
     #[test]
@@ -194,7 +247,7 @@ mod tests {
             vec![Word::MAX, Word::from(1), Word::from(1)],
             false,
         );
-        // high_max * 2 = overflow
+        // high_max * 2 overflows, but no overflow is expected
         try_test!(
             MulWordByU64TestContainer<Fr>,
             vec![WORD_HIGH_MAX, Word::from(2), WORD_HIGH_MAX << 1],
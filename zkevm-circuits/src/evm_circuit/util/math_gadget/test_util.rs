@@ -16,7 +16,7 @@ use crate::{
     },
     util::Challenges,
 };
-use eth_types::{Field, Word, U256};
+use eth_types::{evm_types::Hardfork, Field, Word, U256};
 pub(crate) use halo2_proofs::circuit::{Layouter, Value};
 use halo2_proofs::plonk::{FirstPhase, SecondPhase, ThirdPhase};
 use halo2_proofs::{
@@ -228,7 +228,7 @@ impl<F: Field, G: MathGadgetContainer<F>> Circuit<F> for UnitTestMathGadgetBaseC
                                         | FixedTableTag::Range1024
                                 )
                             })
-                            .flat_map(|tag| tag.build()),
+                            .flat_map(|tag| tag.build(Hardfork::default())),
                     )
                     .enumerate()
                 {
@@ -21,7 +21,10 @@ pub mod copy_circuit;
 pub mod evm_circuit;
 pub mod exp_circuit;
 pub mod keccak_circuit;
+pub mod mpt_circuit;
 pub mod pi_circuit;
+pub mod receipt_circuit;
+pub mod rlp_circuit;
 pub mod state_circuit;
 pub mod super_circuit;
 pub mod table;
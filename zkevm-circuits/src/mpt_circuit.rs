@@ -0,0 +1,4599 @@
+//! The MPT circuit proves the updates of the state trie that are listed in
+//! the MptTable, which is used by the State circuit to verify the accesses to
+//! accounts and storage slots.
+//!
+//! Every [`MptUpdate`] is proved with two paths of RLP encoded trie nodes: one
+//! from the old root and one from the new root, down to the key
+//! (see [`MptUpdateProof`](crate::witness::MptUpdateProof)).  The circuit
+//! assigns one byte per row, and each update is laid out as:
+//!
+//! - The key rows: the 20 bytes of the address followed by the 64 nibbles of
+//!   its hash and, for storage updates, the 32 bytes of the storage key
+//!   followed by the 64 nibbles of its hash.  The hashes are looked up in the
+//!   keccak table, and the address and storage key are the ones of the MptTable
+//!   row.
+//! - For the old side and then the new side: 32 root rows with the bytes of the
+//!   root hash, followed by the bytes of the nodes in the path.  A side can end
+//!   with a drift node (see below).
+//!
+//! The circuit verifies that:
+//!
+//! - Every node is a canonically RLP encoded branch, extension or leaf, or the
+//!   empty node at the root of an empty trie.  The sequence of items of each
+//!   node is checked with a lookup to a fixed table of transitions, and the
+//!   lengths of the items and the nodes with the RLP headers.
+//! - The hash of every node, looked up in the keccak table, is referenced by
+//!   its parent: for a branch the child in the position of the next key nibble,
+//!   for an extension its child, and for an account leaf of a storage update
+//!   its storage root.  The first node is referenced by the root rows.
+//! - The path of every node follows the key: the positions of the branch
+//!   children and the nibbles of the extension and leaf paths are looked up in
+//!   the nibbles of the key hash.  A path ends either in the leaf of the key,
+//!   or in a node that proves that the key is not in the trie: an empty branch
+//!   child, or an extension or leaf whose path diverges from the key.
+//! - The value in the leaf of the key is the old or new value of the MptTable
+//!   row, and it's 0 when the key is not in the trie.  The account fields that
+//!   are not updated are not part of the value, and are checked to be the
+//!   defaults when the account is created.
+//! - Only the nodes in the path of the key change: the nodes at the same
+//!   position of both paths are paired, and the bytes of the items that are not
+//!   on the path of the key are looked up in the paired node of the other side.
+//!   A path can only grow or shrink by the nodes that an update inserts or
+//!   removes: a new branch (and extension) that splits a leaf or extension, or
+//!   a leaf in an empty branch child.  When a leaf or extension is moved below
+//!   a new branch, the node with its shortened path is a drift node, assigned
+//!   after the last node of the side that contains the branch. The drift node
+//!   is referenced by the branch, and its path and items are looked up in the
+//!   moved node of the other side.
+//! - The updates are chained: the old root of every update is the new root of
+//!   the previous one.
+//!
+//! The old root of the first update and the new root of the last update are
+//! exposed as public inputs, split in hi/lo 128 bit halves.  Their RLCs with
+//! the randomness of the PublicInputs circuit are also assigned, so that they
+//! can be linked to the state roots of the PublicInputs circuit when both
+//! circuits are part of the same proof.
+//!
+//! Embedded nodes, which are shorter than 32 bytes and aren't referenced by
+//! their hash, can't appear in the state tries, whose keys are hashed, and are
+//! not supported.  The AccountDestructed proof type is not supported.
+
+use crate::{
+    evm_circuit::util::{constraint_builder::BaseConstraintBuilder, rlc},
+    table::{DynamicTableColumns, KeccakTable, MptTable, ProofType},
+    util::{
+        keccak,
+        rlp::{RlpByteClass, RlpByteTable, RlpDecoderConfig, RlpDecoderRow},
+        Challenges, SubCircuit, SubCircuitConfig,
+    },
+    witness::{self, MptKey, MptUpdate, MptUpdateRow, MptUpdates},
+};
+use bus_mapping::mpt::EMPTY_ROOT;
+use eth_types::{Field, ToBigEndian, ToLittleEndian, ToWord, Word};
+use ethers_core::utils::rlp::{DecoderError, Rlp, RlpStream};
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{and, not, select, sum, Expr},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, Instance, SecondPhase,
+        VirtualCells,
+    },
+    poly::Rotation,
+};
+use keccak256::EMPTY_HASH_LE;
+use log::error;
+use std::array;
+
+#[cfg(any(feature = "test", test))]
+use halo2_proofs::{circuit::SimpleFloorPlanner, plonk::Circuit};
+
+/// Number of rows used for the root hash at the beginning of each side of an
+/// update.
+pub const ROOT_ROWS: usize = 32;
+
+/// Number of rows of the nibbles of a hashed key.
+const KEY_HASH_ROWS: usize = 64;
+
+/// Number of rows of the key of an account update: the address and the
+/// nibbles of its hash.
+const ACCOUNT_KEY_ROWS: usize = 20 + KEY_HASH_ROWS;
+
+/// Number of rows of the key of a storage update: the address and the storage
+/// key, each followed by the nibbles of its hash.
+const STORAGE_KEY_ROWS: usize = ACCOUNT_KEY_ROWS + 32 + KEY_HASH_ROWS;
+
+const MAX_DEGREE: usize = 9;
+
+/// RLP prefix of a 32 bytes string, which precedes the hash of a child node.
+const HASH_PREFIX: u8 = 0xa0;
+
+/// RLP encoding of the empty string, which is also the empty node.
+const EMPTY_STRING: u8 = 0x80;
+
+/// Number of [`NodeTag`]s, without [`NodeTag::None`].
+const NUM_TAGS: usize = 14;
+
+/// Tag of an RLP item of a trie node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum NodeTag {
+    /// No item, which precedes the first item of a node
+    #[default]
+    None = 0,
+    /// The empty node, which is the root of an empty trie
+    EmptyNode,
+    /// Header of the list of items of a branch, extension or leaf
+    ListHeader,
+    /// One of the 16 children of a branch: a hash or the empty string
+    Child,
+    /// Value of a branch, which is always empty in the state tries
+    BranchValue,
+    /// Hex-prefix encoded path of an extension or leaf
+    Path,
+    /// Hash of the child of an extension
+    ExtChild,
+    /// Header of the value of a storage leaf, when the RLP encoded value is
+    /// not a single byte
+    StorageValueHeader,
+    /// RLP encoded value of a storage leaf
+    StorageValue,
+    /// Header of the value of an account leaf
+    AccountValue,
+    /// Header of the list of fields of an account
+    AccountList,
+    /// Nonce of an account
+    Nonce,
+    /// Balance of an account
+    Balance,
+    /// Storage root of an account
+    StorageRoot,
+    /// Code hash of an account
+    CodeHash,
+}
+
+impl NodeTag {
+    /// Returns true for the items that only contain an RLP header, whose
+    /// length is the number of remaining bytes of the node.
+    fn is_container(&self) -> bool {
+        matches!(
+            self,
+            Self::EmptyNode
+                | Self::ListHeader
+                | Self::StorageValueHeader
+                | Self::AccountValue
+                | Self::AccountList
+        )
+    }
+}
+
+/// Kind of a trie node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum NodeKind {
+    /// The empty node
+    #[default]
+    Empty = 0,
+    /// Branch with 16 children and a value
+    Branch,
+    /// Extension with a path and a child
+    Extension,
+    /// Leaf with a path and a value
+    Leaf,
+}
+
+/// Flags of the update type, in the order of the columns of the proof type
+/// table.
+const UPDATE_NONCE: usize = 0;
+const UPDATE_BALANCE: usize = 1;
+const UPDATE_CODE_HASH: usize = 2;
+const UPDATE_STORAGE: usize = 3;
+const UPDATE_NON_EXISTING: usize = 4;
+
+/// Rows of the proof type table: the proof type followed by the update flags.
+const PROOF_TYPES: [(ProofType, [bool; 5]); 6] = [
+    (ProofType::NonceChanged, [true, false, false, false, false]),
+    (
+        ProofType::BalanceChanged,
+        [false, true, false, false, false],
+    ),
+    (
+        ProofType::CodeHashExists,
+        [false, false, true, false, false],
+    ),
+    (
+        ProofType::AccountDoesNotExist,
+        [false, false, true, false, true],
+    ),
+    (
+        ProofType::StorageChanged,
+        [false, false, false, true, false],
+    ),
+    (
+        ProofType::StorageDoesNotExist,
+        [false, false, false, true, true],
+    ),
+];
+
+/// Flags of the key rows.
+const KEY_ADDRESS: usize = 0;
+const KEY_ADDRESS_HASH: usize = 1;
+const KEY_STORAGE_KEY: usize = 2;
+const KEY_STORAGE_KEY_HASH: usize = 3;
+
+/// Returns the transitions between the items of the trie nodes, as rows of
+/// `(previous tag, tag, item index, node kind, trie level, byte class of the
+/// first byte of the item)`.  The level is 0 for the account trie and 1 for
+/// the storage tries.
+fn node_transitions() -> Vec<(NodeTag, NodeTag, usize, NodeKind, usize, RlpByteClass)> {
+    use NodeKind::*;
+    use NodeTag::*;
+    use RlpByteClass::*;
+
+    let mut rows = Vec::new();
+    for level in 0..2 {
+        rows.push((None, EmptyNode, 0, Empty, level, ShortString));
+        for kind in [Branch, Extension, Leaf] {
+            for class in [ShortList, LongList] {
+                rows.push((None, ListHeader, 0, kind, level, class));
+            }
+        }
+        rows.push((ListHeader, Child, 1, Branch, level, ShortString));
+        for index in 2..=16 {
+            rows.push((Child, Child, index, Branch, level, ShortString));
+        }
+        rows.push((Child, BranchValue, 17, Branch, level, ShortString));
+        for kind in [Extension, Leaf] {
+            for class in [Single, ShortString] {
+                rows.push((ListHeader, Path, 1, kind, level, class));
+            }
+        }
+        rows.push((Path, ExtChild, 2, Extension, level, ShortString));
+    }
+    rows.extend([
+        (Path, AccountValue, 2, Leaf, 0, LongString),
+        (AccountValue, AccountList, 3, Leaf, 0, LongList),
+        (AccountList, Nonce, 4, Leaf, 0, Single),
+        (AccountList, Nonce, 4, Leaf, 0, ShortString),
+        (Nonce, Balance, 5, Leaf, 0, Single),
+        (Nonce, Balance, 5, Leaf, 0, ShortString),
+        (Balance, StorageRoot, 6, Leaf, 0, ShortString),
+        (StorageRoot, CodeHash, 7, Leaf, 0, ShortString),
+        (Path, StorageValue, 2, Leaf, 1, Single),
+        (Path, StorageValueHeader, 2, Leaf, 1, ShortString),
+        (StorageValueHeader, StorageValue, 3, Leaf, 1, ShortString),
+    ]);
+    rows
+}
+
+/// Config for MptCircuit
+#[derive(Clone, Debug)]
+pub struct MptCircuitConfig<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_last: Column<Fixed>,
+    byte_table: RlpByteTable,
+    /// Bytes with their nibbles: (byte, hi, lo)
+    nibble_table: [Column<Fixed>; 3],
+    q_transition_table: Column<Fixed>,
+    /// Columns of [`node_transitions`]
+    transition_table: [Column<Fixed>; 6],
+    q_proof_type_table: Column<Fixed>,
+    /// Columns of [`PROOF_TYPES`]
+    proof_type_table: [Column<Fixed>; 6],
+
+    // Updates
+    byte: Column<Advice>,
+    nibble_hi: Column<Advice>,
+    nibble_lo: Column<Advice>,
+    is_padding: Column<Advice>,
+    is_update_start: Column<Advice>,
+    is_new_side: Column<Advice>,
+    update_index: Column<Advice>,
+    is_first_update: Column<Advice>,
+    update_flags: [Column<Advice>; 5],
+
+    // Keys
+    key_flags: [Column<Advice>; 4],
+    key_index: Column<Advice>,
+    key_acc: Column<Advice>,
+    is_key_lo: Column<Advice>,
+
+    // Roots
+    is_root: Column<Advice>,
+    is_root_start: Column<Advice>,
+    is_root_hi: Column<Advice>,
+    root_hi: Column<Advice>,
+    root_lo: Column<Advice>,
+    initial_root: [Column<Advice>; 2],
+    final_root: [Column<Advice>; 2],
+    pi_randomness: Column<Advice>,
+    root_pi_pow: Column<Advice>,
+    root_pi_rlc: Column<Advice>,
+    initial_root_rlc: Column<Advice>,
+    final_root_rlc: Column<Advice>,
+    initial_root_word: Column<Advice>,
+    final_root_word: Column<Advice>,
+    is_ref: Column<Advice>,
+    ref_index: Column<Advice>,
+
+    // Nodes
+    is_node_start: Column<Advice>,
+    is_node_end: Column<Advice>,
+    is_last_node: Column<Advice>,
+    is_drift: Column<Advice>,
+    level: Column<Advice>,
+    depth: Column<Advice>,
+    node_index: Column<Advice>,
+    is_paired: Column<Advice>,
+    kinds: [Column<Advice>; 4],
+    is_key_leaf: Column<Advice>,
+    is_odd_path: Column<Advice>,
+    is_moved: Column<Advice>,
+    is_moved_direct: Column<Advice>,
+    drift_position: Column<Advice>,
+    key_exists: Column<Advice>,
+    node_len: Column<Advice>,
+    node_remaining: Column<Advice>,
+
+    // Items
+    rlp: RlpDecoderConfig<F>,
+    item_acc: Column<Advice>,
+    tags: [Column<Advice>; NUM_TAGS],
+    item_index: Column<Advice>,
+    is_first_payload: Column<Advice>,
+    is_length_check: Column<Advice>,
+    child_count: Column<Advice>,
+    key_child_count: Column<Advice>,
+    drift_child_count: Column<Advice>,
+    is_key_item: Column<Advice>,
+    is_drift_child: Column<Advice>,
+    is_drift_child_end: Column<Advice>,
+    ref_count: Column<Advice>,
+
+    // Paths
+    nibble_depth: Column<Advice>,
+    is_diverged: Column<Advice>,
+    is_mismatch_hi: Column<Advice>,
+    is_mismatch_lo: Column<Advice>,
+    mismatch_nibble: Column<Advice>,
+    mismatch_inv: Column<Advice>,
+    check_hi: Column<Advice>,
+    check_lo: Column<Advice>,
+    key_position: Column<Advice>,
+    key_nibble: Column<Advice>,
+    mismatch_depth: Column<Advice>,
+    mismatch_path_nibble: Column<Advice>,
+    is_tail_hi: Column<Advice>,
+    is_tail_lo: Column<Advice>,
+    tail_len: Column<Advice>,
+
+    // Node ends
+    is_value_end: Column<Advice>,
+    is_default_check: Column<Advice>,
+    sibling_len: Column<Advice>,
+    rest_len: Column<Advice>,
+    is_paired_end: Column<Advice>,
+    is_drift_end: Column<Advice>,
+    is_side_end: Column<Advice>,
+    is_unpaired_end: Column<Advice>,
+
+    length_is_one: IsZeroConfig<F>,
+
+    key_value_rlc: Column<Advice>,
+    key_input_rlc: Column<Advice>,
+    key_rlc: Column<Advice>,
+    node_rlc: Column<Advice>,
+    node_hash: Column<Advice>,
+    ref_rlc: Column<Advice>,
+    item_rlc: Column<Advice>,
+    sibling_rlc: Column<Advice>,
+    tail_rlc: Column<Advice>,
+    rest_rlc: Column<Advice>,
+
+    instance: Column<Instance>,
+
+    /// MPT table
+    pub mpt_table: MptTable,
+    /// Keccak table
+    pub keccak_table: KeccakTable,
+}
+
+/// Circuit configuration arguments
+pub struct MptCircuitConfigArgs<F: Field> {
+    /// MptTable
+    pub mpt_table: MptTable,
+    /// KeccakTable
+    pub keccak_table: KeccakTable,
+    /// Challenges
+    pub challenges: Challenges<Expression<F>>,
+}
+
+/// Cells with the RLCs of the initial and final roots, and the randomness
+/// used to compute them, which are linked to the state roots of the
+/// PublicInputs circuit, and with the initial and final roots in the encoding
+/// of the MptTable, which are linked to the state roots of the StateCircuit.
+#[derive(Clone, Debug)]
+pub(crate) struct MptRootCells<F: Field> {
+    pub(crate) randomness: AssignedCell<F, F>,
+    pub(crate) initial_root_rlc: AssignedCell<F, F>,
+    pub(crate) final_root_rlc: AssignedCell<F, F>,
+    pub(crate) initial_root_word: AssignedCell<F, F>,
+    pub(crate) final_root_word: AssignedCell<F, F>,
+}
+
+impl<F: Field> SubCircuitConfig<F> for MptCircuitConfig<F> {
+    type ConfigArgs = MptCircuitConfigArgs<F>;
+
+    /// Return a new MptCircuitConfig
+    fn new(
+        meta: &mut ConstraintSystem<F>,
+        Self::ConfigArgs {
+            mpt_table,
+            keccak_table,
+            challenges,
+        }: Self::ConfigArgs,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_first = meta.fixed_column();
+        let q_last = meta.fixed_column();
+        let byte_table = RlpByteTable::configure(meta);
+        let nibble_table = array::from_fn(|_| meta.fixed_column());
+        let q_transition_table = meta.fixed_column();
+        let transition_table = array::from_fn(|_| meta.fixed_column());
+        let q_proof_type_table = meta.fixed_column();
+        let proof_type_table = array::from_fn(|_| meta.fixed_column());
+
+        let byte = meta.advice_column();
+        let nibble_hi = meta.advice_column();
+        let nibble_lo = meta.advice_column();
+        let is_padding = meta.advice_column();
+        let is_update_start = meta.advice_column();
+        let is_new_side = meta.advice_column();
+        let update_index = meta.advice_column();
+        let is_first_update = meta.advice_column();
+        let update_flags: [Column<Advice>; 5] = array::from_fn(|_| meta.advice_column());
+
+        let key_flags: [Column<Advice>; 4] = array::from_fn(|_| meta.advice_column());
+        let key_index = meta.advice_column();
+        let key_acc = meta.advice_column();
+        let is_key_lo = meta.advice_column();
+
+        let is_root = meta.advice_column();
+        let is_root_start = meta.advice_column();
+        let is_root_hi = meta.advice_column();
+        let root_hi = meta.advice_column();
+        let root_lo = meta.advice_column();
+        let initial_root = [meta.advice_column(), meta.advice_column()];
+        let final_root = [meta.advice_column(), meta.advice_column()];
+        let pi_randomness = meta.advice_column();
+        let root_pi_pow = meta.advice_column();
+        let root_pi_rlc = meta.advice_column();
+        let initial_root_rlc = meta.advice_column();
+        let final_root_rlc = meta.advice_column();
+        let initial_root_word = meta.advice_column_in(SecondPhase);
+        let final_root_word = meta.advice_column_in(SecondPhase);
+        let is_ref = meta.advice_column();
+        let ref_index = meta.advice_column();
+
+        let is_node_start = meta.advice_column();
+        let is_node_end = meta.advice_column();
+        let is_last_node = meta.advice_column();
+        let is_drift = meta.advice_column();
+        let level = meta.advice_column();
+        let depth = meta.advice_column();
+        let node_index = meta.advice_column();
+        let is_paired = meta.advice_column();
+        let kinds: [Column<Advice>; 4] = array::from_fn(|_| meta.advice_column());
+        let is_key_leaf = meta.advice_column();
+        let is_odd_path = meta.advice_column();
+        let is_moved = meta.advice_column();
+        let is_moved_direct = meta.advice_column();
+        let drift_position = meta.advice_column();
+        let key_exists = meta.advice_column();
+        let node_len = meta.advice_column();
+        let node_remaining = meta.advice_column();
+
+        let item_acc = meta.advice_column();
+        let tags: [Column<Advice>; NUM_TAGS] = array::from_fn(|_| meta.advice_column());
+        let item_index = meta.advice_column();
+        let is_first_payload = meta.advice_column();
+        let is_length_check = meta.advice_column();
+        let child_count = meta.advice_column();
+        let key_child_count = meta.advice_column();
+        let drift_child_count = meta.advice_column();
+        let is_key_item = meta.advice_column();
+        let is_drift_child = meta.advice_column();
+        let is_drift_child_end = meta.advice_column();
+        let ref_count = meta.advice_column();
+
+        let nibble_depth = meta.advice_column();
+        let is_diverged = meta.advice_column();
+        let is_mismatch_hi = meta.advice_column();
+        let is_mismatch_lo = meta.advice_column();
+        let mismatch_nibble = meta.advice_column();
+        let mismatch_inv = meta.advice_column();
+        let check_hi = meta.advice_column();
+        let check_lo = meta.advice_column();
+        let key_position = meta.advice_column();
+        let key_nibble = meta.advice_column();
+        let mismatch_depth = meta.advice_column();
+        let mismatch_path_nibble = meta.advice_column();
+        let is_tail_hi = meta.advice_column();
+        let is_tail_lo = meta.advice_column();
+        let tail_len = meta.advice_column();
+
+        let is_value_end = meta.advice_column();
+        let is_default_check = meta.advice_column();
+        let sibling_len = meta.advice_column();
+        let rest_len = meta.advice_column();
+        let is_paired_end = meta.advice_column();
+        let is_drift_end = meta.advice_column();
+        let is_side_end = meta.advice_column();
+        let is_unpaired_end = meta.advice_column();
+
+        let key_value_rlc = meta.advice_column_in(SecondPhase);
+        let key_input_rlc = meta.advice_column_in(SecondPhase);
+        let key_rlc = meta.advice_column_in(SecondPhase);
+        let node_rlc = meta.advice_column_in(SecondPhase);
+        let node_hash = meta.advice_column_in(SecondPhase);
+        let ref_rlc = meta.advice_column_in(SecondPhase);
+        let item_rlc = meta.advice_column_in(SecondPhase);
+        let sibling_rlc = meta.advice_column_in(SecondPhase);
+        let tail_rlc = meta.advice_column_in(SecondPhase);
+        let rest_rlc = meta.advice_column_in(SecondPhase);
+
+        let instance = meta.instance_column();
+        for column in initial_root.iter().chain(final_root.iter()).chain(
+            [
+                pi_randomness,
+                initial_root_rlc,
+                final_root_rlc,
+                initial_root_word,
+                final_root_word,
+            ]
+            .iter(),
+        ) {
+            meta.enable_equality(*column);
+        }
+        meta.enable_equality(instance);
+
+        let table_columns: [Column<Advice>; 7] = mpt_table.columns().try_into().unwrap();
+        let [address, storage_key, proof_type, new_root, old_root, new_value, old_value] =
+            table_columns;
+
+        let cur = |meta: &mut VirtualCells<'_, F>, column: Column<Advice>| {
+            meta.query_advice(column, Rotation::cur())
+        };
+        let prev = |meta: &mut VirtualCells<'_, F>, column: Column<Advice>| {
+            meta.query_advice(column, Rotation::prev())
+        };
+        let q_not_first = |meta: &mut VirtualCells<'_, F>| {
+            meta.query_fixed(q_enable, Rotation::cur()) - meta.query_fixed(q_first, Rotation::cur())
+        };
+        // Rows that are not padding, key or root rows
+        let node_row = |meta: &mut VirtualCells<'_, F>| {
+            1.expr()
+                - cur(meta, is_padding)
+                - sum::expr(key_flags.map(|column| cur(meta, column)))
+                - cur(meta, is_root)
+        };
+        let tag = |tags: &[Expression<F>], tag: NodeTag| tags[tag as usize - 1].clone();
+        let tag_value = |tags: &[Expression<F>]| {
+            sum::expr(
+                tags.iter()
+                    .enumerate()
+                    .map(|(i, tag)| (i + 1).expr() * tag.clone()),
+            )
+        };
+        let kind_value = |kinds: &[Expression<F>]| {
+            sum::expr(
+                kinds
+                    .iter()
+                    .enumerate()
+                    .map(|(i, kind)| i.expr() * kind.clone()),
+            )
+        };
+        // Items that end with their header, whose payload is decoded as items
+        let is_container = |tags: &[Expression<F>]| {
+            tag(tags, NodeTag::EmptyNode)
+                + tag(tags, NodeTag::ListHeader)
+                + tag(tags, NodeTag::StorageValueHeader)
+                + tag(tags, NodeTag::AccountValue)
+                + tag(tags, NodeTag::AccountList)
+        };
+        let empty_code_hash_rlc = |challenges: &Challenges<Expression<F>>| {
+            rlc::expr(
+                &EMPTY_HASH_LE.map(|byte| byte.expr()),
+                challenges.evm_word(),
+            )
+        };
+        let empty_root_rlc = |challenges: &Challenges<Expression<F>>| {
+            rlc::expr(
+                &EMPTY_ROOT.to_word().to_le_bytes().map(|byte| byte.expr()),
+                challenges.evm_word(),
+            )
+        };
+
+        let rlp = RlpDecoderConfig::configure(
+            meta,
+            |meta| q_not_first(meta) * node_row(meta),
+            byte,
+            &byte_table,
+            |meta| is_container(&tags.map(|column| cur(meta, column))),
+        );
+        let RlpDecoderConfig {
+            is_item_start,
+            is_item_end,
+            is_header,
+            classes,
+            length,
+            ref counter_is_zero,
+            ref length_is_zero,
+            ..
+        } = rlp;
+        let length_minus_one_inv = meta.advice_column();
+        let length_is_one = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_fixed(q_enable, Rotation::cur()) * node_row(meta),
+            |meta| cur(meta, length) - 1.expr(),
+            length_minus_one_inv,
+        );
+
+        meta.create_gate("flags are boolean", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+            for (name, column) in [
+                ("is_padding is boolean", is_padding),
+                ("is_update_start is boolean", is_update_start),
+                ("is_new_side is boolean", is_new_side),
+                ("is_first_update is boolean", is_first_update),
+                ("is_key_lo is boolean", is_key_lo),
+                ("is_root is boolean", is_root),
+                ("is_root_start is boolean", is_root_start),
+                ("is_root_hi is boolean", is_root_hi),
+                ("is_ref is boolean", is_ref),
+                ("is_node_start is boolean", is_node_start),
+                ("is_node_end is boolean", is_node_end),
+                ("is_last_node is boolean", is_last_node),
+                ("is_drift is boolean", is_drift),
+                ("level is boolean", level),
+                ("is_paired is boolean", is_paired),
+                ("is_key_leaf is boolean", is_key_leaf),
+                ("is_odd_path is boolean", is_odd_path),
+                ("is_moved is boolean", is_moved),
+                ("is_moved_direct is boolean", is_moved_direct),
+                ("key_exists is boolean", key_exists),
+                ("is_first_payload is boolean", is_first_payload),
+                ("is_length_check is boolean", is_length_check),
+                ("is_key_item is boolean", is_key_item),
+                ("is_drift_child is boolean", is_drift_child),
+                ("is_drift_child_end is boolean", is_drift_child_end),
+                ("is_diverged is boolean", is_diverged),
+                ("is_mismatch_hi is boolean", is_mismatch_hi),
+                ("is_mismatch_lo is boolean", is_mismatch_lo),
+                ("check_hi is boolean", check_hi),
+                ("check_lo is boolean", check_lo),
+                ("is_tail_hi is boolean", is_tail_hi),
+                ("is_tail_lo is boolean", is_tail_lo),
+                ("is_value_end is boolean", is_value_end),
+                ("is_default_check is boolean", is_default_check),
+                ("is_paired_end is boolean", is_paired_end),
+                ("is_drift_end is boolean", is_drift_end),
+                ("is_side_end is boolean", is_side_end),
+                ("is_unpaired_end is boolean", is_unpaired_end),
+            ] {
+                cb.require_boolean(name, cur(meta, column));
+            }
+            for (name, columns) in [
+                ("update flags are boolean", update_flags.as_slice()),
+                ("key flags are boolean", key_flags.as_slice()),
+                ("node kind flags are boolean", kinds.as_slice()),
+                ("tag flags are boolean", tags.as_slice()),
+            ] {
+                for column in columns {
+                    cb.require_boolean(name, cur(meta, *column));
+                }
+            }
+            cb.require_boolean(
+                "padding, key, root and node rows are exclusive",
+                cur(meta, is_padding)
+                    + sum::expr(key_flags.map(|column| cur(meta, column)))
+                    + cur(meta, is_root),
+            );
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("first row", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_padding = cur(meta, is_padding);
+            let byte = cur(meta, byte);
+            cb.condition(not::expr(is_padding.clone()), |cb| {
+                cb.require_equal(
+                    "the first row starts an update",
+                    cur(meta, is_update_start),
+                    1.expr(),
+                );
+                cb.require_equal("update_index = 1", cur(meta, update_index), 1.expr());
+                cb.require_equal(
+                    "the first update is_first_update",
+                    cur(meta, is_first_update),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "an update starts with the address",
+                    cur(meta, key_flags[KEY_ADDRESS]),
+                    1.expr(),
+                );
+                cb.require_zero("key_index = 0", cur(meta, key_index));
+                cb.require_equal("key_acc = byte", cur(meta, key_acc), byte.clone());
+                cb.require_equal(
+                    "key_input_rlc = byte",
+                    cur(meta, key_input_rlc),
+                    byte.clone(),
+                );
+                cb.require_zero("an update starts with the old side", cur(meta, is_new_side));
+                cb.require_zero(
+                    "the storage key of an account update is 0",
+                    (1.expr() - cur(meta, update_flags[UPDATE_STORAGE])) * cur(meta, storage_key),
+                );
+            });
+            cb.condition(is_padding, |cb| {
+                for i in 0..2 {
+                    cb.require_equal(
+                        "final_root is initial_root when there are no updates",
+                        cur(meta, final_root[i]),
+                        cur(meta, initial_root[i]),
+                    );
+                }
+                cb.require_equal(
+                    "final_root_rlc is initial_root_rlc when there are no updates",
+                    cur(meta, final_root_rlc),
+                    cur(meta, initial_root_rlc),
+                );
+                cb.require_equal(
+                    "final_root_word is initial_root_word when there are no updates",
+                    cur(meta, final_root_word),
+                    cur(meta, initial_root_word),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_first, Rotation::cur()))
+        });
+
+        meta.create_gate("padding", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_padding_prev = prev(meta, is_padding);
+            let is_padding = cur(meta, is_padding);
+
+            cb.require_zero(
+                "padding is not followed by an update",
+                is_padding_prev.clone() * not::expr(is_padding.clone()),
+            );
+            for i in 0..2 {
+                cb.require_equal(
+                    "initial_root is the same in all rows",
+                    cur(meta, initial_root[i]),
+                    prev(meta, initial_root[i]),
+                );
+                cb.require_equal(
+                    "final_root is the same in all rows",
+                    cur(meta, final_root[i]),
+                    prev(meta, final_root[i]),
+                );
+            }
+            for (name, column) in [
+                ("pi_randomness is the same in all rows", pi_randomness),
+                ("initial_root_rlc is the same in all rows", initial_root_rlc),
+                ("final_root_rlc is the same in all rows", final_root_rlc),
+                (
+                    "initial_root_word is the same in all rows",
+                    initial_root_word,
+                ),
+                ("final_root_word is the same in all rows", final_root_word),
+            ] {
+                cb.require_equal(name, cur(meta, column), prev(meta, column));
+            }
+
+            // Transition from the last update to the padding
+            cb.condition(is_padding - is_padding_prev, |cb| {
+                cb.require_equal(
+                    "the last update ends with the new side",
+                    and::expr([
+                        prev(meta, is_new_side),
+                        prev(meta, is_node_end),
+                        prev(meta, is_last_node),
+                    ]),
+                    1.expr(),
+                );
+                for (i, root_column) in [root_hi, root_lo].into_iter().enumerate() {
+                    cb.require_equal(
+                        "final_root is the new root of the last update",
+                        cur(meta, final_root[i]),
+                        prev(meta, root_column),
+                    );
+                }
+                cb.require_equal(
+                    "final_root_rlc is the new root of the last update",
+                    cur(meta, final_root_rlc),
+                    prev(meta, root_pi_rlc),
+                );
+                cb.require_equal(
+                    "final_root_word is the new root of the last update",
+                    cur(meta, final_root_word),
+                    prev(meta, new_root),
+                );
+            });
+
+            cb.gate(q_not_first(meta))
+        });
+
+        meta.create_gate("padding rows don't contain updates", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            cb.require_equal("the last row is padding", cur(meta, is_padding), 1.expr());
+
+            cb.gate(meta.query_fixed(q_last, Rotation::cur()))
+        });
+
+        meta.create_gate("padding rows are empty", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            for column in table_columns {
+                cb.require_zero("mpt table is empty in padding rows", cur(meta, column));
+            }
+            cb.require_zero(
+                "padding rows don't start an update",
+                cur(meta, is_update_start),
+            );
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                cur(meta, is_padding),
+            ]))
+        });
+
+        meta.create_gate("updates", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_update_start = cur(meta, is_update_start);
+            let is_new_side_prev = prev(meta, is_new_side);
+            let is_new_side = cur(meta, is_new_side);
+            let side_end_prev = and::expr([prev(meta, is_node_end), prev(meta, is_last_node)]);
+            let is_key = sum::expr(key_flags.map(|column| cur(meta, column)));
+            let is_key_prev = sum::expr(key_flags.map(|column| prev(meta, column)));
+
+            cb.condition(is_update_start.clone(), |cb| {
+                cb.require_zero("an update starts with the old side", is_new_side.clone());
+                cb.require_equal(
+                    "the previous update is complete",
+                    is_new_side_prev.clone() * side_end_prev.clone(),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "old_root is the new_root of the previous update",
+                    cur(meta, old_root),
+                    prev(meta, new_root),
+                );
+                cb.require_zero(
+                    "an update starts with the key rows",
+                    cur(meta, is_root_start),
+                );
+                cb.require_equal(
+                    "an update starts with the address",
+                    cur(meta, key_flags[KEY_ADDRESS]),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "update_index increases by 1",
+                    cur(meta, update_index),
+                    prev(meta, update_index) + 1.expr(),
+                );
+                cb.require_zero(
+                    "only the first update is_first_update",
+                    cur(meta, is_first_update),
+                );
+                cb.require_zero(
+                    "the storage key of an account update is 0",
+                    (1.expr() - cur(meta, update_flags[UPDATE_STORAGE])) * cur(meta, storage_key),
+                );
+            });
+
+            cb.condition(not::expr(is_update_start), |cb| {
+                for column in table_columns
+                    .into_iter()
+                    .chain([update_index, is_first_update])
+                    .chain(update_flags)
+                {
+                    cb.require_equal(
+                        "the update is the same for all its rows",
+                        cur(meta, column),
+                        prev(meta, column),
+                    );
+                }
+                let side_switch = is_new_side.clone() - is_new_side_prev.clone();
+                cb.require_boolean("the new side follows the old side", side_switch.clone());
+                cb.require_zero(
+                    "the new side starts after the old side ends",
+                    side_switch.clone() * (1.expr() - side_end_prev.clone()),
+                );
+                cb.require_equal(
+                    "the sides start with the root rows, after the key rows",
+                    cur(meta, is_root_start),
+                    side_switch + is_key_prev.clone() - is_key.clone(),
+                );
+                cb.require_zero(
+                    "the key rows are at the start of the update",
+                    is_key * (1.expr() - is_key_prev),
+                );
+            });
+
+            cb.gate(and::expr([
+                q_not_first(meta),
+                not::expr(cur(meta, is_padding)),
+            ]))
+        });
+
+        meta.create_gate("key rows", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte_prev = prev(meta, byte);
+            let byte = cur(meta, byte);
+            let is_update_start = cur(meta, is_update_start);
+            let is_storage_update = cur(meta, update_flags[UPDATE_STORAGE]);
+            let [a, ah, s, sh] = key_flags.map(|column| cur(meta, column));
+            let [a_prev, ah_prev, s_prev, sh_prev] = key_flags.map(|column| prev(meta, column));
+            let key_index_prev = prev(meta, key_index);
+            let key_input_rlc_prev = prev(meta, key_input_rlc);
+            let key_rlc_prev = prev(meta, key_rlc);
+
+            // Transitions between the key rows
+            cb.require_zero(
+                "the address is followed by its hash",
+                a_prev.clone() * (1.expr() - a.clone() - ah.clone()),
+            );
+            cb.condition(a_prev.clone() * ah.clone(), |cb| {
+                cb.require_equal(
+                    "the address has 20 bytes",
+                    key_index_prev.clone(),
+                    19.expr(),
+                );
+                cb.require_equal(
+                    "the address is the one of the mpt table",
+                    prev(meta, key_acc),
+                    cur(meta, address),
+                );
+            });
+            cb.require_zero(
+                "the address hash is not followed by an address or a storage key hash",
+                ah_prev.clone() * (a.clone() + sh.clone()),
+            );
+            cb.require_zero(
+                "only storage updates have a storage key",
+                ah_prev.clone() * s.clone() * (1.expr() - is_storage_update.clone()),
+            );
+            cb.require_zero(
+                "storage updates have a storage key",
+                ah_prev.clone()
+                    * (1.expr() - ah.clone())
+                    * (1.expr() - s.clone())
+                    * is_storage_update,
+            );
+            cb.condition(ah_prev.clone() * (1.expr() - ah.clone()), |cb| {
+                cb.require_equal(
+                    "the address hash has 64 nibbles",
+                    key_index_prev.clone(),
+                    (KEY_HASH_ROWS - 1).expr(),
+                );
+            });
+            cb.require_zero(
+                "the storage key is followed by its hash",
+                s_prev.clone() * (1.expr() - s.clone() - sh.clone()),
+            );
+            cb.condition(s_prev.clone() * sh.clone(), |cb| {
+                cb.require_equal(
+                    "the storage key has 32 bytes",
+                    key_index_prev.clone(),
+                    31.expr(),
+                );
+                cb.require_equal(
+                    "the storage key is the one of the mpt table",
+                    prev(meta, key_value_rlc),
+                    cur(meta, storage_key),
+                );
+            });
+            cb.require_zero(
+                "the storage key hash is the last key",
+                sh_prev.clone() * (a.clone() + ah.clone() + s.clone()),
+            );
+            cb.condition(sh_prev.clone() * (1.expr() - sh.clone()), |cb| {
+                cb.require_equal(
+                    "the storage key hash has 64 nibbles",
+                    key_index_prev.clone(),
+                    (KEY_HASH_ROWS - 1).expr(),
+                );
+            });
+            cb.require_zero(
+                "the address hash follows the address",
+                ah.clone() * (1.expr() - ah_prev.clone() - a_prev.clone()),
+            );
+            cb.require_zero(
+                "the storage key follows the address hash",
+                s.clone() * (1.expr() - s_prev.clone() - ah_prev.clone()),
+            );
+            cb.require_zero(
+                "the storage key hash follows the storage key",
+                sh.clone() * (1.expr() - sh_prev.clone() - s_prev.clone()),
+            );
+            cb.require_zero(
+                "the address is at the start of the update",
+                a.clone() * (1.expr() - a_prev.clone() - is_update_start),
+            );
+
+            let is_continue = a.clone() * a_prev.clone()
+                + ah.clone() * ah_prev.clone()
+                + s.clone() * s_prev.clone()
+                + sh.clone() * sh_prev.clone();
+            cb.require_equal(
+                "key_index increases by 1 in each key",
+                cur(meta, key_index),
+                is_continue * (key_index_prev + 1.expr()),
+            );
+
+            cb.condition(a.clone(), |cb| {
+                cb.require_equal(
+                    "key_acc accumulates the address bytes",
+                    cur(meta, key_acc),
+                    a_prev.clone() * prev(meta, key_acc) * 256.expr() + byte.clone(),
+                );
+            });
+            cb.condition(s.clone(), |cb| {
+                cb.require_equal(
+                    "key_value_rlc accumulates the storage key bytes",
+                    cur(meta, key_value_rlc),
+                    s_prev.clone() * prev(meta, key_value_rlc) * challenges.evm_word()
+                        + byte.clone(),
+                );
+            });
+            cb.condition(a.clone() + s.clone(), |cb| {
+                cb.require_equal(
+                    "key_input_rlc accumulates the key bytes",
+                    cur(meta, key_input_rlc),
+                    (a_prev.clone() + s_prev.clone())
+                        * key_input_rlc_prev.clone()
+                        * challenges.keccak_input()
+                        + byte.clone(),
+                );
+            });
+
+            let is_hash = ah.clone() + sh.clone();
+            let is_hash_continue = ah.clone() * ah_prev.clone() + sh.clone() * sh_prev.clone();
+            let is_key_lo_prev = prev(meta, is_key_lo);
+            let is_key_lo = cur(meta, is_key_lo);
+            cb.condition(is_hash, |cb| {
+                cb.require_equal(
+                    "key_input_rlc is kept in the hash rows",
+                    cur(meta, key_input_rlc),
+                    key_input_rlc_prev.clone(),
+                );
+                cb.require_zero("the hash rows contain nibbles", cur(meta, nibble_hi));
+                cb.require_equal(
+                    "the hash nibbles alternate between hi and lo",
+                    is_key_lo.clone(),
+                    is_hash_continue.clone() * (1.expr() - is_key_lo_prev),
+                );
+                cb.require_equal(
+                    "key_rlc accumulates the hash bytes",
+                    cur(meta, key_rlc),
+                    select::expr(
+                        is_key_lo.clone(),
+                        key_rlc_prev.clone() * challenges.evm_word()
+                            + byte_prev.clone() * 16.expr()
+                            + byte.clone(),
+                        is_hash_continue.clone() * key_rlc_prev.clone(),
+                    ),
+                );
+            });
+
+            cb.gate(and::expr([
+                q_not_first(meta),
+                not::expr(cur(meta, is_padding)),
+            ]))
+        });
+
+        meta.create_gate("root rows", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = cur(meta, byte);
+            let q_not_first = q_not_first(meta);
+            let is_root_prev = q_not_first.clone() * prev(meta, is_root);
+            let is_root_hi_prev = prev(meta, is_root_hi);
+            let root_pi_pow_prev = prev(meta, root_pi_pow);
+            let is_root = cur(meta, is_root);
+            let is_root_start = cur(meta, is_root_start);
+            let is_root_hi = cur(meta, is_root_hi);
+            let ref_index_prev = prev(meta, ref_index);
+            let root_hi_prev = prev(meta, root_hi);
+            let root_lo_prev = prev(meta, root_lo);
+            let root_hi = cur(meta, root_hi);
+            let root_lo = cur(meta, root_lo);
+            let root_pi_rlc_prev = prev(meta, root_pi_rlc);
+            let root_pi_rlc = cur(meta, root_pi_rlc);
+            let root_pi_pow = cur(meta, root_pi_pow);
+
+            cb.require_zero(
+                "root rows start after a non-root row",
+                is_root_start.clone() * is_root_prev.clone(),
+            );
+            cb.require_zero(
+                "root rows are contiguous",
+                (is_root.clone() - is_root_start.clone()) * (1.expr() - is_root_prev.clone()),
+            );
+            cb.require_zero(
+                "root start is a root row",
+                is_root_start.clone() * (1.expr() - is_root.clone()),
+            );
+            cb.require_zero(
+                "root_hi rows are root rows",
+                is_root_hi.clone() * (1.expr() - is_root.clone()),
+            );
+
+            cb.condition(is_root.clone(), |cb| {
+                cb.require_equal(
+                    "root rows are a hash reference",
+                    cur(meta, is_ref),
+                    1.expr(),
+                );
+            });
+
+            cb.condition(is_root_start.clone(), |cb| {
+                cb.require_equal(
+                    "root_hi starts with the first root byte",
+                    is_root_hi.clone(),
+                    1.expr(),
+                );
+                cb.require_equal("root_hi = byte", root_hi.clone(), byte.clone());
+                cb.require_zero("root_lo = 0", root_lo.clone());
+                cb.require_equal("root_pi_pow = 1", root_pi_pow.clone(), 1.expr());
+                cb.require_equal("root_pi_rlc = byte", root_pi_rlc.clone(), byte.clone());
+            });
+
+            cb.condition(is_root.clone() - is_root_start, |cb| {
+                let is_root_hi_drop = is_root_hi_prev.clone() - is_root_hi.clone();
+                cb.require_boolean("is_root_hi goes from 1 to 0", is_root_hi_drop.clone());
+                cb.require_zero(
+                    "root_hi has 16 bytes",
+                    is_root_hi_drop * (ref_index_prev.clone() - 16.expr()),
+                );
+                cb.require_equal(
+                    "root_hi accumulates the first 16 root bytes",
+                    root_hi.clone(),
+                    select::expr(
+                        is_root_hi.clone(),
+                        root_hi_prev.clone() * 256.expr() + byte.clone(),
+                        root_hi_prev.clone(),
+                    ),
+                );
+                cb.require_equal(
+                    "root_lo accumulates the last 16 root bytes",
+                    root_lo.clone(),
+                    select::expr(
+                        is_root_hi.clone(),
+                        root_lo_prev.clone(),
+                        root_lo_prev.clone() * 256.expr() + byte.clone(),
+                    ),
+                );
+                cb.require_equal(
+                    "root_pi_pow is multiplied by the randomness of the public inputs",
+                    root_pi_pow.clone(),
+                    root_pi_pow_prev * cur(meta, pi_randomness),
+                );
+                cb.require_equal(
+                    "root_pi_rlc accumulates the root bytes",
+                    root_pi_rlc.clone(),
+                    root_pi_rlc_prev.clone() + byte.clone() * root_pi_pow,
+                );
+            });
+
+            cb.condition(not::expr(is_root.clone()) * is_root_prev.clone(), |cb| {
+                cb.require_equal(
+                    "the root has 32 bytes",
+                    ref_index_prev.clone(),
+                    ROOT_ROWS.expr(),
+                );
+                cb.require_zero("root_hi has 16 bytes", is_root_hi_prev);
+                cb.require_equal(
+                    "the first node follows the root rows",
+                    cur(meta, is_node_start),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "the first node hash is the root",
+                    cur(meta, node_hash),
+                    prev(meta, ref_rlc),
+                );
+                cb.require_equal(
+                    "the root rows contain the root of the mpt table",
+                    prev(meta, ref_rlc),
+                    select::expr(
+                        cur(meta, is_new_side),
+                        cur(meta, new_root),
+                        cur(meta, old_root),
+                    ),
+                );
+            });
+            cb.condition(
+                not::expr(is_root.clone())
+                    * is_root_prev
+                    * cur(meta, is_first_update)
+                    * not::expr(cur(meta, is_new_side)),
+                |cb| {
+                    for (i, root_column) in
+                        [root_hi.clone(), root_lo.clone()].into_iter().enumerate()
+                    {
+                        cb.require_equal(
+                            "initial_root is the old root of the first update",
+                            cur(meta, initial_root[i]),
+                            root_column,
+                        );
+                    }
+                    cb.require_equal(
+                        "initial_root_rlc is the old root of the first update",
+                        cur(meta, initial_root_rlc),
+                        root_pi_rlc_prev.clone(),
+                    );
+                    cb.require_equal(
+                        "initial_root_word is the old root of the first update",
+                        cur(meta, initial_root_word),
+                        cur(meta, old_root),
+                    );
+                },
+            );
+
+            cb.condition(not::expr(is_root) * q_not_first, |cb| {
+                cb.require_equal("root_hi is kept after the root rows", root_hi, root_hi_prev);
+                cb.require_equal("root_lo is kept after the root rows", root_lo, root_lo_prev);
+                cb.require_equal(
+                    "root_pi_rlc is kept after the root rows",
+                    root_pi_rlc,
+                    root_pi_rlc_prev,
+                );
+            });
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(cur(meta, is_padding)),
+            ]))
+        });
+
+        meta.create_gate("non-node rows", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            for column in [
+                is_node_start,
+                is_node_end,
+                is_last_node,
+                is_drift,
+                is_item_start,
+                is_item_end,
+                is_key_item,
+                is_drift_child,
+                check_hi,
+                check_lo,
+                is_mismatch_hi,
+                is_mismatch_lo,
+                is_tail_hi,
+                is_tail_lo,
+                is_value_end,
+                is_default_check,
+                is_paired_end,
+                is_drift_end,
+                is_side_end,
+                is_unpaired_end,
+                is_drift_child_end,
+                is_moved_direct,
+            ]
+            .into_iter()
+            .chain(kinds)
+            .chain(tags)
+            .chain(classes)
+            {
+                cb.require_zero(
+                    "node flags are disabled outside of the nodes",
+                    cur(meta, column),
+                );
+            }
+            cb.require_zero(
+                "key rows are not a hash reference",
+                sum::expr(key_flags.map(|column| cur(meta, column))) * cur(meta, is_ref),
+            );
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()) * (1.expr() - node_row(meta)))
+        });
+
+        meta.create_gate("nodes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            // The values that are the same for all the bytes of a node
+            let node_columns = [
+                is_last_node,
+                is_drift,
+                level,
+                depth,
+                node_index,
+                is_paired,
+                is_key_leaf,
+                is_odd_path,
+                is_moved,
+                is_moved_direct,
+                drift_position,
+                key_exists,
+                node_hash,
+            ];
+            let byte = cur(meta, byte);
+            let is_storage_update = cur(meta, update_flags[UPDATE_STORAGE]);
+            let is_root_prev = prev(meta, is_root);
+            let is_node_start = cur(meta, is_node_start);
+            let is_node_end_prev = prev(meta, is_node_end);
+            let is_node_end = cur(meta, is_node_end);
+            let is_last_node_prev = prev(meta, is_last_node);
+            let is_last_node = cur(meta, is_last_node);
+            let level_prev = prev(meta, level);
+            let is_paired_prev = prev(meta, is_paired);
+            let is_drift = cur(meta, is_drift);
+            let is_paired = cur(meta, is_paired);
+            let is_diverged = cur(meta, is_diverged);
+            let is_key_leaf = cur(meta, is_key_leaf);
+            let is_moved = cur(meta, is_moved);
+            let is_moved_direct = cur(meta, is_moved_direct);
+            let level = cur(meta, level);
+            let [is_empty, is_branch, is_extension, is_leaf] =
+                kinds.map(|column| cur(meta, column));
+            let [_, is_branch_prev, is_extension_prev, is_leaf_prev] =
+                kinds.map(|column| prev(meta, column));
+            let tags = tags.map(|column| cur(meta, column));
+
+            cb.condition(is_node_start.clone(), |cb| {
+                cb.require_equal(
+                    "a node starts after the root rows, after a node that is not the last one, \
+                     or is a drift node",
+                    is_root_prev.clone()
+                        + is_node_end_prev.clone() * not::expr(is_last_node_prev.clone())
+                        + is_drift.clone(),
+                    1.expr(),
+                );
+                cb.require_zero(
+                    "a drift node follows the last node of the side",
+                    is_drift.clone()
+                        * (1.expr() - is_node_end_prev.clone() * is_last_node_prev.clone()),
+                );
+                cb.require_equal("node_len = 1", cur(meta, node_len), 1.expr());
+                cb.require_equal("node_rlc = byte", cur(meta, node_rlc), byte.clone());
+                cb.require_equal(
+                    "nibble_depth starts at the node depth",
+                    cur(meta, nibble_depth),
+                    cur(meta, depth),
+                );
+                cb.require_zero(
+                    "the first byte of a node is not a reference",
+                    cur(meta, is_ref),
+                );
+            });
+            cb.condition(is_root_prev, |cb| {
+                cb.require_zero("the first node is in the account trie", level.clone());
+                cb.require_zero("the first node is at depth 0", cur(meta, depth));
+                cb.require_zero("the first node has index 0", cur(meta, node_index));
+            });
+            cb.condition(
+                is_node_end_prev.clone() * not::expr(is_last_node_prev),
+                |cb| {
+                    cb.require_equal(
+                        "a node that is not the last one is followed by a node",
+                        is_node_start.clone(),
+                        1.expr(),
+                    );
+                    cb.require_equal(
+                        "the node hash is referenced by the previous node",
+                        cur(meta, node_hash),
+                        prev(meta, ref_rlc),
+                    );
+                    cb.require_equal(
+                        "the child of a leaf is the root of the storage trie",
+                        level.clone(),
+                        level_prev.clone() + is_leaf_prev.clone(),
+                    );
+                    cb.require_equal(
+                        "the depth of a node is the number of nibbles of the path to it",
+                        cur(meta, depth),
+                        is_branch_prev.clone() * (prev(meta, depth) + 1.expr())
+                            + is_extension_prev.clone() * prev(meta, nibble_depth),
+                    );
+                    cb.require_equal(
+                        "node_index is the position of the node in its trie",
+                        cur(meta, node_index),
+                        (1.expr() - is_leaf_prev.clone()) * (prev(meta, node_index) + 1.expr()),
+                    );
+                    cb.require_zero(
+                        "the child of an extension is a branch",
+                        is_extension_prev * (1.expr() - is_branch.clone()),
+                    );
+                    cb.require_zero(
+                        "a storage leaf has no child",
+                        is_leaf_prev.clone() * level_prev,
+                    );
+                    cb.require_zero(
+                        "only storage updates continue into the storage trie",
+                        is_leaf_prev * (1.expr() - is_storage_update.clone()),
+                    );
+                    cb.require_zero(
+                        "the paired nodes are a prefix of the path",
+                        is_paired.clone() * (1.expr() - is_paired_prev),
+                    );
+                },
+            );
+
+            cb.condition(not::expr(is_node_start.clone()), |cb| {
+                for column in node_columns.into_iter().chain(kinds) {
+                    cb.require_equal(
+                        "the node values are the same for all the bytes of a node",
+                        cur(meta, column),
+                        prev(meta, column),
+                    );
+                }
+                cb.require_zero("a node end is followed by a node start", is_node_end_prev);
+                cb.require_equal(
+                    "node_len increases by 1",
+                    cur(meta, node_len),
+                    prev(meta, node_len) + 1.expr(),
+                );
+                cb.require_equal(
+                    "node_rlc accumulates the node bytes",
+                    cur(meta, node_rlc),
+                    prev(meta, node_rlc) * challenges.keccak_input() + byte,
+                );
+                cb.require_equal(
+                    "node_remaining decreases by 1",
+                    cur(meta, node_remaining),
+                    prev(meta, node_remaining) - 1.expr(),
+                );
+            });
+
+            cb.condition(is_drift.clone(), |cb| {
+                cb.require_equal(
+                    "a drift node is the last node",
+                    is_last_node.clone(),
+                    1.expr(),
+                );
+                cb.require_zero("a drift node is not paired", is_paired.clone());
+                cb.require_equal(
+                    "a drift node is a leaf or an extension",
+                    is_leaf.clone() + is_extension.clone(),
+                    1.expr(),
+                );
+            });
+            cb.require_equal(
+                "a node has one kind",
+                is_empty.clone() + is_branch.clone() + is_extension.clone() + is_leaf.clone(),
+                1.expr(),
+            );
+            cb.require_zero(
+                "the empty node is the root of a trie",
+                is_empty.clone() * cur(meta, node_index),
+            );
+
+            cb.condition(is_node_end.clone(), |cb| {
+                cb.require_zero(
+                    "the node ends with its last byte",
+                    cur(meta, node_remaining),
+                );
+                cb.require_equal(
+                    "the node ends with an item",
+                    cur(meta, is_item_end),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "the node ends with its last item",
+                    tag(&tags, NodeTag::BranchValue)
+                        + tag(&tags, NodeTag::ExtChild)
+                        + tag(&tags, NodeTag::StorageValue)
+                        + tag(&tags, NodeTag::CodeHash)
+                        + tag(&tags, NodeTag::EmptyNode),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "the leaf of the key is a leaf whose path doesn't diverge from the key",
+                    is_key_leaf.clone(),
+                    is_leaf.clone() * not::expr(is_diverged.clone()) * not::expr(is_drift.clone()),
+                );
+                cb.require_zero(
+                    "the path of the leaf of the key has all the key nibbles",
+                    is_key_leaf.clone() * (cur(meta, nibble_depth) - KEY_HASH_ROWS.expr()),
+                );
+                cb.require_equal(
+                    "an unpaired leaf or extension that diverges from the key is moved",
+                    is_moved.clone(),
+                    not::expr(is_paired.clone())
+                        * is_diverged.clone()
+                        * (is_leaf.clone() + is_extension.clone()),
+                );
+                cb.require_equal(
+                    "the key exists if its leaf is in the trie of the update",
+                    cur(meta, key_exists),
+                    is_key_leaf.clone()
+                        * (level.clone() * is_storage_update.clone()
+                            + not::expr(level.clone()) * not::expr(is_storage_update.clone())),
+                );
+                cb.require_equal(
+                    "only the last node doesn't reference a child",
+                    cur(meta, ref_count),
+                    32.expr() * not::expr(is_last_node.clone()),
+                );
+                cb.require_zero(
+                    "a directly moved extension has no tail",
+                    is_moved_direct.clone() * cur(meta, tail_len),
+                );
+            });
+            cb.condition(is_node_end.clone() * is_branch.clone(), |cb| {
+                cb.require_equal(
+                    "a branch has one child in the path of the key",
+                    cur(meta, key_child_count),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "an unpaired branch has a drift child",
+                    cur(meta, drift_child_count),
+                    not::expr(is_paired.clone()),
+                );
+            });
+            cb.require_zero(
+                "a directly moved node is an extension",
+                is_moved_direct.clone() * not::expr(is_extension),
+            );
+            cb.require_zero(
+                "a directly moved node is moved",
+                is_moved_direct * not::expr(is_moved.clone()),
+            );
+
+            cb.require_equal(
+                "is_paired_end",
+                cur(meta, is_paired_end),
+                is_node_end.clone() * is_paired.clone(),
+            );
+            cb.require_equal(
+                "is_drift_end",
+                cur(meta, is_drift_end),
+                is_node_end.clone() * is_drift.clone(),
+            );
+            cb.require_equal(
+                "is_side_end",
+                cur(meta, is_side_end),
+                is_node_end.clone() * is_last_node * not::expr(is_drift.clone()),
+            );
+            cb.require_equal(
+                "is_unpaired_end",
+                cur(meta, is_unpaired_end),
+                and::expr([
+                    is_node_end,
+                    not::expr(is_paired),
+                    not::expr(is_moved),
+                    not::expr(is_drift),
+                    not::expr(is_empty),
+                ]),
+            );
+
+            cb.gate(q_not_first(meta) * node_row(meta))
+        });
+
+        meta.create_gate("items", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = cur(meta, byte);
+            let is_item_start_prev = prev(meta, is_item_start);
+            let is_item_start = cur(meta, is_item_start);
+            let is_item_end = cur(meta, is_item_end);
+            let is_header_prev = prev(meta, is_header);
+            let is_header = cur(meta, is_header);
+            let classes_cur = classes.map(|column| cur(meta, column));
+            let [_, is_long_string_prev, _, is_long_list_prev] =
+                classes.map(|column| prev(meta, column));
+            let is_short_string_prev = prev(meta, classes[0]);
+            let length = cur(meta, length);
+            let counter_is_zero = counter_is_zero.expr();
+            let length_is_one = length_is_one.expr();
+            let is_first_payload = cur(meta, is_first_payload);
+            let is_key_item = cur(meta, is_key_item);
+            let is_drift_child = cur(meta, is_drift_child);
+            let is_paired = cur(meta, is_paired);
+            let is_key_leaf = cur(meta, is_key_leaf);
+            let is_drift = cur(meta, is_drift);
+            let level = cur(meta, level);
+            let update_flags = update_flags.map(|column| cur(meta, column));
+            let tags = tags.map(|column| cur(meta, column));
+            let is_container = is_container(&tags);
+            let is_child = tag(&tags, NodeTag::Child);
+            let is_nonce = tag(&tags, NodeTag::Nonce);
+            let is_balance = tag(&tags, NodeTag::Balance);
+            let is_storage_root = tag(&tags, NodeTag::StorageRoot);
+            let is_code_hash = tag(&tags, NodeTag::CodeHash);
+            let is_storage_value = tag(&tags, NodeTag::StorageValue);
+            let is_ext_child = tag(&tags, NodeTag::ExtChild);
+
+            cb.condition(is_item_start.clone(), |cb| {
+                cb.require_equal(
+                    "item_acc starts with the single byte item",
+                    cur(meta, item_acc),
+                    not::expr(is_header.clone()) * byte.clone(),
+                );
+                cb.require_equal(
+                    "item_rlc starts with the single byte item",
+                    cur(meta, item_rlc),
+                    not::expr(is_header.clone()) * byte.clone(),
+                );
+                cb.require_zero(
+                    "a child is a hash or empty",
+                    is_child.clone()
+                        * (byte.clone() - EMPTY_STRING.expr())
+                        * (byte.clone() - HASH_PREFIX.expr()),
+                );
+                cb.require_zero(
+                    "the branch value and the empty node are empty",
+                    (tag(&tags, NodeTag::BranchValue) + tag(&tags, NodeTag::EmptyNode))
+                        * (byte.clone() - EMPTY_STRING.expr()),
+                );
+                cb.require_zero(
+                    "the extension child, storage root and code hash are hashes",
+                    (is_ext_child.clone() + is_storage_root.clone() + is_code_hash.clone())
+                        * (byte.clone() - HASH_PREFIX.expr()),
+                );
+                cb.require_zero(
+                    "the drift child is a hash",
+                    is_drift_child.clone() * (byte.clone() - HASH_PREFIX.expr()),
+                );
+                cb.require_zero(
+                    "the other children of an unpaired branch are empty",
+                    and::expr([
+                        is_child.clone(),
+                        not::expr(is_paired.clone()),
+                        not::expr(is_key_item.clone()),
+                        not::expr(is_drift_child.clone()),
+                    ]) * (byte.clone() - EMPTY_STRING.expr()),
+                );
+            });
+            cb.condition(is_header.clone(), |cb| {
+                cb.require_zero("item_acc = 0 in the header", cur(meta, item_acc));
+                cb.require_zero("item_rlc = 0 in the header", cur(meta, item_rlc));
+            });
+            cb.require_equal("an item has one tag", sum::expr(tags.clone()), 1.expr());
+            cb.condition(is_item_end.clone(), |cb| {
+                cb.require_zero(
+                    "the length of a container is the number of remaining bytes of the node",
+                    is_container.clone() * (length.clone() - cur(meta, node_remaining)),
+                );
+            });
+            cb.require_equal(
+                "is_first_payload",
+                is_first_payload.clone(),
+                not::expr(is_header.clone())
+                    * (is_item_start.clone() + not::expr(is_item_start.clone()) * is_header_prev),
+            );
+            cb.condition(
+                and::expr([
+                    is_first_payload.clone(),
+                    not::expr(is_item_start.clone()),
+                    is_short_string_prev,
+                ]) * length_is_one,
+                |cb| {
+                    cb.require_equal(
+                        "a string with a single byte below 0x80 is encoded as the byte",
+                        sum::expr(classes_cur),
+                        1.expr(),
+                    );
+                },
+            );
+            cb.require_equal(
+                "is_length_check",
+                cur(meta, is_length_check),
+                is_item_start_prev
+                    * (is_long_string_prev + is_long_list_prev)
+                    * counter_is_zero.clone(),
+            );
+
+            // The items in the path of the key, which are not compared with
+            // the paired node
+            let expected_key_item = is_ext_child.clone() * not::expr(cur(meta, is_diverged))
+                + (tag(&tags, NodeTag::StorageValueHeader) + is_storage_value.clone())
+                    * is_key_leaf.clone()
+                + is_key_leaf.clone()
+                    * (is_nonce.clone() * update_flags[UPDATE_NONCE].clone()
+                        + is_balance.clone() * update_flags[UPDATE_BALANCE].clone()
+                        + is_code_hash.clone() * update_flags[UPDATE_CODE_HASH].clone()
+                        + is_storage_root.clone() * update_flags[UPDATE_STORAGE].clone());
+            cb.require_zero(
+                "the key items are the ones in the path of the key",
+                not::expr(is_child.clone())
+                    * (is_key_item.clone() - not::expr(is_drift.clone()) * expected_key_item),
+            );
+            cb.require_zero(
+                "the drift child is a child",
+                is_drift_child.clone() * not::expr(is_child.clone()),
+            );
+            cb.require_zero(
+                "the drift child is not in the path of the key",
+                is_drift_child.clone() * is_key_item.clone(),
+            );
+            cb.require_zero(
+                "only unpaired branches have a drift child",
+                is_drift_child.clone() * is_paired.clone(),
+            );
+            cb.require_equal(
+                "is_drift_child_end",
+                cur(meta, is_drift_child_end),
+                is_drift_child * is_item_end.clone(),
+            );
+            cb.require_equal(
+                "the hashes in the path of the key are references to the next node",
+                cur(meta, is_ref),
+                is_key_item.clone()
+                    * not::expr(is_header.clone())
+                    * (is_child + is_ext_child + is_storage_root.clone()),
+            );
+            cb.require_equal(
+                "is_value_end",
+                cur(meta, is_value_end),
+                is_item_end.clone()
+                    * is_key_item.clone()
+                    * (is_nonce.clone()
+                        + is_balance.clone()
+                        + is_code_hash.clone()
+                        + is_storage_value),
+            );
+            cb.require_equal(
+                "is_default_check",
+                cur(meta, is_default_check),
+                and::expr([
+                    is_item_end,
+                    not::expr(is_key_item),
+                    is_key_leaf,
+                    not::expr(level),
+                    not::expr(is_paired),
+                ]) * (is_nonce + is_balance + is_storage_root + is_code_hash),
+            );
+
+            cb.gate(q_not_first(meta) * node_row(meta))
+        });
+
+        meta.create_gate("item transitions", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte_value = cur(meta, byte);
+            let node_start = cur(meta, is_node_start);
+            let in_node = not::expr(node_start.clone());
+            let item_start = cur(meta, is_item_start);
+            let header = cur(meta, is_header);
+            let key_item = cur(meta, is_key_item);
+            let drift_child = cur(meta, is_drift_child);
+            let tag_exprs = tags.map(|column| cur(meta, column));
+            let is_container = is_container(&tag_exprs);
+            let is_child = tag(&tag_exprs, NodeTag::Child);
+            let is_path = tag(&tag_exprs, NodeTag::Path);
+            let keccak_input = challenges.keccak_input();
+
+            cb.require_equal(
+                "an item starts at the start of the node or after the end of an item",
+                item_start.clone(),
+                node_start.clone() + in_node.clone() * prev(meta, is_item_end),
+            );
+            cb.require_equal(
+                "item_index increases by 1 at every item",
+                cur(meta, item_index),
+                in_node.clone() * (prev(meta, item_index) + item_start.clone()),
+            );
+
+            cb.condition(not::expr(item_start.clone()), |cb| {
+                for column in tags.into_iter().chain([is_key_item, is_drift_child]) {
+                    cb.require_equal(
+                        "the item values are the same for all the bytes of an item",
+                        cur(meta, column),
+                        prev(meta, column),
+                    );
+                }
+            });
+            cb.condition(
+                not::expr(item_start.clone()) * not::expr(header.clone()),
+                |cb| {
+                    cb.require_equal(
+                        "item_acc accumulates the payload bytes",
+                        cur(meta, item_acc),
+                        prev(meta, item_acc) * 256.expr() + byte_value.clone(),
+                    );
+                    cb.require_equal(
+                        "item_rlc accumulates the payload bytes",
+                        cur(meta, item_rlc),
+                        prev(meta, item_rlc) * challenges.evm_word() + byte_value.clone(),
+                    );
+                },
+            );
+
+            cb.require_equal(
+                "child_count counts the children that are not empty",
+                cur(meta, child_count),
+                in_node.clone() * prev(meta, child_count)
+                    + is_child.clone() * item_start.clone() * not::expr(length_is_zero.expr()),
+            );
+            cb.require_equal(
+                "key_child_count counts the children in the path of the key",
+                cur(meta, key_child_count),
+                in_node.clone() * prev(meta, key_child_count)
+                    + is_child.clone() * key_item.clone() * item_start.clone(),
+            );
+            cb.require_equal(
+                "drift_child_count counts the drift children",
+                cur(meta, drift_child_count),
+                in_node.clone() * prev(meta, drift_child_count) + drift_child * item_start.clone(),
+            );
+            cb.require_equal(
+                "ref_count counts the bytes of the references",
+                cur(meta, ref_count),
+                in_node.clone() * prev(meta, ref_count) + cur(meta, is_ref),
+            );
+
+            // The bytes of the items that are not in the path of the key,
+            // which are the same in the paired node of the other side
+            let is_sibling = not::expr(key_item) * not::expr(is_container);
+            let sibling_rlc_prev = in_node.clone() * prev(meta, sibling_rlc);
+            cb.require_equal(
+                "sibling_rlc accumulates the bytes of the sibling items",
+                cur(meta, sibling_rlc),
+                sibling_rlc_prev.clone()
+                    + is_sibling.clone()
+                        * (sibling_rlc_prev * (keccak_input.clone() - 1.expr())
+                            + byte_value.clone()),
+            );
+            cb.require_equal(
+                "sibling_len counts the bytes of the sibling items",
+                cur(meta, sibling_len),
+                in_node.clone() * prev(meta, sibling_len) + is_sibling,
+            );
+
+            // The bytes of the items after the path, which are the same in a
+            // moved node and its drift node
+            let is_rest = 1.expr()
+                - tag(&tag_exprs, NodeTag::ListHeader)
+                - is_path
+                - tag(&tag_exprs, NodeTag::EmptyNode);
+            let rest_rlc_prev = in_node.clone() * prev(meta, rest_rlc);
+            cb.require_equal(
+                "rest_rlc accumulates the bytes of the items after the path",
+                cur(meta, rest_rlc),
+                rest_rlc_prev.clone()
+                    + is_rest.clone()
+                        * (rest_rlc_prev * (keccak_input.clone() - 1.expr()) + byte_value.clone()),
+            );
+            cb.require_equal(
+                "rest_len counts the bytes of the items after the path",
+                cur(meta, rest_len),
+                in_node.clone() * prev(meta, rest_len) + is_rest,
+            );
+
+            // The nibbles of the path after the divergence from the key, which
+            // are the path of the drift node
+            let is_tail_hi = cur(meta, is_tail_hi);
+            let is_tail_lo = cur(meta, is_tail_lo);
+            let tail_rlc_prev = in_node.clone() * prev(meta, tail_rlc);
+            let tail_rlc_hi = tail_rlc_prev.clone()
+                + is_tail_hi.clone()
+                    * (tail_rlc_prev * (keccak_input.clone() - 1.expr()) + cur(meta, nibble_hi));
+            cb.require_equal(
+                "tail_rlc accumulates the nibbles of the tail",
+                cur(meta, tail_rlc),
+                tail_rlc_hi.clone()
+                    + is_tail_lo.clone()
+                        * (tail_rlc_hi * (keccak_input - 1.expr()) + cur(meta, nibble_lo)),
+            );
+            cb.require_equal(
+                "tail_len counts the nibbles of the tail",
+                cur(meta, tail_len),
+                in_node * prev(meta, tail_len) + is_tail_hi + is_tail_lo,
+            );
+
+            cb.gate(q_not_first(meta) * node_row(meta))
+        });
+
+        meta.create_gate("hash references", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte_prev = prev(meta, byte);
+            let byte = cur(meta, byte);
+            let is_ref_prev = prev(meta, is_ref);
+            let is_ref = cur(meta, is_ref);
+            let ref_index_prev = prev(meta, ref_index);
+            let ref_index = cur(meta, ref_index);
+            let ref_rlc_prev = prev(meta, ref_rlc);
+            let ref_rlc = cur(meta, ref_rlc);
+            let is_root_start = cur(meta, is_root_start);
+            let is_node_start = cur(meta, is_node_start);
+
+            // A hash reference never continues into the root rows.
+            let is_ref_continue = is_ref_prev.clone() * not::expr(is_root_start.clone());
+
+            cb.condition(is_ref.clone() * is_ref_continue.clone(), |cb| {
+                cb.require_equal(
+                    "ref_index increases by 1",
+                    ref_index.clone(),
+                    ref_index_prev.clone() + 1.expr(),
+                );
+                cb.require_equal(
+                    "ref_rlc accumulates the referenced hash",
+                    ref_rlc.clone(),
+                    ref_rlc_prev.clone() * challenges.evm_word() + byte.clone(),
+                );
+            });
+            cb.condition(is_ref.clone() * (1.expr() - is_ref_continue), |cb| {
+                cb.require_equal("ref_index = 1", ref_index.clone(), 1.expr());
+                cb.require_equal("ref_rlc = byte", ref_rlc.clone(), byte);
+                cb.require_zero(
+                    "a hash reference in a node is preceded by the 32 bytes string prefix",
+                    (1.expr() - is_root_start.clone()) * (byte_prev - HASH_PREFIX.expr()),
+                );
+            });
+            cb.condition(not::expr(is_ref.clone()), |cb| {
+                cb.require_zero("ref_index = 0", ref_index);
+                cb.require_equal(
+                    "ref_rlc is kept until the end of the node",
+                    ref_rlc,
+                    (1.expr() - is_node_start) * ref_rlc_prev,
+                );
+                cb.require_zero(
+                    "hash references have 32 bytes",
+                    is_ref_prev * (ref_index_prev - 32.expr()),
+                );
+            });
+
+            cb.gate(and::expr([
+                q_not_first(meta),
+                not::expr(cur(meta, is_padding)),
+            ]))
+        });
+
+        meta.create_gate("paths", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let tag_exprs = tags.map(|column| cur(meta, column));
+            let is_path = tag(&tag_exprs, NodeTag::Path);
+            let is_child = tag(&tag_exprs, NodeTag::Child);
+            let in_node = not::expr(cur(meta, is_node_start));
+            let first_payload = cur(meta, is_first_payload);
+            let path_payload = is_path.clone() * not::expr(cur(meta, is_header));
+            let odd = cur(meta, is_odd_path);
+            let [is_leaf, is_extension] =
+                [NodeKind::Leaf, NodeKind::Extension].map(|kind| cur(meta, kinds[kind as usize]));
+            let hi = cur(meta, nibble_hi);
+            let lo = cur(meta, nibble_lo);
+            let mismatch_hi = cur(meta, is_mismatch_hi);
+            let mismatch_lo = cur(meta, is_mismatch_lo);
+            let mismatch_key_nibble = cur(meta, mismatch_nibble);
+            let drift = cur(meta, is_drift);
+            let diverged_prev = in_node.clone() * prev(meta, is_diverged);
+            let nibbles = cur(meta, nibble_depth);
+            let nibbles_prev = prev(meta, nibble_depth);
+            // Payload bytes of the path with a lo nibble
+            let has_lo = not::expr(first_payload.clone()) + first_payload.clone() * odd.clone();
+
+            cb.condition(is_path * first_payload.clone(), |cb| {
+                cb.require_equal(
+                    "the hex-prefix flag of the path encodes the node kind and its parity",
+                    hi.clone(),
+                    2.expr() * is_leaf + odd.clone(),
+                );
+                cb.require_zero(
+                    "the flag of an even path is followed by a zero nibble",
+                    not::expr(odd.clone()) * lo.clone(),
+                );
+            });
+            cb.require_zero(
+                "the path of an extension is not empty",
+                and::expr([
+                    is_extension,
+                    path_payload.clone(),
+                    first_payload.clone(),
+                    not::expr(odd.clone()),
+                    cur(meta, is_item_end),
+                ]),
+            );
+            cb.condition(in_node.clone(), |cb| {
+                cb.require_equal(
+                    "nibble_depth counts the nibbles of the path",
+                    nibbles.clone(),
+                    nibbles_prev.clone()
+                        + path_payload.clone()
+                            * select::expr(first_payload.clone(), odd.clone(), 2.expr()),
+                );
+            });
+            cb.require_equal(
+                "the path diverges from the key at the first mismatch",
+                cur(meta, is_diverged),
+                diverged_prev.clone() + mismatch_hi.clone() + mismatch_lo.clone(),
+            );
+            cb.require_zero(
+                "a hi nibble mismatch is in the path",
+                mismatch_hi.clone()
+                    * (1.expr() - path_payload.clone() * not::expr(first_payload.clone())),
+            );
+            cb.require_zero(
+                "a lo nibble mismatch is in the path",
+                mismatch_lo.clone() * (1.expr() - path_payload.clone() * has_lo.clone()),
+            );
+            cb.require_zero(
+                "the path of a drift node is not compared with the key",
+                drift.clone() * (mismatch_hi.clone() + mismatch_lo.clone()),
+            );
+            cb.require_zero(
+                "the nibble of a mismatch is not the key nibble",
+                (mismatch_hi.clone() + mismatch_lo.clone())
+                    * ((mismatch_hi.clone() * hi.clone() + mismatch_lo.clone() * lo.clone()
+                        - mismatch_key_nibble.clone())
+                        * cur(meta, mismatch_inv)
+                        - 1.expr()),
+            );
+
+            let is_key_child = is_child.clone() * cur(meta, is_key_item) * cur(meta, is_item_start);
+            cb.require_equal(
+                "the hi nibbles are compared with the key until the path diverges",
+                cur(meta, check_hi),
+                and::expr([
+                    path_payload.clone(),
+                    not::expr(first_payload.clone()),
+                    not::expr(diverged_prev.clone()),
+                    not::expr(drift.clone()),
+                ]),
+            );
+            cb.require_equal(
+                "the lo nibbles and the key children are compared with the key",
+                cur(meta, check_lo),
+                path_payload.clone()
+                    * has_lo.clone()
+                    * (1.expr() - diverged_prev.clone() - mismatch_hi.clone())
+                    * not::expr(drift.clone())
+                    + is_key_child,
+            );
+            cb.condition(cur(meta, check_lo), |cb| {
+                cb.require_equal(
+                    "key_position is the position of the nibble in the key",
+                    cur(meta, key_position),
+                    is_child.clone() * cur(meta, depth)
+                        + not::expr(is_child.clone()) * (nibbles.clone() - 1.expr()),
+                );
+                cb.require_equal(
+                    "key_nibble is the child position or the key nibble of the path",
+                    cur(meta, key_nibble),
+                    is_child.clone() * (cur(meta, item_index) - 1.expr())
+                        + not::expr(is_child)
+                            * (lo.clone()
+                                + mismatch_lo.clone() * (mismatch_key_nibble.clone() - lo.clone())),
+                );
+            });
+            cb.condition(mismatch_hi.clone(), |cb| {
+                cb.require_equal(
+                    "the hi nibble mismatch is at the previous nibble depth",
+                    cur(meta, mismatch_depth),
+                    nibbles_prev.clone(),
+                );
+                cb.require_equal(
+                    "mismatch_path_nibble is the hi nibble",
+                    cur(meta, mismatch_path_nibble),
+                    hi.clone(),
+                );
+            });
+            cb.condition(mismatch_lo.clone(), |cb| {
+                cb.require_equal(
+                    "the lo nibble mismatch is at the last nibble depth",
+                    cur(meta, mismatch_depth),
+                    nibbles.clone() - 1.expr(),
+                );
+                cb.require_equal(
+                    "mismatch_path_nibble is the lo nibble",
+                    cur(meta, mismatch_path_nibble),
+                    lo.clone(),
+                );
+            });
+            cb.condition(
+                in_node * (1.expr() - mismatch_hi.clone() - mismatch_lo),
+                |cb| {
+                    for column in [mismatch_depth, mismatch_path_nibble] {
+                        cb.require_equal(
+                            "the mismatch is kept until the end of the node",
+                            cur(meta, column),
+                            prev(meta, column),
+                        );
+                    }
+                },
+            );
+            cb.require_equal(
+                "the hi nibbles after the divergence are in the tail",
+                cur(meta, is_tail_hi),
+                path_payload.clone()
+                    * not::expr(first_payload)
+                    * (drift.clone() + diverged_prev.clone()),
+            );
+            cb.require_equal(
+                "the lo nibbles after the divergence are in the tail",
+                cur(meta, is_tail_lo),
+                path_payload * has_lo * (drift + diverged_prev + mismatch_hi),
+            );
+
+            cb.gate(q_not_first(meta) * node_row(meta))
+        });
+
+        meta.create_gate("values", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let tag_exprs = tags.map(|column| cur(meta, column));
+            let is_nonce = tag(&tag_exprs, NodeTag::Nonce);
+            let is_balance = tag(&tag_exprs, NodeTag::Balance);
+            let is_storage_root = tag(&tag_exprs, NodeTag::StorageRoot);
+            let is_code_hash = tag(&tag_exprs, NodeTag::CodeHash);
+            let item_rlc = cur(meta, item_rlc);
+            let value = select::expr(
+                cur(meta, is_new_side),
+                cur(meta, new_value),
+                cur(meta, old_value),
+            );
+            let empty_code_hash = empty_code_hash_rlc(&challenges);
+            let empty_root = empty_root_rlc(&challenges);
+            let is_value_end = cur(meta, is_value_end);
+
+            // The nonce is encoded as a field element, while the other values
+            // are encoded as an RLC.
+            cb.condition(is_value_end.clone(), |cb| {
+                cb.require_zero(
+                    "the leaf of the key contains the value of the mpt table",
+                    not::expr(is_code_hash.clone())
+                        * (select::expr(is_nonce.clone(), cur(meta, item_acc), item_rlc.clone())
+                            - value.clone()),
+                );
+            });
+            cb.condition(is_value_end * is_code_hash.clone(), |cb| {
+                // A code hash of 0 in the mpt table is the empty code hash.
+                cb.require_zero(
+                    "the code hash is the value of the mpt table, or the empty code hash",
+                    (item_rlc.clone() - value.clone())
+                        * (item_rlc.clone() - empty_code_hash.clone()),
+                );
+                cb.require_zero(
+                    "the code hash is the value of the mpt table, or the value is 0",
+                    (item_rlc.clone() - value.clone()) * value.clone(),
+                );
+            });
+            cb.condition(cur(meta, is_side_end), |cb| {
+                cb.require_zero(
+                    "the value is 0 when the key is not in the trie",
+                    not::expr(cur(meta, key_exists)) * value,
+                );
+                cb.require_zero(
+                    "the key is not in the trie in non-existence proofs",
+                    cur(meta, update_flags[UPDATE_NON_EXISTING]) * cur(meta, key_exists),
+                );
+            });
+            cb.condition(cur(meta, is_default_check), |cb| {
+                cb.require_zero(
+                    "the nonce and balance of a new account are 0",
+                    (is_nonce + is_balance) * cur(meta, length),
+                );
+                cb.require_zero(
+                    "the storage root and code hash of a new account are empty",
+                    is_storage_root * (item_rlc.clone() - empty_root)
+                        + is_code_hash * (item_rlc - empty_code_hash),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()) * node_row(meta))
+        });
+
+        meta.lookup_any("byte and its nibbles", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            [byte, nibble_hi, nibble_lo]
+                .into_iter()
+                .zip(nibble_table)
+                .map(|(column, table_column)| {
+                    (
+                        q_enable.clone() * cur(meta, column),
+                        meta.query_fixed(table_column, Rotation::cur()),
+                    )
+                })
+                .collect()
+        });
+
+        meta.lookup_any("update flags of the proof type", |meta| {
+            let enable = meta.query_fixed(q_enable, Rotation::cur()) * cur(meta, is_update_start);
+            [1.expr(), cur(meta, proof_type)]
+                .into_iter()
+                .chain(update_flags.map(|column| cur(meta, column)))
+                .zip(
+                    [q_proof_type_table]
+                        .into_iter()
+                        .chain(proof_type_table)
+                        .map(|column| meta.query_fixed(column, Rotation::cur())),
+                )
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+        });
+
+        meta.lookup_any("node item transitions", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                node_row(meta),
+                cur(meta, is_item_start),
+            ]);
+            let tags_prev = tags.map(|column| prev(meta, column));
+            let tags = tags.map(|column| cur(meta, column));
+            let kinds = kinds.map(|column| cur(meta, column));
+            [
+                1.expr(),
+                not::expr(cur(meta, is_node_start)) * tag_value(&tags_prev),
+                tag_value(&tags),
+                cur(meta, item_index),
+                kind_value(&kinds),
+                cur(meta, level),
+                rlp.class(meta),
+            ]
+            .into_iter()
+            .zip(
+                [q_transition_table]
+                    .into_iter()
+                    .chain(transition_table)
+                    .map(|column| meta.query_fixed(column, Rotation::cur())),
+            )
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        // Range checks of the canonical RLP encoding and the values
+        #[allow(clippy::type_complexity)]
+        let range_checks: [(
+            &str,
+            Box<dyn Fn(&mut VirtualCells<'_, F>) -> Expression<F> + '_>,
+        ); 6] = [
+            (
+                "the first length byte of a long item is not 0",
+                Box::new(|meta| {
+                    node_row(meta)
+                        * prev(meta, is_item_start)
+                        * (prev(meta, classes[1]) + prev(meta, classes[3]))
+                        * (cur(meta, byte) - 1.expr())
+                }),
+            ),
+            (
+                "the length of a long item is at least 56",
+                Box::new(|meta| cur(meta, is_length_check) * (cur(meta, byte) - 56.expr())),
+            ),
+            (
+                "the account values and storage value have no leading zeros",
+                Box::new(|meta| {
+                    let tags = tags.map(|column| cur(meta, column));
+                    node_row(meta)
+                        * cur(meta, is_first_payload)
+                        * (tag(&tags, NodeTag::Nonce)
+                            + tag(&tags, NodeTag::Balance)
+                            + tag(&tags, NodeTag::StorageValue))
+                        * (cur(meta, byte) - 1.expr())
+                }),
+            ),
+            (
+                "the nonce has 8 bytes, and the balance and storage value 32",
+                Box::new(|meta| {
+                    let tags = tags.map(|column| cur(meta, column));
+                    let is_nonce = tag(&tags, NodeTag::Nonce);
+                    cur(meta, is_item_end)
+                        * (is_nonce.clone()
+                            + tag(&tags, NodeTag::Balance)
+                            + tag(&tags, NodeTag::StorageValue))
+                        * (select::expr(is_nonce, 8.expr(), 32.expr()) - cur(meta, length))
+                }),
+            ),
+            (
+                "the storage value is not empty",
+                Box::new(|meta| {
+                    cur(meta, tags[NodeTag::StorageValue as usize - 1])
+                        * cur(meta, is_item_end)
+                        * (cur(meta, length) - 1.expr())
+                }),
+            ),
+            (
+                "a branch has at least 2 children",
+                Box::new(|meta| {
+                    cur(meta, is_node_end)
+                        * cur(meta, kinds[NodeKind::Branch as usize])
+                        * (cur(meta, child_count) - 2.expr())
+                }),
+            ),
+        ];
+        for (name, range_check) in range_checks {
+            meta.lookup_any(name, |meta| {
+                vec![(
+                    meta.query_fixed(q_enable, Rotation::cur()) * range_check(meta),
+                    meta.query_fixed(byte_table.byte, Rotation::cur()),
+                )]
+            });
+        }
+
+        meta.lookup_any(
+            "keccak256_table_lookup(node_rlc, node_len, node_hash)",
+            |meta| {
+                let enable = and::expr([
+                    meta.query_fixed(q_enable, Rotation::cur()),
+                    cur(meta, is_node_end),
+                ]);
+
+                let mut constraints = vec![(enable.clone(), cur(meta, keccak_table.is_enabled))];
+                for (circuit_column, table_column) in
+                    keccak_table.match_columns(node_rlc, node_len, node_hash)
+                {
+                    constraints.push((
+                        enable.clone() * cur(meta, circuit_column),
+                        cur(meta, table_column),
+                    ))
+                }
+                constraints
+            },
+        );
+
+        meta.lookup_any("keccak256_table_lookup(key, key_len, key_hash)", |meta| {
+            let [_, ah, _, sh] = key_flags.map(|column| cur(meta, column));
+            let [_, ah_prev, _, sh_prev] = key_flags.map(|column| prev(meta, column));
+            let enable = q_not_first(meta)
+                * (ah_prev.clone() * not::expr(ah) + sh_prev.clone() * not::expr(sh));
+
+            vec![
+                (enable.clone(), cur(meta, keccak_table.is_enabled)),
+                (
+                    enable.clone() * prev(meta, key_input_rlc),
+                    cur(meta, keccak_table.input_rlc),
+                ),
+                (
+                    enable.clone() * (20.expr() * ah_prev + 32.expr() * sh_prev),
+                    cur(meta, keccak_table.input_len),
+                ),
+                (
+                    enable * prev(meta, key_rlc),
+                    cur(meta, keccak_table.output_rlc),
+                ),
+            ]
+        });
+
+        // The nibbles of the key hashes, by update, trie level and position
+        let key_nibble_table = |meta: &mut VirtualCells<'_, F>| {
+            let is_key_hash =
+                cur(meta, key_flags[KEY_ADDRESS_HASH]) + cur(meta, key_flags[KEY_STORAGE_KEY_HASH]);
+            [
+                cur(meta, update_index),
+                cur(meta, key_flags[KEY_STORAGE_KEY_HASH]),
+                cur(meta, key_index),
+                cur(meta, byte),
+            ]
+            .map(|value| is_key_hash.clone() * value)
+        };
+        meta.lookup_any("hi nibbles of the paths follow the key", |meta| {
+            let enable = meta.query_fixed(q_enable, Rotation::cur()) * cur(meta, check_hi);
+            let hi = cur(meta, nibble_hi);
+            let input = [
+                cur(meta, update_index),
+                cur(meta, level),
+                prev(meta, nibble_depth),
+                hi.clone() + cur(meta, is_mismatch_hi) * (cur(meta, mismatch_nibble) - hi),
+            ];
+            input
+                .into_iter()
+                .zip(key_nibble_table(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+        });
+        meta.lookup_any(
+            "lo nibbles of the paths and branch children follow the key",
+            |meta| {
+                let enable = meta.query_fixed(q_enable, Rotation::cur()) * cur(meta, check_lo);
+                let input = [
+                    cur(meta, update_index),
+                    cur(meta, level),
+                    cur(meta, key_position),
+                    cur(meta, key_nibble),
+                ];
+                input
+                    .into_iter()
+                    .zip(key_nibble_table(meta))
+                    .map(|(input, table)| (enable.clone() * input, table))
+                    .collect()
+            },
+        );
+
+        meta.lookup_any("paired nodes have the same siblings", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            let is_paired_end = cur(meta, is_paired_end);
+            let is_new_side = cur(meta, is_new_side);
+            let kinds = kinds.map(|column| cur(meta, column));
+            let values = [
+                cur(meta, update_index),
+                cur(meta, level),
+                cur(meta, node_index),
+                cur(meta, depth),
+                kind_value(&kinds),
+                cur(meta, sibling_rlc),
+                cur(meta, sibling_len),
+            ];
+            [1.expr(), not::expr(is_new_side.clone())]
+                .into_iter()
+                .chain(values.clone())
+                .zip([1.expr(), is_new_side].into_iter().chain(values))
+                .map(|(input, table)| {
+                    (
+                        q_enable.clone() * is_paired_end.clone() * input,
+                        is_paired_end.clone() * table,
+                    )
+                })
+                .collect()
+        });
+
+        // The drift children of the unpaired branches, and the drift nodes or
+        // directly moved extensions that they reference
+        let drift_child = |meta: &mut VirtualCells<'_, F>| {
+            let is_drift_child_end = cur(meta, is_drift_child_end);
+            [
+                1.expr(),
+                cur(meta, update_index),
+                cur(meta, is_new_side),
+                cur(meta, level),
+                cur(meta, depth),
+                cur(meta, item_index) - 1.expr(),
+                cur(meta, item_rlc),
+            ]
+            .map(|value| is_drift_child_end.clone() * value)
+        };
+        let drift_reference = |meta: &mut VirtualCells<'_, F>| {
+            let is_drift_end = cur(meta, is_drift_end);
+            let is_moved_direct_end = cur(meta, is_moved_direct) * cur(meta, is_node_end);
+            let drift = [
+                1.expr(),
+                cur(meta, update_index),
+                cur(meta, is_new_side),
+                cur(meta, level),
+                cur(meta, depth) - 1.expr(),
+                cur(meta, drift_position),
+                cur(meta, node_hash),
+            ];
+            let moved_direct = [
+                1.expr(),
+                cur(meta, update_index),
+                not::expr(cur(meta, is_new_side)),
+                cur(meta, level),
+                cur(meta, mismatch_depth),
+                cur(meta, mismatch_path_nibble),
+                cur(meta, item_rlc),
+            ];
+            let mut values = drift.map(|value| is_drift_end.clone() * value);
+            for (value, moved_direct) in values.iter_mut().zip(moved_direct) {
+                *value = value.clone() + is_moved_direct_end.clone() * moved_direct;
+            }
+            values
+        };
+        meta.lookup_any("drift children reference a drift node", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            drift_child(meta)
+                .into_iter()
+                .zip(drift_reference(meta))
+                .map(|(input, table)| (q_enable.clone() * input, table))
+                .collect()
+        });
+        meta.lookup_any("drift nodes are referenced by a drift child", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            drift_reference(meta)
+                .into_iter()
+                .zip(drift_child(meta))
+                .map(|(input, table)| (q_enable.clone() * input, table))
+                .collect()
+        });
+
+        // The moved nodes that are not directly referenced, and their drift
+        // nodes on the other side
+        let moved_node = |meta: &mut VirtualCells<'_, F>| {
+            let enable =
+                cur(meta, is_node_end) * (cur(meta, is_moved) - cur(meta, is_moved_direct));
+            let kinds = kinds.map(|column| cur(meta, column));
+            [
+                1.expr(),
+                cur(meta, update_index),
+                not::expr(cur(meta, is_new_side)),
+                cur(meta, level),
+                cur(meta, mismatch_depth),
+                cur(meta, mismatch_path_nibble),
+                kind_value(&kinds),
+                cur(meta, tail_rlc),
+                cur(meta, tail_len),
+                cur(meta, rest_rlc),
+                cur(meta, rest_len),
+            ]
+            .map(|value| enable.clone() * value)
+        };
+        let drift_node = |meta: &mut VirtualCells<'_, F>| {
+            let enable = cur(meta, is_drift_end);
+            let kinds = kinds.map(|column| cur(meta, column));
+            [
+                1.expr(),
+                cur(meta, update_index),
+                cur(meta, is_new_side),
+                cur(meta, level),
+                cur(meta, depth) - 1.expr(),
+                cur(meta, drift_position),
+                kind_value(&kinds),
+                cur(meta, tail_rlc),
+                cur(meta, tail_len),
+                cur(meta, rest_rlc),
+                cur(meta, rest_len),
+            ]
+            .map(|value| enable.clone() * value)
+        };
+        meta.lookup_any("moved nodes have a drift node", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            moved_node(meta)
+                .into_iter()
+                .zip(drift_node(meta))
+                .map(|(input, table)| (q_enable.clone() * input, table))
+                .collect()
+        });
+        meta.lookup_any("drift nodes are the tail of a moved node", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            drift_node(meta)
+                .into_iter()
+                .zip(moved_node(meta))
+                .map(|(input, table)| (q_enable.clone() * input, table))
+                .collect()
+        });
+
+        meta.lookup_any(
+            "unpaired nodes are inserted or removed by the update",
+            |meta| {
+                let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+                let is_unpaired_end = cur(meta, is_unpaired_end);
+                let is_side_end = cur(meta, is_side_end);
+                let is_new_side = cur(meta, is_new_side);
+                let input = [
+                    1.expr(),
+                    cur(meta, update_index),
+                    not::expr(is_new_side.clone()),
+                ];
+                let table = [
+                    cur(meta, is_paired)
+                        + cur(meta, is_moved)
+                        + cur(meta, kinds[NodeKind::Empty as usize]),
+                    cur(meta, update_index),
+                    is_new_side,
+                ];
+                input
+                    .into_iter()
+                    .zip(table)
+                    .map(|(input, table)| {
+                        (
+                            q_enable.clone() * is_unpaired_end.clone() * input,
+                            is_side_end.clone() * table,
+                        )
+                    })
+                    .collect()
+            },
+        );
+
+        Self {
+            q_enable,
+            q_first,
+            q_last,
+            byte_table,
+            nibble_table,
+            q_transition_table,
+            transition_table,
+            q_proof_type_table,
+            proof_type_table,
+            byte,
+            nibble_hi,
+            nibble_lo,
+            is_padding,
+            is_update_start,
+            is_new_side,
+            update_index,
+            is_first_update,
+            update_flags,
+            key_flags,
+            key_index,
+            key_acc,
+            is_key_lo,
+            is_root,
+            is_root_start,
+            is_root_hi,
+            root_hi,
+            root_lo,
+            initial_root,
+            final_root,
+            pi_randomness,
+            root_pi_pow,
+            root_pi_rlc,
+            initial_root_rlc,
+            final_root_rlc,
+            initial_root_word,
+            final_root_word,
+            is_ref,
+            ref_index,
+            is_node_start,
+            is_node_end,
+            is_last_node,
+            is_drift,
+            level,
+            depth,
+            node_index,
+            is_paired,
+            kinds,
+            is_key_leaf,
+            is_odd_path,
+            is_moved,
+            is_moved_direct,
+            drift_position,
+            key_exists,
+            node_len,
+            node_remaining,
+            rlp,
+            item_acc,
+            tags,
+            item_index,
+            is_first_payload,
+            is_length_check,
+            child_count,
+            key_child_count,
+            drift_child_count,
+            is_key_item,
+            is_drift_child,
+            is_drift_child_end,
+            ref_count,
+            nibble_depth,
+            is_diverged,
+            is_mismatch_hi,
+            is_mismatch_lo,
+            mismatch_nibble,
+            mismatch_inv,
+            check_hi,
+            check_lo,
+            key_position,
+            key_nibble,
+            mismatch_depth,
+            mismatch_path_nibble,
+            is_tail_hi,
+            is_tail_lo,
+            tail_len,
+            is_value_end,
+            is_default_check,
+            sibling_len,
+            rest_len,
+            is_paired_end,
+            is_drift_end,
+            is_side_end,
+            is_unpaired_end,
+            length_is_one,
+            key_value_rlc,
+            key_input_rlc,
+            key_rlc,
+            node_rlc,
+            node_hash,
+            ref_rlc,
+            item_rlc,
+            sibling_rlc,
+            tail_rlc,
+            rest_rlc,
+            instance,
+            mpt_table,
+            keccak_table,
+        }
+    }
+}
+
+impl<F: Field> MptCircuitConfig<F> {
+    /// Assign the MPT updates and the MptTable.  When `n_rows` is 0, the
+    /// number of rows is calculated from the updates.  The RLCs of the roots
+    /// are computed with `pi_randomness`, the randomness of the PublicInputs
+    /// circuit.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        updates: &MptUpdates,
+        n_rows: usize,
+        pi_randomness: F,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<MptRootCells<F>, Error> {
+        let mut rows: Vec<(&MptUpdate, MptCircuitRow)> = Vec::new();
+        for update in updates.updates() {
+            let update_rows = update_rows(update).map_err(|err| {
+                error!("invalid proof of the MPT update {:?}: {}", update.key, err);
+                Error::Synthesis
+            })?;
+            rows.extend(update_rows.into_iter().map(|row| (update, row)));
+        }
+        let n_rows = if n_rows == 0 { rows.len() + 1 } else { n_rows };
+        if rows.len() >= n_rows {
+            error!(
+                "MPT updates require {} rows, but the circuit has {}",
+                rows.len() + 1,
+                n_rows
+            );
+            return Err(Error::Synthesis);
+        }
+        let initial_root = split_root::<F>(updates.old_root());
+        let final_root = split_root::<F>(updates.new_root());
+        let [initial_root_rlc, final_root_rlc] = [updates.old_root(), updates.new_root()]
+            .map(|root| rlc::value(&root.to_be_bytes(), pi_randomness));
+        let [initial_root_word, final_root_word] =
+            [updates.old_root(), updates.new_root()].map(|root| {
+                challenges
+                    .evm_word()
+                    .map(|evm_word| rlc::value(&root.to_le_bytes(), evm_word))
+            });
+
+        self.byte_table.load(layouter)?;
+        layouter.assign_region(
+            || "mpt circuit fixed tables",
+            |mut region| {
+                for byte in 0..=u8::MAX {
+                    for (column, value) in
+                        self.nibble_table
+                            .into_iter()
+                            .zip([byte, byte >> 4, byte & 0xf])
+                    {
+                        region.assign_fixed(
+                            || "nibble table",
+                            column,
+                            byte as usize,
+                            || Value::known(F::from(value as u64)),
+                        )?;
+                    }
+                }
+                for (offset, (tag_prev, tag, item_index, kind, level, class)) in
+                    node_transitions().into_iter().enumerate()
+                {
+                    for (column, value) in [self.q_transition_table]
+                        .into_iter()
+                        .chain(self.transition_table)
+                        .zip([
+                            1,
+                            tag_prev as u64,
+                            tag as u64,
+                            item_index as u64,
+                            kind as u64,
+                            level as u64,
+                            class as u64,
+                        ])
+                    {
+                        region.assign_fixed(
+                            || "node transitions table",
+                            column,
+                            offset,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                for (offset, (proof_type, flags)) in PROOF_TYPES.iter().enumerate() {
+                    for (column, value) in [self.q_proof_type_table]
+                        .into_iter()
+                        .chain(self.proof_type_table)
+                        .zip(
+                            [1, *proof_type as u64]
+                                .into_iter()
+                                .chain(flags.map(|flag| flag as u64)),
+                        )
+                    {
+                        region.assign_fixed(
+                            || "proof type table",
+                            column,
+                            offset,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        let (root_instance_cells, root_cells, root_word_cells) = layouter.assign_region(
+            || "mpt circuit",
+            |mut region| {
+                let length_is_one = IsZeroChip::construct(self.length_is_one.clone());
+                let keccak_input = challenges.keccak_input();
+                let evm_word = challenges.evm_word();
+                let zero = Value::known(F::zero());
+
+                let mut prev = MptCircuitRow::default();
+                let mut update_index = 0;
+                let mut key_acc = F::zero();
+                let mut root = [0u128; 2];
+                let mut root_pi_pow = F::zero();
+                let mut root_pi_rlc = F::zero();
+                let mut item_acc = F::zero();
+                // Values in the second phase
+                let mut key_value_rlc = zero;
+                let mut key_input_rlc = zero;
+                let mut key_rlc = zero;
+                let mut node_rlc = zero;
+                let mut ref_rlc = zero;
+                let mut item_rlc = zero;
+                let mut sibling_rlc = zero;
+                let mut tail_rlc = zero;
+                let mut rest_rlc = zero;
+                let mut initial_cells = Vec::new();
+                let mut final_cells = Vec::new();
+                let mut root_cells = Vec::new();
+                let mut root_word_cells = Vec::new();
+
+                for offset in 0..n_rows {
+                    for (name, column, value) in [
+                        ("q_enable", self.q_enable, true),
+                        ("q_first", self.q_first, offset == 0),
+                        ("q_last", self.q_last, offset == n_rows - 1),
+                    ] {
+                        region.assign_fixed(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(F::from(value as u64)),
+                        )?;
+                    }
+
+                    let (update, row) = match rows.get(offset) {
+                        Some((update, row)) => (Some(*update), row.clone()),
+                        None => (None, MptCircuitRow::default()),
+                    };
+                    let is_padding = update.is_none();
+                    let byte = F::from(row.byte as u64);
+                    let known_byte = Value::known(byte);
+                    let key_flag = |flag: usize| row.key_flag == Some(flag);
+                    let key_flag_prev = |flag: usize| prev.key_flag == Some(flag);
+                    let is_key_hash = key_flag(KEY_ADDRESS_HASH) || key_flag(KEY_STORAGE_KEY_HASH);
+                    let is_node = row.tag != NodeTag::None;
+
+                    if row.is_update_start {
+                        update_index += 1;
+                    }
+                    let flags = update
+                        .and_then(|update| update_flags(update).ok())
+                        .unwrap_or_default();
+
+                    // Keys
+                    key_acc = if key_flag(KEY_ADDRESS) {
+                        let acc = if key_flag_prev(KEY_ADDRESS) {
+                            key_acc * F::from(256)
+                        } else {
+                            F::zero()
+                        };
+                        acc + byte
+                    } else {
+                        F::zero()
+                    };
+                    key_value_rlc = if key_flag(KEY_STORAGE_KEY) {
+                        let acc = if key_flag_prev(KEY_STORAGE_KEY) {
+                            key_value_rlc * evm_word
+                        } else {
+                            zero
+                        };
+                        acc + known_byte
+                    } else {
+                        zero
+                    };
+                    key_input_rlc = if key_flag(KEY_ADDRESS) || key_flag(KEY_STORAGE_KEY) {
+                        let acc = if key_flag_prev(KEY_ADDRESS) || key_flag_prev(KEY_STORAGE_KEY) {
+                            key_input_rlc * keccak_input
+                        } else {
+                            zero
+                        };
+                        acc + known_byte
+                    } else if is_key_hash {
+                        key_input_rlc
+                    } else {
+                        zero
+                    };
+                    key_rlc = if is_key_hash {
+                        if row.is_key_lo {
+                            key_rlc * evm_word
+                                + Value::known(F::from(prev.byte as u64 * 16))
+                                + known_byte
+                        } else if row.key_flag == prev.key_flag {
+                            key_rlc
+                        } else {
+                            zero
+                        }
+                    } else {
+                        zero
+                    };
+
+                    // Roots
+                    if row.is_root {
+                        if row.is_root_start {
+                            root = [0, 0];
+                            root_pi_pow = F::one();
+                            root_pi_rlc = byte;
+                        } else {
+                            root_pi_pow *= pi_randomness;
+                            root_pi_rlc += byte * root_pi_pow;
+                        }
+                        let half = if row.is_root_hi { 0 } else { 1 };
+                        root[half] = root[half] * 256 + row.byte as u128;
+                    }
+                    ref_rlc = if row.is_ref {
+                        if row.ref_index == 1 {
+                            known_byte
+                        } else {
+                            ref_rlc * evm_word + known_byte
+                        }
+                    } else if row.is_node_start {
+                        zero
+                    } else {
+                        ref_rlc
+                    };
+
+                    // Nodes and items
+                    let node_hash = if is_node {
+                        evm_word.map(|evm_word| rlc::value(&row.node_hash.to_le_bytes(), evm_word))
+                    } else {
+                        zero
+                    };
+                    node_rlc = if !is_node {
+                        zero
+                    } else if row.is_node_start {
+                        known_byte
+                    } else {
+                        node_rlc * keccak_input + known_byte
+                    };
+                    (item_acc, item_rlc) = if !is_node || row.rlp.is_header {
+                        (F::zero(), zero)
+                    } else if row.rlp.is_item_start {
+                        (byte, known_byte)
+                    } else {
+                        (
+                            item_acc * F::from(256) + byte,
+                            item_rlc * evm_word + known_byte,
+                        )
+                    };
+                    let in_node = is_node && !row.is_node_start;
+                    let accumulate = |acc: Value<F>, value: F, is_included: bool| {
+                        let acc = if in_node { acc } else { zero };
+                        if is_included {
+                            acc * keccak_input + Value::known(value)
+                        } else {
+                            acc
+                        }
+                    };
+                    sibling_rlc = accumulate(sibling_rlc, byte, row.is_sibling);
+                    rest_rlc = accumulate(rest_rlc, byte, row.is_rest);
+                    tail_rlc =
+                        accumulate(tail_rlc, F::from(row.nibble_hi() as u64), row.is_tail_hi);
+                    if row.is_tail_lo {
+                        tail_rlc =
+                            tail_rlc * keccak_input + Value::known(F::from(row.nibble_lo() as u64));
+                    }
+                    let mismatch_inv = if row.is_mismatch_hi || row.is_mismatch_lo {
+                        let nibble = if row.is_mismatch_hi {
+                            row.nibble_hi()
+                        } else {
+                            row.nibble_lo()
+                        };
+                        (F::from(nibble as u64) - F::from(row.mismatch_nibble as u64))
+                            .invert()
+                            .unwrap_or(F::zero())
+                    } else {
+                        F::zero()
+                    };
+
+                    let (nibble_hi, nibble_lo) = if is_key_hash {
+                        (0, row.byte)
+                    } else {
+                        (row.nibble_hi(), row.nibble_lo())
+                    };
+                    let mut advice = vec![
+                        ("byte", self.byte, byte),
+                        ("nibble_hi", self.nibble_hi, F::from(nibble_hi as u64)),
+                        ("nibble_lo", self.nibble_lo, F::from(nibble_lo as u64)),
+                        ("is_padding", self.is_padding, F::from(is_padding as u64)),
+                        (
+                            "update_index",
+                            self.update_index,
+                            F::from(if is_padding { 0 } else { update_index }),
+                        ),
+                        (
+                            "is_first_update",
+                            self.is_first_update,
+                            F::from((update_index == 1 && !is_padding) as u64),
+                        ),
+                        ("key_index", self.key_index, F::from(row.key_index as u64)),
+                        ("key_acc", self.key_acc, key_acc),
+                        ("root_hi", self.root_hi, F::from_u128(root[0])),
+                        ("root_lo", self.root_lo, F::from_u128(root[1])),
+                        ("root_pi_pow", self.root_pi_pow, root_pi_pow),
+                        ("root_pi_rlc", self.root_pi_rlc, root_pi_rlc),
+                        ("ref_index", self.ref_index, F::from(row.ref_index as u64)),
+                        ("level", self.level, F::from(row.level as u64)),
+                        ("depth", self.depth, F::from(row.depth as u64)),
+                        (
+                            "node_index",
+                            self.node_index,
+                            F::from(row.node_index as u64),
+                        ),
+                        (
+                            "drift_position",
+                            self.drift_position,
+                            F::from(row.drift_position as u64),
+                        ),
+                        ("node_len", self.node_len, F::from(row.node_len as u64)),
+                        (
+                            "node_remaining",
+                            self.node_remaining,
+                            F::from(row.node_remaining as u64),
+                        ),
+                        ("item_acc", self.item_acc, item_acc),
+                        (
+                            "item_index",
+                            self.item_index,
+                            F::from(row.item_index as u64),
+                        ),
+                        (
+                            "child_count",
+                            self.child_count,
+                            F::from(row.child_count as u64),
+                        ),
+                        (
+                            "key_child_count",
+                            self.key_child_count,
+                            F::from(row.key_child_count as u64),
+                        ),
+                        (
+                            "drift_child_count",
+                            self.drift_child_count,
+                            F::from(row.drift_child_count as u64),
+                        ),
+                        ("ref_count", self.ref_count, F::from(row.ref_count as u64)),
+                        (
+                            "nibble_depth",
+                            self.nibble_depth,
+                            F::from(row.nibble_depth as u64),
+                        ),
+                        (
+                            "mismatch_nibble",
+                            self.mismatch_nibble,
+                            F::from(row.mismatch_nibble as u64),
+                        ),
+                        ("mismatch_inv", self.mismatch_inv, mismatch_inv),
+                        (
+                            "key_position",
+                            self.key_position,
+                            F::from(row.key_position as u64),
+                        ),
+                        (
+                            "key_nibble",
+                            self.key_nibble,
+                            F::from(row.key_nibble as u64),
+                        ),
+                        (
+                            "mismatch_depth",
+                            self.mismatch_depth,
+                            F::from(row.mismatch_depth as u64),
+                        ),
+                        (
+                            "mismatch_path_nibble",
+                            self.mismatch_path_nibble,
+                            F::from(row.mismatch_path_nibble as u64),
+                        ),
+                        ("tail_len", self.tail_len, F::from(row.tail_len as u64)),
+                        (
+                            "sibling_len",
+                            self.sibling_len,
+                            F::from(row.sibling_len as u64),
+                        ),
+                        ("rest_len", self.rest_len, F::from(row.rest_len as u64)),
+                    ];
+                    for (name, column, value) in [
+                        ("is_update_start", self.is_update_start, row.is_update_start),
+                        ("is_new_side", self.is_new_side, row.is_new_side),
+                        ("is_key_lo", self.is_key_lo, row.is_key_lo),
+                        ("is_root", self.is_root, row.is_root),
+                        ("is_root_start", self.is_root_start, row.is_root_start),
+                        ("is_root_hi", self.is_root_hi, row.is_root_hi),
+                        ("is_ref", self.is_ref, row.is_ref),
+                        ("is_node_start", self.is_node_start, row.is_node_start),
+                        ("is_node_end", self.is_node_end, row.is_node_end),
+                        ("is_last_node", self.is_last_node, row.is_last_node),
+                        ("is_drift", self.is_drift, row.is_drift),
+                        ("is_paired", self.is_paired, row.is_paired),
+                        ("is_key_leaf", self.is_key_leaf, row.is_key_leaf),
+                        ("is_odd_path", self.is_odd_path, row.is_odd_path),
+                        ("is_moved", self.is_moved, row.is_moved),
+                        ("is_moved_direct", self.is_moved_direct, row.is_moved_direct),
+                        ("key_exists", self.key_exists, row.key_exists),
+                        (
+                            "is_first_payload",
+                            self.is_first_payload,
+                            row.is_first_payload,
+                        ),
+                        ("is_length_check", self.is_length_check, row.is_length_check),
+                        ("is_key_item", self.is_key_item, row.is_key_item),
+                        ("is_drift_child", self.is_drift_child, row.is_drift_child),
+                        (
+                            "is_drift_child_end",
+                            self.is_drift_child_end,
+                            row.is_drift_child_end,
+                        ),
+                        ("is_diverged", self.is_diverged, row.is_diverged),
+                        ("is_mismatch_hi", self.is_mismatch_hi, row.is_mismatch_hi),
+                        ("is_mismatch_lo", self.is_mismatch_lo, row.is_mismatch_lo),
+                        ("check_hi", self.check_hi, row.check_hi),
+                        ("check_lo", self.check_lo, row.check_lo),
+                        ("is_tail_hi", self.is_tail_hi, row.is_tail_hi),
+                        ("is_tail_lo", self.is_tail_lo, row.is_tail_lo),
+                        ("is_value_end", self.is_value_end, row.is_value_end),
+                        (
+                            "is_default_check",
+                            self.is_default_check,
+                            row.is_default_check,
+                        ),
+                        ("is_paired_end", self.is_paired_end, row.is_paired_end),
+                        ("is_drift_end", self.is_drift_end, row.is_drift_end),
+                        ("is_side_end", self.is_side_end, row.is_side_end),
+                        ("is_unpaired_end", self.is_unpaired_end, row.is_unpaired_end),
+                    ] {
+                        advice.push((name, column, F::from(value as u64)));
+                    }
+                    for (i, column) in self.update_flags.into_iter().enumerate() {
+                        advice.push(("update flag", column, F::from(flags[i] as u64)));
+                    }
+                    for (i, column) in self.key_flags.into_iter().enumerate() {
+                        advice.push(("key flag", column, F::from(key_flag(i) as u64)));
+                    }
+                    for (i, column) in self.kinds.into_iter().enumerate() {
+                        advice.push((
+                            "node kind",
+                            column,
+                            F::from((is_node && row.kind as usize == i) as u64),
+                        ));
+                    }
+                    for (i, column) in self.tags.into_iter().enumerate() {
+                        advice.push(("tag", column, F::from((row.tag as usize == i + 1) as u64)));
+                    }
+                    for (name, column, value) in advice {
+                        region.assign_advice(|| name, column, offset, || Value::known(value))?;
+                    }
+
+                    for (name, column, value) in [
+                        ("key_value_rlc", self.key_value_rlc, key_value_rlc),
+                        ("key_input_rlc", self.key_input_rlc, key_input_rlc),
+                        ("key_rlc", self.key_rlc, key_rlc),
+                        ("node_rlc", self.node_rlc, node_rlc),
+                        ("node_hash", self.node_hash, node_hash),
+                        ("ref_rlc", self.ref_rlc, ref_rlc),
+                        ("item_rlc", self.item_rlc, item_rlc),
+                        ("sibling_rlc", self.sibling_rlc, sibling_rlc),
+                        ("tail_rlc", self.tail_rlc, tail_rlc),
+                        ("rest_rlc", self.rest_rlc, rest_rlc),
+                    ] {
+                        region.assign_advice(|| name, column, offset, || value)?;
+                    }
+
+                    for i in 0..2 {
+                        let initial_cell = region.assign_advice(
+                            || "initial_root",
+                            self.initial_root[i],
+                            offset,
+                            || Value::known(initial_root[i]),
+                        )?;
+                        let final_cell = region.assign_advice(
+                            || "final_root",
+                            self.final_root[i],
+                            offset,
+                            || Value::known(final_root[i]),
+                        )?;
+                        if offset == 0 {
+                            initial_cells.push(initial_cell);
+                        }
+                        if offset == n_rows - 1 {
+                            final_cells.push(final_cell);
+                        }
+                    }
+                    for (name, column, value) in [
+                        ("pi_randomness", self.pi_randomness, pi_randomness),
+                        ("initial_root_rlc", self.initial_root_rlc, initial_root_rlc),
+                        ("final_root_rlc", self.final_root_rlc, final_root_rlc),
+                    ] {
+                        let cell = region.assign_advice(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(value),
+                        )?;
+                        if offset == 0 {
+                            root_cells.push(cell);
+                        }
+                    }
+                    for (name, column, value) in [
+                        (
+                            "initial_root_word",
+                            self.initial_root_word,
+                            initial_root_word,
+                        ),
+                        ("final_root_word", self.final_root_word, final_root_word),
+                    ] {
+                        let cell = region.assign_advice(|| name, column, offset, || value)?;
+                        if offset == 0 {
+                            root_word_cells.push(cell);
+                        }
+                    }
+
+                    let table_row = match update {
+                        Some(update) => update.table_assignment(evm_word),
+                        None => MptUpdateRow([zero; 7]),
+                    };
+                    self.mpt_table.assign(&mut region, offset, &table_row)?;
+                    self.rlp.assign(&mut region, offset, &row.rlp)?;
+                    length_is_one.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(row.rlp.length) - F::one()),
+                    )?;
+
+                    prev = row;
+                }
+
+                Ok((
+                    [initial_cells, final_cells].concat(),
+                    root_cells,
+                    root_word_cells,
+                ))
+            },
+        )?;
+
+        for (i, cell) in root_instance_cells.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), self.instance, i)?;
+        }
+
+        let [randomness, initial_root_rlc, final_root_rlc]: [AssignedCell<F, F>; 3] =
+            root_cells.try_into().unwrap();
+        let [initial_root_word, final_root_word]: [AssignedCell<F, F>; 2] =
+            root_word_cells.try_into().unwrap();
+        Ok(MptRootCells {
+            randomness,
+            initial_root_rlc,
+            final_root_rlc,
+            initial_root_word,
+            final_root_word,
+        })
+    }
+}
+
+/// Split a root in its hi and lo 128 bit halves.
+fn split_root<F: Field>(root: Word) -> [F; 2] {
+    [
+        F::from_u128((root >> 128).low_u128()),
+        F::from_u128(root.low_u128()),
+    ]
+}
+
+/// The update flags of the proof type of `update`.
+fn update_flags(update: &MptUpdate) -> Result<[bool; 5], DecoderError> {
+    let proof_type = update.proof_type_tag();
+    PROOF_TYPES
+        .iter()
+        .find(|(pt, _)| *pt == proof_type)
+        .map(|(_, flags)| *flags)
+        .ok_or(DecoderError::Custom("unsupported proof type"))
+}
+
+/// The nibbles of the hashes of the keys of `update`: the address, followed
+/// by the storage key for storage updates.
+fn key_nibbles(update: &MptUpdate) -> Vec<[u8; KEY_HASH_ROWS]> {
+    key_preimages(update)
+        .iter()
+        .map(|preimage| {
+            let hash = keccak(preimage).to_be_bytes();
+            array::from_fn(|i| {
+                let byte = hash[i / 2];
+                if i % 2 == 0 {
+                    byte >> 4
+                } else {
+                    byte & 0xf
+                }
+            })
+        })
+        .collect()
+}
+
+/// The keys of `update` that are hashed to get their path in the tries.
+fn key_preimages(update: &MptUpdate) -> Vec<Vec<u8>> {
+    match update.key {
+        MptKey::Account { address, .. } => vec![address.as_bytes().to_vec()],
+        MptKey::AccountStorage {
+            address,
+            storage_key,
+            ..
+        } => vec![
+            address.as_bytes().to_vec(),
+            storage_key.to_be_bytes().to_vec(),
+        ],
+    }
+}
+
+/// Hex-prefix encoding of the `nibbles` of the path of a leaf or extension.
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let is_odd = nibbles.len() % 2 == 1;
+    let flag = 2 * is_leaf as u8 + is_odd as u8;
+    let mut bytes = vec![flag << 4];
+    let mut nibbles = nibbles.iter();
+    if is_odd {
+        bytes[0] |= nibbles.next().unwrap();
+    }
+    while let (Some(hi), Some(lo)) = (nibbles.next(), nibbles.next()) {
+        bytes.push(hi << 4 | lo);
+    }
+    bytes
+}
+
+/// An RLP item of a trie node.
+#[derive(Clone, Copy, Debug)]
+struct NodeItem {
+    tag: NodeTag,
+    /// Position of the item in the node
+    start: usize,
+    header_len: usize,
+    /// Number of rows of the item: the header of a container, or the whole
+    /// item
+    len: usize,
+}
+
+impl NodeItem {
+    fn string(tag: NodeTag, rlp: &Rlp, start: usize) -> Result<Self, DecoderError> {
+        if !rlp.is_data() {
+            return Err(DecoderError::Custom("embedded nodes are not supported"));
+        }
+        let info = rlp.payload_info()?;
+        Ok(Self {
+            tag,
+            start,
+            header_len: info.header_len,
+            len: info.header_len + info.value_len,
+        })
+    }
+
+    fn container(tag: NodeTag, rlp: &Rlp, start: usize) -> Result<Self, DecoderError> {
+        let info = rlp.payload_info()?;
+        Ok(Self {
+            tag,
+            start,
+            header_len: info.header_len,
+            len: info.header_len,
+        })
+    }
+
+    /// Returns true if the item is a hash: a 32 bytes string.
+    fn is_hash(&self, bytes: &[u8]) -> bool {
+        self.len == 33 && bytes[self.start] == HASH_PREFIX
+    }
+}
+
+/// A node of the path of an update, with its position in the trie.
+#[derive(Clone, Debug, Default)]
+struct PathNode {
+    bytes: Vec<u8>,
+    kind: NodeKind,
+    items: Vec<NodeItem>,
+    /// Nibbles of the path of an extension or leaf
+    path: Vec<u8>,
+    is_odd_path: bool,
+    level: usize,
+    depth: usize,
+    index: usize,
+    is_last: bool,
+    is_drift: bool,
+    is_paired: bool,
+    /// Position and nibble of the first nibble of the path that is not the
+    /// key nibble
+    mismatch: Option<(usize, u8)>,
+    is_key_leaf: bool,
+    is_moved: bool,
+    is_moved_direct: bool,
+    drift_position: usize,
+    /// Item of the child of an unpaired branch that references a drift node
+    drift_child: Option<usize>,
+    /// Items in the path of the key
+    key_items: Vec<bool>,
+}
+
+impl PathNode {
+    /// Decode the items of a node of the trie of `level`.
+    fn decode(bytes: &[u8], level: usize) -> Result<Self, DecoderError> {
+        use NodeTag::*;
+
+        let mut node = Self {
+            bytes: bytes.to_vec(),
+            level,
+            ..Default::default()
+        };
+        if bytes == [EMPTY_STRING] {
+            node.items.push(NodeItem {
+                tag: EmptyNode,
+                start: 0,
+                header_len: 1,
+                len: 1,
+            });
+            return Ok(node);
+        }
+
+        let rlp = Rlp::new(bytes);
+        let info = rlp.payload_info()?;
+        if !rlp.is_list() || info.header_len + info.value_len != bytes.len() {
+            return Err(DecoderError::Custom("a node is an RLP list"));
+        }
+        node.items.push(NodeItem::container(ListHeader, &rlp, 0)?);
+        match rlp.item_count()? {
+            17 => {
+                node.kind = NodeKind::Branch;
+                for i in 0..17 {
+                    let (item, start) = rlp.at_with_offset(i)?;
+                    let item =
+                        NodeItem::string(if i < 16 { Child } else { BranchValue }, &item, start)?;
+                    if bytes[start..start + item.len] != [EMPTY_STRING]
+                        && (i == 16 || !item.is_hash(bytes))
+                    {
+                        return Err(DecoderError::Custom(
+                            "a branch child is a hash or empty, and the branch value is empty",
+                        ));
+                    }
+                    node.items.push(item);
+                }
+            }
+            2 => {
+                let (path, start) = rlp.at_with_offset(0)?;
+                node.items.push(NodeItem::string(Path, &path, start)?);
+                let path = path.data()?;
+                let flag = path.first().map_or(u8::MAX, |byte| byte >> 4);
+                if flag > 3 || (flag % 2 == 0 && path[0] & 0xf != 0) {
+                    return Err(DecoderError::Custom("invalid hex-prefix path"));
+                }
+                node.is_odd_path = flag % 2 == 1;
+                if node.is_odd_path {
+                    node.path.push(path[0] & 0xf);
+                }
+                for byte in &path[1..] {
+                    node.path.extend([byte >> 4, byte & 0xf]);
+                }
+
+                let (value, start) = rlp.at_with_offset(1)?;
+                if flag < 2 {
+                    node.kind = NodeKind::Extension;
+                    let child = NodeItem::string(ExtChild, &value, start)?;
+                    if node.path.is_empty() || !child.is_hash(bytes) {
+                        return Err(DecoderError::Custom("an extension has a path and a hash"));
+                    }
+                    node.items.push(child);
+                } else if level == 0 {
+                    node.kind = NodeKind::Leaf;
+                    let account_value = NodeItem::container(AccountValue, &value, start)?;
+                    let account = Rlp::new(value.data()?);
+                    let info = account.payload_info()?;
+                    if !account.is_list()
+                        || info.header_len + info.value_len != value.data()?.len()
+                        || account.item_count()? != 4
+                    {
+                        return Err(DecoderError::Custom(
+                            "the value of an account leaf is a list of 4 fields",
+                        ));
+                    }
+                    let account_start = start + account_value.header_len;
+                    node.items.push(account_value);
+                    node.items
+                        .push(NodeItem::container(AccountList, &account, account_start)?);
+                    for (i, tag) in [Nonce, Balance, StorageRoot, CodeHash]
+                        .into_iter()
+                        .enumerate()
+                    {
+                        let (field, offset) = account.at_with_offset(i)?;
+                        let field = NodeItem::string(tag, &field, account_start + offset)?;
+                        if matches!(tag, StorageRoot | CodeHash) && !field.is_hash(bytes) {
+                            return Err(DecoderError::Custom(
+                                "the storage root and code hash are hashes",
+                            ));
+                        }
+                        node.items.push(field);
+                    }
+                } else {
+                    node.kind = NodeKind::Leaf;
+                    if value.as_raw().len() == 1 {
+                        node.items
+                            .push(NodeItem::string(StorageValue, &value, start)?);
+                    } else {
+                        let header = NodeItem::container(StorageValueHeader, &value, start)?;
+                        let storage_value = Rlp::new(value.data()?);
+                        let info = storage_value.payload_info()?;
+                        if info.header_len + info.value_len != value.data()?.len() {
+                            return Err(DecoderError::Custom(
+                                "the value of a storage leaf is RLP encoded",
+                            ));
+                        }
+                        node.items.push(header);
+                        node.items.push(NodeItem::string(
+                            StorageValue,
+                            &storage_value,
+                            start + header.header_len,
+                        )?);
+                    }
+                }
+            }
+            _ => {
+                return Err(DecoderError::Custom(
+                    "a node is a branch, extension or leaf",
+                ))
+            }
+        }
+        Ok(node)
+    }
+
+    /// The bytes of the items that are not in the path of the key, which
+    /// are the same in a paired node.
+    fn sibling_bytes(&self) -> Vec<u8> {
+        self.items
+            .iter()
+            .zip(&self.key_items)
+            .filter(|(item, is_key_item)| !**is_key_item && !item.tag.is_container())
+            .flat_map(|(item, _)| self.bytes[item.start..item.start + item.len].to_vec())
+            .collect()
+    }
+
+    /// The item in the path of the key that references the next node.
+    fn reference(&self) -> Option<&NodeItem> {
+        self.items
+            .iter()
+            .zip(&self.key_items)
+            .find(|(item, is_key_item)| {
+                **is_key_item
+                    && matches!(
+                        item.tag,
+                        NodeTag::Child | NodeTag::ExtChild | NodeTag::StorageRoot
+                    )
+                    && item.len > 1
+            })
+            .map(|(item, _)| item)
+    }
+}
+
+/// Decode the `nodes` of a path and check that they follow the `keys`.
+fn decode_path(
+    nodes: &[Vec<u8>],
+    keys: &[[u8; KEY_HASH_ROWS]],
+    flags: &[bool; 5],
+) -> Result<Vec<PathNode>, DecoderError> {
+    use NodeTag::{
+        Balance, Child, CodeHash, ExtChild, Nonce, StorageRoot, StorageValue, StorageValueHeader,
+    };
+
+    let mut path: Vec<PathNode> = Vec::with_capacity(nodes.len());
+    let (mut level, mut depth, mut index) = (0, 0, 0);
+    for (i, bytes) in nodes.iter().enumerate() {
+        let key = keys.get(level).ok_or(DecoderError::Custom(
+            "only storage updates continue into the storage trie",
+        ))?;
+        let mut node = PathNode::decode(bytes, level)?;
+        if node.kind == NodeKind::Empty && index != 0 {
+            return Err(DecoderError::Custom("the empty node is the root of a trie"));
+        }
+        node.depth = depth;
+        node.index = index;
+        node.is_last = i == nodes.len() - 1;
+        if depth + node.path.len() > KEY_HASH_ROWS
+            || (node.kind == NodeKind::Branch && depth == KEY_HASH_ROWS)
+        {
+            return Err(DecoderError::Custom("the path is longer than the key"));
+        }
+        node.mismatch = node
+            .path
+            .iter()
+            .enumerate()
+            .find(|(j, nibble)| **nibble != key[depth + j])
+            .map(|(j, nibble)| (depth + j, *nibble));
+        node.is_key_leaf = node.kind == NodeKind::Leaf && node.mismatch.is_none();
+        if node.is_key_leaf && depth + node.path.len() != KEY_HASH_ROWS {
+            return Err(DecoderError::Custom(
+                "the path of a leaf has all the key nibbles",
+            ));
+        }
+        let (is_key_leaf, is_diverged) = (node.is_key_leaf, node.mismatch.is_some());
+        node.key_items = node
+            .items
+            .iter()
+            .enumerate()
+            .map(|(j, item)| match item.tag {
+                Child => j == 1 + key[depth] as usize,
+                ExtChild => !is_diverged,
+                StorageValueHeader | StorageValue => is_key_leaf,
+                Nonce => is_key_leaf && flags[UPDATE_NONCE],
+                Balance => is_key_leaf && flags[UPDATE_BALANCE],
+                CodeHash => is_key_leaf && flags[UPDATE_CODE_HASH],
+                StorageRoot => is_key_leaf && flags[UPDATE_STORAGE],
+                _ => false,
+            })
+            .collect();
+
+        match (node.reference(), nodes.get(i + 1)) {
+            (Some(item), Some(next)) => {
+                if node.bytes[item.start + 1..item.start + item.len] != keccak(next).to_be_bytes() {
+                    return Err(DecoderError::Custom(
+                        "a node is referenced by the previous node",
+                    ));
+                }
+            }
+            (None, None) => (),
+            _ => {
+                return Err(DecoderError::Custom(
+                    "only the last node doesn't reference a child",
+                ))
+            }
+        }
+
+        match node.kind {
+            NodeKind::Branch => {
+                depth += 1;
+                index += 1;
+            }
+            NodeKind::Extension => {
+                depth += node.path.len();
+                index += 1;
+            }
+            NodeKind::Leaf => {
+                level += 1;
+                depth = 0;
+                index = 0;
+            }
+            NodeKind::Empty => (),
+        }
+        path.push(node);
+    }
+    Ok(path)
+}
+
+/// Decode the old and new paths of `update`, pair their nodes, and append
+/// the drift nodes of the leaves and extensions that are moved below a new
+/// branch.
+fn update_paths(update: &MptUpdate) -> Result<[Vec<PathNode>; 2], DecoderError> {
+    let flags = update_flags(update)?;
+    let keys = key_nibbles(update);
+    let [old_nodes, new_nodes] = update.proof.paths();
+    let mut sides = [
+        decode_path(&old_nodes, &keys, &flags)?,
+        decode_path(&new_nodes, &keys, &flags)?,
+    ];
+
+    // The nodes at the same position, whose items that are not in the path
+    // of the key are the same
+    let paired = sides[0]
+        .iter()
+        .zip(&sides[1])
+        .take_while(|(old, new)| {
+            old.kind != NodeKind::Empty
+                && (old.kind, old.level, old.depth, old.index)
+                    == (new.kind, new.level, new.depth, new.index)
+                && old.sibling_bytes() == new.sibling_bytes()
+        })
+        .count();
+    for side in sides.iter_mut() {
+        for node in side[..paired].iter_mut() {
+            node.is_paired = true;
+        }
+    }
+
+    let mut drift_nodes = [vec![], vec![]];
+    for side in 0..2 {
+        let other = 1 - side;
+        for i in paired..sides[side].len() {
+            let node = &sides[side][i];
+            let (depth, nibble) = match node.mismatch {
+                Some(mismatch) if node.kind != NodeKind::Branch => mismatch,
+                _ => continue,
+            };
+            let branch = sides[other]
+                .iter()
+                .position(|branch| {
+                    !branch.is_paired
+                        && branch.kind == NodeKind::Branch
+                        && (branch.level, branch.depth) == (node.level, depth)
+                })
+                .ok_or(DecoderError::Custom("a moved node is below a new branch"))?;
+            let branch = &mut sides[other][branch];
+            let child = 1 + nibble as usize;
+            if branch.drift_child.is_some() || !branch.items[child].is_hash(&branch.bytes) {
+                return Err(DecoderError::Custom(
+                    "a new branch references the moved node",
+                ));
+            }
+            branch.drift_child = Some(child);
+            let child_start = branch.items[child].start + 1;
+            let child_hash = branch.bytes[child_start..child_start + 32].to_vec();
+
+            let node = &mut sides[side][i];
+            node.is_moved = true;
+            let tail = node.path[depth - node.depth + 1..].to_vec();
+            let rest = &node.bytes[node.items[2].start..];
+            if node.kind == NodeKind::Extension && tail.is_empty() {
+                node.is_moved_direct = true;
+                if rest[1..] != child_hash {
+                    return Err(DecoderError::Custom(
+                        "a new branch references the child of the moved extension",
+                    ));
+                }
+                continue;
+            }
+            let mut stream = RlpStream::new_list(2);
+            stream
+                .append(&hex_prefix(&tail, node.kind == NodeKind::Leaf))
+                .append_raw(rest, 1);
+            let bytes = stream.out().to_vec();
+            if keccak(&bytes).to_be_bytes()[..] != child_hash {
+                return Err(DecoderError::Custom(
+                    "a new branch references the moved node with the tail of its path",
+                ));
+            }
+            let mut drift_node = PathNode::decode(&bytes, node.level)?;
+            drift_node.depth = depth + 1;
+            drift_node.is_drift = true;
+            drift_node.is_last = true;
+            drift_node.drift_position = nibble as usize;
+            drift_node.key_items = vec![false; drift_node.items.len()];
+            drift_nodes[other].push(drift_node);
+        }
+    }
+    if sides
+        .iter()
+        .flatten()
+        .any(|node| node.kind == NodeKind::Branch && !node.is_paired && node.drift_child.is_none())
+    {
+        return Err(DecoderError::Custom(
+            "an unpaired branch references a moved node",
+        ));
+    }
+
+    for (side, drift_nodes) in sides.iter_mut().zip(drift_nodes) {
+        side.extend(drift_nodes);
+    }
+    Ok(sides)
+}
+
+/// Witness of a row of the MPT circuit.
+#[derive(Clone, Debug, Default)]
+struct MptCircuitRow {
+    byte: u8,
+    is_update_start: bool,
+    is_new_side: bool,
+    key_flag: Option<usize>,
+    key_index: usize,
+    is_key_lo: bool,
+    is_root: bool,
+    is_root_start: bool,
+    is_root_hi: bool,
+    is_ref: bool,
+    ref_index: usize,
+
+    is_node_start: bool,
+    is_node_end: bool,
+    is_last_node: bool,
+    is_drift: bool,
+    level: usize,
+    depth: usize,
+    node_index: usize,
+    is_paired: bool,
+    kind: NodeKind,
+    is_key_leaf: bool,
+    is_odd_path: bool,
+    is_moved: bool,
+    is_moved_direct: bool,
+    drift_position: usize,
+    key_exists: bool,
+    node_len: usize,
+    node_remaining: usize,
+    node_hash: Word,
+
+    rlp: RlpDecoderRow,
+    tag: NodeTag,
+    item_index: usize,
+    is_first_payload: bool,
+    is_length_check: bool,
+    child_count: usize,
+    key_child_count: usize,
+    drift_child_count: usize,
+    is_key_item: bool,
+    is_drift_child: bool,
+    is_drift_child_end: bool,
+    ref_count: usize,
+    is_sibling: bool,
+    is_rest: bool,
+
+    nibble_depth: usize,
+    is_diverged: bool,
+    is_mismatch_hi: bool,
+    is_mismatch_lo: bool,
+    mismatch_nibble: u8,
+    check_hi: bool,
+    check_lo: bool,
+    key_position: usize,
+    key_nibble: usize,
+    mismatch_depth: usize,
+    mismatch_path_nibble: u8,
+    is_tail_hi: bool,
+    is_tail_lo: bool,
+    tail_len: usize,
+
+    is_value_end: bool,
+    is_default_check: bool,
+    sibling_len: usize,
+    rest_len: usize,
+    is_paired_end: bool,
+    is_drift_end: bool,
+    is_side_end: bool,
+    is_unpaired_end: bool,
+}
+
+impl MptCircuitRow {
+    fn nibble_hi(&self) -> u8 {
+        self.byte >> 4
+    }
+
+    fn nibble_lo(&self) -> u8 {
+        self.byte & 0xf
+    }
+}
+
+/// Generate the rows that prove an update: the key rows, followed by the
+/// root rows and the rows of the path nodes of the old side and then of the
+/// new side.
+fn update_rows(update: &MptUpdate) -> Result<Vec<MptCircuitRow>, DecoderError> {
+    let sides = update_paths(update)?;
+    let keys = key_nibbles(update);
+    let is_storage_update = matches!(update.key, MptKey::AccountStorage { .. });
+
+    let mut rows = Vec::new();
+    for (i, preimage) in key_preimages(update).into_iter().enumerate() {
+        let flag = if i == 0 { KEY_ADDRESS } else { KEY_STORAGE_KEY };
+        for (key_index, byte) in preimage.into_iter().enumerate() {
+            rows.push(MptCircuitRow {
+                byte,
+                key_flag: Some(flag),
+                key_index,
+                ..Default::default()
+            });
+        }
+        for (key_index, nibble) in keys[i].into_iter().enumerate() {
+            rows.push(MptCircuitRow {
+                byte: nibble,
+                key_flag: Some(flag + 1),
+                key_index,
+                is_key_lo: key_index % 2 == 1,
+                ..Default::default()
+            });
+        }
+    }
+    rows[0].is_update_start = true;
+
+    for (is_new_side, root, nodes) in [
+        (false, update.old_root, &sides[0]),
+        (true, update.new_root, &sides[1]),
+    ] {
+        let start = rows.len();
+        for (i, byte) in root.to_be_bytes().into_iter().enumerate() {
+            rows.push(MptCircuitRow {
+                byte,
+                is_root: true,
+                is_root_start: i == 0,
+                is_root_hi: i < ROOT_ROWS / 2,
+                is_ref: true,
+                ref_index: i + 1,
+                ..Default::default()
+            });
+        }
+        for node in nodes {
+            rows.extend(node_rows(node, &keys[node.level], is_storage_update));
+        }
+        for row in rows[start..].iter_mut() {
+            row.is_new_side = is_new_side;
+        }
+    }
+    Ok(rows)
+}
+
+/// Generate the rows of the bytes of a path node.
+fn node_rows(
+    node: &PathNode,
+    key: &[u8; KEY_HASH_ROWS],
+    is_storage_update: bool,
+) -> Vec<MptCircuitRow> {
+    use NodeTag::*;
+
+    let node_hash = keccak(&node.bytes);
+    let key_exists = node.is_key_leaf && (node.level == 1) == is_storage_update;
+    let is_moved_or_drift = node.is_moved || node.is_drift;
+    let mut rows: Vec<MptCircuitRow> = Vec::with_capacity(node.bytes.len());
+    for (item_index, item) in node.items.iter().enumerate() {
+        let tag = item.tag;
+        let is_key_item = node.key_items[item_index];
+        let is_drift_child = node.drift_child == Some(item_index);
+        for j in 0..item.len {
+            let offset = item.start + j;
+            debug_assert_eq!(offset, rows.len());
+            let byte = node.bytes[offset];
+            let is_node_start = offset == 0;
+            let is_node_end = offset == node.bytes.len() - 1;
+            let prev = rows.last().cloned().unwrap_or_default();
+            // The accumulated value of the previous row of the node
+            let acc = |value: usize| if is_node_start { 0 } else { value };
+
+            let is_item_start = j == 0;
+            let rlp = RlpDecoderRow::new(byte, is_item_start, tag.is_container(), &prev.rlp);
+            debug_assert_eq!(rlp.is_item_end, j == item.len - 1);
+            debug_assert_eq!(rlp.is_header, j < item.header_len);
+            let RlpDecoderRow {
+                is_item_end,
+                is_header,
+                length,
+                ..
+            } = rlp;
+            let is_first_payload = !is_header && (is_item_start || prev.rlp.is_header);
+            let is_length_check = prev.rlp.is_item_start
+                && matches!(
+                    RlpByteClass::from(prev.byte),
+                    RlpByteClass::LongString | RlpByteClass::LongList
+                )
+                && rlp.counter == 0;
+            let is_ref = is_key_item && !is_header && matches!(tag, Child | ExtChild | StorageRoot);
+            let is_sibling = !is_key_item && !tag.is_container();
+            let is_rest = !matches!(tag, ListHeader | Path | EmptyNode);
+
+            // Path nibbles
+            let (hi, lo) = (byte >> 4, byte & 0xf);
+            let is_path_payload = tag == Path && !is_header;
+            let has_lo = !is_first_payload || node.is_odd_path;
+            let nibble_depth = if is_node_start {
+                node.depth
+            } else if is_path_payload {
+                prev.nibble_depth
+                    + if is_first_payload {
+                        node.is_odd_path as usize
+                    } else {
+                        2
+                    }
+            } else {
+                prev.nibble_depth
+            };
+            let is_diverged_prev = !is_node_start && prev.is_diverged;
+            let check_hi =
+                is_path_payload && !is_first_payload && !is_diverged_prev && !node.is_drift;
+            let is_mismatch_hi = check_hi && hi != key[prev.nibble_depth];
+            let is_lo_compared =
+                is_path_payload && has_lo && !is_diverged_prev && !is_mismatch_hi && !node.is_drift;
+            let is_mismatch_lo = is_lo_compared && lo != key[nibble_depth - 1];
+            let is_key_child = tag == Child && is_key_item && is_item_start;
+            let (key_position, key_nibble) = if is_key_child {
+                (node.depth, item_index - 1)
+            } else if is_lo_compared {
+                (nibble_depth - 1, key[nibble_depth - 1] as usize)
+            } else {
+                (0, 0)
+            };
+            let (mismatch_depth, mismatch_path_nibble, mismatch_nibble) = if is_mismatch_hi {
+                (prev.nibble_depth, hi, key[prev.nibble_depth])
+            } else if is_mismatch_lo {
+                (nibble_depth - 1, lo, key[nibble_depth - 1])
+            } else if is_node_start {
+                (0, 0, 0)
+            } else {
+                (prev.mismatch_depth, prev.mismatch_path_nibble, 0)
+            };
+            let is_tail_hi =
+                is_path_payload && !is_first_payload && (node.is_drift || is_diverged_prev);
+            let is_tail_lo =
+                is_path_payload && has_lo && (node.is_drift || is_diverged_prev || is_mismatch_hi);
+
+            rows.push(MptCircuitRow {
+                byte,
+                is_ref,
+                ref_index: if is_ref {
+                    if prev.is_ref {
+                        prev.ref_index + 1
+                    } else {
+                        1
+                    }
+                } else {
+                    0
+                },
+
+                is_node_start,
+                is_node_end,
+                is_last_node: node.is_last,
+                is_drift: node.is_drift,
+                level: node.level,
+                depth: node.depth,
+                node_index: node.index,
+                is_paired: node.is_paired,
+                kind: node.kind,
+                is_key_leaf: node.is_key_leaf,
+                is_odd_path: node.is_odd_path,
+                is_moved: node.is_moved,
+                is_moved_direct: node.is_moved_direct,
+                drift_position: node.drift_position,
+                key_exists,
+                node_len: offset + 1,
+                node_remaining: node.bytes.len() - offset - 1,
+                node_hash,
+
+                rlp,
+                tag,
+                item_index,
+                is_first_payload,
+                is_length_check,
+                child_count: acc(prev.child_count)
+                    + (tag == Child && is_item_start && length != 0) as usize,
+                key_child_count: acc(prev.key_child_count) + is_key_child as usize,
+                drift_child_count: acc(prev.drift_child_count)
+                    + (is_drift_child && is_item_start) as usize,
+                is_key_item,
+                is_drift_child,
+                is_drift_child_end: is_drift_child && is_item_end,
+                ref_count: acc(prev.ref_count) + is_ref as usize,
+                is_sibling,
+                is_rest,
+
+                nibble_depth,
+                is_diverged: is_diverged_prev || is_mismatch_hi || is_mismatch_lo,
+                is_mismatch_hi,
+                is_mismatch_lo,
+                mismatch_nibble,
+                check_hi,
+                check_lo: is_lo_compared || is_key_child,
+                key_position,
+                key_nibble,
+                mismatch_depth,
+                mismatch_path_nibble,
+                is_tail_hi,
+                is_tail_lo,
+                tail_len: acc(prev.tail_len) + is_tail_hi as usize + is_tail_lo as usize,
+
+                is_value_end: is_item_end
+                    && is_key_item
+                    && matches!(tag, Nonce | Balance | CodeHash | StorageValue),
+                is_default_check: is_item_end
+                    && !is_key_item
+                    && node.is_key_leaf
+                    && node.level == 0
+                    && !node.is_paired
+                    && matches!(tag, Nonce | Balance | StorageRoot | CodeHash),
+                sibling_len: acc(prev.sibling_len) + is_sibling as usize,
+                rest_len: acc(prev.rest_len) + is_rest as usize,
+                is_paired_end: is_node_end && node.is_paired,
+                is_drift_end: is_node_end && node.is_drift,
+                is_side_end: is_node_end && node.is_last && !node.is_drift,
+                is_unpaired_end: is_node_end
+                    && !node.is_paired
+                    && !is_moved_or_drift
+                    && node.kind != NodeKind::Empty,
+                ..Default::default()
+            });
+        }
+    }
+    rows
+}
+
+/// Number of rows required to prove the updates, without padding
+fn num_rows(updates: &MptUpdates) -> usize {
+    updates
+        .updates()
+        .map(|update| update_rows(update).map_or(0, |rows| rows.len()))
+        .sum()
+}
+
+/// The inputs of the keccak table that are looked up by the MptCircuit to
+/// prove `update`: its keys, and the nodes of its paths, including the drift
+/// nodes.
+pub(crate) fn update_keccak_inputs(update: &MptUpdate) -> Vec<Vec<u8>> {
+    let nodes = match update_paths(update) {
+        Ok(sides) => sides.into_iter().flatten().map(|node| node.bytes).collect(),
+        Err(_) => update.proof.paths().into_iter().flatten().collect(),
+    };
+    [key_preimages(update), nodes].concat()
+}
+
+/// MPT Circuit for proving the updates of the state trie
+#[derive(Clone, Default, Debug)]
+pub struct MptCircuit<F: Field> {
+    /// MPT updates, with the trie nodes that prove them
+    pub updates: MptUpdates,
+    /// Number of rows of the circuit, 0 means that it's calculated from the
+    /// updates
+    pub n_rows: usize,
+    /// Randomness of the PublicInputs circuit, used for the RLCs of the
+    /// initial and final roots
+    pub randomness: F,
+}
+
+impl<F: Field> MptCircuit<F> {
+    /// Return a new MptCircuit
+    pub fn new(updates: MptUpdates, n_rows: usize) -> Self {
+        Self {
+            updates,
+            n_rows,
+            randomness: F::zero(),
+        }
+    }
+}
+
+impl<F: Field> SubCircuit<F> for MptCircuit<F> {
+    type Config = MptCircuitConfig<F>;
+
+    fn new_from_block(block: &witness::Block<F>) -> Self {
+        Self {
+            randomness: block.randomness,
+            ..Self::new(
+                block.mpt_updates.clone(),
+                block.circuits_params.max_mpt_rows,
+            )
+        }
+    }
+
+    /// The old root of the first update and the new root of the last update,
+    /// split in hi/lo halves
+    fn instance(&self) -> Vec<Vec<F>> {
+        vec![[
+            split_root(self.updates.old_root()),
+            split_root(self.updates.new_root()),
+        ]
+        .concat()]
+    }
+
+    /// Return the minimum number of rows required to prove the block
+    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
+        let rows = num_rows(&block.mpt_updates) + 1;
+        (rows, block.circuits_params.max_mpt_rows.max(rows))
+    }
+
+    /// Make the assignments to the MptCircuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config
+            .assign(
+                layouter,
+                &self.updates,
+                self.n_rows,
+                self.randomness,
+                challenges,
+            )
+            .map(|_| ())
+    }
+}
+
+#[cfg(any(feature = "test", test))]
+impl<F: Field> Circuit<F> for MptCircuit<F> {
+    type Config = (MptCircuitConfig<F>, Challenges);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let mpt_table = MptTable::construct(meta);
+        let keccak_table = KeccakTable::construct(meta);
+        let challenges = Challenges::construct(meta);
+
+        let config = {
+            let challenges = challenges.exprs(meta);
+            MptCircuitConfig::new(
+                meta,
+                MptCircuitConfigArgs {
+                    mpt_table,
+                    keccak_table,
+                    challenges,
+                },
+            )
+        };
+
+        (config, challenges)
+    }
+
+    fn synthesize(
+        &self,
+        (config, challenges): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let challenges = challenges.values(&mut layouter);
+
+        config
+            .keccak_table
+            .dev_load(&mut layouter, &self.updates.keccak_inputs(), &challenges)?;
+        self.synthesize_sub(&config, &challenges, &mut layouter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        table::AccountFieldTag,
+        witness::{block_convert, MptUpdateProof},
+    };
+    use bus_mapping::{
+        mock::BlockData,
+        mpt::{StateTrie, TrieUpdate},
+        operation::AccountField,
+    };
+    use eth_types::{bytecode, geth_types::GethData, Address, ToWord, H256};
+    use ethers_core::utils::rlp::{self, RlpStream};
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+    };
+    use mock::TestContext;
+
+    /// Leaf of a trie that only contains this leaf, so its path is the whole
+    /// hashed key.
+    fn leaf(key: &[u8], value: Vec<u8>) -> Vec<u8> {
+        let mut path = vec![0x20];
+        path.extend_from_slice(&keccak(key).to_be_bytes());
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&path).append(&value);
+        stream.out().to_vec()
+    }
+
+    fn account_leaf(
+        address: Address,
+        nonce: u64,
+        balance: u64,
+        storage_root: Word,
+        code_hash: Word,
+    ) -> Vec<u8> {
+        let mut account = RlpStream::new_list(4);
+        account
+            .append(&nonce)
+            .append(&Word::from(balance))
+            .append(&H256::from_uint(&storage_root))
+            .append(&H256::from_uint(&code_hash));
+        leaf(address.as_bytes(), account.out().to_vec())
+    }
+
+    fn storage_leaf(key: Word, value: u64) -> Vec<u8> {
+        leaf(&key.to_be_bytes(), rlp::encode(&Word::from(value)).to_vec())
+    }
+
+    fn empty_root() -> Word {
+        keccak(&[0x80])
+    }
+
+    fn update(key: MptKey, values: (u64, u64), paths: (Vec<Vec<u8>>, Vec<Vec<u8>>)) -> MptUpdate {
+        MptUpdate {
+            key,
+            old_value: Word::from(values.0),
+            new_value: Word::from(values.1),
+            old_root: keccak(&paths.0[0]),
+            new_root: keccak(&paths.1[0]),
+            proof: MptUpdateProof {
+                old_nodes: paths.0,
+                new_nodes: paths.1,
+            },
+        }
+    }
+
+    /// Update generated by a StateTrie
+    fn trie_update(key: MptKey, values: (u64, u64), update: TrieUpdate) -> MptUpdate {
+        MptUpdate {
+            key,
+            old_value: Word::from(values.0),
+            new_value: Word::from(values.1),
+            old_root: update.old_root.to_word(),
+            new_root: update.new_root.to_word(),
+            proof: MptUpdateProof {
+                old_nodes: update.old_nodes,
+                new_nodes: update.new_nodes,
+            },
+        }
+    }
+
+    fn test_mpt_circuit(updates: Vec<MptUpdate>) -> Result<(), Vec<VerifyFailure>> {
+        let old_root = updates[0].old_root;
+        let circuit = MptCircuit::<Fr>::new(MptUpdates::from_updates(old_root, updates), 0);
+        let prover = MockProver::<Fr>::run(11, &circuit, circuit.instance()).unwrap();
+        prover.verify()
+    }
+
+    /// Returns true if the updates can be proved.  The witness of an update
+    /// that doesn't follow the trie can't be generated.
+    fn is_provable(k: u32, updates: Vec<MptUpdate>) -> bool {
+        let old_root = updates[0].old_root;
+        let circuit = MptCircuit::<Fr>::new(MptUpdates::from_updates(old_root, updates), 0);
+        MockProver::<Fr>::run(k, &circuit, circuit.instance())
+            .map_or(false, |prover| prover.verify().is_ok())
+    }
+
+    fn balance_key(address: Address) -> MptKey {
+        MptKey::Account {
+            address,
+            field_tag: AccountFieldTag::Balance,
+        }
+    }
+
+    fn account_updates() -> Vec<MptUpdate> {
+        let address = Address::repeat_byte(0xaa);
+        let code_hash = keccak(&[]);
+        let leaf_0 = account_leaf(address, 1, 100, empty_root(), code_hash);
+        let leaf_1 = account_leaf(address, 2, 100, empty_root(), code_hash);
+        let leaf_2 = account_leaf(address, 2, 50, empty_root(), code_hash);
+        vec![
+            update(
+                MptKey::Account {
+                    address,
+                    field_tag: AccountFieldTag::Nonce,
+                },
+                (1, 2),
+                (vec![leaf_0], vec![leaf_1.clone()]),
+            ),
+            update(
+                balance_key(address),
+                (100, 50),
+                (vec![leaf_1], vec![leaf_2]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn mpt_circuit_account_updates() {
+        assert_eq!(test_mpt_circuit(account_updates()), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_account_does_not_exist() {
+        let leaf = account_leaf(
+            Address::repeat_byte(0xaa),
+            1,
+            100,
+            empty_root(),
+            keccak(&[]),
+        );
+        let update = update(
+            MptKey::Account {
+                address: Address::repeat_byte(0xbb),
+                field_tag: AccountFieldTag::NonExisting,
+            },
+            (0, 0),
+            (vec![leaf.clone()], vec![leaf]),
+        );
+        assert_eq!(test_mpt_circuit(vec![update]), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_storage_updates() {
+        let address = Address::repeat_byte(0xaa);
+        let storage_key = Word::from(0x1234);
+        let account = |storage_root| account_leaf(address, 1, 100, storage_root, keccak(&[]));
+        let key = MptKey::AccountStorage {
+            tx_id: 1,
+            address,
+            storage_key,
+            exists: true,
+        };
+
+        // Write to a slot of an empty storage trie
+        let storage_1 = storage_leaf(storage_key, 5);
+        let insert = update(
+            key,
+            (0, 5),
+            (
+                vec![account(empty_root()), vec![0x80]],
+                vec![account(keccak(&storage_1)), storage_1.clone()],
+            ),
+        );
+        assert_eq!(test_mpt_circuit(vec![insert]), Ok(()));
+
+        // Update an existing slot
+        let storage_2 = storage_leaf(storage_key, 0x0102030405);
+        let change = update(
+            key,
+            (5, 0x0102030405),
+            (
+                vec![account(keccak(&storage_1)), storage_1],
+                vec![account(keccak(&storage_2)), storage_2],
+            ),
+        );
+        assert_eq!(test_mpt_circuit(vec![change]), Ok(()));
+    }
+
+    /// Updates of a state trie that insert storage slots and accounts,
+    /// splitting the leaves that are moved below new branches, and remove an
+    /// account, collapsing its branch.  The updates are applied in the order
+    /// of their keys.
+    fn trie_updates() -> Vec<MptUpdate> {
+        let [a, b, c, d] = [0x11, 0x22, 0x33, 0x44].map(Address::repeat_byte);
+        let mut trie = StateTrie::default();
+        for (address, balance) in [(a, 100), (c, 300)] {
+            trie.set_account_field(&address, AccountField::Balance, Word::from(balance))
+                .unwrap();
+        }
+
+        let mut updates = Vec::new();
+        for (storage_key, value) in [(1, 10), (2, 20), (3, 0)] {
+            let storage_key = Word::from(storage_key);
+            let update = trie
+                .set_storage(&a, &storage_key, Word::from(value))
+                .unwrap();
+            updates.push(trie_update(
+                MptKey::AccountStorage {
+                    tx_id: 1,
+                    address: a,
+                    storage_key,
+                    exists: value != 0,
+                },
+                (0, value),
+                update,
+            ));
+        }
+        let update = trie
+            .set_account_field(&a, AccountField::Nonce, Word::one())
+            .unwrap();
+        updates.push(trie_update(
+            MptKey::Account {
+                address: a,
+                field_tag: AccountFieldTag::Nonce,
+            },
+            (0, 1),
+            update,
+        ));
+        for (address, values) in [(b, (0, 200)), (c, (300, 0))] {
+            let update = trie
+                .set_account_field(&address, AccountField::Balance, Word::from(values.1))
+                .unwrap();
+            updates.push(trie_update(balance_key(address), values, update));
+        }
+        let proof = trie.account_proof(&d).unwrap();
+        updates.push(trie_update(
+            MptKey::Account {
+                address: d,
+                field_tag: AccountFieldTag::NonExisting,
+            },
+            (0, 0),
+            TrieUpdate {
+                old_root: trie.root(),
+                new_root: trie.root(),
+                old_nodes: proof.clone(),
+                new_nodes: proof,
+            },
+        ));
+        updates
+    }
+
+    #[test]
+    fn mpt_circuit_trie_updates() {
+        assert!(is_provable(14, trie_updates()));
+    }
+
+    #[test]
+    fn mpt_circuit_wrong_key() {
+        // The proof of the balance of an account is used for another account.
+        let mut trie = StateTrie::default();
+        trie.set_account_field(
+            &Address::repeat_byte(0x11),
+            AccountField::Balance,
+            Word::from(100),
+        )
+        .unwrap();
+        let update = trie
+            .set_account_field(
+                &Address::repeat_byte(0x22),
+                AccountField::Balance,
+                Word::from(100),
+            )
+            .unwrap();
+        let update = trie_update(balance_key(Address::repeat_byte(0x55)), (0, 100), update);
+        assert!(!is_provable(12, vec![update]));
+    }
+
+    #[test]
+    fn mpt_circuit_changed_sibling() {
+        // The new path is the one of a trie where another account has also
+        // changed.
+        let [a, b] = [0x11, 0x22].map(Address::repeat_byte);
+        let mut trie = StateTrie::default();
+        for address in [a, b] {
+            trie.set_account_field(&address, AccountField::Balance, Word::from(100))
+                .unwrap();
+        }
+        let old_root = trie.root();
+        let old_nodes = trie.account_proof(&a).unwrap();
+        trie.set_account_field(&b, AccountField::Balance, Word::from(200))
+            .unwrap();
+        let update = trie
+            .set_account_field(&a, AccountField::Balance, Word::from(50))
+            .unwrap();
+        let update = trie_update(
+            balance_key(a),
+            (100, 50),
+            TrieUpdate {
+                old_root,
+                old_nodes,
+                ..update
+            },
+        );
+        assert!(!is_provable(12, vec![update]));
+    }
+
+    #[test]
+    fn mpt_circuit_wrong_drift() {
+        // The leaf that is moved below the new branch is changed.
+        let [a, b] = [0x11, 0x22].map(Address::repeat_byte);
+        let mut trie = StateTrie::default();
+        trie.set_account_field(&a, AccountField::Balance, Word::from(100))
+            .unwrap();
+        let old_root = trie.root();
+        let old_nodes = trie.account_proof(&a).unwrap();
+        trie.set_account_field(&a, AccountField::Balance, Word::from(150))
+            .unwrap();
+        let update = trie
+            .set_account_field(&b, AccountField::Balance, Word::from(100))
+            .unwrap();
+        let mut update = trie_update(balance_key(b), (0, 100), update);
+        update.old_root = old_root.to_word();
+        update.proof.old_nodes = old_nodes;
+        assert!(!is_provable(12, vec![update]));
+    }
+
+    #[test]
+    fn mpt_circuit_from_block() {
+        let code = bytecode! {
+            PUSH1(0x2a)
+            PUSH1(0x01)
+            SSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(block.mpt_updates.old_root(), block.prev_state_root);
+        // The state root after the block is the one of the committed StateDB
+        assert_eq!(
+            block.mpt_updates.new_root(),
+            builder.sdb.state_root().unwrap().to_word()
+        );
+
+        let circuit = MptCircuit::<Fr>::new_from_block(&block);
+        let prover = MockProver::<Fr>::run(14, &circuit, circuit.instance()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_wrong_value() {
+        let mut updates = account_updates();
+        updates[1].new_value = Word::from(51);
+        assert!(test_mpt_circuit(updates).is_err());
+    }
+
+    #[test]
+    fn mpt_circuit_broken_root_chain() {
+        // The balance update is valid by itself, but it's applied to the trie
+        // before the nonce update.
+        let address = Address::repeat_byte(0xaa);
+        let code_hash = keccak(&[]);
+        let mut updates = account_updates();
+        updates[1] = update(
+            updates[1].key,
+            (100, 50),
+            (
+                vec![account_leaf(address, 1, 100, empty_root(), code_hash)],
+                vec![account_leaf(address, 1, 50, empty_root(), code_hash)],
+            ),
+        );
+        assert!(test_mpt_circuit(updates).is_err());
+    }
+}
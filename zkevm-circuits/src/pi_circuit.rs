@@ -1,33 +1,59 @@
 //! Public Input Circuit implementation
 
+mod commitment;
+mod header;
+mod rw_commitment;
+mod tx_list;
+mod withdrawals;
+
 use std::marker::PhantomData;
 
-use eth_types::geth_types::BlockConstants;
+use eth_types::geth_types::{block_header_rlp, BlockConstants, Withdrawal};
 use eth_types::sign_types::SignData;
-use eth_types::H256;
 use eth_types::{
     geth_types::Transaction, Address, BigEndianHash, Field, ToBigEndian, ToLittleEndian, ToScalar,
-    Word,
+    ToWord, Word,
 };
-use halo2_proofs::plonk::{Instance, SecondPhase};
+use eth_types::{Bytes, H256, H64};
+use ethers_core::types::Bloom;
+use ethers_core::utils::keccak256;
+use halo2_proofs::plonk::{Expression, Instance, SecondPhase};
 
+use crate::evm_circuit::util::pow_of_two;
 use crate::table::BlockTable;
+use crate::table::KeccakTable;
+use crate::table::LookupTable;
+use crate::table::RlpTable;
+use crate::table::RwTable;
 use crate::table::TxFieldTag;
 use crate::table::TxTable;
+use crate::table::WithdrawalTable;
 use crate::tx_circuit::TX_LEN;
-use crate::util::{random_linear_combine_word as rlc, Challenges, SubCircuit, SubCircuitConfig};
+use crate::util::{
+    random_linear_combine_word as rlc,
+    rlp::RlpByteTable,
+    trie::{list_trie, list_trie_keccak_inputs, list_trie_num_rows, split_root, ListTrieConfig},
+    Challenges, SubCircuit, SubCircuitConfig,
+};
 use crate::witness;
+use commitment::{rpi_bytes_len, rpi_fields, PiCommitmentConfig};
 use gadgets::is_zero::IsZeroChip;
 use gadgets::util::{not, or, Expr};
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Selector},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Selector, VirtualCells},
     poly::Rotation,
 };
+use header::{block_tag_factor, header_region_len, BlockHeaderConfig};
+use itertools::Itertools;
+use rw_commitment::{rw_commitment_region_len, RwCommitmentConfig};
+pub use tx_list::{decode_tx_list, encode_tx_list, tx_list_max_len, TX_LIST_ENTRY_MAX_FIXED_BYTES};
+use tx_list::{tx_list_region_len, TxListConfig};
+use withdrawals::{withdrawals_region_len, WithdrawalsConfig};
 
 /// Fixed by the spec
 const BLOCK_LEN: usize = 7 + 256;
-const EXTRA_LEN: usize = 2;
+const EXTRA_LEN: usize = 6;
 const ZERO_BYTE_GAS_COST: u64 = 4;
 const NONZERO_BYTE_GAS_COST: u64 = 16;
 
@@ -56,15 +82,25 @@ pub struct TxValues {
     value: Word,
     call_data_len: u64,
     call_data_gas_cost: u64,
+    tx_type: u64,
+    chain_id: u64,
+    max_fee_per_gas: Word,
+    max_priority_fee_per_gas: Word,
+    access_list_addresses_len: u64,
+    access_list_storage_keys_len: u64,
+    block_number: u64,
     tx_sign_hash: [u8; 32],
 }
 
 /// Extra values (not contained in block or tx tables)
 #[derive(Default, Debug, Clone)]
 pub struct ExtraValues {
-    // block_hash: H256,
+    block_hash: H256,
     state_root: H256,
     prev_state_root: H256,
+    chunk_state_root: H256,
+    prev_rw_commitment: H256,
+    rw_commitment: H256,
 }
 
 /// PublicData contains all the values that the PiCircuit recieves as input
@@ -77,12 +113,60 @@ pub struct PublicData {
     pub history_hashes: Vec<Word>,
     /// Block Transactions
     pub transactions: Vec<eth_types::Transaction>,
-    /// Block State Root
+    /// Transactions of the previous blocks of a batch, with the numbers of
+    /// their blocks, which precede `transactions` in the tx table.  Empty
+    /// when the block is proved alone.
+    pub prev_transactions: Vec<eth_types::Transaction>,
+    /// Block State Root, after the last block of the batch
     pub state_root: H256,
-    /// Previous block root
+    /// Previous block root, before the first block of the batch
     pub prev_state_root: H256,
+    /// State root after the chunk of the block that is proved, which is
+    /// `state_root` for the last chunk.
+    pub chunk_state_root: H256,
+    /// Rw operations of the chunk of the block, without the Start ones, in
+    /// the order of their rw counters
+    pub rws: Vec<witness::Rw>,
+    /// Digest of the rw operations of the previous chunk of the block, see
+    /// [`RwMap::commitment`](witness::RwMap::commitment)
+    pub prev_rw_digest: H256,
+    /// Rw operation of the previous chunks of the block with the highest rw
+    /// counter, or `None` for the first chunk
+    pub prev_last_rw: Option<witness::Rw>,
     /// Constants related to Ethereum block
     pub block_constants: BlockConstants,
+    /// Hash of the ommers list
+    pub ommers_hash: H256,
+    /// Block Receipts Root
+    pub receipts_root: H256,
+    /// Bloom filter of the logs of the block
+    pub logs_bloom: Bloom,
+    /// Gas used by the block
+    pub gas_used: Word,
+    /// Extra data of the block, up to 32 bytes
+    pub extra_data: Bytes,
+    /// Mix hash
+    pub mix_hash: H256,
+    /// Nonce
+    pub nonce: H64,
+    /// RLP encoded headers of the blocks of the history hashes, aligned with
+    /// `history_hashes`.  The parent hash of each header is the previous
+    /// history hash, or zero for the genesis block.  The header of the oldest
+    /// of 256 history hashes is not needed.
+    pub history_headers: Vec<Vec<u8>>,
+    /// Headers of the previous blocks of a batch, oldest first, whose hashes
+    /// are the last history hashes.  Empty when the block is proved alone.
+    pub prev_block_headers: Vec<eth_types::Block<()>>,
+    /// RLP encoded tx list posted by the proposer of the block, whose valid
+    /// entries are the transactions (see [`decode_tx_list`]).  When `None`,
+    /// the tx list is the encoding of `transactions`.
+    pub tx_list: Option<Bytes>,
+    /// Withdrawals of the block (EIP-4895)
+    pub withdrawals: Vec<Withdrawal>,
+    /// Withdrawals of the previous blocks of a batch, aligned with
+    /// `prev_block_headers`, which precede `withdrawals` in the
+    /// WithdrawalTable.  Empty when none of them has withdrawals.
+    pub prev_withdrawals: Vec<Vec<Withdrawal>>,
 }
 
 impl PublicData {
@@ -121,6 +205,7 @@ impl PublicData {
                 .expect("Error computing tx_sign_hash");
             let mut msg_hash_le = [0u8; 32];
             msg_hash_le.copy_from_slice(sign_data.msg_hash.to_bytes().as_slice());
+            let access_list = tx.access_list.clone().unwrap_or_default();
             tx_vals.push(TxValues {
                 nonce: tx.nonce,
                 gas_price: tx.gas_price,
@@ -137,6 +222,17 @@ impl PublicData {
                         NONZERO_BYTE_GAS_COST
                     }
                 }),
+                tx_type: tx.tx_type as u64,
+                chain_id,
+                max_fee_per_gas: tx.gas_fee_cap,
+                max_priority_fee_per_gas: tx.gas_tip_cap,
+                access_list_addresses_len: access_list.0.len() as u64,
+                access_list_storage_keys_len: access_list
+                    .0
+                    .iter()
+                    .map(|item| item.storage_keys.len() as u64)
+                    .sum(),
+                block_number: tx.block_number,
                 tx_sign_hash: msg_hash_le,
             });
         }
@@ -146,14 +242,377 @@ impl PublicData {
     /// Returns struct with the extra values
     pub fn get_extra_values(&self) -> ExtraValues {
         ExtraValues {
-            // block_hash: self.hash.unwrap_or_else(H256::zero),
+            block_hash: self.block_hash(),
             state_root: self.state_root,
             prev_state_root: self.prev_state_root,
+            chunk_state_root: self.chunk_state_root,
+            prev_rw_commitment: self.prev_rw_commitment(),
+            rw_commitment: self.rw_commitment(),
+        }
+    }
+
+    /// Returns the commitment to the rw operations of the previous chunks of
+    /// the block, opened from its last row and digest, or zero for the first
+    /// chunk.
+    pub fn prev_rw_commitment(&self) -> H256 {
+        self.prev_last_rw
+            .as_ref()
+            .map_or_else(H256::zero, |last_rw| {
+                witness::rw_commitment(Some(last_rw), self.prev_rw_digest)
+            })
+    }
+
+    /// Returns the digest of the rw operations of the chunk, chained to
+    /// [`Self::prev_rw_commitment`].
+    pub fn rw_digest(&self) -> H256 {
+        H256(keccak256(self.rw_digest_input()))
+    }
+
+    /// Returns the commitment to the rw operations of the chunk, see
+    /// [`RwMap::commitment`](witness::RwMap::commitment).
+    pub fn rw_commitment(&self) -> H256 {
+        witness::rw_commitment(self.rws.last(), self.rw_digest())
+    }
+
+    /// Returns the rw counter of the first operation of the chunk: the one
+    /// of the EndBlock reads that end the previous chunk, which read the
+    /// tx id before the last row of the previous chunks.
+    pub fn rw_counter_start(&self) -> usize {
+        self.prev_last_rw
+            .as_ref()
+            .map_or(1, |last_rw| last_rw.rw_counter() - 1)
+    }
+
+    /// Returns the id of the first tx of the chunk, the one after the tx
+    /// whose cumulative gas used is the last row of the previous chunks.
+    pub fn first_tx_id(&self) -> usize {
+        self.prev_last_rw
+            .as_ref()
+            .map_or(1, |last_rw| last_rw.id().unwrap_or_default() + 1)
+    }
+
+    /// Input of the keccak hash of [`Self::rw_digest`]
+    fn rw_digest_input(&self) -> Vec<u8> {
+        self.prev_rw_commitment()
+            .as_bytes()
+            .iter()
+            .copied()
+            .chain(self.rws.iter().flat_map(|rw| rw.to_be_bytes()))
+            .collect()
+    }
+
+    /// Inputs of the keccak hashes of the rw commitments: the opening of the
+    /// previous one, if any, the digest of the chunk and its commitment.
+    fn rw_commitment_keccak_inputs(&self) -> Vec<Vec<u8>> {
+        let record = |rw: Option<&witness::Rw>| {
+            rw.map_or_else(|| vec![0; witness::RW_RECORD_LEN], |rw| rw.to_be_bytes())
+        };
+        self.prev_last_rw
+            .as_ref()
+            .map(|last_rw| {
+                [
+                    record(Some(last_rw)),
+                    self.prev_rw_digest.as_bytes().to_vec(),
+                ]
+                .concat()
+            })
+            .into_iter()
+            .chain([
+                self.rw_digest_input(),
+                [
+                    record(self.rws.last()),
+                    self.rw_digest().as_bytes().to_vec(),
+                ]
+                .concat(),
+            ])
+            .collect()
+    }
+
+    /// Returns the header of the block, whose parent hash is the last history
+    /// hash.
+    pub fn block_header(&self) -> eth_types::Block<()> {
+        eth_types::Block {
+            parent_hash: self
+                .history_hashes
+                .last()
+                .map(H256::from_uint)
+                .unwrap_or_default(),
+            uncles_hash: self.ommers_hash,
+            author: Some(self.block_constants.coinbase),
+            state_root: self.state_root,
+            transactions_root: self.transactions_root(),
+            receipts_root: self.receipts_root,
+            logs_bloom: Some(self.logs_bloom),
+            difficulty: self.block_constants.difficulty,
+            number: Some(self.block_constants.number),
+            gas_limit: self.block_constants.gas_limit,
+            gas_used: self.gas_used,
+            timestamp: self.block_constants.timestamp,
+            extra_data: self.extra_data.clone(),
+            mix_hash: Some(self.mix_hash),
+            nonce: Some(self.nonce),
+            base_fee_per_gas: Some(self.block_constants.base_fee),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the hash of the block, the keccak hash of its RLP encoded
+    /// header.
+    pub fn block_hash(&self) -> H256 {
+        H256(keccak256(block_header_rlp(&self.block_header())))
+    }
+
+    /// Returns the inputs of the keccak hashes computed by the PI circuit: the
+    /// RLP encoded header of the block, of the blocks of the history hashes
+    /// and of the previous blocks of the batch, the nodes of the transactions
+    /// trie, the tx list, the nodes of the withdrawals tries of the blocks of
+    /// the batch and the rw commitments.
+    pub fn keccak_inputs(&self) -> Vec<Vec<u8>> {
+        std::iter::once(block_header_rlp(&self.block_header()))
+            .chain(self.history_headers.iter().cloned())
+            .chain(self.prev_block_headers.iter().map(block_header_rlp))
+            .chain(list_trie_keccak_inputs(&self.signed_txs()))
+            .chain(std::iter::once(self.tx_list_bytes()))
+            .chain(
+                self.block_withdrawals()
+                    .into_iter()
+                    .flat_map(|(_, withdrawals)| {
+                        list_trie_keccak_inputs(&withdrawals_rlp(withdrawals))
+                    }),
+            )
+            .chain(self.rw_commitment_keccak_inputs())
+            .collect()
+    }
+
+    /// Returns the tx list of the block.
+    pub fn tx_list_bytes(&self) -> Vec<u8> {
+        match &self.tx_list {
+            Some(tx_list) => tx_list.to_vec(),
+            None => encode_tx_list(&self.signed_txs()),
         }
     }
 
+    /// Returns the keccak hash of the tx list, whose hi and lo 128 bit halves
+    /// are public inputs of the PiCircuit.
+    pub fn tx_list_hash(&self) -> H256 {
+        H256(keccak256(self.tx_list_bytes()))
+    }
+
+    /// Returns the encoding of the signed transactions of the block, in order,
+    /// which are the values of the transactions trie.
+    pub fn signed_txs(&self) -> Vec<Vec<u8>> {
+        let chain_id = self.chain_id.as_u64();
+        self.transactions
+            .iter()
+            .map(|tx| Transaction::from(tx).signed_rlp(chain_id))
+            .collect()
+    }
+
+    /// Returns the root of the transactions trie of the block.
+    pub fn transactions_root(&self) -> H256 {
+        list_trie(&self.signed_txs()).root()
+    }
+
+    /// Returns the RLP encoding of the withdrawals of the block, in order,
+    /// which are the values of the withdrawals trie.
+    pub fn withdrawals_rlp(&self) -> Vec<Vec<u8>> {
+        withdrawals_rlp(&self.withdrawals)
+    }
+
+    /// Returns the root of the withdrawals trie of the block, whose hi and lo
+    /// 128 bit halves follow the hash of the tx list in the public inputs of
+    /// the PiCircuit.
+    pub fn withdrawals_root(&self) -> H256 {
+        list_trie(&self.withdrawals_rlp()).root()
+    }
+
+    /// Returns the number of every block of the batch, oldest first, with its
+    /// withdrawals.
+    fn block_withdrawals(&self) -> Vec<(u64, &[Withdrawal])> {
+        self.prev_block_headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| {
+                (
+                    header.number.unwrap_or_default().as_u64(),
+                    self.prev_withdrawals
+                        .get(index)
+                        .map_or(&[][..], Vec::as_slice),
+                )
+            })
+            .chain(std::iter::once((
+                self.block_constants.number.as_u64(),
+                self.withdrawals.as_slice(),
+            )))
+            .collect()
+    }
+
+    /// Returns the rows of the WithdrawalTable: the withdrawals of the blocks
+    /// of the batch, in order, and the numbers of their blocks.
+    pub fn table_withdrawals(&self) -> (Vec<Withdrawal>, Vec<u64>) {
+        self.block_withdrawals()
+            .into_iter()
+            .flat_map(|(number, withdrawals)| {
+                withdrawals
+                    .iter()
+                    .map(move |withdrawal| (withdrawal.clone(), number))
+            })
+            .unzip()
+    }
+
+    /// Returns the number of every previous block of the batch, oldest first,
+    /// with the root of its withdrawals trie.  They follow the withdrawals
+    /// root of the block in the public inputs of the PiCircuit.
+    pub fn prev_withdrawals_roots(&self) -> Vec<(u64, H256)> {
+        let mut block_withdrawals = self.block_withdrawals();
+        block_withdrawals.pop();
+        block_withdrawals
+            .into_iter()
+            .map(|(number, withdrawals)| (number, list_trie(&withdrawals_rlp(withdrawals)).root()))
+            .collect()
+    }
+
+    /// Returns the serialization of the raw public inputs hashed by the
+    /// PiCircuit with [`PiCommitment::Keccak`]: the block values, the extra
+    /// values, the tx table padded to `max_txs` txs and the calldata padded
+    /// to `max_calldata` bytes.  Numbers are serialized as 1, 8 or 20 (for
+    /// addresses) big endian bytes, and words and hashes as 32 big endian
+    /// bytes.
+    pub fn rpi_bytes(&self, max_txs: usize, max_calldata: usize) -> Vec<u8> {
+        rpi_fields(self, max_txs, max_calldata)
+            .into_iter()
+            .flat_map(|(_, bytes)| bytes)
+            .collect()
+    }
+
+    /// Returns the keccak hash of the serialized raw public inputs, whose hi
+    /// and lo 128 bit halves are the public inputs of the PiCircuit with
+    /// [`PiCommitment::Keccak`].
+    pub fn hash(&self, max_txs: usize, max_calldata: usize) -> H256 {
+        H256(keccak256(self.rpi_bytes(max_txs, max_calldata)))
+    }
+
+    /// Returns the PI randomness of the PiCircuit with
+    /// [`PiCommitment::Keccak`], derived from the hash of the serialized raw
+    /// public inputs as `hash_hi * 2^128 + hash_lo`.
+    pub fn keccak_randomness<F: Field>(&self, max_txs: usize, max_calldata: usize) -> F {
+        let [hi, lo] = split_root::<F>(self.hash(max_txs, max_calldata).to_word());
+        hi * pow_of_two::<F>(128) + lo
+    }
+
+    /// Returns the transactions of the tx table: the ones of the previous
+    /// blocks of the batch followed by the ones of the block.
     fn txs(&self) -> Vec<Transaction> {
-        self.transactions.iter().map(Transaction::from).collect()
+        let number = self.block_constants.number.as_u64();
+        self.prev_transactions
+            .iter()
+            .map(Transaction::from)
+            .chain(self.transactions.iter().map(|tx| Transaction {
+                block_number: number,
+                ..Transaction::from(tx)
+            }))
+            .collect()
+    }
+}
+
+/// Convert a witness block to the public data of the PI circuit.  For a batch
+/// of blocks, the block values are the ones of the last block, while the
+/// previous state root is the one before the first block of the batch.  For a
+/// chunk of a block, the previous state root is the one before the chunk.
+pub fn public_data_convert<F: Field>(block: &witness::Block<F>) -> PublicData {
+    let context = block.context.last();
+    let chunk = &block.context.chunk;
+    // The state root after the updates proved by the MPT circuit
+    let chunk_state_root = H256::from_uint(&block.mpt_updates.new_root());
+    let mut prev_transactions = block.eth_txs();
+    prev_transactions.truncate(prev_transactions.len() - block.eth_block.transactions.len());
+    let withdrawals_of = |number: u64| -> Vec<Withdrawal> {
+        block
+            .withdrawals
+            .iter()
+            .zip_eq(&block.withdrawal_blocks)
+            .filter(|(_, block_number)| **block_number == number)
+            .map(|(withdrawal, _)| withdrawal.clone())
+            .collect()
+    };
+    PublicData {
+        chain_id: context.chain_id,
+        history_hashes: context.history_hashes.clone(),
+        transactions: block.eth_block.transactions.clone(),
+        prev_transactions,
+        state_root: if chunk.is_last() {
+            chunk_state_root
+        } else {
+            H256::from_uint(&chunk.block_state_root)
+        },
+        prev_state_root: H256::from_uint(&block.prev_state_root),
+        chunk_state_root,
+        rws: block
+            .rws
+            .0
+            .values()
+            .flatten()
+            .filter(|rw| !matches!(rw, witness::Rw::Start { .. }))
+            .sorted_by_key(|rw| rw.rw_counter())
+            .copied()
+            .collect(),
+        prev_rw_digest: chunk.prev_rw_digest,
+        prev_last_rw: chunk.prev_last_rw,
+        block_constants: BlockConstants {
+            coinbase: context.coinbase,
+            timestamp: context.timestamp,
+            number: context.number.as_u64().into(),
+            difficulty: context.difficulty,
+            gas_limit: context.gas_limit.into(),
+            base_fee: context.base_fee,
+            mix_hash: block.eth_block.mix_hash.unwrap_or_default(),
+        },
+        ommers_hash: block.eth_block.uncles_hash,
+        receipts_root: block.eth_block.receipts_root,
+        logs_bloom: block.eth_block.logs_bloom.unwrap_or_default(),
+        gas_used: block.eth_block.gas_used,
+        extra_data: block.eth_block.extra_data.clone(),
+        mix_hash: block.eth_block.mix_hash.unwrap_or_default(),
+        nonce: block.eth_block.nonce.unwrap_or_default(),
+        history_headers: context.history_headers.clone(),
+        prev_block_headers: block.prev_eth_blocks.iter().map(header_of).collect(),
+        // The tx list is the encoding of the transactions of the block
+        tx_list: None,
+        withdrawals: withdrawals_of(context.number.as_u64()),
+        prev_withdrawals: block
+            .prev_eth_blocks
+            .iter()
+            .map(|eth_block| withdrawals_of(eth_block.number.unwrap_or_default().as_u64()))
+            .collect(),
+    }
+}
+
+/// Returns the RLP encoding of `withdrawals`, which are the values of the
+/// withdrawals trie of their block.
+fn withdrawals_rlp(withdrawals: &[Withdrawal]) -> Vec<Vec<u8>> {
+    withdrawals.iter().map(Withdrawal::rlp).collect()
+}
+
+/// Returns the header of `block`, without its transactions.
+fn header_of<TX>(block: &eth_types::Block<TX>) -> eth_types::Block<()> {
+    eth_types::Block {
+        parent_hash: block.parent_hash,
+        uncles_hash: block.uncles_hash,
+        author: block.author,
+        state_root: block.state_root,
+        transactions_root: block.transactions_root,
+        receipts_root: block.receipts_root,
+        logs_bloom: block.logs_bloom,
+        difficulty: block.difficulty,
+        number: block.number,
+        gas_limit: block.gas_limit,
+        gas_used: block.gas_used,
+        timestamp: block.timestamp,
+        extra_data: block.extra_data.clone(),
+        mix_hash: block.mix_hash,
+        nonce: block.nonce,
+        base_fee_per_gas: block.base_fee_per_gas,
+        ..Default::default()
     }
 }
 
@@ -165,10 +624,10 @@ pub struct PiCircuitConfig<F: Field> {
     /// Max number of supported calldata bytes
     max_calldata: usize,
 
-    q_block_table: Selector,
     q_tx_table: Selector,
     q_tx_calldata: Selector,
     q_calldata_start: Selector,
+    q_tx_block_number: Selector,
 
     tx_id_inv: Column<Advice>,
     tx_value_inv: Column<Advice>,
@@ -176,6 +635,12 @@ pub struct PiCircuitConfig<F: Field> {
     fixed_u16: Column<Fixed>,
     calldata_gas_cost: Column<Advice>,
     is_final: Column<Advice>,
+    /// Number of the block, in the BlockNumber rows of the txs
+    block_number: Column<Advice>,
+    block_number_diff_inv: Column<Advice>,
+    /// Whether the tx of a BlockNumber row is a tx of the block, and so a
+    /// value of the transactions trie
+    is_block_tx: Column<Advice>,
 
     raw_public_inputs: Column<Advice>,
     rpi_rlc_acc: Column<Advice>,
@@ -183,16 +648,47 @@ pub struct PiCircuitConfig<F: Field> {
     q_not_end: Selector,
     q_end: Selector,
 
-    pi: Column<Instance>, // rpi_rand, rpi_rlc, chain_ID, state_root, prev_state_root
+    // rpi_rand, rpi_rlc, chain_ID, state_root, prev_state_root, block_hash, randomness,
+    // chunk_state_root, prev_rw_commitment, rw_commitment, or the hash of the raw
+    // public inputs, followed by the hash of the tx list, the withdrawals root and
+    // the number and withdrawals root of every previous block of the batch
+    pi: Column<Instance>,
+
+    /// Table of the bytes and their RLP classes, shared by the regions that
+    /// range check or decode bytes.
+    byte_table: RlpByteTable,
+    header: BlockHeaderConfig<F>,
+    /// Trie of the signed transactions, whose root is the transactions root
+    /// of the header.  The values of the trie are the signed encodings of the
+    /// txs of the TxTable with the number of the block, in the RlpTable.
+    tx_root: ListTrieConfig<F>,
+    /// Tx list posted by the proposer, whose valid entries are the values of
+    /// the transactions trie.
+    tx_list: TxListConfig<F>,
+    /// Tries of the withdrawals of the blocks of the batch, whose roots are
+    /// public inputs.  The paths are keyed by `id_offset`, the number of
+    /// their block.
+    withdrawals_root: ListTrieConfig<F>,
+    /// Withdrawals of the batch, which are the values of the withdrawals
+    /// tries and the rows of the WithdrawalTable.
+    withdrawals: WithdrawalsConfig<F>,
+    /// Rw commitment of the chunk, which is the commitment to the rows of
+    /// the RwTable, and opening of the previous rw commitment.
+    rw_commitment: RwCommitmentConfig<F>,
+    commitment: PiCommitmentConfig<F>,
 
     _marker: PhantomData<F>,
     // External tables
     block_table: BlockTable,
     tx_table: TxTable,
+    keccak_table: KeccakTable,
+    rlp_table: RlpTable,
+    withdrawal_table: WithdrawalTable,
+    rw_table: RwTable,
 }
 
 /// Circuit configuration arguments
-pub struct PiCircuitConfigArgs {
+pub struct PiCircuitConfigArgs<F: Field> {
     /// Max number of supported transactions
     pub max_txs: usize,
     /// Max number of supported calldata bytes
@@ -201,10 +697,20 @@ pub struct PiCircuitConfigArgs {
     pub tx_table: TxTable,
     /// BlockTable
     pub block_table: BlockTable,
+    /// KeccakTable
+    pub keccak_table: KeccakTable,
+    /// RlpTable
+    pub rlp_table: RlpTable,
+    /// WithdrawalTable
+    pub withdrawal_table: WithdrawalTable,
+    /// RwTable
+    pub rw_table: RwTable,
+    /// Challenges
+    pub challenges: Challenges<Expression<F>>,
 }
 
 impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
-    type ConfigArgs = PiCircuitConfigArgs;
+    type ConfigArgs = PiCircuitConfigArgs<F>;
 
     /// Return a new PiCircuitConfig
     fn new(
@@ -214,13 +720,17 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             max_calldata,
             block_table,
             tx_table,
+            keccak_table,
+            rlp_table,
+            withdrawal_table,
+            rw_table,
+            challenges,
         }: Self::ConfigArgs,
     ) -> Self {
-        let q_block_table = meta.selector();
-
         let q_tx_table = meta.complex_selector();
         let q_tx_calldata = meta.complex_selector();
         let q_calldata_start = meta.complex_selector();
+        let q_tx_block_number = meta.complex_selector();
         // Tx Table
         let tx_id = tx_table.tx_id;
         let tx_value = tx_table.value;
@@ -236,6 +746,9 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
         let fixed_u16 = meta.fixed_column();
         let calldata_gas_cost = meta.advice_column_in(SecondPhase);
         let is_final = meta.advice_column();
+        let block_number = meta.advice_column();
+        let block_number_diff_inv = meta.advice_column();
+        let is_block_tx = meta.advice_column();
 
         let raw_public_inputs = meta.advice_column_in(SecondPhase);
         let rpi_rlc_acc = meta.advice_column_in(SecondPhase);
@@ -249,6 +762,7 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
         meta.enable_equality(rpi_rlc_acc);
         meta.enable_equality(rand_rpi);
         meta.enable_equality(pi);
+        meta.enable_equality(block_number);
 
         // 0.0 rpi_rlc_acc[0] == RLC(raw_public_inputs, rand_rpi)
         meta.create_gate(
@@ -284,15 +798,6 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             vec![q_not_end * (cur_rand_rpi - next_rand_rpi)]
         });
 
-        // 0.2 Block table -> value column match with raw_public_inputs at expected
-        // offset
-        meta.create_gate("block_table[i] = raw_public_inputs[offset + i]", |meta| {
-            let q_block_table = meta.query_selector(q_block_table);
-            let block_value = meta.query_advice(block_table.value, Rotation::cur());
-            let rpi_block_value = meta.query_advice(raw_public_inputs, Rotation::cur());
-            vec![q_block_table * (block_value - rpi_block_value)]
-        });
-
         let offset = BLOCK_LEN + 1 + EXTRA_LEN;
         let tx_table_len = max_txs * TX_LEN + 1;
 
@@ -519,14 +1024,122 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             ]
         });
 
+        let byte_table = RlpByteTable::configure(meta);
+        let header =
+            BlockHeaderConfig::configure(meta, byte_table.byte, keccak_table, challenges.clone());
+
+        // Every row of the BlockTable is a row proved by the header regions,
+        // but the IsLastChunk row, which is scaled to the all-zero row.
+        meta.lookup_any("block table rows are proved by the headers", |meta| {
+            let block_row = block_table.table_exprs(meta);
+            let factor = block_tag_factor(block_row[0].clone());
+            std::iter::once(factor.clone())
+                .chain(block_row.into_iter().map(|expr| factor.clone() * expr))
+                .zip(header.block_table_row(meta))
+                .collect()
+        });
+        let tx_root = ListTrieConfig::configure(meta, &byte_table, &keccak_table, &challenges);
+
+        // The txs of the block are the values of the transactions trie: the
+        // ids of the paths of the trie, offset by the number of txs of the
+        // previous blocks of the batch, are the ids of the txs of the TxTable
+        // whose BlockNumber is the number of the block, and the values are
+        // their signed encodings in the RlpTable.
+        let block_number_is_current = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(q_tx_block_number),
+            |meta| {
+                meta.query_advice(tx_value, Rotation::cur())
+                    - meta.query_advice(block_number, Rotation::cur())
+            },
+            block_number_diff_inv,
+        );
+        meta.create_gate("is_block_tx = tx.block_number == block.number", |meta| {
+            let q_tx_block_number = meta.query_selector(q_tx_block_number);
+            let is_block_tx = meta.query_advice(is_block_tx, Rotation::cur());
+            vec![q_tx_block_number * (is_block_tx - block_number_is_current.expr())]
+        });
+        let tx_root_id = |meta: &mut VirtualCells<'_, F>| {
+            meta.query_advice(tx_root.id, Rotation::cur())
+                + meta.query_advice(tx_root.id_offset, Rotation::cur())
+        };
+        let block_tx_id = |meta: &mut VirtualCells<'_, F>| {
+            meta.query_selector(q_tx_block_number)
+                * meta.query_advice(is_block_tx, Rotation::cur())
+                * meta.query_advice(tx_id, Rotation::cur())
+        };
+        meta.lookup_any(
+            "the values of the transactions trie are txs of the block",
+            |meta| {
+                let enable = tx_root.path_end(meta);
+                vec![(enable * tx_root_id(meta), block_tx_id(meta))]
+            },
+        );
+        meta.lookup_any(
+            "the txs of the block are values of the transactions trie",
+            |meta| {
+                let path_end = tx_root.path_end(meta);
+                vec![(block_tx_id(meta), path_end * tx_root_id(meta))]
+            },
+        );
+        meta.lookup_any(
+            "the values of the transactions trie are the signed txs",
+            |meta| {
+                let enable = tx_root.path_end(meta);
+                [
+                    tx_root_id(meta),
+                    TxFieldTag::SignedTx.expr(),
+                    meta.query_advice(tx_root.value_len, Rotation::cur()),
+                    meta.query_advice(tx_root.value_rlc, Rotation::cur()),
+                ]
+                .into_iter()
+                .zip(rlp_table.table_exprs(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+            },
+        );
+
+        let tx_list = TxListConfig::configure(
+            meta,
+            max_txs,
+            max_calldata,
+            &byte_table,
+            &keccak_table,
+            &tx_root,
+            &challenges,
+        );
+        let withdrawals_root =
+            ListTrieConfig::configure(meta, &byte_table, &keccak_table, &challenges);
+        let withdrawals = WithdrawalsConfig::configure(
+            meta,
+            byte_table.byte,
+            &withdrawals_root,
+            &withdrawal_table,
+            &challenges,
+        );
+        let rw_commitment = RwCommitmentConfig::configure(
+            meta,
+            byte_table.byte,
+            &rw_table,
+            &keccak_table,
+            &challenges,
+        );
+        let commitment = PiCommitmentConfig::configure(
+            meta,
+            rpi_bytes_len(max_txs, max_calldata),
+            byte_table.byte,
+            &keccak_table,
+            &challenges,
+        );
+
         Self {
             max_txs,
             max_calldata,
-            q_block_table,
             block_table,
             q_tx_table,
             q_tx_calldata,
             q_calldata_start,
+            q_tx_block_number,
             tx_table,
             tx_id_inv,
             tx_value_inv,
@@ -534,12 +1147,27 @@ impl<F: Field> SubCircuitConfig<F> for PiCircuitConfig<F> {
             fixed_u16,
             calldata_gas_cost,
             is_final,
+            block_number,
+            block_number_diff_inv,
+            is_block_tx,
             raw_public_inputs,
             rpi_rlc_acc,
             rand_rpi,
             q_not_end,
             q_end,
             pi,
+            byte_table,
+            header,
+            tx_root,
+            tx_list,
+            withdrawals_root,
+            withdrawals,
+            rw_commitment,
+            commitment,
+            keccak_table,
+            rlp_table,
+            withdrawal_table,
+            rw_table,
             _marker: PhantomData,
         }
     }
@@ -604,8 +1232,8 @@ impl<F: Field> PiCircuitConfig<F> {
         )?;
         Ok(())
     }
-    /// Assigns a tx_table row and stores the values in a vec for the
-    /// raw_public_inputs column
+    /// Assigns a tx_table row and stores the values and the cells of the
+    /// raw_public_inputs column in vecs
     #[allow(clippy::too_many_arguments)]
     fn assign_tx_row(
         &self,
@@ -616,6 +1244,7 @@ impl<F: Field> PiCircuitConfig<F> {
         index: usize,
         tx_value: F,
         raw_pi_vals: &mut [F],
+        raw_pi_cells: &mut [Option<AssignedCell<F, F>>],
     ) -> Result<(), Error> {
         let tx_id = F::from(tx_id as u64);
         // tx_id_inv = (tag - CallDataLength)^(-1)
@@ -672,21 +1301,21 @@ impl<F: Field> PiCircuitConfig<F> {
         let index_offset = id_offset + tx_table_len;
         let value_offset = index_offset + tx_table_len;
 
-        region.assign_advice(
+        let tx_id_cell = region.assign_advice(
             || "raw_pi.tx_id",
             self.raw_public_inputs,
             offset + id_offset,
             || Value::known(tx_id),
         )?;
 
-        region.assign_advice(
+        let index_cell = region.assign_advice(
             || "raw_pi.tx_index",
             self.raw_public_inputs,
             offset + index_offset,
             || Value::known(index),
         )?;
 
-        region.assign_advice(
+        let tx_value_cell = region.assign_advice(
             || "raw_pi.tx_value",
             self.raw_public_inputs,
             offset + value_offset,
@@ -697,7 +1326,38 @@ impl<F: Field> PiCircuitConfig<F> {
         raw_pi_vals[offset + id_offset] = tx_id;
         raw_pi_vals[offset + index_offset] = index;
         raw_pi_vals[offset + value_offset] = tx_value;
+        raw_pi_cells[offset + id_offset] = Some(tx_id_cell);
+        raw_pi_cells[offset + index_offset] = Some(index_cell);
+        raw_pi_cells[offset + value_offset] = Some(tx_value_cell);
+
+        Ok(())
+    }
 
+    /// Assigns the number of the block in the BlockNumber row of a tx, copied
+    /// from `number_cell`, and whether the tx is a tx of the block.
+    fn assign_tx_block_number(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        tx_block_number: u64,
+        number_cell: &AssignedCell<F, F>,
+        number: u64,
+    ) -> Result<(), Error> {
+        self.q_tx_block_number.enable(region, offset)?;
+        number_cell.copy_advice(|| "block number", region, self.block_number, offset)?;
+        let diff = F::from(tx_block_number) - F::from(number);
+        region.assign_advice(
+            || "block_number_diff_inv",
+            self.block_number_diff_inv,
+            offset,
+            || Value::known(diff.invert().unwrap_or(F::zero())),
+        )?;
+        region.assign_advice(
+            || "is_block_tx",
+            self.is_block_tx,
+            offset,
+            || Value::known(F::from((tx_block_number == number) as u64)),
+        )?;
         Ok(())
     }
 
@@ -714,6 +1374,7 @@ impl<F: Field> PiCircuitConfig<F> {
         is_final: bool,
         gas_cost: F,
         raw_pi_vals: &mut [F],
+        raw_pi_cells: &mut [Option<AssignedCell<F, F>>],
     ) -> Result<(), Error> {
         let tx_id = F::from(tx_id as u64);
         let tx_id_inv = tx_id.invert().unwrap_or(F::zero());
@@ -789,7 +1450,7 @@ impl<F: Field> PiCircuitConfig<F> {
 
         let value_offset = BLOCK_LEN + 1 + EXTRA_LEN + 3 * tx_table_len;
 
-        region.assign_advice(
+        let tx_value_cell = region.assign_advice(
             || "raw_pi.tx_value",
             self.raw_public_inputs,
             offset + value_offset,
@@ -798,184 +1459,143 @@ impl<F: Field> PiCircuitConfig<F> {
 
         // Add copy to vec
         raw_pi_vals[offset + value_offset] = tx_value;
+        raw_pi_cells[offset + value_offset] = Some(tx_value_cell);
 
         Ok(())
     }
 
-    /// Assigns the values for block table in the block_table column
-    /// and in the raw_public_inputs column. A copy is also stored in
-    /// a vector for computing RLC(raw_public_inputs)
-    fn assign_block_table(
+    /// Assigns the values of the block table in the raw_public_inputs column,
+    /// which are linked to the header region that proves the rows of the
+    /// block table. A copy is also stored in a vector for computing
+    /// RLC(raw_public_inputs). Returns the cells of the raw_public_inputs
+    /// column.
+    fn assign_block_values(
         &self,
         region: &mut Region<'_, F>,
         block_values: BlockValues,
         randomness: F,
         raw_pi_vals: &mut [F],
-    ) -> Result<AssignedCell<F, F>, Error> {
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let mut cells = Vec::with_capacity(BLOCK_LEN + 1);
         let mut offset = 0;
-        for i in 0..BLOCK_LEN + 1 {
-            self.q_block_table.enable(region, offset + i)?;
-        }
-
         // zero row
-        region.assign_advice(
-            || "zero",
-            self.block_table.value,
-            offset,
-            || Value::known(F::zero()),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "zero",
             self.raw_public_inputs,
             offset,
             || Value::known(F::zero()),
         )?;
         raw_pi_vals[offset] = F::zero();
+        cells.push(cell);
         offset += 1;
 
         // coinbase
         let coinbase = block_values.coinbase.to_scalar().unwrap();
-        region.assign_advice(
-            || "coinbase",
-            self.block_table.value,
-            offset,
-            || Value::known(coinbase),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "coinbase",
             self.raw_public_inputs,
             offset,
             || Value::known(coinbase),
         )?;
         raw_pi_vals[offset] = coinbase;
+        cells.push(cell);
         offset += 1;
 
         // gas_limit
         let gas_limit = F::from(block_values.gas_limit);
-        region.assign_advice(
-            || "gas_limit",
-            self.block_table.value,
-            offset,
-            || Value::known(gas_limit),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "gas_limit",
             self.raw_public_inputs,
             offset,
             || Value::known(gas_limit),
         )?;
         raw_pi_vals[offset] = gas_limit;
+        cells.push(cell);
         offset += 1;
 
         // number
         let number = F::from(block_values.number);
-        region.assign_advice(
-            || "number",
-            self.block_table.value,
-            offset,
-            || Value::known(number),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "number",
             self.raw_public_inputs,
             offset,
             || Value::known(number),
         )?;
         raw_pi_vals[offset] = number;
+        cells.push(cell);
         offset += 1;
 
         // timestamp
         let timestamp = F::from(block_values.timestamp);
-        region.assign_advice(
-            || "timestamp",
-            self.block_table.value,
-            offset,
-            || Value::known(timestamp),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "timestamp",
             self.raw_public_inputs,
             offset,
             || Value::known(timestamp),
         )?;
         raw_pi_vals[offset] = timestamp;
+        cells.push(cell);
         offset += 1;
 
         // difficulty
         let difficulty = rlc(block_values.difficulty.to_le_bytes(), randomness);
-        region.assign_advice(
-            || "difficulty",
-            self.block_table.value,
-            offset,
-            || Value::known(difficulty),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "difficulty",
             self.raw_public_inputs,
             offset,
             || Value::known(difficulty),
         )?;
         raw_pi_vals[offset] = difficulty;
+        cells.push(cell);
         offset += 1;
 
         // base_fee
         let base_fee = rlc(block_values.base_fee.to_le_bytes(), randomness);
-        region.assign_advice(
-            || "base_fee",
-            self.block_table.value,
-            offset,
-            || Value::known(base_fee),
-        )?;
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "base_fee",
             self.raw_public_inputs,
             offset,
             || Value::known(base_fee),
         )?;
         raw_pi_vals[offset] = base_fee;
+        cells.push(cell);
         offset += 1;
 
         // chain_id
         let chain_id = F::from(block_values.chain_id);
-        region.assign_advice(
-            || "chain_id",
-            self.block_table.value,
-            offset,
-            || Value::known(chain_id),
-        )?;
-        let chain_id_cell = region.assign_advice(
+        let cell = region.assign_advice(
             || "chain_id",
             self.raw_public_inputs,
             offset,
             || Value::known(chain_id),
         )?;
         raw_pi_vals[offset] = chain_id;
+        cells.push(cell);
         offset += 1;
 
         for prev_hash in block_values.history_hashes {
             let prev_hash = rlc(prev_hash.to_fixed_bytes(), randomness);
-            region.assign_advice(
-                || "prev_hash",
-                self.block_table.value,
-                offset,
-                || Value::known(prev_hash),
-            )?;
-            region.assign_advice(
+            let cell = region.assign_advice(
                 || "prev_hash",
                 self.raw_public_inputs,
                 offset,
                 || Value::known(prev_hash),
             )?;
+            cells.push(cell);
             raw_pi_vals[offset] = prev_hash;
             offset += 1;
         }
 
-        Ok(chain_id_cell)
+        Ok(cells)
     }
 
     /// Assigns the extra fields (not in block or tx tables):
+    ///   - block hash
     ///   - state root
     ///   - previous block state root
+    ///   - state root after the chunk
+    ///   - rw commitment of the previous chunks
+    ///   - rw commitment of the chunk
     /// to the raw_public_inputs column and stores a copy in a
     /// vector for computing RLC(raw_public_inputs).
     fn assign_extra_fields(
@@ -984,18 +1604,18 @@ impl<F: Field> PiCircuitConfig<F> {
         extra: ExtraValues,
         randomness: F,
         raw_pi_vals: &mut [F],
-    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+    ) -> Result<[AssignedCell<F, F>; EXTRA_LEN], Error> {
         let mut offset = BLOCK_LEN + 1;
         // block hash
-        // let block_hash = rlc(extra.block_hash.to_fixed_bytes(), randomness);
-        // region.assign_advice(
-        //     || "block.hash",
-        //     self.raw_public_inputs,
-        //     offset,
-        //     || Ok(block_hash),
-        // )?;
-        // raw_pi_vals[offset] = block_hash;
-        // offset += 1;
+        let block_hash = rlc(extra.block_hash.to_fixed_bytes(), randomness);
+        let block_hash_cell = region.assign_advice(
+            || "block.hash",
+            self.raw_public_inputs,
+            offset,
+            || Value::known(block_hash),
+        )?;
+        raw_pi_vals[offset] = block_hash;
+        offset += 1;
 
         // block state root
         let state_root = rlc(extra.state_root.to_fixed_bytes(), randomness);
@@ -1017,7 +1637,36 @@ impl<F: Field> PiCircuitConfig<F> {
             || Value::known(prev_state_root),
         )?;
         raw_pi_vals[offset] = prev_state_root;
-        Ok([state_root_cell, prev_state_root_cell])
+        offset += 1;
+
+        // chunk state root and rw commitments, which are the final root of
+        // the MptCircuit and the rw commitments proved by the rw commitment
+        // region.
+        let [chunk_state_root_cell, prev_rw_commitment_cell, rw_commitment_cell] = [
+            ("chunk.state_root", extra.chunk_state_root),
+            ("chunk.prev_rw_commitment", extra.prev_rw_commitment),
+            ("chunk.rw_commitment", extra.rw_commitment),
+        ]
+        .map(|(annotation, value)| {
+            let value = rlc(value.to_fixed_bytes(), randomness);
+            let cell = region.assign_advice(
+                || annotation,
+                self.raw_public_inputs,
+                offset,
+                || Value::known(value),
+            );
+            raw_pi_vals[offset] = value;
+            offset += 1;
+            cell
+        });
+        Ok([
+            block_hash_cell,
+            state_root_cell,
+            prev_state_root_cell,
+            chunk_state_root_cell?,
+            prev_rw_commitment_cell?,
+            rw_commitment_cell?,
+        ])
     }
 
     /// Assign `rpi_rlc_acc` and `rand_rpi` columns
@@ -1083,17 +1732,57 @@ impl<F: Field> PiCircuitConfig<F> {
     }
 }
 
+/// Public inputs of the PiCircuit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PiCommitment {
+    /// The RLC of the raw public inputs with `rand_rpi`, `rand_rpi`, the
+    /// chain id, the state roots, the block hash and the randomness.
+    #[default]
+    Rlc,
+    /// The hi and lo halves of the keccak hash of the serialized raw public
+    /// inputs, see [`PublicData::hash`].
+    Keccak,
+}
+
+/// Cells of the values of the block that are proved by other sub-circuits of
+/// a composition: the state roots before and after the updates of the
+/// MptCircuit, with the randomness of their RLCs, and the number, the receipts
+/// root and the logs bloom of the header, proved by the ReceiptCircuit.
+#[derive(Clone, Debug)]
+pub(crate) struct PiLinkedCells<F: Field> {
+    pub(crate) randomness: AssignedCell<F, F>,
+    pub(crate) prev_state_root: AssignedCell<F, F>,
+    pub(crate) chunk_state_root: AssignedCell<F, F>,
+    pub(crate) number: AssignedCell<F, F>,
+    pub(crate) receipts_root: AssignedCell<F, F>,
+    pub(crate) logs_bloom: AssignedCell<F, F>,
+}
+
+/// Number of slots of the rw commitment region for a RwTable of `max_rws`
+/// rows, or for `num_rws` rows if `max_rws` is 0.
+fn num_rw_slots(max_rws: usize, num_rws: usize) -> usize {
+    if max_rws == 0 {
+        num_rws.max(1)
+    } else {
+        (max_rws - 1).max(1)
+    }
+}
+
 /// Public Inputs Circuit
 #[derive(Clone, Default, Debug)]
 pub struct PiCircuit<F: Field> {
     max_txs: usize,
     max_calldata: usize,
+    /// Max number of rows of the RwTable, or 0 to fit the rows of the chunk
+    max_rws: usize,
     /// Randomness for RLC encdoing
     pub randomness: F,
     /// Randomness for PI encoding
     pub rand_rpi: F,
     /// PublicInputs data known by the verifier
     pub public_data: PublicData,
+    /// Public inputs exposed by the circuit
+    pub commitment: PiCommitment,
 }
 
 impl<F: Field> PiCircuit<F> {
@@ -1101,6 +1790,7 @@ impl<F: Field> PiCircuit<F> {
     pub fn new(
         max_txs: usize,
         max_calldata: usize,
+        max_rws: usize,
         randomness: impl Into<F>,
         rand_rpi: impl Into<F>,
         public_data: PublicData,
@@ -1108,142 +1798,181 @@ impl<F: Field> PiCircuit<F> {
         Self {
             max_txs,
             max_calldata,
+            max_rws,
             randomness: randomness.into(),
             rand_rpi: rand_rpi.into(),
             public_data,
+            commitment: PiCommitment::default(),
         }
     }
-}
 
-impl<F: Field> SubCircuit<F> for PiCircuit<F> {
-    type Config = PiCircuitConfig<F>;
-
-    fn new_from_block(block: &witness::Block<F>) -> Self {
-        let public_data = PublicData {
-            chain_id: block.context.chain_id,
-            history_hashes: block.context.history_hashes.clone(),
-            transactions: block.eth_block.transactions.clone(),
-            state_root: block.eth_block.state_root,
-            prev_state_root: H256::from_uint(&block.prev_state_root),
-            block_constants: BlockConstants {
-                coinbase: block.context.coinbase,
-                timestamp: block.context.timestamp,
-                number: block.context.number.as_u64().into(),
-                difficulty: block.context.difficulty,
-                gas_limit: block.context.gas_limit.into(),
-                base_fee: block.context.base_fee,
-            },
-        };
-        PiCircuit::new(
-            block.circuits_params.max_txs,
-            block.circuits_params.max_calldata,
-            block.randomness,
-            block.randomness + F::from_u128(1),
-            public_data,
-        )
+    /// Sets the public inputs exposed by the circuit.  With
+    /// [`PiCommitment::Keccak`] the PI randomness is not a public input, so
+    /// it's derived from the hash of the raw public inputs.
+    pub fn set_commitment(&mut self, commitment: PiCommitment) {
+        self.commitment = commitment;
+        if commitment == PiCommitment::Keccak {
+            self.randomness = self
+                .public_data
+                .keccak_randomness(self.max_txs, self.max_calldata);
+        }
     }
 
-    /// Return the minimum number of rows required to prove the block
-    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
-        let row_num = |tx_num, calldata_len| {
-            BLOCK_LEN + 1 + EXTRA_LEN + 3 * (TX_LEN * tx_num + 1) + calldata_len
-        };
-        let calldata_len = block.txs.iter().map(|tx| tx.call_data.len()).sum();
-        (
-            row_num(block.txs.len(), calldata_len),
-            row_num(
-                block.circuits_params.max_txs,
-                block.circuits_params.max_calldata,
-            ),
-        )
+    /// Number of slots of the rw commitment region, which has room for every
+    /// row of the RwTable but the Start one that precedes them.
+    fn num_rw_slots(&self) -> usize {
+        num_rw_slots(self.max_rws, self.public_data.rws.len())
     }
 
-    /// Compute the public inputs for this circuit.
-    fn instance(&self) -> Vec<Vec<F>> {
-        let rlc_rpi_col = raw_public_inputs_col::<F>(
-            self.max_txs,
-            self.max_calldata,
+    /// Make the assignments to the PiCircuit, and return the cells that are
+    /// linked to the values proved by other sub-circuits.
+    pub(crate) fn assign(
+        &self,
+        config: &PiCircuitConfig<F>,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<PiLinkedCells<F>, Error> {
+        layouter.assign_region(
+            || "fixed u16 table",
+            |mut region| {
+                for i in 0..(1 << 16) {
+                    region.assign_fixed(
+                        || format!("row_{}", i),
+                        config.fixed_u16,
+                        i,
+                        || Value::known(F::from(i as u64)),
+                    )?;
+                }
+
+                Ok(())
+            },
+        )?;
+        config.byte_table.load(layouter)?;
+        let header_cells =
+            config
+                .header
+                .assign(layouter, &self.public_data, self.randomness, challenges)?;
+        let tx_root_cells = config.tx_root.assign(
+            layouter,
+            "transactions trie",
+            &self.public_data.signed_txs(),
+            self.public_data.prev_transactions.len(),
+            challenges,
+        )?;
+        let tx_list_cells = config.tx_list.assign(
+            layouter,
+            &self.public_data.tx_list_bytes(),
+            self.public_data.chain_id.as_u64(),
+            tx_list_region_len(tx_list_max_len(self.max_txs, self.max_calldata)),
+            challenges,
+        )?;
+        // One withdrawals trie per block of the batch, whose paths are keyed
+        // by the number of the block
+        let mut withdrawals_root_cells = self
+            .public_data
+            .block_withdrawals()
+            .into_iter()
+            .map(|(number, withdrawals)| {
+                config.withdrawals_root.assign(
+                    layouter,
+                    "withdrawals trie",
+                    &withdrawals_rlp(withdrawals),
+                    number as usize,
+                    challenges,
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let withdrawals_root_cells_last = withdrawals_root_cells
+            .pop()
+            .expect("the batch has a last block");
+        let (withdrawals, withdrawal_blocks) = self.public_data.table_withdrawals();
+        config
+            .withdrawals
+            .assign(layouter, &withdrawals, &withdrawal_blocks, challenges)?;
+        let rw_commitment_cells = config.rw_commitment.assign(
+            layouter,
             &self.public_data,
+            self.num_rw_slots(),
             self.randomness,
-        );
-        assert_eq!(
-            rlc_rpi_col.len(),
-            BLOCK_LEN + 1 + EXTRA_LEN + 3 * (TX_LEN * self.max_txs + 1) + self.max_calldata
-        );
-
-        // Computation of raw_pulic_inputs
-        let rlc_rpi = rlc_rpi_col
-            .iter()
-            .rev()
-            .fold(F::zero(), |acc, val| acc * self.rand_rpi + val);
-
-        // let block_hash = public_data
-        //     .eth_block
-        //     .hash
-        //     .unwrap_or_else(H256::zero)
-        //     .to_fixed_bytes();
-        let public_inputs = vec![
-            self.rand_rpi,
-            rlc_rpi,
-            F::from(self.public_data.chain_id.as_u64()),
-            rlc(
-                self.public_data.state_root.to_fixed_bytes(),
-                self.randomness,
-            ),
-            rlc(
-                self.public_data.prev_state_root.to_fixed_bytes(),
-                self.randomness,
-            ),
-        ];
-
-        vec![public_inputs]
-    }
-
-    /// Make the assignments to the PiCircuit
-    fn synthesize_sub(
-        &self,
-        config: &Self::Config,
-        _challenges: &Challenges<Value<F>>,
-        layouter: &mut impl Layouter<F>,
-    ) -> Result<(), Error> {
-        layouter.assign_region(
-            || "fixed u16 table",
-            |mut region| {
-                for i in 0..(1 << 16) {
-                    region.assign_fixed(
-                        || format!("row_{}", i),
-                        config.fixed_u16,
-                        i,
-                        || Value::known(F::from(i as u64)),
-                    )?;
-                }
-
-                Ok(())
-            },
-        )?;
-        let pi_cells = layouter.assign_region(
-            || "region 0",
-            |mut region| {
-                let circuit_len = config.circuit_len();
-                let mut raw_pi_vals = vec![F::zero(); circuit_len];
+            &header_cells.randomness,
+            challenges,
+        )?;
+        let (pi_cells, raw_pi_cells, linked_cells) = layouter.assign_region(
+            || "region 0",
+            |mut region| {
+                let circuit_len = config.circuit_len();
+                let mut raw_pi_vals = vec![F::zero(); circuit_len];
+                let mut raw_pi_cells = vec![None; circuit_len];
 
                 // Assign block table
                 let block_values = self.public_data.get_block_table_values();
-                let chain_id = config.assign_block_table(
+                let block_cells = config.assign_block_values(
                     &mut region,
                     block_values,
                     self.randomness,
                     &mut raw_pi_vals,
                 )?;
+                let chain_id = block_cells[7].clone();
+                for (i, cell) in block_cells.iter().enumerate() {
+                    raw_pi_cells[i] = Some(cell.clone());
+                }
 
                 // Assign extra fields
                 let extra_vals = self.public_data.get_extra_values();
-                let [state_root, prev_state_root] = config.assign_extra_fields(
+                let extra_cells = config.assign_extra_fields(
                     &mut region,
                     extra_vals,
                     self.randomness,
                     &mut raw_pi_vals,
                 )?;
+                for (i, cell) in extra_cells.iter().enumerate() {
+                    raw_pi_cells[BLOCK_LEN + 1 + i] = Some(cell.clone());
+                }
+                let [block_hash, state_root, prev_state_root, chunk_state_root, prev_rw_commitment, rw_commitment] =
+                    extra_cells;
+
+                // Link the block values to the ones encoded in the header
+                for (header_cell, pi_cell) in [
+                    &header_cells.beneficiary,
+                    &header_cells.gas_limit,
+                    &header_cells.number,
+                    &header_cells.timestamp,
+                    &header_cells.difficulty,
+                    &header_cells.base_fee,
+                    &header_cells.chain_id,
+                ]
+                .into_iter()
+                .chain(&header_cells.history_hashes)
+                .zip(&block_cells[1..])
+                .chain([
+                    (&header_cells.block_hash, &block_hash),
+                    (&header_cells.state_root, &state_root),
+                    (&header_cells.transactions_root, &tx_root_cells.root_rlc),
+                    (&header_cells.number, &withdrawals_root_cells_last.id_offset),
+                ]) {
+                    region.constrain_equal(header_cell.cell(), pi_cell.cell())?;
+                }
+                // Link the rw commitments and the start of the chunk to the
+                // ones proved by the rw commitment region
+                for (cell, rw_commitment_cell) in [
+                    (
+                        &prev_rw_commitment,
+                        &rw_commitment_cells.prev_rw_commitment,
+                    ),
+                    (&rw_commitment, &rw_commitment_cells.rw_commitment),
+                    (
+                        &header_cells.rw_counter_start,
+                        &rw_commitment_cells.rw_counter_start,
+                    ),
+                    (
+                        &header_cells.first_tx_id,
+                        &rw_commitment_cells.first_tx_id,
+                    ),
+                ] {
+                    region.constrain_equal(cell.cell(), rw_commitment_cell.cell())?;
+                }
+                // The txs of the tx list are the ones of the chain of the block
+                region.constrain_equal(tx_list_cells.chain_id.cell(), chain_id.cell())?;
 
                 let mut offset = 0;
                 // Assign Tx table
@@ -1260,6 +1989,7 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                     0,
                     F::zero(),
                     &mut raw_pi_vals,
+                    &mut raw_pi_cells,
                 )?;
                 offset += 1;
 
@@ -1291,6 +2021,28 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                         ),
                         (TxFieldTag::CallDataLength, F::from(tx.call_data_len)),
                         (TxFieldTag::CallDataGasCost, F::from(tx.call_data_gas_cost)),
+                        (TxFieldTag::TxType, F::from(tx.tx_type)),
+                        (
+                            TxFieldTag::ChainId,
+                            rlc(Word::from(tx.chain_id).to_le_bytes(), self.randomness),
+                        ),
+                        (
+                            TxFieldTag::MaxFeePerGas,
+                            rlc(tx.max_fee_per_gas.to_le_bytes(), self.randomness),
+                        ),
+                        (
+                            TxFieldTag::MaxPriorityFeePerGas,
+                            rlc(tx.max_priority_fee_per_gas.to_le_bytes(), self.randomness),
+                        ),
+                        (
+                            TxFieldTag::AccessListAddressesLen,
+                            F::from(tx.access_list_addresses_len),
+                        ),
+                        (
+                            TxFieldTag::AccessListStorageKeysLen,
+                            F::from(tx.access_list_storage_keys_len),
+                        ),
+                        (TxFieldTag::BlockNumber, F::from(tx.block_number)),
                         (
                             TxFieldTag::TxSignHash,
                             rlc(tx.tx_sign_hash, self.randomness),
@@ -1304,7 +2056,17 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                             0,
                             *value,
                             &mut raw_pi_vals,
+                            &mut raw_pi_cells,
                         )?;
+                        if *tag == TxFieldTag::BlockNumber {
+                            config.assign_tx_block_number(
+                                &mut region,
+                                offset,
+                                tx.block_number,
+                                &block_cells[3],
+                                self.public_data.block_constants.number.as_u64(),
+                            )?;
+                        }
                         offset += 1;
                     }
                 }
@@ -1349,6 +2111,7 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                             is_final,
                             gas_cost,
                             &mut raw_pi_vals,
+                            &mut raw_pi_cells,
                         )?;
                         offset += 1;
                         calldata_count += 1;
@@ -1365,6 +2128,7 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                         false,
                         F::zero(),
                         &mut raw_pi_vals,
+                        &mut raw_pi_cells,
                     )?;
                     offset += 1;
                 }
@@ -1377,22 +2141,223 @@ impl<F: Field> SubCircuit<F> for PiCircuit<F> {
                 let (rpi_rand, rpi_rlc) =
                     config.assign_rlc_pi(&mut region, self.rand_rpi, raw_pi_vals)?;
 
-                Ok(vec![
-                    rpi_rand,
-                    rpi_rlc,
-                    chain_id,
-                    state_root,
-                    prev_state_root,
-                ])
+                let linked_cells = PiLinkedCells {
+                    randomness: header_cells.randomness.clone(),
+                    prev_state_root: prev_state_root.clone(),
+                    chunk_state_root: chunk_state_root.clone(),
+                    number: header_cells.number.clone(),
+                    receipts_root: header_cells.receipts_root.clone(),
+                    logs_bloom: header_cells.logs_bloom.clone(),
+                };
+                Ok((
+                    vec![
+                        rpi_rand,
+                        rpi_rlc,
+                        chain_id,
+                        state_root,
+                        prev_state_root,
+                        block_hash,
+                        header_cells.randomness.clone(),
+                        chunk_state_root,
+                        prev_rw_commitment,
+                        rw_commitment,
+                    ],
+                    raw_pi_cells,
+                    linked_cells,
+                ))
             },
         )?;
 
+        // With the keccak commitment, the public inputs are the halves of the
+        // hash of the raw public inputs instead.
+        let pi_cells = match self.commitment {
+            PiCommitment::Rlc => pi_cells,
+            PiCommitment::Keccak => {
+                let raw_pi_cells: Vec<_> = raw_pi_cells
+                    .into_iter()
+                    .map(|cell| cell.expect("every raw public input is assigned"))
+                    .collect();
+                config
+                    .commitment
+                    .assign(
+                        layouter,
+                        &rpi_fields(&self.public_data, self.max_txs, self.max_calldata),
+                        self.randomness,
+                        &header_cells.randomness,
+                        &raw_pi_cells,
+                        challenges,
+                    )?
+                    .to_vec()
+            }
+        };
+
         // Constrain raw_public_input cells to public inputs
-        for (i, pi_cell) in pi_cells.iter().enumerate() {
+        let tx_list_cells = [tx_list_cells.hash_hi, tx_list_cells.hash_lo];
+        let withdrawals_root_cells = [
+            withdrawals_root_cells_last.root_hi,
+            withdrawals_root_cells_last.root_lo,
+        ]
+        .into_iter()
+        .chain(
+            withdrawals_root_cells
+                .into_iter()
+                .flat_map(|cells| [cells.id_offset, cells.root_hi, cells.root_lo]),
+        )
+        .collect::<Vec<_>>();
+        for (i, pi_cell) in pi_cells
+            .iter()
+            .chain(&tx_list_cells)
+            .chain(&withdrawals_root_cells)
+            .enumerate()
+        {
             layouter.constrain_instance(pi_cell.cell(), config.pi, i)?;
         }
 
-        Ok(())
+        Ok(linked_cells)
+    }
+}
+
+impl<F: Field> SubCircuit<F> for PiCircuit<F> {
+    type Config = PiCircuitConfig<F>;
+
+    fn new_from_block(block: &witness::Block<F>) -> Self {
+        PiCircuit::new(
+            block.circuits_params.max_txs,
+            block.circuits_params.max_calldata,
+            block.circuits_params.max_rws,
+            block.randomness,
+            block.randomness + F::from_u128(1),
+            public_data_convert(block),
+        )
+    }
+
+    /// Return the minimum number of rows required to prove the block
+    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
+        // The transactions trie depends on the encoding of the txs of the block
+        let public_data = public_data_convert(block);
+        let row_num = |tx_num, calldata_len| {
+            std::cmp::max(
+                BLOCK_LEN + 1 + EXTRA_LEN + 3 * (TX_LEN * tx_num + 1) + calldata_len,
+                header_region_len(public_data.prev_block_headers.len()),
+            )
+        };
+        let calldata_len = block.txs.iter().map(|tx| tx.call_data.len()).sum();
+        // The withdrawals tries of the blocks are in the same columns
+        let withdrawals_root_rows = public_data
+            .block_withdrawals()
+            .into_iter()
+            .map(|(_, withdrawals)| list_trie_num_rows(&withdrawals_rlp(withdrawals)))
+            .sum();
+        let tx_root_rows = list_trie_num_rows(&public_data.signed_txs())
+            .max(withdrawals_root_rows)
+            .max(withdrawals_region_len(block.withdrawals.len()));
+        let rw_commitment_rows =
+            |max_rws| rw_commitment_region_len(num_rw_slots(max_rws, public_data.rws.len()));
+        // The tx list region has room for the largest tx list
+        let tx_list_rows = tx_list_region_len(tx_list_max_len(
+            block.circuits_params.max_txs,
+            block.circuits_params.max_calldata,
+        ));
+        (
+            row_num(block.txs.len(), calldata_len)
+                .max(tx_root_rows)
+                .max(tx_list_rows)
+                .max(rw_commitment_rows(0)),
+            row_num(
+                block.circuits_params.max_txs,
+                block.circuits_params.max_calldata,
+            )
+            .max(tx_root_rows)
+            .max(tx_list_rows)
+            .max(rw_commitment_rows(block.circuits_params.max_rws)),
+        )
+    }
+
+    /// Compute the public inputs for this circuit.
+    fn instance(&self) -> Vec<Vec<F>> {
+        let tx_list_hash = split_root::<F>(self.public_data.tx_list_hash().to_word());
+        let withdrawals_roots = split_root::<F>(self.public_data.withdrawals_root().to_word())
+            .into_iter()
+            .chain(
+                self.public_data
+                    .prev_withdrawals_roots()
+                    .into_iter()
+                    .flat_map(|(number, root)| {
+                        [F::from(number)]
+                            .into_iter()
+                            .chain(split_root::<F>(root.to_word()))
+                    }),
+            )
+            .collect::<Vec<_>>();
+        if self.commitment == PiCommitment::Keccak {
+            let hash = self.public_data.hash(self.max_txs, self.max_calldata);
+            return vec![[
+                split_root(hash.to_word()).to_vec(),
+                tx_list_hash.to_vec(),
+                withdrawals_roots,
+            ]
+            .concat()];
+        }
+
+        let rlc_rpi_col = raw_public_inputs_col::<F>(
+            self.max_txs,
+            self.max_calldata,
+            &self.public_data,
+            self.randomness,
+        );
+        assert_eq!(
+            rlc_rpi_col.len(),
+            BLOCK_LEN + 1 + EXTRA_LEN + 3 * (TX_LEN * self.max_txs + 1) + self.max_calldata
+        );
+
+        // Computation of raw_pulic_inputs
+        let rlc_rpi = rlc_rpi_col
+            .iter()
+            .rev()
+            .fold(F::zero(), |acc, val| acc * self.rand_rpi + val);
+
+        let public_inputs = vec![
+            self.rand_rpi,
+            rlc_rpi,
+            F::from(self.public_data.chain_id.as_u64()),
+            rlc(
+                self.public_data.state_root.to_fixed_bytes(),
+                self.randomness,
+            ),
+            rlc(
+                self.public_data.prev_state_root.to_fixed_bytes(),
+                self.randomness,
+            ),
+            rlc(
+                self.public_data.block_hash().to_fixed_bytes(),
+                self.randomness,
+            ),
+            self.randomness,
+            rlc(
+                self.public_data.chunk_state_root.to_fixed_bytes(),
+                self.randomness,
+            ),
+            rlc(
+                self.public_data.prev_rw_commitment().to_fixed_bytes(),
+                self.randomness,
+            ),
+            rlc(
+                self.public_data.rw_commitment().to_fixed_bytes(),
+                self.randomness,
+            ),
+        ];
+
+        vec![[public_inputs, tx_list_hash.to_vec(), withdrawals_roots].concat()]
+    }
+
+    /// Make the assignments to the PiCircuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        self.assign(config, challenges, layouter).map(|_| ())
     }
 }
 
@@ -1417,13 +2382,22 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
-        Self::default()
+        Self(PiCircuit {
+            commitment: self.0.commitment,
+            ..Default::default()
+        })
     }
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         let block_table = BlockTable::construct(meta);
         let tx_table = TxTable::construct(meta);
-        (
+        let keccak_table = KeccakTable::construct(meta);
+        let rlp_table = RlpTable::construct(meta);
+        let withdrawal_table = WithdrawalTable::construct(meta);
+        let rw_table = RwTable::construct(meta);
+        let challenges = Challenges::construct(meta);
+        let config = {
+            let challenges = challenges.exprs(meta);
             PiCircuitConfig::new(
                 meta,
                 PiCircuitConfigArgs {
@@ -1431,10 +2405,15 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
                     max_calldata: MAX_CALLDATA,
                     block_table,
                     tx_table,
+                    keccak_table,
+                    rlp_table,
+                    withdrawal_table,
+                    rw_table,
+                    challenges,
                 },
-            ),
-            Challenges::construct(meta),
-        )
+            )
+        };
+        (config, challenges)
     }
 
     fn synthesize(
@@ -1443,10 +2422,73 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
         let challenges = challenges.values(&mut layouter);
+        let mut keccak_inputs = self.0.public_data.keccak_inputs();
+        if self.0.commitment == PiCommitment::Keccak {
+            keccak_inputs.push(self.0.public_data.rpi_bytes(MAX_TXS, MAX_CALLDATA));
+        }
+        config
+            .keccak_table
+            .dev_load(&mut layouter, &keccak_inputs, &challenges)?;
+        config.rlp_table.load(
+            &mut layouter,
+            &self.0.public_data.txs(),
+            self.0.public_data.chain_id.as_u64(),
+            &challenges,
+        )?;
+        let (withdrawals, withdrawal_blocks) = self.0.public_data.table_withdrawals();
+        config
+            .withdrawal_table
+            .load(&mut layouter, &withdrawals, &withdrawal_blocks)?;
+        config.block_table.load(
+            &mut layouter,
+            &block_contexts(&self.0.public_data),
+            challenges.evm_word(),
+        )?;
+        config.rw_table.load(
+            &mut layouter,
+            &self.0.public_data.rws,
+            0,
+            challenges.evm_word(),
+        )?;
         self.0.synthesize_sub(&config, &challenges, &mut layouter)
     }
 }
 
+/// Returns the contexts of the blocks of the public data, whose rows are
+/// assigned to the BlockTable of the [`PiTestCircuit`].
+#[cfg(any(feature = "test", test))]
+fn block_contexts(public_data: &PublicData) -> witness::BlockContexts {
+    let context = |header: &eth_types::Block<()>| witness::BlockContext {
+        coinbase: header.author.unwrap_or_default(),
+        gas_limit: header.gas_limit.as_u64(),
+        number: header.number.unwrap_or_default().as_u64().into(),
+        timestamp: header.timestamp,
+        difficulty: header.difficulty,
+        base_fee: header.base_fee_per_gas.unwrap_or_default(),
+        history_hashes: Vec::new(),
+        history_headers: Vec::new(),
+        chain_id: public_data.chain_id,
+    };
+    let last = witness::BlockContext {
+        history_hashes: public_data.history_hashes.clone(),
+        ..context(&public_data.block_header())
+    };
+    witness::BlockContexts {
+        ctxs: public_data
+            .prev_block_headers
+            .iter()
+            .map(context)
+            .chain(std::iter::once(last))
+            .map(|ctx| (ctx.number.low_u64(), ctx))
+            .collect(),
+        chunk: witness::ChunkContext {
+            rw_counter_start: public_data.rw_counter_start(),
+            first_tx_id: public_data.first_tx_id(),
+            ..Default::default()
+        },
+    }
+}
+
 /// Compute the raw_public_inputs column from the verifier's perspective.
 fn raw_public_inputs_col<F: Field>(
     max_txs: usize,
@@ -1494,10 +2536,16 @@ fn raw_public_inputs_col<F: Field>(
     }
 
     // Insert Extra Values
+    // block hash
+    result[BLOCK_LEN + 1] = rlc(extra.block_hash.to_fixed_bytes(), randomness);
     // block Root
-    result[BLOCK_LEN + 1] = rlc(extra.state_root.to_fixed_bytes(), randomness);
+    result[BLOCK_LEN + 2] = rlc(extra.state_root.to_fixed_bytes(), randomness);
     // parent block hash
-    result[BLOCK_LEN + 2] = rlc(extra.prev_state_root.to_fixed_bytes(), randomness);
+    result[BLOCK_LEN + 3] = rlc(extra.prev_state_root.to_fixed_bytes(), randomness);
+    // chunk state root and rw commitments
+    result[BLOCK_LEN + 4] = rlc(extra.chunk_state_root.to_fixed_bytes(), randomness);
+    result[BLOCK_LEN + 5] = rlc(extra.prev_rw_commitment.to_fixed_bytes(), randomness);
+    result[BLOCK_LEN + 6] = rlc(extra.rw_commitment.to_fixed_bytes(), randomness);
 
     // Insert Tx table
     offset = 0;
@@ -1530,6 +2578,13 @@ fn raw_public_inputs_col<F: Field>(
             rlc(tx.value.to_le_bytes(), randomness),
             F::from(tx.call_data_len),
             F::from(tx.call_data_gas_cost),
+            F::from(tx.tx_type),
+            rlc(Word::from(tx.chain_id).to_le_bytes(), randomness),
+            rlc(tx.max_fee_per_gas.to_le_bytes(), randomness),
+            rlc(tx.max_priority_fee_per_gas.to_le_bytes(), randomness),
+            F::from(tx.access_list_addresses_len),
+            F::from(tx.access_list_storage_keys_len),
+            F::from(tx.block_number),
             rlc(tx.tx_sign_hash, randomness),
         ] {
             result[id_offset + offset] = F::from((i + 1) as u64);
@@ -1561,6 +2616,7 @@ fn raw_public_inputs_col<F: Field>(
 mod pi_circuit_test {
     use super::*;
 
+    use crate::table::{CallContextFieldTag, TxReceiptFieldTag};
     use crate::test_util::rand_tx;
     use halo2_proofs::{
         dev::{MockProver, VerifyFailure},
@@ -1573,19 +2629,31 @@ mod pi_circuit_test {
     fn run<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
         k: u32,
         public_data: PublicData,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        run_with_commitment::<F, MAX_TXS, MAX_CALLDATA>(k, public_data, PiCommitment::Rlc, None)
+    }
+
+    /// Run the circuit with the given `commitment`, and with the public
+    /// inputs of `instance_data` if it's given.
+    fn run_with_commitment<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
+        k: u32,
+        public_data: PublicData,
+        commitment: PiCommitment,
+        instance_data: Option<PublicData>,
     ) -> Result<(), Vec<VerifyFailure>> {
         let mut rng = ChaCha20Rng::seed_from_u64(2);
         let randomness = F::random(&mut rng);
         let rand_rpi = F::random(&mut rng);
 
-        let circuit = PiTestCircuit::<F, MAX_TXS, MAX_CALLDATA>(PiCircuit::new(
-            MAX_TXS,
-            MAX_CALLDATA,
-            randomness,
-            rand_rpi,
-            public_data,
-        ));
-        let public_inputs = circuit.0.instance();
+        let new_circuit = |public_data| {
+            let mut circuit =
+                PiCircuit::new(MAX_TXS, MAX_CALLDATA, 0, randomness, rand_rpi, public_data);
+            circuit.set_commitment(commitment);
+            circuit
+        };
+        let public_inputs =
+            new_circuit(instance_data.unwrap_or_else(|| public_data.clone())).instance();
+        let circuit = PiTestCircuit::<F, MAX_TXS, MAX_CALLDATA>(new_circuit(public_data));
 
         let prover = match MockProver::run(k, &circuit, public_inputs) {
             Ok(prover) => prover,
@@ -1624,4 +2692,622 @@ mod pi_circuit_test {
         let k = 17;
         assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data), Ok(()));
     }
+
+    #[test]
+    fn test_signed_txs() {
+        let public_data = PublicData {
+            chain_id: *mock::MOCK_CHAIN_ID,
+            transactions: mock::CORRECT_MOCK_TXS
+                .iter()
+                .map(|tx| eth_types::Transaction::from(tx.clone()))
+                .collect(),
+            ..Default::default()
+        };
+
+        // The hash of a signed tx is the tx hash
+        let signed_txs = public_data.signed_txs();
+        assert_eq!(signed_txs.len(), public_data.transactions.len());
+        for (tx, signed_tx) in public_data.transactions.iter().zip(signed_txs) {
+            assert_eq!(H256(keccak256(signed_tx)), tx.hash);
+        }
+        assert_ne!(
+            public_data.transactions_root(),
+            *bus_mapping::mpt::EMPTY_ROOT
+        );
+        assert_eq!(
+            PublicData::default().transactions_root(),
+            *bus_mapping::mpt::EMPTY_ROOT
+        );
+    }
+
+    /// Returns the header of the block `number` of a history chain.
+    fn history_header(number: u64, parent_hash: H256) -> eth_types::Block<()> {
+        eth_types::Block {
+            parent_hash,
+            number: Some(number.into()),
+            gas_limit: Word::from(30_000_000u64),
+            base_fee_per_gas: Some(Word::from(1_000_000_000u64)),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the hashes and headers of a chain of `len` blocks starting at
+    /// the genesis block.
+    fn history_chain(len: usize) -> (Vec<Word>, Vec<Vec<u8>>) {
+        let mut parent_hash = H256::zero();
+        (0..len)
+            .map(|number| {
+                let header = block_header_rlp(&history_header(number as u64, parent_hash));
+                parent_hash = H256(keccak256(&header));
+                (parent_hash.into_uint(), header)
+            })
+            .unzip()
+    }
+
+    fn history_public_data() -> PublicData {
+        let (history_hashes, history_headers) = history_chain(3);
+        let mut public_data = PublicData {
+            chain_id: Word::from(1337u64),
+            history_hashes,
+            history_headers,
+            extra_data: Bytes::from(b"zkevm".to_vec()),
+            gas_used: Word::from(21000u64),
+            ..Default::default()
+        };
+        public_data.block_constants.number = 3.into();
+        public_data.block_constants.gas_limit = Word::from(30_000_000u64);
+        public_data.block_constants.timestamp = Word::from(1_000u64);
+        public_data.block_constants.difficulty = Word::from(0x20000u64);
+        public_data.block_constants.base_fee = Word::from(875_000_000u64);
+        public_data
+    }
+
+    #[test]
+    fn test_history_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        let k = 17;
+        assert_eq!(
+            run::<Fr, MAX_TXS, MAX_CALLDATA>(k, history_public_data()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_wrong_history_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The history hashes don't form a chain of parent hashes
+        let mut public_data = history_public_data();
+        public_data.history_hashes[1] = Word::from(0xcafeu64);
+        public_data.history_headers[1] = Vec::new();
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
+
+    /// Public data of the block 3 of a batch with the blocks 1 and 2 of its
+    /// history.
+    fn batch_public_data() -> PublicData {
+        let mut public_data = history_public_data();
+        public_data.prev_block_headers = (1..3)
+            .map(|number| {
+                let parent_hash = public_data.history_hashes[number as usize - 1];
+                history_header(number, H256::from_uint(&parent_hash))
+            })
+            .collect();
+        public_data
+    }
+
+    #[test]
+    fn test_batch_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        let k = 17;
+        assert_eq!(
+            run::<Fr, MAX_TXS, MAX_CALLDATA>(k, batch_public_data()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_wrong_batch_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The rows of a previous block in the BlockTable are the fields of a
+        // header that isn't the one of its history hash.
+        let mut public_data = batch_public_data();
+        public_data.prev_block_headers[1].timestamp = Word::from(1_000u64);
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
+
+    #[test]
+    fn test_rpi_bytes() {
+        const MAX_TXS: usize = 4;
+        const MAX_CALLDATA: usize = 64;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let mut public_data = history_public_data();
+        let chain_id = public_data.chain_id.as_u64();
+        for i in 0..2 {
+            let eth_tx = eth_types::Transaction::from(&rand_tx(&mut rng, chain_id, i & 2 == 0));
+            public_data.transactions.push(eth_tx);
+        }
+        let randomness = Fr::from(0xcafeu64);
+
+        // The serialized fields are encoded as the raw public inputs
+        let fields = rpi_fields(&public_data, MAX_TXS, MAX_CALLDATA);
+        let encoded: Vec<Fr> = fields
+            .iter()
+            .map(|(encoding, bytes)| encoding.value(bytes, randomness))
+            .collect();
+        assert_eq!(
+            encoded,
+            raw_public_inputs_col(MAX_TXS, MAX_CALLDATA, &public_data, randomness)
+        );
+
+        // The serialization has a fixed length
+        let rpi_bytes = public_data.rpi_bytes(MAX_TXS, MAX_CALLDATA);
+        assert_eq!(rpi_bytes.len(), rpi_bytes_len(MAX_TXS, MAX_CALLDATA));
+        assert_eq!(
+            public_data.hash(MAX_TXS, MAX_CALLDATA),
+            H256(keccak256(&rpi_bytes))
+        );
+    }
+
+    #[test]
+    fn test_keccak_pi() {
+        const MAX_TXS: usize = 4;
+        const MAX_CALLDATA: usize = 64;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let mut public_data = history_public_data();
+        let chain_id = public_data.chain_id.as_u64();
+        for i in 0..2 {
+            let eth_tx = eth_types::Transaction::from(&rand_tx(&mut rng, chain_id, i & 2 == 0));
+            public_data.transactions.push(eth_tx);
+        }
+
+        let k = 17;
+        assert_eq!(
+            run_with_commitment::<Fr, MAX_TXS, MAX_CALLDATA>(
+                k,
+                public_data,
+                PiCommitment::Keccak,
+                None
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_wrong_keccak_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The public inputs are the hash of another state root
+        let public_data = history_public_data();
+        let mut instance_data = public_data.clone();
+        instance_data.state_root = H256::repeat_byte(0xca);
+
+        let k = 17;
+        assert!(run_with_commitment::<Fr, MAX_TXS, MAX_CALLDATA>(
+            k,
+            public_data,
+            PiCommitment::Keccak,
+            Some(instance_data)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_wrong_keccak_randomness() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The PI randomness is chosen by the prover instead of derived from
+        // the hash
+        let public_data = history_public_data();
+        let mut circuit = PiCircuit::<Fr>::new(
+            MAX_TXS,
+            MAX_CALLDATA,
+            0,
+            Fr::from(7),
+            Fr::from(11),
+            public_data,
+        );
+        circuit.set_commitment(PiCommitment::Keccak);
+        let public_inputs = circuit.instance();
+        circuit.randomness += Fr::from(1);
+        let circuit = PiTestCircuit::<Fr, MAX_TXS, MAX_CALLDATA>(circuit);
+
+        let k = 17;
+        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    /// Public data whose tx list has 3 valid txs, a typed entry that is not
+    /// followed by a list, an entry of an unsupported type, a tx of another
+    /// chain and a typed tx without fields.  The first two valid txs have 10
+    /// bytes of call data, and the last one doesn't fit in 2 txs.
+    fn tx_list_public_data() -> (PublicData, Vec<Vec<u8>>) {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let chain_id = 1337u64;
+        let valid_txs: Vec<Vec<u8>> = (0..3)
+            .map(|i| rand_tx(&mut rng, chain_id, i & 2 == 0).signed_rlp(chain_id))
+            .collect();
+        let other_chain_tx = rand_tx(&mut rng, chain_id + 1, false).signed_rlp(chain_id + 1);
+        let entries = vec![
+            valid_txs[0].clone(),
+            b"\x01junk".to_vec(),
+            b"\x03\xc0".to_vec(),
+            other_chain_tx,
+            valid_txs[1].clone(),
+            b"\x02\xc0".to_vec(),
+            valid_txs[2].clone(),
+        ];
+
+        let public_data = PublicData {
+            chain_id: Word::from(chain_id),
+            tx_list: Some(Bytes::from(encode_tx_list(&entries))),
+            ..Default::default()
+        };
+        (public_data, valid_txs)
+    }
+
+    #[test]
+    fn test_decode_tx_list() {
+        let (public_data, valid_txs) = tx_list_public_data();
+        let chain_id = public_data.chain_id.as_u64();
+        let tx_list = public_data.tx_list_bytes();
+
+        // The entries that are not txs of the chain and the txs that don't
+        // fit in max_txs are skipped
+        let signed_txs = |txs: Vec<eth_types::Transaction>| -> Vec<Vec<u8>> {
+            txs.iter()
+                .map(|tx| Transaction::from(tx).signed_rlp(chain_id))
+                .collect()
+        };
+        let txs = decode_tx_list(&tx_list, chain_id, 2, 32).unwrap();
+        assert_eq!(signed_txs(txs), valid_txs[..2]);
+        let txs = decode_tx_list(&tx_list, chain_id, 3, 32).unwrap();
+        assert_eq!(signed_txs(txs), valid_txs);
+
+        // The txs whose call data doesn't fit with the one of the previous
+        // txs are skipped
+        let txs = decode_tx_list(&tx_list, chain_id, 3, 15).unwrap();
+        assert_eq!(
+            signed_txs(txs),
+            [valid_txs[0].clone(), valid_txs[2].clone()]
+        );
+        let txs = decode_tx_list(&tx_list, chain_id, 3, 20).unwrap();
+        assert_eq!(signed_txs(txs), valid_txs);
+
+        // Non canonical encodings are skipped: the nonce 3, which follows
+        // the long list header, with a leading zero
+        let mut leading_zero = valid_txs[0].clone();
+        assert_eq!((leading_zero[0], leading_zero[2]), (0xf8, 0x03));
+        leading_zero.splice(2..3, [0x82, 0x00, 0x03]);
+        leading_zero[1] += 2;
+        let tx_list = encode_tx_list(&[leading_zero, valid_txs[1].clone()]);
+        let txs = decode_tx_list(&tx_list, chain_id, 3, 32).unwrap();
+        assert_eq!(signed_txs(txs), valid_txs[1..2]);
+
+        // The hash of a decoded tx is the one of its encoding
+        let txs = decode_tx_list(&encode_tx_list(&valid_txs), chain_id, 3, 32).unwrap();
+        for (tx, signed_tx) in txs.iter().zip(&valid_txs) {
+            assert_eq!(tx.hash, H256(keccak256(signed_tx)));
+        }
+
+        // The tx list must be a list
+        assert!(decode_tx_list(&[0x80], chain_id, 3, 32).is_err());
+        assert!(decode_tx_list(&tx_list[..tx_list.len() - 1], chain_id, 3, 32).is_err());
+    }
+
+    #[test]
+    fn test_tx_list_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 32;
+
+        let (mut public_data, _) = tx_list_public_data();
+        let tx_list = public_data.tx_list_bytes();
+        public_data.transactions = decode_tx_list(
+            &tx_list,
+            public_data.chain_id.as_u64(),
+            MAX_TXS,
+            MAX_CALLDATA,
+        )
+        .unwrap();
+
+        let k = 17;
+        assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data), Ok(()));
+    }
+
+    #[test]
+    fn test_tx_list_calldata_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 15;
+
+        // The second tx doesn't fit in max_calldata with the first one, and
+        // is skipped
+        let (mut public_data, _) = tx_list_public_data();
+        let tx_list = public_data.tx_list_bytes();
+        public_data.transactions = decode_tx_list(
+            &tx_list,
+            public_data.chain_id.as_u64(),
+            MAX_TXS,
+            MAX_CALLDATA,
+        )
+        .unwrap();
+        assert_eq!(public_data.transactions.len(), 2);
+
+        let k = 17;
+        assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_tx_list_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 32;
+
+        // The second tx is not in the tx list
+        let (mut public_data, valid_txs) = tx_list_public_data();
+        let chain_id = public_data.chain_id.as_u64();
+        public_data.transactions = decode_tx_list(
+            &public_data.tx_list_bytes(),
+            chain_id,
+            MAX_TXS,
+            MAX_CALLDATA,
+        )
+        .unwrap();
+        public_data.tx_list = Some(Bytes::from(encode_tx_list(&valid_txs[..1])));
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
+
+    #[test]
+    fn test_skipped_tx_list_entry_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 32;
+
+        // The second tx of the tx list is skipped, and the third one is
+        // included instead
+        let (mut public_data, valid_txs) = tx_list_public_data();
+        let chain_id = public_data.chain_id.as_u64();
+        public_data.transactions = decode_tx_list(
+            &encode_tx_list(&[valid_txs[0].clone(), valid_txs[2].clone()]),
+            chain_id,
+            MAX_TXS,
+            MAX_CALLDATA,
+        )
+        .unwrap();
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
+
+    fn withdrawals_public_data() -> PublicData {
+        let withdrawals = (0..3u64)
+            .map(|i| Withdrawal {
+                index: i.into(),
+                validator_index: (100 + i).into(),
+                address: Address::repeat_byte(0xaa + i as u8),
+                amount: (32_000_000_000 + i).into(),
+            })
+            .collect();
+        PublicData {
+            chain_id: Word::from(1337u64),
+            withdrawals,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_withdrawals_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        let public_data = withdrawals_public_data();
+        assert_ne!(
+            public_data.withdrawals_root(),
+            *bus_mapping::mpt::EMPTY_ROOT
+        );
+        assert_eq!(
+            PublicData::default().withdrawals_root(),
+            *bus_mapping::mpt::EMPTY_ROOT
+        );
+
+        let k = 17;
+        assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data), Ok(()));
+    }
+
+    #[test]
+    fn test_zero_amount_withdrawal_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // A withdrawal without amount would look like the padding withdrawal
+        // that ends the withdrawal table
+        let mut public_data = withdrawals_public_data();
+        public_data.withdrawals[1].validator_index = (1u64 << 40).into();
+        public_data.withdrawals[1].amount = 0.into();
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
+
+    #[test]
+    fn test_wrong_withdrawals_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The public inputs have the withdrawals root of another amount
+        let public_data = withdrawals_public_data();
+        let mut instance_data = public_data.clone();
+        instance_data.withdrawals[1].amount = 1.into();
+
+        let k = 17;
+        assert!(run_with_commitment::<Fr, MAX_TXS, MAX_CALLDATA>(
+            k,
+            public_data,
+            PiCommitment::Rlc,
+            Some(instance_data)
+        )
+        .is_err());
+    }
+
+    /// Public data of the batch of [`batch_public_data`], whose block 1 and
+    /// block 3 have withdrawals.
+    fn batch_withdrawals_public_data() -> PublicData {
+        let mut withdrawals = withdrawals_public_data().withdrawals;
+        let mut public_data = batch_public_data();
+        public_data.withdrawals = withdrawals.split_off(2);
+        public_data.prev_withdrawals = vec![withdrawals, vec![]];
+        public_data
+    }
+
+    #[test]
+    fn test_batch_withdrawals_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        let public_data = batch_withdrawals_public_data();
+        assert_eq!(public_data.table_withdrawals().1, vec![1, 1, 3],);
+        assert_eq!(
+            public_data
+                .prev_withdrawals_roots()
+                .into_iter()
+                .map(|(number, _)| number)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let k = 17;
+        assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_batch_withdrawals_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The public inputs have the withdrawals roots of a batch where the
+        // second withdrawal of the block 1 is in the block 2
+        let public_data = batch_withdrawals_public_data();
+        let mut instance_data = public_data.clone();
+        let withdrawal = instance_data.prev_withdrawals[0].pop().unwrap();
+        instance_data.prev_withdrawals[1].push(withdrawal);
+
+        let k = 17;
+        assert!(run_with_commitment::<Fr, MAX_TXS, MAX_CALLDATA>(
+            k,
+            public_data,
+            PiCommitment::Rlc,
+            Some(instance_data)
+        )
+        .is_err());
+    }
+
+    /// Public data of the second chunk of a block, whose first chunk ends
+    /// with the EndBlock reads of the third tx at rw counters 99 and 100.  The
+    /// EndTx of the fourth tx reads the cumulative gas used of the third one.
+    fn chunk_public_data() -> PublicData {
+        let prev_last_rw = witness::Rw::TxReceipt {
+            rw_counter: 100,
+            is_write: false,
+            tx_id: 3,
+            field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+            value: 63000,
+        };
+        let rws = vec![
+            witness::Rw::CallContext {
+                rw_counter: 99,
+                is_write: true,
+                call_id: 99,
+                field_tag: CallContextFieldTag::TxId,
+                value: 4.into(),
+            },
+            witness::Rw::Stack {
+                rw_counter: 100,
+                is_write: true,
+                call_id: 99,
+                stack_pointer: 1023,
+                value: Word::MAX,
+            },
+            witness::Rw::TxReceipt {
+                rw_counter: 101,
+                is_write: false,
+                tx_id: 3,
+                field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                value: 63000,
+            },
+            witness::Rw::TxReceipt {
+                rw_counter: 102,
+                is_write: true,
+                tx_id: 4,
+                field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                value: 84000,
+            },
+        ];
+        PublicData {
+            chain_id: Word::from(1337u64),
+            rws,
+            prev_rw_digest: H256::repeat_byte(0x11),
+            prev_last_rw: Some(prev_last_rw),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_chunk_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        let public_data = chunk_public_data();
+        assert_eq!(public_data.rw_counter_start(), 99);
+        assert_eq!(public_data.first_tx_id(), 4);
+
+        let k = 17;
+        assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_chunk_start_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The cumulative gas used at the end of the previous chunks is not
+        // the one read by the chunk
+        let mut public_data = chunk_public_data();
+        public_data.prev_last_rw = Some(witness::Rw::TxReceipt {
+            rw_counter: 100,
+            is_write: false,
+            tx_id: 3,
+            field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+            value: 42000,
+        });
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
+
+    #[test]
+    fn test_missing_rw_pi() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 8;
+
+        // The rw commitment skips a rw counter
+        let mut public_data = chunk_public_data();
+        public_data.rws.remove(1);
+
+        let k = 17;
+        assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, public_data).is_err());
+    }
 }
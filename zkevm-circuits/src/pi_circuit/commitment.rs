@@ -0,0 +1,602 @@
+//! Keccak commitment region of the PublicInputs circuit.
+//!
+//! With [`PiCommitment::Keccak`](super::PiCommitment::Keccak), the raw public
+//! inputs are serialized as bytes and the keccak hash of the serialization,
+//! split in hi/lo 128 bit halves, is the only public input of the circuit, so
+//! that a verifier contract can recompute it from the public data (see
+//! [`PublicData::hash`]).
+//!
+//! The region assigns one byte per row: the bytes of every raw public input,
+//! in the order of the raw_public_inputs column and each one in a fixed number
+//! of rows (see [`rpi_fields`]), followed by the 32 bytes of the hash.  The
+//! bytes of each raw public input are accumulated in its encoding, as a number
+//! or as an RLC with the PI randomness of its little or big endian bytes,
+//! which is copied to its cell of the raw_public_inputs column.  All the bytes
+//! are accumulated in an RLC with the keccak input challenge, which is looked
+//! up in the keccak table with the RLC of the bytes of the hash.
+//!
+//! The PI randomness of the RLC encodings is not a public input with this
+//! commitment, so it's derived from the hash: it's kept in all the rows of the
+//! region and it's `hash_hi * 2^128 + hash_lo` at the last one (see
+//! [`PublicData::keccak_randomness`]).
+
+use super::{PublicData, TxValues};
+use crate::{
+    evm_circuit::util::{constraint_builder::BaseConstraintBuilder, pow_of_two_expr, rlc},
+    table::{KeccakTable, LookupTable},
+    util::Challenges,
+};
+use eth_types::{Address, Field, ToBigEndian, Word, H256};
+use ethers_core::utils::keccak256;
+use gadgets::util::{not, select, Expr};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase},
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+const MAX_DEGREE: usize = 9;
+
+/// Number of rows of the hash at the end of the region
+const HASH_ROWS: usize = 32;
+
+/// Encoding of a raw public input in the raw_public_inputs column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RpiEncoding {
+    /// Its big endian bytes as a number
+    Number,
+    /// RLC of its little endian bytes with the PI randomness
+    RlcLe,
+    /// RLC of its big endian bytes with the PI randomness
+    RlcBe,
+}
+
+impl RpiEncoding {
+    /// Encode the big endian `bytes` of a raw public input.
+    pub(crate) fn value<F: Field>(&self, bytes: &[u8], randomness: F) -> F {
+        match self {
+            Self::Number => bytes.iter().fold(F::zero(), |acc, byte| {
+                acc * F::from(256) + F::from(*byte as u64)
+            }),
+            Self::RlcLe => rlc::value(bytes.iter().rev(), randomness),
+            Self::RlcBe => rlc::value(bytes, randomness),
+        }
+    }
+}
+
+fn number(value: u64, len: usize) -> (RpiEncoding, Vec<u8>) {
+    (RpiEncoding::Number, value.to_be_bytes()[8 - len..].to_vec())
+}
+
+fn address(value: Address) -> (RpiEncoding, Vec<u8>) {
+    (RpiEncoding::Number, value.as_bytes().to_vec())
+}
+
+fn word(value: Word) -> (RpiEncoding, Vec<u8>) {
+    (RpiEncoding::RlcLe, value.to_be_bytes().to_vec())
+}
+
+fn hash(value: H256) -> (RpiEncoding, Vec<u8>) {
+    (RpiEncoding::RlcBe, value.as_bytes().to_vec())
+}
+
+/// Fields of a tx in the tx table, in the order of their tags
+fn tx_fields(tx: &TxValues) -> Vec<(RpiEncoding, Vec<u8>)> {
+    vec![
+        word(tx.nonce),
+        word(tx.gas),
+        word(tx.gas_price),
+        address(tx.from_addr),
+        address(tx.to_addr),
+        number(tx.is_create, 1),
+        word(tx.value),
+        number(tx.call_data_len, 8),
+        number(tx.call_data_gas_cost, 8),
+        number(tx.tx_type, 1),
+        word(Word::from(tx.chain_id)),
+        word(tx.max_fee_per_gas),
+        word(tx.max_priority_fee_per_gas),
+        number(tx.access_list_addresses_len, 8),
+        number(tx.access_list_storage_keys_len, 8),
+        number(tx.block_number, 8),
+        // The sign hash is stored with its little endian bytes
+        (
+            RpiEncoding::RlcLe,
+            tx.tx_sign_hash.iter().rev().copied().collect(),
+        ),
+    ]
+}
+
+/// Serialization of every raw public input, in the order of the
+/// raw_public_inputs column, with the encoding of its cell.  Numbers are
+/// serialized in 1, 8 or 20 bytes (for the addresses) and words and hashes in
+/// 32 bytes, all of them big endian, so the serialization has a fixed length
+/// for the given `max_txs` and `max_calldata`.
+pub(crate) fn rpi_fields(
+    public_data: &PublicData,
+    max_txs: usize,
+    max_calldata: usize,
+) -> Vec<(RpiEncoding, Vec<u8>)> {
+    let block = public_data.get_block_table_values();
+    let extra = public_data.get_extra_values();
+    let txs = public_data.get_tx_table_values();
+    assert!(txs.len() <= max_txs);
+
+    // Block values, preceded by the zero row
+    let mut fields = vec![
+        number(0, 1),
+        address(block.coinbase),
+        number(block.gas_limit, 8),
+        number(block.number, 8),
+        number(block.timestamp, 8),
+        word(block.difficulty),
+        word(block.base_fee),
+        number(block.chain_id, 8),
+    ];
+    fields.extend(block.history_hashes.into_iter().map(hash));
+
+    // Extra values
+    fields.extend(
+        [
+            extra.block_hash,
+            extra.state_root,
+            extra.prev_state_root,
+            extra.chunk_state_root,
+            extra.prev_rw_commitment,
+            extra.rw_commitment,
+        ]
+        .map(hash),
+    );
+
+    // Tx table, preceded by the zero row: the tx ids, the indexes and the
+    // values
+    let tx_default = TxValues::default();
+    let tx_rows: Vec<(u64, (RpiEncoding, Vec<u8>))> = std::iter::once((0, number(0, 8)))
+        .chain((0..max_txs).flat_map(|i| {
+            let tx = txs.get(i).unwrap_or(&tx_default);
+            tx_fields(tx)
+                .into_iter()
+                .map(move |field| ((i + 1) as u64, field))
+        }))
+        .collect();
+    fields.extend(tx_rows.iter().map(|(tx_id, _)| number(*tx_id, 8)));
+    fields.extend(tx_rows.iter().map(|_| number(0, 8)));
+    fields.extend(tx_rows.into_iter().map(|(_, field)| field));
+
+    // Calldata of the txs, padded with zeros
+    let calldata: Vec<u8> = public_data
+        .txs()
+        .iter()
+        .flat_map(|tx| tx.call_data.0.to_vec())
+        .collect();
+    assert!(calldata.len() <= max_calldata);
+    fields.extend(
+        calldata
+            .into_iter()
+            .chain(std::iter::repeat(0))
+            .take(max_calldata)
+            .map(|byte| number(byte as u64, 1)),
+    );
+
+    fields
+}
+
+/// Number of bytes of the serialized raw public inputs
+pub(crate) fn rpi_bytes_len(max_txs: usize, max_calldata: usize) -> usize {
+    rpi_fields(&PublicData::default(), max_txs, max_calldata)
+        .iter()
+        .map(|(_, bytes)| bytes.len())
+        .sum()
+}
+
+/// Config of the keccak commitment region of the PublicInputs circuit
+#[derive(Clone, Debug)]
+pub(crate) struct PiCommitmentConfig<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    is_field_start: Column<Fixed>,
+    /// Rows of the hash
+    q_hash: Column<Fixed>,
+    /// Rows of the first 16 bytes of the hash
+    q_hash_hi: Column<Fixed>,
+    q_last: Column<Fixed>,
+
+    randomness: Column<Advice>,
+    byte: Column<Advice>,
+    value_num: Column<Advice>,
+    value_le: Column<Advice>,
+    value_be: Column<Advice>,
+    pow_r: Column<Advice>,
+    hash_hi: Column<Advice>,
+    hash_lo: Column<Advice>,
+    input_rlc: Column<Advice>,
+    hash_rlc: Column<Advice>,
+
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> PiCommitmentConfig<F> {
+    /// Configure the commitment region for the serialization of `rpi_len`
+    /// bytes.  The bytes are range checked with `u8_table`.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        rpi_len: usize,
+        u8_table: Column<Fixed>,
+        keccak_table: &KeccakTable,
+        challenges: &Challenges<Expression<F>>,
+    ) -> Self {
+        let config = Self {
+            q_enable: meta.fixed_column(),
+            q_first: meta.fixed_column(),
+            is_field_start: meta.fixed_column(),
+            q_hash: meta.fixed_column(),
+            q_hash_hi: meta.fixed_column(),
+            q_last: meta.fixed_column(),
+            randomness: meta.advice_column(),
+            byte: meta.advice_column(),
+            value_num: meta.advice_column(),
+            value_le: meta.advice_column(),
+            value_be: meta.advice_column(),
+            pow_r: meta.advice_column(),
+            hash_hi: meta.advice_column(),
+            hash_lo: meta.advice_column(),
+            input_rlc: meta.advice_column_in(SecondPhase),
+            hash_rlc: meta.advice_column_in(SecondPhase),
+            _marker: PhantomData,
+        };
+        let c = &config;
+        for column in [
+            c.randomness,
+            c.value_num,
+            c.value_le,
+            c.value_be,
+            c.hash_hi,
+            c.hash_lo,
+        ] {
+            meta.enable_equality(column);
+        }
+
+        meta.create_gate("commitment rpi bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_first = meta.query_fixed(c.q_first, Rotation::cur());
+            let is_field_start = meta.query_fixed(c.is_field_start, Rotation::cur());
+            let byte = meta.query_advice(c.byte, Rotation::cur());
+            let randomness = meta.query_advice(c.randomness, Rotation::cur());
+            // The encodings of a field start from zero at its first row.
+            let mut prev = |column| {
+                not::expr(is_field_start.clone()) * meta.query_advice(column, Rotation::prev())
+            };
+            let value_num_prev = prev(c.value_num);
+            let value_le_prev = prev(c.value_le);
+            let value_be_prev = prev(c.value_be);
+            let pow_r_prev = prev(c.pow_r);
+
+            cb.require_equal(
+                "value_num = value_num_prev * 256 + byte",
+                meta.query_advice(c.value_num, Rotation::cur()),
+                value_num_prev * 256.expr() + byte.clone(),
+            );
+            cb.require_equal(
+                "value_le = value_le_prev * randomness + byte",
+                meta.query_advice(c.value_le, Rotation::cur()),
+                value_le_prev * randomness.clone() + byte.clone(),
+            );
+            let pow_r = meta.query_advice(c.pow_r, Rotation::cur());
+            cb.require_equal(
+                "pow_r = randomness^index",
+                pow_r.clone(),
+                select::expr(is_field_start, 1.expr(), pow_r_prev * randomness.clone()),
+            );
+            cb.require_equal(
+                "value_be = value_be_prev + byte * pow_r",
+                meta.query_advice(c.value_be, Rotation::cur()),
+                value_be_prev + byte.clone() * pow_r,
+            );
+            cb.require_equal(
+                "input_rlc accumulates the bytes",
+                meta.query_advice(c.input_rlc, Rotation::cur()),
+                not::expr(q_first.clone())
+                    * meta.query_advice(c.input_rlc, Rotation::prev())
+                    * challenges.keccak_input()
+                    + byte,
+            );
+            cb.condition(not::expr(q_first), |cb| {
+                cb.require_equal(
+                    "randomness is the same in all rows",
+                    randomness,
+                    meta.query_advice(c.randomness, Rotation::prev()),
+                );
+            });
+
+            cb.gate(
+                meta.query_fixed(c.q_enable, Rotation::cur())
+                    * not::expr(meta.query_fixed(c.q_hash, Rotation::cur())),
+            )
+        });
+
+        meta.create_gate("commitment hash bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(c.byte, Rotation::cur());
+            let q_hash_hi = meta.query_fixed(c.q_hash_hi, Rotation::cur());
+            // The accumulators of the hash start from zero at its first row.
+            let q_hash_prev = meta.query_fixed(c.q_hash, Rotation::prev());
+            let mut prev =
+                |column| q_hash_prev.clone() * meta.query_advice(column, Rotation::prev());
+            let hash_hi_prev = prev(c.hash_hi);
+            let hash_lo_prev = prev(c.hash_lo);
+            let hash_rlc_prev = prev(c.hash_rlc);
+
+            cb.require_equal(
+                "input_rlc is kept in the hash rows",
+                meta.query_advice(c.input_rlc, Rotation::cur()),
+                meta.query_advice(c.input_rlc, Rotation::prev()),
+            );
+            cb.require_equal(
+                "hash_hi accumulates the first 16 hash bytes",
+                meta.query_advice(c.hash_hi, Rotation::cur()),
+                select::expr(
+                    q_hash_hi.clone(),
+                    hash_hi_prev.clone() * 256.expr() + byte.clone(),
+                    hash_hi_prev,
+                ),
+            );
+            cb.require_equal(
+                "hash_lo accumulates the last 16 hash bytes",
+                meta.query_advice(c.hash_lo, Rotation::cur()),
+                select::expr(
+                    q_hash_hi,
+                    hash_lo_prev.clone(),
+                    hash_lo_prev * 256.expr() + byte.clone(),
+                ),
+            );
+            cb.require_equal(
+                "hash_rlc accumulates the hash bytes",
+                meta.query_advice(c.hash_rlc, Rotation::cur()),
+                hash_rlc_prev * challenges.evm_word() + byte,
+            );
+            let randomness = meta.query_advice(c.randomness, Rotation::cur());
+            cb.require_equal(
+                "randomness is kept in the hash rows",
+                randomness.clone(),
+                meta.query_advice(c.randomness, Rotation::prev()),
+            );
+            cb.condition(meta.query_fixed(c.q_last, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "randomness = hash_hi * 2^128 + hash_lo",
+                    randomness,
+                    meta.query_advice(c.hash_hi, Rotation::cur()) * pow_of_two_expr(128)
+                        + meta.query_advice(c.hash_lo, Rotation::cur()),
+                );
+            });
+
+            cb.gate(meta.query_fixed(c.q_hash, Rotation::cur()))
+        });
+
+        meta.lookup_any("commitment byte is in u8 range", |meta| {
+            vec![(
+                meta.query_fixed(c.q_enable, Rotation::cur())
+                    * meta.query_advice(c.byte, Rotation::cur()),
+                meta.query_fixed(u8_table, Rotation::cur()),
+            )]
+        });
+
+        meta.lookup_any(
+            "keccak256_table_lookup(input_rlc, rpi_len, hash_rlc)",
+            |meta| {
+                let enable = meta.query_fixed(c.q_last, Rotation::cur());
+                [
+                    1.expr(),
+                    meta.query_advice(c.input_rlc, Rotation::cur()),
+                    rpi_len.expr(),
+                    meta.query_advice(c.hash_rlc, Rotation::cur()),
+                ]
+                .into_iter()
+                .zip(keccak_table.table_exprs(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+            },
+        );
+
+        config
+    }
+
+    /// Assign the fixed columns, the byte and the input RLC of a row of the
+    /// region.
+    #[allow(clippy::too_many_arguments)]
+    fn assign_row(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        rpi_len: usize,
+        is_field_start: bool,
+        byte: u8,
+        input_rlc: Value<F>,
+    ) -> Result<(), Error> {
+        let is_hash = offset >= rpi_len;
+        for (name, column, value) in [
+            ("q_enable", self.q_enable, true),
+            ("q_first", self.q_first, offset == 0),
+            ("is_field_start", self.is_field_start, is_field_start),
+            ("q_hash", self.q_hash, is_hash),
+            (
+                "q_hash_hi",
+                self.q_hash_hi,
+                is_hash && offset < rpi_len + HASH_ROWS / 2,
+            ),
+            ("q_last", self.q_last, offset == rpi_len + HASH_ROWS - 1),
+        ] {
+            region.assign_fixed(
+                || format!("{} {}", name, offset),
+                column,
+                offset,
+                || Value::known(F::from(value as u64)),
+            )?;
+        }
+        region.assign_advice(
+            || format!("byte {}", offset),
+            self.byte,
+            offset,
+            || Value::known(F::from(byte as u64)),
+        )?;
+        region.assign_advice(
+            || format!("input_rlc {}", offset),
+            self.input_rlc,
+            offset,
+            || input_rlc,
+        )?;
+        Ok(())
+    }
+
+    /// Assign the serialization of the raw public inputs `fields` followed by
+    /// its hash, copy the encoding of every field to its cell in `rpi_cells`
+    /// and the randomness, derived from the hash, to `randomness_cell`, and
+    /// return the cells of the hi and lo halves of the hash.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        fields: &[(RpiEncoding, Vec<u8>)],
+        randomness: F,
+        randomness_cell: &AssignedCell<F, F>,
+        rpi_cells: &[AssignedCell<F, F>],
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+        assert_eq!(fields.len(), rpi_cells.len());
+        let rpi_len = fields.iter().map(|(_, bytes)| bytes.len()).sum();
+        let hash = keccak256(
+            fields
+                .iter()
+                .flat_map(|(_, bytes)| bytes.iter().copied())
+                .collect::<Vec<_>>(),
+        );
+
+        layouter.assign_region(
+            || "pi commitment",
+            |mut region| {
+                let mut offset = 0;
+                let mut input_rlc = Value::known(F::zero());
+                for ((encoding, field), rpi_cell) in fields.iter().zip(rpi_cells) {
+                    let mut value_num = F::zero();
+                    let mut value_le = F::zero();
+                    let mut value_be = F::zero();
+                    let mut pow_r = F::one();
+                    for (i, byte) in field.iter().enumerate() {
+                        let byte_f = F::from(*byte as u64);
+                        if i > 0 {
+                            pow_r *= randomness;
+                        }
+                        value_num = value_num * F::from(256) + byte_f;
+                        value_le = value_le * randomness + byte_f;
+                        value_be += byte_f * pow_r;
+                        input_rlc = input_rlc * challenges.keccak_input() + Value::known(byte_f);
+
+                        self.assign_row(&mut region, offset, rpi_len, i == 0, *byte, input_rlc)?;
+                        let randomness_assigned = region.assign_advice(
+                            || format!("randomness {}", offset),
+                            self.randomness,
+                            offset,
+                            || Value::known(randomness),
+                        )?;
+                        if offset == 0 {
+                            region.constrain_equal(
+                                randomness_assigned.cell(),
+                                randomness_cell.cell(),
+                            )?;
+                        }
+                        for (name, column, value) in [
+                            ("pow_r", self.pow_r, pow_r),
+                            ("hash_hi", self.hash_hi, F::zero()),
+                            ("hash_lo", self.hash_lo, F::zero()),
+                        ] {
+                            region.assign_advice(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(value),
+                            )?;
+                        }
+                        region.assign_advice(
+                            || format!("hash_rlc {}", offset),
+                            self.hash_rlc,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+                        for (cell_encoding, name, column, value) in [
+                            (RpiEncoding::Number, "value_num", self.value_num, value_num),
+                            (RpiEncoding::RlcLe, "value_le", self.value_le, value_le),
+                            (RpiEncoding::RlcBe, "value_be", self.value_be, value_be),
+                        ] {
+                            let cell = region.assign_advice(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(value),
+                            )?;
+                            // The encoding of the field is complete at its last
+                            // byte
+                            if cell_encoding == *encoding && i == field.len() - 1 {
+                                region.constrain_equal(cell.cell(), rpi_cell.cell())?;
+                            }
+                        }
+                        offset += 1;
+                    }
+                }
+
+                let mut hash_parts = [0u128; 2];
+                let mut hash_rlc = Value::known(F::zero());
+                let mut hash_cells = Vec::new();
+                for (i, byte) in hash.iter().enumerate() {
+                    hash_parts[i / 16] = hash_parts[i / 16] * 256 + *byte as u128;
+                    hash_rlc =
+                        hash_rlc * challenges.evm_word() + Value::known(F::from(*byte as u64));
+
+                    self.assign_row(&mut region, offset, rpi_len, false, *byte, input_rlc)?;
+                    region.assign_advice(
+                        || format!("randomness {}", offset),
+                        self.randomness,
+                        offset,
+                        || Value::known(randomness),
+                    )?;
+                    for (name, column) in [
+                        ("pow_r", self.pow_r),
+                        ("value_num", self.value_num),
+                        ("value_le", self.value_le),
+                        ("value_be", self.value_be),
+                    ] {
+                        region.assign_advice(
+                            || format!("{} {}", name, offset),
+                            column,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+                    }
+                    hash_cells = [("hash_hi", self.hash_hi), ("hash_lo", self.hash_lo)]
+                        .into_iter()
+                        .zip(hash_parts)
+                        .map(|((name, column), part)| {
+                            region.assign_advice(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(F::from_u128(part)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    region.assign_advice(
+                        || format!("hash_rlc {}", offset),
+                        self.hash_rlc,
+                        offset,
+                        || hash_rlc,
+                    )?;
+                    offset += 1;
+                }
+
+                Ok(hash_cells
+                    .try_into()
+                    .expect("the region ends with the hash rows"))
+            },
+        )
+    }
+}
@@ -0,0 +1,1524 @@
+//! Block header region of the PublicInputs circuit.
+//!
+//! The region proves that the block hash is the keccak hash of the RLP
+//! encoding of the block header, whose fields are linked to the public values
+//! of the block, and that the history hashes form a chain of parent hashes
+//! that ends at the parent of the block.  It assigns one byte per row:
+//!
+//! - The fields of the header, in the order of its RLP encoding, each one in a
+//!   fixed number of rows: its maximum length.  Integers and the extra data are
+//!   right aligned, preceded by padding rows, and integers must be minimal.
+//! - The 32 bytes of the block hash.
+//! - The chain id, as an integer of 8 bytes.
+//! - The rw counter of the first operation and the id of the first tx of the
+//!   chunk of the block, as integers of 8 bytes, which are copied from the rw
+//!   commitment region that proves them.
+//! - The 32 bytes of each of the 256 history hashes, oldest first.
+//!
+//! The bytes of each field are accumulated in the encodings used by the rest
+//! of the circuit: as a number and as RLCs with the PI randomness (of the
+//! little and big endian bytes), which are copied to the raw public inputs,
+//! and as RLCs with the keccak input and EVM word challenges, which are used
+//! in the keccak lookups and to link the transactions root to the root of the
+//! transactions trie.  The RLP encoding of every header field is added to
+//! the RLC of the header, which is looked up in the keccak table with the
+//! block hash.
+//!
+//! The parent hash of the header is the last history hash.  Every history hash
+//! but the oldest one is, if not zero, the hash of an RLP list whose first item
+//! is the previous history hash.  Only that item is decoded from the headers of
+//! the history, the rest of their bytes are witnessed as an RLC.  History
+//! hashes that are zero are missing and must precede the rest, so the oldest
+//! history hash that is not zero must be the one of the genesis block, whose
+//! parent hash is zero, unless it's the first one.
+//!
+//! In a batch, the header and the hash of every previous block of the batch
+//! are assigned in a region of their own, whose block hash is the history hash
+//! of its number.  The integers of the headers, the chain id and the history
+//! hashes are the rows of the BlockTable, which are looked up in the rows
+//! proved by these regions, in the encoding of the table: numbers, and RLCs
+//! with the EVM word challenge of the little endian bytes.  So are the first
+//! rw counter and tx id of the chunk, but not whether it's the last one.
+//!
+//! The number of history hashes is not checked against the number of the
+//! block, and the base fee is always part of the header.
+
+use super::PublicData;
+use crate::{
+    evm_circuit::util::constraint_builder::BaseConstraintBuilder,
+    table::{BlockContextFieldTag, KeccakTable},
+    util::Challenges,
+};
+use eth_types::{geth_types::block_header_rlp, Field, ToBigEndian, Word, H256};
+use ethers_core::utils::keccak256;
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{and, not, select, Expr},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase, VirtualCells,
+    },
+    poly::Rotation,
+};
+
+/// Number of history hashes
+pub(crate) const HISTORY_LEN: usize = 256;
+
+/// Maximum length of the RLP encoding of a block header, which bounds the
+/// length of the headers of the history hashes.
+pub(crate) const MAX_HEADER_LEN: usize = 1024;
+
+const MAX_DEGREE: usize = 9;
+
+/// RLP prefix of a string whose length is encoded in two bytes
+const LONG_STRING_PREFIX: u64 = 0xb9;
+/// RLP prefix of a list whose length is encoded in two bytes
+const LONG_LIST_PREFIX: u64 = 0xf9;
+/// RLP prefix of a 32 bytes string
+const HASH_PREFIX: u64 = 0xa0;
+/// Length of the RLP encoding of the parent hash, the first item of a header
+const PARENT_HASH_LEN: u64 = 33;
+
+/// Field assigned in the header region.  The fields of the header are
+/// followed by the block hash and the history hashes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HeaderField {
+    /// Parent hash
+    ParentHash,
+    /// Hash of the ommers list
+    OmmersHash,
+    /// Coinbase
+    Beneficiary,
+    /// State root
+    StateRoot,
+    /// Transactions root
+    TransactionsRoot,
+    /// Receipts root
+    ReceiptsRoot,
+    /// Bloom filter of the logs
+    LogsBloom,
+    /// Difficulty
+    Difficulty,
+    /// Number
+    Number,
+    /// Gas limit
+    GasLimit,
+    /// Gas used
+    GasUsed,
+    /// Timestamp
+    Timestamp,
+    /// Extra data
+    ExtraData,
+    /// Mix hash
+    MixHash,
+    /// Nonce
+    Nonce,
+    /// Base fee
+    BaseFee,
+    /// Hash of the header
+    BlockHash,
+    /// Chain id, which is not part of the header
+    ChainId,
+    /// Rw counter of the first operation of the chunk, which is not part of
+    /// the header
+    RwCounterStart,
+    /// Id of the first tx of the chunk, which is not part of the header
+    FirstTxId,
+    /// One of the history hashes
+    HistoryHash,
+}
+
+/// How the bytes of a field are RLP encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FieldKind {
+    /// String of fixed length
+    Fixed,
+    /// Integer, encoded as its big endian bytes without leading zeros
+    Int,
+    /// String of variable length
+    Bytes,
+}
+
+impl HeaderField {
+    /// Fields of the header, in the order of its RLP encoding
+    const HEADER: [Self; 16] = [
+        Self::ParentHash,
+        Self::OmmersHash,
+        Self::Beneficiary,
+        Self::StateRoot,
+        Self::TransactionsRoot,
+        Self::ReceiptsRoot,
+        Self::LogsBloom,
+        Self::Difficulty,
+        Self::Number,
+        Self::GasLimit,
+        Self::GasUsed,
+        Self::Timestamp,
+        Self::ExtraData,
+        Self::MixHash,
+        Self::Nonce,
+        Self::BaseFee,
+    ];
+
+    /// Number of rows of the field, which is its maximum length in bytes
+    fn size(&self) -> usize {
+        match self {
+            Self::Beneficiary => 20,
+            Self::LogsBloom => 256,
+            Self::Number
+            | Self::GasLimit
+            | Self::GasUsed
+            | Self::Timestamp
+            | Self::Nonce
+            | Self::ChainId
+            | Self::RwCounterStart
+            | Self::FirstTxId => 8,
+            _ => 32,
+        }
+    }
+
+    fn kind(&self) -> FieldKind {
+        match self {
+            Self::Difficulty
+            | Self::Number
+            | Self::GasLimit
+            | Self::GasUsed
+            | Self::Timestamp
+            | Self::BaseFee
+            | Self::ChainId
+            | Self::RwCounterStart
+            | Self::FirstTxId => FieldKind::Int,
+            Self::ExtraData => FieldKind::Bytes,
+            _ => FieldKind::Fixed,
+        }
+    }
+
+    fn is_header(&self) -> bool {
+        !matches!(
+            self,
+            Self::BlockHash
+                | Self::ChainId
+                | Self::RwCounterStart
+                | Self::FirstTxId
+                | Self::HistoryHash
+        )
+    }
+
+    /// Whether the row of the BlockTable with the value of the field is
+    /// indexed by the number of the block, instead of 0.
+    fn is_block_indexed(&self) -> bool {
+        !matches!(self, Self::ChainId | Self::RwCounterStart | Self::FirstTxId)
+    }
+
+    /// Tag of the rows of the BlockTable with the value of the field, and
+    /// whether the value is encoded as an RLC instead of a number.
+    fn block_tag(&self) -> Option<(BlockContextFieldTag, bool)> {
+        match self {
+            Self::Beneficiary => Some((BlockContextFieldTag::Coinbase, false)),
+            Self::Timestamp => Some((BlockContextFieldTag::Timestamp, false)),
+            Self::Number => Some((BlockContextFieldTag::Number, false)),
+            Self::Difficulty => Some((BlockContextFieldTag::Difficulty, true)),
+            Self::GasLimit => Some((BlockContextFieldTag::GasLimit, false)),
+            Self::BaseFee => Some((BlockContextFieldTag::BaseFee, true)),
+            Self::ChainId => Some((BlockContextFieldTag::ChainId, true)),
+            Self::RwCounterStart => Some((BlockContextFieldTag::RwCounterStart, false)),
+            Self::FirstTxId => Some((BlockContextFieldTag::FirstTxId, false)),
+            Self::HistoryHash => Some((BlockContextFieldTag::BlockHash, true)),
+            _ => None,
+        }
+    }
+}
+
+/// Fields of a header region with their first row.  Only the region of the
+/// last block of a batch contains the chain id, the fields of the chunk and
+/// the history hashes.
+fn layout(is_last: bool) -> Vec<(HeaderField, usize)> {
+    let mut fields = HeaderField::HEADER.to_vec();
+    fields.push(HeaderField::BlockHash);
+    if is_last {
+        fields.extend([
+            HeaderField::ChainId,
+            HeaderField::RwCounterStart,
+            HeaderField::FirstTxId,
+        ]);
+        fields.extend([HeaderField::HistoryHash; HISTORY_LEN]);
+    }
+    let mut offset = 0;
+    fields
+        .into_iter()
+        .map(|field| {
+            let start = offset;
+            offset += field.size();
+            (field, start)
+        })
+        .collect()
+}
+
+/// Number of rows of a header region
+fn region_len(is_last: bool) -> usize {
+    layout(is_last)
+        .last()
+        .map(|(field, start)| start + field.size())
+        .unwrap_or_default()
+}
+
+/// Number of rows of the header regions of a block and of the
+/// `num_prev_blocks` previous blocks of its batch
+pub(crate) fn header_region_len(num_prev_blocks: usize) -> usize {
+    region_len(true) + num_prev_blocks * region_len(false)
+}
+
+/// Tags of the rows of the BlockTable with the fields of the chunk of the
+/// block that are not proved by the header regions.
+const CHUNK_TAGS: [BlockContextFieldTag; 1] = [BlockContextFieldTag::IsLastChunk];
+
+/// Factor that scales a row of the BlockTable in the lookup of the rows proved
+/// by the header regions, which is zero for the rows of the chunk that are not
+/// proved, so that they are looked up as the all-zero row.  It's not zero for
+/// the other tags, and it's looked up with the scaled row, so that the scaled
+/// row matches only the row with its tag.
+pub(crate) fn block_tag_factor<F: Field>(tag: Expression<F>) -> Expression<F> {
+    CHUNK_TAGS.iter().fold(1.expr(), |acc, chunk_tag| {
+        acc * (tag.clone() - chunk_tag.expr())
+    })
+}
+
+/// Value of [`block_tag_factor`] for `tag`
+fn block_tag_factor_value<F: Field>(tag: BlockContextFieldTag) -> F {
+    CHUNK_TAGS.iter().fold(F::one(), |acc, chunk_tag| {
+        acc * (F::from(tag as u64) - F::from(*chunk_tag as u64))
+    })
+}
+
+/// Minimal big endian bytes of an integer
+fn int_bytes(value: Word) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    bytes[leading_zeros..].to_vec()
+}
+
+/// Cells of the header region that are copied to the raw public inputs, or
+/// linked to the values proved by other sub-circuits.
+#[derive(Clone, Debug)]
+pub(crate) struct HeaderCells<F: Field> {
+    /// PI randomness, which is exposed as a public input
+    pub(crate) randomness: AssignedCell<F, F>,
+    /// Coinbase, as a number
+    pub(crate) beneficiary: AssignedCell<F, F>,
+    /// Number, as a number
+    pub(crate) number: AssignedCell<F, F>,
+    /// Gas limit, as a number
+    pub(crate) gas_limit: AssignedCell<F, F>,
+    /// Timestamp, as a number
+    pub(crate) timestamp: AssignedCell<F, F>,
+    /// Difficulty, as the RLC of its little endian bytes
+    pub(crate) difficulty: AssignedCell<F, F>,
+    /// Base fee, as the RLC of its little endian bytes
+    pub(crate) base_fee: AssignedCell<F, F>,
+    /// Chain id, as a number
+    pub(crate) chain_id: AssignedCell<F, F>,
+    /// Rw counter of the first operation of the chunk, as a number
+    pub(crate) rw_counter_start: AssignedCell<F, F>,
+    /// Id of the first tx of the chunk, as a number
+    pub(crate) first_tx_id: AssignedCell<F, F>,
+    /// State root, as the RLC of its big endian bytes
+    pub(crate) state_root: AssignedCell<F, F>,
+    /// Transactions root, as the RLC of its big endian bytes with the EVM
+    /// word challenge
+    pub(crate) transactions_root: AssignedCell<F, F>,
+    /// Receipts root, as the RLC of its big endian bytes with the EVM word
+    /// challenge
+    pub(crate) receipts_root: AssignedCell<F, F>,
+    /// Logs bloom, as the RLC of its bytes with the EVM word challenge
+    pub(crate) logs_bloom: AssignedCell<F, F>,
+    /// Block hash, as the RLC of its big endian bytes
+    pub(crate) block_hash: AssignedCell<F, F>,
+    /// History hashes, as the RLC of their big endian bytes
+    pub(crate) history_hashes: Vec<AssignedCell<F, F>>,
+}
+
+/// Values assigned in a header region
+struct HeaderValues<'a> {
+    header: eth_types::Block<()>,
+    block_hash: H256,
+    chain_id: Word,
+    /// First rw counter and tx id of the chunk of the block
+    rw_counter_start: usize,
+    first_tx_id: usize,
+    /// History hashes, padded to `HISTORY_LEN`, with their headers.  Empty
+    /// for the previous blocks of a batch.
+    history: Vec<(H256, Option<&'a Vec<u8>>)>,
+}
+
+/// Config of the header region of the PublicInputs circuit
+#[derive(Clone, Debug)]
+pub(crate) struct BlockHeaderConfig<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    is_field_start: Column<Fixed>,
+    is_field_end: Column<Fixed>,
+    /// Rows of the fields of the header
+    q_header: Column<Fixed>,
+    /// Rows of integers and the extra data, which have variable length
+    q_var: Column<Fixed>,
+    /// Rows of integers
+    q_int: Column<Fixed>,
+    /// Rows of the logs bloom
+    q_bloom: Column<Fixed>,
+    /// RLP prefix of the fields of fixed length but the logs bloom
+    prefix: Column<Fixed>,
+    /// Last row of the block hash
+    q_block_hash: Column<Fixed>,
+    /// Last row of every history hash
+    q_history: Column<Fixed>,
+    /// Last row of every history hash but the first
+    q_chain: Column<Fixed>,
+    /// Last row of the number
+    q_number: Column<Fixed>,
+    /// Row of the BlockTable proved at the last row of a field: (block_tag,
+    /// q_block_num * number - block_index_delta, value_evm if q_block_word
+    /// else value_num), scaled by `block_factor`, the `block_tag_factor` of
+    /// its tag, which is zero in the rest of the rows.
+    block_tag: Column<Fixed>,
+    block_factor: Column<Fixed>,
+    q_block_num: Column<Fixed>,
+    block_index_delta: Column<Fixed>,
+    q_block_word: Column<Fixed>,
+    /// Table of the powers of the keccak input challenge: (q_pow_table,
+    /// pow_index, pow_table)
+    q_pow_table: Column<Fixed>,
+    pow_index: Column<Fixed>,
+
+    randomness: Column<Advice>,
+    /// Number of the block of the region, in all its rows
+    number: Column<Advice>,
+    byte: Column<Advice>,
+    byte_inv: Column<Advice>,
+    is_pad: Column<Advice>,
+    is_single: Column<Advice>,
+    len: Column<Advice>,
+    value_num: Column<Advice>,
+    value_le: Column<Advice>,
+    value_be: Column<Advice>,
+    pow_r: Column<Advice>,
+    value_keccak: Column<Advice>,
+    pow_keccak: Column<Advice>,
+    value_evm: Column<Advice>,
+    payload_rlc: Column<Advice>,
+    payload_pow: Column<Advice>,
+    payload_len: Column<Advice>,
+    len_hi: Column<Advice>,
+    len_lo: Column<Advice>,
+    is_present: Column<Advice>,
+    rest_rlc: Column<Advice>,
+    rest_pow: Column<Advice>,
+    pow_table: Column<Advice>,
+
+    len_is_one: IsZeroConfig<F>,
+
+    keccak_table: KeccakTable,
+}
+
+impl<F: Field> BlockHeaderConfig<F> {
+    /// Configure the header region.  The bytes are range checked with
+    /// `u8_table`.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        u8_table: Column<Fixed>,
+        keccak_table: KeccakTable,
+        challenges: Challenges<Expression<F>>,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_first = meta.fixed_column();
+        let is_field_start = meta.fixed_column();
+        let is_field_end = meta.fixed_column();
+        let q_header = meta.fixed_column();
+        let q_var = meta.fixed_column();
+        let q_int = meta.fixed_column();
+        let q_bloom = meta.fixed_column();
+        let prefix = meta.fixed_column();
+        let q_block_hash = meta.fixed_column();
+        let q_history = meta.fixed_column();
+        let q_chain = meta.fixed_column();
+        let q_number = meta.fixed_column();
+        let block_tag = meta.fixed_column();
+        let block_factor = meta.fixed_column();
+        let q_block_num = meta.fixed_column();
+        let block_index_delta = meta.fixed_column();
+        let q_block_word = meta.fixed_column();
+        let q_pow_table = meta.fixed_column();
+        let pow_index = meta.fixed_column();
+
+        let randomness = meta.advice_column();
+        let number = meta.advice_column();
+        let byte = meta.advice_column();
+        let byte_inv = meta.advice_column();
+        let is_pad = meta.advice_column();
+        let is_single = meta.advice_column();
+        let len = meta.advice_column();
+        let value_num = meta.advice_column();
+        let value_le = meta.advice_column_in(SecondPhase);
+        let value_be = meta.advice_column_in(SecondPhase);
+        let pow_r = meta.advice_column_in(SecondPhase);
+        let value_keccak = meta.advice_column_in(SecondPhase);
+        let pow_keccak = meta.advice_column_in(SecondPhase);
+        let value_evm = meta.advice_column_in(SecondPhase);
+        let payload_rlc = meta.advice_column_in(SecondPhase);
+        let payload_pow = meta.advice_column_in(SecondPhase);
+        let payload_len = meta.advice_column();
+        let len_hi = meta.advice_column();
+        let len_lo = meta.advice_column();
+        let is_present = meta.advice_column();
+        let rest_rlc = meta.advice_column_in(SecondPhase);
+        let rest_pow = meta.advice_column_in(SecondPhase);
+        let pow_table = meta.advice_column_in(SecondPhase);
+
+        for column in [
+            randomness,
+            value_num,
+            value_le,
+            value_be,
+            value_keccak,
+            value_evm,
+        ] {
+            meta.enable_equality(column);
+        }
+
+        let len_inv = meta.advice_column();
+        let len_is_one = IsZeroChip::configure(
+            meta,
+            |meta| {
+                meta.query_fixed(q_var, Rotation::cur())
+                    * meta.query_fixed(is_field_end, Rotation::cur())
+            },
+            |meta| meta.query_advice(len, Rotation::cur()) - 1.expr(),
+            len_inv,
+        );
+
+        let keccak_input = challenges.keccak_input();
+        let evm_word = challenges.evm_word();
+
+        meta.create_gate("header field bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_first = meta.query_fixed(q_first, Rotation::cur());
+            let is_field_start = meta.query_fixed(is_field_start, Rotation::cur());
+            let q_var = meta.query_fixed(q_var, Rotation::cur());
+            let q_int = meta.query_fixed(q_int, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_pad_prev = meta.query_advice(is_pad, Rotation::prev());
+            let is_pad = meta.query_advice(is_pad, Rotation::cur());
+            let randomness_cur = meta.query_advice(randomness, Rotation::cur());
+            // The accumulators of a field start from zero at its first row.
+            let mut prev = |column| {
+                not::expr(is_field_start.clone()) * meta.query_advice(column, Rotation::prev())
+            };
+            let len_prev = prev(len);
+            let value_num_prev = prev(value_num);
+            let value_le_prev = prev(value_le);
+            let value_be_prev = prev(value_be);
+            let value_keccak_prev = prev(value_keccak);
+            let value_evm_prev = prev(value_evm);
+            let pow_r_prev = prev(pow_r);
+            let pow_keccak_prev = prev(pow_keccak);
+
+            cb.require_boolean("is_pad is boolean", is_pad.clone());
+            cb.require_boolean(
+                "is_single is boolean",
+                meta.query_advice(is_single, Rotation::cur()),
+            );
+            cb.require_boolean(
+                "is_present is boolean",
+                meta.query_advice(is_present, Rotation::cur()),
+            );
+            cb.require_zero("padding bytes are zero", is_pad.clone() * byte.clone());
+            cb.condition(not::expr(q_var), |cb| {
+                cb.require_zero("only fields of variable length are padded", is_pad.clone());
+            });
+            cb.condition(
+                and::expr([
+                    not::expr(is_field_start.clone()),
+                    not::expr(is_pad_prev.clone()),
+                ]),
+                |cb| {
+                    cb.require_zero("padding precedes the bytes of a field", is_pad.clone());
+                },
+            );
+            cb.condition(
+                q_int
+                    * not::expr(is_pad.clone())
+                    * select::expr(is_field_start.clone(), 1.expr(), is_pad_prev),
+                |cb| {
+                    cb.require_equal(
+                        "integers have no leading zeros",
+                        byte.clone() * meta.query_advice(byte_inv, Rotation::cur()),
+                        1.expr(),
+                    );
+                },
+            );
+
+            cb.require_equal(
+                "len counts the bytes of the field that are not padding",
+                meta.query_advice(len, Rotation::cur()),
+                len_prev + not::expr(is_pad.clone()),
+            );
+            cb.require_equal(
+                "value_num = value_num_prev * 256 + byte",
+                meta.query_advice(value_num, Rotation::cur()),
+                value_num_prev * 256.expr() + byte.clone(),
+            );
+            cb.require_equal(
+                "value_le = value_le_prev * randomness + byte",
+                meta.query_advice(value_le, Rotation::cur()),
+                value_le_prev * randomness_cur.clone() + byte.clone(),
+            );
+            let pow_r_cur = meta.query_advice(pow_r, Rotation::cur());
+            cb.require_equal(
+                "pow_r = randomness^index",
+                pow_r_cur.clone(),
+                select::expr(
+                    is_field_start.clone(),
+                    1.expr(),
+                    pow_r_prev * randomness_cur.clone(),
+                ),
+            );
+            cb.require_equal(
+                "value_be = value_be_prev + byte * pow_r",
+                meta.query_advice(value_be, Rotation::cur()),
+                value_be_prev + byte.clone() * pow_r_cur,
+            );
+            cb.require_equal(
+                "value_keccak = value_keccak_prev * keccak_input + byte",
+                meta.query_advice(value_keccak, Rotation::cur()),
+                value_keccak_prev * keccak_input.clone() + byte.clone(),
+            );
+            cb.require_equal(
+                "pow_keccak = keccak_input^len",
+                meta.query_advice(pow_keccak, Rotation::cur()),
+                select::expr(is_field_start, 1.expr(), pow_keccak_prev)
+                    * select::expr(is_pad, 1.expr(), keccak_input.clone()),
+            );
+            cb.require_equal(
+                "value_evm = value_evm_prev * evm_word + byte",
+                meta.query_advice(value_evm, Rotation::cur()),
+                value_evm_prev * evm_word.clone() + byte,
+            );
+            let number_cur = meta.query_advice(number, Rotation::cur());
+            cb.condition(not::expr(is_first), |cb| {
+                cb.require_equal(
+                    "randomness is the same in all rows",
+                    randomness_cur,
+                    meta.query_advice(randomness, Rotation::prev()),
+                );
+                cb.require_equal(
+                    "number is the same in all rows",
+                    number_cur.clone(),
+                    meta.query_advice(number, Rotation::prev()),
+                );
+            });
+            cb.condition(meta.query_fixed(q_number, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "number is the number of the header",
+                    number_cur,
+                    meta.query_advice(value_num, Rotation::cur()),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("header payload", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_first = meta.query_fixed(q_first, Rotation::cur());
+            let is_header_field_end = meta.query_fixed(q_header, Rotation::cur())
+                * meta.query_fixed(is_field_end, Rotation::cur());
+            let q_var = meta.query_fixed(q_var, Rotation::cur());
+            let q_bloom = meta.query_fixed(q_bloom, Rotation::cur());
+            let is_single = meta.query_advice(is_single, Rotation::cur());
+            let len = meta.query_advice(len, Rotation::cur());
+            let payload_rlc_cur = meta.query_advice(payload_rlc, Rotation::cur());
+            let payload_pow_cur = meta.query_advice(payload_pow, Rotation::cur());
+            let payload_len_cur = meta.query_advice(payload_len, Rotation::cur());
+            let payload_rlc_prev = meta.query_advice(payload_rlc, Rotation::prev());
+            let payload_pow_prev = meta.query_advice(payload_pow, Rotation::prev());
+            let payload_len_prev = meta.query_advice(payload_len, Rotation::prev());
+
+            cb.condition(is_first.clone(), |cb| {
+                cb.require_zero("payload_rlc starts at 0", payload_rlc_cur.clone());
+                cb.require_equal("payload_pow starts at 1", payload_pow_cur.clone(), 1.expr());
+                cb.require_zero("payload_len starts at 0", payload_len_cur.clone());
+            });
+            cb.condition(
+                and::expr([not::expr(is_first), not::expr(is_header_field_end.clone())]),
+                |cb| {
+                    cb.require_equal(
+                        "payload_rlc is unchanged",
+                        payload_rlc_cur.clone(),
+                        payload_rlc_prev.clone(),
+                    );
+                    cb.require_equal(
+                        "payload_pow is unchanged",
+                        payload_pow_cur.clone(),
+                        payload_pow_prev.clone(),
+                    );
+                    cb.require_equal(
+                        "payload_len is unchanged",
+                        payload_len_cur.clone(),
+                        payload_len_prev.clone(),
+                    );
+                },
+            );
+            cb.condition(
+                q_var.clone() * meta.query_fixed(is_field_end, Rotation::cur()),
+                |cb| {
+                    cb.require_zero(
+                        "a single byte is a field of length 1",
+                        is_single.clone() * (len.clone() - 1.expr()),
+                    );
+                },
+            );
+            cb.condition(is_header_field_end, |cb| {
+                // The RLP prefix is 0x80 + size for strings of fixed length,
+                // 0xb90100 for the logs bloom, and 0x80 + len for fields of
+                // variable length, unless they are a single byte below 0x80.
+                let prefix_pow = keccak_input.clone()
+                    + q_bloom.clone() * (keccak_input.clone().square() - 1.expr())
+                        * keccak_input.clone()
+                    + q_var.clone() * is_single.clone() * (1.expr() - keccak_input.clone());
+                let prefix_rlc = meta.query_fixed(prefix, Rotation::cur())
+                    + q_bloom.clone()
+                        * (LONG_STRING_PREFIX.expr() * keccak_input.clone().square()
+                            + keccak_input.clone())
+                    + q_var.clone() * not::expr(is_single.clone()) * (0x80.expr() + len.clone());
+                let prefix_len = (1.expr() - q_var.clone()) * (1.expr() + 2.expr() * q_bloom)
+                    + q_var * not::expr(is_single);
+                let pow_keccak = meta.query_advice(pow_keccak, Rotation::cur());
+
+                cb.require_equal(
+                    "payload_rlc = payload_rlc_prev * keccak_input^(prefix_len + len) + rlc(prefix || field)",
+                    payload_rlc_cur,
+                    payload_rlc_prev * prefix_pow.clone() * pow_keccak.clone()
+                        + prefix_rlc * pow_keccak.clone()
+                        + meta.query_advice(value_keccak, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "payload_pow = payload_pow_prev * keccak_input^(prefix_len + len)",
+                    payload_pow_cur,
+                    payload_pow_prev * prefix_pow * pow_keccak,
+                );
+                cb.require_equal(
+                    "payload_len = payload_len_prev + prefix_len + len",
+                    payload_len_cur,
+                    payload_len_prev + prefix_len + len,
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("header list length", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let list_len = meta.query_advice(len_hi, Rotation::cur()) * 256.expr()
+                + meta.query_advice(len_lo, Rotation::cur());
+            cb.condition(meta.query_fixed(q_block_hash, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "the list length of the header is the length of its payload",
+                    list_len,
+                    meta.query_advice(payload_len, Rotation::cur()),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("history hashes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_present_prev = meta.query_advice(
+                is_present,
+                Rotation(-(HeaderField::HistoryHash.size() as i32)),
+            );
+            let is_present = meta.query_advice(is_present, Rotation::cur());
+            cb.condition(meta.query_fixed(q_history, Rotation::cur()), |cb| {
+                cb.require_zero(
+                    "missing history hashes are zero",
+                    not::expr(is_present.clone())
+                        * meta.query_advice(value_keccak, Rotation::cur()),
+                );
+            });
+            cb.condition(meta.query_fixed(q_chain, Rotation::cur()), |cb| {
+                cb.require_zero(
+                    "missing history hashes precede the rest",
+                    not::expr(is_present) * is_present_prev,
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("powers of keccak_input", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_first = meta.query_fixed(q_first, Rotation::cur());
+            let pow_table_cur = meta.query_advice(pow_table, Rotation::cur());
+            cb.condition(is_first.clone(), |cb| {
+                cb.require_equal("the first power is 1", pow_table_cur.clone(), 1.expr());
+            });
+            cb.condition(not::expr(is_first), |cb| {
+                cb.require_equal(
+                    "pow_table = pow_table_prev * keccak_input",
+                    pow_table_cur,
+                    meta.query_advice(pow_table, Rotation::prev()) * keccak_input.clone(),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_pow_table, Rotation::cur()))
+        });
+
+        meta.lookup_any("header bytes are in u8 range", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            [byte, len_hi, len_lo]
+                .into_iter()
+                .map(|column| {
+                    (
+                        q_enable.clone() * meta.query_advice(column, Rotation::cur()),
+                        meta.query_fixed(u8_table, Rotation::cur()),
+                    )
+                })
+                .collect()
+        });
+
+        meta.lookup_any("single byte fields are below 0x80", |meta| {
+            // A field of variable length is a single byte iff it has length 1
+            // and its byte is below 0x80: 2 * byte must be a byte when it's
+            // single, and byte - 0x80 when it's not but its length is 1.
+            // q_var is only set in the header region.
+            let enable = meta.query_fixed(q_var, Rotation::cur())
+                * meta.query_fixed(is_field_end, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_single = meta.query_advice(is_single, Rotation::cur());
+            vec![(
+                enable
+                    * (is_single.clone() * 2.expr() * byte.clone()
+                        + not::expr(is_single) * len_is_one.expr() * (byte - 0x80.expr())),
+                meta.query_fixed(u8_table, Rotation::cur()),
+            )]
+        });
+
+        meta.lookup_any("keccak256(rlp(header)) = block_hash", |meta| {
+            let enable = meta.query_fixed(q_enable, Rotation::cur())
+                * meta.query_fixed(q_block_hash, Rotation::cur());
+            let payload_len = meta.query_advice(payload_len, Rotation::cur());
+            let list_prefix_rlc = LONG_LIST_PREFIX.expr() * keccak_input.clone().square()
+                + meta.query_advice(len_hi, Rotation::cur()) * keccak_input.clone()
+                + meta.query_advice(len_lo, Rotation::cur());
+            let input_rlc = list_prefix_rlc * meta.query_advice(payload_pow, Rotation::cur())
+                + meta.query_advice(payload_rlc, Rotation::cur());
+
+            vec![
+                (
+                    enable.clone(),
+                    meta.query_advice(keccak_table.is_enabled, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * input_rlc,
+                    meta.query_advice(keccak_table.input_rlc, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * (payload_len + 3.expr()),
+                    meta.query_advice(keccak_table.input_len, Rotation::cur()),
+                ),
+                (
+                    enable * meta.query_advice(value_evm, Rotation::cur()),
+                    meta.query_advice(keccak_table.output_rlc, Rotation::cur()),
+                ),
+            ]
+        });
+
+        // The header of a history hash is rlp([parent_hash, ...rest]), with a
+        // list length of two bytes.
+        let history_list_len = |meta: &mut VirtualCells<'_, F>| {
+            meta.query_advice(len_hi, Rotation::cur()) * 256.expr()
+                + meta.query_advice(len_lo, Rotation::cur())
+        };
+        let history_enable = |meta: &mut VirtualCells<'_, F>| {
+            meta.query_fixed(q_enable, Rotation::cur())
+                * meta.query_fixed(q_chain, Rotation::cur())
+                * meta.query_advice(is_present, Rotation::cur())
+        };
+
+        meta.lookup_any(
+            "keccak256(rlp([history_hash_prev, ...rest])) = history_hash",
+            |meta| {
+                let enable = history_enable(meta);
+                let list_len = history_list_len(meta);
+                let keccak_input_pow = |exp: usize| {
+                    (0..exp).fold(1.expr(), |acc: Expression<F>, _| acc * keccak_input.clone())
+                };
+                let prefix_rlc = LONG_LIST_PREFIX.expr() * keccak_input_pow(3)
+                    + meta.query_advice(len_hi, Rotation::cur()) * keccak_input_pow(2)
+                    + meta.query_advice(len_lo, Rotation::cur()) * keccak_input.clone()
+                    + HASH_PREFIX.expr();
+                let parent_hash_rlc = meta.query_advice(
+                    value_keccak,
+                    Rotation(-(HeaderField::HistoryHash.size() as i32)),
+                );
+                let input_rlc = (prefix_rlc * keccak_input_pow(HeaderField::HistoryHash.size())
+                    + parent_hash_rlc)
+                    * meta.query_advice(rest_pow, Rotation::cur())
+                    + meta.query_advice(rest_rlc, Rotation::cur());
+
+                vec![
+                    (
+                        enable.clone(),
+                        meta.query_advice(keccak_table.is_enabled, Rotation::cur()),
+                    ),
+                    (
+                        enable.clone() * input_rlc,
+                        meta.query_advice(keccak_table.input_rlc, Rotation::cur()),
+                    ),
+                    (
+                        enable.clone() * (list_len + 3.expr()),
+                        meta.query_advice(keccak_table.input_len, Rotation::cur()),
+                    ),
+                    (
+                        enable * meta.query_advice(value_evm, Rotation::cur()),
+                        meta.query_advice(keccak_table.output_rlc, Rotation::cur()),
+                    ),
+                ]
+            },
+        );
+
+        meta.lookup_any("rest_pow = keccak_input^len(rest)", |meta| {
+            let enable = history_enable(meta);
+            let rest_len = history_list_len(meta) - PARENT_HASH_LEN.expr();
+            vec![
+                (
+                    enable.clone(),
+                    meta.query_fixed(q_pow_table, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * rest_len,
+                    meta.query_fixed(pow_index, Rotation::cur()),
+                ),
+                (
+                    enable * meta.query_advice(rest_pow, Rotation::cur()),
+                    meta.query_advice(pow_table, Rotation::cur()),
+                ),
+            ]
+        });
+
+        Self {
+            q_enable,
+            q_first,
+            is_field_start,
+            is_field_end,
+            q_header,
+            q_var,
+            q_int,
+            q_bloom,
+            prefix,
+            q_block_hash,
+            q_history,
+            q_chain,
+            q_number,
+            block_tag,
+            block_factor,
+            q_block_num,
+            block_index_delta,
+            q_block_word,
+            q_pow_table,
+            pow_index,
+            randomness,
+            number,
+            byte,
+            byte_inv,
+            is_pad,
+            is_single,
+            len,
+            value_num,
+            value_le,
+            value_be,
+            pow_r,
+            value_keccak,
+            pow_keccak,
+            value_evm,
+            payload_rlc,
+            payload_pow,
+            payload_len,
+            len_hi,
+            len_lo,
+            is_present,
+            rest_rlc,
+            rest_pow,
+            pow_table,
+            len_is_one,
+            keccak_table,
+        }
+    }
+
+    /// Expressions of the [`block_tag_factor`] of the row of the BlockTable
+    /// proved at the current row, followed by the row scaled by it, or zero.
+    pub(crate) fn block_table_row(&self, meta: &mut VirtualCells<'_, F>) -> Vec<Expression<F>> {
+        let factor = meta.query_fixed(self.block_factor, Rotation::cur());
+        let value_num = meta.query_advice(self.value_num, Rotation::cur());
+        let value = value_num.clone()
+            + meta.query_fixed(self.q_block_word, Rotation::cur())
+                * (meta.query_advice(self.value_evm, Rotation::cur()) - value_num);
+        let index = meta.query_fixed(self.q_block_num, Rotation::cur())
+            * meta.query_advice(self.number, Rotation::cur())
+            - meta.query_fixed(self.block_index_delta, Rotation::cur());
+        vec![
+            factor.clone(),
+            factor.clone() * meta.query_fixed(self.block_tag, Rotation::cur()),
+            factor.clone() * index,
+            factor * value,
+        ]
+    }
+
+    /// Assign the header regions from the public data: the one of the block
+    /// and the ones of the previous blocks of its batch, and return the cells
+    /// that are copied to the raw public inputs.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        public_data: &PublicData,
+        randomness: F,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<HeaderCells<F>, Error> {
+        let history_hashes = public_data.get_block_table_values().history_hashes;
+        // The headers of the history hashes, aligned with the padded history.
+        let history_headers: Vec<Option<&Vec<u8>>> = (0..HISTORY_LEN)
+            .map(|i| {
+                (i + public_data.history_hashes.len())
+                    .checked_sub(HISTORY_LEN)
+                    .and_then(|index| public_data.history_headers.get(index))
+            })
+            .collect();
+        let block = HeaderValues {
+            header: public_data.block_header(),
+            block_hash: public_data.block_hash(),
+            chain_id: public_data.chain_id,
+            rw_counter_start: public_data.rw_counter_start(),
+            first_tx_id: public_data.first_tx_id(),
+            history: history_hashes.into_iter().zip(history_headers).collect(),
+        };
+        let (randomness_cell, cells) =
+            self.assign_region(layouter, &block, randomness, challenges, None)?;
+
+        let field_cells = |field: HeaderField| {
+            cells
+                .iter()
+                .filter(move |(cells_field, _)| *cells_field == field)
+                .map(|(_, cells)| cells.clone())
+        };
+        let field_cell = |field: HeaderField, encoding: usize| {
+            field_cells(field)
+                .next()
+                .expect("the header region contains every field")[encoding]
+                .clone()
+        };
+        let history_hashes: Vec<_> = field_cells(HeaderField::HistoryHash)
+            .map(|cells| cells[2].clone())
+            .collect();
+
+        // The hash of the previous block with the distance `i` to the block
+        // is the history hash `HISTORY_LEN - i`.
+        let num_prev_blocks = public_data.prev_block_headers.len();
+        assert!(
+            num_prev_blocks < HISTORY_LEN,
+            "the previous blocks of a batch are in the history of its last block"
+        );
+        for (header, history_hash) in public_data
+            .prev_block_headers
+            .iter()
+            .zip(&history_hashes[HISTORY_LEN - num_prev_blocks..])
+        {
+            let prev_block = HeaderValues {
+                header: header.clone(),
+                block_hash: H256(keccak256(block_header_rlp(header))),
+                chain_id: public_data.chain_id,
+                rw_counter_start: public_data.rw_counter_start(),
+                first_tx_id: public_data.first_tx_id(),
+                history: Vec::new(),
+            };
+            self.assign_region(
+                layouter,
+                &prev_block,
+                randomness,
+                challenges,
+                Some((&randomness_cell, history_hash)),
+            )?;
+        }
+
+        Ok(HeaderCells {
+            randomness: randomness_cell,
+            beneficiary: field_cell(HeaderField::Beneficiary, 0),
+            number: field_cell(HeaderField::Number, 0),
+            gas_limit: field_cell(HeaderField::GasLimit, 0),
+            timestamp: field_cell(HeaderField::Timestamp, 0),
+            difficulty: field_cell(HeaderField::Difficulty, 1),
+            base_fee: field_cell(HeaderField::BaseFee, 1),
+            chain_id: field_cell(HeaderField::ChainId, 0),
+            rw_counter_start: field_cell(HeaderField::RwCounterStart, 0),
+            first_tx_id: field_cell(HeaderField::FirstTxId, 0),
+            state_root: field_cell(HeaderField::StateRoot, 2),
+            transactions_root: field_cell(HeaderField::TransactionsRoot, 4),
+            receipts_root: field_cell(HeaderField::ReceiptsRoot, 4),
+            logs_bloom: field_cell(HeaderField::LogsBloom, 4),
+            block_hash: field_cell(HeaderField::BlockHash, 2),
+            history_hashes,
+        })
+    }
+
+    /// Assign a header region, and return its randomness cell and the cells
+    /// of the encodings of its fields.  The region of the last block of the
+    /// batch has history hashes and contains the tables of the config, while
+    /// the one of a previous block is linked to the `randomness` and history
+    /// hash cells of the last block.
+    #[allow(clippy::type_complexity)]
+    fn assign_region(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        values: &HeaderValues<'_>,
+        randomness: F,
+        challenges: &Challenges<Value<F>>,
+        links: Option<(&AssignedCell<F, F>, &AssignedCell<F, F>)>,
+    ) -> Result<
+        (
+            AssignedCell<F, F>,
+            Vec<(HeaderField, [AssignedCell<F, F>; 5])>,
+        ),
+        Error,
+    > {
+        let is_last = links.is_none();
+        let header = &values.header;
+        let number = header.number.unwrap_or_default().as_u64();
+
+        let keccak_input = challenges.keccak_input();
+        let evm_word = challenges.evm_word();
+        let len_is_one_chip = IsZeroChip::construct(self.len_is_one.clone());
+
+        layouter.assign_region(
+            || {
+                if is_last {
+                    "pi block header".to_string()
+                } else {
+                    format!("pi header of block {}", number)
+                }
+            },
+            |mut region| {
+                if is_last {
+                    for offset in 0..=MAX_HEADER_LEN {
+                        region.assign_fixed(
+                            || "q_pow_table",
+                            self.q_pow_table,
+                            offset,
+                            || Value::known(F::one()),
+                        )?;
+                        region.assign_fixed(
+                            || "pow_index",
+                            self.pow_index,
+                            offset,
+                            || Value::known(F::from(offset as u64)),
+                        )?;
+                        region.assign_advice(
+                            || "pow_table",
+                            self.pow_table,
+                            offset,
+                            || {
+                                keccak_input
+                                    .map(|keccak_input| keccak_input.pow(&[offset as u64, 0, 0, 0]))
+                            },
+                        )?;
+                    }
+                    // All-zero row of the table of powers
+                    region.assign_advice(
+                        || "pow_table",
+                        self.pow_table,
+                        MAX_HEADER_LEN + 1,
+                        || Value::known(F::zero()),
+                    )?;
+                }
+
+                let mut history_fields = values.history.iter();
+                let mut payload_rlc = Value::known(F::zero());
+                let mut payload_pow = Value::known(F::one());
+                let mut payload_len = 0u64;
+                let mut history_index = 0;
+                let mut cells: Vec<(HeaderField, [AssignedCell<F, F>; 5])> = Vec::new();
+                let mut randomness_cell = None;
+
+                for (field, start) in layout(is_last) {
+                    let size = field.size();
+                    let kind = field.kind();
+                    let mut history_header = None;
+                    let bytes = match field {
+                        HeaderField::ParentHash => header.parent_hash.to_fixed_bytes().to_vec(),
+                        HeaderField::OmmersHash => header.uncles_hash.to_fixed_bytes().to_vec(),
+                        HeaderField::Beneficiary => {
+                            header.author.unwrap_or_default().to_fixed_bytes().to_vec()
+                        }
+                        HeaderField::StateRoot => header.state_root.to_fixed_bytes().to_vec(),
+                        HeaderField::TransactionsRoot => {
+                            header.transactions_root.to_fixed_bytes().to_vec()
+                        }
+                        HeaderField::ReceiptsRoot => header.receipts_root.to_fixed_bytes().to_vec(),
+                        HeaderField::LogsBloom => header
+                            .logs_bloom
+                            .unwrap_or_default()
+                            .to_fixed_bytes()
+                            .to_vec(),
+                        HeaderField::Difficulty => int_bytes(header.difficulty),
+                        HeaderField::Number => {
+                            int_bytes(header.number.unwrap_or_default().as_u64().into())
+                        }
+                        HeaderField::GasLimit => int_bytes(header.gas_limit),
+                        HeaderField::GasUsed => int_bytes(header.gas_used),
+                        HeaderField::Timestamp => int_bytes(header.timestamp),
+                        HeaderField::ExtraData => header.extra_data.to_vec(),
+                        HeaderField::MixHash => header
+                            .mix_hash
+                            .unwrap_or_default()
+                            .to_fixed_bytes()
+                            .to_vec(),
+                        HeaderField::Nonce => {
+                            header.nonce.unwrap_or_default().to_fixed_bytes().to_vec()
+                        }
+                        HeaderField::BaseFee => {
+                            int_bytes(header.base_fee_per_gas.unwrap_or_default())
+                        }
+                        HeaderField::BlockHash => values.block_hash.to_fixed_bytes().to_vec(),
+                        HeaderField::ChainId => int_bytes(values.chain_id),
+                        HeaderField::RwCounterStart => {
+                            int_bytes(Word::from(values.rw_counter_start as u64))
+                        }
+                        HeaderField::FirstTxId => int_bytes(Word::from(values.first_tx_id as u64)),
+                        HeaderField::HistoryHash => {
+                            let (hash, header) = history_fields
+                                .next()
+                                .expect("a history hash for every history field");
+                            if history_index > 0 {
+                                history_header = *header;
+                            }
+                            history_index += 1;
+                            hash.to_fixed_bytes().to_vec()
+                        }
+                    };
+                    assert!(
+                        bytes.len() <= size,
+                        "{:?} is longer than {} bytes",
+                        field,
+                        size
+                    );
+                    let pad = size - bytes.len();
+
+                    let mut len = 0u64;
+                    let mut value_num = F::zero();
+                    let mut value_le = F::zero();
+                    let mut value_be = F::zero();
+                    let mut pow_r = F::one();
+                    let mut value_keccak = Value::known(F::zero());
+                    let mut pow_keccak = Value::known(F::one());
+                    let mut value_evm = Value::known(F::zero());
+
+                    for index in 0..size {
+                        let offset = start + index;
+                        let is_start = index == 0;
+                        let is_end = index == size - 1;
+                        let is_pad = index < pad;
+                        let byte = if is_pad { 0 } else { bytes[index - pad] };
+                        let byte_f = F::from(byte as u64);
+
+                        if !is_start {
+                            pow_r *= randomness;
+                        }
+                        len += !is_pad as u64;
+                        value_num = value_num * F::from(256) + byte_f;
+                        value_le = value_le * randomness + byte_f;
+                        value_be += byte_f * pow_r;
+                        value_keccak = value_keccak * keccak_input + Value::known(byte_f);
+                        if !is_pad {
+                            pow_keccak = pow_keccak * keccak_input;
+                        }
+                        value_evm = value_evm * evm_word + Value::known(byte_f);
+
+                        let is_single = kind != FieldKind::Fixed && len == 1 && byte < 0x80;
+                        if field.is_header() && is_end {
+                            let (prefix_pow, prefix_rlc, prefix_len) = match (field, kind) {
+                                (HeaderField::LogsBloom, _) => (
+                                    keccak_input * keccak_input * keccak_input,
+                                    keccak_input.map(|keccak_input| {
+                                        F::from(LONG_STRING_PREFIX) * keccak_input.square()
+                                            + keccak_input
+                                    }),
+                                    3,
+                                ),
+                                (_, FieldKind::Fixed) => {
+                                    (keccak_input, Value::known(F::from(0x80 + size as u64)), 1)
+                                }
+                                _ if is_single => {
+                                    (Value::known(F::one()), Value::known(F::zero()), 0)
+                                }
+                                _ => (keccak_input, Value::known(F::from(0x80 + len)), 1),
+                            };
+                            payload_rlc = payload_rlc * prefix_pow * pow_keccak
+                                + prefix_rlc * pow_keccak
+                                + value_keccak;
+                            payload_pow = payload_pow * prefix_pow * pow_keccak;
+                            payload_len += prefix_len + len;
+                        }
+
+                        let (len_hi, len_lo, rest_rlc, rest_pow) =
+                            if field == HeaderField::BlockHash && is_end {
+                                (
+                                    payload_len >> 8,
+                                    payload_len & 0xff,
+                                    Value::known(F::zero()),
+                                    Value::known(F::zero()),
+                                )
+                            } else if let (true, Some(history_header)) = (is_end, history_header) {
+                                let rest = history_header
+                                    .get(3 + PARENT_HASH_LEN as usize..)
+                                    .unwrap_or_default();
+                                (
+                                    history_header.get(1).copied().unwrap_or_default() as u64,
+                                    history_header.get(2).copied().unwrap_or_default() as u64,
+                                    rest.iter().fold(Value::known(F::zero()), |acc, byte| {
+                                        acc * keccak_input + Value::known(F::from(*byte as u64))
+                                    }),
+                                    keccak_input.map(|keccak_input| {
+                                        keccak_input.pow(&[rest.len() as u64, 0, 0, 0])
+                                    }),
+                                )
+                            } else {
+                                (0, 0, Value::known(F::zero()), Value::known(F::zero()))
+                            };
+                        let is_present = field == HeaderField::HistoryHash
+                            && is_end
+                            && bytes.iter().any(|byte| *byte != 0);
+
+                        for (name, column, value) in [
+                            ("q_enable", self.q_enable, true as u64),
+                            ("q_first", self.q_first, (offset == 0) as u64),
+                            ("is_field_start", self.is_field_start, is_start as u64),
+                            ("is_field_end", self.is_field_end, is_end as u64),
+                            ("q_header", self.q_header, field.is_header() as u64),
+                            ("q_var", self.q_var, (kind != FieldKind::Fixed) as u64),
+                            ("q_int", self.q_int, (kind == FieldKind::Int) as u64),
+                            (
+                                "q_bloom",
+                                self.q_bloom,
+                                (field == HeaderField::LogsBloom) as u64,
+                            ),
+                            (
+                                "prefix",
+                                self.prefix,
+                                if field.is_header()
+                                    && kind == FieldKind::Fixed
+                                    && field != HeaderField::LogsBloom
+                                {
+                                    0x80 + size as u64
+                                } else {
+                                    0
+                                },
+                            ),
+                            (
+                                "q_block_hash",
+                                self.q_block_hash,
+                                (field == HeaderField::BlockHash && is_end) as u64,
+                            ),
+                            (
+                                "q_history",
+                                self.q_history,
+                                (field == HeaderField::HistoryHash && is_end) as u64,
+                            ),
+                            (
+                                "q_chain",
+                                self.q_chain,
+                                (field == HeaderField::HistoryHash && is_end && history_index > 1)
+                                    as u64,
+                            ),
+                            (
+                                "q_number",
+                                self.q_number,
+                                (field == HeaderField::Number && is_end) as u64,
+                            ),
+                        ] {
+                            region.assign_fixed(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(F::from(value)),
+                            )?;
+                        }
+                        // The history hash with index i is the hash of the
+                        // block number - (HISTORY_LEN - i), and the chain id
+                        // and the fields of the chunk have index 0.
+                        let block_row = field.block_tag().filter(|_| is_end);
+                        let index_delta = if field == HeaderField::HistoryHash {
+                            HISTORY_LEN + 1 - history_index
+                        } else {
+                            0
+                        };
+                        for (name, column, value) in [
+                            (
+                                "block_tag",
+                                self.block_tag,
+                                F::from(block_row.map_or(0, |(tag, _)| tag as u64)),
+                            ),
+                            (
+                                "block_factor",
+                                self.block_factor,
+                                block_row.map_or(F::zero(), |(tag, _)| block_tag_factor_value(tag)),
+                            ),
+                            (
+                                "q_block_num",
+                                self.q_block_num,
+                                F::from(field.is_block_indexed() as u64),
+                            ),
+                            (
+                                "block_index_delta",
+                                self.block_index_delta,
+                                F::from(index_delta as u64),
+                            ),
+                            (
+                                "q_block_word",
+                                self.q_block_word,
+                                F::from(block_row.map_or(false, |(_, is_word)| is_word) as u64),
+                            ),
+                        ] {
+                            region.assign_fixed(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(value),
+                            )?;
+                        }
+
+                        for (name, column, value) in [
+                            ("byte", self.byte, byte_f),
+                            (
+                                "byte_inv",
+                                self.byte_inv,
+                                byte_f.invert().unwrap_or(F::zero()),
+                            ),
+                            ("is_pad", self.is_pad, F::from(is_pad as u64)),
+                            ("is_single", self.is_single, F::from(is_single as u64)),
+                            ("len", self.len, F::from(len)),
+                            ("payload_len", self.payload_len, F::from(payload_len)),
+                            ("len_hi", self.len_hi, F::from(len_hi)),
+                            ("len_lo", self.len_lo, F::from(len_lo)),
+                            ("is_present", self.is_present, F::from(is_present as u64)),
+                            ("pow_r", self.pow_r, pow_r),
+                            ("number", self.number, F::from(number)),
+                        ] {
+                            region.assign_advice(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(value),
+                            )?;
+                        }
+                        for (name, column, value) in [
+                            ("pow_keccak", self.pow_keccak, pow_keccak),
+                            ("payload_rlc", self.payload_rlc, payload_rlc),
+                            ("payload_pow", self.payload_pow, payload_pow),
+                            ("rest_rlc", self.rest_rlc, rest_rlc),
+                            ("rest_pow", self.rest_pow, rest_pow),
+                        ] {
+                            region.assign_advice(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || value,
+                            )?;
+                        }
+                        len_is_one_chip.assign(
+                            &mut region,
+                            offset,
+                            Value::known(F::from(len) - F::one()),
+                        )?;
+
+                        let randomness_assigned = region.assign_advice(
+                            || format!("randomness {}", offset),
+                            self.randomness,
+                            offset,
+                            || Value::known(randomness),
+                        )?;
+                        if offset == 0 {
+                            randomness_cell = Some(randomness_assigned);
+                        }
+                        let value_cells = [
+                            region.assign_advice(
+                                || format!("value_num {}", offset),
+                                self.value_num,
+                                offset,
+                                || Value::known(value_num),
+                            )?,
+                            region.assign_advice(
+                                || format!("value_le {}", offset),
+                                self.value_le,
+                                offset,
+                                || Value::known(value_le),
+                            )?,
+                            region.assign_advice(
+                                || format!("value_be {}", offset),
+                                self.value_be,
+                                offset,
+                                || Value::known(value_be),
+                            )?,
+                            region.assign_advice(
+                                || format!("value_keccak {}", offset),
+                                self.value_keccak,
+                                offset,
+                                || value_keccak,
+                            )?,
+                            region.assign_advice(
+                                || format!("value_evm {}", offset),
+                                self.value_evm,
+                                offset,
+                                || value_evm,
+                            )?,
+                        ];
+                        if is_end {
+                            cells.push((field, value_cells));
+                        }
+                    }
+                }
+
+                let randomness_cell = randomness_cell.expect("the header region is not empty");
+                let field_cell = |field: HeaderField, encoding: usize| {
+                    cells
+                        .iter()
+                        .rev()
+                        .find(|(cells_field, _)| *cells_field == field)
+                        .expect("the header region contains every field")
+                        .1[encoding]
+                        .cell()
+                };
+                match links {
+                    // The parent hash of the header is the last history hash.
+                    None => region.constrain_equal(
+                        field_cell(HeaderField::ParentHash, 3),
+                        field_cell(HeaderField::HistoryHash, 3),
+                    )?,
+                    // The hash of a previous block is its history hash.
+                    Some((randomness, history_hash)) => {
+                        region.constrain_equal(randomness_cell.cell(), randomness.cell())?;
+                        region.constrain_equal(
+                            field_cell(HeaderField::BlockHash, 2),
+                            history_hash.cell(),
+                        )?;
+                    }
+                }
+
+                Ok((randomness_cell, cells))
+            },
+        )
+    }
+}
@@ -0,0 +1,919 @@
+//! Rw commitment region of the PublicInputs circuit.
+//!
+//! When a block is split in chunks, the rw operations of every chunk are
+//! committed to by a hash that is chained to the commitment of the previous
+//! chunks (see [`RwMap::commitment`](crate::witness::RwMap::commitment)):
+//!
+//! - `digest = keccak(prev_rw_commitment || rows)`, where the rows are the rows
+//!   of the rw table but the Start ones, in the order of their rw counters and
+//!   encoded as in [`Rw::to_be_bytes`].
+//! - `rw_commitment = keccak(last_row || digest)`, where the last row is the
+//!   one with the highest rw counter, or zero if there are no rows.
+//!
+//! The region proves that the rw commitment of the chunk, a public input, is
+//! the commitment to the rows of the RwTable, and it opens the previous rw
+//! commitment, also a public input, to check where the chunk starts.  It
+//! assigns one byte per row:
+//!
+//! - The last row of the previous chunks, the digest of the previous chunk and
+//!   the previous rw commitment, which are all zero for the first chunk.
+//! - A slot for every row of the RwTable but the Start ones, each one with the
+//!   fields of a row in a fixed number of rows.  The slots are right aligned:
+//!   the unused ones precede the rows, which are zero.
+//! - The digest and the rw commitment of the chunk.
+//!
+//! The rows of the slots have consecutive rw counters, so they are different,
+//! and they are the rows of the RwTable but the Start ones, and vice versa.
+//! The values of a row are encoded as numbers or as RLCs with the EVM word
+//! challenge of their little endian bytes, like in the RwTable.  Numbers have
+//! at most 20 bytes, so a row has a single encoding.
+//!
+//! A chunk that isn't the last one ends with the EndBlock reads of the tx id
+//! and the cumulative gas used of its last tx, whose rw counters are the first
+//! ones of the next chunk.  So the last row of the previous chunks is the read
+//! of the cumulative gas used of the tx before the first tx of the chunk,
+//! which the EndTx of the first tx reads again: but for the rw counter, it's a
+//! row of the RwTable.  The read of the tx id precedes it with the first rw
+//! counter of the chunk, and the next tx is the first tx of the chunk.  These
+//! values are copied to the header region, which proves them as rows of the
+//! BlockTable.
+
+use super::PublicData;
+use crate::{
+    evm_circuit::util::constraint_builder::BaseConstraintBuilder,
+    table::{KeccakTable, LookupTable, RwTable, RwTableTag, TxReceiptFieldTag},
+    util::Challenges,
+    witness::{Rw, RW_RECORD_LEN},
+};
+use eth_types::Field;
+use gadgets::util::{not, select, Expr};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase, VirtualCells,
+    },
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+const MAX_DEGREE: usize = 9;
+
+/// Number of rows of a hash
+const HASH_ROWS: usize = 32;
+
+/// Number of high bytes of a value that are zero when it's encoded as a
+/// number, which has at most 20 bytes.
+const NUMBER_HIGH_BYTES: usize = 12;
+
+/// Field of a row of the rw table, in the order of [`Rw::to_be_bytes`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RecordField {
+    RwCounter,
+    IsWrite,
+    Tag,
+    Id,
+    Address,
+    FieldTag,
+    StorageKey,
+    Value,
+    ValuePrev,
+    Committed,
+}
+
+impl RecordField {
+    const FIELDS: [Self; 10] = [
+        Self::RwCounter,
+        Self::IsWrite,
+        Self::Tag,
+        Self::Id,
+        Self::Address,
+        Self::FieldTag,
+        Self::StorageKey,
+        Self::Value,
+        Self::ValuePrev,
+        Self::Committed,
+    ];
+
+    /// Number of rows of the field
+    fn size(&self) -> usize {
+        match self {
+            Self::IsWrite | Self::Tag => 1,
+            Self::RwCounter | Self::Id | Self::FieldTag => 8,
+            Self::Address => 20,
+            Self::StorageKey | Self::Value | Self::ValuePrev | Self::Committed => 32,
+        }
+    }
+
+    /// Rotation of the last row of the field from the last row of the record.
+    fn end_rotation(&self) -> Rotation {
+        let following: usize = Self::FIELDS
+            .iter()
+            .skip_while(|field| *field != self)
+            .skip(1)
+            .map(|field| field.size())
+            .sum();
+        Rotation(-(following as i32))
+    }
+}
+
+/// Section of the region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Section {
+    /// Last row of the previous chunks
+    PrevLastRw,
+    /// Digest of the previous chunk
+    PrevDigest,
+    /// Rw commitment of the previous chunks
+    PrevCommitment,
+    /// Slot of a row of the chunk
+    Slot,
+    /// Digest of the chunk
+    Digest,
+    /// Rw commitment of the chunk
+    Commitment,
+}
+
+impl Section {
+    /// Whether the section opens the previous rw commitment
+    fn is_prev(&self) -> bool {
+        matches!(
+            self,
+            Self::PrevLastRw | Self::PrevDigest | Self::PrevCommitment
+        )
+    }
+
+    /// Whether the section is a row of the rw table
+    fn is_record(&self) -> bool {
+        matches!(self, Self::PrevLastRw | Self::Slot)
+    }
+}
+
+/// Number of rows of the rw commitment region with `num_slots` slots
+pub(crate) fn rw_commitment_region_len(num_slots: usize) -> usize {
+    (num_slots + 1) * RW_RECORD_LEN + 4 * HASH_ROWS
+}
+
+/// Cells of the rw commitment region that are linked to the rest of the
+/// circuit.
+#[derive(Clone, Debug)]
+pub(crate) struct RwCommitmentCells<F: Field> {
+    /// Rw commitment of the previous chunks, as the RLC of its big endian
+    /// bytes
+    pub(crate) prev_rw_commitment: AssignedCell<F, F>,
+    /// Rw commitment of the chunk, as the RLC of its big endian bytes
+    pub(crate) rw_commitment: AssignedCell<F, F>,
+    /// Rw counter of the first operation of the chunk, as a number
+    pub(crate) rw_counter_start: AssignedCell<F, F>,
+    /// Id of the first tx of the chunk, as a number
+    pub(crate) first_tx_id: AssignedCell<F, F>,
+}
+
+/// Config of the rw commitment region of the PublicInputs circuit
+#[derive(Clone, Debug)]
+pub(crate) struct RwCommitmentConfig<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    is_field_start: Column<Fixed>,
+    /// Rows of the sections that open the previous rw commitment
+    q_prev: Column<Fixed>,
+    /// First row of the inputs of the keccak hashes of the previous rw
+    /// commitment and of the digest
+    q_input_start: Column<Fixed>,
+    /// First row of every record and of the digest
+    is_record_start: Column<Fixed>,
+    /// Rows of the slots
+    q_slot: Column<Fixed>,
+    /// Rows of the high bytes of the values of the slots, which are zero
+    /// when they are encoded as numbers
+    q_value_high: Column<Fixed>,
+    /// Last row of the last row of the previous chunks
+    q_prev_last_rw_end: Column<Fixed>,
+    /// Last row of the digest of the previous chunk
+    q_prev_digest_end: Column<Fixed>,
+    /// Last row of every slot
+    q_slot_end: Column<Fixed>,
+    /// Last row of the digest of the chunk
+    q_digest_end: Column<Fixed>,
+    /// Last row of the rw commitment of the chunk
+    q_commitment_end: Column<Fixed>,
+
+    randomness: Column<Advice>,
+    /// Whether there are previous chunks, in all the rows
+    has_prev: Column<Advice>,
+    byte: Column<Advice>,
+    value_num: Column<Advice>,
+    pow_r: Column<Advice>,
+    value_be: Column<Advice>,
+    value_evm: Column<Advice>,
+    /// Value of the field in the encoding of the rw table
+    value_rw: Column<Advice>,
+    /// Whether the slot has a row, in all its rows
+    is_used: Column<Advice>,
+    /// Whether the values of the row of the slot are encoded as RLCs, in all
+    /// its rows
+    is_word: Column<Advice>,
+    /// Inverse of `tag * (tag - 1)` at the end of every used slot
+    tag_inv: Column<Advice>,
+    /// RLC with the keccak input challenge of the current record or hash
+    record_rlc: Column<Advice>,
+    input_rlc: Column<Advice>,
+    input_len: Column<Advice>,
+    rw_counter_start: Column<Advice>,
+    first_tx_id: Column<Advice>,
+
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> RwCommitmentConfig<F> {
+    /// Configure the rw commitment region, whose slots are the rows of
+    /// `rw_table`.  The bytes are range checked with `u8_table`.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        u8_table: Column<Fixed>,
+        rw_table: &RwTable,
+        keccak_table: &KeccakTable,
+        challenges: &Challenges<Expression<F>>,
+    ) -> Self {
+        let config = Self {
+            q_enable: meta.fixed_column(),
+            q_first: meta.fixed_column(),
+            is_field_start: meta.fixed_column(),
+            q_prev: meta.fixed_column(),
+            q_input_start: meta.fixed_column(),
+            is_record_start: meta.fixed_column(),
+            q_slot: meta.fixed_column(),
+            q_value_high: meta.fixed_column(),
+            q_prev_last_rw_end: meta.fixed_column(),
+            q_prev_digest_end: meta.fixed_column(),
+            q_slot_end: meta.fixed_column(),
+            q_digest_end: meta.fixed_column(),
+            q_commitment_end: meta.fixed_column(),
+            randomness: meta.advice_column(),
+            has_prev: meta.advice_column(),
+            byte: meta.advice_column(),
+            value_num: meta.advice_column(),
+            pow_r: meta.advice_column(),
+            value_be: meta.advice_column(),
+            value_evm: meta.advice_column_in(SecondPhase),
+            value_rw: meta.advice_column_in(SecondPhase),
+            is_used: meta.advice_column(),
+            is_word: meta.advice_column(),
+            tag_inv: meta.advice_column(),
+            record_rlc: meta.advice_column_in(SecondPhase),
+            input_rlc: meta.advice_column_in(SecondPhase),
+            input_len: meta.advice_column(),
+            rw_counter_start: meta.advice_column(),
+            first_tx_id: meta.advice_column(),
+            _marker: PhantomData,
+        };
+        let c = &config;
+        for column in [c.randomness, c.value_be, c.rw_counter_start, c.first_tx_id] {
+            meta.enable_equality(column);
+        }
+
+        let keccak_input = challenges.keccak_input();
+        let evm_word = challenges.evm_word();
+
+        meta.create_gate("rw commitment bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_first = meta.query_fixed(c.q_first, Rotation::cur());
+            let is_field_start = meta.query_fixed(c.is_field_start, Rotation::cur());
+            let is_record_start = meta.query_fixed(c.is_record_start, Rotation::cur());
+            let q_input_start = meta.query_fixed(c.q_input_start, Rotation::cur());
+            let q_prev = meta.query_fixed(c.q_prev, Rotation::cur());
+            let q_slot = meta.query_fixed(c.q_slot, Rotation::cur());
+            let byte = meta.query_advice(c.byte, Rotation::cur());
+            let randomness = meta.query_advice(c.randomness, Rotation::cur());
+            let has_prev = meta.query_advice(c.has_prev, Rotation::cur());
+            let is_used = meta.query_advice(c.is_used, Rotation::cur());
+            let is_used_prev = meta.query_advice(c.is_used, Rotation::prev());
+            let is_word = meta.query_advice(c.is_word, Rotation::cur());
+            // The encodings of a field start from zero at its first row.
+            let mut prev = |column| {
+                not::expr(is_field_start.clone()) * meta.query_advice(column, Rotation::prev())
+            };
+            let value_num_prev = prev(c.value_num);
+            let value_be_prev = prev(c.value_be);
+            let value_evm_prev = prev(c.value_evm);
+            let pow_r_prev = prev(c.pow_r);
+
+            cb.require_equal(
+                "value_num = value_num_prev * 256 + byte",
+                meta.query_advice(c.value_num, Rotation::cur()),
+                value_num_prev * 256.expr() + byte.clone(),
+            );
+            let pow_r = meta.query_advice(c.pow_r, Rotation::cur());
+            cb.require_equal(
+                "pow_r = randomness^index",
+                pow_r.clone(),
+                select::expr(is_field_start, 1.expr(), pow_r_prev * randomness.clone()),
+            );
+            cb.require_equal(
+                "value_be = value_be_prev + byte * pow_r",
+                meta.query_advice(c.value_be, Rotation::cur()),
+                value_be_prev + byte.clone() * pow_r,
+            );
+            cb.require_equal(
+                "value_evm = value_evm_prev * evm_word + byte",
+                meta.query_advice(c.value_evm, Rotation::cur()),
+                value_evm_prev * evm_word.clone() + byte.clone(),
+            );
+            cb.require_equal(
+                "record_rlc accumulates the bytes of the record",
+                meta.query_advice(c.record_rlc, Rotation::cur()),
+                not::expr(is_record_start.clone())
+                    * meta.query_advice(c.record_rlc, Rotation::prev())
+                    * keccak_input.clone()
+                    + byte.clone(),
+            );
+
+            // The input of a hash accumulates the bytes of the previous
+            // sections and of the used slots.
+            let is_input = q_prev.clone() + q_slot.clone() * is_used.clone();
+            let input_rlc_prev = meta.query_advice(c.input_rlc, Rotation::prev());
+            let input_len_prev = meta.query_advice(c.input_len, Rotation::prev());
+            cb.require_equal(
+                "input_rlc accumulates the bytes of the input",
+                meta.query_advice(c.input_rlc, Rotation::cur()),
+                select::expr(
+                    q_input_start.clone(),
+                    byte.clone(),
+                    input_rlc_prev.clone()
+                        + is_input.clone()
+                            * (input_rlc_prev * (keccak_input.clone() - 1.expr()) + byte.clone()),
+                ),
+            );
+            cb.require_equal(
+                "input_len counts the bytes of the input",
+                meta.query_advice(c.input_len, Rotation::cur()),
+                select::expr(q_input_start, 1.expr(), input_len_prev + is_input),
+            );
+
+            cb.condition(not::expr(q_first), |cb| {
+                cb.require_equal(
+                    "randomness is the same in all rows",
+                    randomness,
+                    meta.query_advice(c.randomness, Rotation::prev()),
+                );
+                cb.require_equal(
+                    "has_prev is the same in all rows",
+                    has_prev.clone(),
+                    meta.query_advice(c.has_prev, Rotation::prev()),
+                );
+            });
+            cb.require_boolean("has_prev is boolean", has_prev.clone());
+            cb.condition(q_prev, |cb| {
+                cb.require_zero(
+                    "the previous rw commitment is zero for the first chunk",
+                    not::expr(has_prev) * byte.clone(),
+                );
+            });
+
+            cb.require_boolean("is_used is boolean", is_used.clone());
+            cb.require_boolean("is_word is boolean", is_word.clone());
+            cb.condition(not::expr(q_slot.clone()), |cb| {
+                cb.require_zero("is_used is zero out of the slots", is_used.clone());
+                cb.require_zero("is_word is zero out of the slots", is_word.clone());
+            });
+            cb.condition(q_slot.clone() * not::expr(is_record_start.clone()), |cb| {
+                cb.require_equal(
+                    "is_used is the same in all the rows of a slot",
+                    is_used.clone(),
+                    is_used_prev.clone(),
+                );
+                cb.require_equal(
+                    "is_word is the same in all the rows of a slot",
+                    is_word.clone(),
+                    meta.query_advice(c.is_word, Rotation::prev()),
+                );
+            });
+            cb.condition(q_slot.clone() * is_record_start, |cb| {
+                cb.require_zero(
+                    "unused slots precede the used ones",
+                    is_used_prev * not::expr(is_used.clone()),
+                );
+            });
+            cb.condition(q_slot.clone(), |cb| {
+                cb.require_zero("unused slots are zero", not::expr(is_used) * byte.clone());
+                let value_num = meta.query_advice(c.value_num, Rotation::cur());
+                cb.require_equal(
+                    "value_rw is the value in the encoding of the rw table",
+                    meta.query_advice(c.value_rw, Rotation::cur()),
+                    value_num.clone()
+                        + is_word.clone()
+                            * (meta.query_advice(c.value_evm, Rotation::cur()) - value_num),
+                );
+            });
+            cb.condition(meta.query_fixed(c.q_value_high, Rotation::cur()), |cb| {
+                cb.require_zero(
+                    "values encoded as numbers have at most 20 bytes",
+                    not::expr(is_word) * byte,
+                );
+            });
+
+            cb.gate(meta.query_fixed(c.q_enable, Rotation::cur()))
+        });
+
+        let record_value = |meta: &mut VirtualCells<'_, F>, field: RecordField, offset: i32| {
+            meta.query_advice(c.value_num, Rotation(field.end_rotation().0 + offset))
+        };
+
+        meta.create_gate("rw commitment slots", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_used = meta.query_advice(c.is_used, Rotation::cur());
+            let is_used_prev = meta.query_advice(c.is_used, Rotation(-(RW_RECORD_LEN as i32)));
+            let tag = record_value(meta, RecordField::Tag, 0);
+            cb.require_equal(
+                "the rows of the slots are not Start rows",
+                is_used.clone()
+                    * tag.clone()
+                    * (tag - 1.expr())
+                    * meta.query_advice(c.tag_inv, Rotation::cur()),
+                is_used.clone(),
+            );
+            cb.require_zero(
+                "the rows of the slots have consecutive rw counters",
+                is_used
+                    * is_used_prev
+                    * (record_value(meta, RecordField::RwCounter, 0)
+                        - record_value(meta, RecordField::RwCounter, -(RW_RECORD_LEN as i32))
+                        - 1.expr()),
+            );
+
+            cb.gate(meta.query_fixed(c.q_slot_end, Rotation::cur()))
+        });
+
+        meta.create_gate("rw commitment start of the chunk", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let has_prev = meta.query_advice(c.has_prev, Rotation::cur());
+            cb.condition(has_prev.clone(), |cb| {
+                cb.require_equal(
+                    "the last row of the previous chunks is a TxReceipt row",
+                    record_value(meta, RecordField::Tag, 0),
+                    RwTableTag::TxReceipt.expr(),
+                );
+                cb.require_equal(
+                    "the last row of the previous chunks is a cumulative gas used",
+                    record_value(meta, RecordField::FieldTag, 0),
+                    TxReceiptFieldTag::CumulativeGasUsed.expr(),
+                );
+                cb.require_zero(
+                    "the last row of the previous chunks is a read",
+                    record_value(meta, RecordField::IsWrite, 0),
+                );
+            });
+            // The EndBlock read of the tx id that precedes the last row of the
+            // previous chunks has the first rw counter of the chunk.
+            cb.require_equal(
+                "rw_counter_start = rw_counter - 1, or 1 for the first chunk",
+                meta.query_advice(c.rw_counter_start, Rotation::cur()),
+                record_value(meta, RecordField::RwCounter, 0) + 1.expr() - 2.expr() * has_prev,
+            );
+            cb.require_equal(
+                "first_tx_id = tx_id + 1",
+                meta.query_advice(c.first_tx_id, Rotation::cur()),
+                record_value(meta, RecordField::Id, 0) + 1.expr(),
+            );
+
+            cb.gate(meta.query_fixed(c.q_prev_last_rw_end, Rotation::cur()))
+        });
+
+        meta.lookup_any("rw commitment bytes are in u8 range", |meta| {
+            vec![(
+                meta.query_fixed(c.q_enable, Rotation::cur())
+                    * meta.query_advice(c.byte, Rotation::cur()),
+                meta.query_fixed(u8_table, Rotation::cur()),
+            )]
+        });
+
+        let keccak_lookup = |meta: &mut VirtualCells<'_, F>,
+                             enable: Expression<F>,
+                             input_rlc: Expression<F>,
+                             input_len: Expression<F>,
+                             output_rlc: Expression<F>| {
+            [1.expr(), input_rlc, input_len, output_rlc]
+                .into_iter()
+                .zip(keccak_table.table_exprs(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect::<Vec<_>>()
+        };
+        let opening_len = (RW_RECORD_LEN + HASH_ROWS).expr();
+        meta.lookup_any(
+            "keccak256(prev_last_rw || prev_digest) = prev_rw_commitment",
+            |meta| {
+                let enable = meta.query_fixed(c.q_prev_digest_end, Rotation::cur())
+                    * meta.query_advice(c.has_prev, Rotation::cur());
+                let input_rlc = meta.query_advice(c.input_rlc, Rotation::cur());
+                let output_rlc = meta.query_advice(c.value_evm, Rotation(HASH_ROWS as i32));
+                keccak_lookup(meta, enable, input_rlc, opening_len.clone(), output_rlc)
+            },
+        );
+        meta.lookup_any("keccak256(prev_rw_commitment || rows) = digest", |meta| {
+            let enable = meta.query_fixed(c.q_digest_end, Rotation::cur());
+            let input_rlc = meta.query_advice(c.input_rlc, Rotation::cur());
+            let input_len = meta.query_advice(c.input_len, Rotation::cur());
+            let output_rlc = meta.query_advice(c.value_evm, Rotation::cur());
+            keccak_lookup(meta, enable, input_rlc, input_len, output_rlc)
+        });
+        meta.lookup_any("keccak256(last_row || digest) = rw_commitment", |meta| {
+            // The last slot ends before the digest of the chunk, whose RLC is
+            // the record RLC at its last row.
+            let enable = meta.query_fixed(c.q_commitment_end, Rotation::cur());
+            let last_row_rlc = meta.query_advice(c.record_rlc, Rotation(-2 * HASH_ROWS as i32));
+            let digest_rlc = meta.query_advice(c.record_rlc, Rotation(-(HASH_ROWS as i32)));
+            let keccak_input_pow =
+                (0..HASH_ROWS).fold(1.expr(), |acc: Expression<F>, _| acc * keccak_input.clone());
+            let input_rlc = last_row_rlc * keccak_input_pow + digest_rlc;
+            let output_rlc = meta.query_advice(c.value_evm, Rotation::cur());
+            keccak_lookup(meta, enable, input_rlc, opening_len.clone(), output_rlc)
+        });
+
+        // The rows of the used slots in the layout of the RwTable, preceded by
+        // a 1, which are all zero in the rest of the rows.
+        let slot_row = |meta: &mut VirtualCells<'_, F>| {
+            let enable = meta.query_fixed(c.q_slot_end, Rotation::cur())
+                * meta.query_advice(c.is_used, Rotation::cur());
+            let num = |meta: &mut VirtualCells<'_, F>, field: RecordField| {
+                meta.query_advice(c.value_num, field.end_rotation())
+            };
+            let evm = |meta: &mut VirtualCells<'_, F>, field: RecordField| {
+                meta.query_advice(c.value_evm, field.end_rotation())
+            };
+            let rw = |meta: &mut VirtualCells<'_, F>, field: RecordField| {
+                meta.query_advice(c.value_rw, field.end_rotation())
+            };
+            vec![
+                1.expr(),
+                num(meta, RecordField::RwCounter),
+                num(meta, RecordField::IsWrite),
+                num(meta, RecordField::Tag),
+                num(meta, RecordField::Id),
+                num(meta, RecordField::Address),
+                num(meta, RecordField::FieldTag),
+                evm(meta, RecordField::StorageKey),
+                rw(meta, RecordField::Value),
+                rw(meta, RecordField::ValuePrev),
+                // aux1 is unused
+                0.expr(),
+                evm(meta, RecordField::Committed),
+            ]
+            .into_iter()
+            .map(|expr| enable.clone() * expr)
+            .collect::<Vec<_>>()
+        };
+        // The rows of the RwTable preceded by a 1, scaled by `tag * (tag - 1)`
+        // so that the Start rows and the unused rows are all zero.
+        let table_row = |meta: &mut VirtualCells<'_, F>| {
+            let tag = meta.query_advice(rw_table.tag, Rotation::cur());
+            let factor = tag.clone() * (tag - 1.expr());
+            std::iter::once(1.expr())
+                .chain(rw_table.table_exprs(meta))
+                .map(|expr| factor.clone() * expr)
+                .collect::<Vec<_>>()
+        };
+        meta.lookup_any("the rows of the slots are rows of the RwTable", |meta| {
+            slot_row(meta).into_iter().zip(table_row(meta)).collect()
+        });
+        meta.lookup_any("the rows of the RwTable are rows of the slots", |meta| {
+            table_row(meta).into_iter().zip(slot_row(meta)).collect()
+        });
+
+        meta.lookup_any(
+            "the last row of the previous chunks is a row of the RwTable",
+            |meta| {
+                // It's a TxReceipt row, whose values are numbers, that the
+                // chunk reads again with another rw counter.
+                let enable = meta.query_fixed(c.q_prev_last_rw_end, Rotation::cur())
+                    * meta.query_advice(c.has_prev, Rotation::cur());
+                let tag = meta.query_advice(rw_table.tag, Rotation::cur());
+                let factor = tag.clone() * (tag - 1.expr());
+                std::iter::once((enable.clone(), factor.clone()))
+                    .chain(
+                        [
+                            (RecordField::IsWrite, c.value_num, rw_table.is_write),
+                            (RecordField::Tag, c.value_num, rw_table.tag),
+                            (RecordField::Id, c.value_num, rw_table.id),
+                            (RecordField::Address, c.value_num, rw_table.address),
+                            (RecordField::FieldTag, c.value_num, rw_table.field_tag),
+                            (RecordField::StorageKey, c.value_evm, rw_table.storage_key),
+                            (RecordField::Value, c.value_num, rw_table.value),
+                        ]
+                        .map(|(field, value, column)| {
+                            (
+                                enable.clone() * meta.query_advice(value, field.end_rotation()),
+                                factor.clone() * meta.query_advice(column, Rotation::cur()),
+                            )
+                        }),
+                    )
+                    .collect()
+            },
+        );
+
+        config
+    }
+
+    /// Assign the rw commitment region for the rows of the chunk of
+    /// `public_data` in `num_slots` slots, and copy the randomness to
+    /// `randomness_cell`.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        public_data: &PublicData,
+        num_slots: usize,
+        randomness: F,
+        randomness_cell: &AssignedCell<F, F>,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<RwCommitmentCells<F>, Error> {
+        let rws = &public_data.rws;
+        assert!(
+            rws.len() <= num_slots,
+            "the chunk has {} rows, more than {} slots",
+            rws.len(),
+            num_slots
+        );
+        let record =
+            |rw: Option<&Rw>| rw.map_or_else(|| vec![0; RW_RECORD_LEN], |rw| rw.to_be_bytes());
+        let has_prev = public_data.prev_last_rw.is_some();
+        let mut sections: Vec<(Section, Option<&Rw>, Vec<u8>)> = vec![
+            (
+                Section::PrevLastRw,
+                public_data.prev_last_rw.as_ref(),
+                record(public_data.prev_last_rw.as_ref()),
+            ),
+            (
+                Section::PrevDigest,
+                None,
+                if has_prev {
+                    public_data.prev_rw_digest.as_bytes().to_vec()
+                } else {
+                    vec![0; HASH_ROWS]
+                },
+            ),
+            (
+                Section::PrevCommitment,
+                None,
+                public_data.prev_rw_commitment().as_bytes().to_vec(),
+            ),
+        ];
+        sections.extend((rws.len()..num_slots).map(|_| (Section::Slot, None, record(None))));
+        sections.extend(
+            rws.iter()
+                .map(|rw| (Section::Slot, Some(rw), record(Some(rw)))),
+        );
+        sections.extend([
+            (
+                Section::Digest,
+                None,
+                public_data.rw_digest().as_bytes().to_vec(),
+            ),
+            (
+                Section::Commitment,
+                None,
+                public_data.rw_commitment().as_bytes().to_vec(),
+            ),
+        ]);
+
+        let keccak_input = challenges.keccak_input();
+        let evm_word = challenges.evm_word();
+
+        layouter.assign_region(
+            || "pi rw commitment",
+            |mut region| {
+                let mut offset = 0;
+                let mut record_rlc = Value::known(F::zero());
+                let mut input_rlc = Value::known(F::zero());
+                let mut input_len = 0u64;
+                let mut prev_rw_commitment = None;
+                let mut rw_commitment = None;
+                let mut rw_counter_start = None;
+                let mut first_tx_id = None;
+
+                for (section, rw, bytes) in sections.iter() {
+                    let sizes: Vec<usize> = if section.is_record() {
+                        RecordField::FIELDS
+                            .iter()
+                            .map(|field| field.size())
+                            .collect()
+                    } else {
+                        vec![HASH_ROWS]
+                    };
+                    let is_used = *section == Section::Slot && rw.is_some();
+                    let is_word = *section == Section::Slot && rw.map_or(false, Rw::is_word_value);
+                    let is_input = section.is_prev() || is_used;
+
+                    let mut bytes = bytes.iter();
+                    let mut index_in_section = 0;
+                    for (field_index, size) in sizes.iter().enumerate() {
+                        let field = section
+                            .is_record()
+                            .then(|| RecordField::FIELDS[field_index]);
+                        let mut value_num = F::zero();
+                        let mut pow_r = F::one();
+                        let mut value_be = F::zero();
+                        let mut value_evm = Value::known(F::zero());
+
+                        for index in 0..*size {
+                            let byte = *bytes.next().expect("a byte for every row of a section");
+                            let byte_f = F::from(byte as u64);
+                            let is_field_start = index == 0;
+                            let is_section_start = index_in_section == 0;
+                            let is_section_end = bytes.as_slice().is_empty();
+                            let is_input_start = is_section_start
+                                && matches!(section, Section::PrevLastRw | Section::PrevCommitment);
+                            let is_record_start = is_section_start
+                                && matches!(
+                                    section,
+                                    Section::PrevLastRw | Section::Slot | Section::Digest
+                                );
+                            let is_value_high = *section == Section::Slot
+                                && matches!(
+                                    field,
+                                    Some(RecordField::Value | RecordField::ValuePrev)
+                                )
+                                && index < NUMBER_HIGH_BYTES;
+
+                            if !is_field_start {
+                                pow_r *= randomness;
+                            }
+                            value_num = value_num * F::from(256) + byte_f;
+                            value_be += byte_f * pow_r;
+                            value_evm = value_evm * evm_word + Value::known(byte_f);
+                            record_rlc = if is_record_start {
+                                Value::known(byte_f)
+                            } else {
+                                record_rlc * keccak_input + Value::known(byte_f)
+                            };
+                            if is_input_start {
+                                input_rlc = Value::known(byte_f);
+                                input_len = 1;
+                            } else if is_input {
+                                input_rlc = input_rlc * keccak_input + Value::known(byte_f);
+                                input_len += 1;
+                            }
+                            let value_rw = if is_word {
+                                value_evm
+                            } else {
+                                Value::known(value_num)
+                            };
+                            let tag_inv = match (is_section_end, is_used, rw) {
+                                (true, true, Some(rw)) => {
+                                    let tag = F::from(rw.tag() as u64);
+                                    (tag * (tag - F::one())).invert().unwrap_or(F::zero())
+                                }
+                                _ => F::zero(),
+                            };
+
+                            for (name, column, value) in [
+                                ("q_enable", self.q_enable, true),
+                                ("q_first", self.q_first, offset == 0),
+                                ("is_field_start", self.is_field_start, is_field_start),
+                                ("q_prev", self.q_prev, section.is_prev()),
+                                ("q_input_start", self.q_input_start, is_input_start),
+                                ("is_record_start", self.is_record_start, is_record_start),
+                                ("q_slot", self.q_slot, *section == Section::Slot),
+                                ("q_value_high", self.q_value_high, is_value_high),
+                                (
+                                    "q_prev_last_rw_end",
+                                    self.q_prev_last_rw_end,
+                                    *section == Section::PrevLastRw && is_section_end,
+                                ),
+                                (
+                                    "q_prev_digest_end",
+                                    self.q_prev_digest_end,
+                                    *section == Section::PrevDigest && is_section_end,
+                                ),
+                                (
+                                    "q_slot_end",
+                                    self.q_slot_end,
+                                    *section == Section::Slot && is_section_end,
+                                ),
+                                (
+                                    "q_digest_end",
+                                    self.q_digest_end,
+                                    *section == Section::Digest && is_section_end,
+                                ),
+                                (
+                                    "q_commitment_end",
+                                    self.q_commitment_end,
+                                    *section == Section::Commitment && is_section_end,
+                                ),
+                            ] {
+                                region.assign_fixed(
+                                    || format!("{} {}", name, offset),
+                                    column,
+                                    offset,
+                                    || Value::known(F::from(value as u64)),
+                                )?;
+                            }
+                            // The first rw counter and tx id of the chunk are
+                            // proved at the end of the last row of the
+                            // previous chunks.
+                            let (rw_counter_start_value, first_tx_id_value) =
+                                if *section == Section::PrevLastRw && is_section_end {
+                                    (public_data.rw_counter_start(), public_data.first_tx_id())
+                                } else {
+                                    (0, 0)
+                                };
+
+                            let randomness_assigned = region.assign_advice(
+                                || format!("randomness {}", offset),
+                                self.randomness,
+                                offset,
+                                || Value::known(randomness),
+                            )?;
+                            if offset == 0 {
+                                region.constrain_equal(
+                                    randomness_assigned.cell(),
+                                    randomness_cell.cell(),
+                                )?;
+                            }
+                            for (name, column, value) in [
+                                ("has_prev", self.has_prev, F::from(has_prev as u64)),
+                                ("byte", self.byte, byte_f),
+                                ("value_num", self.value_num, value_num),
+                                ("pow_r", self.pow_r, pow_r),
+                                ("is_used", self.is_used, F::from(is_used as u64)),
+                                ("is_word", self.is_word, F::from(is_word as u64)),
+                                ("tag_inv", self.tag_inv, tag_inv),
+                                ("input_len", self.input_len, F::from(input_len)),
+                            ] {
+                                region.assign_advice(
+                                    || format!("{} {}", name, offset),
+                                    column,
+                                    offset,
+                                    || Value::known(value),
+                                )?;
+                            }
+                            for (name, column, value) in [
+                                ("value_evm", self.value_evm, value_evm),
+                                ("value_rw", self.value_rw, value_rw),
+                                ("record_rlc", self.record_rlc, record_rlc),
+                                ("input_rlc", self.input_rlc, input_rlc),
+                            ] {
+                                region.assign_advice(
+                                    || format!("{} {}", name, offset),
+                                    column,
+                                    offset,
+                                    || value,
+                                )?;
+                            }
+                            let value_be_cell = region.assign_advice(
+                                || format!("value_be {}", offset),
+                                self.value_be,
+                                offset,
+                                || Value::known(value_be),
+                            )?;
+                            let [rw_counter_start_cell, first_tx_id_cell] = [
+                                (
+                                    "rw_counter_start",
+                                    self.rw_counter_start,
+                                    rw_counter_start_value,
+                                ),
+                                ("first_tx_id", self.first_tx_id, first_tx_id_value),
+                            ]
+                            .map(|(name, column, value)| {
+                                region.assign_advice(
+                                    || format!("{} {}", name, offset),
+                                    column,
+                                    offset,
+                                    || Value::known(F::from(value as u64)),
+                                )
+                            });
+                            let (rw_counter_start_cell, first_tx_id_cell) =
+                                (rw_counter_start_cell?, first_tx_id_cell?);
+
+                            if is_section_end {
+                                match section {
+                                    Section::PrevLastRw => {
+                                        rw_counter_start = Some(rw_counter_start_cell);
+                                        first_tx_id = Some(first_tx_id_cell);
+                                    }
+                                    Section::PrevCommitment => {
+                                        prev_rw_commitment = Some(value_be_cell)
+                                    }
+                                    Section::Commitment => rw_commitment = Some(value_be_cell),
+                                    _ => (),
+                                }
+                            }
+                            index_in_section += 1;
+                            offset += 1;
+                        }
+                    }
+                }
+
+                Ok(RwCommitmentCells {
+                    prev_rw_commitment: prev_rw_commitment
+                        .expect("the region opens the previous rw commitment"),
+                    rw_commitment: rw_commitment.expect("the region ends with the rw commitment"),
+                    rw_counter_start: rw_counter_start
+                        .expect("the region starts with the last row of the previous chunks"),
+                    first_tx_id: first_tx_id
+                        .expect("the region starts with the last row of the previous chunks"),
+                })
+            },
+        )
+    }
+}
@@ -0,0 +1,2102 @@
+//! Tx list region of the PublicInputs circuit.
+//!
+//! On L2, the proposer of a block posts the RLP encoded list of its
+//! transactions, the tx list, as calldata, and the keccak hash of the tx list
+//! is a public input of the circuit.  The entries of the tx list are encoded
+//! like in the network: legacy transactions are the RLP list of their fields,
+//! and typed transactions are an RLP string with their type and fields.  The
+//! transactions of the block are the valid entries, in order: an entry is
+//! valid if it's the encoding of a signed transaction of the chain, if less
+//! than `max_txs` entries before it are valid, and if its call data fits in
+//! `max_calldata` with the one of the previous valid entries (see
+//! [`decode_tx_list`]).  The other entries are skipped instead of invalidating
+//! the block.  The circuit decides the validity of every entry from its bytes,
+//! so the prover can't skip a valid entry.  An entry is the encoding of a
+//! signed transaction if:
+//!
+//! - It's a list (legacy tx), or a string with the type of an EIP-2930 or
+//!   EIP-1559 tx followed by a list that fills it, and the list has the fields
+//!   of a tx of the type.
+//! - The headers of the entry, of its list and of the fields are canonical, the
+//!   integers don't have leading zeros and fit in 8 or 32 bytes, `to` is empty
+//!   or an address, and the access list is a list.
+//! - The chain id of a typed tx is the one of the chain, and `v` of a legacy tx
+//!   encodes it (EIP-155).  `y_parity` is 0 or 1, and `r` and `s` are in `[1,
+//!   n)`, where `n` is the order of secp256k1.
+//!
+//! The circuit doesn't verify that the signature recovers a public key, or the
+//! items of the access list: a tx list with a valid entry whose signature
+//! doesn't recover a sender, or whose access list isn't a list of addresses
+//! and storage keys, can't be proven.
+//!
+//! The region assigns 32 hash rows with the bytes of the hash, followed by one
+//! row per byte of the tx list, which is decoded with the decoder of
+//! [`crate::util::rlp`]: the header of the list, the header of every entry,
+//! and the items in the entries.  The string of a typed tx is decoded as its
+//! type followed by its list, which is a container, so the fields of all the
+//! txs are items of their entry.  The field of every item is looked up in a
+//! fixed table with the type of the tx and the number of fields left, and the
+//! bytes are compared with constants in another fixed table, which bounds the
+//! headers and compares `r` and `s` with `n` byte by byte.  The rules broken by
+//! an entry are counted in `num_bad`, and at the last byte of the entry, the
+//! entry is valid if it broke none, if the number of previous valid entries is
+//! less than `max_txs`, and if the call data of the valid entries up to it fits
+//! in `max_calldata`.  The encoding of the signed transaction of each valid
+//! entry, which is the whole entry for legacy transactions and its payload for
+//! typed transactions, is accumulated in `value_rlc`.
+//!
+//! The valid entries are numbered from 1, and each of them is the value of the
+//! path of the same index in the transactions trie, and vice versa.  The
+//! values of the trie are the signed encodings of the txs of the block in the
+//! RlpTable, where the RLP circuit decodes them and looks up their fields in
+//! the TxTable, so the valid entries are the txs of the TxTable.
+
+use crate::{
+    evm_circuit::util::constraint_builder::BaseConstraintBuilder,
+    table::{KeccakTable, LookupTable},
+    util::{
+        rlp::{RlpByteClass, RlpByteTable, RlpDecoderConfig, RlpDecoderRow},
+        trie::ListTrieConfig,
+        Challenges,
+    },
+};
+use eth_types::{geth_types, Field, Transaction};
+use ethers_core::utils::rlp::{DecoderError, Rlp, RlpStream};
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    less_than::{LtChip, LtConfig, LtInstruction},
+    util::{and, not, select, sum, Expr},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase, VirtualCells,
+    },
+    poly::Rotation,
+};
+use log::error;
+use std::{array, marker::PhantomData};
+
+const MAX_DEGREE: usize = 9;
+
+/// Number of rows of the hash at the beginning of the region
+const HASH_ROWS: usize = 32;
+
+/// Maximum number of bytes of an entry of the tx list without its call data
+/// and access list, which is the one of an EIP-1559 transaction: the string
+/// header (5), the type byte (1), the list header (5), chain_id (9), nonce
+/// (9), max_priority_fee_per_gas (33), max_fee_per_gas (33), gas (9), to (21),
+/// value (33), call data header (5), access list header (5), y_parity (1), r
+/// (33) and s (33).
+pub const TX_LIST_ENTRY_MAX_FIXED_BYTES: usize = 235;
+
+/// Order of the group of secp256k1, big endian
+const SECP256K1_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+// Keys of the comparison table.  The keys 0 to 31 compare a byte with the
+// byte of the same index of `SECP256K1_ORDER`.
+/// Compares the first byte of a string with the header of 32 bytes
+const CMP_WORD_HEADER: u64 = 32;
+/// The first byte of a string of up to 8 bytes is lower
+const CMP_U64_HEADER: u64 = 33;
+/// The first byte of a string of up to 32 bytes is lower
+const CMP_U256_HEADER: u64 = 34;
+/// The first byte of an address is equal
+const CMP_ADDRESS_HEADER: u64 = 35;
+/// The first length byte of a long item is lower if its length is below 56
+const CMP_SHORT_LENGTH: u64 = 36;
+/// The supported tx types are lower
+const CMP_TX_TYPE: u64 = 37;
+/// Number of keys of the comparison table
+const CMP_KEYS: u64 = 38;
+
+/// Constant the bytes are compared with for the key `key` of the comparison
+/// table
+fn cmp_constant(key: u64) -> u8 {
+    match key {
+        0..=31 => SECP256K1_ORDER[key as usize],
+        CMP_WORD_HEADER => 0xa0,
+        CMP_U64_HEADER => 0x89,
+        CMP_U256_HEADER => 0xa1,
+        CMP_ADDRESS_HEADER => 0x94,
+        CMP_SHORT_LENGTH => 0x38,
+        CMP_TX_TYPE => 0x03,
+        _ => unreachable!("invalid comparison key {}", key),
+    }
+}
+
+/// Maximum number of bytes of a tx list with `max_txs` transactions and
+/// `max_calldata` bytes of call data: the list header and the entries.
+pub fn tx_list_max_len(max_txs: usize, max_calldata: usize) -> usize {
+    5 + max_txs * TX_LIST_ENTRY_MAX_FIXED_BYTES + max_calldata
+}
+
+/// Number of rows of the tx list region
+pub(crate) fn tx_list_region_len(tx_list_len: usize) -> usize {
+    // The region has at least one padding row, and contains the comparison
+    // table followed by a row of zeros for its disabled lookups.
+    (HASH_ROWS + tx_list_len + 1).max(CMP_KEYS as usize * 256 + 1)
+}
+
+/// Encode the signed transactions `signed_txs` as a tx list.
+pub fn encode_tx_list(signed_txs: &[Vec<u8>]) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(signed_txs.len());
+    for signed_tx in signed_txs {
+        if Rlp::new(signed_tx).is_list() {
+            // Legacy tx
+            stream.append_raw(signed_tx, 1);
+        } else {
+            // Typed tx
+            stream.append(signed_tx);
+        }
+    }
+    stream.out().to_vec()
+}
+
+/// Decode the transactions of the tx list `tx_list` of a block of the chain
+/// `chain_id`.  An entry of the list is a transaction of the block if it's the
+/// canonical encoding of a signed legacy, EIP-2930 or EIP-1559 transaction of
+/// the chain, with `r` and `s` in the range of secp256k1, if the number of
+/// previous transactions is less than `max_txs`, and if its call data fits in
+/// `max_calldata` with the one of the previous transactions.  The other
+/// entries are skipped.  Returns an error if the tx list is not an RLP list of
+/// entries, or if the signature of a transaction of the block doesn't recover
+/// its sender, or if its access list is invalid, since the circuit can't
+/// prove such a tx list.
+pub fn decode_tx_list(
+    tx_list: &[u8],
+    chain_id: u64,
+    max_txs: usize,
+    max_calldata: usize,
+) -> Result<Vec<Transaction>, DecoderError> {
+    tx_list_rows(tx_list, chain_id, max_txs, max_calldata).map(|(_, txs)| txs)
+}
+
+/// Decode the header of the RLP item at the beginning of `bytes`, and return
+/// the class of its first byte, and the length of its header and of its
+/// payload.  Like in the circuit, the encoding of the length is not required
+/// to be canonical.
+fn rlp_header(bytes: &[u8]) -> Result<(RlpByteClass, usize, usize), DecoderError> {
+    let first = *bytes.first().ok_or(DecoderError::RlpIsTooShort)?;
+    let class = RlpByteClass::from(first);
+    let (header_len, payload_len) = match class {
+        RlpByteClass::Single => (0, 1),
+        RlpByteClass::ShortString => (1, (first - 0x80) as usize),
+        RlpByteClass::ShortList => (1, (first - 0xc0) as usize),
+        RlpByteClass::LongString | RlpByteClass::LongList => {
+            let len_of_len = if class == RlpByteClass::LongString {
+                first - 0xb7
+            } else {
+                first - 0xf7
+            } as usize;
+            let len_bytes = bytes
+                .get(1..1 + len_of_len)
+                .ok_or(DecoderError::RlpIsTooShort)?;
+            let payload_len = len_bytes
+                .iter()
+                .try_fold(0usize, |acc, byte| {
+                    acc.checked_mul(256)?.checked_add(*byte as usize)
+                })
+                .ok_or(DecoderError::RlpIsTooBig)?;
+            (1 + len_of_len, payload_len)
+        }
+    };
+    if header_len
+        .checked_add(payload_len)
+        .map_or(true, |len| len > bytes.len())
+    {
+        return Err(DecoderError::RlpIsTooShort);
+    }
+    Ok((class, header_len, payload_len))
+}
+
+/// Decode the signed transaction `value` of a valid entry.
+fn decode_signed_tx(value: &[u8], chain_id: u64) -> Option<Transaction> {
+    let mut tx: Transaction = Rlp::new(value).as_val().ok()?;
+    tx.recover_from_mut().ok()?;
+    let geth_tx = geth_types::Transaction::from(&tx);
+    // Only the canonical encoding of a tx of the chain is the value of its
+    // path in the transactions trie.
+    if tx.chain_id != Some(chain_id.into())
+        || geth_tx.signed_rlp(chain_id) != value
+        || geth_tx.sign_data(chain_id).is_err()
+    {
+        return None;
+    }
+    tx.hash = ethers_core::utils::keccak256(value).into();
+    Some(tx)
+}
+
+/// Fields of a signed transaction, by how the circuit checks their encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TxField {
+    /// Integer of up to 8 bytes: nonce and gas
+    U64,
+    /// Integer of up to 32 bytes: gas price, fees and value
+    U256,
+    /// Chain id of a typed tx
+    ChainId,
+    /// Empty, or an address
+    To,
+    /// Call data
+    Data,
+    /// Access list of a typed tx, which is a list
+    AccessList,
+    /// `v` of a legacy tx, which encodes the chain id (EIP-155)
+    V,
+    /// `y_parity` of a typed tx
+    YParity,
+    /// `r` or `s`
+    Signature,
+}
+
+impl TxField {
+    /// Fields of the txs of type `tx_type`, which is 0 for legacy txs
+    fn fields(tx_type: u64) -> &'static [Self] {
+        use TxField::*;
+        match tx_type {
+            0 => &[U64, U256, U64, To, U256, Data, V, Signature, Signature],
+            1 => &[
+                ChainId, U64, U256, U64, To, U256, Data, AccessList, YParity, Signature, Signature,
+            ],
+            2 => &[
+                ChainId, U64, U256, U256, U64, To, U256, Data, AccessList, YParity, Signature,
+                Signature,
+            ],
+            _ => &[],
+        }
+    }
+
+    /// Key of the comparison of the first byte of the field, which bounds the
+    /// length of integers and addresses
+    fn cmp_key(self) -> u64 {
+        match self {
+            Self::U64 | Self::ChainId | Self::V | Self::YParity => CMP_U64_HEADER,
+            Self::U256 => CMP_U256_HEADER,
+            Self::Signature => CMP_WORD_HEADER,
+            Self::To => CMP_ADDRESS_HEADER,
+            Self::Data | Self::AccessList => 0,
+        }
+    }
+
+    /// Flags of the field, in the order of [`TxListConfig::field_flags`]
+    fn flags(self) -> [bool; 8] {
+        [
+            !matches!(self, Self::To | Self::Data | Self::AccessList),
+            self == Self::To,
+            self == Self::Data,
+            self == Self::AccessList,
+            self == Self::ChainId,
+            self == Self::V,
+            self == Self::YParity,
+            self == Self::Signature,
+        ]
+    }
+}
+
+/// Values of a row of the tx list, as decoded by the circuit
+#[derive(Clone, Copy, Debug, Default)]
+struct TxListRow {
+    rlp: RlpDecoderRow,
+    is_list_header: bool,
+    remaining: u64,
+    is_entry_start: bool,
+    is_entry_item: bool,
+    is_entry_end: bool,
+    entry_remaining: u64,
+    is_list_entry: bool,
+    is_string_entry: bool,
+    is_type_item: bool,
+    type_ok: bool,
+    tx_type: u64,
+    is_tx_header: bool,
+    in_tx: bool,
+    is_first_payload: bool,
+    is_first_data: bool,
+    is_len_start: bool,
+    is_field_start: bool,
+    is_field_end: bool,
+    fields_left: u64,
+    field: Option<TxField>,
+    cmp_key: u64,
+    cmp_lt: bool,
+    cmp_eq: bool,
+    size_ok: bool,
+    sig_lt: bool,
+    sig_eq: bool,
+    /// Value of the integer up to the row, exact for up to 8 bytes
+    value: u64,
+    num_bad: u64,
+    data_len: u64,
+    calldata_sum: u64,
+    is_valid: bool,
+    tx_id: u64,
+}
+
+impl TxListRow {
+    /// Row of `byte`, which is the first byte of the tx list if `is_first`,
+    /// or follows the byte of `prev`.  Its validity is decided at the end of
+    /// the entry.
+    fn new(byte: u8, is_first: bool, prev: &Self, chain_id: u64) -> Self {
+        let class = RlpByteClass::from(byte);
+        let is_list = matches!(class, RlpByteClass::ShortList | RlpByteClass::LongList);
+        let is_list_header = is_first || (prev.is_list_header && !prev.rlp.is_item_end);
+        let is_item_start = is_first || prev.is_entry_end || prev.rlp.is_item_end;
+        let is_entry_start =
+            is_item_start && !is_list_header && (prev.is_list_header || prev.is_entry_end);
+        let entry_flag = |start, kept| if is_entry_start { start } else { kept };
+        let is_list_entry = entry_flag(is_list, prev.is_list_entry);
+        let is_string_entry = entry_flag(
+            matches!(class, RlpByteClass::ShortString | RlpByteClass::LongString),
+            prev.is_string_entry,
+        );
+        let is_entry_item = if is_item_start {
+            is_entry_start
+        } else {
+            prev.is_entry_item
+        };
+
+        // Typed txs: the type, followed by the list of the tx
+        let is_type_item = if is_item_start {
+            !is_entry_start && is_string_entry && prev.is_entry_item && prev.rlp.is_item_end
+        } else {
+            prev.is_type_item
+        };
+        let is_type_start = is_type_item && is_item_start;
+        let is_second_start = is_item_start && !is_entry_start && prev.is_type_item;
+        let (type_ok, tx_type) = if is_type_start {
+            (byte != 0 && byte < 3, byte as u64)
+        } else if is_entry_start {
+            (false, 0)
+        } else {
+            (prev.type_ok, prev.tx_type)
+        };
+        let is_tx_header = if is_item_start {
+            is_second_start && is_list && type_ok
+        } else {
+            prev.is_tx_header
+        };
+        let in_tx = !is_entry_start
+            && (prev.in_tx
+                || prev.rlp.is_item_end
+                    && (prev.is_entry_item && prev.is_list_entry || prev.is_tx_header));
+
+        let rlp = RlpDecoderRow::new(
+            byte,
+            is_item_start,
+            is_list_header || is_entry_item || is_tx_header,
+            &prev.rlp,
+        );
+        let remaining = if is_list_header {
+            rlp.length
+        } else {
+            prev.remaining - 1
+        };
+        let entry_remaining = if is_list_header {
+            0
+        } else if is_entry_item {
+            rlp.length - !rlp.is_header as u64
+        } else {
+            prev.entry_remaining - 1
+        };
+        let is_entry_end =
+            !is_list_header && entry_remaining == 0 && (!is_entry_item || rlp.is_item_end);
+        let is_first_payload = !is_item_start && !rlp.is_header && prev.rlp.is_header;
+        let is_first_data = !rlp.is_header && is_item_start || is_first_payload;
+        let is_len_start = rlp.is_header && !is_item_start && prev.rlp.is_item_start;
+
+        // Fields of the tx
+        let is_field_start = in_tx && is_item_start;
+        let is_field_end = in_tx && rlp.is_item_end;
+        let is_extra = is_field_start && prev.fields_left == 0;
+        let fields_left = if is_entry_start {
+            if is_list_entry {
+                9
+            } else {
+                is_string_entry as u64
+            }
+        } else if is_tx_header && is_item_start {
+            10 + tx_type
+        } else if is_field_start {
+            prev.fields_left.saturating_sub(1)
+        } else {
+            prev.fields_left
+        };
+        let field = if !in_tx || is_extra {
+            None
+        } else if is_item_start {
+            let fields = TxField::fields(tx_type);
+            Some(fields[fields.len() - prev.fields_left as usize])
+        } else {
+            prev.field
+        };
+        let [is_int, is_to, is_data, is_access_list, is_chain_id, is_v, is_y_parity, is_sig] =
+            field.map_or([false; 8], TxField::flags);
+
+        let cmp_key = if is_field_start {
+            field.map_or(0, TxField::cmp_key)
+        } else if is_len_start {
+            CMP_SHORT_LENGTH
+        } else if is_type_start {
+            CMP_TX_TYPE
+        } else if is_sig && !rlp.is_header && prev.sig_eq {
+            31 - rlp.counter
+        } else {
+            0
+        };
+        let cmp_lt = byte < cmp_constant(cmp_key);
+        let cmp_eq = byte == cmp_constant(cmp_key);
+        let is_empty = class == RlpByteClass::ShortString && rlp.length == 0;
+        let size_bad = is_int && !(cmp_lt || is_sig && cmp_eq)
+            || is_sig && is_empty
+            || is_to && !(cmp_eq || is_empty);
+        let size_ok = if is_field_start {
+            !size_bad
+        } else {
+            in_tx && prev.size_ok
+        };
+        let (sig_lt, sig_eq) = if !is_sig {
+            (false, false)
+        } else if is_item_start {
+            (cmp_lt, cmp_eq)
+        } else {
+            (prev.sig_lt || prev.sig_eq && cmp_lt, prev.sig_eq && cmp_eq)
+        };
+        let value = if !in_tx || rlp.is_header {
+            0
+        } else if is_first_data {
+            byte as u64
+        } else {
+            prev.value.wrapping_mul(256).wrapping_add(byte as u64)
+        };
+        let value_ok = if is_chain_id {
+            value == chain_id
+        } else if is_v {
+            let v = value as u128;
+            v == 2 * chain_id as u128 + 35 || v == 2 * chain_id as u128 + 36
+        } else {
+            !is_y_parity || value <= 1
+        };
+
+        let bad = [
+            // The entry is not a single byte
+            is_entry_start && !rlp.is_header,
+            // Canonical headers and integers
+            is_len_start
+                && if rlp.counter == 0 {
+                    cmp_lt
+                } else {
+                    rlp.length == 0
+                },
+            is_first_payload
+                && rlp.counter == 0
+                && class == RlpByteClass::Single
+                && !is_access_list,
+            is_first_data && is_int && byte == 0,
+            // The fields
+            is_extra,
+            is_field_start && is_list != is_access_list,
+            is_field_start && size_bad,
+            is_field_end && size_ok && !value_ok,
+            is_field_end && is_sig && !sig_lt,
+            // The type and the list of a typed tx, which fills the entry
+            is_type_start && !type_ok,
+            is_second_start && !is_tx_header,
+            is_tx_header && rlp.is_item_end && rlp.length != entry_remaining,
+            // The entry ends with the last field
+            is_entry_end && !rlp.is_item_end,
+            is_entry_end && fields_left != 0,
+        ];
+        let num_bad = if is_list_header {
+            0
+        } else {
+            (!is_entry_start as u64) * prev.num_bad + bad.iter().filter(|bad| **bad).count() as u64
+        };
+        let data_len = if is_field_end && is_data {
+            rlp.length
+        } else if is_entry_start {
+            0
+        } else {
+            prev.data_len
+        };
+
+        Self {
+            rlp,
+            is_list_header,
+            remaining,
+            is_entry_start,
+            is_entry_item,
+            is_entry_end,
+            entry_remaining,
+            is_list_entry,
+            is_string_entry,
+            is_type_item,
+            type_ok,
+            tx_type,
+            is_tx_header,
+            in_tx,
+            is_first_payload,
+            is_first_data,
+            is_len_start,
+            is_field_start,
+            is_field_end,
+            fields_left,
+            field,
+            cmp_key,
+            cmp_lt,
+            cmp_eq,
+            size_ok,
+            sig_lt,
+            sig_eq,
+            value,
+            num_bad,
+            data_len,
+            calldata_sum: prev.calldata_sum,
+            is_valid: false,
+            tx_id: prev.tx_id,
+        }
+    }
+
+    /// Whether the byte is in the signed tx of the entry: the whole entry for
+    /// lists (legacy txs), and the payload for strings (typed txs).
+    fn is_value_byte(&self) -> bool {
+        !self.is_list_header && (self.is_list_entry || self.is_string_entry && !self.is_entry_item)
+    }
+}
+
+/// Decode the tx list `tx_list` like the circuit, and return its rows and the
+/// transactions of its valid entries.
+fn tx_list_rows(
+    tx_list: &[u8],
+    chain_id: u64,
+    max_txs: usize,
+    max_calldata: usize,
+) -> Result<(Vec<TxListRow>, Vec<Transaction>), DecoderError> {
+    let (class, header_len, payload_len) = rlp_header(tx_list)?;
+    if !matches!(class, RlpByteClass::ShortList | RlpByteClass::LongList) {
+        return Err(DecoderError::RlpExpectedToBeList);
+    }
+    if header_len + payload_len != tx_list.len() {
+        return Err(DecoderError::RlpInconsistentLengthAndData);
+    }
+
+    let mut rows: Vec<TxListRow> = Vec::with_capacity(tx_list.len());
+    let mut txs = Vec::new();
+    let mut entry_start = 0;
+    for (index, byte) in tx_list.iter().enumerate() {
+        let prev = rows.last().copied().unwrap_or_default();
+        let mut row = TxListRow::new(*byte, index == 0, &prev, chain_id);
+        if row.is_entry_start {
+            entry_start = index;
+        }
+        if row.is_entry_end {
+            // The entry is valid if it's a signed tx that fits in the limits
+            // of the circuit, which is decided at its last byte.
+            let num_txs = rows[entry_start - 1].tx_id;
+            let is_valid = row.num_bad == 0
+                && num_txs < max_txs as u64
+                && prev.calldata_sum + row.data_len <= max_calldata as u64;
+            row.calldata_sum += is_valid as u64 * row.data_len;
+            rows.push(row);
+            for row in rows[entry_start..].iter_mut() {
+                row.is_valid = is_valid;
+                row.tx_id = num_txs + is_valid as u64;
+            }
+            if is_valid {
+                let value: Vec<u8> = rows[entry_start..]
+                    .iter()
+                    .zip(&tx_list[entry_start..])
+                    .filter(|(row, _)| row.is_value_byte())
+                    .map(|(_, byte)| *byte)
+                    .collect();
+                txs.push(
+                    decode_signed_tx(&value, chain_id).ok_or(DecoderError::Custom(
+                        "the signature or the access list of a tx is invalid",
+                    ))?,
+                );
+            }
+        } else {
+            rows.push(row);
+        }
+    }
+    let last = rows.last().expect("the tx list has a header");
+    if !(last.is_entry_end || last.is_list_header && last.rlp.is_item_end) {
+        return Err(DecoderError::RlpIsTooShort);
+    }
+    Ok((rows, txs))
+}
+
+/// Cells of the tx list region that are linked to the rest of the circuit.
+#[derive(Clone, Debug)]
+pub(crate) struct TxListCells<F: Field> {
+    /// First 16 bytes of the hash of the tx list, as a number
+    pub(crate) hash_hi: AssignedCell<F, F>,
+    /// Last 16 bytes of the hash of the tx list, as a number
+    pub(crate) hash_lo: AssignedCell<F, F>,
+    /// Chain id of the transactions, as a number
+    pub(crate) chain_id: AssignedCell<F, F>,
+}
+
+/// Config of the tx list region of the PublicInputs circuit
+#[derive(Clone, Debug)]
+pub(crate) struct TxListConfig<F> {
+    max_txs: usize,
+    max_calldata: usize,
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    /// Rows of the hash
+    q_hash: Column<Fixed>,
+    /// Rows of the first 16 bytes of the hash
+    q_hash_hi: Column<Fixed>,
+    /// Rows of the tx list
+    q_data: Column<Fixed>,
+    q_data_first: Column<Fixed>,
+    q_last: Column<Fixed>,
+    /// Rows of the comparison table
+    q_cmp_table: Column<Fixed>,
+    /// Comparison table: (key, byte, lt, eq), where `lt` and `eq` compare the
+    /// byte with the constant of the key
+    cmp_table: [Column<Fixed>; 4],
+    /// Rows of the field table
+    q_field_table: Column<Fixed>,
+    /// Field table: (tx type, fields left, key, flags), the comparison key and
+    /// the flags of the field of the txs of the type when `fields left` fields
+    /// are left, including the field
+    field_table: [Column<Fixed>; 11],
+
+    byte: Column<Advice>,
+    is_padding: Column<Advice>,
+    /// Number of bytes of the tx list up to the row
+    len: Column<Advice>,
+    hash_hi: Column<Advice>,
+    hash_lo: Column<Advice>,
+    rlp: RlpDecoderConfig<F>,
+    /// Whether the row is in the header of the list
+    is_list_header: Column<Advice>,
+    /// Number of bytes of the payload of the list left
+    remaining: Column<Advice>,
+    is_entry_start: Column<Advice>,
+    /// Whether the row is in the header of the entry, or is a single byte
+    /// entry
+    is_entry_item: Column<Advice>,
+    is_entry_end: Column<Advice>,
+    /// Number of bytes of the entry left
+    entry_remaining: Column<Advice>,
+    /// Whether the entry is a list
+    is_list_entry: Column<Advice>,
+    /// Whether the entry is a string with a header
+    is_string_entry: Column<Advice>,
+    /// Whether the row is in the first item of a string entry, its type
+    is_type_item: Column<Advice>,
+    /// Whether the type of the string entry is supported
+    type_ok: Column<Advice>,
+    /// Type of the tx of the entry, which is 0 for legacy txs
+    tx_type: Column<Advice>,
+    /// Whether the row is in the header of the list of a typed tx
+    is_tx_header: Column<Advice>,
+    /// Whether the row is after the header of the list of the tx, in its
+    /// fields
+    in_tx: Column<Advice>,
+    is_first_payload: Column<Advice>,
+    /// Whether the row is the first byte of the payload or of a single byte
+    is_first_data: Column<Advice>,
+    /// Whether the row is the first length byte of a long item
+    is_len_start: Column<Advice>,
+    is_field_start: Column<Advice>,
+    is_field_end: Column<Advice>,
+    /// Number of fields of the tx left, including the field of the row.  It's
+    /// 1 before the list of a typed tx, which is required.
+    fields_left: Column<Advice>,
+    /// Flags of the field: is_int, is_to, is_data, is_access_list,
+    /// is_chain_id, is_v, is_y_parity and is_sig
+    field_flags: [Column<Advice>; 8],
+    /// Key of the comparison of the byte in the comparison table
+    cmp_key: Column<Advice>,
+    cmp_lt: Column<Advice>,
+    cmp_eq: Column<Advice>,
+    /// Whether the length of the field is valid for its kind
+    size_ok: Column<Advice>,
+    /// Whether the bytes of `r` or `s` up to the row are lower than the ones
+    /// of the order of secp256k1
+    sig_lt: Column<Advice>,
+    /// Whether the bytes of `r` or `s` up to the row are equal to the ones of
+    /// the order of secp256k1
+    sig_eq: Column<Advice>,
+    /// Value of the integer up to the row
+    field_value: Column<Advice>,
+    /// Difference checked to be zero: the length of the list of a typed tx
+    /// and the bytes left in the entry, and the expected chain id, `v` and
+    /// `y_parity`
+    check: Column<Advice>,
+    chain_id: Column<Advice>,
+    /// Number of rules broken by the entry up to the row
+    num_bad: Column<Advice>,
+    /// Length of the call data of the entry
+    data_len: Column<Advice>,
+    /// Length of the call data of the valid entries up to the row
+    calldata_sum: Column<Advice>,
+    /// Whether the entry is a valid transaction
+    is_valid: Column<Advice>,
+    /// Number of valid entries up to the row, which is the id of the
+    /// transaction of a valid entry
+    tx_id: Column<Advice>,
+    /// Length of the signed transaction of the entry
+    value_len: Column<Advice>,
+
+    input_rlc: Column<Advice>,
+    hash_rlc: Column<Advice>,
+    /// RLC of the signed transaction of the entry with the keccak input
+    /// challenge
+    value_rlc: Column<Advice>,
+
+    byte_is_zero: IsZeroConfig<F>,
+    entry_remaining_is_zero: IsZeroConfig<F>,
+    fields_left_is_zero: IsZeroConfig<F>,
+    check_is_zero: IsZeroConfig<F>,
+    num_bad_is_zero: IsZeroConfig<F>,
+    /// Whether the number of valid entries before the entry is `max_txs`
+    count_is_max: IsZeroConfig<F>,
+    /// Whether the call data of the valid entries before the entry and the one
+    /// of the entry exceed `max_calldata`
+    calldata_exceeds: LtConfig<F, 4>,
+
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> TxListConfig<F> {
+    /// Configure the tx list region of a block of up to `max_txs` txs and
+    /// `max_calldata` bytes of call data, whose valid entries are the values
+    /// of the transactions trie `tx_root`.  The bytes are decoded with the
+    /// classes of `byte_table`.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        max_txs: usize,
+        max_calldata: usize,
+        byte_table: &RlpByteTable,
+        keccak_table: &KeccakTable,
+        tx_root: &ListTrieConfig<F>,
+        challenges: &Challenges<Expression<F>>,
+    ) -> Self {
+        let q_data = meta.fixed_column();
+        let byte = meta.advice_column();
+        let is_padding = meta.advice_column();
+        let is_list_header = meta.advice_column();
+        let is_entry_item = meta.advice_column();
+        let is_entry_end = meta.advice_column();
+        let is_tx_header = meta.advice_column();
+        let data_len = meta.advice_column();
+        let calldata_sum = meta.advice_column();
+        let is_valid = meta.advice_column();
+        let tx_id = meta.advice_column();
+        let data_row = move |meta: &mut VirtualCells<'_, F>| {
+            and::expr([
+                meta.query_fixed(q_data, Rotation::cur()),
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+            ])
+        };
+        let is_zero = |meta: &mut ConstraintSystem<F>, column: Column<Advice>| {
+            let value_inv = meta.advice_column();
+            IsZeroChip::configure(
+                meta,
+                |meta| meta.query_fixed(q_data, Rotation::cur()),
+                |meta| meta.query_advice(column, Rotation::cur()),
+                value_inv,
+            )
+        };
+        let entry_remaining = meta.advice_column();
+        let fields_left = meta.advice_column();
+        let check = meta.advice_column();
+        let num_bad = meta.advice_column();
+
+        let config = Self {
+            max_txs,
+            max_calldata,
+            q_enable: meta.fixed_column(),
+            q_first: meta.fixed_column(),
+            q_hash: meta.fixed_column(),
+            q_hash_hi: meta.fixed_column(),
+            q_data,
+            q_data_first: meta.fixed_column(),
+            q_last: meta.fixed_column(),
+            q_cmp_table: meta.fixed_column(),
+            cmp_table: array::from_fn(|_| meta.fixed_column()),
+            q_field_table: meta.fixed_column(),
+            field_table: array::from_fn(|_| meta.fixed_column()),
+            byte,
+            is_padding,
+            len: meta.advice_column(),
+            hash_hi: meta.advice_column(),
+            hash_lo: meta.advice_column(),
+            rlp: RlpDecoderConfig::configure(meta, data_row, byte, byte_table, |meta| {
+                sum::expr(
+                    [is_list_header, is_entry_item, is_tx_header]
+                        .map(|column| meta.query_advice(column, Rotation::cur())),
+                )
+            }),
+            is_list_header,
+            remaining: meta.advice_column(),
+            is_entry_start: meta.advice_column(),
+            is_entry_item,
+            is_entry_end,
+            entry_remaining,
+            is_list_entry: meta.advice_column(),
+            is_string_entry: meta.advice_column(),
+            is_type_item: meta.advice_column(),
+            type_ok: meta.advice_column(),
+            tx_type: meta.advice_column(),
+            is_tx_header,
+            in_tx: meta.advice_column(),
+            is_first_payload: meta.advice_column(),
+            is_first_data: meta.advice_column(),
+            is_len_start: meta.advice_column(),
+            is_field_start: meta.advice_column(),
+            is_field_end: meta.advice_column(),
+            fields_left,
+            field_flags: array::from_fn(|_| meta.advice_column()),
+            cmp_key: meta.advice_column(),
+            cmp_lt: meta.advice_column(),
+            cmp_eq: meta.advice_column(),
+            size_ok: meta.advice_column(),
+            sig_lt: meta.advice_column(),
+            sig_eq: meta.advice_column(),
+            field_value: meta.advice_column(),
+            check,
+            chain_id: meta.advice_column(),
+            num_bad,
+            data_len,
+            calldata_sum,
+            is_valid,
+            tx_id,
+            value_len: meta.advice_column(),
+            input_rlc: meta.advice_column_in(SecondPhase),
+            hash_rlc: meta.advice_column_in(SecondPhase),
+            value_rlc: meta.advice_column_in(SecondPhase),
+            byte_is_zero: is_zero(meta, byte),
+            entry_remaining_is_zero: is_zero(meta, entry_remaining),
+            fields_left_is_zero: is_zero(meta, fields_left),
+            check_is_zero: is_zero(meta, check),
+            num_bad_is_zero: is_zero(meta, num_bad),
+            count_is_max: {
+                let count_inv = meta.advice_column();
+                IsZeroChip::configure(
+                    meta,
+                    |meta| meta.query_fixed(q_data, Rotation::cur()),
+                    |meta| {
+                        meta.query_advice(tx_id, Rotation::cur())
+                            - meta.query_advice(is_valid, Rotation::cur())
+                            - max_txs.expr()
+                    },
+                    count_inv,
+                )
+            },
+            calldata_exceeds: LtChip::configure(
+                meta,
+                |meta| data_row(meta) * meta.query_advice(is_entry_end, Rotation::cur()),
+                |_| max_calldata.expr(),
+                |meta| {
+                    meta.query_advice(calldata_sum, Rotation::prev())
+                        + meta.query_advice(data_len, Rotation::cur())
+                },
+            ),
+            _marker: PhantomData,
+        };
+        for column in [config.hash_hi, config.hash_lo, config.chain_id] {
+            meta.enable_equality(column);
+        }
+        config.configure_gates(meta, challenges);
+
+        let c = &config;
+        meta.lookup_any("tx list byte is in u8 range", |meta| {
+            vec![(
+                meta.query_fixed(c.q_enable, Rotation::cur())
+                    * meta.query_advice(c.byte, Rotation::cur()),
+                meta.query_fixed(byte_table.byte, Rotation::cur()),
+            )]
+        });
+        for diff in c.calldata_exceeds.diff {
+            meta.lookup_any("tx list call data difference byte is in u8 range", |meta| {
+                vec![(
+                    meta.query_fixed(c.q_data, Rotation::cur())
+                        * meta.query_advice(diff, Rotation::cur()),
+                    meta.query_fixed(byte_table.byte, Rotation::cur()),
+                )]
+            });
+        }
+        meta.lookup_any("tx list byte comparison", |meta| {
+            let enable = data_row(meta);
+            [
+                (1.expr(), c.q_cmp_table),
+                (
+                    meta.query_advice(c.cmp_key, Rotation::cur()),
+                    c.cmp_table[0],
+                ),
+                (meta.query_advice(c.byte, Rotation::cur()), c.cmp_table[1]),
+                (meta.query_advice(c.cmp_lt, Rotation::cur()), c.cmp_table[2]),
+                (meta.query_advice(c.cmp_eq, Rotation::cur()), c.cmp_table[3]),
+            ]
+            .into_iter()
+            .map(|(input, table)| {
+                (
+                    enable.clone() * input,
+                    meta.query_fixed(table, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+        meta.lookup_any("tx list field kind", |meta| {
+            // The fields after the last one are skipped
+            let enable = data_row(meta)
+                * meta.query_advice(c.is_field_start, Rotation::cur())
+                * not::expr(
+                    c.fields_left_is_zero
+                        .expr_at(meta, c.fields_left, Rotation::prev()),
+                );
+            [
+                1.expr(),
+                meta.query_advice(c.tx_type, Rotation::cur()),
+                meta.query_advice(c.fields_left, Rotation::prev()),
+                meta.query_advice(c.cmp_key, Rotation::cur()),
+            ]
+            .into_iter()
+            .chain(
+                c.field_flags
+                    .map(|column| meta.query_advice(column, Rotation::cur())),
+            )
+            .zip(std::iter::once(c.q_field_table).chain(c.field_table))
+            .map(|(input, table)| {
+                (
+                    enable.clone() * input,
+                    meta.query_fixed(table, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+
+        meta.lookup_any("keccak256_table_lookup(input_rlc, len, hash_rlc)", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(c.q_data, Rotation::cur()),
+                not::expr(meta.query_advice(c.is_padding, Rotation::cur())),
+                meta.query_advice(c.is_padding, Rotation::next()),
+            ]);
+            [
+                1.expr(),
+                meta.query_advice(c.input_rlc, Rotation::cur()),
+                meta.query_advice(c.len, Rotation::cur()),
+                meta.query_advice(c.hash_rlc, Rotation::cur()),
+            ]
+            .into_iter()
+            .zip(keccak_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        meta.lookup_any("every valid entry is a value of the tx trie", |meta| {
+            let enable = c.valid_entry_end(meta);
+            [
+                (1.expr(), tx_root.path_end(meta)),
+                (
+                    meta.query_advice(c.tx_id, Rotation::cur()),
+                    meta.query_advice(tx_root.id, Rotation::cur()),
+                ),
+                (
+                    meta.query_advice(c.value_rlc, Rotation::cur()),
+                    meta.query_advice(tx_root.value_rlc, Rotation::cur()),
+                ),
+                (
+                    meta.query_advice(c.value_len, Rotation::cur()),
+                    meta.query_advice(tx_root.value_len, Rotation::cur()),
+                ),
+            ]
+            .into_iter()
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+        meta.lookup_any("every value of the tx trie is a valid entry", |meta| {
+            let enable = tx_root.path_end(meta);
+            vec![
+                (enable.clone(), c.valid_entry_end(meta)),
+                (
+                    enable * meta.query_advice(tx_root.id, Rotation::cur()),
+                    meta.query_advice(c.tx_id, Rotation::cur()),
+                ),
+            ]
+        });
+
+        config
+    }
+
+    /// Enabled in the last byte of a valid entry
+    fn valid_entry_end(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        and::expr([
+            meta.query_fixed(self.q_data, Rotation::cur()),
+            not::expr(meta.query_advice(self.is_padding, Rotation::cur())),
+            meta.query_advice(self.is_entry_end, Rotation::cur()),
+            meta.query_advice(self.is_valid, Rotation::cur()),
+        ])
+    }
+
+    fn configure_gates(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        challenges: &Challenges<Expression<F>>,
+    ) {
+        let c = self;
+
+        meta.create_gate("tx list hash rows", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(c.byte, Rotation::cur());
+            let q_hash_hi = meta.query_fixed(c.q_hash_hi, Rotation::cur());
+            // The accumulators of the hash start from zero at the first row.
+            let not_first = not::expr(meta.query_fixed(c.q_first, Rotation::cur()));
+            let mut prev = |column| not_first.clone() * meta.query_advice(column, Rotation::prev());
+            let hash_hi_prev = prev(c.hash_hi);
+            let hash_lo_prev = prev(c.hash_lo);
+            let hash_rlc_prev = prev(c.hash_rlc);
+
+            cb.require_equal(
+                "hash_hi accumulates the first 16 hash bytes",
+                meta.query_advice(c.hash_hi, Rotation::cur()),
+                select::expr(
+                    q_hash_hi.clone(),
+                    hash_hi_prev.clone() * 256.expr() + byte.clone(),
+                    hash_hi_prev,
+                ),
+            );
+            cb.require_equal(
+                "hash_lo accumulates the last 16 hash bytes",
+                meta.query_advice(c.hash_lo, Rotation::cur()),
+                select::expr(
+                    q_hash_hi,
+                    hash_lo_prev.clone(),
+                    hash_lo_prev * 256.expr() + byte.clone(),
+                ),
+            );
+            cb.require_equal(
+                "hash_rlc accumulates the hash bytes",
+                meta.query_advice(c.hash_rlc, Rotation::cur()),
+                hash_rlc_prev * challenges.evm_word() + byte,
+            );
+
+            cb.gate(meta.query_fixed(c.q_hash, Rotation::cur()))
+        });
+
+        meta.create_gate("tx list padding", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_padding = meta.query_advice(c.is_padding, Rotation::cur());
+            cb.require_boolean("is_padding is boolean", is_padding.clone());
+            cb.condition(meta.query_fixed(c.q_data_first, Rotation::cur()), |cb| {
+                cb.require_zero("the tx list has at least one byte", is_padding.clone());
+            });
+            cb.condition(
+                not::expr(meta.query_fixed(c.q_data_first, Rotation::cur())),
+                |cb| {
+                    cb.require_zero(
+                        "padding is not followed by the tx list",
+                        meta.query_advice(c.is_padding, Rotation::prev()) * not::expr(is_padding),
+                    );
+                },
+            );
+            cb.condition(meta.query_fixed(c.q_last, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "the last row is padding",
+                    meta.query_advice(c.is_padding, Rotation::cur()),
+                    1.expr(),
+                );
+            });
+
+            cb.gate(meta.query_fixed(c.q_data, Rotation::cur()))
+        });
+
+        meta.create_gate("tx list bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_data_first = meta.query_fixed(c.q_data_first, Rotation::cur());
+            let not_first = not::expr(q_data_first.clone());
+            let cur =
+                |meta: &mut VirtualCells<'_, F>, column| meta.query_advice(column, Rotation::cur());
+            let prev = |meta: &mut VirtualCells<'_, F>, column| {
+                meta.query_advice(column, Rotation::prev())
+            };
+            let byte = cur(meta, c.byte);
+            let is_list_header = cur(meta, c.is_list_header);
+            let is_item_start = cur(meta, c.rlp.is_item_start);
+            let is_item_end = cur(meta, c.rlp.is_item_end);
+            let is_header = cur(meta, c.rlp.is_header);
+            let length = cur(meta, c.rlp.length);
+            let is_entry_start = cur(meta, c.is_entry_start);
+            let is_entry_item = cur(meta, c.is_entry_item);
+            let is_entry_end = cur(meta, c.is_entry_end);
+            let is_list_entry = cur(meta, c.is_list_entry);
+            let is_string_entry = cur(meta, c.is_string_entry);
+            let is_type_item = cur(meta, c.is_type_item);
+            let type_ok = cur(meta, c.type_ok);
+            let tx_type = cur(meta, c.tx_type);
+            let is_tx_header = cur(meta, c.is_tx_header);
+            let in_tx = cur(meta, c.in_tx);
+            let is_first_payload = cur(meta, c.is_first_payload);
+            let is_first_data = cur(meta, c.is_first_data);
+            let is_len_start = cur(meta, c.is_len_start);
+            let is_field_start = cur(meta, c.is_field_start);
+            let is_field_end = cur(meta, c.is_field_end);
+            let fields_left = cur(meta, c.fields_left);
+            let cmp_lt = cur(meta, c.cmp_lt);
+            let cmp_eq = cur(meta, c.cmp_eq);
+            let size_ok = cur(meta, c.size_ok);
+            let sig_lt = cur(meta, c.sig_lt);
+            let sig_eq = cur(meta, c.sig_eq);
+            let field_value = cur(meta, c.field_value);
+            let chain_id = cur(meta, c.chain_id);
+            let data_len = cur(meta, c.data_len);
+            let is_valid = cur(meta, c.is_valid);
+            let [is_int, is_to, is_data, is_access_list, is_chain_id, is_v, is_y_parity, is_sig] =
+                c.field_flags.map(|column| cur(meta, column));
+            let is_list = c.rlp.is_list(meta);
+            let is_short_string = cur(meta, c.rlp.classes[0]);
+            let is_string = is_short_string.clone() + cur(meta, c.rlp.classes[1]);
+            let is_single = not::expr(sum::expr(c.rlp.classes.map(|column| cur(meta, column))));
+            let counter_is_zero = c.rlp.counter_is_zero.expr();
+            let length_is_zero = c.rlp.length_is_zero.expr();
+            let byte_is_zero = c.byte_is_zero.expr();
+            let check_is_zero = c.check_is_zero.expr();
+            let no_field_left_prev =
+                c.fields_left_is_zero
+                    .expr_at(meta, c.fields_left, Rotation::prev());
+            let is_item_end_prev = prev(meta, c.rlp.is_item_end);
+            let is_entry_end_prev = prev(meta, c.is_entry_end);
+            let is_entry_item_prev = prev(meta, c.is_entry_item);
+            let is_type_item_prev = prev(meta, c.is_type_item);
+            let is_tx_header_prev = prev(meta, c.is_tx_header);
+            let is_type_start = is_type_item.clone() * is_item_start.clone();
+            let is_second_start = is_item_start.clone()
+                * not::expr(is_entry_start.clone())
+                * is_type_item_prev.clone();
+            let is_tx_start = is_tx_header.clone() * is_item_start.clone();
+
+            for (name, column) in [
+                ("is_list_header is boolean", c.is_list_header),
+                ("is_entry_start is boolean", c.is_entry_start),
+                ("is_entry_item is boolean", c.is_entry_item),
+                ("is_entry_end is boolean", c.is_entry_end),
+                ("is_list_entry is boolean", c.is_list_entry),
+                ("is_string_entry is boolean", c.is_string_entry),
+                ("is_type_item is boolean", c.is_type_item),
+                ("is_tx_header is boolean", c.is_tx_header),
+                ("in_tx is boolean", c.in_tx),
+                ("cmp_lt is boolean", c.cmp_lt),
+                ("cmp_eq is boolean", c.cmp_eq),
+                ("sig_lt is boolean", c.sig_lt),
+                ("sig_eq is boolean", c.sig_eq),
+                ("is_valid is boolean", c.is_valid),
+            ] {
+                cb.require_boolean(name, cur(meta, column));
+            }
+
+            // The tx list starts with the header of the list
+            cb.condition(q_data_first, |cb| {
+                cb.require_equal(
+                    "the list header starts an item",
+                    is_item_start.clone(),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "the first item is the list header",
+                    is_list_header.clone(),
+                    1.expr(),
+                );
+                cb.require_equal("the tx list is a list", is_list.clone(), 1.expr());
+                cb.require_equal("len starts at 1", cur(meta, c.len), 1.expr());
+                cb.require_equal(
+                    "input_rlc starts with the first byte",
+                    cur(meta, c.input_rlc),
+                    byte.clone(),
+                );
+                for column in [
+                    c.is_entry_start,
+                    c.is_entry_item,
+                    c.is_list_entry,
+                    c.is_string_entry,
+                    c.is_type_item,
+                    c.type_ok,
+                    c.tx_type,
+                    c.is_tx_header,
+                    c.in_tx,
+                    c.is_first_payload,
+                    c.is_len_start,
+                    c.fields_left,
+                    c.data_len,
+                    c.calldata_sum,
+                    c.is_valid,
+                    c.tx_id,
+                ] {
+                    cb.require_zero(
+                        "the entry columns are 0 in the list header",
+                        cur(meta, column),
+                    );
+                }
+            });
+
+            cb.require_equal(
+                "hash_rlc is the one of the hash rows",
+                cur(meta, c.hash_rlc),
+                prev(meta, c.hash_rlc),
+            );
+            cb.condition(not_first.clone(), |cb| {
+                cb.require_equal(
+                    "len increases by 1",
+                    cur(meta, c.len),
+                    prev(meta, c.len) + 1.expr(),
+                );
+                cb.require_equal(
+                    "input_rlc accumulates the bytes",
+                    cur(meta, c.input_rlc),
+                    prev(meta, c.input_rlc) * challenges.keccak_input() + byte.clone(),
+                );
+                cb.require_equal(
+                    "chain_id is the same in all the rows",
+                    chain_id.clone(),
+                    prev(meta, c.chain_id),
+                );
+
+                // Items start after the previous one, and entries after the
+                // list header or the previous entry, even if their last item
+                // is longer.
+                cb.require_equal(
+                    "an item starts after the end of the previous item or entry",
+                    is_item_start.clone(),
+                    select::expr(
+                        is_entry_end_prev.clone(),
+                        1.expr(),
+                        is_item_end_prev.clone(),
+                    ),
+                );
+                cb.require_equal(
+                    "the list header is the first item",
+                    is_list_header.clone(),
+                    prev(meta, c.is_list_header) * not::expr(is_item_end_prev.clone()),
+                );
+                cb.require_equal(
+                    "an entry starts after the list header or the previous entry",
+                    is_entry_start.clone(),
+                    is_item_start.clone()
+                        * not::expr(is_list_header.clone())
+                        * (prev(meta, c.is_list_header) + is_entry_end_prev),
+                );
+                cb.require_equal(
+                    "the entry item is the item that starts the entry",
+                    is_entry_item.clone(),
+                    select::expr(
+                        is_item_start.clone(),
+                        is_entry_start.clone(),
+                        is_entry_item_prev.clone(),
+                    ),
+                );
+                for (column, start) in [
+                    (c.is_list_entry, is_list.clone()),
+                    (c.is_string_entry, is_string),
+                ] {
+                    cb.require_equal(
+                        "the kind of the entry is the class of its first byte",
+                        cur(meta, column),
+                        select::expr(is_entry_start.clone(), start, prev(meta, column)),
+                    );
+                }
+                cb.require_equal(
+                    "is_valid is the same in the entry",
+                    (1.expr() - is_entry_start.clone()) * is_valid.clone(),
+                    (1.expr() - is_entry_start.clone()) * prev(meta, c.is_valid),
+                );
+                cb.require_equal(
+                    "tx_id increases by 1 at the start of a valid entry",
+                    cur(meta, c.tx_id),
+                    prev(meta, c.tx_id) + is_entry_start.clone() * is_valid.clone(),
+                );
+
+                // Typed txs: the type, followed by the list of the tx
+                cb.require_equal(
+                    "the type is the first item of a string entry",
+                    is_type_item.clone(),
+                    select::expr(
+                        is_item_start.clone(),
+                        not::expr(is_entry_start.clone())
+                            * is_string_entry.clone()
+                            * is_entry_item_prev.clone()
+                            * is_item_end_prev.clone(),
+                        is_type_item_prev,
+                    ),
+                );
+                cb.require_equal(
+                    "the list of a typed tx is the second item of the entry",
+                    is_tx_header.clone(),
+                    select::expr(
+                        is_item_start.clone(),
+                        is_second_start.clone() * is_list.clone() * type_ok.clone(),
+                        is_tx_header_prev.clone(),
+                    ),
+                );
+                cb.require_equal(
+                    "the fields follow the header of a legacy entry or of the list of a typed tx",
+                    in_tx.clone(),
+                    not::expr(is_entry_start.clone())
+                        * (prev(meta, c.in_tx)
+                            + is_item_end_prev
+                                * (is_entry_item_prev * prev(meta, c.is_list_entry)
+                                    + is_tx_header_prev)),
+                );
+                cb.require_equal(
+                    "is_first_payload is the row after the header",
+                    is_first_payload.clone(),
+                    not::expr(is_item_start.clone())
+                        * not::expr(is_header.clone())
+                        * prev(meta, c.rlp.is_header),
+                );
+                cb.require_equal(
+                    "is_len_start is the row after the first byte of a long item",
+                    is_len_start.clone(),
+                    is_header.clone()
+                        * not::expr(is_item_start.clone())
+                        * prev(meta, c.rlp.is_item_start),
+                );
+
+                // The call data of the entry, and of the valid entries
+                cb.require_equal(
+                    "data_len is the length of the call data of the entry",
+                    data_len.clone(),
+                    select::expr(
+                        is_field_end.clone() * is_data.clone(),
+                        length.clone(),
+                        not::expr(is_entry_start.clone()) * prev(meta, c.data_len),
+                    ),
+                );
+                cb.require_equal(
+                    "calldata_sum adds the call data of the valid entries",
+                    cur(meta, c.calldata_sum),
+                    prev(meta, c.calldata_sum)
+                        + is_entry_end.clone() * is_valid.clone() * data_len.clone(),
+                );
+
+                // The signed tx of the entry: the whole entry for legacy txs,
+                // and the payload for typed txs
+                let is_value_byte = not::expr(is_list_header.clone())
+                    * (is_list_entry.clone()
+                        + is_string_entry.clone() * not::expr(is_entry_item.clone()));
+                let value_rlc_prev = not::expr(is_entry_start.clone()) * prev(meta, c.value_rlc);
+                let value_len_prev = not::expr(is_entry_start.clone()) * prev(meta, c.value_len);
+                cb.require_equal(
+                    "value_rlc accumulates the bytes of the signed tx",
+                    cur(meta, c.value_rlc),
+                    select::expr(
+                        is_value_byte.clone(),
+                        value_rlc_prev.clone() * challenges.keccak_input() + byte.clone(),
+                        value_rlc_prev,
+                    ),
+                );
+                cb.require_equal(
+                    "value_len counts the bytes of the signed tx",
+                    cur(meta, c.value_len),
+                    value_len_prev + is_value_byte,
+                );
+            });
+
+            // The type of a typed tx, which is looked up in the comparison
+            // table
+            cb.condition(is_type_start.clone(), |cb| {
+                cb.require_equal(
+                    "the type of a typed tx is 1 or 2",
+                    type_ok.clone(),
+                    cmp_lt.clone() * not::expr(byte_is_zero.clone()),
+                );
+                cb.require_equal("tx_type is the type byte", tx_type.clone(), byte.clone());
+            });
+            cb.condition(not_first.clone() * not::expr(is_type_start.clone()), |cb| {
+                for column in [c.type_ok, c.tx_type] {
+                    cb.require_equal(
+                        "the type is the same in the entry",
+                        cur(meta, column),
+                        not::expr(is_entry_start.clone()) * prev(meta, column),
+                    );
+                }
+            });
+
+            // The entries fill the payload of the list
+            cb.condition(is_list_header.clone(), |cb| {
+                cb.require_equal(
+                    "remaining is the length of the list in its header",
+                    cur(meta, c.remaining),
+                    length.clone(),
+                );
+            });
+            cb.condition(not::expr(is_list_header.clone()), |cb| {
+                cb.require_equal(
+                    "remaining decreases by 1 in the entries",
+                    cur(meta, c.remaining),
+                    prev(meta, c.remaining) - 1.expr(),
+                );
+                cb.require_equal(
+                    "entry_remaining is the length of the entry, then decreases by 1",
+                    cur(meta, c.entry_remaining),
+                    select::expr(
+                        is_entry_item.clone(),
+                        length.clone() - not::expr(is_header.clone()),
+                        prev(meta, c.entry_remaining) - 1.expr(),
+                    ),
+                );
+            });
+            cb.require_equal(
+                "an entry ends after its bytes, with its header if it's empty",
+                is_entry_end.clone(),
+                not::expr(is_list_header.clone())
+                    * c.entry_remaining_is_zero.expr()
+                    * not::expr(is_entry_item.clone() * not::expr(is_item_end.clone())),
+            );
+
+            // The rows of the items and of the fields
+            cb.require_equal(
+                "is_first_data is the first payload byte or a single byte",
+                is_first_data.clone(),
+                not::expr(is_header.clone()) * is_item_start.clone() + is_first_payload.clone(),
+            );
+            cb.require_equal(
+                "is_field_start is the first byte of a field",
+                is_field_start.clone(),
+                in_tx.clone() * is_item_start.clone(),
+            );
+            cb.require_equal(
+                "is_field_end is the last byte of a field",
+                is_field_end.clone(),
+                in_tx.clone() * is_item_end.clone(),
+            );
+
+            // The fields left, and the kind of the field of the row, which is
+            // looked up in the field table at its first byte
+            cb.condition(is_entry_start.clone(), |cb| {
+                cb.require_equal(
+                    "a legacy tx has 9 fields, and a typed tx starts with its list",
+                    fields_left.clone(),
+                    9.expr() * is_list_entry.clone() + is_string_entry.clone(),
+                );
+            });
+            cb.condition(is_tx_start.clone(), |cb| {
+                cb.require_equal(
+                    "an EIP-2930 tx has 11 fields and an EIP-1559 tx 12",
+                    fields_left.clone(),
+                    10.expr() + tx_type.clone(),
+                );
+            });
+            cb.condition(is_field_start.clone(), |cb| {
+                cb.require_equal(
+                    "fields_left decreases by 1 at each field, down to 0",
+                    fields_left.clone(),
+                    prev(meta, c.fields_left) - 1.expr() + no_field_left_prev.clone(),
+                );
+            });
+            cb.condition(
+                not_first
+                    * not::expr(
+                        is_entry_start.clone() + is_tx_start.clone() + is_field_start.clone(),
+                    ),
+                |cb| {
+                    cb.require_equal(
+                        "fields_left is the same in a field",
+                        fields_left.clone(),
+                        prev(meta, c.fields_left),
+                    );
+                },
+            );
+            cb.condition(in_tx.clone() * not::expr(is_item_start.clone()), |cb| {
+                for column in c.field_flags.into_iter().chain([c.size_ok]) {
+                    cb.require_equal(
+                        "the kind of the field is the same in the field",
+                        cur(meta, column),
+                        prev(meta, column),
+                    );
+                }
+            });
+            cb.condition(not::expr(in_tx.clone()), |cb| {
+                for column in c.field_flags.into_iter().chain([c.size_ok]) {
+                    cb.require_zero("only the fields have a kind", cur(meta, column));
+                }
+            });
+            cb.condition(is_field_start.clone() * no_field_left_prev.clone(), |cb| {
+                for column in c.field_flags.into_iter().chain([c.cmp_key]) {
+                    cb.require_zero(
+                        "the fields after the last one have no kind",
+                        cur(meta, column),
+                    );
+                }
+            });
+
+            // The bytes compared in the comparison table: the first byte of
+            // the fields, of the type and of the length of long items, and
+            // the bytes of `r` and `s` while they are equal to the ones of the
+            // order of secp256k1.
+            cb.condition(not::expr(is_field_start.clone()), |cb| {
+                cb.require_equal(
+                    "cmp_key is decided by the row",
+                    cur(meta, c.cmp_key),
+                    is_sig.clone()
+                        * not::expr(is_header.clone())
+                        * prev(meta, c.sig_eq)
+                        * (31.expr() - cur(meta, c.rlp.counter))
+                        + is_len_start.clone() * CMP_SHORT_LENGTH.expr()
+                        + is_type_start.clone() * CMP_TX_TYPE.expr(),
+                );
+            });
+
+            // The length of the field is valid for its kind: up to 8 or 32
+            // bytes for integers, and 0 or 20 for `to`.  `r` and `s` are not
+            // zero, and are compared with the order of secp256k1 from their
+            // header, which is equal if they have 32 bytes.
+            let is_empty = is_short_string * length_is_zero.clone();
+            let size_bad = is_int.clone()
+                * not::expr(cmp_lt.clone() + is_sig.clone() * cmp_eq.clone())
+                + is_sig.clone() * is_empty.clone()
+                + is_to * not::expr(cmp_eq.clone() + is_empty);
+            cb.condition(is_field_start.clone(), |cb| {
+                cb.require_equal(
+                    "size_ok is decided by the first byte of the field",
+                    size_ok.clone(),
+                    not::expr(size_bad.clone()),
+                );
+            });
+            cb.condition(is_sig.clone() * is_item_start.clone(), |cb| {
+                cb.require_equal(
+                    "sig_lt starts with the comparison of the header",
+                    sig_lt.clone(),
+                    cmp_lt.clone(),
+                );
+                cb.require_equal(
+                    "sig_eq starts with the comparison of the header",
+                    sig_eq.clone(),
+                    cmp_eq.clone(),
+                );
+            });
+            cb.condition(is_sig.clone() * not::expr(is_item_start.clone()), |cb| {
+                cb.require_equal(
+                    "sig_lt is set at the first lower byte",
+                    sig_lt.clone(),
+                    prev(meta, c.sig_lt) + prev(meta, c.sig_eq) * cmp_lt.clone(),
+                );
+                cb.require_equal(
+                    "sig_eq is kept while the bytes are equal",
+                    sig_eq.clone(),
+                    prev(meta, c.sig_eq) * cmp_eq.clone(),
+                );
+            });
+            cb.require_equal(
+                "field_value accumulates the bytes of the field",
+                field_value.clone(),
+                in_tx.clone()
+                    * not::expr(is_header.clone())
+                    * (byte.clone()
+                        + not::expr(is_first_data.clone())
+                            * prev(meta, c.field_value)
+                            * 256.expr()),
+            );
+
+            // The checked values: the list of a typed tx ends with the entry,
+            // and the chain id, `v` and `y_parity` are valid.
+            let double_chain_id = 2.expr() * chain_id.clone();
+            cb.require_equal(
+                "check is the difference of the checked values",
+                cur(meta, c.check),
+                is_tx_header.clone()
+                    * is_item_end.clone()
+                    * (length - cur(meta, c.entry_remaining))
+                    + is_field_end.clone()
+                        * (is_chain_id.clone() * (field_value.clone() - chain_id)
+                            + is_v.clone()
+                                * (field_value.clone() - double_chain_id.clone() - 35.expr())
+                                * (field_value.clone() - double_chain_id - 36.expr())
+                            + is_y_parity.clone() * field_value.clone() * (field_value - 1.expr())),
+            );
+
+            // The rules broken by the entry
+            let is_bad = [
+                // The entry is not a single byte
+                is_entry_start.clone() * not::expr(is_header),
+                // Canonical headers and integers
+                is_len_start * select::expr(counter_is_zero.clone(), cmp_lt, length_is_zero),
+                is_first_payload * counter_is_zero * is_single * not::expr(is_access_list.clone()),
+                is_first_data * is_int * byte_is_zero,
+                // The fields
+                is_field_start.clone() * no_field_left_prev,
+                is_field_start.clone()
+                    * (is_list.clone() + is_access_list.clone()
+                        - 2.expr() * is_list.clone() * is_access_list),
+                is_field_start * size_bad,
+                is_field_end.clone()
+                    * (is_chain_id + is_v + is_y_parity)
+                    * size_ok
+                    * not::expr(check_is_zero.clone()),
+                is_field_end * is_sig * not::expr(sig_lt),
+                // The type and the list of a typed tx, which fills the entry
+                is_type_start * not::expr(type_ok),
+                is_second_start * not::expr(is_tx_header.clone()),
+                is_tx_header * is_item_end.clone() * not::expr(check_is_zero),
+                // The entry ends with the last field
+                is_entry_end.clone() * not::expr(is_item_end.clone()),
+                is_entry_end.clone() * not::expr(c.fields_left_is_zero.expr()),
+            ];
+            cb.require_equal(
+                "num_bad counts the rules broken by the entry",
+                cur(meta, c.num_bad),
+                not::expr(is_list_header.clone())
+                    * (not::expr(is_entry_start) * prev(meta, c.num_bad) + sum::expr(is_bad)),
+            );
+
+            // An entry is valid if it's a signed tx, and if it fits in
+            // `max_txs` and `max_calldata`.
+            cb.condition(is_entry_end, |cb| {
+                cb.require_equal(
+                    "an entry is valid if it's a signed tx that fits in the circuit",
+                    is_valid,
+                    c.num_bad_is_zero.expr()
+                        * not::expr(c.count_is_max.expr())
+                        * not::expr(c.calldata_exceeds.is_lt(meta, None)),
+                );
+            });
+            cb.condition(meta.query_advice(c.is_padding, Rotation::next()), |cb| {
+                cb.require_zero(
+                    "the tx list ends with the payload of the list",
+                    cur(meta, c.remaining),
+                );
+                cb.require_equal(
+                    "the tx list ends with an entry or with the list header",
+                    is_list_header * is_item_end + cur(meta, c.is_entry_end),
+                    1.expr(),
+                );
+            });
+
+            cb.gate(and::expr([
+                meta.query_fixed(c.q_data, Rotation::cur()),
+                not::expr(cur(meta, c.is_padding)),
+            ]))
+        });
+    }
+
+    /// Assign the tx list `tx_list` of a block of the chain `chain_id` in
+    /// `n_rows` rows, and return the cells of its hash and chain id.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        tx_list: &[u8],
+        chain_id: u64,
+        n_rows: usize,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<TxListCells<F>, Error> {
+        let (rows, _) =
+            tx_list_rows(tx_list, chain_id, self.max_txs, self.max_calldata).map_err(|err| {
+                error!("invalid tx list: {}", err);
+                Error::Synthesis
+            })?;
+        if tx_list_region_len(tx_list.len()) > n_rows {
+            error!(
+                "the tx list requires {} rows, but the region has {}",
+                tx_list_region_len(tx_list.len()),
+                n_rows
+            );
+            return Err(Error::Synthesis);
+        }
+        let hash = ethers_core::utils::keccak256(tx_list);
+
+        layouter.assign_region(
+            || "pi tx list",
+            |mut region| {
+                self.assign_tables(&mut region, n_rows)?;
+
+                // Hash rows
+                let mut hash_parts = [0u128; 2];
+                let mut hash_rlc = Value::known(F::zero());
+                let mut hash_cells = Vec::new();
+                for (offset, byte) in hash.iter().enumerate() {
+                    hash_parts[offset / 16] = hash_parts[offset / 16] * 256 + *byte as u128;
+                    hash_rlc =
+                        hash_rlc * challenges.evm_word() + Value::known(F::from(*byte as u64));
+                    region.assign_advice(
+                        || format!("byte {}", offset),
+                        self.byte,
+                        offset,
+                        || Value::known(F::from(*byte as u64)),
+                    )?;
+                    region.assign_advice(
+                        || format!("hash_rlc {}", offset),
+                        self.hash_rlc,
+                        offset,
+                        || hash_rlc,
+                    )?;
+                    hash_cells = [("hash_hi", self.hash_hi), ("hash_lo", self.hash_lo)]
+                        .into_iter()
+                        .zip(hash_parts)
+                        .map(|((name, column), part)| {
+                            region.assign_advice(
+                                || format!("{} {}", name, offset),
+                                column,
+                                offset,
+                                || Value::known(F::from_u128(part)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    self.assign_row(
+                        &mut region,
+                        offset,
+                        &TxListRow::default(),
+                        chain_id,
+                        TxListAccumulators::default(),
+                    )?;
+                }
+
+                // Data rows: the bytes of the tx list, followed by padding
+                let mut acc = TxListAccumulators::default();
+                let mut prev = TxListRow::default();
+                let mut chain_id_cell = None;
+                for (index, row) in rows.iter().enumerate() {
+                    let byte = Value::known(F::from(row.rlp.byte as u64));
+                    acc.input_rlc = acc.input_rlc * challenges.keccak_input() + byte;
+                    acc.hash_rlc = hash_rlc;
+                    if row.is_entry_start {
+                        acc.value_rlc = Value::known(F::zero());
+                        acc.value_len = 0;
+                    }
+                    if row.is_value_byte() {
+                        acc.value_rlc = acc.value_rlc * challenges.keccak_input() + byte;
+                        acc.value_len += 1;
+                    }
+                    acc.field_value = if !row.in_tx || row.rlp.is_header {
+                        F::zero()
+                    } else if row.is_first_data {
+                        F::from(row.rlp.byte as u64)
+                    } else {
+                        acc.field_value * F::from(256) + F::from(row.rlp.byte as u64)
+                    };
+                    acc.calldata_before = prev.calldata_sum;
+                    acc.len = index + 1;
+
+                    let offset = HASH_ROWS + index;
+                    let cell = self.assign_row(&mut region, offset, row, chain_id, acc)?;
+                    chain_id_cell.get_or_insert(cell);
+                    prev = *row;
+                }
+                for offset in HASH_ROWS + rows.len()..n_rows {
+                    self.assign_row(
+                        &mut region,
+                        offset,
+                        &TxListRow::default(),
+                        chain_id,
+                        TxListAccumulators {
+                            is_padding: true,
+                            ..Default::default()
+                        },
+                    )?;
+                }
+
+                let [hash_hi, hash_lo]: [AssignedCell<F, F>; 2] = hash_cells
+                    .try_into()
+                    .expect("the region starts with the hash rows");
+                Ok(TxListCells {
+                    hash_hi,
+                    hash_lo,
+                    chain_id: chain_id_cell.expect("the tx list has a header"),
+                })
+            },
+        )
+    }
+
+    /// Assign the fixed columns, including the comparison and field tables.
+    fn assign_tables(&self, region: &mut Region<'_, F>, n_rows: usize) -> Result<(), Error> {
+        let field_table: Vec<_> = (0..3)
+            .flat_map(|tx_type| {
+                let fields = TxField::fields(tx_type);
+                fields.iter().enumerate().map(move |(index, field)| {
+                    let mut row = vec![tx_type, (fields.len() - index) as u64, field.cmp_key()];
+                    row.extend(field.flags().map(|flag| flag as u64));
+                    row
+                })
+            })
+            .collect();
+        for offset in 0..n_rows {
+            let data_offset = offset.checked_sub(HASH_ROWS);
+            for (name, column, value) in [
+                ("q_enable", self.q_enable, true),
+                ("q_first", self.q_first, offset == 0),
+                ("q_hash", self.q_hash, offset < HASH_ROWS),
+                ("q_hash_hi", self.q_hash_hi, offset < HASH_ROWS / 2),
+                ("q_data", self.q_data, data_offset.is_some()),
+                ("q_data_first", self.q_data_first, data_offset == Some(0)),
+                ("q_last", self.q_last, offset == n_rows - 1),
+                (
+                    "q_cmp_table",
+                    self.q_cmp_table,
+                    offset < CMP_KEYS as usize * 256,
+                ),
+                (
+                    "q_field_table",
+                    self.q_field_table,
+                    offset < field_table.len(),
+                ),
+            ] {
+                region.assign_fixed(
+                    || format!("{} {}", name, offset),
+                    column,
+                    offset,
+                    || Value::known(F::from(value as u64)),
+                )?;
+            }
+
+            let (key, byte) = ((offset / 256) as u64, (offset % 256) as u8);
+            let cmp_row = if key < CMP_KEYS {
+                let constant = cmp_constant(key);
+                [
+                    key,
+                    byte as u64,
+                    (byte < constant) as u64,
+                    (byte == constant) as u64,
+                ]
+            } else {
+                [0; 4]
+            };
+            for (column, value) in self.cmp_table.into_iter().zip(cmp_row) {
+                region.assign_fixed(
+                    || format!("cmp_table {}", offset),
+                    column,
+                    offset,
+                    || Value::known(F::from(value)),
+                )?;
+            }
+            let field_row = field_table
+                .get(offset)
+                .cloned()
+                .unwrap_or_else(|| vec![0; 11]);
+            for (column, value) in self.field_table.into_iter().zip(field_row) {
+                region.assign_fixed(
+                    || format!("field_table {}", offset),
+                    column,
+                    offset,
+                    || Value::known(F::from(value)),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Assign the advice columns of a row, except the hash, and return the
+    /// cell of the chain id.
+    fn assign_row(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        row: &TxListRow,
+        chain_id: u64,
+        acc: TxListAccumulators<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let field_flags = row.field.map_or([false; 8], TxField::flags);
+        for (name, column, value) in [
+            ("is_padding", self.is_padding, acc.is_padding as u64),
+            ("len", self.len, acc.len as u64),
+            (
+                "is_list_header",
+                self.is_list_header,
+                row.is_list_header as u64,
+            ),
+            ("remaining", self.remaining, row.remaining),
+            (
+                "is_entry_start",
+                self.is_entry_start,
+                row.is_entry_start as u64,
+            ),
+            (
+                "is_entry_item",
+                self.is_entry_item,
+                row.is_entry_item as u64,
+            ),
+            ("is_entry_end", self.is_entry_end, row.is_entry_end as u64),
+            ("entry_remaining", self.entry_remaining, row.entry_remaining),
+            (
+                "is_list_entry",
+                self.is_list_entry,
+                row.is_list_entry as u64,
+            ),
+            (
+                "is_string_entry",
+                self.is_string_entry,
+                row.is_string_entry as u64,
+            ),
+            ("is_type_item", self.is_type_item, row.is_type_item as u64),
+            ("type_ok", self.type_ok, row.type_ok as u64),
+            ("tx_type", self.tx_type, row.tx_type),
+            ("is_tx_header", self.is_tx_header, row.is_tx_header as u64),
+            ("in_tx", self.in_tx, row.in_tx as u64),
+            (
+                "is_first_payload",
+                self.is_first_payload,
+                row.is_first_payload as u64,
+            ),
+            (
+                "is_first_data",
+                self.is_first_data,
+                row.is_first_data as u64,
+            ),
+            ("is_len_start", self.is_len_start, row.is_len_start as u64),
+            (
+                "is_field_start",
+                self.is_field_start,
+                row.is_field_start as u64,
+            ),
+            ("is_field_end", self.is_field_end, row.is_field_end as u64),
+            ("fields_left", self.fields_left, row.fields_left),
+            ("cmp_key", self.cmp_key, row.cmp_key),
+            ("cmp_lt", self.cmp_lt, row.cmp_lt as u64),
+            ("cmp_eq", self.cmp_eq, row.cmp_eq as u64),
+            ("size_ok", self.size_ok, row.size_ok as u64),
+            ("sig_lt", self.sig_lt, row.sig_lt as u64),
+            ("sig_eq", self.sig_eq, row.sig_eq as u64),
+            ("num_bad", self.num_bad, row.num_bad),
+            ("data_len", self.data_len, row.data_len),
+            ("calldata_sum", self.calldata_sum, row.calldata_sum),
+            ("is_valid", self.is_valid, row.is_valid as u64),
+            ("tx_id", self.tx_id, row.tx_id),
+            ("value_len", self.value_len, acc.value_len),
+        ]
+        .into_iter()
+        .chain(
+            self.field_flags
+                .into_iter()
+                .zip(field_flags)
+                .map(|(column, flag)| ("field flag", column, flag as u64)),
+        ) {
+            region.assign_advice(
+                || format!("{} {}", name, offset),
+                column,
+                offset,
+                || Value::known(F::from(value)),
+            )?;
+        }
+        self.rlp.assign(region, offset, &row.rlp)?;
+
+        // The checked values, see `check`
+        let chain_id_f = F::from(chain_id);
+        let check = if row.is_tx_header && row.rlp.is_item_end {
+            F::from(row.rlp.length) - F::from(row.entry_remaining)
+        } else if row.is_field_end {
+            let value = acc.field_value;
+            let v = value - chain_id_f.double();
+            match row.field {
+                Some(TxField::ChainId) => value - chain_id_f,
+                Some(TxField::V) => (v - F::from(35)) * (v - F::from(36)),
+                Some(TxField::YParity) => value * (value - F::one()),
+                _ => F::zero(),
+            }
+        } else {
+            F::zero()
+        };
+        for (name, column, value) in [
+            ("field_value", self.field_value, acc.field_value),
+            ("check", self.check, check),
+        ] {
+            region.assign_advice(
+                || format!("{} {}", name, offset),
+                column,
+                offset,
+                || Value::known(value),
+            )?;
+        }
+        for (chip, value) in [
+            (&self.byte_is_zero, row.rlp.byte as u64),
+            (&self.entry_remaining_is_zero, row.entry_remaining),
+            (&self.fields_left_is_zero, row.fields_left),
+            (&self.num_bad_is_zero, row.num_bad),
+        ] {
+            IsZeroChip::construct(chip.clone()).assign(
+                region,
+                offset,
+                Value::known(F::from(value)),
+            )?;
+        }
+        IsZeroChip::construct(self.check_is_zero.clone()).assign(
+            region,
+            offset,
+            Value::known(check),
+        )?;
+        IsZeroChip::construct(self.count_is_max.clone()).assign(
+            region,
+            offset,
+            Value::known(
+                F::from(row.tx_id) - F::from(row.is_valid as u64) - F::from(self.max_txs as u64),
+            ),
+        )?;
+        LtChip::construct(self.calldata_exceeds).assign(
+            region,
+            offset,
+            F::from(self.max_calldata as u64),
+            F::from(acc.calldata_before + row.data_len),
+        )?;
+
+        // The hash rows assign their own byte and accumulators
+        if offset >= HASH_ROWS {
+            region.assign_advice(
+                || format!("byte {}", offset),
+                self.byte,
+                offset,
+                || Value::known(F::from(row.rlp.byte as u64)),
+            )?;
+            for (name, column, value) in [
+                ("hash_hi", self.hash_hi, Value::known(F::zero())),
+                ("hash_lo", self.hash_lo, Value::known(F::zero())),
+                ("hash_rlc", self.hash_rlc, acc.hash_rlc),
+            ] {
+                region.assign_advice(
+                    || format!("{} {}", name, offset),
+                    column,
+                    offset,
+                    || value,
+                )?;
+            }
+        }
+        for (name, column, value) in [
+            ("input_rlc", self.input_rlc, acc.input_rlc),
+            ("value_rlc", self.value_rlc, acc.value_rlc),
+        ] {
+            region.assign_advice(|| format!("{} {}", name, offset), column, offset, || value)?;
+        }
+        region.assign_advice(
+            || format!("chain_id {}", offset),
+            self.chain_id,
+            offset,
+            || Value::known(chain_id_f),
+        )
+    }
+}
+
+/// Values of the advice columns of a row of the tx list region that
+/// accumulate the previous rows
+#[derive(Clone, Copy, Debug)]
+struct TxListAccumulators<F> {
+    is_padding: bool,
+    len: usize,
+    field_value: F,
+    /// Call data of the valid entries before the entry
+    calldata_before: u64,
+    value_len: u64,
+    input_rlc: Value<F>,
+    hash_rlc: Value<F>,
+    value_rlc: Value<F>,
+}
+
+impl<F: Field> Default for TxListAccumulators<F> {
+    fn default() -> Self {
+        Self {
+            is_padding: false,
+            len: 0,
+            field_value: F::zero(),
+            calldata_before: 0,
+            value_len: 0,
+            input_rlc: Value::known(F::zero()),
+            hash_rlc: Value::known(F::zero()),
+            value_rlc: Value::known(F::zero()),
+        }
+    }
+}
@@ -0,0 +1,734 @@
+//! Withdrawals region of the PublicInputs circuit.
+//!
+//! The region proves that the rows of the WithdrawalTable are the values of
+//! the withdrawals tries of the blocks of the batch, whose roots are public
+//! inputs of the circuit.  It
+//! assigns one byte per row: the fields of every withdrawal, in the order of
+//! its RLP encoding `rlp([index, validator_index, address, amount])`, each one
+//! in a fixed number of rows like in the header region.  Integers are right
+//! aligned, preceded by padding rows, and must be minimal.
+//!
+//! The RLP encoding of the fields is accumulated in the payload of the
+//! withdrawal, and at the last row of every withdrawal the encoding of the
+//! withdrawal is the value of the path of its position in its block in the
+//! withdrawals trie of the block, and vice versa.  The position restarts at 1
+//! when the number of the block changes from the previous withdrawal, and the
+//! tries are told apart by their `id_offset`, which is the number of their
+//! block.  The fields of the withdrawal, with its id in the batch and the
+//! number of its block, are also a row of the WithdrawalTable, and vice
+//! versa.
+//!
+//! The withdrawals are preceded by an all-zero row and followed by a padding
+//! withdrawal, which has the next id and zero fields, like in the
+//! WithdrawalTable.  The amount of a withdrawal is never zero in the consensus
+//! layer, which the region checks, so that the EVM circuit can prove that it
+//! did every withdrawal by looking up the padding withdrawal after the last
+//! one.
+
+use crate::{
+    evm_circuit::util::constraint_builder::BaseConstraintBuilder,
+    table::{LookupTable, WithdrawalTable},
+    util::{trie::ListTrieConfig, Challenges},
+};
+use eth_types::{geth_types::Withdrawal, Field};
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{and, not, select, Expr},
+};
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase, VirtualCells,
+    },
+    poly::Rotation,
+};
+use itertools::Itertools;
+
+const MAX_DEGREE: usize = 9;
+
+/// RLP prefix of an address
+const ADDRESS_PREFIX: u64 = 0x94;
+/// RLP prefix of a short list, to which the length of the payload is added
+const SHORT_LIST_PREFIX: u64 = 0xc0;
+
+/// Field of a withdrawal assigned in the region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WithdrawalField {
+    /// Monotonically increasing index of the withdrawal
+    Index,
+    /// Index of the validator
+    ValidatorIndex,
+    /// Recipient of the withdrawal
+    Address,
+    /// Amount of the withdrawal, in Gwei
+    Amount,
+}
+
+impl WithdrawalField {
+    /// Fields of a withdrawal, in the order of its RLP encoding
+    const FIELDS: [Self; 4] = [
+        Self::Index,
+        Self::ValidatorIndex,
+        Self::Address,
+        Self::Amount,
+    ];
+
+    /// Number of rows of the field, which is its maximum length in bytes
+    fn size(&self) -> usize {
+        match self {
+            Self::Address => 20,
+            _ => 8,
+        }
+    }
+
+    fn is_int(&self) -> bool {
+        *self != Self::Address
+    }
+
+    /// Rotation of the last row of the field from the last row of the
+    /// withdrawal.
+    fn end_rotation(&self) -> Rotation {
+        let following: usize = Self::FIELDS
+            .iter()
+            .skip_while(|field| *field != self)
+            .skip(1)
+            .map(|field| field.size())
+            .sum();
+        Rotation(-(following as i32))
+    }
+
+    /// Minimal big endian bytes of the field of `withdrawal`
+    fn bytes(&self, withdrawal: &Withdrawal) -> Vec<u8> {
+        let value = match self {
+            Self::Index => withdrawal.index,
+            Self::ValidatorIndex => withdrawal.validator_index,
+            Self::Address => return withdrawal.address.to_fixed_bytes().to_vec(),
+            Self::Amount => withdrawal.amount,
+        };
+        let bytes = value.as_u64().to_be_bytes();
+        let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+        bytes[leading_zeros..].to_vec()
+    }
+}
+
+/// Number of rows of a withdrawal
+const WITHDRAWAL_ROWS: usize = 44;
+
+/// Number of rows of the withdrawals region with `num_withdrawals`
+/// withdrawals: the all-zero row, the withdrawals and the padding withdrawal.
+pub(crate) fn withdrawals_region_len(num_withdrawals: usize) -> usize {
+    num_withdrawals * WITHDRAWAL_ROWS + 2
+}
+
+/// Config of the withdrawals region of the PublicInputs circuit
+#[derive(Clone, Debug)]
+pub(crate) struct WithdrawalsConfig<F> {
+    q_enable: Column<Fixed>,
+    /// Rows of the fields of the withdrawals
+    q_field: Column<Fixed>,
+    is_field_start: Column<Fixed>,
+    is_field_end: Column<Fixed>,
+    /// Rows of the integer fields
+    q_int: Column<Fixed>,
+    /// Rows of the index, the first field of a withdrawal
+    q_first_field: Column<Fixed>,
+    /// RLP prefix of the fields: 0x80 for integers, to which their length is
+    /// added, and 0x94 for the address
+    prefix: Column<Fixed>,
+    /// Last row of every withdrawal
+    q_end: Column<Fixed>,
+    /// Id of the withdrawal in the batch, starting at 1, in the last row of
+    /// every withdrawal and in the row of the padding withdrawal
+    id: Column<Fixed>,
+
+    /// Number of the block of the withdrawal
+    block_number: Column<Advice>,
+    /// Position of the withdrawal in its block, starting at 1
+    id_in_block: Column<Advice>,
+
+    byte: Column<Advice>,
+    byte_inv: Column<Advice>,
+    is_pad: Column<Advice>,
+    is_single: Column<Advice>,
+    len: Column<Advice>,
+    value_num: Column<Advice>,
+    value_rlc: Column<Advice>,
+    pow_keccak: Column<Advice>,
+    payload_rlc: Column<Advice>,
+    payload_pow: Column<Advice>,
+    payload_len: Column<Advice>,
+
+    len_is_one: IsZeroConfig<F>,
+    /// Whether the withdrawal is in the same block as the previous one, at
+    /// the last row of every withdrawal
+    is_same_block: IsZeroConfig<F>,
+}
+
+impl<F: Field> WithdrawalsConfig<F> {
+    /// Configure the withdrawals region, whose withdrawals are the values of
+    /// the tries of `trie` and the rows of `withdrawal_table`.  The bytes are
+    /// range checked with `u8_table`.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        u8_table: Column<Fixed>,
+        trie: &ListTrieConfig<F>,
+        withdrawal_table: &WithdrawalTable,
+        challenges: &Challenges<Expression<F>>,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_field = meta.fixed_column();
+        let is_field_start = meta.fixed_column();
+        let is_field_end = meta.fixed_column();
+        let q_int = meta.fixed_column();
+        let q_first_field = meta.fixed_column();
+        let prefix = meta.fixed_column();
+        let q_end = meta.fixed_column();
+        let id = meta.fixed_column();
+
+        let byte = meta.advice_column();
+        let byte_inv = meta.advice_column();
+        let is_pad = meta.advice_column();
+        let is_single = meta.advice_column();
+        let len = meta.advice_column();
+        let value_num = meta.advice_column();
+        let value_rlc = meta.advice_column_in(SecondPhase);
+        let pow_keccak = meta.advice_column_in(SecondPhase);
+        let payload_rlc = meta.advice_column_in(SecondPhase);
+        let payload_pow = meta.advice_column_in(SecondPhase);
+        let payload_len = meta.advice_column();
+        let block_number = meta.advice_column();
+        let id_in_block = meta.advice_column();
+
+        let len_inv = meta.advice_column();
+        let len_is_one = IsZeroChip::configure(
+            meta,
+            |meta| {
+                meta.query_fixed(q_int, Rotation::cur())
+                    * meta.query_fixed(is_field_end, Rotation::cur())
+            },
+            |meta| meta.query_advice(len, Rotation::cur()) - 1.expr(),
+            len_inv,
+        );
+        // The last row of the previous withdrawal, or the all-zero row for the
+        // first one
+        let prev_end = Rotation(-(WITHDRAWAL_ROWS as i32));
+        let block_number_diff_inv = meta.advice_column();
+        let is_same_block = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_fixed(q_end, Rotation::cur()),
+            |meta| {
+                meta.query_advice(block_number, Rotation::cur())
+                    - meta.query_advice(block_number, prev_end)
+            },
+            block_number_diff_inv,
+        );
+
+        let keccak_input = challenges.keccak_input();
+
+        meta.create_gate("withdrawal field bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_field_start = meta.query_fixed(is_field_start, Rotation::cur());
+            let q_int = meta.query_fixed(q_int, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_pad_prev = meta.query_advice(is_pad, Rotation::prev());
+            let is_pad = meta.query_advice(is_pad, Rotation::cur());
+            // The accumulators of a field start from zero at its first row.
+            let mut prev = |column| {
+                not::expr(is_field_start.clone()) * meta.query_advice(column, Rotation::prev())
+            };
+            let len_prev = prev(len);
+            let value_num_prev = prev(value_num);
+            let value_rlc_prev = prev(value_rlc);
+            let pow_keccak_prev = prev(pow_keccak);
+
+            cb.require_boolean("is_pad is boolean", is_pad.clone());
+            cb.require_boolean(
+                "is_single is boolean",
+                meta.query_advice(is_single, Rotation::cur()),
+            );
+            cb.require_zero("padding bytes are zero", is_pad.clone() * byte.clone());
+            cb.condition(not::expr(q_int.clone()), |cb| {
+                cb.require_zero("only integers are padded", is_pad.clone());
+            });
+            cb.condition(
+                and::expr([
+                    not::expr(is_field_start.clone()),
+                    not::expr(is_pad_prev.clone()),
+                ]),
+                |cb| {
+                    cb.require_zero("padding precedes the bytes of a field", is_pad.clone());
+                },
+            );
+            cb.condition(
+                q_int
+                    * not::expr(is_pad.clone())
+                    * select::expr(is_field_start.clone(), 1.expr(), is_pad_prev),
+                |cb| {
+                    cb.require_equal(
+                        "integers have no leading zeros",
+                        byte.clone() * meta.query_advice(byte_inv, Rotation::cur()),
+                        1.expr(),
+                    );
+                },
+            );
+
+            cb.require_equal(
+                "len counts the bytes of the field that are not padding",
+                meta.query_advice(len, Rotation::cur()),
+                len_prev + not::expr(is_pad.clone()),
+            );
+            cb.require_equal(
+                "value_num = value_num_prev * 256 + byte",
+                meta.query_advice(value_num, Rotation::cur()),
+                value_num_prev * 256.expr() + byte.clone(),
+            );
+            cb.require_equal(
+                "value_rlc = value_rlc_prev * keccak_input + byte",
+                meta.query_advice(value_rlc, Rotation::cur()),
+                value_rlc_prev * keccak_input.clone() + byte,
+            );
+            cb.require_equal(
+                "pow_keccak = keccak_input^len",
+                meta.query_advice(pow_keccak, Rotation::cur()),
+                select::expr(is_field_start, 1.expr(), pow_keccak_prev)
+                    * select::expr(is_pad, 1.expr(), keccak_input.clone()),
+            );
+
+            cb.gate(meta.query_fixed(q_field, Rotation::cur()))
+        });
+
+        meta.create_gate("withdrawal payload", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_field_end = meta.query_fixed(is_field_end, Rotation::cur());
+            let q_int = meta.query_fixed(q_int, Rotation::cur());
+            let q_first_field = meta.query_fixed(q_first_field, Rotation::cur());
+            let is_single = meta.query_advice(is_single, Rotation::cur());
+            let len = meta.query_advice(len, Rotation::cur());
+            let payload_rlc_cur = meta.query_advice(payload_rlc, Rotation::cur());
+            let payload_pow_cur = meta.query_advice(payload_pow, Rotation::cur());
+            let payload_len_cur = meta.query_advice(payload_len, Rotation::cur());
+            let payload_rlc_prev = meta.query_advice(payload_rlc, Rotation::prev());
+            let payload_pow_prev = meta.query_advice(payload_pow, Rotation::prev());
+            let payload_len_prev = meta.query_advice(payload_len, Rotation::prev());
+
+            cb.condition(
+                and::expr([
+                    not::expr(q_first_field.clone()),
+                    not::expr(is_field_end.clone()),
+                ]),
+                |cb| {
+                    cb.require_equal(
+                        "payload_rlc is unchanged",
+                        payload_rlc_cur.clone(),
+                        payload_rlc_prev.clone(),
+                    );
+                    cb.require_equal(
+                        "payload_pow is unchanged",
+                        payload_pow_cur.clone(),
+                        payload_pow_prev.clone(),
+                    );
+                    cb.require_equal(
+                        "payload_len is unchanged",
+                        payload_len_cur.clone(),
+                        payload_len_prev.clone(),
+                    );
+                },
+            );
+            cb.condition(q_int.clone() * is_field_end.clone(), |cb| {
+                cb.require_zero(
+                    "a single byte is an integer of length 1",
+                    is_single.clone() * (len.clone() - 1.expr()),
+                );
+            });
+            cb.condition(is_field_end, |cb| {
+                // The RLP prefix is 0x94 for the address, and 0x80 + len for
+                // integers, unless they are a single byte below 0x80.  The
+                // payload of a withdrawal starts at its first field.
+                let has_prefix = not::expr(q_int.clone() * is_single);
+                let prefix_pow = 1.expr() + has_prefix.clone() * (keccak_input.clone() - 1.expr());
+                let prefix_rlc =
+                    has_prefix.clone() * (meta.query_fixed(prefix, Rotation::cur()) + q_int * len.clone());
+                let payload_rlc_prev = not::expr(q_first_field.clone()) * payload_rlc_prev;
+                let payload_pow_prev = select::expr(q_first_field.clone(), 1.expr(), payload_pow_prev);
+                let payload_len_prev = not::expr(q_first_field) * payload_len_prev;
+                let pow_keccak = meta.query_advice(pow_keccak, Rotation::cur());
+
+                cb.require_equal(
+                    "payload_rlc = payload_rlc_prev * keccak_input^(prefix_len + len) + rlc(prefix || field)",
+                    payload_rlc_cur,
+                    payload_rlc_prev * prefix_pow.clone() * pow_keccak.clone()
+                        + prefix_rlc * pow_keccak.clone()
+                        + meta.query_advice(value_rlc, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "payload_pow = payload_pow_prev * keccak_input^(prefix_len + len)",
+                    payload_pow_cur,
+                    payload_pow_prev * prefix_pow * pow_keccak,
+                );
+                cb.require_equal(
+                    "payload_len = payload_len_prev + prefix_len + len",
+                    payload_len_cur,
+                    payload_len_prev + has_prefix + len,
+                );
+            });
+            cb.condition(meta.query_fixed(q_end, Rotation::cur()), |cb| {
+                // The amount is the last field, and it's minimal.
+                cb.require_zero(
+                    "the amount of a withdrawal is not zero",
+                    meta.query_advice(is_pad, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "id_in_block = is_same_block ? id_in_block_prev + 1 : 1",
+                    meta.query_advice(id_in_block, Rotation::cur()),
+                    is_same_block.expr() * meta.query_advice(id_in_block, prev_end) + 1.expr(),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_field, Rotation::cur()))
+        });
+
+        meta.lookup_any("withdrawal bytes are in u8 range", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            vec![(
+                q_enable * meta.query_advice(byte, Rotation::cur()),
+                meta.query_fixed(u8_table, Rotation::cur()),
+            )]
+        });
+
+        meta.lookup_any("single byte integers are below 0x80", |meta| {
+            // An integer is a single byte iff it has length 1 and its byte is
+            // below 0x80: 2 * byte must be a byte when it's single, and
+            // byte - 0x80 when it's not but its length is 1.
+            let enable = meta.query_fixed(q_int, Rotation::cur())
+                * meta.query_fixed(is_field_end, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_single = meta.query_advice(is_single, Rotation::cur());
+            vec![(
+                enable
+                    * (is_single.clone() * 2.expr() * byte.clone()
+                        + not::expr(is_single) * len_is_one.expr() * (byte - 0x80.expr())),
+                meta.query_fixed(u8_table, Rotation::cur()),
+            )]
+        });
+
+        // The encoding of a withdrawal is rlp([index, validator_index,
+        // address, amount]), whose payload is shorter than 56 bytes.
+        let withdrawal_value = |meta: &mut VirtualCells<'_, F>| {
+            let q_end = meta.query_fixed(q_end, Rotation::cur());
+            let payload_len = meta.query_advice(payload_len, Rotation::cur());
+            let value_rlc = (SHORT_LIST_PREFIX.expr() + payload_len.clone())
+                * meta.query_advice(payload_pow, Rotation::cur())
+                + meta.query_advice(payload_rlc, Rotation::cur());
+            [
+                meta.query_advice(block_number, Rotation::cur()),
+                meta.query_advice(id_in_block, Rotation::cur()),
+                1.expr() + payload_len,
+                value_rlc,
+            ]
+            .map(|expr| q_end.clone() * expr)
+        };
+        let trie_value = |meta: &mut VirtualCells<'_, F>| {
+            let path_end = trie.path_end(meta);
+            [
+                meta.query_advice(trie.id_offset, Rotation::cur()),
+                meta.query_advice(trie.id, Rotation::cur()),
+                meta.query_advice(trie.value_len, Rotation::cur()),
+                meta.query_advice(trie.value_rlc, Rotation::cur()),
+            ]
+            .map(|expr| path_end.clone() * expr)
+        };
+        meta.lookup_any(
+            "the withdrawals are values of the withdrawals tries",
+            |meta| {
+                withdrawal_value(meta)
+                    .into_iter()
+                    .zip(trie_value(meta))
+                    .collect()
+            },
+        );
+        meta.lookup_any(
+            "the values of the withdrawals tries are withdrawals",
+            |meta| {
+                trie_value(meta)
+                    .into_iter()
+                    .zip(withdrawal_value(meta))
+                    .collect()
+            },
+        );
+
+        // The rows of the region in the layout of the WithdrawalTable, which
+        // are all zero but at the end of the withdrawals and at the padding
+        // withdrawal.
+        let withdrawal_row = |meta: &mut VirtualCells<'_, F>| {
+            let q_end = meta.query_fixed(q_end, Rotation::cur());
+            [
+                meta.query_fixed(id, Rotation::cur()),
+                q_end.clone() * meta.query_advice(block_number, Rotation::cur()),
+            ]
+            .into_iter()
+            .chain(
+                [
+                    WithdrawalField::ValidatorIndex,
+                    WithdrawalField::Address,
+                    WithdrawalField::Amount,
+                ]
+                .map(|field| q_end.clone() * meta.query_advice(value_num, field.end_rotation())),
+            )
+            .collect::<Vec<_>>()
+        };
+        meta.lookup_any("the withdrawals are rows of the WithdrawalTable", |meta| {
+            withdrawal_row(meta)
+                .into_iter()
+                .zip(withdrawal_table.table_exprs(meta))
+                .collect()
+        });
+        meta.lookup_any("the rows of the WithdrawalTable are withdrawals", |meta| {
+            withdrawal_table
+                .table_exprs(meta)
+                .into_iter()
+                .zip(withdrawal_row(meta))
+                .collect()
+        });
+
+        Self {
+            q_enable,
+            q_field,
+            is_field_start,
+            is_field_end,
+            q_int,
+            q_first_field,
+            prefix,
+            q_end,
+            id,
+            byte,
+            byte_inv,
+            is_pad,
+            is_single,
+            len,
+            value_num,
+            value_rlc,
+            pow_keccak,
+            payload_rlc,
+            payload_pow,
+            payload_len,
+            block_number,
+            id_in_block,
+            len_is_one,
+            is_same_block,
+        }
+    }
+
+    /// Assign the withdrawals region with the withdrawals of a batch and the
+    /// numbers of their blocks.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        withdrawals: &[Withdrawal],
+        withdrawal_blocks: &[u64],
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        let keccak_input = challenges.keccak_input();
+        let len_is_one_chip = IsZeroChip::construct(self.len_is_one.clone());
+        let is_same_block_chip = IsZeroChip::construct(self.is_same_block.clone());
+
+        layouter.assign_region(
+            || "pi withdrawals",
+            |mut region| {
+                let region_len = withdrawals_region_len(withdrawals.len());
+                // The all-zero row and the padding withdrawal only have an id.
+                for (offset, id) in [(0, 0), (region_len - 1, withdrawals.len() + 1)] {
+                    region.assign_fixed(
+                        || "q_enable",
+                        self.q_enable,
+                        offset,
+                        || Value::known(F::one()),
+                    )?;
+                    region.assign_fixed(
+                        || "id",
+                        self.id,
+                        offset,
+                        || Value::known(F::from(id as u64)),
+                    )?;
+                    for column in [
+                        self.byte,
+                        self.byte_inv,
+                        self.is_pad,
+                        self.is_single,
+                        self.len,
+                        self.value_num,
+                        self.value_rlc,
+                        self.pow_keccak,
+                        self.payload_rlc,
+                        self.payload_pow,
+                        self.payload_len,
+                        self.block_number,
+                        self.id_in_block,
+                    ] {
+                        region.assign_advice(
+                            || "withdrawal padding",
+                            column,
+                            offset,
+                            || Value::known(F::zero()),
+                        )?;
+                    }
+                    len_is_one_chip.assign(&mut region, offset, Value::known(-F::one()))?;
+                }
+
+                let mut offset = 1;
+                let mut block_number_prev = 0;
+                let mut id_in_block = 0;
+                for (index, (withdrawal, block_number)) in
+                    withdrawals.iter().zip_eq(withdrawal_blocks).enumerate()
+                {
+                    let is_same_block = *block_number == block_number_prev;
+                    id_in_block = if is_same_block { id_in_block + 1 } else { 1 };
+                    let block_number_diff = F::from(*block_number) - F::from(block_number_prev);
+                    block_number_prev = *block_number;
+                    let mut payload_rlc = Value::known(F::zero());
+                    let mut payload_pow = Value::known(F::one());
+                    let mut payload_len = 0u64;
+
+                    for field in WithdrawalField::FIELDS {
+                        let size = field.size();
+                        let bytes = field.bytes(withdrawal);
+                        let pad = size - bytes.len();
+
+                        let mut len = 0u64;
+                        let mut value_num = F::zero();
+                        let mut value_rlc = Value::known(F::zero());
+                        let mut pow_keccak = Value::known(F::one());
+
+                        for index_in_field in 0..size {
+                            let is_start = index_in_field == 0;
+                            let is_end = index_in_field == size - 1;
+                            let is_pad = index_in_field < pad;
+                            let byte = if is_pad {
+                                0
+                            } else {
+                                bytes[index_in_field - pad]
+                            };
+                            let byte_f = F::from(byte as u64);
+
+                            len += !is_pad as u64;
+                            value_num = value_num * F::from(256) + byte_f;
+                            value_rlc = value_rlc * keccak_input + Value::known(byte_f);
+                            if !is_pad {
+                                pow_keccak = pow_keccak * keccak_input;
+                            }
+
+                            let is_single = field.is_int() && len == 1 && byte < 0x80;
+                            if is_end {
+                                let (prefix_pow, prefix_rlc, prefix_len) = if is_single {
+                                    (Value::known(F::one()), Value::known(F::zero()), 0)
+                                } else if field.is_int() {
+                                    (keccak_input, Value::known(F::from(0x80 + len)), 1)
+                                } else {
+                                    (keccak_input, Value::known(F::from(ADDRESS_PREFIX)), 1)
+                                };
+                                payload_rlc = payload_rlc * prefix_pow * pow_keccak
+                                    + prefix_rlc * pow_keccak
+                                    + value_rlc;
+                                payload_pow = payload_pow * prefix_pow * pow_keccak;
+                                payload_len += prefix_len + len;
+                            }
+                            let is_withdrawal_end = is_end && field == WithdrawalField::Amount;
+
+                            for (name, column, value) in [
+                                ("q_enable", self.q_enable, F::one()),
+                                ("q_field", self.q_field, F::one()),
+                                (
+                                    "is_field_start",
+                                    self.is_field_start,
+                                    F::from(is_start as u64),
+                                ),
+                                ("is_field_end", self.is_field_end, F::from(is_end as u64)),
+                                ("q_int", self.q_int, F::from(field.is_int() as u64)),
+                                (
+                                    "q_first_field",
+                                    self.q_first_field,
+                                    F::from((field == WithdrawalField::Index) as u64),
+                                ),
+                                (
+                                    "prefix",
+                                    self.prefix,
+                                    F::from(if field.is_int() { 0x80 } else { ADDRESS_PREFIX }),
+                                ),
+                                ("q_end", self.q_end, F::from(is_withdrawal_end as u64)),
+                                (
+                                    "id",
+                                    self.id,
+                                    F::from(if is_withdrawal_end {
+                                        index as u64 + 1
+                                    } else {
+                                        0
+                                    }),
+                                ),
+                            ] {
+                                region.assign_fixed(
+                                    || name,
+                                    column,
+                                    offset,
+                                    || Value::known(value),
+                                )?;
+                            }
+                            for (name, column, value) in [
+                                ("byte", self.byte, Value::known(byte_f)),
+                                (
+                                    "byte_inv",
+                                    self.byte_inv,
+                                    Value::known(byte_f.invert().unwrap_or(F::zero())),
+                                ),
+                                ("is_pad", self.is_pad, Value::known(F::from(is_pad as u64))),
+                                (
+                                    "is_single",
+                                    self.is_single,
+                                    Value::known(F::from(is_single as u64)),
+                                ),
+                                ("len", self.len, Value::known(F::from(len))),
+                                ("value_num", self.value_num, Value::known(value_num)),
+                                ("value_rlc", self.value_rlc, value_rlc),
+                                ("pow_keccak", self.pow_keccak, pow_keccak),
+                                ("payload_rlc", self.payload_rlc, payload_rlc),
+                                ("payload_pow", self.payload_pow, payload_pow),
+                                (
+                                    "payload_len",
+                                    self.payload_len,
+                                    Value::known(F::from(payload_len)),
+                                ),
+                                (
+                                    "block_number",
+                                    self.block_number,
+                                    Value::known(F::from(*block_number)),
+                                ),
+                                (
+                                    "id_in_block",
+                                    self.id_in_block,
+                                    Value::known(F::from(id_in_block)),
+                                ),
+                            ] {
+                                region.assign_advice(|| name, column, offset, || value)?;
+                            }
+                            if is_withdrawal_end {
+                                is_same_block_chip.assign(
+                                    &mut region,
+                                    offset,
+                                    Value::known(block_number_diff),
+                                )?;
+                            }
+                            len_is_one_chip.assign(
+                                &mut region,
+                                offset,
+                                Value::known(F::from(len) - F::one()),
+                            )?;
+                            offset += 1;
+                        }
+                    }
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
@@ -0,0 +1,2626 @@
+//! The receipt circuit proves the receipts root and the logs bloom of a block
+//! from the TxReceipt and TxLog rows of the RwTable, so that the inclusion of
+//! a log in the block can be verified against a proven root.
+//!
+//! The circuit is made of four independent sets of columns, each one assigned
+//! in its own region:
+//!
+//! - Receipt rows: one byte per row, with the encoding of every receipt (`type
+//!   || rlp([status, cumulative_gas_used, bloom, logs])`, see
+//!   [`Receipt::rlp`]).  The items are decoded like in the RLP circuit: the
+//!   header of every item determines its length, the items follow each other in
+//!   the order of the fields of a receipt and of its logs, and every list ends
+//!   with the last byte of its payload.  The status, the cumulative gas, the
+//!   log addresses, topics and data are looked up in the RwTable.  The number
+//!   of logs of every receipt, and the numbers of topics and data bytes of
+//!   every log are checked against their `LogLength`, `TopicLength` and
+//!   `DataLength` rows, so that all the TxLog rows of the RwTable are in the
+//!   receipts.  The type byte of typed receipts and the block number are looked
+//!   up in the TxTable.  The receipts are the ones of the txs 1, 2, ... and the
+//!   tx after the last receipt is a padding tx of the TxTable, so that every tx
+//!   has a receipt.
+//! - Bloom entries: three rows per log address or topic of the receipts, with
+//!   the first 6 bytes of its hash, looked up in the keccak table.  Every pair
+//!   of hash bytes selects the bit index `(hi & 7) * 256 + lo` of the bloom.
+//! - Bloom bits: a fixed layout of 2048 rows, one per bit in descending order
+//!   of index, for the logs bloom of the block followed by the bloom of every
+//!   transaction.  A bit is set if and only if its index is selected by an
+//!   entry of the transaction (of any transaction, for the block bloom).  The
+//!   bits are accumulated in bytes, which are the bloom bytes of the receipt
+//!   rows, and in 128 bit limbs, which are the logs bloom public inputs.  The
+//!   bytes of the block bloom are also accumulated in an RLC with the EVM word
+//!   challenge, the encoding of the header fields in the PublicInputs circuit.
+//! - Receipts trie: the rows of the trie of the receipt encodings (see
+//!   [`crate::util::trie`]), in the order of the transactions.  The value at
+//!   the end of every path is the encoding of the receipt with the same tx id
+//!   in the receipt rows, and every receipt has a path.
+//!
+//! The public inputs are the number of the block, the receipts root, split in
+//! hi/lo 128 bit halves, followed by the 16 limbs of the logs bloom, most
+//! significant first.
+//!
+//! In a composition with the PublicInputs circuit, the block number, the
+//! receipts root and the logs bloom are copied to the cells of the header of
+//! the block.
+//!
+//! Since every tx of the TxTable has a receipt in the block, the circuit
+//! proves the receipts of a single whole block.  A batch of several blocks
+//! can't be proved, and neither can a chunk of a block, whose RwTable doesn't
+//! have the receipts of the txs of the other chunks, so the circuit refuses
+//! to assign them.
+
+use crate::{
+    evm_circuit::util::{constraint_builder::BaseConstraintBuilder, rlc},
+    table::{
+        KeccakTable, LookupTable, RwTable, RwTableTag, TxFieldTag, TxLogFieldTag,
+        TxReceiptFieldTag, TxTable,
+    },
+    util::{
+        build_tx_log_expression, keccak,
+        rlp::{RlpByteTable, RlpDecoderConfig, RlpDecoderRow},
+        trie::{list_trie_keccak_inputs, list_trie_num_rows, split_root, ListTrieConfig},
+        Challenges, SubCircuit, SubCircuitConfig,
+    },
+    witness::{self, logs_bloom, receipts_trie, Receipt, BLOOM_BYTES},
+};
+use eth_types::{Field, ToBigEndian, ToWord};
+use ethers_core::{types::Bloom, utils::rlp};
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{and, not, select, sum, Expr},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, Instance, SecondPhase,
+        VirtualCells,
+    },
+    poly::Rotation,
+};
+use log::error;
+use std::marker::PhantomData;
+
+#[cfg(any(feature = "test", test))]
+use crate::witness::{Rw, Transaction};
+#[cfg(any(feature = "test", test))]
+use eth_types::{Address, Word};
+#[cfg(any(feature = "test", test))]
+use halo2_proofs::{circuit::SimpleFloorPlanner, plonk::Circuit};
+
+/// Number of bits of a bloom filter, and rows of each bloom in the bloom bits.
+pub const BLOOM_BITS: usize = BLOOM_BYTES * 8;
+
+/// Number of 128 bit limbs of a bloom filter.
+pub const BLOOM_LIMBS: usize = BLOOM_BITS / 128;
+
+/// Number of rows of a bloom entry, one for each bit that it sets.
+const ENTRY_ROWS: usize = 3;
+
+/// Number of items of a receipt encoding, see [`ReceiptItem`].
+const RECEIPT_ITEMS: usize = 11;
+
+const MAX_DEGREE: usize = 9;
+
+/// Columns of the receipt rows
+#[derive(Clone, Debug)]
+struct ReceiptColumns<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_last: Column<Fixed>,
+
+    byte: Column<Advice>,
+    block_number: Column<Advice>,
+    tx_id: Column<Advice>,
+    is_padding: Column<Advice>,
+    is_receipt_start: Column<Advice>,
+    is_receipt_end: Column<Advice>,
+    receipt_len: Column<Advice>,
+    /// Difference between a byte and its lower bound in a canonical encoding
+    canonical_diff: Column<Advice>,
+
+    /// Decoding of the headers of the items, whose containers are the lists
+    rlp: RlpDecoderConfig<F>,
+
+    // Item of the receipt that the byte belongs to
+    is_type: Column<Advice>,
+    is_receipt: Column<Advice>,
+    is_status: Column<Advice>,
+    is_gas: Column<Advice>,
+    is_bloom: Column<Advice>,
+    is_logs: Column<Advice>,
+    is_log: Column<Advice>,
+    is_address: Column<Advice>,
+    is_topics: Column<Advice>,
+    is_topic: Column<Advice>,
+    is_data: Column<Advice>,
+
+    // Bytes of the payload of each list left after the row
+    receipt_remaining: Column<Advice>,
+    logs_remaining: Column<Advice>,
+    log_remaining: Column<Advice>,
+    topics_remaining: Column<Advice>,
+
+    bloom_count: Column<Advice>,
+    log_id: Column<Advice>,
+    topic_count: Column<Advice>,
+    data_count: Column<Advice>,
+    value_acc: Column<Advice>,
+
+    receipt_rlc: Column<Advice>,
+    value_rlc: Column<Advice>,
+    item_rlc: Column<Advice>,
+
+    /// Whether the tx id of the padding rows is the one after the last tx of
+    /// the TxTable
+    is_after_max_txs: IsZeroConfig<F>,
+}
+
+/// Columns of the bloom entries
+#[derive(Clone, Debug)]
+struct EntryColumns {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+
+    tx_id: Column<Advice>,
+    is_entry: Column<Advice>,
+    item_len: Column<Advice>,
+    hash_hi: Column<Advice>,
+    hash_lo: Column<Advice>,
+    hash_hi_quotient: Column<Advice>,
+    hash_hi_remainder: Column<Advice>,
+    bit_index: Column<Advice>,
+
+    item_rlc: Column<Advice>,
+    hash_rest_rlc: Column<Advice>,
+}
+
+/// Columns of the bloom bits
+#[derive(Clone, Debug)]
+struct BitColumns {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_block: Column<Fixed>,
+    tx_id: Column<Fixed>,
+    bit_index: Column<Fixed>,
+    byte_index: Column<Fixed>,
+    q_byte_start: Column<Fixed>,
+    q_byte_end: Column<Fixed>,
+    q_limb_start: Column<Fixed>,
+    q_limb_end: Column<Fixed>,
+
+    bit: Column<Advice>,
+    byte_acc: Column<Advice>,
+    limb_acc: Column<Advice>,
+    /// RLC of the bytes of the block bloom with the EVM word challenge
+    bloom_rlc: Column<Advice>,
+}
+
+impl<F: Field> ReceiptColumns<F> {
+    /// Flag of every item of a receipt
+    fn item_flags(&self) -> [(ReceiptItem, Column<Advice>); RECEIPT_ITEMS] {
+        [
+            (ReceiptItem::TxType, self.is_type),
+            (ReceiptItem::Receipt, self.is_receipt),
+            (ReceiptItem::Status, self.is_status),
+            (ReceiptItem::CumulativeGas, self.is_gas),
+            (ReceiptItem::Bloom, self.is_bloom),
+            (ReceiptItem::Logs, self.is_logs),
+            (ReceiptItem::Log, self.is_log),
+            (ReceiptItem::Address, self.is_address),
+            (ReceiptItem::Topics, self.is_topics),
+            (ReceiptItem::Topic, self.is_topic),
+            (ReceiptItem::Data, self.is_data),
+        ]
+    }
+
+    /// Enabled in the last byte of the items with `flag`
+    fn item_end(&self, meta: &mut VirtualCells<'_, F>, flag: Column<Advice>) -> Expression<F> {
+        and::expr([
+            meta.query_fixed(self.q_enable, Rotation::cur()),
+            meta.query_advice(flag, Rotation::cur()),
+            meta.query_advice(self.rlp.is_item_end, Rotation::cur()),
+        ])
+    }
+
+    /// Enabled in the last byte of a receipt
+    fn receipt_end(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        and::expr([
+            meta.query_fixed(self.q_enable, Rotation::cur()),
+            meta.query_advice(self.is_receipt_end, Rotation::cur()),
+        ])
+    }
+
+    /// Row of a log field in the RwTable
+    fn log_field(
+        &self,
+        meta: &mut VirtualCells<'_, F>,
+        field_tag: TxLogFieldTag,
+        index: Expression<F>,
+        value: Expression<F>,
+    ) -> [Expression<F>; 5] {
+        [
+            RwTableTag::TxLog.expr(),
+            meta.query_advice(self.tx_id, Rotation::cur()),
+            build_tx_log_expression(
+                index,
+                field_tag.expr(),
+                meta.query_advice(self.log_id, Rotation::cur()),
+            ),
+            0.expr(),
+            value,
+        ]
+    }
+
+    /// `[is_item_end, tx_id, item_rlc, item_len]`, where the items are the log
+    /// addresses and topics.
+    fn item(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; 4] {
+        let is_address_end = self.item_end(meta, self.is_address);
+        let is_topic_end = self.item_end(meta, self.is_topic);
+        [
+            is_address_end.clone() + is_topic_end.clone(),
+            meta.query_advice(self.tx_id, Rotation::cur()),
+            meta.query_advice(self.item_rlc, Rotation::cur()),
+            is_address_end * 20.expr() + is_topic_end * 32.expr(),
+        ]
+    }
+}
+
+impl EntryColumns {
+    /// `[is_entry, tx_id, item_rlc, item_len]`, in the first row of each
+    /// entry.
+    fn entry<F: Field>(&self, meta: &mut VirtualCells<'_, F>) -> [Expression<F>; 4] {
+        [
+            meta.query_fixed(self.q_first, Rotation::cur())
+                * meta.query_advice(self.is_entry, Rotation::cur()),
+            meta.query_advice(self.tx_id, Rotation::cur()),
+            meta.query_advice(self.item_rlc, Rotation::cur()),
+            meta.query_advice(self.item_len, Rotation::cur()),
+        ]
+    }
+}
+
+/// Lookup of `[tag, id, address, field_tag, value]` in the RwTable
+fn rw_lookup<F: Field>(
+    meta: &mut VirtualCells<'_, F>,
+    rw_table: &RwTable,
+    enable: Expression<F>,
+    input: [Expression<F>; 5],
+) -> Vec<(Expression<F>, Expression<F>)> {
+    let table = [
+        rw_table.tag,
+        rw_table.id,
+        rw_table.address,
+        rw_table.field_tag,
+        rw_table.value,
+    ];
+    input
+        .into_iter()
+        .zip(table)
+        .map(|(input, column)| {
+            (
+                enable.clone() * input,
+                meta.query_advice(column, Rotation::cur()),
+            )
+        })
+        .collect()
+}
+
+/// Lookup of `[tx_id, tag, index, value]` in the TxTable
+fn tx_lookup<F: Field>(
+    meta: &mut VirtualCells<'_, F>,
+    tx_table: &TxTable,
+    enable: Expression<F>,
+    input: [Expression<F>; 4],
+) -> Vec<(Expression<F>, Expression<F>)> {
+    input
+        .into_iter()
+        .zip(tx_table.table_exprs(meta))
+        .map(|(input, table)| (enable.clone() * input, table))
+        .collect()
+}
+
+/// Config for ReceiptCircuit
+#[derive(Clone, Debug)]
+pub struct ReceiptCircuitConfig<F> {
+    byte_table: RlpByteTable,
+    receipts: ReceiptColumns<F>,
+    entries: EntryColumns,
+    bits: BitColumns,
+    trie: ListTrieConfig<F>,
+    instance: Column<Instance>,
+    max_txs: usize,
+
+    /// RW table
+    pub rw_table: RwTable,
+    /// TX table
+    pub tx_table: TxTable,
+    /// Keccak table
+    pub keccak_table: KeccakTable,
+    _marker: PhantomData<F>,
+}
+
+/// Circuit configuration arguments
+pub struct ReceiptCircuitConfigArgs<F: Field> {
+    /// RwTable
+    pub rw_table: RwTable,
+    /// TxTable
+    pub tx_table: TxTable,
+    /// KeccakTable
+    pub keccak_table: KeccakTable,
+    /// Max number of txs of the TxTable
+    pub max_txs: usize,
+    /// Challenges
+    pub challenges: Challenges<Expression<F>>,
+}
+
+impl<F: Field> SubCircuitConfig<F> for ReceiptCircuitConfig<F> {
+    type ConfigArgs = ReceiptCircuitConfigArgs<F>;
+
+    /// Return a new ReceiptCircuitConfig
+    fn new(
+        meta: &mut ConstraintSystem<F>,
+        Self::ConfigArgs {
+            rw_table,
+            tx_table,
+            keccak_table,
+            max_txs,
+            challenges,
+        }: Self::ConfigArgs,
+    ) -> Self {
+        let byte_table = RlpByteTable::configure(meta);
+
+        let q_enable = meta.fixed_column();
+        let byte = meta.advice_column();
+        let tx_id = meta.advice_column();
+        let is_padding = meta.advice_column();
+        let is_receipt = meta.advice_column();
+        let is_logs = meta.advice_column();
+        let is_log = meta.advice_column();
+        let is_topics = meta.advice_column();
+        let list_flags = [is_receipt, is_logs, is_log, is_topics];
+        let rlp = RlpDecoderConfig::configure(
+            meta,
+            |meta| {
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * not::expr(meta.query_advice(is_padding, Rotation::cur()))
+            },
+            byte,
+            &byte_table,
+            |meta| sum::expr(list_flags.map(|column| meta.query_advice(column, Rotation::cur()))),
+        );
+        let tx_id_inv = meta.advice_column();
+        let is_after_max_txs = IsZeroChip::configure(
+            meta,
+            |meta| {
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * meta.query_advice(is_padding, Rotation::cur())
+            },
+            |meta| meta.query_advice(tx_id, Rotation::cur()) - (max_txs + 1).expr(),
+            tx_id_inv,
+        );
+
+        let receipts = ReceiptColumns {
+            q_enable,
+            q_first: meta.fixed_column(),
+            q_last: meta.fixed_column(),
+            byte,
+            block_number: meta.advice_column(),
+            tx_id,
+            is_padding,
+            is_receipt_start: meta.advice_column(),
+            is_receipt_end: meta.advice_column(),
+            receipt_len: meta.advice_column(),
+            canonical_diff: meta.advice_column(),
+            rlp,
+            is_type: meta.advice_column(),
+            is_receipt,
+            is_status: meta.advice_column(),
+            is_gas: meta.advice_column(),
+            is_bloom: meta.advice_column(),
+            is_logs,
+            is_log,
+            is_address: meta.advice_column(),
+            is_topics,
+            is_topic: meta.advice_column(),
+            is_data: meta.advice_column(),
+            receipt_remaining: meta.advice_column(),
+            logs_remaining: meta.advice_column(),
+            log_remaining: meta.advice_column(),
+            topics_remaining: meta.advice_column(),
+            bloom_count: meta.advice_column(),
+            log_id: meta.advice_column(),
+            topic_count: meta.advice_column(),
+            data_count: meta.advice_column(),
+            value_acc: meta.advice_column(),
+            receipt_rlc: meta.advice_column_in(SecondPhase),
+            value_rlc: meta.advice_column_in(SecondPhase),
+            item_rlc: meta.advice_column_in(SecondPhase),
+            is_after_max_txs,
+        };
+        let entries = EntryColumns {
+            q_enable: meta.fixed_column(),
+            q_first: meta.fixed_column(),
+            tx_id: meta.advice_column(),
+            is_entry: meta.advice_column(),
+            item_len: meta.advice_column(),
+            hash_hi: meta.advice_column(),
+            hash_lo: meta.advice_column(),
+            hash_hi_quotient: meta.advice_column(),
+            hash_hi_remainder: meta.advice_column(),
+            bit_index: meta.advice_column(),
+            item_rlc: meta.advice_column_in(SecondPhase),
+            hash_rest_rlc: meta.advice_column_in(SecondPhase),
+        };
+        let bits = BitColumns {
+            q_enable: meta.fixed_column(),
+            q_first: meta.fixed_column(),
+            q_block: meta.fixed_column(),
+            tx_id: meta.fixed_column(),
+            bit_index: meta.fixed_column(),
+            byte_index: meta.fixed_column(),
+            q_byte_start: meta.fixed_column(),
+            q_byte_end: meta.fixed_column(),
+            q_limb_start: meta.fixed_column(),
+            q_limb_end: meta.fixed_column(),
+            bit: meta.advice_column(),
+            byte_acc: meta.advice_column(),
+            limb_acc: meta.advice_column(),
+            bloom_rlc: meta.advice_column_in(SecondPhase),
+        };
+
+        let instance = meta.instance_column();
+        meta.enable_equality(receipts.block_number);
+        meta.enable_equality(bits.limb_acc);
+        meta.enable_equality(bits.bloom_rlc);
+        meta.enable_equality(instance);
+
+        configure_receipts(meta, &receipts, &challenges);
+        configure_entries(meta, &entries);
+        configure_bits(meta, &bits, &challenges);
+        let trie = ListTrieConfig::configure(meta, &byte_table, &keccak_table, &challenges);
+
+        let r = &receipts;
+        let e = &entries;
+
+        for (name, q_enable, column) in [
+            ("hash_hi is in u8 range", e.q_enable, e.hash_hi),
+            ("hash_lo is in u8 range", e.q_enable, e.hash_lo),
+            (
+                "hash_hi_quotient is in u8 range",
+                e.q_enable,
+                e.hash_hi_quotient,
+            ),
+        ] {
+            meta.lookup_any(name, |meta| {
+                vec![(
+                    meta.query_fixed(q_enable, Rotation::cur())
+                        * meta.query_advice(column, Rotation::cur()),
+                    meta.query_fixed(byte_table.byte, Rotation::cur()),
+                )]
+            });
+        }
+        // The remainder is in [0, 7] when both it and 7 - remainder are bytes.
+        meta.lookup_any("hash_hi_remainder is in [0, 7]", |meta| {
+            vec![(
+                meta.query_fixed(e.q_enable, Rotation::cur())
+                    * (7.expr() - meta.query_advice(e.hash_hi_remainder, Rotation::cur())),
+                meta.query_fixed(byte_table.byte, Rotation::cur()),
+            )]
+        });
+        meta.lookup_any("hash_hi_remainder is a byte", |meta| {
+            vec![(
+                meta.query_fixed(e.q_enable, Rotation::cur())
+                    * meta.query_advice(e.hash_hi_remainder, Rotation::cur()),
+                meta.query_fixed(byte_table.byte, Rotation::cur()),
+            )]
+        });
+
+        // Receipt rows lookups
+
+        // The encoding of a receipt is canonical, so that its hash is the one
+        // of the receipts trie.
+        meta.lookup_any("canonical_diff is in u8 range", |meta| {
+            vec![(
+                meta.query_fixed(r.q_enable, Rotation::cur())
+                    * meta.query_advice(r.canonical_diff, Rotation::cur()),
+                meta.query_fixed(byte_table.byte, Rotation::cur()),
+            )]
+        });
+
+        meta.lookup_any("tx type in TxTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(r.q_enable, Rotation::cur()),
+                meta.query_advice(r.is_receipt_start, Rotation::cur()),
+            ]);
+            let input = [
+                meta.query_advice(r.tx_id, Rotation::cur()),
+                TxFieldTag::TxType.expr(),
+                0.expr(),
+                meta.query_advice(r.is_type, Rotation::cur())
+                    * meta.query_advice(r.byte, Rotation::cur()),
+            ];
+            tx_lookup(meta, &tx_table, enable, input)
+        });
+        meta.lookup_any("block number in TxTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(r.q_enable, Rotation::cur()),
+                meta.query_advice(r.is_receipt_start, Rotation::cur()),
+            ]);
+            let input = [
+                meta.query_advice(r.tx_id, Rotation::cur()),
+                TxFieldTag::BlockNumber.expr(),
+                0.expr(),
+                meta.query_advice(r.block_number, Rotation::cur()),
+            ];
+            tx_lookup(meta, &tx_table, enable, input)
+        });
+        // Like in the EndBlock gadget, the txs after the last one are padding
+        // txs, whose caller address is 0.
+        meta.lookup_any("the tx after the last receipt is padding", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(r.q_enable, Rotation::cur()),
+                meta.query_advice(r.is_padding, Rotation::cur()),
+                not::expr(r.is_after_max_txs.expr()),
+            ]);
+            let input = [
+                meta.query_advice(r.tx_id, Rotation::cur()),
+                TxFieldTag::CallerAddress.expr(),
+                0.expr(),
+                0.expr(),
+            ];
+            tx_lookup(meta, &tx_table, enable, input)
+        });
+
+        meta.lookup_any("status in RwTable", |meta| {
+            let enable = meta.query_fixed(r.q_enable, Rotation::cur())
+                * meta.query_advice(r.is_status, Rotation::cur());
+            let input = [
+                RwTableTag::TxReceipt.expr(),
+                meta.query_advice(r.tx_id, Rotation::cur()),
+                0.expr(),
+                TxReceiptFieldTag::PostStateOrStatus.expr(),
+                meta.query_advice(r.value_acc, Rotation::cur()),
+            ];
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        meta.lookup_any("cumulative gas used in RwTable", |meta| {
+            let enable = r.item_end(meta, r.is_gas);
+            let input = [
+                RwTableTag::TxReceipt.expr(),
+                meta.query_advice(r.tx_id, Rotation::cur()),
+                0.expr(),
+                TxReceiptFieldTag::CumulativeGasUsed.expr(),
+                meta.query_advice(r.value_acc, Rotation::cur()),
+            ];
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        meta.lookup_any("number of logs in RwTable", |meta| {
+            let enable = r.receipt_end(meta);
+            let input = [
+                RwTableTag::TxReceipt.expr(),
+                meta.query_advice(r.tx_id, Rotation::cur()),
+                0.expr(),
+                TxReceiptFieldTag::LogLength.expr(),
+                meta.query_advice(r.log_id, Rotation::cur()),
+            ];
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        meta.lookup_any("log address in RwTable", |meta| {
+            let enable = r.item_end(meta, r.is_address);
+            let value = meta.query_advice(r.value_acc, Rotation::cur());
+            let input = r.log_field(meta, TxLogFieldTag::Address, 0.expr(), value);
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        meta.lookup_any("log topic in RwTable", |meta| {
+            let enable = r.item_end(meta, r.is_topic);
+            let index = meta.query_advice(r.topic_count, Rotation::cur()) - 1.expr();
+            let value = meta.query_advice(r.value_rlc, Rotation::cur());
+            let input = r.log_field(meta, TxLogFieldTag::Topic, index, value);
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        meta.lookup_any("log data byte in RwTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(r.q_enable, Rotation::cur()),
+                meta.query_advice(r.is_data, Rotation::cur()),
+                not::expr(meta.query_advice(r.rlp.is_header, Rotation::cur())),
+            ]);
+            let index = meta.query_advice(r.data_count, Rotation::cur()) - 1.expr();
+            let value = meta.query_advice(r.byte, Rotation::cur());
+            let input = r.log_field(meta, TxLogFieldTag::Data, index, value);
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        // With the number of logs of the receipt, the numbers of topics and data
+        // bytes of every log show that all the TxLog rows are in the receipt.
+        meta.lookup_any("number of log topics in RwTable", |meta| {
+            let enable = r.item_end(meta, r.is_data);
+            let value = meta.query_advice(r.topic_count, Rotation::cur());
+            let input = r.log_field(meta, TxLogFieldTag::TopicLength, 0.expr(), value);
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+        meta.lookup_any("number of log data bytes in RwTable", |meta| {
+            let enable = r.item_end(meta, r.is_data);
+            let value = meta.query_advice(r.data_count, Rotation::cur());
+            let input = r.log_field(meta, TxLogFieldTag::DataLength, 0.expr(), value);
+            rw_lookup(meta, &rw_table, enable, input)
+        });
+
+        meta.lookup_any("receipt bloom byte in bloom bits", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(r.q_enable, Rotation::cur()),
+                meta.query_advice(r.is_bloom, Rotation::cur()),
+                not::expr(meta.query_advice(r.rlp.is_header, Rotation::cur())),
+            ]);
+            vec![
+                (
+                    enable.clone(),
+                    meta.query_fixed(bits.q_byte_end, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * meta.query_advice(r.tx_id, Rotation::cur()),
+                    meta.query_fixed(bits.tx_id, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * (meta.query_advice(r.bloom_count, Rotation::cur()) - 1.expr()),
+                    meta.query_fixed(bits.byte_index, Rotation::cur()),
+                ),
+                (
+                    enable * meta.query_advice(r.byte, Rotation::cur()),
+                    meta.query_advice(bits.byte_acc, Rotation::cur()),
+                ),
+            ]
+        });
+
+        // Log addresses and topics are the bloom entries: every item of the
+        // receipts has an entry, and every entry is an item of the receipts.
+        meta.lookup_any("receipt log item in bloom entries", |meta| {
+            let [enable, tx_id, item_rlc, item_len] = r.item(meta);
+            [1.expr(), tx_id, item_rlc, item_len]
+                .into_iter()
+                .zip(e.entry(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+        });
+        meta.lookup_any("bloom entry in receipt log items", |meta| {
+            let [enable, tx_id, item_rlc, item_len] = e.entry(meta);
+            [1.expr(), tx_id, item_rlc, item_len]
+                .into_iter()
+                .zip(r.item(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+        });
+
+        // Bloom entries lookups
+
+        meta.lookup_any("keccak256_table_lookup(item_rlc, item_len, hash)", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(e.q_first, Rotation::cur()),
+                meta.query_advice(e.is_entry, Rotation::cur()),
+            ]);
+            let evm_word = challenges.evm_word();
+            let hash_rlc = (0..ENTRY_ROWS as i32)
+                .flat_map(|row| [(e.hash_hi, row), (e.hash_lo, row)])
+                .fold(0.expr(), |acc, (column, row)| {
+                    acc * evm_word.clone() + meta.query_advice(column, Rotation(row))
+                })
+                * challenges.evm_word_powers_of_randomness::<26>()[25].clone()
+                + meta.query_advice(e.hash_rest_rlc, Rotation::cur());
+            [
+                1.expr(),
+                meta.query_advice(e.item_rlc, Rotation::cur()),
+                meta.query_advice(e.item_len, Rotation::cur()),
+                hash_rlc,
+            ]
+            .into_iter()
+            .zip(keccak_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        for (name, tx_id) in [
+            ("bloom entry bit is set in the tx bloom", true),
+            ("bloom entry bit is set in the block bloom", false),
+        ] {
+            meta.lookup_any(name, |meta| {
+                let enable = and::expr([
+                    meta.query_fixed(e.q_enable, Rotation::cur()),
+                    meta.query_advice(e.is_entry, Rotation::cur()),
+                ]);
+                let tx_id = if tx_id {
+                    meta.query_advice(e.tx_id, Rotation::cur())
+                } else {
+                    0.expr()
+                };
+                vec![
+                    (
+                        enable.clone(),
+                        meta.query_fixed(bits.q_enable, Rotation::cur()),
+                    ),
+                    (
+                        enable.clone() * tx_id,
+                        meta.query_fixed(bits.tx_id, Rotation::cur()),
+                    ),
+                    (
+                        enable.clone() * meta.query_advice(e.bit_index, Rotation::cur()),
+                        meta.query_fixed(bits.bit_index, Rotation::cur()),
+                    ),
+                    (enable, meta.query_advice(bits.bit, Rotation::cur())),
+                ]
+            });
+        }
+
+        // Bloom bits lookups
+
+        meta.lookup_any("tx bloom bit is set by a bloom entry", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(bits.q_enable, Rotation::cur()),
+                not::expr(meta.query_fixed(bits.q_block, Rotation::cur())),
+                meta.query_advice(bits.bit, Rotation::cur()),
+            ]);
+            vec![
+                (
+                    enable.clone(),
+                    meta.query_fixed(e.q_enable, Rotation::cur())
+                        * meta.query_advice(e.is_entry, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * meta.query_fixed(bits.tx_id, Rotation::cur()),
+                    meta.query_advice(e.tx_id, Rotation::cur()),
+                ),
+                (
+                    enable * meta.query_fixed(bits.bit_index, Rotation::cur()),
+                    meta.query_advice(e.bit_index, Rotation::cur()),
+                ),
+            ]
+        });
+        meta.lookup_any("block bloom bit is set by a bloom entry", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(bits.q_block, Rotation::cur()),
+                meta.query_advice(bits.bit, Rotation::cur()),
+            ]);
+            vec![
+                (
+                    enable.clone(),
+                    meta.query_fixed(e.q_enable, Rotation::cur())
+                        * meta.query_advice(e.is_entry, Rotation::cur()),
+                ),
+                (
+                    enable * meta.query_fixed(bits.bit_index, Rotation::cur()),
+                    meta.query_advice(e.bit_index, Rotation::cur()),
+                ),
+            ]
+        });
+
+        // Receipts trie lookups
+
+        meta.lookup_any("the value of every path is a receipt", |meta| {
+            let enable = trie.path_end(meta);
+            [
+                (1.expr(), r.receipt_end(meta)),
+                (
+                    meta.query_advice(trie.id, Rotation::cur()),
+                    meta.query_advice(r.tx_id, Rotation::cur()),
+                ),
+                (
+                    meta.query_advice(trie.value_rlc, Rotation::cur()),
+                    meta.query_advice(r.receipt_rlc, Rotation::cur()),
+                ),
+                (
+                    meta.query_advice(trie.value_len, Rotation::cur()),
+                    meta.query_advice(r.receipt_len, Rotation::cur()),
+                ),
+            ]
+            .into_iter()
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+        meta.lookup_any("every receipt has a path", |meta| {
+            let enable = r.receipt_end(meta);
+            vec![
+                (enable.clone(), trie.path_end(meta)),
+                (
+                    enable * meta.query_advice(r.tx_id, Rotation::cur()),
+                    meta.query_advice(trie.id, Rotation::cur()),
+                ),
+            ]
+        });
+
+        Self {
+            byte_table,
+            receipts,
+            entries,
+            bits,
+            trie,
+            instance,
+            max_txs,
+            rw_table,
+            tx_table,
+            keccak_table,
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn configure_receipts<F: Field>(
+    meta: &mut ConstraintSystem<F>,
+    r: &ReceiptColumns<F>,
+    challenges: &Challenges<Expression<F>>,
+) {
+    let item_flags = r.item_flags().map(|(_, column)| column);
+    let list_flags = [r.is_receipt, r.is_logs, r.is_log, r.is_topics];
+
+    meta.create_gate("receipt flags are boolean", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+        for column in [r.is_padding, r.is_receipt_start, r.is_receipt_end]
+            .into_iter()
+            .chain(item_flags)
+        {
+            cb.require_boolean(
+                "receipt flag is boolean",
+                meta.query_advice(column, Rotation::cur()),
+            );
+        }
+        cb.require_equal(
+            "a byte of a receipt belongs to one item",
+            sum::expr(item_flags.map(|column| meta.query_advice(column, Rotation::cur()))),
+            not::expr(meta.query_advice(r.is_padding, Rotation::cur())),
+        );
+        cb.gate(meta.query_fixed(r.q_enable, Rotation::cur()))
+    });
+
+    meta.create_gate("receipt first row", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+        cb.require_equal(
+            "the first receipt is the one of the first tx",
+            meta.query_advice(r.tx_id, Rotation::cur()),
+            1.expr(),
+        );
+        cb.require_equal(
+            "the first row starts a receipt unless there are no receipts",
+            meta.query_advice(r.is_receipt_start, Rotation::cur()),
+            not::expr(meta.query_advice(r.is_padding, Rotation::cur())),
+        );
+        cb.gate(meta.query_fixed(r.q_first, Rotation::cur()))
+    });
+
+    meta.create_gate("receipt padding", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let is_padding_prev = meta.query_advice(r.is_padding, Rotation::prev());
+        let is_padding = meta.query_advice(r.is_padding, Rotation::cur());
+        let tx_id_prev = meta.query_advice(r.tx_id, Rotation::prev());
+        let tx_id = meta.query_advice(r.tx_id, Rotation::cur());
+
+        cb.require_equal(
+            "block_number is the same in all the rows",
+            meta.query_advice(r.block_number, Rotation::cur()),
+            meta.query_advice(r.block_number, Rotation::prev()),
+        );
+        cb.require_zero(
+            "padding is not followed by a receipt",
+            is_padding_prev.clone() * not::expr(is_padding.clone()),
+        );
+        cb.condition(is_padding - is_padding_prev.clone(), |cb| {
+            cb.require_equal(
+                "the last receipt ends before the padding",
+                meta.query_advice(r.is_receipt_end, Rotation::prev()),
+                1.expr(),
+            );
+            cb.require_equal(
+                "the padding rows have the id of the tx after the last receipt",
+                tx_id.clone(),
+                tx_id_prev.clone() + 1.expr(),
+            );
+        });
+        cb.condition(is_padding_prev, |cb| {
+            cb.require_equal("tx_id is the same in the padding rows", tx_id, tx_id_prev);
+        });
+
+        cb.gate(
+            meta.query_fixed(r.q_enable, Rotation::cur())
+                - meta.query_fixed(r.q_first, Rotation::cur()),
+        )
+    });
+
+    meta.create_gate("receipt padding rows are empty", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+        for column in [
+            r.is_receipt_start,
+            r.is_receipt_end,
+            r.rlp.is_item_start,
+            r.rlp.is_item_end,
+            r.canonical_diff,
+        ] {
+            cb.require_zero(
+                "flags are disabled in padding rows",
+                meta.query_advice(column, Rotation::cur()),
+            );
+        }
+        cb.gate(and::expr([
+            meta.query_fixed(r.q_enable, Rotation::cur()),
+            meta.query_advice(r.is_padding, Rotation::cur()),
+        ]))
+    });
+
+    meta.create_gate("receipt last row", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+        cb.require_equal(
+            "the last row is padding",
+            meta.query_advice(r.is_padding, Rotation::cur()),
+            1.expr(),
+        );
+        cb.gate(meta.query_fixed(r.q_last, Rotation::cur()))
+    });
+
+    meta.create_gate("receipt items", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let byte = meta.query_advice(r.byte, Rotation::cur());
+        let is_item_start = meta.query_advice(r.rlp.is_item_start, Rotation::cur());
+        let is_header = meta.query_advice(r.rlp.is_header, Rotation::cur());
+        let is_list =
+            sum::expr(list_flags.map(|column| meta.query_advice(column, Rotation::cur())));
+        let accumulators = [r.value_acc, r.value_rlc, r.item_rlc]
+            .map(|column| meta.query_advice(column, Rotation::cur()));
+
+        // The header of the item is decoded by the RLP decoder
+        cb.condition(is_item_start, |cb| {
+            cb.require_equal(
+                "only the receipt, the logs, a log and its topics are lists",
+                r.rlp.is_list(meta),
+                is_list,
+            );
+            for accumulator in accumulators.clone() {
+                cb.require_equal(
+                    "the accumulators start with the single byte item",
+                    accumulator,
+                    not::expr(is_header.clone()) * byte.clone(),
+                );
+            }
+        });
+
+        cb.condition(is_header, |cb| {
+            for accumulator in accumulators {
+                cb.require_zero("header rows don't have a value", accumulator);
+            }
+        });
+
+        cb.gate(and::expr([
+            meta.query_fixed(r.q_enable, Rotation::cur()),
+            not::expr(meta.query_advice(r.is_padding, Rotation::cur())),
+        ]))
+    });
+
+    meta.create_gate("receipt item transitions", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let byte = meta.query_advice(r.byte, Rotation::cur());
+        let is_item_start = meta.query_advice(r.rlp.is_item_start, Rotation::cur());
+        let is_header = meta.query_advice(r.rlp.is_header, Rotation::cur());
+
+        cb.require_equal(
+            "an item starts after the end of the previous one",
+            is_item_start.clone(),
+            meta.query_advice(r.rlp.is_item_end, Rotation::prev()),
+        );
+
+        cb.condition(not::expr(is_item_start.clone()), |cb| {
+            for column in item_flags {
+                cb.require_equal(
+                    "the item is the same in all its bytes",
+                    meta.query_advice(column, Rotation::cur()),
+                    meta.query_advice(column, Rotation::prev()),
+                );
+            }
+        });
+
+        // Payload bytes
+        cb.condition(
+            and::expr([not::expr(is_header), not::expr(is_item_start)]),
+            |cb| {
+                for (name, column, factor) in [
+                    (
+                        "value_acc accumulates the payload bytes",
+                        r.value_acc,
+                        256.expr(),
+                    ),
+                    (
+                        "value_rlc accumulates the payload bytes",
+                        r.value_rlc,
+                        challenges.evm_word(),
+                    ),
+                    (
+                        "item_rlc accumulates the payload bytes",
+                        r.item_rlc,
+                        challenges.keccak_input(),
+                    ),
+                ] {
+                    cb.require_equal(
+                        name,
+                        meta.query_advice(column, Rotation::cur()),
+                        meta.query_advice(column, Rotation::prev()) * factor + byte.clone(),
+                    );
+                }
+            },
+        );
+
+        cb.gate(and::expr([
+            meta.query_fixed(r.q_enable, Rotation::cur())
+                - meta.query_fixed(r.q_first, Rotation::cur()),
+            not::expr(meta.query_advice(r.is_padding, Rotation::cur())),
+        ]))
+    });
+
+    meta.create_gate("receipt structure", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let [is_type, is_receipt, is_status, is_gas, is_bloom, is_logs, is_log, is_address, is_topics, is_topic, is_data] =
+            item_flags.map(|column| meta.query_advice(column, Rotation::cur()));
+        let [is_type_prev, is_receipt_prev, is_status_prev, is_gas_prev, is_bloom_prev, is_logs_prev, is_log_prev, is_address_prev, is_topics_prev, is_topic_prev, is_data_prev] =
+            item_flags.map(|column| meta.query_advice(column, Rotation::prev()));
+        let length_prev = meta.query_advice(r.rlp.length, Rotation::prev());
+        let topics_remaining_prev = meta.query_advice(r.topics_remaining, Rotation::prev());
+
+        // Every item is followed by the next field of the receipt or of its logs
+        cb.condition(
+            and::expr([
+                meta.query_advice(r.rlp.is_item_start, Rotation::cur()),
+                not::expr(meta.query_advice(r.is_receipt_start, Rotation::cur())),
+            ]),
+            |cb| {
+                for (name, item, prev_items) in [
+                    (
+                        "the receipt list follows the type byte",
+                        is_receipt.clone(),
+                        is_type_prev,
+                    ),
+                    (
+                        "the status follows the receipt list header",
+                        is_status.clone(),
+                        is_receipt_prev.clone(),
+                    ),
+                    (
+                        "the cumulative gas follows the status",
+                        is_gas.clone(),
+                        is_status_prev,
+                    ),
+                    (
+                        "the bloom follows the cumulative gas",
+                        is_bloom.clone(),
+                        is_gas_prev,
+                    ),
+                    (
+                        "the logs follow the bloom",
+                        is_logs.clone(),
+                        is_bloom_prev,
+                    ),
+                    (
+                        "a log follows the logs list header or the previous log",
+                        is_log.clone(),
+                        is_logs_prev.clone() + is_data_prev,
+                    ),
+                    (
+                        "the address follows the log list header",
+                        is_address.clone(),
+                        is_log_prev.clone(),
+                    ),
+                    (
+                        "the topics follow the address",
+                        is_topics.clone(),
+                        is_address_prev,
+                    ),
+                    (
+                        "a topic or the data follow the topics list header or a topic",
+                        is_topic.clone() + is_data.clone(),
+                        is_topics_prev.clone() + is_topic_prev.clone(),
+                    ),
+                ] {
+                    cb.require_equal(name, item, prev_items);
+                }
+                cb.require_zero(
+                    "the data follows the last topic",
+                    is_data.clone()
+                        * (is_topics_prev.clone() * length_prev.clone()
+                            + is_topic_prev * topics_remaining_prev.clone()),
+                );
+            },
+        );
+
+        // The number of bytes of the payload of every list
+        for (name, column, list_prev, payload_flags) in [
+            (
+                "receipt_remaining decreases in the payload of the receipt",
+                r.receipt_remaining,
+                is_receipt_prev,
+                not::expr(is_type + is_receipt),
+            ),
+            (
+                "logs_remaining decreases in the payload of the logs",
+                r.logs_remaining,
+                is_logs_prev,
+                sum::expr([
+                    is_log,
+                    is_address.clone(),
+                    is_topics.clone(),
+                    is_topic.clone(),
+                    is_data.clone(),
+                ]),
+            ),
+            (
+                "log_remaining decreases in the payload of a log",
+                r.log_remaining,
+                is_log_prev,
+                sum::expr([is_address, is_topics, is_topic.clone(), is_data]),
+            ),
+            (
+                "topics_remaining decreases in the payload of the topics",
+                r.topics_remaining,
+                is_topics_prev,
+                is_topic,
+            ),
+        ] {
+            cb.condition(payload_flags, |cb| {
+                cb.require_equal(
+                    name,
+                    meta.query_advice(column, Rotation::cur()),
+                    select::expr(
+                        list_prev,
+                        length_prev.clone(),
+                        meta.query_advice(column, Rotation::prev()),
+                    ) - 1.expr(),
+                );
+            });
+        }
+
+        cb.gate(and::expr([
+            meta.query_fixed(r.q_enable, Rotation::cur())
+                - meta.query_fixed(r.q_first, Rotation::cur()),
+            not::expr(meta.query_advice(r.is_padding, Rotation::cur())),
+        ]))
+    });
+
+    meta.create_gate("receipt rows", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let q_first = meta.query_fixed(r.q_first, Rotation::cur());
+        let byte = meta.query_advice(r.byte, Rotation::cur());
+        let is_receipt_start = meta.query_advice(r.is_receipt_start, Rotation::cur());
+        let is_item_start = meta.query_advice(r.rlp.is_item_start, Rotation::cur());
+        let is_item_end = meta.query_advice(r.rlp.is_item_end, Rotation::cur());
+        let is_header = meta.query_advice(r.rlp.is_header, Rotation::cur());
+        let length = meta.query_advice(r.rlp.length, Rotation::cur());
+        let [is_type, is_receipt, is_status, is_gas, is_bloom, is_logs, is_log, is_address, _, is_topic, is_data] =
+            r.item_flags()
+                .map(|(_, column)| meta.query_advice(column, Rotation::cur()));
+        let cur_prev = |column| {
+            (
+                meta.query_advice(column, Rotation::cur()),
+                meta.query_advice(column, Rotation::prev()),
+            )
+        };
+        let (tx_id, tx_id_prev) = cur_prev(r.tx_id);
+        let (receipt_len, receipt_len_prev) = cur_prev(r.receipt_len);
+        let (receipt_rlc, receipt_rlc_prev) = cur_prev(r.receipt_rlc);
+        let (log_id, log_id_prev) = cur_prev(r.log_id);
+        let (topic_count, topic_count_prev) = cur_prev(r.topic_count);
+        let (data_count, data_count_prev) = cur_prev(r.data_count);
+        let (bloom_count, bloom_count_prev) = cur_prev(r.bloom_count);
+
+        cb.condition(is_receipt_start.clone(), |cb| {
+            cb.require_equal(
+                "a receipt starts with an item",
+                is_item_start.clone(),
+                1.expr(),
+            );
+            cb.require_equal(
+                "a receipt starts with the type byte or the list header",
+                is_type.clone() + is_receipt,
+                1.expr(),
+            );
+            cb.require_equal("receipt_len = 1", receipt_len.clone(), 1.expr());
+            cb.require_equal("receipt_rlc = byte", receipt_rlc.clone(), byte.clone());
+            for (name, count) in [
+                ("log_id = 0", log_id.clone()),
+                ("topic_count = 0", topic_count.clone()),
+                ("data_count = 0", data_count.clone()),
+                ("bloom_count = 0", bloom_count.clone()),
+            ] {
+                cb.require_zero(name, count);
+            }
+        });
+        cb.condition(
+            and::expr([is_receipt_start.clone(), not::expr(q_first)]),
+            |cb| {
+                cb.require_equal(
+                    "a receipt starts after the end of the previous one",
+                    meta.query_advice(r.is_receipt_end, Rotation::prev()),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "receipts are in the order of the txs",
+                    tx_id.clone(),
+                    tx_id_prev.clone() + 1.expr(),
+                );
+            },
+        );
+
+        cb.condition(not::expr(is_receipt_start), |cb| {
+            cb.require_zero(
+                "a receipt end is followed by a receipt start",
+                meta.query_advice(r.is_receipt_end, Rotation::prev()),
+            );
+            cb.require_equal("tx_id is the same for a receipt", tx_id, tx_id_prev);
+            cb.require_equal(
+                "receipt_len increases by 1",
+                receipt_len,
+                receipt_len_prev + 1.expr(),
+            );
+            cb.require_equal(
+                "receipt_rlc accumulates the receipt bytes",
+                receipt_rlc,
+                receipt_rlc_prev * challenges.keccak_input() + byte.clone(),
+            );
+            cb.require_equal(
+                "log_id increases at the start of every log",
+                log_id,
+                log_id_prev + is_log.clone() * is_item_start.clone(),
+            );
+            cb.require_equal(
+                "topic_count counts the topics of the log",
+                topic_count,
+                not::expr(is_log) * topic_count_prev + is_topic.clone() * is_item_start.clone(),
+            );
+            cb.require_equal(
+                "data_count counts the data bytes of the log",
+                data_count,
+                not::expr(is_item_start.clone()) * data_count_prev
+                    + is_data.clone() * not::expr(is_header.clone()),
+            );
+            cb.require_equal(
+                "bloom_count counts the bloom bytes",
+                bloom_count,
+                bloom_count_prev + is_bloom.clone() * not::expr(is_header.clone()),
+            );
+        });
+
+        cb.condition(meta.query_advice(r.is_receipt_end, Rotation::cur()), |cb| {
+            cb.require_equal(
+                "a receipt ends at the end of an item",
+                is_item_end.clone(),
+                1.expr(),
+            );
+            cb.require_equal(
+                "a receipt ends with the logs list header or the data of its last log",
+                is_logs.clone() + is_data.clone(),
+                1.expr(),
+            );
+            cb.require_zero(
+                "a receipt ends at the end of its list",
+                meta.query_advice(r.receipt_remaining, Rotation::cur()),
+            );
+            cb.require_zero(
+                "a receipt ends at the end of its logs",
+                is_logs * length.clone()
+                    + is_data.clone() * meta.query_advice(r.logs_remaining, Rotation::cur()),
+            );
+        });
+        cb.condition(is_data * is_item_end.clone(), |cb| {
+            cb.require_zero(
+                "a log ends with its data",
+                meta.query_advice(r.log_remaining, Rotation::cur()),
+            );
+        });
+
+        cb.condition(is_type, |cb| {
+            cb.require_zero(
+                "the type of a typed receipt is 1 or 2",
+                (byte.clone() - 1.expr()) * (byte.clone() - 2.expr()),
+            );
+        });
+        cb.condition(is_status, |cb| {
+            cb.require_zero(
+                "status is encoded as 0x01 or as the empty string",
+                (byte.clone() - 1.expr()) * (byte.clone() - 0x80.expr()),
+            );
+        });
+        cb.condition(is_bloom.clone() * is_item_start.clone(), |cb| {
+            cb.require_equal(
+                "the bloom is a string with 2 length bytes",
+                byte.clone(),
+                0xb9.expr(),
+            );
+        });
+        cb.condition(is_item_end, |cb| {
+            cb.require_zero(
+                "the bloom, the addresses and the topics have a fixed length",
+                is_bloom * (length.clone() - BLOOM_BYTES.expr())
+                    + is_address * (length.clone() - 20.expr())
+                    + is_topic * (length - 32.expr()),
+            );
+        });
+
+        // A single length byte is at least 56, and the first one of several
+        // is not 0.  A string of a single byte below 0x80 is encoded as the
+        // byte itself, and the cumulative gas has no leading zeros.
+        let counter_is_zero = r.rlp.counter_is_zero.expr();
+        let is_first_length_byte = and::expr([
+            is_header.clone(),
+            not::expr(is_item_start.clone()),
+            meta.query_advice(r.rlp.is_item_start, Rotation::prev()),
+        ]);
+        let is_first_payload_byte = and::expr([
+            not::expr(is_header.clone()),
+            not::expr(is_item_start.clone()),
+            meta.query_advice(r.rlp.is_header, Rotation::prev()),
+        ]);
+        let is_single_gas = and::expr([is_gas.clone(), is_item_start, not::expr(is_header)]);
+        cb.require_equal(
+            "canonical_diff is the difference between a byte and its lower bound",
+            meta.query_advice(r.canonical_diff, Rotation::cur()),
+            is_first_length_byte * (byte.clone() - 1.expr() - counter_is_zero.clone() * 55.expr())
+                + is_first_payload_byte
+                    * (byte.clone()
+                        - counter_is_zero.clone() * 0x80.expr()
+                        - not::expr(counter_is_zero) * is_gas)
+                + is_single_gas * (byte - 1.expr()),
+        );
+
+        cb.gate(and::expr([
+            meta.query_fixed(r.q_enable, Rotation::cur()),
+            not::expr(meta.query_advice(r.is_padding, Rotation::cur())),
+        ]))
+    });
+}
+
+fn configure_entries<F: Field>(meta: &mut ConstraintSystem<F>, e: &EntryColumns) {
+    meta.create_gate("bloom entries", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let is_entry = meta.query_advice(e.is_entry, Rotation::cur());
+        let hash_hi = meta.query_advice(e.hash_hi, Rotation::cur());
+        let hash_lo = meta.query_advice(e.hash_lo, Rotation::cur());
+        let remainder = meta.query_advice(e.hash_hi_remainder, Rotation::cur());
+
+        cb.require_boolean("is_entry is boolean", is_entry.clone());
+        cb.require_equal(
+            "hash_hi = 8 * quotient + remainder",
+            hash_hi,
+            meta.query_advice(e.hash_hi_quotient, Rotation::cur()) * 8.expr() + remainder.clone(),
+        );
+        cb.require_equal(
+            "bit_index is the 11 low bits of the pair of hash bytes",
+            meta.query_advice(e.bit_index, Rotation::cur()),
+            remainder * 256.expr() + hash_lo,
+        );
+        cb.condition(
+            not::expr(meta.query_fixed(e.q_first, Rotation::cur())),
+            |cb| {
+                for column in [e.is_entry, e.tx_id] {
+                    cb.require_equal(
+                        "the rows of an entry belong to the same entry",
+                        meta.query_advice(column, Rotation::cur()),
+                        meta.query_advice(column, Rotation::prev()),
+                    );
+                }
+            },
+        );
+
+        cb.gate(meta.query_fixed(e.q_enable, Rotation::cur()))
+    });
+}
+
+fn configure_bits<F: Field>(
+    meta: &mut ConstraintSystem<F>,
+    bits: &BitColumns,
+    challenges: &Challenges<Expression<F>>,
+) {
+    meta.create_gate("bloom bits", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        let bit = meta.query_advice(bits.bit, Rotation::cur());
+        cb.require_boolean("bit is boolean", bit.clone());
+        for (name, acc, q_start) in [
+            (
+                "byte_acc accumulates 8 bits",
+                bits.byte_acc,
+                bits.q_byte_start,
+            ),
+            (
+                "limb_acc accumulates 128 bits",
+                bits.limb_acc,
+                bits.q_limb_start,
+            ),
+        ] {
+            cb.require_equal(
+                name,
+                meta.query_advice(acc, Rotation::cur()),
+                not::expr(meta.query_fixed(q_start, Rotation::cur()))
+                    * meta.query_advice(acc, Rotation::prev())
+                    * 2.expr()
+                    + bit.clone(),
+            );
+        }
+
+        cb.gate(meta.query_fixed(bits.q_enable, Rotation::cur()))
+    });
+
+    meta.create_gate("block bloom rlc", |meta| {
+        let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+        // The RLC starts from zero at the first row, and accumulates every
+        // byte at its last bit.
+        let bloom_rlc_prev = not::expr(meta.query_fixed(bits.q_first, Rotation::cur()))
+            * meta.query_advice(bits.bloom_rlc, Rotation::prev());
+        cb.require_equal(
+            "bloom_rlc = bloom_rlc_prev * evm_word + byte_acc at the end of a byte",
+            meta.query_advice(bits.bloom_rlc, Rotation::cur()),
+            select::expr(
+                meta.query_fixed(bits.q_byte_end, Rotation::cur()),
+                bloom_rlc_prev.clone() * challenges.evm_word()
+                    + meta.query_advice(bits.byte_acc, Rotation::cur()),
+                bloom_rlc_prev,
+            ),
+        );
+
+        cb.gate(meta.query_fixed(bits.q_block, Rotation::cur()))
+    });
+}
+
+/// Cells of the values proved by the receipt circuit, which are linked to the
+/// header of the block in the PublicInputs circuit.
+#[derive(Clone, Debug)]
+pub(crate) struct ReceiptCells<F: Field> {
+    /// Number of the block
+    pub(crate) block_number: AssignedCell<F, F>,
+    /// Receipts root, as the RLC of its big endian bytes with the EVM word
+    /// challenge
+    pub(crate) receipts_root: AssignedCell<F, F>,
+    /// Logs bloom, as the RLC of its bytes with the EVM word challenge
+    pub(crate) logs_bloom: AssignedCell<F, F>,
+}
+
+impl<F: Field> ReceiptCircuitConfig<F> {
+    /// Assign the receipt rows, the bloom entries and bits, and the receipts
+    /// trie rows of the `receipts` of the block `block_number`, and return the
+    /// cells of the values that they prove.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        receipts: &[Receipt],
+        block_number: u64,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<ReceiptCells<F>, Error> {
+        if receipts.len() > self.max_txs {
+            error!(
+                "{} receipts don't fit in the receipt circuit with max_txs = {}",
+                receipts.len(),
+                self.max_txs
+            );
+            return Err(Error::Synthesis);
+        }
+
+        self.byte_table.load(layouter)?;
+
+        let block_number_cell =
+            self.assign_receipts(layouter, receipts, block_number, challenges)?;
+        self.assign_entries(layouter, receipts, challenges)?;
+        let (limb_cells, bloom_cell) = self.assign_bits(layouter, receipts, challenges)?;
+        let root_cells = self.trie.assign(
+            layouter,
+            "receipts trie",
+            &receipts_values(receipts),
+            0,
+            challenges,
+        )?;
+
+        for (i, cell) in [&block_number_cell, &root_cells.root_hi, &root_cells.root_lo]
+            .into_iter()
+            .chain(limb_cells.iter())
+            .enumerate()
+        {
+            layouter.constrain_instance(cell.cell(), self.instance, i)?;
+        }
+
+        Ok(ReceiptCells {
+            block_number: block_number_cell,
+            receipts_root: root_cells.root_rlc,
+            logs_bloom: bloom_cell,
+        })
+    }
+
+    /// Assign the receipt rows, and return the block number cell.
+    fn assign_receipts(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        receipts: &[Receipt],
+        block_number: u64,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let r = &self.receipts;
+        let rows: Vec<ReceiptRow> = receipts.iter().flat_map(receipt_rows).collect();
+        let n_rows = rows.len() + 1;
+        let padding = ReceiptRow {
+            tx_id: receipts.last().map_or(1, |receipt| receipt.tx_id + 1),
+            ..Default::default()
+        };
+
+        layouter.assign_region(
+            || "receipt rows",
+            |mut region| {
+                let keccak_input = challenges.keccak_input();
+                let evm_word = challenges.evm_word();
+                let is_after_max_txs_chip = IsZeroChip::construct(r.is_after_max_txs.clone());
+
+                let mut receipt_rlc = Value::known(F::zero());
+                let mut value_rlc = Value::known(F::zero());
+                let mut item_rlc = Value::known(F::zero());
+                let mut value_acc = F::zero();
+                let mut receipt_len = 0;
+                let [mut log_id, mut topic_count, mut data_count, mut bloom_count] = [0u64; 4];
+                let [mut receipt_remaining, mut logs_remaining, mut log_remaining, mut topics_remaining] =
+                    [0usize; 4];
+                let mut block_number_cell = None;
+
+                for offset in 0..n_rows {
+                    for (name, column, value) in [
+                        ("q_enable", r.q_enable, true),
+                        ("q_first", r.q_first, offset == 0),
+                        ("q_last", r.q_last, offset == n_rows - 1),
+                    ] {
+                        region.assign_fixed(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(F::from(value as u64)),
+                        )?;
+                    }
+
+                    let is_padding = offset >= rows.len();
+                    let row = rows.get(offset).unwrap_or(&padding);
+                    let prev = offset
+                        .checked_sub(1)
+                        .and_then(|prev| rows.get(prev))
+                        .unwrap_or(&padding);
+                    let byte = Value::known(F::from(row.rlp.byte as u64));
+                    let is_item = |item| !is_padding && row.item == item;
+
+                    let mut canonical_diff = 0;
+                    if is_padding {
+                        receipt_rlc = Value::known(F::zero());
+                        receipt_len = 0;
+                        [log_id, topic_count, data_count, bloom_count] = [0; 4];
+                        value_acc = F::zero();
+                        value_rlc = Value::known(F::zero());
+                        item_rlc = Value::known(F::zero());
+                        [receipt_remaining, logs_remaining, log_remaining, topics_remaining] = [0; 4];
+                    } else {
+                        if row.is_receipt_start {
+                            receipt_rlc = byte;
+                            receipt_len = 1;
+                            [log_id, topic_count, data_count, bloom_count] = [0; 4];
+                        } else {
+                            receipt_rlc = receipt_rlc * keccak_input + byte;
+                            receipt_len += 1;
+                        }
+                        let is_item_start = row.rlp.is_item_start as u64;
+                        let is_payload = !row.rlp.is_header as u64;
+                        log_id += is_item(ReceiptItem::Log) as u64 * is_item_start;
+                        topic_count = !is_item(ReceiptItem::Log) as u64 * topic_count
+                            + is_item(ReceiptItem::Topic) as u64 * is_item_start;
+                        data_count = !row.rlp.is_item_start as u64 * data_count
+                            + is_item(ReceiptItem::Data) as u64 * is_payload;
+                        bloom_count += is_item(ReceiptItem::Bloom) as u64 * is_payload;
+
+                        (value_acc, value_rlc, item_rlc) = if row.rlp.is_header {
+                            (F::zero(), Value::known(F::zero()), Value::known(F::zero()))
+                        } else if row.rlp.is_item_start {
+                            (F::from(row.rlp.byte as u64), byte, byte)
+                        } else {
+                            (
+                                value_acc * F::from(256) + F::from(row.rlp.byte as u64),
+                                value_rlc * evm_word + byte,
+                                item_rlc * keccak_input + byte,
+                            )
+                        };
+
+                        let remaining = |remaining: usize, list, payload: &[ReceiptItem]| {
+                            if payload.contains(&row.item) {
+                                if prev.item == list {
+                                    prev.rlp.length as usize - 1
+                                } else {
+                                    remaining - 1
+                                }
+                            } else {
+                                0
+                            }
+                        };
+                        receipt_remaining = remaining(
+                            receipt_remaining,
+                            ReceiptItem::Receipt,
+                            &[
+                                ReceiptItem::Status,
+                                ReceiptItem::CumulativeGas,
+                                ReceiptItem::Bloom,
+                                ReceiptItem::Logs,
+                                ReceiptItem::Log,
+                                ReceiptItem::Address,
+                                ReceiptItem::Topics,
+                                ReceiptItem::Topic,
+                                ReceiptItem::Data,
+                            ],
+                        );
+                        logs_remaining = remaining(
+                            logs_remaining,
+                            ReceiptItem::Logs,
+                            &[
+                                ReceiptItem::Log,
+                                ReceiptItem::Address,
+                                ReceiptItem::Topics,
+                                ReceiptItem::Topic,
+                                ReceiptItem::Data,
+                            ],
+                        );
+                        log_remaining = remaining(
+                            log_remaining,
+                            ReceiptItem::Log,
+                            &[
+                                ReceiptItem::Address,
+                                ReceiptItem::Topics,
+                                ReceiptItem::Topic,
+                                ReceiptItem::Data,
+                            ],
+                        );
+                        topics_remaining =
+                            remaining(topics_remaining, ReceiptItem::Topics, &[ReceiptItem::Topic]);
+
+                        let byte = row.rlp.byte as usize;
+                        canonical_diff = if row.rlp.is_header && !row.rlp.is_item_start && prev.rlp.is_item_start
+                        {
+                            byte - 1 - 55 * (row.rlp.counter == 0) as usize
+                        } else if !row.rlp.is_header && !row.rlp.is_item_start && prev.rlp.is_header {
+                            byte - 0x80 * (row.rlp.counter == 0) as usize
+                                - (row.rlp.counter != 0 && row.item == ReceiptItem::CumulativeGas)
+                                    as usize
+                        } else if row.item == ReceiptItem::CumulativeGas
+                            && row.rlp.is_item_start
+                            && !row.rlp.is_header
+                        {
+                            byte - 1
+                        } else {
+                            0
+                        };
+                    }
+
+                    let cell = region.assign_advice(
+                        || "block_number",
+                        r.block_number,
+                        offset,
+                        || Value::known(F::from(block_number)),
+                    )?;
+                    block_number_cell.get_or_insert(cell);
+
+                    for (name, column, value) in [
+                        ("byte", r.byte, F::from(row.rlp.byte as u64)),
+                        ("tx_id", r.tx_id, F::from(row.tx_id as u64)),
+                        ("is_padding", r.is_padding, F::from(is_padding as u64)),
+                        (
+                            "is_receipt_start",
+                            r.is_receipt_start,
+                            F::from(row.is_receipt_start as u64),
+                        ),
+                        (
+                            "is_receipt_end",
+                            r.is_receipt_end,
+                            F::from(row.is_receipt_end as u64),
+                        ),
+                        ("receipt_len", r.receipt_len, F::from(receipt_len as u64)),
+                        (
+                            "canonical_diff",
+                            r.canonical_diff,
+                            F::from(canonical_diff as u64),
+                        ),
+                        (
+                            "receipt_remaining",
+                            r.receipt_remaining,
+                            F::from(receipt_remaining as u64),
+                        ),
+                        (
+                            "logs_remaining",
+                            r.logs_remaining,
+                            F::from(logs_remaining as u64),
+                        ),
+                        (
+                            "log_remaining",
+                            r.log_remaining,
+                            F::from(log_remaining as u64),
+                        ),
+                        (
+                            "topics_remaining",
+                            r.topics_remaining,
+                            F::from(topics_remaining as u64),
+                        ),
+                        ("bloom_count", r.bloom_count, F::from(bloom_count)),
+                        ("log_id", r.log_id, F::from(log_id)),
+                        ("topic_count", r.topic_count, F::from(topic_count)),
+                        ("data_count", r.data_count, F::from(data_count)),
+                        ("value_acc", r.value_acc, value_acc),
+                    ]
+                    .into_iter()
+                    .chain(r.item_flags().map(|(item, column)| {
+                        ("item flag", column, F::from(is_item(item) as u64))
+                    })) {
+                        region.assign_advice(|| name, column, offset, || Value::known(value))?;
+                    }
+                    for (name, column, value) in [
+                        ("receipt_rlc", r.receipt_rlc, receipt_rlc),
+                        ("value_rlc", r.value_rlc, value_rlc),
+                        ("item_rlc", r.item_rlc, item_rlc),
+                    ] {
+                        region.assign_advice(|| name, column, offset, || value)?;
+                    }
+
+                    r.rlp.assign(&mut region, offset, &row.rlp)?;
+                    is_after_max_txs_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(
+                            F::from(row.tx_id as u64) - F::from(self.max_txs as u64 + 1),
+                        ),
+                    )?;
+                }
+                Ok(block_number_cell.unwrap())
+            },
+        )
+    }
+
+    fn assign_entries(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        receipts: &[Receipt],
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        let e = &self.entries;
+        let entries = bloom_entries(receipts);
+        // An empty entry when there are no logs, so that the region is not
+        // empty.
+        let n_entries = entries.len().max(1);
+
+        layouter.assign_region(
+            || "bloom entries",
+            |mut region| {
+                let keccak_input = challenges.keccak_input();
+                let evm_word = challenges.evm_word();
+
+                for index in 0..n_entries {
+                    let (tx_id, item) = match entries.get(index) {
+                        Some((tx_id, item)) => (*tx_id, item.clone()),
+                        None => (0, Vec::new()),
+                    };
+                    let is_entry = index < entries.len();
+                    let hash = if is_entry {
+                        keccak(&item).to_be_bytes()
+                    } else {
+                        [0; 32]
+                    };
+                    let (item_rlc, hash_rest_rlc) = if is_entry {
+                        (
+                            keccak_input
+                                .map(|randomness| rlc::value(item.iter().rev(), randomness)),
+                            evm_word.map(|randomness| {
+                                rlc::value(hash[2 * ENTRY_ROWS..].iter().rev(), randomness)
+                            }),
+                        )
+                    } else {
+                        (Value::known(F::zero()), Value::known(F::zero()))
+                    };
+
+                    for row in 0..ENTRY_ROWS {
+                        let offset = index * ENTRY_ROWS + row;
+                        let [hash_hi, hash_lo] = [hash[2 * row], hash[2 * row + 1]];
+                        for (name, column, value) in [
+                            ("q_enable", e.q_enable, true),
+                            ("q_first", e.q_first, row == 0),
+                        ] {
+                            region.assign_fixed(
+                                || name,
+                                column,
+                                offset,
+                                || Value::known(F::from(value as u64)),
+                            )?;
+                        }
+                        for (name, column, value) in [
+                            ("tx_id", e.tx_id, tx_id as u64),
+                            ("is_entry", e.is_entry, is_entry as u64),
+                            ("item_len", e.item_len, item.len() as u64),
+                            ("hash_hi", e.hash_hi, hash_hi as u64),
+                            ("hash_lo", e.hash_lo, hash_lo as u64),
+                            (
+                                "hash_hi_quotient",
+                                e.hash_hi_quotient,
+                                (hash_hi >> 3) as u64,
+                            ),
+                            (
+                                "hash_hi_remainder",
+                                e.hash_hi_remainder,
+                                (hash_hi & 7) as u64,
+                            ),
+                            (
+                                "bit_index",
+                                e.bit_index,
+                                witness::bloom_bit_index(hash_hi, hash_lo) as u64,
+                            ),
+                        ] {
+                            region.assign_advice(
+                                || name,
+                                column,
+                                offset,
+                                || Value::known(F::from(value)),
+                            )?;
+                        }
+                        for (name, column, value) in [
+                            ("item_rlc", e.item_rlc, item_rlc),
+                            ("hash_rest_rlc", e.hash_rest_rlc, hash_rest_rlc),
+                        ] {
+                            region.assign_advice(|| name, column, offset, || value)?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assign the bloom bits, and return the cells of the limbs and of the
+    /// RLC of the block bloom.
+    #[allow(clippy::type_complexity)]
+    fn assign_bits(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        receipts: &[Receipt],
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(Vec<AssignedCell<F, F>>, AssignedCell<F, F>), Error> {
+        let bits = &self.bits;
+        // The block bloom followed by the bloom of every tx
+        let blooms: Vec<Bloom> = std::iter::once(logs_bloom(receipts))
+            .chain(
+                (0..self.max_txs).map(|i| receipts.get(i).map(Receipt::bloom).unwrap_or_default()),
+            )
+            .collect();
+
+        layouter.assign_region(
+            || "bloom bits",
+            |mut region| {
+                let mut limb_cells = Vec::new();
+                let mut bloom_cell = None;
+                let mut byte_acc = 0u64;
+                let mut limb_acc = 0u128;
+                let mut bloom_rlc = Value::known(F::zero());
+
+                for (tx_id, bloom) in blooms.iter().enumerate() {
+                    for row in 0..BLOOM_BITS {
+                        let offset = tx_id * BLOOM_BITS + row;
+                        let bit_index = BLOOM_BITS - 1 - row;
+                        let byte_index = row / 8;
+                        let bit = (bloom.0[byte_index] >> (bit_index % 8)) & 1;
+                        byte_acc = if row % 8 == 0 { 0 } else { byte_acc * 2 } + bit as u64;
+                        limb_acc = if row % 128 == 0 { 0 } else { limb_acc * 2 } + bit as u128;
+                        if tx_id == 0 && row % 8 == 7 {
+                            bloom_rlc =
+                                bloom_rlc * challenges.evm_word() + Value::known(F::from(byte_acc));
+                        }
+
+                        for (name, column, value) in [
+                            ("q_enable", bits.q_enable, 1),
+                            ("q_first", bits.q_first, (offset == 0) as u64),
+                            ("q_block", bits.q_block, (tx_id == 0) as u64),
+                            ("tx_id", bits.tx_id, tx_id as u64),
+                            ("bit_index", bits.bit_index, bit_index as u64),
+                            ("byte_index", bits.byte_index, byte_index as u64),
+                            ("q_byte_start", bits.q_byte_start, (row % 8 == 0) as u64),
+                            ("q_byte_end", bits.q_byte_end, (row % 8 == 7) as u64),
+                            ("q_limb_start", bits.q_limb_start, (row % 128 == 0) as u64),
+                            ("q_limb_end", bits.q_limb_end, (row % 128 == 127) as u64),
+                        ] {
+                            region.assign_fixed(
+                                || name,
+                                column,
+                                offset,
+                                || Value::known(F::from(value)),
+                            )?;
+                        }
+                        for (name, column, value) in [
+                            ("bit", bits.bit, F::from(bit as u64)),
+                            ("byte_acc", bits.byte_acc, F::from(byte_acc)),
+                        ] {
+                            region.assign_advice(
+                                || name,
+                                column,
+                                offset,
+                                || Value::known(value),
+                            )?;
+                        }
+                        let limb_cell = region.assign_advice(
+                            || "limb_acc",
+                            bits.limb_acc,
+                            offset,
+                            || Value::known(F::from_u128(limb_acc)),
+                        )?;
+                        if tx_id == 0 && row % 128 == 127 {
+                            limb_cells.push(limb_cell);
+                        }
+                        // The RLC is only accumulated in the rows of the
+                        // block bloom.
+                        let bloom_rlc_cell = region.assign_advice(
+                            || "bloom_rlc",
+                            bits.bloom_rlc,
+                            offset,
+                            || {
+                                if tx_id == 0 {
+                                    bloom_rlc
+                                } else {
+                                    Value::known(F::zero())
+                                }
+                            },
+                        )?;
+                        if tx_id == 0 && row == BLOOM_BITS - 1 {
+                            bloom_cell = Some(bloom_rlc_cell);
+                        }
+                    }
+                }
+                Ok((limb_cells, bloom_cell.expect("the block bloom is assigned")))
+            },
+        )
+    }
+}
+
+/// Item of the encoding of a receipt that a byte belongs to.  The lists are
+/// only their header, followed by the items of their payload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ReceiptItem {
+    #[default]
+    TxType,
+    Receipt,
+    Status,
+    CumulativeGas,
+    Bloom,
+    Logs,
+    Log,
+    Address,
+    Topics,
+    Topic,
+    Data,
+}
+
+/// Witness of a byte of the receipt rows
+#[derive(Clone, Debug, Default)]
+struct ReceiptRow {
+    tx_id: usize,
+    item: ReceiptItem,
+    is_receipt_start: bool,
+    is_receipt_end: bool,
+    /// Decoding of the byte in its item
+    rlp: RlpDecoderRow,
+}
+
+/// Header of a list with a payload of `len` bytes.
+fn list_header(len: usize) -> Vec<u8> {
+    if len < 56 {
+        vec![0xc0 + len as u8]
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        [vec![0xf7 + len_bytes.len() as u8], len_bytes].concat()
+    }
+}
+
+/// Generate the rows of an RLP item.  For lists, `item` is only the list
+/// header.
+fn item_rows(tx_id: usize, receipt_item: ReceiptItem, item: &[u8]) -> Vec<ReceiptRow> {
+    let is_list = matches!(
+        receipt_item,
+        ReceiptItem::Receipt | ReceiptItem::Logs | ReceiptItem::Log | ReceiptItem::Topics
+    );
+    let mut rlp = RlpDecoderRow::default();
+    item.iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            rlp = RlpDecoderRow::new(*byte, i == 0, is_list, &rlp);
+            debug_assert_eq!(rlp.is_item_end, i == item.len() - 1);
+            ReceiptRow {
+                tx_id,
+                item: receipt_item,
+                rlp,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Rows of the encoding of `receipt` (see [`Receipt::rlp`]).
+fn receipt_rows(receipt: &Receipt) -> Vec<ReceiptRow> {
+    let tx_id = receipt.tx_id;
+    let string_rows = |item, value: &[u8]| item_rows(tx_id, item, &rlp::encode(&value.to_vec()));
+    let list_rows = |item, payload: Vec<ReceiptRow>| {
+        [item_rows(tx_id, item, &list_header(payload.len())), payload].concat()
+    };
+
+    let logs = receipt
+        .logs
+        .iter()
+        .flat_map(|log| {
+            let topics = log
+                .topics
+                .iter()
+                .flat_map(|topic| string_rows(ReceiptItem::Topic, &topic.to_be_bytes()))
+                .collect();
+            list_rows(
+                ReceiptItem::Log,
+                [
+                    string_rows(ReceiptItem::Address, log.address.as_bytes()),
+                    list_rows(ReceiptItem::Topics, topics),
+                    string_rows(ReceiptItem::Data, &log.data),
+                ]
+                .concat(),
+            )
+        })
+        .collect();
+    let type_rows = match receipt.tx_type as u8 {
+        0 => vec![],
+        tx_type => item_rows(tx_id, ReceiptItem::TxType, &[tx_type]),
+    };
+    let mut rows = [
+        type_rows,
+        list_rows(
+            ReceiptItem::Receipt,
+            [
+                item_rows(tx_id, ReceiptItem::Status, &rlp::encode(&receipt.status)),
+                item_rows(
+                    tx_id,
+                    ReceiptItem::CumulativeGas,
+                    &rlp::encode(&receipt.cumulative_gas_used),
+                ),
+                string_rows(ReceiptItem::Bloom, receipt.bloom().as_bytes()),
+                list_rows(ReceiptItem::Logs, logs),
+            ]
+            .concat(),
+        ),
+    ]
+    .concat();
+    rows[0].is_receipt_start = true;
+    rows.last_mut().unwrap().is_receipt_end = true;
+    debug_assert_eq!(
+        rows.iter().map(|row| row.rlp.byte).collect::<Vec<_>>(),
+        receipt.rlp()
+    );
+    rows
+}
+
+/// Addresses and topics of the logs of the receipts, with the id of their tx.
+fn bloom_entries(receipts: &[Receipt]) -> Vec<(usize, Vec<u8>)> {
+    receipts
+        .iter()
+        .flat_map(|receipt| {
+            receipt.logs.iter().flat_map(move |log| {
+                std::iter::once(log.address.as_bytes().to_vec())
+                    .chain(log.topics.iter().map(|topic| topic.to_be_bytes().to_vec()))
+                    .map(move |item| (receipt.tx_id, item))
+            })
+        })
+        .collect()
+}
+
+/// Split a bloom in its 128 bit limbs, most significant first.
+fn split_bloom<F: Field>(bloom: &Bloom) -> Vec<F> {
+    bloom
+        .0
+        .chunks(16)
+        .map(|limb| F::from_u128(u128::from_be_bytes(limb.try_into().unwrap())))
+        .collect()
+}
+
+/// Inputs to the keccak table required to prove `receipts`: the addresses and
+/// topics of the logs, and the nodes of the receipts trie.
+pub fn receipts_keccak_inputs(receipts: &[Receipt]) -> Vec<Vec<u8>> {
+    bloom_entries(receipts)
+        .into_iter()
+        .map(|(_, item)| item)
+        .chain(list_trie_keccak_inputs(&receipts_values(receipts)))
+        .collect()
+}
+
+/// Values of the receipts trie: the encoding of every receipt.
+fn receipts_values(receipts: &[Receipt]) -> Vec<Vec<u8>> {
+    receipts.iter().map(Receipt::rlp).collect()
+}
+
+/// Number of rows required to prove the receipts, without padding
+fn num_rows(receipts: &[Receipt], max_txs: usize) -> usize {
+    let receipt_rows: usize = receipts.iter().map(|receipt| receipt.rlp().len()).sum();
+    let entry_rows = bloom_entries(receipts).len().max(1) * ENTRY_ROWS;
+    // The bloom bits are followed by an empty row for the disabled lookups
+    let bit_rows = (max_txs + 1) * BLOOM_BITS + 1;
+    let trie_rows = list_trie_num_rows(&receipts_values(receipts));
+    [receipt_rows + 1, entry_rows, bit_rows, trie_rows, 256]
+        .into_iter()
+        .max()
+        .unwrap()
+}
+
+/// Receipt Circuit for proving the receipts root and the logs bloom of a
+/// block
+#[derive(Clone, Default, Debug)]
+pub struct ReceiptCircuit<F: Field> {
+    /// Receipts of the txs of the block
+    pub receipts: Vec<Receipt>,
+    /// Number of the block
+    pub block_number: u64,
+    /// Max number of txs
+    pub max_txs: usize,
+    /// Whether the receipts are the ones of a single whole block, which is
+    /// the only witness that the circuit can prove
+    pub is_whole_block: bool,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> ReceiptCircuit<F> {
+    /// Return a new ReceiptCircuit
+    pub fn new(receipts: Vec<Receipt>, block_number: u64, max_txs: usize) -> Self {
+        Self {
+            receipts,
+            block_number,
+            max_txs,
+            is_whole_block: true,
+            _marker: PhantomData::default(),
+        }
+    }
+
+    /// Make the assignments to the ReceiptCircuit, and return the cells of
+    /// the values that it proves.
+    pub(crate) fn assign(
+        &self,
+        config: &ReceiptCircuitConfig<F>,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<ReceiptCells<F>, Error> {
+        if !self.is_whole_block {
+            error!("the receipt circuit can't prove a batch of blocks or a chunk of a block");
+            return Err(Error::Synthesis);
+        }
+        config.assign(layouter, &self.receipts, self.block_number, challenges)
+    }
+}
+
+impl<F: Field> SubCircuit<F> for ReceiptCircuit<F> {
+    type Config = ReceiptCircuitConfig<F>;
+
+    fn new_from_block(block: &witness::Block<F>) -> Self {
+        Self {
+            is_whole_block: block.context.ctxs.len() == 1 && block.context.chunk.total == 1,
+            ..Self::new(
+                block.receipts.clone(),
+                block.context.last().number.low_u64(),
+                block.circuits_params.max_txs,
+            )
+        }
+    }
+
+    /// The block number and the receipts root, split in hi/lo halves,
+    /// followed by the limbs of the logs bloom
+    fn instance(&self) -> Vec<Vec<F>> {
+        vec![[
+            vec![F::from(self.block_number)],
+            split_root(receipts_trie(&self.receipts).root().to_word()).to_vec(),
+            split_bloom(&logs_bloom(&self.receipts)),
+        ]
+        .concat()]
+    }
+
+    /// Return the minimum number of rows required to prove the block
+    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
+        let rows = num_rows(&block.receipts, block.circuits_params.max_txs);
+        (rows, rows)
+    }
+
+    /// Make the assignments to the ReceiptCircuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        self.assign(config, challenges, layouter).map(|_| ())
+    }
+}
+
+/// TxReceipt and TxLog rows of the RwTable that correspond to `receipts`.
+#[cfg(any(feature = "test", test))]
+fn receipt_rws(receipts: &[Receipt]) -> Vec<Rw> {
+    let mut rws = Vec::new();
+    for receipt in receipts {
+        let tx_id = receipt.tx_id;
+        for (field_tag, value) in [
+            (TxReceiptFieldTag::PostStateOrStatus, receipt.status),
+            (
+                TxReceiptFieldTag::CumulativeGasUsed,
+                receipt.cumulative_gas_used,
+            ),
+            (TxReceiptFieldTag::LogLength, receipt.logs.len() as u64),
+        ] {
+            rws.push(Rw::TxReceipt {
+                rw_counter: rws.len() + 1,
+                is_write: true,
+                tx_id,
+                field_tag,
+                value,
+            });
+        }
+        for (i, log) in receipt.logs.iter().enumerate() {
+            let values = [
+                (TxLogFieldTag::Address, 0, log.address.to_word()),
+                (TxLogFieldTag::TopicLength, 0, Word::from(log.topics.len())),
+                (TxLogFieldTag::DataLength, 0, Word::from(log.data.len())),
+            ]
+            .into_iter()
+            .chain(
+                log.topics
+                    .iter()
+                    .enumerate()
+                    .map(|(index, topic)| (TxLogFieldTag::Topic, index, *topic)),
+            )
+            .chain(
+                log.data
+                    .iter()
+                    .enumerate()
+                    .map(|(index, byte)| (TxLogFieldTag::Data, index, Word::from(*byte))),
+            );
+            for (field_tag, index, value) in values {
+                rws.push(Rw::TxLog {
+                    rw_counter: rws.len() + 1,
+                    is_write: true,
+                    tx_id,
+                    log_id: i as u64 + 1,
+                    field_tag,
+                    index,
+                    value,
+                });
+            }
+        }
+    }
+    rws
+}
+
+#[cfg(any(feature = "test", test))]
+impl<F: Field> ReceiptCircuit<F> {
+    /// Load the RwTable and the TxTable of the receipts, and `keccak_inputs`
+    /// in the keccak table.
+    fn load_tables(
+        &self,
+        config: &ReceiptCircuitConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        keccak_inputs: &[Vec<u8>],
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        let rws = receipt_rws(&self.receipts);
+        config
+            .rw_table
+            .load(layouter, &rws, rws.len() + 1, challenges.evm_word())?;
+        let txs: Vec<Transaction> = self
+            .receipts
+            .iter()
+            .map(|receipt| Transaction {
+                id: receipt.tx_id,
+                tx_type: receipt.tx_type,
+                block_number: self.block_number,
+                caller_address: Address::repeat_byte(0xfe),
+                ..Default::default()
+            })
+            .collect();
+        config
+            .tx_table
+            .load(layouter, &txs, self.max_txs, challenges)?;
+        config
+            .keccak_table
+            .dev_load(layouter, keccak_inputs, challenges)
+    }
+}
+
+// The ReceiptTestCircuit is a wrapper over ReceiptCircuit that takes the
+// generic const parameter MAX_TXS, which is required during the configuration
+// to know the id of the tx after the last one of the TxTable.
+/// Test Circuit for ReceiptCircuit
+#[cfg(any(feature = "test", test))]
+#[derive(Default)]
+pub struct ReceiptTestCircuit<F: Field, const MAX_TXS: usize>(pub ReceiptCircuit<F>);
+
+#[cfg(any(feature = "test", test))]
+impl<F: Field, const MAX_TXS: usize> Circuit<F> for ReceiptTestCircuit<F, MAX_TXS> {
+    type Config = (ReceiptCircuitConfig<F>, Challenges);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let rw_table = RwTable::construct(meta);
+        let tx_table = TxTable::construct(meta);
+        let keccak_table = KeccakTable::construct(meta);
+        let challenges = Challenges::construct(meta);
+
+        let config = {
+            let challenges = challenges.exprs(meta);
+            ReceiptCircuitConfig::new(
+                meta,
+                ReceiptCircuitConfigArgs {
+                    rw_table,
+                    tx_table,
+                    keccak_table,
+                    max_txs: MAX_TXS,
+                    challenges,
+                },
+            )
+        };
+
+        (config, challenges)
+    }
+
+    fn synthesize(
+        &self,
+        (config, challenges): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let challenges = challenges.values(&mut layouter);
+        self.0.load_tables(
+            &config,
+            &mut layouter,
+            &receipts_keccak_inputs(&self.0.receipts),
+            &challenges,
+        )?;
+        self.0.synthesize_sub(&config, &challenges, &mut layouter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::{block_convert, Log};
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData, geth_types::TxType};
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+    };
+    use mock::TestContext;
+
+    const MAX_TXS: usize = 3;
+    const BLOCK_NUMBER: u64 = 0xcafe;
+
+    fn receipts() -> Vec<Receipt> {
+        vec![
+            Receipt {
+                tx_id: 1,
+                tx_type: TxType::Legacy,
+                status: 1,
+                cumulative_gas_used: 21000,
+                logs: vec![],
+            },
+            Receipt {
+                tx_id: 2,
+                tx_type: TxType::Eip1559,
+                status: 1,
+                cumulative_gas_used: 75000,
+                logs: vec![
+                    Log {
+                        address: Address::repeat_byte(0xaa),
+                        topics: vec![Word::from(0x1234), Word::MAX],
+                        data: vec![0x01, 0x02, 0x03],
+                    },
+                    Log {
+                        address: Address::repeat_byte(0xbb),
+                        topics: vec![],
+                        data: vec![0x7f],
+                    },
+                    Log {
+                        address: Address::repeat_byte(0xcc),
+                        topics: vec![Word::from(0x80)],
+                        data: vec![0xab; 60],
+                    },
+                ],
+            },
+            Receipt {
+                tx_id: 3,
+                tx_type: TxType::Eip2930,
+                status: 0,
+                cumulative_gas_used: 100000,
+                logs: vec![],
+            },
+        ]
+    }
+
+    /// Circuit that assigns `receipts` against the tables of the receipts of
+    /// `tables`.
+    struct TamperedReceiptCircuit {
+        tables: ReceiptCircuit<Fr>,
+        receipts: ReceiptCircuit<Fr>,
+    }
+
+    impl Circuit<Fr> for TamperedReceiptCircuit {
+        type Config = (ReceiptCircuitConfig<Fr>, Challenges);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            ReceiptTestCircuit::<Fr, MAX_TXS>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            (config, challenges): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let challenges = challenges.values(&mut layouter);
+            let keccak_inputs = [
+                receipts_keccak_inputs(&self.tables.receipts),
+                receipts_keccak_inputs(&self.receipts.receipts),
+            ]
+            .concat();
+            self.tables
+                .load_tables(&config, &mut layouter, &keccak_inputs, &challenges)?;
+            self.receipts
+                .synthesize_sub(&config, &challenges, &mut layouter)
+        }
+    }
+
+    fn test_receipt_circuit(
+        receipts: Vec<Receipt>,
+        instance: Option<Vec<Fr>>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let circuit =
+            ReceiptTestCircuit::<Fr, MAX_TXS>(ReceiptCircuit::new(receipts, BLOCK_NUMBER, MAX_TXS));
+        let instance = instance.map_or_else(|| circuit.0.instance(), |instance| vec![instance]);
+        let prover = MockProver::<Fr>::run(14, &circuit, instance).unwrap();
+        prover.verify()
+    }
+
+    /// Prove `receipts` against the tables of the receipts of the block.
+    fn test_tampered_receipts(receipts: Vec<Receipt>) -> Result<(), Vec<VerifyFailure>> {
+        let circuit = TamperedReceiptCircuit {
+            tables: ReceiptCircuit::new(self::receipts(), BLOCK_NUMBER, MAX_TXS),
+            receipts: ReceiptCircuit::new(receipts, BLOCK_NUMBER, MAX_TXS),
+        };
+        let instance = circuit.receipts.instance();
+        let prover = MockProver::<Fr>::run(14, &circuit, instance).unwrap();
+        prover.verify()
+    }
+
+    #[test]
+    fn receipt_circuit_receipts() {
+        assert_eq!(test_receipt_circuit(receipts(), None), Ok(()));
+        assert_eq!(test_tampered_receipts(receipts()), Ok(()));
+    }
+
+    #[test]
+    fn receipt_circuit_no_receipts() {
+        assert_eq!(test_receipt_circuit(vec![], None), Ok(()));
+    }
+
+    /// Witness of a block with a tx that emits two logs
+    fn block_with_logs() -> witness::Block<Fr> {
+        let code = bytecode! {
+            PUSH32(Word::from(0x2a))
+            PUSH1(0x00)
+            MSTORE
+            PUSH32(Word::from(0xbeef))
+            PUSH32(Word::from(0xcafe))
+            PUSH1(0x20)
+            PUSH1(0x00)
+            LOG2
+            PUSH1(0x00)
+            PUSH1(0x00)
+            LOG0
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(code)
+            .unwrap()
+            .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        block_convert::<Fr>(&builder.block, &builder.code_db).unwrap()
+    }
+
+    #[test]
+    fn receipt_circuit_from_block() {
+        let block = block_with_logs();
+        assert_eq!(block.receipts.len(), 1);
+        assert_eq!(block.receipts[0].status, 1);
+        assert_eq!(block.receipts[0].logs.len(), 2);
+        assert_eq!(
+            block.receipts[0].logs[0].topics,
+            vec![Word::from(0xcafe), Word::from(0xbeef)]
+        );
+        assert_eq!(block.receipts[0].logs[0].data.len(), 0x20);
+
+        assert_eq!(block.circuits_params.max_txs, 1);
+        let circuit = ReceiptTestCircuit::<Fr, 1>(ReceiptCircuit::new_from_block(&block));
+        let prover = MockProver::<Fr>::run(14, &circuit, circuit.0.instance()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn receipt_circuit_rejects_batch_and_chunk() {
+        let mut batch = block_with_logs();
+        let mut prev_ctx = batch.context.last().clone();
+        prev_ctx.number = prev_ctx.number - 1;
+        batch
+            .context
+            .ctxs
+            .insert(prev_ctx.number.low_u64(), prev_ctx);
+        let mut chunk = block_with_logs();
+        chunk.context.chunk.total = 2;
+
+        for block in [batch, chunk] {
+            let circuit = ReceiptTestCircuit::<Fr, 1>(ReceiptCircuit::new_from_block(&block));
+            assert!(!circuit.0.is_whole_block);
+            assert!(MockProver::<Fr>::run(14, &circuit, circuit.0.instance()).is_err());
+        }
+    }
+
+    #[test]
+    fn receipt_circuit_wrong_root() {
+        let circuit = ReceiptCircuit::<Fr>::new(receipts(), BLOCK_NUMBER, MAX_TXS);
+        let mut instance = circuit.instance().remove(0);
+        instance[2] += Fr::one();
+        assert!(test_receipt_circuit(receipts(), Some(instance)).is_err());
+    }
+
+    #[test]
+    fn receipt_circuit_wrong_bloom() {
+        let circuit = ReceiptCircuit::<Fr>::new(receipts(), BLOCK_NUMBER, MAX_TXS);
+        let mut instance = circuit.instance().remove(0);
+        instance[3 + BLOOM_LIMBS - 1] += Fr::one();
+        assert!(test_receipt_circuit(receipts(), Some(instance)).is_err());
+    }
+
+    #[test]
+    fn receipt_circuit_wrong_block_number() {
+        let circuit = ReceiptCircuit::<Fr>::new(receipts(), BLOCK_NUMBER, MAX_TXS);
+        let mut instance = circuit.instance().remove(0);
+        instance[0] += Fr::one();
+        assert!(test_receipt_circuit(receipts(), Some(instance)).is_err());
+    }
+
+    #[test]
+    fn receipt_circuit_missing_receipt() {
+        let mut receipts = receipts();
+        receipts.pop();
+        assert!(test_tampered_receipts(receipts).is_err());
+    }
+
+    #[test]
+    fn receipt_circuit_missing_log() {
+        let mut receipts = receipts();
+        receipts[1].logs.pop();
+        assert!(test_tampered_receipts(receipts).is_err());
+    }
+
+    #[test]
+    fn receipt_circuit_missing_topic() {
+        let mut receipts = receipts();
+        receipts[1].logs[0].topics.pop();
+        assert!(test_tampered_receipts(receipts).is_err());
+    }
+
+    #[test]
+    fn receipt_circuit_missing_data() {
+        let mut receipts = receipts();
+        receipts[1].logs[0].data.pop();
+        assert!(test_tampered_receipts(receipts).is_err());
+    }
+}
@@ -0,0 +1,2492 @@
+//! The RLP circuit decodes the signed message of the transactions and proves
+//! that it's the RLP encoding of the fields of the transaction in the TxTable.
+//! The messages of the supported transaction types are:
+//!
+//! - Legacy (EIP-155): `rlp([nonce, gas_price, gas, to, value, data, chain_id,
+//!   0, 0])`
+//! - EIP-2930: `0x01 || rlp([chain_id, nonce, gas_price, gas, to, value, data,
+//!   access_list])`
+//! - EIP-1559: `0x02 || rlp([chain_id, nonce, max_priority_fee_per_gas,
+//!   max_fee_per_gas, gas, to, value, data, access_list])`
+//!
+//! The circuit assigns one byte of the message per row.  Every RLP item of the
+//! message (the type byte, the list header and each of the fields) is split in
+//! header rows, with the bytes of the RLP prefix, and value rows, with the
+//! bytes of the payload.  Items shorter than 0x80 bytes that are encoded as a
+//! single byte only have a value row.  The circuit verifies that:
+//!
+//! - The prefix of every item is decoded correctly, and the number of payload
+//!   bytes of every item matches its prefix.
+//! - The items follow the order of the fields of the type of the transaction.
+//! - The length of the list matches the total length of the fields.
+//! - The value of each field and the type of the transaction match the
+//!   corresponding rows of the TxTable, and the chain_id matches the one of the
+//!   BlockTable.
+//! - The hash of the message, looked up in the keccak table, is the one exposed
+//!   in the RlpTable as `TxSignHash`, where the Tx circuit looks it up to link
+//!   it to the signature verification.
+//! - The number of addresses and storage keys of the access list match the
+//!   access list lengths of the TxTable.
+//!
+//! Every transaction is decoded twice: its signed message is followed by its
+//! signed encoding, the value of the transactions trie, in which the signature
+//! replaces the chain_id and the empty r and s of legacy messages, and follows
+//! the access list of typed messages:
+//!
+//! - Legacy: `rlp([nonce, gas_price, gas, to, value, data, v, r, s])`
+//! - EIP-2930 and EIP-1559: the fields of the message followed by `y_parity, r,
+//!   s`
+//!
+//! The fields of the signed encoding are decoded and looked up in the TxTable
+//! like the ones of the message.  Instead of its hash, the RLC of the signed
+//! encoding and its length are exposed in the RlpTable as `SignedTx`, where
+//! the PI circuit looks them up to link the transactions trie to the TxTable.
+//! The signature itself is verified by the Tx circuit, so its fields are not
+//! constrained here.
+//!
+//! The payload of the access list is decoded in parts: the header of each
+//! entry, its address, the header of its list of storage keys and each of the
+//! storage keys.
+//!
+//! The encoding of the fields is not required to be canonical: since the
+//! signature is verified over the hash of the exact bytes, a non-canonical
+//! encoding would just produce a different (unsigned) message.
+
+use crate::{
+    evm_circuit::util::{constraint_builder::BaseConstraintBuilder, rlc},
+    table::{
+        BlockContextFieldTag, BlockTable, DynamicTableColumns, KeccakTable, LookupTable, RlpTable,
+        TxFieldTag, TxTable,
+    },
+    util::{
+        keccak,
+        rlp::{RlpByteClass, RlpByteTable, RlpDecoderConfig, RlpDecoderRow},
+        Challenges, SubCircuit, SubCircuitConfig,
+    },
+    witness,
+};
+use eth_types::{
+    geth_types::{Transaction, TxType},
+    Field, ToLittleEndian, Word,
+};
+use ethers_core::utils::rlp::{DecoderError, Rlp};
+use gadgets::{
+    binary_number::{BinaryNumberChip, BinaryNumberConfig},
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{and, not, select, sum, Expr},
+};
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase, VirtualCells,
+    },
+    poly::Rotation,
+};
+use log::error;
+use std::marker::PhantomData;
+use strum_macros::EnumIter;
+
+#[cfg(any(feature = "test", test))]
+use crate::{
+    tx_circuit::tx_fields,
+    witness::{BlockContext, BlockContexts},
+};
+#[cfg(any(feature = "test", test))]
+use halo2_proofs::{circuit::SimpleFloorPlanner, plonk::Circuit};
+
+/// Maximum number of rows used by the signed message and the signed encoding
+/// of a transaction without call data and access list, which are the ones of
+/// an EIP-1559 transaction: the type byte (1), the list header (5), chain_id
+/// (9), nonce (9), max_priority_fee_per_gas (33), max_fee_per_gas (33), gas
+/// (9), to (21), value (33), call data header (5) and access list header (5)
+/// in both, and y_parity (1), r (33) and s (33) in the signed encoding.
+pub const TX_MAX_FIXED_ROWS: usize = 2 * 163 + 67;
+
+/// Maximum number of RlpTable rows with a field of a tx, without the call
+/// data, which is the one of an EIP-1559 transaction: chain_id, nonce,
+/// max_priority_fee_per_gas, max_fee_per_gas, gas, callee_address and value
+/// in both the signed message and the signed encoding, tx_sign_hash and the
+/// signed tx.
+pub const TABLE_MAX_ROWS_PER_TX: usize = 16;
+
+const MAX_DEGREE: usize = 9;
+
+/// Item of the signed message of a transaction that is decoded in a row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter)]
+pub enum RlpTxTag {
+    /// Header of the list of fields
+    #[default]
+    Prefix = 0,
+    /// Nonce
+    Nonce,
+    /// Gas price
+    GasPrice,
+    /// Gas
+    Gas,
+    /// Callee address, empty for contract creations
+    To,
+    /// Value
+    Value,
+    /// Call data
+    Data,
+    /// Chain ID
+    ChainId,
+    /// Signature r, which is 0 in the signed message of legacy transactions
+    SigR,
+    /// Signature s, which is 0 in the signed message of legacy transactions
+    SigS,
+    /// Type byte of the envelope of typed transactions
+    TxType,
+    /// Max priority fee per gas
+    MaxPriorityFeePerGas,
+    /// Max fee per gas
+    MaxFeePerGas,
+    /// Access list, whose payload is decoded in [`AccessListPart`]s
+    AccessList,
+    /// Signature v, or y_parity for typed transactions, only in the signed
+    /// encoding
+    SigV,
+}
+
+/// Part of the payload of an access list that is decoded in a row: `[[address,
+/// [storage_key, ...]], ...]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum AccessListPart {
+    /// Header of the list of an entry
+    #[default]
+    Entry,
+    /// Address of an entry, with its header
+    Address,
+    /// Header of the list of storage keys of an entry
+    StorageKeys,
+    /// Storage key, with its header
+    StorageKey,
+}
+
+impl From<RlpTxTag> for usize {
+    fn from(tag: RlpTxTag) -> Self {
+        tag as usize
+    }
+}
+
+impl RlpTxTag {
+    /// Return the items of the signed message of a transaction of type
+    /// `tx_type`, or of its signed encoding when `is_signed`, in order.
+    fn tx_tags(tx_type: TxType, is_signed: bool) -> Vec<Self> {
+        let mut tags = Self::message_tags(tx_type).to_vec();
+        if is_signed {
+            // The signature replaces the chain_id and the empty r and s of
+            // legacy messages, and follows the access list of typed messages.
+            if tx_type == TxType::Legacy {
+                tags.truncate(tags.len() - 3);
+            }
+            tags.extend([Self::SigV, Self::SigR, Self::SigS]);
+        }
+        tags
+    }
+
+    /// Return the items of the signed message of a transaction of type
+    /// `tx_type`, in order.
+    fn message_tags(tx_type: TxType) -> &'static [Self] {
+        match tx_type {
+            TxType::Legacy => &[
+                Self::Prefix,
+                Self::Nonce,
+                Self::GasPrice,
+                Self::Gas,
+                Self::To,
+                Self::Value,
+                Self::Data,
+                Self::ChainId,
+                Self::SigR,
+                Self::SigS,
+            ],
+            TxType::Eip2930 => &[
+                Self::TxType,
+                Self::Prefix,
+                Self::ChainId,
+                Self::Nonce,
+                Self::GasPrice,
+                Self::Gas,
+                Self::To,
+                Self::Value,
+                Self::Data,
+                Self::AccessList,
+            ],
+            TxType::Eip1559 => &[
+                Self::TxType,
+                Self::Prefix,
+                Self::ChainId,
+                Self::Nonce,
+                Self::MaxPriorityFeePerGas,
+                Self::MaxFeePerGas,
+                Self::Gas,
+                Self::To,
+                Self::Value,
+                Self::Data,
+                Self::AccessList,
+            ],
+        }
+    }
+}
+
+/// Config for RlpCircuit
+#[derive(Clone, Debug)]
+pub struct RlpCircuitConfig<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_last: Column<Fixed>,
+    byte_table: RlpByteTable,
+    /// Table of the transitions between the tags of the items of each type of
+    /// tx: (q_transition_table, tx_type_table, tag_table, tag_next_table).
+    q_transition_table: Column<Fixed>,
+    tx_type_table: Column<Fixed>,
+    tag_table: Column<Fixed>,
+    tag_next_table: Column<Fixed>,
+    is_signed_table: Column<Fixed>,
+
+    byte: Column<Advice>,
+    is_padding: Column<Advice>,
+    /// Whether the row decodes the signed encoding of the tx, instead of its
+    /// signed message
+    is_signed: Column<Advice>,
+    tag: BinaryNumberConfig<RlpTxTag, 4>,
+    /// Decoding of the headers of the items, whose containers are the lists
+    rlp: RlpDecoderConfig<F>,
+    is_list: Column<Advice>,
+    is_tx_type: Column<Advice>,
+    tx_type: Column<Advice>,
+    value_acc: Column<Advice>,
+    tx_remaining: Column<Advice>,
+    keccak_len: Column<Advice>,
+    is_field: Column<Advice>,
+    is_to_end: Column<Advice>,
+    is_data_end: Column<Advice>,
+    is_chain_id_end: Column<Advice>,
+    is_tx_end: Column<Advice>,
+
+    // Decoding of the payload of the access list
+    is_al_entry: Column<Advice>,
+    is_al_address: Column<Advice>,
+    is_al_storage_keys: Column<Advice>,
+    is_al_storage_key: Column<Advice>,
+    is_al_part_start: Column<Advice>,
+    al_counter: Column<Advice>,
+    al_length: Column<Advice>,
+    al_entry_remaining: Column<Advice>,
+    al_storage_keys_remaining: Column<Advice>,
+    al_addresses: Column<Advice>,
+    al_storage_keys: Column<Advice>,
+
+    value_rlc: Column<Advice>,
+    keccak_rlc: Column<Advice>,
+    hash_rlc: Column<Advice>,
+
+    al_counter_is_zero: IsZeroConfig<F>,
+    al_storage_keys_remaining_is_zero: IsZeroConfig<F>,
+
+    /// RLP table
+    pub rlp_table: RlpTable,
+    /// Tx table
+    pub tx_table: TxTable,
+    /// Block table
+    pub block_table: BlockTable,
+    /// Keccak table
+    pub keccak_table: KeccakTable,
+}
+
+/// Circuit configuration arguments
+pub struct RlpCircuitConfigArgs<F: Field> {
+    /// RlpTable
+    pub rlp_table: RlpTable,
+    /// TxTable
+    pub tx_table: TxTable,
+    /// BlockTable
+    pub block_table: BlockTable,
+    /// KeccakTable
+    pub keccak_table: KeccakTable,
+    /// Challenges
+    pub challenges: Challenges<Expression<F>>,
+}
+
+impl<F: Field> SubCircuitConfig<F> for RlpCircuitConfig<F> {
+    type ConfigArgs = RlpCircuitConfigArgs<F>;
+
+    /// Return a new RlpCircuitConfig
+    fn new(
+        meta: &mut ConstraintSystem<F>,
+        Self::ConfigArgs {
+            rlp_table,
+            tx_table,
+            block_table,
+            keccak_table,
+            challenges,
+        }: Self::ConfigArgs,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_first = meta.fixed_column();
+        let q_last = meta.fixed_column();
+        let byte_table = RlpByteTable::configure(meta);
+        let q_transition_table = meta.fixed_column();
+        let tx_type_table = meta.fixed_column();
+        let tag_table = meta.fixed_column();
+        let tag_next_table = meta.fixed_column();
+        let is_signed_table = meta.fixed_column();
+
+        let byte = meta.advice_column();
+        let is_padding = meta.advice_column();
+        let is_signed = meta.advice_column();
+        let tag = BinaryNumberChip::configure(meta, q_enable, None);
+        let is_list = meta.advice_column();
+        let is_tx_type = meta.advice_column();
+        let tx_type = meta.advice_column();
+        let value_acc = meta.advice_column();
+        let tx_remaining = meta.advice_column();
+        let keccak_len = meta.advice_column();
+        let is_field = meta.advice_column();
+        let is_to_end = meta.advice_column();
+        let is_data_end = meta.advice_column();
+        let is_chain_id_end = meta.advice_column();
+        let is_tx_end = meta.advice_column();
+
+        let is_al_entry = meta.advice_column();
+        let is_al_address = meta.advice_column();
+        let is_al_storage_keys = meta.advice_column();
+        let is_al_storage_key = meta.advice_column();
+        let is_al_part_start = meta.advice_column();
+        let al_counter = meta.advice_column();
+        let al_length = meta.advice_column();
+        let al_entry_remaining = meta.advice_column();
+        let al_storage_keys_remaining = meta.advice_column();
+        let al_addresses = meta.advice_column();
+        let al_storage_keys = meta.advice_column();
+
+        let value_rlc = meta.advice_column_in(SecondPhase);
+        let keccak_rlc = meta.advice_column_in(SecondPhase);
+        let hash_rlc = meta.advice_column_in(SecondPhase);
+
+        let rlp = RlpDecoderConfig::configure(
+            meta,
+            |meta| {
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * not::expr(meta.query_advice(is_padding, Rotation::cur()))
+            },
+            byte,
+            &byte_table,
+            |meta| meta.query_advice(is_list, Rotation::cur()),
+        );
+        let RlpDecoderConfig {
+            is_item_start,
+            is_item_end,
+            is_header,
+            classes: [_, _, is_short_list, is_long_list],
+            counter,
+            length,
+            ref length_is_zero,
+            ..
+        } = rlp;
+
+        let al_counter_inv = meta.advice_column();
+        let al_counter_is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_fixed(q_enable, Rotation::cur()),
+            |meta| meta.query_advice(al_counter, Rotation::cur()),
+            al_counter_inv,
+        );
+        let al_storage_keys_remaining_inv = meta.advice_column();
+        let al_storage_keys_remaining_is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_fixed(q_enable, Rotation::cur()),
+            |meta| meta.query_advice(al_storage_keys_remaining, Rotation::cur()),
+            al_storage_keys_remaining_inv,
+        );
+
+        let table_columns: [Column<Advice>; 4] = rlp_table.columns().try_into().unwrap();
+        let tx_id = rlp_table.tx_id;
+
+        meta.create_gate("flags are boolean", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+            for (name, column) in [
+                ("is_padding is boolean", is_padding),
+                ("is_signed is boolean", is_signed),
+                ("is_tx_type is boolean", is_tx_type),
+                ("is_field is boolean", is_field),
+            ] {
+                cb.require_boolean(name, meta.query_advice(column, Rotation::cur()));
+            }
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("first row", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            cb.condition(
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+                |cb| {
+                    cb.require_equal(
+                        "the first row starts an item",
+                        meta.query_advice(is_item_start, Rotation::cur()),
+                        1.expr(),
+                    );
+                    cb.require_equal(
+                        "the first row is the type byte or the list header",
+                        meta.query_advice(is_tx_type, Rotation::cur())
+                            + meta.query_advice(is_list, Rotation::cur()),
+                        1.expr(),
+                    );
+                    cb.require_equal(
+                        "the type of the first tx is its type byte, or 0 for legacy txs",
+                        meta.query_advice(tx_type, Rotation::cur()),
+                        meta.query_advice(is_tx_type, Rotation::cur())
+                            * meta.query_advice(byte, Rotation::cur()),
+                    );
+                    cb.require_equal(
+                        "the first tx has id 1",
+                        meta.query_advice(tx_id, Rotation::cur()),
+                        1.expr(),
+                    );
+                    cb.require_zero(
+                        "the first tx starts with its signed message",
+                        meta.query_advice(is_signed, Rotation::cur()),
+                    );
+                    cb.require_equal(
+                        "keccak_rlc starts with the first byte",
+                        meta.query_advice(keccak_rlc, Rotation::cur()),
+                        meta.query_advice(byte, Rotation::cur()),
+                    );
+                    cb.require_equal(
+                        "keccak_len starts at 1",
+                        meta.query_advice(keccak_len, Rotation::cur()),
+                        1.expr(),
+                    );
+                },
+            );
+
+            cb.gate(meta.query_fixed(q_first, Rotation::cur()))
+        });
+
+        meta.create_gate("the last row is padding", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            cb.require_equal(
+                "the last row is padding",
+                meta.query_advice(is_padding, Rotation::cur()),
+                1.expr(),
+            );
+
+            cb.gate(meta.query_fixed(q_last, Rotation::cur()))
+        });
+
+        meta.create_gate("padding", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_padding_prev = meta.query_advice(is_padding, Rotation::prev());
+            let is_padding = meta.query_advice(is_padding, Rotation::cur());
+
+            cb.require_zero(
+                "padding is not followed by a tx",
+                is_padding_prev.clone() * not::expr(is_padding.clone()),
+            );
+            cb.condition(is_padding - is_padding_prev, |cb| {
+                cb.require_equal(
+                    "padding starts after the end of a tx",
+                    meta.query_advice(is_tx_end, Rotation::prev()),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "padding starts after the signed encoding of a tx",
+                    meta.query_advice(is_signed, Rotation::prev()),
+                    1.expr(),
+                );
+            });
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    - meta.query_fixed(q_first, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("padding rows are empty", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            for column in table_columns {
+                cb.require_zero(
+                    "rlp table is empty in padding rows",
+                    meta.query_advice(column, Rotation::cur()),
+                );
+            }
+            for column in [
+                is_item_start,
+                is_item_end,
+                is_field,
+                is_to_end,
+                is_data_end,
+                is_chain_id_end,
+                is_tx_end,
+            ] {
+                cb.require_zero(
+                    "flags are disabled in padding rows",
+                    meta.query_advice(column, Rotation::cur()),
+                );
+            }
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_padding, Rotation::cur()),
+            ]))
+        });
+
+        meta.create_gate("items", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_item_start = meta.query_advice(is_item_start, Rotation::cur());
+            let is_header = meta.query_advice(is_header, Rotation::cur());
+            let is_list = meta.query_advice(is_list, Rotation::cur());
+            let value_acc = meta.query_advice(value_acc, Rotation::cur());
+            let value_rlc = meta.query_advice(value_rlc, Rotation::cur());
+
+            cb.require_equal(
+                "the list header is decoded in the Prefix rows",
+                is_list.clone(),
+                tag.value_equals(RlpTxTag::Prefix, Rotation::cur())(meta),
+            );
+            cb.require_equal(
+                "the type byte is decoded in the TxType rows",
+                meta.query_advice(is_tx_type, Rotation::cur()),
+                tag.value_equals(RlpTxTag::TxType, Rotation::cur())(meta),
+            );
+            let is_access_list = tag.value_equals(RlpTxTag::AccessList, Rotation::cur())(meta);
+
+            // The header of the item is decoded by the RLP decoder
+            cb.condition(is_item_start, |cb| {
+                cb.require_equal(
+                    "only the Prefix and the AccessList items are lists",
+                    rlp.is_list(meta),
+                    is_list + is_access_list,
+                );
+                cb.require_equal(
+                    "value_acc starts with the single byte item",
+                    value_acc.clone(),
+                    not::expr(is_header.clone()) * byte.clone(),
+                );
+                cb.require_equal(
+                    "value_rlc starts with the single byte item",
+                    value_rlc.clone(),
+                    not::expr(is_header.clone()) * byte.clone(),
+                );
+            });
+
+            cb.condition(is_header, |cb| {
+                cb.require_zero("header rows don't have a value", value_acc);
+                cb.require_zero("header rows don't have a value", value_rlc);
+            });
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("item transitions", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_item_start = meta.query_advice(is_item_start, Rotation::cur());
+            let is_header = meta.query_advice(is_header, Rotation::cur());
+            let is_list_prev = meta.query_advice(is_list, Rotation::prev());
+            let is_list = meta.query_advice(is_list, Rotation::cur());
+            let is_tx_type = meta.query_advice(is_tx_type, Rotation::cur());
+            let is_tx_end_prev = meta.query_advice(is_tx_end, Rotation::prev());
+            let tx_type_prev = meta.query_advice(tx_type, Rotation::prev());
+            let tx_type = meta.query_advice(tx_type, Rotation::cur());
+            let length_prev = meta.query_advice(length, Rotation::prev());
+            let tag_prev = tag.value(Rotation::prev())(meta);
+            let tag = tag.value(Rotation::cur())(meta);
+            let tx_id_prev = meta.query_advice(tx_id, Rotation::prev());
+            let tx_id = meta.query_advice(tx_id, Rotation::cur());
+            let is_signed_prev = meta.query_advice(is_signed, Rotation::prev());
+            let is_signed = meta.query_advice(is_signed, Rotation::cur());
+
+            cb.require_equal(
+                "an item starts after the end of the previous one",
+                is_item_start.clone(),
+                meta.query_advice(is_item_end, Rotation::prev()),
+            );
+
+            cb.require_equal(
+                "tx_id increases by 1 after the end of the signed encoding of a tx",
+                tx_id,
+                tx_id_prev + is_tx_end_prev.clone() * is_signed_prev.clone(),
+            );
+            cb.condition(is_tx_end_prev.clone(), |cb| {
+                cb.require_equal(
+                    "the signed message and the signed encoding of a tx alternate",
+                    is_signed.clone(),
+                    not::expr(is_signed_prev.clone()),
+                );
+            });
+            cb.condition(not::expr(is_tx_end_prev.clone()), |cb| {
+                cb.require_equal(
+                    "is_signed is the same within a message",
+                    is_signed,
+                    is_signed_prev,
+                );
+            });
+
+            // The order of the items of a tx is checked with the tag transitions lookup.
+            cb.condition(is_tx_end_prev.clone(), |cb| {
+                cb.require_equal(
+                    "a tx starts with the type byte or the list header",
+                    is_tx_type.clone() + is_list.clone(),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "the type of a tx is its type byte, or 0 for legacy txs",
+                    tx_type.clone(),
+                    is_tx_type.clone() * byte.clone(),
+                );
+            });
+            cb.condition(not::expr(is_tx_end_prev.clone()), |cb| {
+                cb.require_equal("tx_type is the same within a tx", tx_type, tx_type_prev);
+            });
+
+            cb.condition(not::expr(is_item_start.clone()), |cb| {
+                cb.require_equal("tag is the same within an item", tag, tag_prev);
+            });
+
+            // Payload bytes
+            cb.condition(
+                and::expr([not::expr(is_header), not::expr(is_item_start)]),
+                |cb| {
+                    cb.require_equal(
+                        "value_acc accumulates the payload bytes",
+                        meta.query_advice(value_acc, Rotation::cur()),
+                        meta.query_advice(value_acc, Rotation::prev()) * 256.expr() + byte.clone(),
+                    );
+                    cb.require_equal(
+                        "value_rlc accumulates the payload bytes",
+                        meta.query_advice(value_rlc, Rotation::cur()),
+                        meta.query_advice(value_rlc, Rotation::prev()) * challenges.evm_word()
+                            + byte.clone(),
+                    );
+                },
+            );
+
+            // The message of the tx
+            let is_tx_start = is_tx_end_prev;
+            cb.condition(is_tx_start.clone(), |cb| {
+                cb.require_equal(
+                    "keccak_rlc starts with the first byte of the tx",
+                    meta.query_advice(keccak_rlc, Rotation::cur()),
+                    byte.clone(),
+                );
+                cb.require_equal(
+                    "keccak_len starts at 1",
+                    meta.query_advice(keccak_len, Rotation::cur()),
+                    1.expr(),
+                );
+            });
+            cb.condition(not::expr(is_tx_start), |cb| {
+                cb.require_equal(
+                    "keccak_rlc accumulates the bytes of the tx",
+                    meta.query_advice(keccak_rlc, Rotation::cur()),
+                    meta.query_advice(keccak_rlc, Rotation::prev()) * challenges.keccak_input()
+                        + byte,
+                );
+                cb.require_equal(
+                    "keccak_len increases by 1",
+                    meta.query_advice(keccak_len, Rotation::cur()),
+                    meta.query_advice(keccak_len, Rotation::prev()) + 1.expr(),
+                );
+            });
+            // The type byte and the list header are not part of the list
+            cb.condition(1.expr() - is_list - is_tx_type, |cb| {
+                cb.require_equal(
+                    "tx_remaining starts at the length of the list and decreases in every row",
+                    meta.query_advice(tx_remaining, Rotation::cur()),
+                    select::expr(
+                        is_list_prev,
+                        length_prev,
+                        meta.query_advice(tx_remaining, Rotation::prev()),
+                    ) - 1.expr(),
+                );
+            });
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur())
+                    - meta.query_fixed(q_first, Rotation::cur()),
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("transaction fields", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let tag_is = |meta: &mut VirtualCells<'_, F>, value| {
+                tag.value_equals(value, Rotation::cur())(meta)
+            };
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_item_end = meta.query_advice(is_item_end, Rotation::cur());
+            let is_payload = not::expr(meta.query_advice(is_header, Rotation::cur()));
+            let is_data = tag_is(meta, RlpTxTag::Data);
+            let value_acc = meta.query_advice(value_acc, Rotation::cur());
+            let value_rlc = meta.query_advice(value_rlc, Rotation::cur());
+            let counter = meta.query_advice(counter, Rotation::cur());
+            let length = meta.query_advice(length, Rotation::cur());
+            let is_signed = meta.query_advice(is_signed, Rotation::cur());
+
+            for (name, column, value) in [
+                ("is_to_end", is_to_end, RlpTxTag::To),
+                ("is_data_end", is_data_end, RlpTxTag::Data),
+                ("is_chain_id_end", is_chain_id_end, RlpTxTag::ChainId),
+            ] {
+                cb.require_equal(
+                    name,
+                    meta.query_advice(column, Rotation::cur()),
+                    is_item_end.clone() * tag_is(meta, value),
+                );
+            }
+            cb.require_equal(
+                "a tx ends with the signature, or the access list of typed messages",
+                meta.query_advice(is_tx_end, Rotation::cur()),
+                is_item_end.clone()
+                    * (tag_is(meta, RlpTxTag::SigS)
+                        + not::expr(is_signed.clone()) * tag_is(meta, RlpTxTag::AccessList)),
+            );
+
+            // Fields that are looked up in the TxTable at the end of their item
+            let fields = [
+                (RlpTxTag::Nonce, TxFieldTag::Nonce, value_rlc.clone()),
+                (RlpTxTag::GasPrice, TxFieldTag::GasPrice, value_rlc.clone()),
+                (RlpTxTag::Gas, TxFieldTag::Gas, value_acc.clone()),
+                (RlpTxTag::To, TxFieldTag::CalleeAddress, value_acc.clone()),
+                (RlpTxTag::Value, TxFieldTag::Value, value_rlc.clone()),
+                (RlpTxTag::ChainId, TxFieldTag::ChainId, value_rlc.clone()),
+                (
+                    RlpTxTag::MaxPriorityFeePerGas,
+                    TxFieldTag::MaxPriorityFeePerGas,
+                    value_rlc.clone(),
+                ),
+                (RlpTxTag::MaxFeePerGas, TxFieldTag::MaxFeePerGas, value_rlc),
+            ]
+            .map(|(tag, field_tag, value)| (tag_is(meta, tag), field_tag, value));
+            let hash_rlc = meta.query_advice(hash_rlc, Rotation::cur());
+            let keccak_rlc = meta.query_advice(keccak_rlc, Rotation::cur());
+            let keccak_len = meta.query_advice(keccak_len, Rotation::cur());
+            let is_tx_end = meta.query_advice(is_tx_end, Rotation::cur());
+
+            cb.require_equal(
+                "is_field is enabled at the end of the fields and in the call data bytes",
+                meta.query_advice(is_field, Rotation::cur()),
+                is_item_end.clone() * sum::expr(fields.iter().map(|(is_tag, _, _)| is_tag.clone()))
+                    + is_payload.clone() * is_data.clone(),
+            );
+            // The tx_id column of the RlpTable is the tx_id of every row.
+            let [_, table_tag, table_index, table_value] =
+                table_columns.map(|column| meta.query_advice(column, Rotation::cur()));
+            cb.require_equal(
+                "rlp table tag",
+                table_tag,
+                is_item_end.clone()
+                    * sum::expr(
+                        fields
+                            .iter()
+                            .map(|(is_tag, field_tag, _)| is_tag.clone() * field_tag.expr()),
+                    )
+                    + is_payload.clone() * is_data.clone() * TxFieldTag::CallData.expr()
+                    + is_tx_end.clone()
+                        * select::expr(
+                            is_signed.clone(),
+                            TxFieldTag::SignedTx.expr(),
+                            TxFieldTag::TxSignHash.expr(),
+                        ),
+            );
+            cb.require_equal(
+                "rlp table index is the position of the call data byte, or the length of the \
+                 signed tx",
+                table_index,
+                is_payload.clone() * is_data.clone() * (length.clone() - counter - 1.expr())
+                    + is_tx_end.clone() * is_signed.clone() * keccak_len,
+            );
+            cb.require_equal(
+                "rlp table value",
+                table_value,
+                is_item_end.clone()
+                    * sum::expr(
+                        fields
+                            .iter()
+                            .map(|(is_tag, _, value)| is_tag.clone() * value.clone()),
+                    )
+                    + is_payload * is_data * byte
+                    + is_tx_end.clone() * select::expr(is_signed.clone(), keccak_rlc, hash_rlc),
+            );
+
+            cb.require_zero(
+                "the callee address is empty or 20 bytes long",
+                meta.query_advice(is_to_end, Rotation::cur())
+                    * length.clone()
+                    * (length - 20.expr()),
+            );
+            cb.require_zero(
+                "signature fields are 0 in the signed message",
+                is_item_end
+                    * not::expr(is_signed)
+                    * (tag_is(meta, RlpTxTag::SigR) + tag_is(meta, RlpTxTag::SigS))
+                    * value_acc,
+            );
+            cb.require_zero(
+                "the length of the list is the length of the fields",
+                is_tx_end * meta.query_advice(tx_remaining, Rotation::cur()),
+            );
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("access list", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_short_list = meta.query_advice(is_short_list, Rotation::cur());
+            let is_long_list = meta.query_advice(is_long_list, Rotation::cur());
+            let parts = [
+                is_al_entry,
+                is_al_address,
+                is_al_storage_keys,
+                is_al_storage_key,
+            ];
+            let [is_entry, is_address, is_storage_keys, is_storage_key] =
+                parts.map(|column| meta.query_advice(column, Rotation::cur()));
+            let [is_entry_prev, is_address_prev, is_storage_keys_prev, is_storage_key_prev] =
+                parts.map(|column| meta.query_advice(column, Rotation::prev()));
+            let is_payload = sum::expr([
+                is_entry.clone(),
+                is_address.clone(),
+                is_storage_keys.clone(),
+                is_storage_key.clone(),
+            ]);
+            let is_payload_prev = sum::expr([
+                is_entry_prev.clone(),
+                is_address_prev.clone(),
+                is_storage_keys_prev.clone(),
+                is_storage_key_prev.clone(),
+            ]);
+            let is_part_start = meta.query_advice(is_al_part_start, Rotation::cur());
+            let al_counter_is_zero_prev =
+                al_counter_is_zero.expr_at(meta, al_counter, Rotation::prev());
+            let al_counter_prev = meta.query_advice(al_counter, Rotation::prev());
+            let al_counter = meta.query_advice(al_counter, Rotation::cur());
+            let al_length_prev = meta.query_advice(al_length, Rotation::prev());
+            let al_length = meta.query_advice(al_length, Rotation::cur());
+            let entry_remaining = meta.query_advice(al_entry_remaining, Rotation::cur());
+            let storage_keys_remaining =
+                meta.query_advice(al_storage_keys_remaining, Rotation::cur());
+            // The storage keys of an entry end at the end of a part of their
+            // list when no bytes of the list are left.
+            let is_storage_keys_end = (is_storage_keys.clone() + is_storage_key.clone())
+                * al_counter_is_zero.expr()
+                * al_storage_keys_remaining_is_zero.expr();
+            let is_storage_key_list_prev =
+                is_storage_keys_prev.clone() + is_storage_key_prev.clone();
+            let is_storage_keys_end_prev = is_storage_key_list_prev.clone()
+                * al_storage_keys_remaining_is_zero.expr_at(
+                    meta,
+                    al_storage_keys_remaining,
+                    Rotation::prev(),
+                );
+
+            for (name, column) in [
+                ("is_al_entry is boolean", is_al_entry),
+                ("is_al_address is boolean", is_al_address),
+                ("is_al_storage_keys is boolean", is_al_storage_keys),
+                ("is_al_storage_key is boolean", is_al_storage_key),
+            ] {
+                cb.require_boolean(name, meta.query_advice(column, Rotation::cur()));
+            }
+            cb.require_equal(
+                "the payload rows of the access list are decoded in one part",
+                is_payload.clone(),
+                tag.value_equals(RlpTxTag::AccessList, Rotation::cur())(meta)
+                    * not::expr(meta.query_advice(is_header, Rotation::cur())),
+            );
+            cb.require_equal(
+                "a part starts at the start of the payload or after the end of the previous part",
+                is_part_start.clone(),
+                is_payload.clone()
+                    * (not::expr(is_payload_prev.clone())
+                        + is_payload_prev.clone() * al_counter_is_zero_prev),
+            );
+
+            cb.condition(is_part_start.clone(), |cb| {
+                cb.require_equal(
+                    "the payload starts with an entry, and entries follow the storage keys of \
+                     the previous entry",
+                    is_entry.clone(),
+                    not::expr(is_payload_prev.clone()) + is_storage_keys_end_prev.clone(),
+                );
+                cb.require_equal(
+                    "the address follows the header of the entry",
+                    is_address.clone(),
+                    is_entry_prev.clone(),
+                );
+                cb.require_equal(
+                    "the list of storage keys follows the address",
+                    is_storage_keys.clone(),
+                    is_address_prev.clone(),
+                );
+                cb.require_equal(
+                    "storage keys follow the header of their list until it ends",
+                    is_storage_key.clone(),
+                    is_storage_key_list_prev - is_storage_keys_end_prev,
+                );
+                cb.require_equal(
+                    "entries and lists of storage keys are lists",
+                    is_short_list.clone() + is_long_list.clone(),
+                    is_entry.clone() + is_storage_keys.clone(),
+                );
+                cb.require_equal(
+                    "the length of short lists is in the first byte",
+                    al_length.clone(),
+                    is_short_list * (byte.clone() - 0xc0.expr()),
+                );
+                cb.require_equal(
+                    "al_counter is the number of length bytes of long lists, or of payload \
+                     bytes of addresses and storage keys",
+                    al_counter.clone(),
+                    is_long_list * (byte.clone() - 0xf7.expr())
+                        + is_address.clone() * 20.expr()
+                        + is_storage_key.clone() * 32.expr(),
+                );
+                cb.require_zero(
+                    "addresses are 20 bytes long",
+                    is_address.clone() * (byte.clone() - 0x94.expr()),
+                );
+                cb.require_zero(
+                    "storage keys are 32 bytes long",
+                    is_storage_key.clone() * (byte.clone() - 0xa0.expr()),
+                );
+            });
+
+            cb.condition(is_payload.clone() - is_part_start, |cb| {
+                for (is_part, is_part_prev) in [
+                    (is_entry.clone(), is_entry_prev),
+                    (is_address.clone(), is_address_prev),
+                    (is_storage_keys.clone(), is_storage_keys_prev),
+                    (is_storage_key.clone(), is_storage_key_prev),
+                ] {
+                    cb.require_equal("the part is the same until its end", is_part, is_part_prev);
+                }
+                cb.require_equal(
+                    "al_counter decreases in every row of a part",
+                    al_counter.clone(),
+                    al_counter_prev - 1.expr(),
+                );
+                cb.require_zero(
+                    "the length of long lists is decoded from the length bytes",
+                    (is_entry.clone() + is_storage_keys.clone())
+                        * (al_length.clone() - (al_length_prev * 256.expr() + byte)),
+                );
+            });
+
+            cb.condition(is_payload.clone(), |cb| {
+                cb.require_equal(
+                    "al_entry_remaining starts at the length of the entry and decreases in the \
+                     rest of the entry",
+                    entry_remaining.clone(),
+                    select::expr(
+                        is_entry.clone(),
+                        al_length.clone(),
+                        meta.query_advice(al_entry_remaining, Rotation::prev()) - 1.expr(),
+                    ),
+                );
+            });
+            cb.condition(is_storage_keys.clone() + is_storage_key.clone(), |cb| {
+                cb.require_equal(
+                    "al_storage_keys_remaining starts at the length of the list of storage keys \
+                     and decreases in the storage keys",
+                    storage_keys_remaining,
+                    select::expr(
+                        is_storage_keys,
+                        al_length,
+                        meta.query_advice(al_storage_keys_remaining, Rotation::prev()) - 1.expr(),
+                    ),
+                );
+            });
+            cb.require_zero(
+                "an entry ends with its storage keys",
+                is_storage_keys_end.clone() * entry_remaining,
+            );
+            cb.condition(
+                meta.query_advice(is_item_end, Rotation::cur()) * is_payload,
+                |cb| {
+                    cb.require_equal(
+                        "the access list ends with the storage keys of an entry",
+                        is_storage_keys_end,
+                        1.expr(),
+                    );
+                },
+            );
+
+            // The number of addresses and storage keys of the access list of
+            // the tx
+            let is_tx_start = select::expr(
+                meta.query_fixed(q_first, Rotation::cur()),
+                1.expr(),
+                meta.query_advice(is_tx_end, Rotation::prev()),
+            );
+            for (name, column, is_part) in [
+                (
+                    "al_addresses counts the addresses",
+                    al_addresses,
+                    is_address,
+                ),
+                (
+                    "al_storage_keys counts the storage keys",
+                    al_storage_keys,
+                    is_storage_key,
+                ),
+            ] {
+                cb.require_equal(
+                    name,
+                    meta.query_advice(column, Rotation::cur()),
+                    not::expr(is_tx_start.clone()) * meta.query_advice(column, Rotation::prev())
+                        + meta.query_advice(is_al_part_start, Rotation::cur()) * is_part,
+                );
+            }
+
+            cb.gate(and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+            ]))
+        });
+
+        meta.lookup_any("tx field in TxTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_field, Rotation::cur()),
+            ]);
+            table_columns
+                .into_iter()
+                .map(|column| meta.query_advice(column, Rotation::cur()))
+                .zip(tx_table.table_exprs(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+        });
+
+        meta.lookup_any("tag transitions", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur())
+                    - meta.query_fixed(q_first, Rotation::cur()),
+                meta.query_advice(is_item_start, Rotation::cur()),
+                not::expr(meta.query_advice(is_tx_end, Rotation::prev())),
+            ]);
+            [
+                1.expr(),
+                meta.query_advice(tx_type, Rotation::cur()),
+                tag.value(Rotation::prev())(meta),
+                tag.value(Rotation::cur())(meta),
+            ]
+            .into_iter()
+            .chain(std::iter::once(
+                meta.query_advice(is_signed, Rotation::cur()),
+            ))
+            .zip([
+                q_transition_table,
+                tx_type_table,
+                tag_table,
+                tag_next_table,
+                is_signed_table,
+            ])
+            .map(|(input, table)| {
+                (
+                    enable.clone() * input,
+                    meta.query_fixed(table, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+
+        meta.lookup_any("tx_type in TxTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_tx_end, Rotation::cur()),
+            ]);
+            [
+                meta.query_advice(tx_id, Rotation::cur()),
+                TxFieldTag::TxType.expr(),
+                0.expr(),
+                meta.query_advice(tx_type, Rotation::cur()),
+            ]
+            .into_iter()
+            .zip(tx_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        meta.lookup_any("is_create in TxTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_to_end, Rotation::cur()),
+            ]);
+            [
+                meta.query_advice(tx_id, Rotation::cur()),
+                TxFieldTag::IsCreate.expr(),
+                0.expr(),
+                length_is_zero.expr(),
+            ]
+            .into_iter()
+            .zip(tx_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        meta.lookup_any("call data length in TxTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_data_end, Rotation::cur()),
+            ]);
+            [
+                meta.query_advice(tx_id, Rotation::cur()),
+                TxFieldTag::CallDataLength.expr(),
+                0.expr(),
+                meta.query_advice(length, Rotation::cur()),
+            ]
+            .into_iter()
+            .zip(tx_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        for (name, field_tag, column) in [
+            (
+                "access list addresses length in TxTable",
+                TxFieldTag::AccessListAddressesLen,
+                al_addresses,
+            ),
+            (
+                "access list storage keys length in TxTable",
+                TxFieldTag::AccessListStorageKeysLen,
+                al_storage_keys,
+            ),
+        ] {
+            meta.lookup_any(name, |meta| {
+                let enable = and::expr([
+                    meta.query_fixed(q_enable, Rotation::cur()),
+                    meta.query_advice(is_tx_end, Rotation::cur()),
+                ]);
+                [
+                    meta.query_advice(tx_id, Rotation::cur()),
+                    field_tag.expr(),
+                    0.expr(),
+                    meta.query_advice(column, Rotation::cur()),
+                ]
+                .into_iter()
+                .zip(tx_table.table_exprs(meta))
+                .map(|(input, table)| (enable.clone() * input, table))
+                .collect()
+            });
+        }
+
+        meta.lookup_any("chain_id in BlockTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_chain_id_end, Rotation::cur()),
+            ]);
+            [
+                BlockContextFieldTag::ChainId.expr(),
+                0.expr(),
+                meta.query_advice(value_rlc, Rotation::cur()),
+            ]
+            .into_iter()
+            .zip(block_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
+        // Only the hash of the signed message is used.
+        meta.lookup_any(
+            "keccak256_table_lookup(keccak_rlc, keccak_len, hash_rlc)",
+            |meta| {
+                let enable = and::expr([
+                    meta.query_fixed(q_enable, Rotation::cur()),
+                    meta.query_advice(is_tx_end, Rotation::cur()),
+                    not::expr(meta.query_advice(is_signed, Rotation::cur())),
+                ]);
+
+                let mut constraints = vec![(
+                    enable.clone(),
+                    meta.query_advice(keccak_table.is_enabled, Rotation::cur()),
+                )];
+                for (circuit_column, table_column) in
+                    keccak_table.match_columns(keccak_rlc, keccak_len, hash_rlc)
+                {
+                    constraints.push((
+                        enable.clone() * meta.query_advice(circuit_column, Rotation::cur()),
+                        meta.query_advice(table_column, Rotation::cur()),
+                    ))
+                }
+                constraints
+            },
+        );
+
+        Self {
+            q_enable,
+            q_first,
+            q_last,
+            byte_table,
+            q_transition_table,
+            tx_type_table,
+            tag_table,
+            tag_next_table,
+            is_signed_table,
+            byte,
+            is_padding,
+            is_signed,
+            tag,
+            rlp,
+            is_list,
+            is_tx_type,
+            tx_type,
+            value_acc,
+            tx_remaining,
+            keccak_len,
+            is_field,
+            is_to_end,
+            is_data_end,
+            is_chain_id_end,
+            is_tx_end,
+            is_al_entry,
+            is_al_address,
+            is_al_storage_keys,
+            is_al_storage_key,
+            is_al_part_start,
+            al_counter,
+            al_length,
+            al_entry_remaining,
+            al_storage_keys_remaining,
+            al_addresses,
+            al_storage_keys,
+            value_rlc,
+            keccak_rlc,
+            hash_rlc,
+            al_counter_is_zero,
+            al_storage_keys_remaining_is_zero,
+            rlp_table,
+            tx_table,
+            block_table,
+            keccak_table,
+        }
+    }
+}
+
+impl<F: Field> RlpCircuitConfig<F> {
+    /// Assign the signed messages of the transactions and the RlpTable.  When
+    /// `n_rows` is 0, the number of rows is calculated from the messages.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        messages: &[Vec<u8>],
+        n_rows: usize,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        let rows = messages_rows(messages);
+        let n_rows = if n_rows == 0 { rows.len() + 1 } else { n_rows };
+        if rows.len() >= n_rows {
+            error!(
+                "RLP messages require {} rows, but the circuit has {}",
+                rows.len() + 1,
+                n_rows
+            );
+            return Err(Error::Synthesis);
+        }
+        let values = row_values(&rows, challenges);
+
+        self.byte_table.load(layouter)?;
+
+        layouter.assign_region(
+            || "rlp circuit tag transitions table",
+            |mut region| {
+                let transitions = [TxType::Legacy, TxType::Eip2930, TxType::Eip1559]
+                    .into_iter()
+                    .flat_map(|tx_type| [(tx_type, false), (tx_type, true)])
+                    .flat_map(|(tx_type, is_signed)| {
+                        RlpTxTag::tx_tags(tx_type, is_signed)
+                            .windows(2)
+                            .map(|tags| (tx_type, is_signed, tags[0], tags[1]))
+                            .collect::<Vec<_>>()
+                    });
+                for (offset, (tx_type, is_signed, tag, tag_next)) in transitions.enumerate() {
+                    for (name, column, value) in [
+                        ("q_transition_table", self.q_transition_table, 1),
+                        ("tx_type_table", self.tx_type_table, tx_type as u64),
+                        ("tag_table", self.tag_table, tag as u64),
+                        ("tag_next_table", self.tag_next_table, tag_next as u64),
+                        ("is_signed_table", self.is_signed_table, is_signed as u64),
+                    ] {
+                        region.assign_fixed(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_region(
+            || "rlp circuit",
+            |mut region| {
+                let tag_chip = BinaryNumberChip::construct(self.tag);
+                let al_counter_is_zero_chip =
+                    IsZeroChip::construct(self.al_counter_is_zero.clone());
+                let al_storage_keys_remaining_is_zero_chip =
+                    IsZeroChip::construct(self.al_storage_keys_remaining_is_zero.clone());
+
+                for offset in 0..n_rows {
+                    for (name, column, value) in [
+                        ("q_enable", self.q_enable, true),
+                        ("q_first", self.q_first, offset == 0),
+                        ("q_last", self.q_last, offset == n_rows - 1),
+                    ] {
+                        region.assign_fixed(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(F::from(value as u64)),
+                        )?;
+                    }
+
+                    let is_padding = offset >= rows.len();
+                    let (row, values) = if is_padding {
+                        (RlpCircuitRow::default(), RlpRowValues::default())
+                    } else {
+                        (rows[offset].clone(), values[offset].clone())
+                    };
+                    let access_list = row.access_list.clone().unwrap_or_default();
+
+                    for (name, column, value) in [
+                        ("byte", self.byte, F::from(row.rlp.byte as u64)),
+                        ("is_padding", self.is_padding, F::from(is_padding as u64)),
+                        ("is_signed", self.is_signed, F::from(row.is_signed as u64)),
+                        (
+                            "is_list",
+                            self.is_list,
+                            F::from((!is_padding && row.tag == RlpTxTag::Prefix) as u64),
+                        ),
+                        (
+                            "is_tx_type",
+                            self.is_tx_type,
+                            F::from((!is_padding && row.tag == RlpTxTag::TxType) as u64),
+                        ),
+                        ("tx_type", self.tx_type, F::from(row.tx_type as u64)),
+                        ("value_acc", self.value_acc, values.value_acc),
+                        (
+                            "tx_remaining",
+                            self.tx_remaining,
+                            F::from(values.tx_remaining as u64),
+                        ),
+                        (
+                            "keccak_len",
+                            self.keccak_len,
+                            F::from(values.keccak_len as u64),
+                        ),
+                        ("is_field", self.is_field, F::from(values.is_field as u64)),
+                        (
+                            "is_to_end",
+                            self.is_to_end,
+                            F::from(row.is_item_end_of(RlpTxTag::To) as u64),
+                        ),
+                        (
+                            "is_data_end",
+                            self.is_data_end,
+                            F::from(row.is_item_end_of(RlpTxTag::Data) as u64),
+                        ),
+                        (
+                            "is_chain_id_end",
+                            self.is_chain_id_end,
+                            F::from(row.is_item_end_of(RlpTxTag::ChainId) as u64),
+                        ),
+                        ("is_tx_end", self.is_tx_end, F::from(row.is_tx_end() as u64)),
+                        (
+                            "is_al_entry",
+                            self.is_al_entry,
+                            F::from(row.is_access_list_part(AccessListPart::Entry) as u64),
+                        ),
+                        (
+                            "is_al_address",
+                            self.is_al_address,
+                            F::from(row.is_access_list_part(AccessListPart::Address) as u64),
+                        ),
+                        (
+                            "is_al_storage_keys",
+                            self.is_al_storage_keys,
+                            F::from(row.is_access_list_part(AccessListPart::StorageKeys) as u64),
+                        ),
+                        (
+                            "is_al_storage_key",
+                            self.is_al_storage_key,
+                            F::from(row.is_access_list_part(AccessListPart::StorageKey) as u64),
+                        ),
+                        (
+                            "is_al_part_start",
+                            self.is_al_part_start,
+                            F::from(access_list.is_part_start as u64),
+                        ),
+                        (
+                            "al_counter",
+                            self.al_counter,
+                            F::from(access_list.counter as u64),
+                        ),
+                        (
+                            "al_length",
+                            self.al_length,
+                            F::from(access_list.length as u64),
+                        ),
+                        (
+                            "al_entry_remaining",
+                            self.al_entry_remaining,
+                            F::from(access_list.entry_remaining as u64),
+                        ),
+                        (
+                            "al_storage_keys_remaining",
+                            self.al_storage_keys_remaining,
+                            F::from(access_list.storage_keys_remaining as u64),
+                        ),
+                        (
+                            "al_addresses",
+                            self.al_addresses,
+                            F::from(values.al_addresses as u64),
+                        ),
+                        (
+                            "al_storage_keys",
+                            self.al_storage_keys,
+                            F::from(values.al_storage_keys as u64),
+                        ),
+                    ] {
+                        region.assign_advice(|| name, column, offset, || Value::known(value))?;
+                    }
+                    for (name, column, value) in [
+                        ("value_rlc", self.value_rlc, values.value_rlc),
+                        ("keccak_rlc", self.keccak_rlc, values.keccak_rlc),
+                        ("hash_rlc", self.hash_rlc, values.hash_rlc),
+                    ] {
+                        region.assign_advice(|| name, column, offset, || value)?;
+                    }
+
+                    self.rlp_table
+                        .assign(&mut region, offset, &values.table_row)?;
+                    tag_chip.assign(&mut region, offset, &row.tag)?;
+                    self.rlp.assign(&mut region, offset, &row.rlp)?;
+                    al_counter_is_zero_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(access_list.counter as u64)),
+                    )?;
+                    al_storage_keys_remaining_is_zero_chip.assign(
+                        &mut region,
+                        offset,
+                        Value::known(F::from(access_list.storage_keys_remaining as u64)),
+                    )?;
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Witness of a row of the RLP circuit.
+#[derive(Clone, Debug, Default)]
+struct RlpCircuitRow {
+    tx_id: usize,
+    tx_type: TxType,
+    /// Whether the row belongs to the signed encoding of the tx instead of its
+    /// signed message
+    is_signed: bool,
+    tag: RlpTxTag,
+    /// Decoding of the byte in its item
+    rlp: RlpDecoderRow,
+    /// Hash of the signed message of the tx
+    sign_hash: Word,
+    /// Decoding of the payload rows of the access list
+    access_list: Option<AccessListRow>,
+}
+
+/// Witness of the decoding of a payload row of an access list.
+#[derive(Clone, Debug, Default)]
+struct AccessListRow {
+    part: AccessListPart,
+    is_part_start: bool,
+    /// Number of length bytes left in the header of a list, and of payload
+    /// bytes left in an address or a storage key.
+    counter: usize,
+    /// Length of the payload of a list
+    length: usize,
+    /// Bytes of the entry left after the row
+    entry_remaining: usize,
+    /// Bytes of the list of storage keys left after the row
+    storage_keys_remaining: usize,
+}
+
+impl RlpCircuitRow {
+    fn is_access_list_part(&self, part: AccessListPart) -> bool {
+        matches!(&self.access_list, Some(row) if row.part == part)
+    }
+
+    fn is_access_list_part_start(&self, part: AccessListPart) -> bool {
+        matches!(&self.access_list, Some(row) if row.part == part && row.is_part_start)
+    }
+}
+
+impl RlpCircuitRow {
+    fn is_item_end_of(&self, tag: RlpTxTag) -> bool {
+        self.rlp.is_item_end && self.tag == tag
+    }
+
+    fn is_tx_end(&self) -> bool {
+        self.is_item_end_of(RlpTxTag::SigS)
+            || (!self.is_signed && self.is_item_end_of(RlpTxTag::AccessList))
+    }
+
+    /// Returns the TxTable field that is exposed in the row, if any.
+    fn field_tag(&self) -> TxFieldTag {
+        match self.tag {
+            RlpTxTag::Data if !self.rlp.is_header => TxFieldTag::CallData,
+            _ if !self.rlp.is_item_end => TxFieldTag::Null,
+            RlpTxTag::Nonce => TxFieldTag::Nonce,
+            RlpTxTag::GasPrice => TxFieldTag::GasPrice,
+            RlpTxTag::Gas => TxFieldTag::Gas,
+            RlpTxTag::To => TxFieldTag::CalleeAddress,
+            RlpTxTag::Value => TxFieldTag::Value,
+            RlpTxTag::ChainId => TxFieldTag::ChainId,
+            RlpTxTag::MaxPriorityFeePerGas => TxFieldTag::MaxPriorityFeePerGas,
+            RlpTxTag::MaxFeePerGas => TxFieldTag::MaxFeePerGas,
+            _ if !self.is_tx_end() => TxFieldTag::Null,
+            _ if self.is_signed => TxFieldTag::SignedTx,
+            _ => TxFieldTag::TxSignHash,
+        }
+    }
+}
+
+/// Values of the accumulators and of the RlpTable in a row of the RLP circuit.
+#[derive(Clone, Debug)]
+struct RlpRowValues<F> {
+    value_acc: F,
+    value_rlc: Value<F>,
+    tx_remaining: usize,
+    keccak_len: usize,
+    keccak_rlc: Value<F>,
+    hash_rlc: Value<F>,
+    is_field: bool,
+    table_row: [Value<F>; 4],
+    /// Number of addresses of the access list of the tx up to the row
+    al_addresses: usize,
+    /// Number of storage keys of the access list of the tx up to the row
+    al_storage_keys: usize,
+}
+
+impl<F: Field> Default for RlpRowValues<F> {
+    fn default() -> Self {
+        let zero = Value::known(F::zero());
+        Self {
+            value_acc: F::zero(),
+            value_rlc: zero,
+            tx_remaining: 0,
+            keccak_len: 0,
+            keccak_rlc: zero,
+            hash_rlc: zero,
+            is_field: false,
+            table_row: [zero; 4],
+            al_addresses: 0,
+            al_storage_keys: 0,
+        }
+    }
+}
+
+/// Compute the accumulators and the RlpTable row of every row.
+fn row_values<F: Field>(
+    rows: &[RlpCircuitRow],
+    challenges: &Challenges<Value<F>>,
+) -> Vec<RlpRowValues<F>> {
+    let evm_word = challenges.evm_word();
+    let keccak_input = challenges.keccak_input();
+    let mut values: Vec<RlpRowValues<F>> = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let byte = F::from(row.rlp.byte as u64);
+        let prev = i.checked_sub(1).map(|i| (&rows[i], &values[i]));
+
+        let (value_acc, value_rlc) = if row.rlp.is_header {
+            (F::zero(), Value::known(F::zero()))
+        } else if row.rlp.is_item_start {
+            (byte, Value::known(byte))
+        } else {
+            let (_, prev) = prev.expect("payload rows follow the start of the item");
+            (
+                prev.value_acc * F::from(256) + byte,
+                prev.value_rlc * evm_word + Value::known(byte),
+            )
+        };
+        let is_tx_start = prev.map_or(true, |(prev_row, _)| prev_row.is_tx_end());
+        let (keccak_len, keccak_rlc) = match prev {
+            Some((_, prev)) if !is_tx_start => (
+                prev.keccak_len + 1,
+                prev.keccak_rlc * keccak_input + Value::known(byte),
+            ),
+            _ => (1, Value::known(byte)),
+        };
+        let tx_remaining = match prev {
+            _ if matches!(row.tag, RlpTxTag::TxType | RlpTxTag::Prefix) => 0,
+            Some((prev_row, _)) if prev_row.tag == RlpTxTag::Prefix => {
+                prev_row.rlp.length as usize - 1
+            }
+            Some((_, prev)) => prev.tx_remaining - 1,
+            None => unreachable!("the first row is the TxType or the Prefix"),
+        };
+        let (al_addresses, al_storage_keys) = match prev {
+            Some((_, prev)) if !is_tx_start => (prev.al_addresses, prev.al_storage_keys),
+            _ => (0, 0),
+        };
+        let al_addresses =
+            al_addresses + row.is_access_list_part_start(AccessListPart::Address) as usize;
+        let al_storage_keys =
+            al_storage_keys + row.is_access_list_part_start(AccessListPart::StorageKey) as usize;
+        let hash_rlc = if row.is_tx_end() && !row.is_signed {
+            evm_word.map(|randomness| rlc::value(&row.sign_hash.to_le_bytes(), randomness))
+        } else {
+            Value::known(F::zero())
+        };
+
+        let field_tag = row.field_tag();
+        let (index, value) = match field_tag {
+            TxFieldTag::Null => (0, Value::known(F::zero())),
+            TxFieldTag::CallData => (
+                (row.rlp.length - row.rlp.counter - 1) as usize,
+                Value::known(byte),
+            ),
+            TxFieldTag::Gas | TxFieldTag::CalleeAddress => (0, Value::known(value_acc)),
+            TxFieldTag::TxSignHash => (0, hash_rlc),
+            TxFieldTag::SignedTx => (keccak_len, keccak_rlc),
+            _ => (0, value_rlc),
+        };
+
+        values.push(RlpRowValues {
+            value_acc,
+            value_rlc,
+            tx_remaining,
+            keccak_len,
+            keccak_rlc,
+            hash_rlc,
+            is_field: !matches!(
+                field_tag,
+                TxFieldTag::Null | TxFieldTag::TxSignHash | TxFieldTag::SignedTx
+            ),
+            table_row: [
+                Value::known(F::from(row.tx_id as u64)),
+                Value::known(F::from(field_tag as u64)),
+                Value::known(F::from(index as u64)),
+                value,
+            ],
+            al_addresses,
+            al_storage_keys,
+        });
+    }
+    values
+}
+
+/// Returns the messages that the RLP circuit decodes for `txs`: the signed
+/// message of every tx followed by its signed encoding.
+pub(crate) fn tx_messages(txs: &[Transaction], chain_id: u64) -> Vec<Vec<u8>> {
+    txs.iter()
+        .flat_map(|tx| [tx.sign_message(chain_id), tx.signed_rlp(chain_id)])
+        .collect()
+}
+
+/// Generate the rows that decode the messages of the txs (see
+/// [`tx_messages`]), with ids starting at 1.
+fn messages_rows(messages: &[Vec<u8>]) -> Vec<RlpCircuitRow> {
+    messages
+        .iter()
+        .enumerate()
+        .flat_map(|(i, message)| tx_rows(i / 2 + 1, message, i % 2 == 1))
+        .collect()
+}
+
+/// Generate the rows that decode the signed message of a tx, or its signed
+/// encoding when `is_signed`: the type byte of typed txs, the list header and
+/// the items of the fields.
+fn tx_rows(tx_id: usize, message: &[u8], is_signed: bool) -> Vec<RlpCircuitRow> {
+    // The envelope of typed txs starts with the type byte, while the message of
+    // legacy txs starts with the list header.
+    let (tx_type, type_len) = match message[0] {
+        0x00..=0x7f => (
+            TxType::try_from(message[0] as u64).expect("supported tx type"),
+            1,
+        ),
+        _ => (TxType::Legacy, 0),
+    };
+    let items = || -> Result<Vec<&[u8]>, DecoderError> {
+        let rlp = Rlp::new(&message[type_len..]);
+        let header_len = rlp.payload_info()?.header_len;
+        let mut items = vec![
+            &message[..type_len],
+            &message[type_len..type_len + header_len],
+        ];
+        items.extend(rlp.iter().map(|item| item.as_raw()));
+        Ok(items)
+    };
+    let items = items().expect("signed message is RLP encoded");
+    let sign_hash = keccak(message);
+
+    RlpTxTag::tx_tags(tx_type, is_signed)
+        .into_iter()
+        .zip(items.into_iter().filter(|item| !item.is_empty()))
+        .flat_map(|(tag, item)| {
+            let mut rows = item_rows(tx_id, tx_type, tag, item, sign_hash);
+            for row in rows.iter_mut() {
+                row.is_signed = is_signed;
+            }
+            rows
+        })
+        .collect()
+}
+
+/// Generate the rows of an RLP item.  For the Prefix, `item` is only the list
+/// header.
+fn item_rows(
+    tx_id: usize,
+    tx_type: TxType,
+    tag: RlpTxTag,
+    item: &[u8],
+    sign_hash: Word,
+) -> Vec<RlpCircuitRow> {
+    let mut rlp = RlpDecoderRow::default();
+    let mut rows: Vec<_> = item
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            rlp = RlpDecoderRow::new(*byte, i == 0, tag == RlpTxTag::Prefix, &rlp);
+            debug_assert_eq!(rlp.is_item_end, i == item.len() - 1);
+            RlpCircuitRow {
+                tx_id,
+                tx_type,
+                tag,
+                rlp,
+                sign_hash,
+                ..Default::default()
+            }
+        })
+        .collect();
+    let header_len = rows.iter().take_while(|row| row.rlp.is_header).count();
+    if tag == RlpTxTag::AccessList {
+        for (row, access_list_row) in rows[header_len..]
+            .iter_mut()
+            .zip(access_list_rows(&item[header_len..]))
+        {
+            row.access_list = Some(access_list_row);
+        }
+    }
+    rows
+}
+
+/// Decode the `payload` of an access list in parts, one row per byte.
+fn access_list_rows(payload: &[u8]) -> Vec<AccessListRow> {
+    let mut rows = Vec::with_capacity(payload.len());
+    let mut part = AccessListPart::Entry;
+    let (mut entry_remaining, mut storage_keys_remaining) = (0, 0);
+    while rows.len() < payload.len() {
+        let first = payload[rows.len()];
+        let (counter, mut length) = match (part, RlpByteClass::from(first)) {
+            (AccessListPart::Address, _) => (20, 0),
+            (AccessListPart::StorageKey, _) => (32, 0),
+            (_, RlpByteClass::ShortList) => (0, first as usize - 0xc0),
+            (_, RlpByteClass::LongList) => (first as usize - 0xf7, 0),
+            _ => unreachable!("entries and lists of storage keys are lists"),
+        };
+        for i in 0..=counter {
+            let byte = payload[rows.len()];
+            if i > 0 && matches!(part, AccessListPart::Entry | AccessListPart::StorageKeys) {
+                length = length * 256 + byte as usize;
+            }
+            match part {
+                AccessListPart::Entry => entry_remaining = length,
+                AccessListPart::Address => entry_remaining -= 1,
+                AccessListPart::StorageKeys => {
+                    entry_remaining -= 1;
+                    storage_keys_remaining = length;
+                }
+                AccessListPart::StorageKey => {
+                    entry_remaining -= 1;
+                    storage_keys_remaining -= 1;
+                }
+            }
+            rows.push(AccessListRow {
+                part,
+                is_part_start: i == 0,
+                counter: counter - i,
+                length,
+                entry_remaining,
+                storage_keys_remaining: match part {
+                    AccessListPart::StorageKeys | AccessListPart::StorageKey => {
+                        storage_keys_remaining
+                    }
+                    _ => 0,
+                },
+            });
+        }
+        part = match part {
+            AccessListPart::Entry => AccessListPart::Address,
+            AccessListPart::Address => AccessListPart::StorageKeys,
+            _ if storage_keys_remaining == 0 => AccessListPart::Entry,
+            _ => AccessListPart::StorageKey,
+        };
+    }
+    rows
+}
+
+/// Assignments of the RlpTable rows that contain a field of the txs, preceded
+/// by the all-zero row.  The rows that the RLP circuit uses to decode the
+/// messages are not included.
+pub(crate) fn rlp_table_assignments<F: Field>(
+    txs: &[Transaction],
+    chain_id: u64,
+    challenges: &Challenges<Value<F>>,
+) -> Vec<[Value<F>; 4]> {
+    let rows = messages_rows(&tx_messages(txs, chain_id));
+    std::iter::once([Value::known(F::zero()); 4])
+        .chain(
+            rows.iter()
+                .zip(row_values(&rows, challenges))
+                .filter(|(row, _)| row.field_tag() != TxFieldTag::Null)
+                .map(|(_, values)| values.table_row),
+        )
+        .collect()
+}
+
+/// RLP Circuit for proving the encoding of the signed message of the
+/// transactions
+#[derive(Clone, Default, Debug)]
+pub struct RlpCircuit<F: Field> {
+    /// List of Transactions
+    pub txs: Vec<Transaction>,
+    /// Chain ID
+    pub chain_id: u64,
+    /// Number of rows of the circuit, 0 means that it's calculated from the
+    /// transactions
+    pub n_rows: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> RlpCircuit<F> {
+    /// Return a new RlpCircuit
+    pub fn new(txs: Vec<Transaction>, chain_id: u64, n_rows: usize) -> Self {
+        Self {
+            txs,
+            chain_id,
+            n_rows,
+            _marker: PhantomData::default(),
+        }
+    }
+
+    /// Return the minimum number of rows required to prove an input of a
+    /// particular size.
+    pub fn min_num_rows(txs_len: usize, call_data_len: usize) -> usize {
+        txs_len * TX_MAX_FIXED_ROWS + 2 * call_data_len + 1
+    }
+
+    fn messages(&self) -> Vec<Vec<u8>> {
+        tx_messages(&self.txs, self.chain_id)
+    }
+}
+
+impl<F: Field> SubCircuit<F> for RlpCircuit<F> {
+    type Config = RlpCircuitConfig<F>;
+
+    fn new_from_block(block: &witness::Block<F>) -> Self {
+        Self::new(
+            block.eth_txs().iter().map(|tx| tx.into()).collect(),
+            block.context.last().chain_id.as_u64(),
+            Self::min_num_rows(
+                block.circuits_params.max_txs,
+                block.circuits_params.max_calldata,
+            ),
+        )
+    }
+
+    /// Return the minimum number of rows required to prove the block
+    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
+        let circuit = Self::new_from_block(block);
+        let rows = circuit.messages().iter().map(Vec::len).sum::<usize>() + 1;
+        (rows, circuit.n_rows.max(rows))
+    }
+
+    /// Make the assignments to the RlpCircuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.assign(layouter, &self.messages(), self.n_rows, challenges)
+    }
+
+    fn instance(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
+}
+
+/// Load the tables that the RLP circuit looks up, from `txs` and without
+/// running the circuits that produce them.  The TxTable is assigned with the
+/// same layout as the Tx circuit, but only with the fields that the RLP
+/// circuit uses.
+#[cfg(any(feature = "test", test))]
+fn dev_load_tables<F: Field>(
+    config: &RlpCircuitConfig<F>,
+    layouter: &mut impl Layouter<F>,
+    txs: &[Transaction],
+    chain_id: u64,
+    messages: &[Vec<u8>],
+    challenges: &Challenges<Value<F>>,
+) -> Result<(), Error> {
+    config
+        .keccak_table
+        .dev_load(layouter, messages, challenges)?;
+    config.block_table.load(
+        layouter,
+        &BlockContexts::from(BlockContext {
+            chain_id: Word::from(chain_id),
+            ..Default::default()
+        }),
+        challenges.evm_word(),
+    )?;
+
+    let tx_table = &config.tx_table;
+    layouter.assign_region(
+        || "tx table",
+        |mut region| {
+            let mut offset = 0;
+            let mut assign_row = |tx_id: usize, tag: TxFieldTag, index: usize, value| {
+                region.assign_advice(
+                    || "tx_id",
+                    tx_table.tx_id,
+                    offset,
+                    || Value::known(F::from(tx_id as u64)),
+                )?;
+                region.assign_fixed(
+                    || "tag",
+                    tx_table.tag,
+                    offset,
+                    || Value::known(F::from(tag as u64)),
+                )?;
+                region.assign_advice(
+                    || "index",
+                    tx_table.index,
+                    offset,
+                    || Value::known(F::from(index as u64)),
+                )?;
+                region.assign_advice(|| "value", tx_table.value, offset, || value)?;
+                offset += 1;
+                Ok::<(), Error>(())
+            };
+            assign_row(0, TxFieldTag::Null, 0, Value::known(F::zero()))?;
+            for (i, tx) in txs.iter().enumerate() {
+                for (tag, value) in tx_fields(tx, chain_id, challenges) {
+                    assign_row(i + 1, tag, 0, value)?;
+                }
+                for (index, byte) in tx.call_data.iter().enumerate() {
+                    assign_row(
+                        i + 1,
+                        TxFieldTag::CallData,
+                        index,
+                        Value::known(F::from(*byte as u64)),
+                    )?;
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+#[cfg(any(feature = "test", test))]
+impl<F: Field> Circuit<F> for RlpCircuit<F> {
+    type Config = (RlpCircuitConfig<F>, Challenges);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let rlp_table = RlpTable::construct(meta);
+        let tx_table = TxTable::construct(meta);
+        let block_table = BlockTable::construct(meta);
+        let keccak_table = KeccakTable::construct(meta);
+        let challenges = Challenges::construct(meta);
+
+        let config = {
+            let challenges = challenges.exprs(meta);
+            RlpCircuitConfig::new(
+                meta,
+                RlpCircuitConfigArgs {
+                    rlp_table,
+                    tx_table,
+                    block_table,
+                    keccak_table,
+                    challenges,
+                },
+            )
+        };
+
+        (config, challenges)
+    }
+
+    fn synthesize(
+        &self,
+        (config, challenges): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let challenges = challenges.values(&mut layouter);
+
+        dev_load_tables(
+            &config,
+            &mut layouter,
+            &self.txs,
+            self.chain_id,
+            &self.messages(),
+            &challenges,
+        )?;
+        self.synthesize_sub(&config, &challenges, &mut layouter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::{AccessList, Address, Bytes, H256};
+    use ethers_core::types::transaction::eip2930::AccessListItem;
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+    };
+
+    fn mock_txs() -> Vec<Transaction> {
+        mock::CORRECT_MOCK_TXS
+            .iter()
+            .map(|tx| Transaction::from(tx.clone()))
+            .collect()
+    }
+
+    fn test_rlp_circuit(txs: Vec<Transaction>, n_rows: usize) -> Result<(), Vec<VerifyFailure>> {
+        let circuit = RlpCircuit::<Fr>::new(txs, mock::MOCK_CHAIN_ID.as_u64(), n_rows);
+        let prover = MockProver::<Fr>::run(12, &circuit, vec![]).unwrap();
+        prover.verify()
+    }
+
+    /// RlpCircuit that decodes `messages`, while the TxTable is assigned from
+    /// the txs of the circuit.
+    #[derive(Clone, Default, Debug)]
+    struct TamperedRlpCircuit {
+        circuit: RlpCircuit<Fr>,
+        messages: Vec<Vec<u8>>,
+    }
+
+    impl Circuit<Fr> for TamperedRlpCircuit {
+        type Config = (RlpCircuitConfig<Fr>, Challenges);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            RlpCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            (config, challenges): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let challenges = challenges.values(&mut layouter);
+
+            dev_load_tables(
+                &config,
+                &mut layouter,
+                &self.circuit.txs,
+                self.circuit.chain_id,
+                &self.messages,
+                &challenges,
+            )?;
+            config.assign(&mut layouter, &self.messages, 0, &challenges)
+        }
+    }
+
+    #[test]
+    fn rlp_circuit_mock_txs() {
+        assert_eq!(test_rlp_circuit(mock_txs(), 0), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_with_padding() {
+        let txs = mock_txs()[..2].to_vec();
+        let call_data_len = txs.iter().map(|tx| tx.call_data.len()).sum();
+        let n_rows = RlpCircuit::<Fr>::min_num_rows(txs.len(), call_data_len);
+        assert_eq!(test_rlp_circuit(txs, n_rows), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_long_fields() {
+        let mut tx = mock_txs()[0].clone();
+        // Call data with the length encoded in 2 bytes
+        tx.call_data = Bytes::from(vec![0xab; 300]);
+        tx.value = Word::MAX;
+        tx.gas_price = Word::from(0x7f);
+        assert_eq!(test_rlp_circuit(vec![tx], 0), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_contract_creation() {
+        let mut tx = mock_txs()[0].clone();
+        tx.to = None;
+        assert_eq!(test_rlp_circuit(vec![tx], 0), Ok(()));
+    }
+
+    fn typed_tx(tx_type: TxType) -> Transaction {
+        mock::MockTransaction::default()
+            .transaction_type(tx_type as u64)
+            .to(mock::MOCK_ACCOUNTS[1])
+            .nonce(Word::from(0x103))
+            .value(Word::from(0x3e8))
+            .gas_price(Word::from(0x4d2))
+            .max_priority_fee_per_gas(Word::from(0x10))
+            .max_fee_per_gas(Word::from(0x4d2))
+            .input(Bytes::from(b"hello"))
+            .access_list(AccessList(vec![AccessListItem {
+                address: mock::MOCK_ACCOUNTS[2],
+                storage_keys: vec![H256::zero(), H256::from_low_u64_be(1)],
+            }]))
+            .build()
+            .into()
+    }
+
+    /// Return the TxTable fields of the rows of the message of `tx`, without
+    /// the call data.
+    fn message_fields(tx: &Transaction) -> Vec<TxFieldTag> {
+        let message = tx.sign_message(mock::MOCK_CHAIN_ID.as_u64());
+        let rows = tx_rows(1, &message, false);
+        assert_eq!(rows.len(), message.len());
+        assert!(rows.last().unwrap().is_tx_end());
+        rows.iter()
+            .map(RlpCircuitRow::field_tag)
+            .filter(|tag| !matches!(tag, TxFieldTag::Null | TxFieldTag::CallData))
+            .collect()
+    }
+
+    #[test]
+    fn rlp_circuit_rows() {
+        let tx = mock_txs()[0].clone();
+        let messages = tx_messages(&[tx.clone()], mock::MOCK_CHAIN_ID.as_u64());
+        assert!(messages.concat().len() <= TX_MAX_FIXED_ROWS + 2 * tx.call_data.len());
+        assert_eq!(
+            message_fields(&tx),
+            vec![
+                TxFieldTag::Nonce,
+                TxFieldTag::GasPrice,
+                TxFieldTag::Gas,
+                TxFieldTag::CalleeAddress,
+                TxFieldTag::Value,
+                TxFieldTag::ChainId,
+                TxFieldTag::TxSignHash,
+            ]
+        );
+    }
+
+    #[test]
+    fn rlp_circuit_rows_eip1559() {
+        let tx = typed_tx(TxType::Eip1559);
+        let message = tx.sign_message(mock::MOCK_CHAIN_ID.as_u64());
+        assert_eq!(message[0], 0x02);
+        assert_eq!(tx_rows(1, &message, false)[0].tag, RlpTxTag::TxType);
+        assert_eq!(
+            message_fields(&tx),
+            vec![
+                TxFieldTag::ChainId,
+                TxFieldTag::Nonce,
+                TxFieldTag::MaxPriorityFeePerGas,
+                TxFieldTag::MaxFeePerGas,
+                TxFieldTag::Gas,
+                TxFieldTag::CalleeAddress,
+                TxFieldTag::Value,
+                TxFieldTag::TxSignHash,
+            ]
+        );
+        assert_eq!(message_fields(&tx).len(), TABLE_MAX_ROWS_PER_TX);
+    }
+
+    #[test]
+    fn rlp_circuit_typed_txs() {
+        let mut txs = mock_txs();
+        txs.push(typed_tx(TxType::Eip2930));
+        txs.push(typed_tx(TxType::Eip1559));
+        let mut tx = typed_tx(TxType::Eip1559);
+        tx.access_list = None;
+        tx.to = None;
+        txs.push(tx);
+        assert_eq!(test_rlp_circuit(txs, 0), Ok(()));
+    }
+
+    fn mock_challenges() -> Challenges<Value<Fr>> {
+        let randomness = Value::known(Fr::from(0x100));
+        Challenges::mock(randomness, randomness, randomness)
+    }
+
+    fn access_list_tx() -> Transaction {
+        let mut tx = typed_tx(TxType::Eip2930);
+        tx.access_list = Some(AccessList(vec![
+            AccessListItem {
+                address: mock::MOCK_ACCOUNTS[2],
+                storage_keys: vec![],
+            },
+            AccessListItem {
+                address: mock::MOCK_ACCOUNTS[3],
+                storage_keys: (0..3).map(H256::from_low_u64_be).collect(),
+            },
+            AccessListItem {
+                address: mock::MOCK_ACCOUNTS[4],
+                storage_keys: vec![H256::repeat_byte(0xff)],
+            },
+        ]));
+        tx
+    }
+
+    #[test]
+    fn rlp_circuit_access_list_rows() {
+        let tx = access_list_tx();
+        let rows = tx_rows(1, &tx.sign_message(mock::MOCK_CHAIN_ID.as_u64()), false);
+        let values = row_values::<Fr>(&rows, &mock_challenges());
+        let last = values.last().unwrap();
+        assert_eq!((last.al_addresses, last.al_storage_keys), (3, 4));
+
+        let parts = rows
+            .iter()
+            .filter_map(|row| row.access_list.as_ref())
+            .filter(|row| row.is_part_start)
+            .map(|row| row.part)
+            .collect::<Vec<_>>();
+        use AccessListPart::*;
+        assert_eq!(
+            parts,
+            vec![
+                Entry,
+                Address,
+                StorageKeys,
+                Entry,
+                Address,
+                StorageKeys,
+                StorageKey,
+                StorageKey,
+                StorageKey,
+                Entry,
+                Address,
+                StorageKeys,
+                StorageKey,
+            ]
+        );
+        let last = rows.last().unwrap().access_list.as_ref().unwrap();
+        assert_eq!(
+            (
+                last.counter,
+                last.entry_remaining,
+                last.storage_keys_remaining
+            ),
+            (0, 0, 0)
+        );
+
+        // Legacy txs don't have an access list
+        let tx = mock_txs()[0].clone();
+        let rows = tx_rows(1, &tx.sign_message(mock::MOCK_CHAIN_ID.as_u64()), false);
+        let values = row_values::<Fr>(&rows, &mock_challenges());
+        let last = values.last().unwrap();
+        assert_eq!((last.al_addresses, last.al_storage_keys), (0, 0));
+    }
+
+    #[test]
+    fn rlp_circuit_access_lists() {
+        let mut txs = mock_txs();
+        txs.push(access_list_tx());
+        let mut tx = access_list_tx();
+        tx.tx_type = TxType::Eip1559;
+        txs.push(tx);
+        assert_eq!(test_rlp_circuit(txs, 0), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_access_list() {
+        let chain_id = mock::MOCK_CHAIN_ID.as_u64();
+        let tampers: [fn(&mut AccessList); 2] = [
+            |access_list| {
+                access_list.0[1].storage_keys.pop();
+            },
+            |access_list| {
+                access_list.0.pop();
+            },
+        ];
+        for tamper in tampers {
+            let tx = access_list_tx();
+            let mut tampered_tx = tx.clone();
+            tamper(tampered_tx.access_list.as_mut().unwrap());
+            let circuit = TamperedRlpCircuit {
+                circuit: RlpCircuit::new(vec![tx], chain_id, 0),
+                messages: tx_messages(&[tampered_tx], chain_id),
+            };
+            let prover = MockProver::<Fr>::run(12, &circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    /// Decode the messages of the mock txs tampered with `tamper`.  When
+    /// `only_signed`, the signed messages are not tampered.
+    fn test_tampered_tx(
+        tamper: impl Fn(&mut Transaction),
+        only_signed: bool,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let chain_id = mock::MOCK_CHAIN_ID.as_u64();
+        let txs = mock_txs();
+        let messages = txs
+            .iter()
+            .flat_map(|tx| {
+                let mut tampered_tx = tx.clone();
+                tamper(&mut tampered_tx);
+                let message_tx = if only_signed { tx } else { &tampered_tx };
+                [
+                    message_tx.sign_message(chain_id),
+                    tampered_tx.signed_rlp(chain_id),
+                ]
+            })
+            .collect();
+        let circuit = TamperedRlpCircuit {
+            circuit: RlpCircuit::new(txs, chain_id, 0),
+            messages,
+        };
+        let prover = MockProver::<Fr>::run(12, &circuit, vec![]).unwrap();
+        prover.verify()
+    }
+
+    fn test_tampered_message(tamper: impl Fn(&mut Transaction)) -> Result<(), Vec<VerifyFailure>> {
+        test_tampered_tx(tamper, false)
+    }
+
+    #[test]
+    fn rlp_circuit_untampered_message() {
+        assert_eq!(test_tampered_message(|_| ()), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_callee() {
+        assert!(test_tampered_message(|tx| tx.to = Some(Address::repeat_byte(0x42))).is_err());
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_value() {
+        assert!(test_tampered_message(|tx| tx.value += Word::one()).is_err());
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_call_data() {
+        assert!(test_tampered_message(|tx| tx.call_data = Bytes::from(vec![1, 2, 3])).is_err());
+    }
+
+    #[test]
+    fn rlp_circuit_signed_rows() {
+        let chain_id = mock::MOCK_CHAIN_ID.as_u64();
+        for (tx, fields) in [
+            (
+                mock_txs()[0].clone(),
+                vec![
+                    TxFieldTag::Nonce,
+                    TxFieldTag::GasPrice,
+                    TxFieldTag::Gas,
+                    TxFieldTag::CalleeAddress,
+                    TxFieldTag::Value,
+                    TxFieldTag::SignedTx,
+                ],
+            ),
+            (
+                typed_tx(TxType::Eip1559),
+                vec![
+                    TxFieldTag::ChainId,
+                    TxFieldTag::Nonce,
+                    TxFieldTag::MaxPriorityFeePerGas,
+                    TxFieldTag::MaxFeePerGas,
+                    TxFieldTag::Gas,
+                    TxFieldTag::CalleeAddress,
+                    TxFieldTag::Value,
+                    TxFieldTag::SignedTx,
+                ],
+            ),
+        ] {
+            let signed_tx = tx.signed_rlp(chain_id);
+            let rows = tx_rows(1, &signed_tx, true);
+            assert_eq!(rows.len(), signed_tx.len());
+            assert!(rows.last().unwrap().is_tx_end());
+            assert_eq!(
+                rows.iter()
+                    .map(RlpCircuitRow::field_tag)
+                    .filter(|tag| !matches!(tag, TxFieldTag::Null | TxFieldTag::CallData))
+                    .collect::<Vec<_>>(),
+                fields
+            );
+
+            // The signed tx is exposed with its length and its RLC
+            let challenges = mock_challenges();
+            let table_row = row_values::<Fr>(&rows, &challenges)
+                .last()
+                .unwrap()
+                .table_row;
+            let expected = challenges
+                .keccak_input()
+                .map(|randomness| rlc::value(signed_tx.iter().rev(), randomness));
+            table_row[3]
+                .zip(expected)
+                .assert_if_known(|(value, expected)| value == expected);
+            table_row[2].assert_if_known(|index| *index == Fr::from(signed_tx.len() as u64));
+        }
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_signed_tx() {
+        assert_eq!(test_tampered_tx(|_| (), true), Ok(()));
+        assert!(test_tampered_tx(|tx| tx.value += Word::one(), true).is_err());
+        assert!(test_tampered_tx(|tx| tx.gas_limit += Word::one(), true).is_err());
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_tx_type() {
+        let tx = typed_tx(TxType::Eip1559);
+        let mut tampered_tx = tx.clone();
+        tampered_tx.tx_type = TxType::Eip2930;
+        let chain_id = mock::MOCK_CHAIN_ID.as_u64();
+        let circuit = TamperedRlpCircuit {
+            circuit: RlpCircuit::new(vec![tx], chain_id, 0),
+            messages: tx_messages(&[tampered_tx], chain_id),
+        };
+        let prover = MockProver::<Fr>::run(12, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn rlp_circuit_wrong_chain_id() {
+        let txs = mock_txs();
+        let messages = tx_messages(&txs, 1);
+        let circuit = TamperedRlpCircuit {
+            circuit: RlpCircuit::new(txs, mock::MOCK_CHAIN_ID.as_u64(), 0),
+            messages,
+        };
+        let prover = MockProver::<Fr>::run(12, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
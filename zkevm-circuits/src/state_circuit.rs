@@ -20,20 +20,19 @@ use gadgets::{
     binary_number::{BinaryNumberChip, BinaryNumberConfig},
 };
 use halo2_proofs::{
-    circuit::{Layouter, Region, SimpleFloorPlanner, Value},
+    circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
     plonk::{
         Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase,
         VirtualCells,
     },
     poly::Rotation,
 };
+use itertools::Itertools;
 use lexicographic_ordering::Config as LexicographicOrderingConfig;
 use lookups::{Chip as LookupsChip, Config as LookupsConfig, Queries as LookupsQueries};
 use multiple_precision_integer::{Chip as MpiChip, Config as MpiConfig, Queries as MpiQueries};
 use random_linear_combination::{Chip as RlcChip, Config as RlcConfig, Queries as RlcQueries};
-#[cfg(test)]
-use std::collections::HashMap;
-use std::{iter::once, marker::PhantomData};
+use std::{collections::HashMap, iter::once, marker::PhantomData};
 
 use self::{
     constraint_builder::{MptUpdateTableQueries, RwTableQueries},
@@ -124,6 +123,7 @@ impl<F: Field> SubCircuitConfig<F> for StateCircuitConfig<F> {
         );
         let mpt_proof_type = meta.advice_column_in(SecondPhase);
         let state_root = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(state_root);
 
         let sort_keys = SortKeysConfig {
             tag,
@@ -189,10 +189,13 @@ impl<F: Field> StateCircuitConfig<F> {
             || "state circuit",
             |mut region| {
                 self.assign_with_region(&mut region, rows, &updates, n_rows, challenges.evm_word())
+                    .map(|_| ())
             },
         )
     }
 
+    /// Assign the rows and return the cells of the state roots before and
+    /// after them.
     fn assign_with_region(
         &self,
         region: &mut Region<'_, F>,
@@ -200,7 +203,7 @@ impl<F: Field> StateCircuitConfig<F> {
         updates: &MptUpdates,
         n_rows: usize, // 0 means dynamically calculated from `rows`.
         randomness: Value<F>,
-    ) -> Result<(), Error> {
+    ) -> Result<StateRootCells<F>, Error> {
         let tag_chip = BinaryNumberChip::construct(self.sort_keys.tag);
 
         let (rows, padding_length) = RwMap::table_assignments_prepad(rows, n_rows);
@@ -211,6 +214,23 @@ impl<F: Field> StateCircuitConfig<F> {
         let mut state_root =
             randomness.map(|randomness| rlc::value(&updates.old_root().to_le_bytes(), randomness));
 
+        // When a block is split in chunks, the receipt of the last tx of the
+        // previous chunk is read without being written first, so its value is
+        // the initial value of the receipt.  The PublicInputs circuit checks
+        // it against the last row of the previous chunk, which is opened from
+        // the rw commitment of the previous chunks.
+        let read_receipts: HashMap<_, _> = rows
+            .clone()
+            .filter(|row| matches!(row, Rw::TxReceipt { .. }))
+            .group_by(|row| (row.id(), row.field_tag()))
+            .into_iter()
+            .filter_map(|(key, mut rows)| {
+                let first = rows.next().unwrap();
+                (!first.is_write()).then_some((key, *first))
+            })
+            .collect();
+
+        let mut state_root_cells = Vec::new();
         for (offset, (row, prev_row)) in rows.zip(prev_rows).enumerate() {
             if offset >= padding_length {
                 log::trace!("state circuit assign offset:{} row:{:#?}", offset, row);
@@ -280,11 +300,17 @@ impl<F: Field> StateCircuitConfig<F> {
                 }
             }
 
-            // The initial value can be determined from the mpt updates or is 0.
+            // The initial value can be determined from the mpt updates, from
+            // the receipts of a previous chunk or is 0.
+            let read_receipt = match row {
+                Rw::TxReceipt { .. } => read_receipts.get(&(row.id(), row.field_tag())),
+                _ => None,
+            };
             let initial_value = randomness.map(|randomness| {
                 updates
                     .get(row)
                     .map(|u| u.value_assignments(randomness).1)
+                    .or_else(|| read_receipt.map(|rw| rw.value_assignment(randomness)))
                     .unwrap_or_default()
             });
             region.assign_advice(
@@ -341,12 +367,15 @@ impl<F: Field> StateCircuitConfig<F> {
             // State root assignment is at previous row (offset - 1) because the state root
             // changes on the last access row.
             if offset != 0 {
-                region.assign_advice(
+                let cell = region.assign_advice(
                     || "state_root",
                     self.state_root,
                     offset - 1,
                     || state_root,
                 )?;
+                if offset == 1 {
+                    state_root_cells.push(cell);
+                }
             }
 
             if offset == rows_len - 1 {
@@ -359,19 +388,38 @@ impl<F: Field> StateCircuitConfig<F> {
                         new_root
                     });
                 }
-                region.assign_advice(
+                let cell = region.assign_advice(
                     || "last row state_root",
                     self.state_root,
                     offset,
                     || state_root,
                 )?;
+                // With a single row, the state root is the same before and
+                // after it.
+                if offset == 0 {
+                    state_root_cells.push(cell.clone());
+                }
+                state_root_cells.push(cell);
             }
         }
 
-        Ok(())
+        let [initial_state_root, final_state_root]: [AssignedCell<F, F>; 2] =
+            state_root_cells.try_into().unwrap();
+        Ok(StateRootCells {
+            initial_state_root,
+            final_state_root,
+        })
     }
 }
 
+/// Cells of the state roots before and after the rows of the rw table, which
+/// are linked to the roots of the MptCircuit.
+#[derive(Clone, Debug)]
+pub(crate) struct StateRootCells<F: Field> {
+    pub(crate) initial_state_root: AssignedCell<F, F>,
+    pub(crate) final_state_root: AssignedCell<F, F>,
+}
+
 /// Keys for sorting the rows of the state circuit
 #[derive(Clone, Copy)]
 pub struct SortKeysConfig {
@@ -411,31 +459,16 @@ impl<F: Field> StateCircuit<F> {
             _marker: PhantomData::default(),
         }
     }
-}
-
-#[cfg(any(feature = "test", test))]
-impl<F: Field> SubCircuit<F> for StateCircuit<F> {
-    type Config = StateCircuitConfig<F>;
-
-    fn new_from_block(block: &witness::Block<F>) -> Self {
-        Self::new(block.rws.clone(), block.circuits_params.max_rws)
-    }
-
-    /// Return the minimum number of rows required to prove the block
-    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
-        (
-            block.rws.0.values().flatten().count() + 1,
-            block.circuits_params.max_rws,
-        )
-    }
 
-    /// Make the assignments to the StateCircuit
-    fn synthesize_sub(
+    /// Make the assignments to the StateCircuit and return the cells of the
+    /// state roots before and after the rows.
+    #[cfg(any(feature = "test", test))]
+    pub(crate) fn assign(
         &self,
-        config: &Self::Config,
+        config: &StateCircuitConfig<F>,
         challenges: &Challenges<Value<F>>,
         layouter: &mut impl Layouter<F>,
-    ) -> Result<(), Error> {
+    ) -> Result<StateRootCells<F>, Error> {
         config.load_aux_tables(layouter)?;
 
         let randomness = challenges.evm_word();
@@ -453,7 +486,7 @@ impl<F: Field> SubCircuit<F> for StateCircuit<F> {
                     randomness,
                 )?;
 
-                config.assign_with_region(
+                let state_root_cells = config.assign_with_region(
                     &mut region,
                     &self.rows,
                     &self.updates,
@@ -477,10 +510,40 @@ impl<F: Field> SubCircuit<F> for StateCircuit<F> {
                     }
                 }
 
-                Ok(())
+                Ok(state_root_cells)
             },
         )
     }
+}
+
+#[cfg(any(feature = "test", test))]
+impl<F: Field> SubCircuit<F> for StateCircuit<F> {
+    type Config = StateCircuitConfig<F>;
+
+    fn new_from_block(block: &witness::Block<F>) -> Self {
+        Self {
+            updates: block.mpt_updates.clone(),
+            ..Self::new(block.rws.clone(), block.circuits_params.max_rws)
+        }
+    }
+
+    /// Return the minimum number of rows required to prove the block
+    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
+        (
+            block.rws.0.values().flatten().count() + 1,
+            block.circuits_params.max_rws,
+        )
+    }
+
+    /// Make the assignments to the StateCircuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        self.assign(config, challenges, layouter).map(|_| ())
+    }
 
     /// powers of randomness for instance columns
     fn instance(&self) -> Vec<Vec<F>> {
@@ -123,6 +123,9 @@ impl<F: Field> ConstraintBuilder<F> {
         self.condition(q.tag_matches(RwTableTag::TxRefund), |cb| {
             cb.build_tx_refund_constraints(q)
         });
+        self.condition(q.tag_matches(RwTableTag::TransientStorage), |cb| {
+            cb.build_transient_storage_constraints(q)
+        });
         self.condition(q.tag_matches(RwTableTag::Account), |cb| {
             cb.build_account_constraints(q)
         });
@@ -319,6 +322,19 @@ impl<F: Field> ConstraintBuilder<F> {
         );
     }
 
+    fn build_transient_storage_constraints(&mut self, q: &Queries<F>) {
+        self.require_zero("field_tag is 0 for TransientStorage", q.field_tag());
+        // Transient storage is keyed by tx_id, so every tx starts with a zeroed
+        // transient storage.
+        self.require_zero("initial TransientStorage value is 0", q.initial_value());
+
+        self.require_equal(
+            "state_root is unchanged for TransientStorage",
+            q.state_root(),
+            q.state_root_prev(),
+        );
+    }
+
     fn build_tx_refund_constraints(&mut self, q: &Queries<F>) {
         self.require_zero("address is 0 for TxRefund", q.rw_table.address.clone());
         self.require_zero("field_tag is 0 for TxRefund", q.field_tag());
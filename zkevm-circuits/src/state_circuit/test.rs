@@ -954,6 +954,68 @@ fn bad_initial_tx_refund_value() {
     );
 }
 
+#[test]
+fn transient_storage_is_zeroed_per_tx() {
+    let account_address = address!("0x0000000000000000000000000000000004356002");
+    let rows = vec![
+        Rw::TransientStorage {
+            rw_counter: 1,
+            is_write: true,
+            tx_id: 1,
+            account_address,
+            storage_key: U256::from(5),
+            value: U256::from(10),
+            value_prev: U256::zero(),
+        },
+        Rw::TransientStorage {
+            rw_counter: 2,
+            is_write: false,
+            tx_id: 1,
+            account_address,
+            storage_key: U256::from(5),
+            value: U256::from(10),
+            value_prev: U256::from(10),
+        },
+        Rw::TransientStorage {
+            rw_counter: 3,
+            is_write: false,
+            tx_id: 2,
+            account_address,
+            storage_key: U256::from(5),
+            value: U256::zero(),
+            value_prev: U256::zero(),
+        },
+    ];
+
+    assert_eq!(verify(rows), Ok(()));
+}
+
+#[test]
+fn bad_initial_transient_storage_value() {
+    let rows = vec![Rw::TransientStorage {
+        rw_counter: 1,
+        is_write: false,
+        tx_id: 1,
+        account_address: address!("0x0000000000000000000000000000000004356002"),
+        storage_key: U256::from(5),
+        value: U256::zero(),
+        value_prev: U256::zero(),
+    }];
+    let v = Fr::from(10);
+    let overrides = HashMap::from([
+        ((AdviceColumn::IsWrite, 0), Fr::from(1)),
+        ((AdviceColumn::Value, 0), v),
+        ((AdviceColumn::IsZero, 0), Fr::zero()),
+        ((AdviceColumn::NonEmptyWitness, 0), v.invert().unwrap()),
+        ((AdviceColumn::InitialValue, 0), v),
+    ]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "initial TransientStorage value is 0",
+    );
+}
+
 #[test]
 fn bad_initial_tx_log_value() {
     let rows = vec![Rw::TxLog {
@@ -0,0 +1,1462 @@
+//! Composition of top-level circuits from a chosen subset of the zkEVM
+//! sub-circuits.
+//!
+//! A [`ComposedCircuit`] is parametrized by a [`SubCircuitSet`] that declares
+//! which sub-circuits it contains.  The shared lookup tables required by the
+//! selected sub-circuits are constructed once and wired into every
+//! sub-circuit that uses them.  A table is:
+//!
+//! - internal, when the sub-circuit that constrains its content (its producer)
+//!   is part of the composition.
+//! - external, when it's used by some sub-circuit of the composition but its
+//!   producer lives in a different top-level circuit.  External tables are
+//!   assigned directly from the witness.
+//!
+//! External tables are linked to the proof that contains their producer
+//! through instance columns, one per table column, that contain the rows of
+//! the table.  The composition that uses an external table constrains every
+//! row of the table to be one of the instance rows, and the composition that
+//! exports an internal table (see [`SubCircuitSet::exporting`]) constrains
+//! every instance row to be one of the rows of the table.  Two proofs that
+//! share the instance values of a table thus agree on its content.  The Copy
+//! and Exponentiation tables are queried at several rotations, so they can't
+//! be linked and must be internal.
+//!
+//! This allows splitting the block proof in different ways, for example:
+//!
+//! - [`SubCircuitSet::EVM_PROOF`]: EVM, State, Copy and Exponentiation
+//!   circuits, which take the Tx, Bytecode, Block, Keccak, Withdrawal and MPT
+//!   tables as external inputs, and export the Rw table.
+//! - [`SubCircuitSet::DATA_PROOF`]: Tx, RLP, PublicInputs, Keccak and Bytecode
+//!   circuits, which take the Rw table as external input, to prove the rw
+//!   commitment, and export the tables used by the
+//!   [`SubCircuitSet::EVM_PROOF`].
+
+use crate::bytecode_circuit::circuit::{
+    BytecodeCircuit, BytecodeCircuitConfig, BytecodeCircuitConfigArgs,
+};
+use crate::copy_circuit::{CopyCircuit, CopyCircuitConfig, CopyCircuitConfigArgs};
+use crate::evm_circuit::{EvmCircuit, EvmCircuitConfig, EvmCircuitConfigArgs};
+use crate::exp_circuit::{ExpCircuit, ExpCircuitConfig, OFFSET_INCREMENT};
+use crate::keccak_circuit::keccak_packed_multi::{
+    KeccakCircuit, KeccakCircuitConfig, KeccakCircuitConfigArgs,
+};
+use crate::mpt_circuit::{MptCircuit, MptCircuitConfig, MptCircuitConfigArgs};
+use crate::pi_circuit::{PiCircuit, PiCircuitConfig, PiCircuitConfigArgs};
+use crate::receipt_circuit::{ReceiptCircuit, ReceiptCircuitConfig, ReceiptCircuitConfigArgs};
+use crate::rlp_circuit::rlp_table_assignments;
+use crate::rlp_circuit::{
+    RlpCircuit, RlpCircuitConfig, RlpCircuitConfigArgs, TABLE_MAX_ROWS_PER_TX,
+};
+use crate::state_circuit::{StateCircuit, StateCircuitConfig, StateCircuitConfigArgs};
+use crate::table::{
+    BlockTable, BytecodeTable, CopyTable, DynamicTableColumns, ExpTable, KeccakTable, MptTable,
+    RlpTable, RwTable, TxTable, WithdrawalTable,
+};
+use crate::tx_circuit::{TxCircuit, TxCircuitConfig, TxCircuitConfigArgs, TX_LEN};
+use crate::util::{log2_ceil, Challenges, SubCircuit, SubCircuitConfig};
+use crate::witness::{block_convert, Block, RwMap, Transaction};
+use bus_mapping::circuit_input_builder::{CircuitInputBuilder, CircuitsParams};
+use bus_mapping::mock::BlockData;
+use eth_types::geth_types::GethData;
+use eth_types::{evm_types::Hardfork, Field};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Advice, Any, Circuit, Column, ConstraintSystem, Error, Expression},
+    poly::Rotation,
+};
+use itertools::Itertools;
+use std::array;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// Sub-circuits that can be part of a [`ComposedCircuit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+pub enum SubCircuitKind {
+    /// EVM Circuit
+    Evm,
+    /// State Circuit
+    State,
+    /// Tx Circuit
+    Tx,
+    /// Bytecode Circuit
+    Bytecode,
+    /// Copy Circuit
+    Copy,
+    /// Exponentiation Circuit
+    Exp,
+    /// Keccak Circuit
+    Keccak,
+    /// PublicInputs Circuit
+    Pi,
+    /// MPT Circuit
+    Mpt,
+    /// RLP Circuit
+    Rlp,
+    /// Receipt Circuit
+    Receipt,
+}
+
+impl SubCircuitKind {
+    /// Returns the shared tables used by the sub-circuit, either because it
+    /// constrains their content or because it does lookups to them.
+    pub fn tables(&self) -> &'static [SharedTable] {
+        match self {
+            Self::Evm => &[
+                SharedTable::Tx,
+                SharedTable::Rw,
+                SharedTable::Bytecode,
+                SharedTable::Block,
+                SharedTable::Copy,
+                SharedTable::Keccak,
+                SharedTable::Exp,
+                SharedTable::Withdrawal,
+            ],
+            Self::State => &[SharedTable::Rw, SharedTable::Mpt],
+            Self::Tx => &[SharedTable::Tx, SharedTable::Keccak, SharedTable::Rlp],
+            Self::Bytecode => &[SharedTable::Bytecode, SharedTable::Keccak],
+            Self::Copy => &[
+                SharedTable::Tx,
+                SharedTable::Rw,
+                SharedTable::Bytecode,
+                SharedTable::Copy,
+            ],
+            Self::Exp => &[SharedTable::Exp],
+            Self::Keccak => &[SharedTable::Keccak],
+            Self::Pi => &[
+                SharedTable::Block,
+                SharedTable::Tx,
+                SharedTable::Keccak,
+                SharedTable::Rlp,
+                SharedTable::Withdrawal,
+                SharedTable::Rw,
+            ],
+            Self::Mpt => &[SharedTable::Mpt, SharedTable::Keccak],
+            Self::Rlp => &[
+                SharedTable::Tx,
+                SharedTable::Block,
+                SharedTable::Keccak,
+                SharedTable::Rlp,
+            ],
+            Self::Receipt => &[SharedTable::Tx, SharedTable::Rw, SharedTable::Keccak],
+        }
+    }
+}
+
+/// Lookup tables shared between sub-circuits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+pub enum SharedTable {
+    /// Tx Table
+    Tx,
+    /// Rw Table
+    Rw,
+    /// MPT Table
+    Mpt,
+    /// Bytecode Table
+    Bytecode,
+    /// Block Table
+    Block,
+    /// Copy Table
+    Copy,
+    /// Exponentiation Table
+    Exp,
+    /// Keccak Table
+    Keccak,
+    /// RLP Table
+    Rlp,
+    /// Withdrawal Table
+    Withdrawal,
+}
+
+impl SharedTable {
+    /// Returns the sub-circuit that constrains the content of the table, if
+    /// any.
+    pub fn producer(&self) -> Option<SubCircuitKind> {
+        match self {
+            Self::Tx => Some(SubCircuitKind::Tx),
+            Self::Rw => Some(SubCircuitKind::State),
+            Self::Mpt => Some(SubCircuitKind::Mpt),
+            Self::Bytecode => Some(SubCircuitKind::Bytecode),
+            Self::Block => Some(SubCircuitKind::Pi),
+            Self::Copy => Some(SubCircuitKind::Copy),
+            Self::Exp => Some(SubCircuitKind::Exp),
+            Self::Keccak => Some(SubCircuitKind::Keccak),
+            Self::Rlp => Some(SubCircuitKind::Rlp),
+            Self::Withdrawal => Some(SubCircuitKind::Pi),
+        }
+    }
+
+    /// Returns true if the table can be linked between top-level circuits,
+    /// which requires that its lookups only query the current row.
+    pub fn is_linkable(&self) -> bool {
+        !matches!(self, Self::Copy | Self::Exp)
+    }
+}
+
+/// Offset of the bits that mark the exported tables in the bitmask of a
+/// [`SubCircuitSet`].
+const EXPORTED_TABLES_OFFSET: u32 = 16;
+
+/// Set of sub-circuits, encoded as a bitmask so that it can be used as a
+/// const generic parameter of [`ComposedCircuit`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubCircuitSet(u32);
+
+impl SubCircuitSet {
+    /// Set without sub-circuits
+    pub const EMPTY: Self = Self(0);
+    /// Set with the sub-circuits of the SuperCircuit
+    pub const ALL: Self = Self::EMPTY
+        .with(SubCircuitKind::Evm)
+        .with(SubCircuitKind::State)
+        .with(SubCircuitKind::Tx)
+        .with(SubCircuitKind::Bytecode)
+        .with(SubCircuitKind::Copy)
+        .with(SubCircuitKind::Exp)
+        .with(SubCircuitKind::Keccak)
+        .with(SubCircuitKind::Pi)
+        .with(SubCircuitKind::Rlp)
+        .with(SubCircuitKind::Mpt)
+        .with(SubCircuitKind::Receipt);
+    /// Set proving the execution trace: EVM, State, Copy and Exponentiation
+    /// circuits, exporting the Rw table used by the [`Self::DATA_PROOF`]
+    pub const EVM_PROOF: Self = Self::EMPTY
+        .with(SubCircuitKind::Evm)
+        .with(SubCircuitKind::State)
+        .with(SubCircuitKind::Copy)
+        .with(SubCircuitKind::Exp)
+        .exporting(SharedTable::Rw);
+    /// Set proving the block data: Tx, RLP, PublicInputs, Keccak and Bytecode
+    /// circuits, exporting the tables used by the [`Self::EVM_PROOF`]
+    pub const DATA_PROOF: Self = Self::EMPTY
+        .with(SubCircuitKind::Tx)
+        .with(SubCircuitKind::Rlp)
+        .with(SubCircuitKind::Pi)
+        .with(SubCircuitKind::Keccak)
+        .with(SubCircuitKind::Bytecode)
+        .exporting(SharedTable::Tx)
+        .exporting(SharedTable::Bytecode)
+        .exporting(SharedTable::Block)
+        .exporting(SharedTable::Keccak)
+        .exporting(SharedTable::Withdrawal);
+
+    /// Return the set extended with `kind`
+    pub const fn with(self, kind: SubCircuitKind) -> Self {
+        Self(self.0 | (1 << kind as u32))
+    }
+
+    /// Return the set exporting `table`, so that its content is exposed as
+    /// instance values that other top-level circuits can use as an external
+    /// table.  Only has effect when the producer of the table is in the set.
+    pub const fn exporting(self, table: SharedTable) -> Self {
+        Self(self.0 | (1 << (EXPORTED_TABLES_OFFSET + table as u32)))
+    }
+
+    /// Build a set from its bitmask representation
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Return the bitmask representation of the set
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if the set contains `kind`
+    pub const fn contains(self, kind: SubCircuitKind) -> bool {
+        self.0 & (1 << kind as u32) != 0
+    }
+
+    /// Returns true if the set is marked as exporting `table`
+    pub const fn exports(self, table: SharedTable) -> bool {
+        self.0 & (1 << (EXPORTED_TABLES_OFFSET + table as u32)) != 0
+    }
+
+    /// Iterate over the sub-circuits in the set
+    pub fn iter(self) -> impl Iterator<Item = SubCircuitKind> {
+        SubCircuitKind::iter().filter(move |kind| self.contains(*kind))
+    }
+
+    /// Returns the shared tables used by any sub-circuit in the set
+    pub fn tables(self) -> Vec<SharedTable> {
+        SharedTable::iter()
+            .filter(|table| self.iter().any(|kind| kind.tables().contains(table)))
+            .collect()
+    }
+
+    /// Returns the shared tables whose producer is part of the set
+    pub fn internal_tables(self) -> Vec<SharedTable> {
+        self.tables()
+            .into_iter()
+            .filter(|table| !self.is_external(*table))
+            .collect()
+    }
+
+    /// Returns the shared tables used by the set whose producer is not part
+    /// of it.  These tables are external inputs of the composed circuit.
+    pub fn external_tables(self) -> Vec<SharedTable> {
+        self.tables()
+            .into_iter()
+            .filter(|table| self.is_external(*table))
+            .collect()
+    }
+
+    /// Returns the internal tables whose content is exported as instance
+    /// values
+    pub fn exported_tables(self) -> Vec<SharedTable> {
+        self.internal_tables()
+            .into_iter()
+            .filter(|table| self.exports(*table))
+            .collect()
+    }
+
+    /// Returns the tables that are linked to other top-level circuits through
+    /// instance columns: the external and the exported tables.
+    pub fn linked_tables(self) -> Vec<SharedTable> {
+        self.tables()
+            .into_iter()
+            .filter(|table| self.is_external(*table) || self.exports(*table))
+            .collect()
+    }
+
+    fn is_external(self, table: SharedTable) -> bool {
+        table
+            .producer()
+            .map_or(true, |producer| !self.contains(producer))
+    }
+}
+
+fn table<T: Clone>(table: &Option<T>) -> T {
+    table
+        .clone()
+        .expect("shared table is constructed for every sub-circuit that uses it")
+}
+
+/// Configuration of a [`ComposedCircuit`].  Only the shared tables and the
+/// sub-circuits that are part of the composition are configured.
+#[derive(Clone)]
+pub struct ComposedCircuitConfig<F: Field> {
+    sub_circuits: SubCircuitSet,
+
+    tx_table: Option<TxTable>,
+    rw_table: Option<RwTable>,
+    mpt_table: Option<MptTable>,
+    bytecode_table: Option<BytecodeTable>,
+    block_table: Option<BlockTable>,
+    copy_table: Option<CopyTable>,
+    exp_table: Option<ExpTable>,
+    keccak_table: Option<KeccakTable>,
+    rlp_table: Option<RlpTable>,
+    withdrawal_table: Option<WithdrawalTable>,
+
+    evm_circuit: Option<EvmCircuitConfig<F>>,
+    state_circuit: Option<StateCircuitConfig<F>>,
+    tx_circuit: Option<TxCircuitConfig<F>>,
+    bytecode_circuit: Option<BytecodeCircuitConfig<F>>,
+    copy_circuit: Option<CopyCircuitConfig<F>>,
+    exp_circuit: Option<ExpCircuitConfig<F>>,
+    keccak_circuit: Option<KeccakCircuitConfig<F>>,
+    pi_circuit: Option<PiCircuitConfig<F>>,
+    mpt_circuit: Option<MptCircuitConfig<F>>,
+    rlp_circuit: Option<RlpCircuitConfig<F>>,
+    receipt_circuit: Option<ReceiptCircuitConfig<F>>,
+}
+
+/// Composed circuit configuration arguments
+pub struct ComposedCircuitConfigArgs {
+    /// Sub-circuits that are part of the composition
+    pub sub_circuits: SubCircuitSet,
+    /// Max txs
+    pub max_txs: usize,
+    /// Max calldata
+    pub max_calldata: usize,
+    /// Mock randomness
+    pub mock_randomness: u64,
+    /// Hardfork whose rules are used to prove the block
+    pub hardfork: Hardfork,
+}
+
+impl<F: Field> SubCircuitConfig<F> for ComposedCircuitConfig<F> {
+    type ConfigArgs = ComposedCircuitConfigArgs;
+
+    /// Configure ComposedCircuitConfig
+    fn new(
+        meta: &mut ConstraintSystem<F>,
+        Self::ConfigArgs {
+            sub_circuits,
+            max_txs,
+            max_calldata,
+            mock_randomness,
+            hardfork,
+        }: Self::ConfigArgs,
+    ) -> Self {
+        let tables = sub_circuits.tables();
+        let uses = |table: SharedTable| tables.contains(&table);
+
+        let tx_table = uses(SharedTable::Tx).then(|| TxTable::construct(meta));
+        let rw_table = uses(SharedTable::Rw).then(|| RwTable::construct(meta));
+        let mpt_table = uses(SharedTable::Mpt).then(|| MptTable::construct(meta));
+        let bytecode_table = uses(SharedTable::Bytecode).then(|| BytecodeTable::construct(meta));
+        let block_table = uses(SharedTable::Block).then(|| BlockTable::construct(meta));
+        let q_copy_table = uses(SharedTable::Copy).then(|| meta.fixed_column());
+        let copy_table = q_copy_table.map(|q_copy_table| CopyTable::construct(meta, q_copy_table));
+        let exp_table = uses(SharedTable::Exp).then(|| ExpTable::construct(meta));
+        let keccak_table = uses(SharedTable::Keccak).then(|| KeccakTable::construct(meta));
+        let rlp_table = uses(SharedTable::Rlp).then(|| RlpTable::construct(meta));
+        let withdrawal_table =
+            uses(SharedTable::Withdrawal).then(|| WithdrawalTable::construct(meta));
+
+        // Use a mock randomness instead of the randomness derived from the challange
+        // (either from mock or real prover) to help debugging assignments.
+        let power_of_randomness: [Expression<F>; 31] = array::from_fn(|i| {
+            Expression::Constant(F::from(mock_randomness).pow(&[1 + i as u64, 0, 0, 0]))
+        });
+
+        let challenges = Challenges::mock(
+            power_of_randomness[0].clone(),
+            power_of_randomness[0].clone(),
+            power_of_randomness[0].clone(),
+        );
+
+        // The sub-circuits are configured in the same order as their instance
+        // columns are returned by `instance`.
+        let keccak_circuit = sub_circuits.contains(SubCircuitKind::Keccak).then(|| {
+            KeccakCircuitConfig::new(
+                meta,
+                KeccakCircuitConfigArgs {
+                    keccak_table: table(&keccak_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let pi_circuit = sub_circuits.contains(SubCircuitKind::Pi).then(|| {
+            PiCircuitConfig::new(
+                meta,
+                PiCircuitConfigArgs {
+                    max_txs,
+                    max_calldata,
+                    block_table: table(&block_table),
+                    tx_table: table(&tx_table),
+                    keccak_table: table(&keccak_table),
+                    rlp_table: table(&rlp_table),
+                    withdrawal_table: table(&withdrawal_table),
+                    rw_table: table(&rw_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let tx_circuit = sub_circuits.contains(SubCircuitKind::Tx).then(|| {
+            TxCircuitConfig::new(
+                meta,
+                TxCircuitConfigArgs {
+                    tx_table: table(&tx_table),
+                    keccak_table: table(&keccak_table),
+                    rlp_table: table(&rlp_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let rlp_circuit = sub_circuits.contains(SubCircuitKind::Rlp).then(|| {
+            RlpCircuitConfig::new(
+                meta,
+                RlpCircuitConfigArgs {
+                    rlp_table: table(&rlp_table),
+                    tx_table: table(&tx_table),
+                    block_table: table(&block_table),
+                    keccak_table: table(&keccak_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let bytecode_circuit = sub_circuits.contains(SubCircuitKind::Bytecode).then(|| {
+            BytecodeCircuitConfig::new(
+                meta,
+                BytecodeCircuitConfigArgs {
+                    bytecode_table: table(&bytecode_table),
+                    keccak_table: table(&keccak_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let copy_circuit = sub_circuits.contains(SubCircuitKind::Copy).then(|| {
+            CopyCircuitConfig::new(
+                meta,
+                CopyCircuitConfigArgs {
+                    tx_table: table(&tx_table),
+                    rw_table: table(&rw_table),
+                    bytecode_table: table(&bytecode_table),
+                    copy_table: table(&copy_table),
+                    q_enable: table(&q_copy_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let state_circuit = sub_circuits.contains(SubCircuitKind::State).then(|| {
+            StateCircuitConfig::new(
+                meta,
+                StateCircuitConfigArgs {
+                    rw_table: table(&rw_table),
+                    mpt_table: table(&mpt_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let exp_circuit = sub_circuits
+            .contains(SubCircuitKind::Exp)
+            .then(|| ExpCircuitConfig::new(meta, table(&exp_table)));
+        let evm_circuit = sub_circuits.contains(SubCircuitKind::Evm).then(|| {
+            EvmCircuitConfig::new(
+                meta,
+                EvmCircuitConfigArgs {
+                    challenges: challenges.clone(),
+                    tx_table: table(&tx_table),
+                    rw_table: table(&rw_table),
+                    bytecode_table: table(&bytecode_table),
+                    block_table: table(&block_table),
+                    copy_table: table(&copy_table),
+                    keccak_table: table(&keccak_table),
+                    exp_table: table(&exp_table),
+                    withdrawal_table: table(&withdrawal_table),
+                    hardfork,
+                },
+            )
+        });
+        let mpt_circuit = sub_circuits.contains(SubCircuitKind::Mpt).then(|| {
+            MptCircuitConfig::new(
+                meta,
+                MptCircuitConfigArgs {
+                    mpt_table: table(&mpt_table),
+                    keccak_table: table(&keccak_table),
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+        let receipt_circuit = sub_circuits.contains(SubCircuitKind::Receipt).then(|| {
+            ReceiptCircuitConfig::new(
+                meta,
+                ReceiptCircuitConfigArgs {
+                    rw_table: table(&rw_table),
+                    tx_table: table(&tx_table),
+                    keccak_table: table(&keccak_table),
+                    max_txs,
+                    challenges: challenges.clone(),
+                },
+            )
+        });
+
+        let config = Self {
+            sub_circuits,
+            tx_table,
+            rw_table,
+            mpt_table,
+            bytecode_table,
+            block_table,
+            copy_table,
+            exp_table,
+            keccak_table,
+            rlp_table,
+            withdrawal_table,
+            evm_circuit,
+            state_circuit,
+            tx_circuit,
+            bytecode_circuit,
+            copy_circuit,
+            exp_circuit,
+            keccak_circuit,
+            pi_circuit,
+            mpt_circuit,
+            rlp_circuit,
+            receipt_circuit,
+        };
+        config.link_tables(meta);
+        config
+    }
+}
+
+impl<F: Field> ComposedCircuitConfig<F> {
+    /// Returns the sub-circuits that are part of the composition
+    pub fn sub_circuits(&self) -> SubCircuitSet {
+        self.sub_circuits
+    }
+
+    /// Returns the shared tables that are external inputs of the composition
+    pub fn external_tables(&self) -> Vec<SharedTable> {
+        self.sub_circuits.external_tables()
+    }
+
+    /// Returns the columns of a linkable shared table, in the order of its
+    /// lookup expressions.
+    fn table_columns(&self, shared_table: SharedTable) -> Vec<Column<Any>> {
+        let advice_columns = |columns: Vec<Column<Advice>>| -> Vec<Column<Any>> {
+            columns.into_iter().map(|column| column.into()).collect()
+        };
+        match shared_table {
+            SharedTable::Tx => {
+                let tx_table = table(&self.tx_table);
+                vec![
+                    tx_table.tx_id.into(),
+                    tx_table.tag.into(),
+                    tx_table.index.into(),
+                    tx_table.value.into(),
+                ]
+            }
+            SharedTable::Rw => advice_columns(table(&self.rw_table).columns()),
+            SharedTable::Mpt => advice_columns(table(&self.mpt_table).columns()),
+            SharedTable::Bytecode => advice_columns(table(&self.bytecode_table).columns()),
+            SharedTable::Block => advice_columns(table(&self.block_table).columns()),
+            SharedTable::Keccak => advice_columns(table(&self.keccak_table).columns()),
+            SharedTable::Rlp => advice_columns(table(&self.rlp_table).columns()),
+            SharedTable::Withdrawal => advice_columns(table(&self.withdrawal_table).columns()),
+            SharedTable::Copy | SharedTable::Exp => {
+                unreachable!("{:?} table is not linkable", shared_table)
+            }
+        }
+    }
+
+    /// Link the external and exported tables to instance columns that
+    /// contain their rows.  The rows of an external table must be instance
+    /// rows, and the instance rows of an exported table must be rows of the
+    /// table.  Unused instance rows are zero, like the unassigned rows of the
+    /// tables.
+    fn link_tables(&self, meta: &mut ConstraintSystem<F>) {
+        for shared_table in self.sub_circuits.linked_tables() {
+            assert!(
+                shared_table.is_linkable(),
+                "{:?} table can't be linked between top-level circuits, its producer must be \
+                 part of the composition",
+                shared_table
+            );
+            let columns = self.table_columns(shared_table);
+            let instance_columns: Vec<_> = columns.iter().map(|_| meta.instance_column()).collect();
+            let is_external = self.sub_circuits.is_external(shared_table);
+            let name = if is_external {
+                format!("external {:?} table row in instance", shared_table)
+            } else {
+                format!("exported {:?} table instance row in table", shared_table)
+            };
+            meta.lookup_any(Box::leak(name.into_boxed_str()), |meta| {
+                columns
+                    .iter()
+                    .zip(instance_columns.iter())
+                    .map(|(column, instance_column)| {
+                        let table_expr = meta.query_any(*column, Rotation::cur());
+                        let instance_expr = meta.query_instance(*instance_column, Rotation::cur());
+                        if is_external {
+                            (table_expr, instance_expr)
+                        } else {
+                            (instance_expr, table_expr)
+                        }
+                    })
+                    .collect()
+            });
+        }
+    }
+
+    /// Assign the shared tables that are not assigned by any sub-circuit of
+    /// the composition: the external tables, and the Block and Withdrawal
+    /// tables which are always assigned at the top level.  They use the same
+    /// rows as the instance values that link them.
+    fn load_tables(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        block: &Block<F>,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        let external_tables = self.external_tables();
+        for shared_table in self.sub_circuits.tables() {
+            if !(external_tables.contains(&shared_table)
+                || matches!(shared_table, SharedTable::Block | SharedTable::Withdrawal))
+            {
+                continue;
+            }
+            let columns = self.table_columns(shared_table);
+            let rows = table_rows(shared_table, block, challenges);
+            layouter.assign_region(
+                || format!("{:?} table", shared_table),
+                |mut region| {
+                    for (offset, row) in rows.iter().enumerate() {
+                        for (column, value) in columns.iter().zip_eq(row.iter()) {
+                            let annotation = || format!("{:?} table row {}", shared_table, offset);
+                            match column.column_type() {
+                                Any::Fixed => region.assign_fixed(
+                                    annotation,
+                                    (*column).try_into().unwrap(),
+                                    offset,
+                                    || *value,
+                                )?,
+                                _ => region.assign_advice(
+                                    annotation,
+                                    (*column).try_into().unwrap(),
+                                    offset,
+                                    || *value,
+                                )?,
+                            };
+                        }
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of columns of a linkable shared table.
+fn table_width(shared_table: SharedTable) -> usize {
+    match shared_table {
+        SharedTable::Tx => 4,
+        SharedTable::Rw => 11,
+        SharedTable::Mpt => 7,
+        SharedTable::Bytecode => 5,
+        SharedTable::Block => 3,
+        SharedTable::Keccak => 4,
+        SharedTable::Rlp => 4,
+        SharedTable::Withdrawal => 5,
+        SharedTable::Copy | SharedTable::Exp => {
+            unreachable!("{:?} table is not linkable", shared_table)
+        }
+    }
+}
+
+/// Rows of a linkable shared table assigned directly from the witness, in
+/// the same layout as the circuit that produces the table.
+fn table_rows<F: Field>(
+    shared_table: SharedTable,
+    block: &Block<F>,
+    challenges: &Challenges<Value<F>>,
+) -> Vec<Vec<Value<F>>> {
+    let zero_row = || vec![Value::known(F::zero()); table_width(shared_table)];
+    match shared_table {
+        SharedTable::Tx => {
+            let padding_txs: Vec<Transaction> = (block.txs.len()..block.circuits_params.max_txs)
+                .map(|i| Transaction {
+                    id: i + 1,
+                    ..Default::default()
+                })
+                .collect();
+            std::iter::once(zero_row())
+                .chain(
+                    block
+                        .txs
+                        .iter()
+                        .chain(padding_txs.iter())
+                        .flat_map(|tx| tx.table_assignments(*challenges))
+                        .map(|row| row.to_vec()),
+                )
+                .collect()
+        }
+        SharedTable::Rw => {
+            let (rows, _) = RwMap::table_assignments_prepad(
+                &block.rws.table_assignments(),
+                block.circuits_params.max_rws,
+            );
+            rows.iter()
+                .map(|row| {
+                    row.table_assignment(challenges.evm_word())
+                        .values()
+                        .to_vec()
+                })
+                .collect()
+        }
+        SharedTable::Mpt => block
+            .mpt_updates
+            .table_assignments(challenges.evm_word())
+            .iter()
+            .map(|row| row.values().copied().collect())
+            .collect(),
+        SharedTable::Bytecode => std::iter::once(zero_row())
+            .chain(
+                block
+                    .bytecodes
+                    .values()
+                    .flat_map(|bytecode| bytecode.table_assignments(challenges))
+                    .map(|row| row.to_vec()),
+            )
+            .collect(),
+        SharedTable::Block => std::iter::once(zero_row())
+            .chain(
+                block
+                    .context
+                    .table_assignments(challenges.evm_word())
+                    .into_iter()
+                    .map(|row| row.to_vec()),
+            )
+            .collect(),
+        SharedTable::Keccak => std::iter::once(zero_row())
+            .chain(
+                block
+                    .keccak_inputs
+                    .iter()
+                    .flat_map(|input| KeccakTable::assignments(input, challenges))
+                    .map(|row| row.to_vec()),
+            )
+            .collect(),
+        SharedTable::Rlp => rlp_table_assignments(
+            &block
+                .eth_txs()
+                .iter()
+                .map(|tx| tx.into())
+                .collect::<Vec<_>>(),
+            block.context.last().chain_id.as_u64(),
+            challenges,
+        )
+        .into_iter()
+        .map(|row| row.to_vec())
+        .collect(),
+        SharedTable::Withdrawal => std::iter::once(zero_row())
+            .chain(
+                WithdrawalTable::assignments(&block.withdrawals, &block.withdrawal_blocks)
+                    .into_iter()
+                    .map(|row| row.to_vec()),
+            )
+            .collect(),
+        SharedTable::Copy | SharedTable::Exp => {
+            unreachable!("{:?} table is not linkable", shared_table)
+        }
+    }
+}
+
+/// Number of rows used to assign a shared table directly from the witness.
+fn table_num_rows<F: Field>(shared_table: SharedTable, block: &Block<F>) -> usize {
+    match shared_table {
+        SharedTable::Tx => {
+            1 + TX_LEN * block.circuits_params.max_txs + block.circuits_params.max_calldata
+        }
+        SharedTable::Rw => block.circuits_params.max_rws,
+        SharedTable::Mpt => block.mpt_updates.len(),
+        SharedTable::Bytecode => {
+            1 + block
+                .bytecodes
+                .values()
+                .map(|bytecode| bytecode.bytes.len() + 1)
+                .sum::<usize>()
+        }
+        SharedTable::Block => 1 + block.context.table_assignments::<F>(Value::unknown()).len(),
+        SharedTable::Copy => {
+            1 + block
+                .copy_events
+                .iter()
+                .map(|c| c.bytes.len() * 2)
+                .sum::<usize>()
+        }
+        SharedTable::Exp => {
+            1 + block
+                .exp_events
+                .iter()
+                .map(|e| e.steps.len() * OFFSET_INCREMENT)
+                .sum::<usize>()
+        }
+        SharedTable::Keccak => 1 + block.keccak_inputs.len(),
+        SharedTable::Rlp => {
+            1 + block
+                .txs
+                .iter()
+                .map(|tx| TABLE_MAX_ROWS_PER_TX + 2 * tx.call_data.len())
+                .sum::<usize>()
+        }
+        SharedTable::Withdrawal => 2 + block.withdrawals.len(),
+    }
+}
+
+/// A top-level circuit that contains the subset of the zkEVM sub-circuits
+/// selected by the `SUB_CIRCUITS` bitmask (see [`SubCircuitSet::bits`]).
+#[derive(Clone, Default, Debug)]
+pub struct ComposedCircuit<
+    F: Field,
+    const SUB_CIRCUITS: u32,
+    const MAX_TXS: usize,
+    const MAX_CALLDATA: usize,
+    const MOCK_RANDOMNESS: u64,
+> {
+    /// Witness block, used to assign the external tables
+    pub block: Option<Block<F>>,
+    /// EVM Circuit
+    pub evm_circuit: Option<EvmCircuit<F>>,
+    /// State Circuit
+    pub state_circuit: Option<StateCircuit<F>>,
+    /// Tx Circuit
+    pub tx_circuit: Option<TxCircuit<F>>,
+    /// Public Input Circuit
+    pub pi_circuit: Option<PiCircuit<F>>,
+    /// Bytecode Circuit
+    pub bytecode_circuit: Option<BytecodeCircuit<F>>,
+    /// Copy Circuit
+    pub copy_circuit: Option<CopyCircuit<F>>,
+    /// Exp Circuit
+    pub exp_circuit: Option<ExpCircuit<F>>,
+    /// Keccak Circuit
+    pub keccak_circuit: Option<KeccakCircuit<F>>,
+    /// MPT Circuit
+    pub mpt_circuit: Option<MptCircuit<F>>,
+    /// RLP Circuit
+    pub rlp_circuit: Option<RlpCircuit<F>>,
+    /// Receipt Circuit
+    pub receipt_circuit: Option<ReceiptCircuit<F>>,
+}
+
+/// Top-level circuit with the EVM, State, Copy and Exponentiation circuits.
+pub type EvmProofCircuit<
+    F,
+    const MAX_TXS: usize,
+    const MAX_CALLDATA: usize,
+    const MOCK_RANDOMNESS: u64,
+> = ComposedCircuit<F, { SubCircuitSet::EVM_PROOF.bits() }, MAX_TXS, MAX_CALLDATA, MOCK_RANDOMNESS>;
+
+/// Top-level circuit with the Tx, RLP, PublicInputs, Keccak and Bytecode
+/// circuits.
+pub type DataProofCircuit<
+    F,
+    const MAX_TXS: usize,
+    const MAX_CALLDATA: usize,
+    const MOCK_RANDOMNESS: u64,
+> = ComposedCircuit<
+    F,
+    { SubCircuitSet::DATA_PROOF.bits() },
+    MAX_TXS,
+    MAX_CALLDATA,
+    MOCK_RANDOMNESS,
+>;
+
+impl<
+        F: Field,
+        const SUB_CIRCUITS: u32,
+        const MAX_TXS: usize,
+        const MAX_CALLDATA: usize,
+        const MOCK_RANDOMNESS: u64,
+    > ComposedCircuit<F, SUB_CIRCUITS, MAX_TXS, MAX_CALLDATA, MOCK_RANDOMNESS>
+{
+    /// Returns the sub-circuits that are part of the composition
+    pub fn sub_circuits() -> SubCircuitSet {
+        SubCircuitSet::from_bits(SUB_CIRCUITS)
+    }
+
+    /// Returns the instance columns of the external and exported tables,
+    /// which contain the rows of each table.  Two top-level circuits that use
+    /// the same table must be verified with the same values for them.
+    pub fn linked_tables_instance(block: &Block<F>) -> Vec<Vec<F>> {
+        let challenges = Challenges::mock(
+            Value::known(block.randomness),
+            Value::known(block.randomness),
+            Value::known(block.randomness),
+        );
+        let mut instance = Vec::new();
+        for shared_table in Self::sub_circuits().linked_tables() {
+            let rows = table_rows(shared_table, block, &challenges);
+            let mut columns = vec![Vec::with_capacity(rows.len()); table_width(shared_table)];
+            for row in rows {
+                for (column, value) in columns.iter_mut().zip_eq(row) {
+                    value.map(|value| column.push(value));
+                }
+            }
+            instance.extend(columns);
+        }
+        instance
+    }
+}
+
+// Eventhough the ComposedCircuit is not a subcircuit we implement the
+// SubCircuit trait for it in order to get the `new_from_block` and `instance`
+// methods that allow us to generalize integration tests.
+impl<
+        F: Field,
+        const SUB_CIRCUITS: u32,
+        const MAX_TXS: usize,
+        const MAX_CALLDATA: usize,
+        const MOCK_RANDOMNESS: u64,
+    > SubCircuit<F> for ComposedCircuit<F, SUB_CIRCUITS, MAX_TXS, MAX_CALLDATA, MOCK_RANDOMNESS>
+{
+    type Config = ComposedCircuitConfig<F>;
+
+    fn new_from_block(block: &Block<F>) -> Self {
+        let sub_circuits = Self::sub_circuits();
+        let contains = |kind| sub_circuits.contains(kind);
+
+        Self {
+            block: Some(block.clone()),
+            evm_circuit: contains(SubCircuitKind::Evm).then(|| EvmCircuit::new_from_block(block)),
+            state_circuit: contains(SubCircuitKind::State)
+                .then(|| StateCircuit::new_from_block(block)),
+            tx_circuit: contains(SubCircuitKind::Tx).then(|| TxCircuit::new_from_block(block)),
+            pi_circuit: contains(SubCircuitKind::Pi).then(|| PiCircuit::new_from_block(block)),
+            bytecode_circuit: contains(SubCircuitKind::Bytecode)
+                .then(|| BytecodeCircuit::new_from_block(block)),
+            copy_circuit: contains(SubCircuitKind::Copy)
+                .then(|| CopyCircuit::new_from_block_no_external(block)),
+            exp_circuit: contains(SubCircuitKind::Exp).then(|| ExpCircuit::new_from_block(block)),
+            keccak_circuit: contains(SubCircuitKind::Keccak)
+                .then(|| KeccakCircuit::new_from_block(block)),
+            mpt_circuit: contains(SubCircuitKind::Mpt).then(|| MptCircuit::new_from_block(block)),
+            rlp_circuit: contains(SubCircuitKind::Rlp).then(|| RlpCircuit::new_from_block(block)),
+            receipt_circuit: contains(SubCircuitKind::Receipt)
+                .then(|| ReceiptCircuit::new_from_block(block)),
+        }
+    }
+
+    /// Returns suitable inputs for the ComposedCircuit.
+    fn instance(&self) -> Vec<Vec<F>> {
+        let mut instance = Vec::new();
+        if let Some(circuit) = &self.keccak_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.pi_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.tx_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.rlp_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.bytecode_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.copy_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.state_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.exp_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.evm_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.mpt_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(circuit) = &self.receipt_circuit {
+            instance.extend_from_slice(&circuit.instance());
+        }
+        if let Some(block) = &self.block {
+            instance.extend(Self::linked_tables_instance(block));
+        }
+
+        instance
+    }
+
+    /// Return the minimum number of rows required to prove the block
+    fn min_num_rows_block(block: &Block<F>) -> (usize, usize) {
+        let sub_circuits = Self::sub_circuits();
+        let rows: Vec<(usize, usize)> = sub_circuits
+            .iter()
+            .map(|kind| match kind {
+                SubCircuitKind::Evm => EvmCircuit::min_num_rows_block(block),
+                SubCircuitKind::State => StateCircuit::min_num_rows_block(block),
+                SubCircuitKind::Tx => TxCircuit::min_num_rows_block(block),
+                SubCircuitKind::Bytecode => BytecodeCircuit::min_num_rows_block(block),
+                SubCircuitKind::Copy => CopyCircuit::min_num_rows_block(block),
+                SubCircuitKind::Exp => ExpCircuit::min_num_rows_block(block),
+                SubCircuitKind::Keccak => KeccakCircuit::min_num_rows_block(block),
+                SubCircuitKind::Pi => PiCircuit::min_num_rows_block(block),
+                SubCircuitKind::Mpt => MptCircuit::min_num_rows_block(block),
+                SubCircuitKind::Rlp => RlpCircuit::min_num_rows_block(block),
+                SubCircuitKind::Receipt => ReceiptCircuit::min_num_rows_block(block),
+            })
+            .chain(
+                sub_circuits
+                    .external_tables()
+                    .into_iter()
+                    .map(|shared_table| {
+                        let num_rows = table_num_rows(shared_table, block);
+                        (num_rows, num_rows)
+                    }),
+            )
+            .collect();
+        let (rows_without_padding, rows_with_padding): (Vec<usize>, Vec<usize>) =
+            rows.into_iter().unzip();
+        (
+            itertools::max(rows_without_padding).unwrap_or_default(),
+            itertools::max(rows_with_padding).unwrap_or_default(),
+        )
+    }
+
+    /// Make the assignments to the ComposedCircuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        if let (Some(circuit), Some(config)) = (&self.keccak_circuit, &config.keccak_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        if let (Some(circuit), Some(config)) = (&self.bytecode_circuit, &config.bytecode_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        if let (Some(circuit), Some(config)) = (&self.tx_circuit, &config.tx_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        if let (Some(circuit), Some(config)) = (&self.rlp_circuit, &config.rlp_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        let state_cells = match (&self.state_circuit, &config.state_circuit) {
+            (Some(circuit), Some(config)) => Some(circuit.assign(config, challenges, layouter)?),
+            _ => None,
+        };
+        if let (Some(circuit), Some(config)) = (&self.copy_circuit, &config.copy_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        if let (Some(circuit), Some(config)) = (&self.exp_circuit, &config.exp_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        if let (Some(circuit), Some(config)) = (&self.evm_circuit, &config.evm_circuit) {
+            circuit.synthesize_sub(config, challenges, layouter)?;
+        }
+        let pi_cells = match (&self.pi_circuit, &config.pi_circuit) {
+            (Some(circuit), Some(config)) => Some(circuit.assign(config, challenges, layouter)?),
+            _ => None,
+        };
+        let mpt_cells = match (&self.mpt_circuit, &config.mpt_circuit) {
+            (Some(circuit), Some(config)) => Some(config.assign(
+                layouter,
+                &circuit.updates,
+                circuit.n_rows,
+                circuit.randomness,
+                challenges,
+            )?),
+            _ => None,
+        };
+        // The roots proved by the MptCircuit are the state roots of the
+        // PublicInputs circuit.
+        if let (Some(pi_cells), Some(mpt_cells)) = (&pi_cells, &mpt_cells) {
+            layouter.assign_region(
+                || "mpt roots are the state roots of the public inputs",
+                |mut region| {
+                    for (mpt_cell, pi_cell) in [
+                        (&mpt_cells.randomness, &pi_cells.randomness),
+                        (&mpt_cells.initial_root_rlc, &pi_cells.prev_state_root),
+                        (&mpt_cells.final_root_rlc, &pi_cells.chunk_state_root),
+                    ] {
+                        region.constrain_equal(mpt_cell.cell(), pi_cell.cell())?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        // The roots proved by the MptCircuit are the state roots before and
+        // after the rows of the StateCircuit.
+        if let (Some(state_cells), Some(mpt_cells)) = (&state_cells, &mpt_cells) {
+            layouter.assign_region(
+                || "state roots are the roots of the mpt circuit",
+                |mut region| {
+                    for (state_cell, mpt_cell) in [
+                        (
+                            &state_cells.initial_state_root,
+                            &mpt_cells.initial_root_word,
+                        ),
+                        (&state_cells.final_state_root, &mpt_cells.final_root_word),
+                    ] {
+                        region.constrain_equal(state_cell.cell(), mpt_cell.cell())?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        let receipt_cells = match (&self.receipt_circuit, &config.receipt_circuit) {
+            (Some(circuit), Some(config)) => Some(circuit.assign(config, challenges, layouter)?),
+            _ => None,
+        };
+        // The number, the receipts root and the logs bloom of the header of
+        // the block are the ones proved by the ReceiptCircuit.
+        if let (Some(pi_cells), Some(receipt_cells)) = (&pi_cells, &receipt_cells) {
+            layouter.assign_region(
+                || "receipts root and logs bloom of the public inputs",
+                |mut region| {
+                    for (receipt_cell, pi_cell) in [
+                        (&receipt_cells.block_number, &pi_cells.number),
+                        (&receipt_cells.receipts_root, &pi_cells.receipts_root),
+                        (&receipt_cells.logs_bloom, &pi_cells.logs_bloom),
+                    ] {
+                        region.constrain_equal(receipt_cell.cell(), pi_cell.cell())?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: Field,
+        const SUB_CIRCUITS: u32,
+        const MAX_TXS: usize,
+        const MAX_CALLDATA: usize,
+        const MOCK_RANDOMNESS: u64,
+    > Circuit<F> for ComposedCircuit<F, SUB_CIRCUITS, MAX_TXS, MAX_CALLDATA, MOCK_RANDOMNESS>
+{
+    type Config = ComposedCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        Self::Config::new(
+            meta,
+            ComposedCircuitConfigArgs {
+                sub_circuits: Self::sub_circuits(),
+                max_txs: MAX_TXS,
+                max_calldata: MAX_CALLDATA,
+                mock_randomness: MOCK_RANDOMNESS,
+                hardfork: Hardfork::default(),
+            },
+        )
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let block = self.block.as_ref().unwrap();
+        let hardfork = block.circuits_params.hardfork;
+        let config = ComposedCircuitConfig {
+            evm_circuit: config
+                .evm_circuit
+                .map(|config| config.with_hardfork(hardfork)),
+            ..config
+        };
+        let challenges = Challenges::mock(
+            Value::known(block.randomness),
+            Value::known(block.randomness),
+            Value::known(block.randomness),
+        );
+
+        config.load_tables(&mut layouter, block, &challenges)?;
+        self.synthesize_sub(&config, &challenges, &mut layouter)
+    }
+}
+
+impl<
+        F: Field,
+        const SUB_CIRCUITS: u32,
+        const MAX_TXS: usize,
+        const MAX_CALLDATA: usize,
+        const MOCK_RANDOMNESS: u64,
+    > ComposedCircuit<F, SUB_CIRCUITS, MAX_TXS, MAX_CALLDATA, MOCK_RANDOMNESS>
+{
+    /// From the witness data, generate a ComposedCircuit instance with the
+    /// selected sub-circuits filled with their corresponding witnesses.
+    ///
+    /// Also, return with it the minimum required SRS degree for the
+    /// circuit and the Public Inputs needed.
+    #[allow(clippy::type_complexity)]
+    pub fn build(
+        geth_data: GethData,
+        circuits_params: CircuitsParams,
+    ) -> Result<(u32, Self, Vec<Vec<F>>, CircuitInputBuilder), bus_mapping::Error> {
+        let block_data =
+            BlockData::new_from_geth_data_with_params(geth_data.clone(), circuits_params);
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&geth_data.eth_block, &geth_data.geth_traces)
+            .expect("could not handle block tx");
+
+        let ret = Self::build_from_circuit_input_builder(&builder)?;
+        Ok((ret.0, ret.1, ret.2, builder))
+    }
+
+    /// From CircuitInputBuilder, generate a ComposedCircuit instance with the
+    /// selected sub-circuits filled with their corresponding witnesses.
+    ///
+    /// Also, return with it the minimum required SRS degree for the circuit and
+    /// the Public Inputs needed.
+    pub fn build_from_circuit_input_builder(
+        builder: &CircuitInputBuilder,
+    ) -> Result<(u32, Self, Vec<Vec<F>>), bus_mapping::Error> {
+        let mut block = block_convert(&builder.block, &builder.code_db).unwrap();
+        block.randomness = F::from(MOCK_RANDOMNESS);
+        assert_eq!(block.circuits_params.max_txs, MAX_TXS);
+        assert_eq!(block.circuits_params.max_calldata, MAX_CALLDATA);
+
+        const NUM_BLINDING_ROWS: usize = 64;
+        let (_, rows_needed) = Self::min_num_rows_block(&block);
+        let k = log2_ceil(NUM_BLINDING_ROWS + rows_needed);
+        log::debug!(
+            "composed circuit {:?} uses k = {}, external tables: {:?}",
+            Self::sub_circuits(),
+            k,
+            Self::sub_circuits().external_tables()
+        );
+
+        let circuit = Self::new_from_block(&block);
+
+        let instance = circuit.instance();
+        Ok((k, circuit, instance))
+    }
+}
+
+#[cfg(test)]
+mod composition_tests {
+    use super::*;
+    use crate::super_circuit::super_circuit_tests::{
+        block_1tx, test_composed_circuit, TEST_MOCK_RANDOMNESS,
+    };
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    #[test]
+    fn sub_circuit_set_tables() {
+        assert!(SubCircuitSet::ALL.external_tables().is_empty());
+        assert_eq!(
+            SubCircuitSet::ALL.internal_tables().len(),
+            SharedTable::iter().count()
+        );
+
+        assert_eq!(
+            SubCircuitSet::EVM_PROOF.external_tables(),
+            vec![
+                SharedTable::Tx,
+                SharedTable::Mpt,
+                SharedTable::Bytecode,
+                SharedTable::Block,
+                SharedTable::Keccak,
+                SharedTable::Withdrawal,
+            ]
+        );
+        assert_eq!(
+            SubCircuitSet::EVM_PROOF.internal_tables(),
+            vec![SharedTable::Rw, SharedTable::Copy, SharedTable::Exp]
+        );
+
+        assert_eq!(
+            SubCircuitSet::EVM_PROOF.exported_tables(),
+            vec![SharedTable::Rw]
+        );
+        assert_eq!(
+            SubCircuitSet::EVM_PROOF.linked_tables(),
+            vec![
+                SharedTable::Tx,
+                SharedTable::Rw,
+                SharedTable::Mpt,
+                SharedTable::Bytecode,
+                SharedTable::Block,
+                SharedTable::Keccak,
+                SharedTable::Withdrawal,
+            ]
+        );
+        assert!(SubCircuitSet::ALL.exported_tables().is_empty());
+
+        assert_eq!(
+            SubCircuitSet::DATA_PROOF.external_tables(),
+            vec![SharedTable::Rw]
+        );
+        assert_eq!(
+            SubCircuitSet::DATA_PROOF.exported_tables(),
+            vec![
+                SharedTable::Tx,
+                SharedTable::Bytecode,
+                SharedTable::Block,
+                SharedTable::Keccak,
+                SharedTable::Withdrawal,
+            ]
+        );
+        assert_eq!(
+            SubCircuitSet::DATA_PROOF.tables(),
+            vec![
+                SharedTable::Tx,
+                SharedTable::Rw,
+                SharedTable::Bytecode,
+                SharedTable::Block,
+                SharedTable::Keccak,
+                SharedTable::Rlp,
+                SharedTable::Withdrawal,
+            ]
+        );
+
+        let with_mpt = SubCircuitSet::EVM_PROOF.with(SubCircuitKind::Mpt);
+        assert!(!with_mpt.external_tables().contains(&SharedTable::Mpt));
+        assert!(with_mpt.internal_tables().contains(&SharedTable::Mpt));
+
+        let keccak_only = SubCircuitSet::EMPTY.with(SubCircuitKind::Keccak);
+        assert_eq!(
+            keccak_only.iter().collect::<Vec<_>>(),
+            [SubCircuitKind::Keccak]
+        );
+        assert_eq!(keccak_only.tables(), vec![SharedTable::Keccak]);
+        assert!(keccak_only.external_tables().is_empty());
+    }
+
+    #[test]
+    fn composed_circuit_degree() {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        EvmProofCircuit::<_, 1, 32, 0x100>::configure(&mut cs);
+        log::info!("evm proof circuit degree: {}", cs.degree());
+        assert!(cs.degree() <= 9);
+
+        let mut cs = ConstraintSystem::<Fr>::default();
+        DataProofCircuit::<_, 1, 32, 0x100>::configure(&mut cs);
+        log::info!("data proof circuit degree: {}", cs.degree());
+        assert!(cs.degree() <= 9);
+    }
+
+    const MAX_TXS: usize = 1;
+    const MAX_CALLDATA: usize = 32;
+
+    fn circuits_params() -> CircuitsParams {
+        CircuitsParams {
+            max_txs: MAX_TXS,
+            max_calldata: MAX_CALLDATA,
+            max_rws: 256,
+            max_copy_rows: 256,
+            max_bytecode: 512,
+            max_mpt_rows: 0,
+            keccak_padding: None,
+            hardfork: Hardfork::default(),
+        }
+    }
+
+    #[test]
+    fn evm_and_data_proofs_share_linked_tables() {
+        let geth_data = block_1tx();
+        let block_data =
+            BlockData::new_from_geth_data_with_params(geth_data.clone(), circuits_params());
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&geth_data.eth_block, &geth_data.geth_traces)
+            .unwrap();
+        let mut block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        block.randomness = Fr::from(TEST_MOCK_RANDOMNESS);
+
+        // The MPT table is external to the EVM proof only, the Rw table is
+        // exported by the EVM proof and the rest of the external tables of
+        // the EVM proof are exported by the data proof.
+        let mut evm_instance =
+            EvmProofCircuit::<Fr, MAX_TXS, MAX_CALLDATA, TEST_MOCK_RANDOMNESS>::linked_tables_instance(
+                &block,
+            );
+        let mpt_columns_start = table_width(SharedTable::Tx) + table_width(SharedTable::Rw);
+        evm_instance.drain(mpt_columns_start..mpt_columns_start + table_width(SharedTable::Mpt));
+        let data_instance =
+            DataProofCircuit::<Fr, MAX_TXS, MAX_CALLDATA, TEST_MOCK_RANDOMNESS>::linked_tables_instance(
+                &block,
+            );
+        assert_eq!(evm_instance, data_instance);
+    }
+
+    // High memory usage tests.  Run in serial with:
+    // `cargo test [...] serial_ -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn serial_test_evm_proof_circuit_1tx() {
+        test_composed_circuit::<
+            { SubCircuitSet::EVM_PROOF.bits() },
+            MAX_TXS,
+            MAX_CALLDATA,
+            TEST_MOCK_RANDOMNESS,
+        >(block_1tx(), circuits_params());
+    }
+
+    #[ignore]
+    #[test]
+    fn serial_test_data_proof_circuit_1tx() {
+        test_composed_circuit::<
+            { SubCircuitSet::DATA_PROOF.bits() },
+            MAX_TXS,
+            MAX_CALLDATA,
+            TEST_MOCK_RANDOMNESS,
+        >(block_1tx(), circuits_params());
+    }
+}
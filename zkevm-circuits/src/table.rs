@@ -4,14 +4,15 @@ use crate::copy_circuit::number_or_hash_to_field;
 use crate::evm_circuit::util::rlc;
 use crate::exp_circuit::{OFFSET_INCREMENT, ROWS_PER_STEP};
 use crate::impl_expr;
+use crate::rlp_circuit::rlp_table_assignments;
 use crate::util::build_tx_log_address;
 use crate::util::Challenges;
 use crate::witness::{
-    Block, BlockContext, Bytecode, MptUpdateRow, MptUpdates, Rw, RwMap, RwRow, Transaction,
+    Block, BlockContexts, Bytecode, MptUpdateRow, MptUpdates, Rw, RwMap, RwRow, Transaction,
 };
 use bus_mapping::circuit_input_builder::{CopyDataType, CopyEvent, CopyStep, ExpEvent};
 use core::iter::once;
-use eth_types::{Field, ToLittleEndian, ToScalar, Word, U256};
+use eth_types::{geth_types, Field, ToLittleEndian, ToScalar, Word, U256};
 use gadgets::binary_number::{BinaryNumberChip, BinaryNumberConfig};
 use gadgets::util::{split_u256, split_u256_limb64};
 use halo2_proofs::{
@@ -22,6 +23,7 @@ use halo2_proofs::{
 use halo2_proofs::{circuit::Layouter, plonk::*, poly::Rotation};
 use itertools::Itertools;
 use keccak256::plain::Keccak;
+use serde::{Deserialize, Serialize};
 use std::array;
 use strum_macros::{EnumCount, EnumIter};
 
@@ -80,9 +82,27 @@ pub enum TxFieldTag {
     CallDataLength,
     /// Gas cost for transaction call data (4 for byte == 0, 16 otherwise)
     CallDataGasCost,
+    /// Type of the transaction envelope (EIP-2718)
+    TxType,
+    /// ChainID
+    ChainId,
+    /// MaxFeePerGas (EIP-1559)
+    MaxFeePerGas,
+    /// MaxPriorityFeePerGas (EIP-1559)
+    MaxPriorityFeePerGas,
+    /// Number of addresses in the access list (EIP-2930)
+    AccessListAddressesLen,
+    /// Number of storage keys in the access list (EIP-2930)
+    AccessListStorageKeysLen,
+    /// Number of the block of the batch that contains the transaction
+    BlockNumber,
     /// TxSignHash: Hash of the transaction without the signature, used for
     /// signing.
     TxSignHash,
+    /// Encoding of the signed transaction, the value of the transactions
+    /// trie.  Only in the RlpTable, with its length as index and its RLC with
+    /// the keccak input challenge as value.
+    SignedTx,
     /// CallData
     CallData,
 }
@@ -193,8 +213,79 @@ impl<F: Field> LookupTable<F> for TxTable {
     }
 }
 
+/// Table with the fields of the transactions decoded from the RLP encoding of
+/// their signed message and of the signed transaction, shared between the RLP
+/// Circuit, the Tx Circuit and the PI Circuit.  The values use the same
+/// encoding as the TxTable.  Rows with the `TxFieldTag::Null` tag don't
+/// contain any field.
+#[derive(Clone, Copy, Debug)]
+pub struct RlpTable {
+    /// Tx ID
+    pub tx_id: Column<Advice>,
+    /// Tag (TxFieldTag)
+    pub tag: Column<Advice>,
+    /// Index for Tag = CallData
+    pub index: Column<Advice>,
+    /// Value
+    pub value: Column<Advice>,
+}
+
+impl DynamicTableColumns for RlpTable {
+    fn columns(&self) -> Vec<Column<Advice>> {
+        vec![self.tx_id, self.tag, self.index, self.value]
+    }
+}
+
+impl RlpTable {
+    /// Construct a new RlpTable
+    pub fn construct<F: Field>(meta: &mut ConstraintSystem<F>) -> Self {
+        Self {
+            tx_id: meta.advice_column(),
+            tag: meta.advice_column(),
+            index: meta.advice_column(),
+            value: meta.advice_column_in(SecondPhase),
+        }
+    }
+
+    pub(crate) fn assign<F: Field>(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        row: &[Value<F>; 4],
+    ) -> Result<(), Error> {
+        for (column, value) in self.columns().iter().zip_eq(row) {
+            region.assign_advice(|| "assign rlp table row value", *column, offset, || *value)?;
+        }
+        Ok(())
+    }
+
+    /// Assign the fields of the signed messages and of the signed encoding of
+    /// `txs` to the `RlpTable`, without the rows that the RLP Circuit uses to
+    /// decode them.
+    pub fn load<F: Field>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        txs: &[geth_types::Transaction],
+        chain_id: u64,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rlp table",
+            |mut region| {
+                for (offset, row) in rlp_table_assignments(txs, chain_id, challenges)
+                    .iter()
+                    .enumerate()
+                {
+                    self.assign(&mut region, offset, row)?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
 /// Tag to identify the operation type in a RwTable row
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
 pub enum RwTableTag {
     /// Start (used for padding)
     Start = 1,
@@ -220,6 +311,8 @@ pub enum RwTableTag {
     TxLog,
     /// Tx Receipt operation
     TxReceipt,
+    /// Transient Storage operation (EIP-1153)
+    TransientStorage,
 }
 impl_expr!(RwTableTag);
 
@@ -234,6 +327,7 @@ impl RwTableTag {
                 | RwTableTag::Account
                 | RwTableTag::AccountStorage
                 | RwTableTag::AccountDestructed
+                | RwTableTag::TransientStorage
         )
     }
 }
@@ -245,7 +339,9 @@ impl From<RwTableTag> for usize {
 }
 
 /// Tag for an AccountField in RwTable
-#[derive(Clone, Copy, Debug, EnumIter, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    Clone, Copy, Debug, EnumIter, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
 pub enum AccountFieldTag {
     /// Nonce field
     Nonce = 1,
@@ -259,7 +355,7 @@ pub enum AccountFieldTag {
 impl_expr!(AccountFieldTag);
 
 /// Tag for a TxLogField in RwTable
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum TxLogFieldTag {
     /// Address field
     Address = 1,
@@ -267,11 +363,15 @@ pub enum TxLogFieldTag {
     Topic,
     /// Data field
     Data,
+    /// Number of topics
+    TopicLength,
+    /// Number of data bytes
+    DataLength,
 }
 impl_expr!(TxLogFieldTag);
 
 /// Tag for a TxReceiptField in RwTable
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumCount)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumCount, Serialize, Deserialize)]
 pub enum TxReceiptFieldTag {
     /// Tx result
     PostStateOrStatus = 1,
@@ -283,7 +383,7 @@ pub enum TxReceiptFieldTag {
 impl_expr!(TxReceiptFieldTag);
 
 /// Tag for a CallContextField in RwTable
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum CallContextFieldTag {
     /// RwCounterEndOfReversion
     RwCounterEndOfReversion = 1,
@@ -337,6 +437,8 @@ pub enum CallContextFieldTag {
     MemorySize,
     /// ReversibleWriteCounter
     ReversibleWriteCounter,
+    /// IsInvalidTx
+    IsInvalidTx,
 }
 impl_expr!(CallContextFieldTag);
 
@@ -459,6 +561,7 @@ impl RwTable {
 }
 
 /// The types of proofs in the MPT table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProofType {
     /// Nonce updated
     NonceChanged = AccountFieldTag::Nonce as isize,
@@ -663,6 +766,13 @@ pub enum BlockContextFieldTag {
     /// Chain ID field.  Although this is not a field in the block header, we
     /// add it here for convenience.
     ChainId,
+    /// Rw counter of the first step of the chunk of the block that is proved,
+    /// which is 1 when the block isn't split in chunks.
+    RwCounterStart,
+    /// Id of the first tx of the chunk of the block that is proved.
+    FirstTxId,
+    /// Whether the chunk of the block that is proved is the last one.
+    IsLastChunk,
 }
 impl_expr!(BlockContextFieldTag);
 
@@ -687,11 +797,11 @@ impl BlockTable {
         }
     }
 
-    /// Assign the `BlockTable` from a `BlockContext`.
+    /// Assign the `BlockTable` from the `BlockContexts` of a batch.
     pub fn load<F: Field>(
         &self,
         layouter: &mut impl Layouter<F>,
-        block: &BlockContext,
+        block: &BlockContexts,
         randomness: Value<F>,
     ) -> Result<(), Error> {
         layouter.assign_region(
@@ -733,6 +843,117 @@ impl DynamicTableColumns for BlockTable {
     }
 }
 
+/// Table with the withdrawals of the blocks of a batch
+#[derive(Clone, Debug)]
+pub struct WithdrawalTable {
+    /// Withdrawal id, which is the position of the withdrawal in the batch
+    /// starting at 1
+    pub id: Column<Advice>,
+    /// Number of the block of the withdrawal
+    pub block_number: Column<Advice>,
+    /// Index of the validator
+    pub validator_index: Column<Advice>,
+    /// Recipient of the withdrawal
+    pub address: Column<Advice>,
+    /// Amount of the withdrawal, in Gwei
+    pub amount: Column<Advice>,
+}
+
+impl WithdrawalTable {
+    /// Construct a new WithdrawalTable
+    pub fn construct<F: Field>(meta: &mut ConstraintSystem<F>) -> Self {
+        Self {
+            id: meta.advice_column(),
+            block_number: meta.advice_column(),
+            validator_index: meta.advice_column(),
+            address: meta.advice_column(),
+            amount: meta.advice_column(),
+        }
+    }
+
+    /// Assign the `WithdrawalTable` from a list of withdrawals and the numbers
+    /// of their blocks.
+    pub fn load<F: Field>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        withdrawals: &[geth_types::Withdrawal],
+        withdrawal_blocks: &[u64],
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "withdrawal table",
+            |mut region| {
+                let mut offset = 0;
+                for column in self.columns() {
+                    region.assign_advice(
+                        || "withdrawal table all-zero row",
+                        column,
+                        offset,
+                        || Value::known(F::zero()),
+                    )?;
+                }
+                offset += 1;
+
+                let withdrawal_table_columns = self.columns();
+                for row in Self::assignments(withdrawals, withdrawal_blocks) {
+                    for (column, value) in withdrawal_table_columns.iter().zip_eq(row) {
+                        region.assign_advice(
+                            || format!("withdrawal table row {}", offset),
+                            *column,
+                            offset,
+                            || value,
+                        )?;
+                    }
+                    offset += 1;
+                }
+
+                Ok(())
+            },
+        )
+    }
+
+    /// Generate the rows of the withdrawals after the all-zero row, followed
+    /// by a padding withdrawal with the next id and zero fields, which tells
+    /// the EVM circuit where the withdrawals end.
+    pub fn assignments<F: Field>(
+        withdrawals: &[geth_types::Withdrawal],
+        withdrawal_blocks: &[u64],
+    ) -> Vec<[Value<F>; 5]> {
+        withdrawals
+            .iter()
+            .zip_eq(withdrawal_blocks)
+            .enumerate()
+            .map(|(index, (withdrawal, block_number))| {
+                [
+                    Value::known(F::from(index as u64 + 1)),
+                    Value::known(F::from(*block_number)),
+                    Value::known(F::from(withdrawal.validator_index.as_u64())),
+                    Value::known(withdrawal.address.to_scalar().unwrap()),
+                    Value::known(F::from(withdrawal.amount.as_u64())),
+                ]
+            })
+            .chain(once([
+                Value::known(F::from(withdrawals.len() as u64 + 1)),
+                Value::known(F::zero()),
+                Value::known(F::zero()),
+                Value::known(F::zero()),
+                Value::known(F::zero()),
+            ]))
+            .collect()
+    }
+}
+
+impl DynamicTableColumns for WithdrawalTable {
+    fn columns(&self) -> Vec<Column<Advice>> {
+        vec![
+            self.id,
+            self.block_number,
+            self.validator_index,
+            self.address,
+            self.amount,
+        ]
+    }
+}
+
 /// Keccak Table, used to verify keccak hashing from RLC'ed input.
 #[derive(Clone, Debug)]
 pub struct KeccakTable {
@@ -6,17 +6,22 @@
 
 pub mod sign_verify;
 
-use crate::table::{KeccakTable, TxFieldTag, TxTable};
+use crate::table::{KeccakTable, LookupTable, RlpTable, TxFieldTag, TxTable};
 use crate::util::{random_linear_combine_word as rlc, Challenges, SubCircuit, SubCircuitConfig};
 use crate::witness;
 use bus_mapping::circuit_input_builder::keccak_inputs_tx_circuit;
 use eth_types::{
     sign_types::SignData,
-    {geth_types::Transaction, Address, Field, ToLittleEndian, ToScalar},
+    {geth_types::Transaction, Address, Field, ToLittleEndian, ToScalar, Word},
+};
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{and, not, Expr},
 };
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
     plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed},
+    poly::Rotation,
 };
 use itertools::Itertools;
 use log::error;
@@ -34,10 +39,117 @@ pub use halo2_proofs::halo2curves::{
 
 /// Number of static fields per tx: [nonce, gas, gas_price,
 /// caller_address, callee_address, is_create, value, call_data_length,
-/// call_data_gas_cost, tx_sign_hash].
+/// call_data_gas_cost, tx_type, chain_id, max_fee_per_gas,
+/// max_priority_fee_per_gas, access_list_addresses_len,
+/// access_list_storage_keys_len, block_number, tx_sign_hash].
 /// Note that call data bytes are layed out in the TxTable after all the static
 /// fields arranged by txs.
-pub(crate) const TX_LEN: usize = 10;
+pub(crate) const TX_LEN: usize = 17;
+
+/// Rows from the CallerAddress of a tx to its TxSignHash in the TxTable
+const CALLER_ADDRESS_TO_SIGN_HASH: i32 = 13;
+
+/// Returns the fields of the tx that are assigned to the TxTable, except for
+/// the call data and the sign hash.
+pub(crate) fn tx_fields<F: Field>(
+    tx: &Transaction,
+    chain_id: u64,
+    challenges: &Challenges<Value<F>>,
+) -> [(TxFieldTag, Value<F>); TX_LEN - 1] {
+    let access_list = tx.access_list.clone().unwrap_or_default();
+
+    [
+        (
+            TxFieldTag::Nonce,
+            challenges
+                .evm_word()
+                .map(|challenge| rlc(tx.nonce.to_le_bytes(), challenge)),
+        ),
+        (
+            TxFieldTag::Gas,
+            Value::known(F::from(tx.gas_limit.as_u64())),
+        ),
+        (
+            TxFieldTag::GasPrice,
+            challenges
+                .evm_word()
+                .map(|challenge| rlc(tx.gas_price.to_le_bytes(), challenge)),
+        ),
+        (
+            TxFieldTag::CallerAddress,
+            Value::known(tx.from.to_scalar().expect("tx.from too big")),
+        ),
+        (
+            TxFieldTag::CalleeAddress,
+            Value::known(
+                tx.to
+                    .unwrap_or_else(Address::zero)
+                    .to_scalar()
+                    .expect("tx.to too big"),
+            ),
+        ),
+        (
+            TxFieldTag::IsCreate,
+            Value::known(F::from(tx.to.is_none() as u64)),
+        ),
+        (
+            TxFieldTag::Value,
+            challenges
+                .evm_word()
+                .map(|challenge| rlc(tx.value.to_le_bytes(), challenge)),
+        ),
+        (
+            TxFieldTag::CallDataLength,
+            Value::known(F::from(tx.call_data.0.len() as u64)),
+        ),
+        (
+            TxFieldTag::CallDataGasCost,
+            Value::known(F::from(
+                tx.call_data
+                    .0
+                    .iter()
+                    .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 }),
+            )),
+        ),
+        (TxFieldTag::TxType, Value::known(F::from(tx.tx_type as u64))),
+        (
+            TxFieldTag::ChainId,
+            challenges
+                .evm_word()
+                .map(|challenge| rlc(Word::from(chain_id).to_le_bytes(), challenge)),
+        ),
+        (
+            TxFieldTag::MaxFeePerGas,
+            challenges
+                .evm_word()
+                .map(|challenge| rlc(tx.gas_fee_cap.to_le_bytes(), challenge)),
+        ),
+        (
+            TxFieldTag::MaxPriorityFeePerGas,
+            challenges
+                .evm_word()
+                .map(|challenge| rlc(tx.gas_tip_cap.to_le_bytes(), challenge)),
+        ),
+        (
+            TxFieldTag::AccessListAddressesLen,
+            Value::known(F::from(access_list.0.len() as u64)),
+        ),
+        (
+            TxFieldTag::AccessListStorageKeysLen,
+            Value::known(F::from(
+                access_list
+                    .0
+                    .iter()
+                    .map(|item| item.storage_keys.len() as u64)
+                    .sum::<u64>(),
+            )),
+        ),
+        (
+            TxFieldTag::BlockNumber,
+            Value::known(F::from(tx.block_number)),
+        ),
+    ]
+}
 
 /// Config for TxCircuit
 #[derive(Clone, Debug)]
@@ -46,10 +158,15 @@ pub struct TxCircuitConfig<F: Field> {
     tag: Column<Fixed>,
     index: Column<Advice>,
     value: Column<Advice>,
+    q_sign_hash: Column<Fixed>,
+    // Padding txs are the ones whose caller address is 0, like in the
+    // SignVerifyChip
+    is_padding: IsZeroConfig<F>,
     sign_verify: SignVerifyConfig,
     _marker: PhantomData<F>,
     // External tables
     keccak_table: KeccakTable,
+    rlp_table: RlpTable,
 }
 
 /// Circuit configuration arguments
@@ -58,6 +175,8 @@ pub struct TxCircuitConfigArgs<F: Field> {
     pub tx_table: TxTable,
     /// KeccakTable
     pub keccak_table: KeccakTable,
+    /// RlpTable
+    pub rlp_table: RlpTable,
     /// Challenges
     pub challenges: Challenges<Expression<F>>,
 }
@@ -71,6 +190,7 @@ impl<F: Field> SubCircuitConfig<F> for TxCircuitConfig<F> {
         Self::ConfigArgs {
             tx_table,
             keccak_table,
+            rlp_table,
             challenges,
         }: Self::ConfigArgs,
     ) -> Self {
@@ -80,6 +200,34 @@ impl<F: Field> SubCircuitConfig<F> for TxCircuitConfig<F> {
         let value = tx_table.value;
         meta.enable_equality(value);
 
+        let q_sign_hash = meta.fixed_column();
+        let caller_address_inv = meta.advice_column();
+        let is_padding = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_fixed(q_sign_hash, Rotation::cur()),
+            |meta| meta.query_advice(value, Rotation(-CALLER_ADDRESS_TO_SIGN_HASH)),
+            caller_address_inv,
+        );
+
+        // The signed message of the txs is the RLP encoding of their fields.
+        // Padding txs don't have a signature, and their sign hash is 0.
+        meta.lookup_any("tx sign hash in RlpTable", |meta| {
+            let enable = and::expr([
+                meta.query_fixed(q_sign_hash, Rotation::cur()),
+                not::expr(is_padding.expr()),
+            ]);
+            [
+                meta.query_advice(tx_id, Rotation::cur()),
+                TxFieldTag::TxSignHash.expr(),
+                0.expr(),
+                meta.query_advice(value, Rotation::cur()),
+            ]
+            .into_iter()
+            .zip(rlp_table.table_exprs(meta))
+            .map(|(input, table)| (enable.clone() * input, table))
+            .collect()
+        });
+
         let sign_verify = SignVerifyConfig::new(meta, keccak_table.clone(), challenges);
 
         Self {
@@ -87,8 +235,11 @@ impl<F: Field> SubCircuitConfig<F> for TxCircuitConfig<F> {
             tag,
             index,
             value,
+            q_sign_hash,
+            is_padding,
             sign_verify,
             keccak_table,
+            rlp_table,
             _marker: PhantomData,
         }
     }
@@ -123,6 +274,12 @@ impl<F: Field> TxCircuitConfig<F> {
             offset,
             || Value::known(F::from(tag as u64)),
         )?;
+        region.assign_fixed(
+            || "q_sign_hash",
+            self.q_sign_hash,
+            offset,
+            || Value::known(F::from((tag == TxFieldTag::TxSignHash) as u64)),
+        )?;
         region.assign_advice(
             || "index",
             self.index,
@@ -205,66 +362,23 @@ impl<F: Field> TxCircuit<F> {
                         &tx_default
                     };
 
-                    for (tag, value) in [
-                        (
-                            TxFieldTag::Nonce,
-                            challenges
-                                .evm_word()
-                                .map(|challenge| rlc(tx.nonce.to_le_bytes(), challenge)),
-                        ),
-                        (
-                            TxFieldTag::Gas,
-                            Value::known(F::from(tx.gas_limit.as_u64())),
-                        ),
-                        (
-                            TxFieldTag::GasPrice,
-                            challenges
-                                .evm_word()
-                                .map(|challenge| rlc(tx.gas_price.to_le_bytes(), challenge)),
-                        ),
-                        (
-                            TxFieldTag::CallerAddress,
-                            Value::known(tx.from.to_scalar().expect("tx.from too big")),
-                        ),
-                        (
-                            TxFieldTag::CalleeAddress,
-                            Value::known(
-                                tx.to
-                                    .unwrap_or_else(Address::zero)
-                                    .to_scalar()
-                                    .expect("tx.to too big"),
-                            ),
-                        ),
-                        (
-                            TxFieldTag::IsCreate,
-                            Value::known(F::from(tx.to.is_none() as u64)),
-                        ),
-                        (
-                            TxFieldTag::Value,
-                            challenges
-                                .evm_word()
-                                .map(|challenge| rlc(tx.value.to_le_bytes(), challenge)),
-                        ),
-                        (
-                            TxFieldTag::CallDataLength,
-                            Value::known(F::from(tx.call_data.0.len() as u64)),
-                        ),
-                        (
-                            TxFieldTag::CallDataGasCost,
-                            Value::known(F::from(
-                                tx.call_data
-                                    .0
-                                    .iter()
-                                    .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 }),
-                            )),
-                        ),
-                        (
-                            TxFieldTag::TxSignHash,
-                            assigned_sig_verif.msg_hash_rlc.value().copied(),
-                        ),
-                    ] {
+                    for (tag, value) in
+                        tx_fields(tx, self.chain_id, challenges)
+                            .into_iter()
+                            .chain([(
+                                TxFieldTag::TxSignHash,
+                                assigned_sig_verif.msg_hash_rlc.value().copied(),
+                            )])
+                    {
                         let assigned_cell =
                             config.assign_row(&mut region, offset, i + 1, tag, 0, value)?;
+                        if tag == TxFieldTag::TxSignHash {
+                            IsZeroChip::construct(config.is_padding.clone()).assign(
+                                &mut region,
+                                offset,
+                                assigned_sig_verif.address.value().copied(),
+                            )?;
+                        }
                         offset += 1;
 
                         // Ref. spec 0. Copy constraints using fixed offsets between the tx rows and
@@ -324,13 +438,8 @@ impl<F: Field> SubCircuit<F> for TxCircuit<F> {
         Self::new(
             block.circuits_params.max_txs,
             block.circuits_params.max_calldata,
-            block.context.chain_id.as_u64(),
-            block
-                .eth_block
-                .transactions
-                .iter()
-                .map(|tx| tx.into())
-                .collect(),
+            block.context.last().chain_id.as_u64(),
+            block.eth_txs().iter().map(|tx| tx.into()).collect(),
         )
     }
 
@@ -394,6 +503,7 @@ impl<F: Field> Circuit<F> for TxCircuit<F> {
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         let tx_table = TxTable::construct(meta);
         let keccak_table = KeccakTable::construct(meta);
+        let rlp_table = RlpTable::construct(meta);
         let challenges = Challenges::construct(meta);
 
         let config = {
@@ -403,6 +513,7 @@ impl<F: Field> Circuit<F> for TxCircuit<F> {
                 TxCircuitConfigArgs {
                     tx_table,
                     keccak_table,
+                    rlp_table,
                     challenges,
                 },
             )
@@ -426,6 +537,9 @@ impl<F: Field> Circuit<F> for TxCircuit<F> {
             })?,
             &challenges,
         )?;
+        config
+            .rlp_table
+            .load(&mut layouter, &self.txs, self.chain_id, &challenges)?;
         self.synthesize_sub(&config, &challenges, &mut layouter)
     }
 }
@@ -0,0 +1,403 @@
+//! Decoding of RLP encoded bytes, shared by the circuits that assign one byte
+//! per row: the class of the bytes, in a fixed table, and the decoding of the
+//! headers of the items.
+//!
+//! The circuit using the decoder sets `is_item_start` at the first byte of
+//! every item, including the first row it decodes, and the decoder verifies
+//! that:
+//!
+//! - The class flags of every byte are its class, and an item has a header iff
+//!   its first byte has a class.
+//! - The header of a long item continues for the bytes of its length, which is
+//!   accumulated in `length`, and the payload for `length` bytes, counted down
+//!   by `counter`.
+//! - An item ends at its last payload byte, or with its header when it's empty
+//!   or a container, whose payload is decoded as items.
+//!
+//! The rules of the canonical encoding, and which items are lists, are left to
+//! the circuit using the decoder.
+
+use crate::evm_circuit::util::constraint_builder::BaseConstraintBuilder;
+use eth_types::Field;
+use gadgets::{
+    is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction},
+    util::{not, select, sum, Expr},
+};
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
+    poly::Rotation,
+};
+use std::array;
+
+const MAX_DEGREE: usize = 9;
+
+/// Class of the first byte of an RLP item, which determines how the item is
+/// encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RlpByteClass {
+    /// `[0x00, 0x7f]`: the byte is the item
+    Single = 0,
+    /// `[0x80, 0xb7]`: string of up to 55 bytes
+    ShortString,
+    /// `[0xb8, 0xbf]`: string, followed by its length
+    LongString,
+    /// `[0xc0, 0xf7]`: list of up to 55 bytes
+    ShortList,
+    /// `[0xf8, 0xff]`: list, followed by its length
+    LongList,
+}
+
+impl RlpByteClass {
+    /// Classes of the items with a header, in the order of the class flags of
+    /// [`RlpDecoderConfig`].
+    pub(crate) const HEADER_CLASSES: [Self; 4] = [
+        Self::ShortString,
+        Self::LongString,
+        Self::ShortList,
+        Self::LongList,
+    ];
+}
+
+impl From<u8> for RlpByteClass {
+    fn from(byte: u8) -> Self {
+        match byte {
+            0x00..=0x7f => Self::Single,
+            0x80..=0xb7 => Self::ShortString,
+            0xb8..=0xbf => Self::LongString,
+            0xc0..=0xf7 => Self::ShortList,
+            0xf8..=0xff => Self::LongList,
+        }
+    }
+}
+
+/// Fixed table of the bytes and their class: (byte, class).  Its `byte`
+/// column is also used to range check values in 0..256.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RlpByteTable {
+    /// The values 0..256
+    pub(crate) byte: Column<Fixed>,
+    /// [`RlpByteClass`] of `byte`
+    pub(crate) class: Column<Fixed>,
+}
+
+impl RlpByteTable {
+    /// Configure the table
+    pub(crate) fn configure<F: Field>(meta: &mut ConstraintSystem<F>) -> Self {
+        Self {
+            byte: meta.fixed_column(),
+            class: meta.fixed_column(),
+        }
+    }
+
+    /// Assign the table
+    pub(crate) fn load<F: Field>(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rlp byte table",
+            |mut region| {
+                for byte in 0..=u8::MAX {
+                    for (name, column, value) in [
+                        ("byte", self.byte, byte as u64),
+                        ("class", self.class, RlpByteClass::from(byte) as u64),
+                    ] {
+                        region.assign_fixed(
+                            || name,
+                            column,
+                            byte as usize,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Values of the decoder at a row
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RlpDecoderRow {
+    pub(crate) byte: u8,
+    pub(crate) is_item_start: bool,
+    pub(crate) is_item_end: bool,
+    pub(crate) is_header: bool,
+    pub(crate) counter: u64,
+    pub(crate) length: u64,
+}
+
+impl RlpDecoderRow {
+    /// Row of `byte`, which is the first byte of an item if `is_item_start`,
+    /// or follows the byte of `prev`.  The item ends with its header if
+    /// `is_container`.
+    pub(crate) fn new(byte: u8, is_item_start: bool, is_container: bool, prev: &Self) -> Self {
+        let class = RlpByteClass::from(byte);
+        let is_header = if is_item_start {
+            class != RlpByteClass::Single
+        } else {
+            prev.is_header && prev.counter != 0
+        };
+        let (counter, length) = if is_item_start {
+            match class {
+                RlpByteClass::Single => (0, 1),
+                RlpByteClass::ShortString => (0, byte as u64 - 0x80),
+                RlpByteClass::LongString => (byte as u64 - 0xb7, 0),
+                RlpByteClass::ShortList => (0, byte as u64 - 0xc0),
+                RlpByteClass::LongList => (byte as u64 - 0xf7, 0),
+            }
+        } else if is_header {
+            (prev.counter - 1, prev.length * 256 + byte as u64)
+        } else {
+            let remaining = if prev.is_header {
+                prev.length
+            } else {
+                prev.counter
+            };
+            (remaining - 1, prev.length)
+        };
+        Self {
+            byte,
+            is_item_start,
+            is_item_end: counter == 0 && (!is_header || is_container || length == 0),
+            is_header,
+            counter,
+            length,
+        }
+    }
+}
+
+/// Config of the decoder of the headers of RLP items, see the module docs.
+#[derive(Clone, Debug)]
+pub(crate) struct RlpDecoderConfig<F> {
+    pub(crate) is_item_start: Column<Advice>,
+    pub(crate) is_item_end: Column<Advice>,
+    pub(crate) is_header: Column<Advice>,
+    /// Class flags of the byte, see [`RlpByteClass::HEADER_CLASSES`].  A byte
+    /// without flags is a single byte.
+    pub(crate) classes: [Column<Advice>; 4],
+    /// Number of length bytes left in the header, then of payload bytes left
+    pub(crate) counter: Column<Advice>,
+    /// Length of the payload of the item
+    pub(crate) length: Column<Advice>,
+    pub(crate) counter_is_zero: IsZeroConfig<F>,
+    pub(crate) length_is_zero: IsZeroConfig<F>,
+}
+
+impl<F: Field> RlpDecoderConfig<F> {
+    /// Configure the decoder of the bytes of `byte` in the rows enabled by
+    /// `q_enable`, whose classes are looked up in `byte_table`.  The items for
+    /// which `is_container` is set end with their header.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: impl Fn(&mut VirtualCells<'_, F>) -> Expression<F>,
+        byte: Column<Advice>,
+        byte_table: &RlpByteTable,
+        is_container: impl Fn(&mut VirtualCells<'_, F>) -> Expression<F>,
+    ) -> Self {
+        let counter = meta.advice_column();
+        let length = meta.advice_column();
+        let counter_inv = meta.advice_column();
+        let counter_is_zero = IsZeroChip::configure(
+            meta,
+            &q_enable,
+            |meta| meta.query_advice(counter, Rotation::cur()),
+            counter_inv,
+        );
+        let length_inv = meta.advice_column();
+        let length_is_zero = IsZeroChip::configure(
+            meta,
+            &q_enable,
+            |meta| meta.query_advice(length, Rotation::cur()),
+            length_inv,
+        );
+        let config = Self {
+            is_item_start: meta.advice_column(),
+            is_item_end: meta.advice_column(),
+            is_header: meta.advice_column(),
+            classes: array::from_fn(|_| meta.advice_column()),
+            counter,
+            length,
+            counter_is_zero,
+            length_is_zero,
+        };
+        let d = &config;
+
+        meta.create_gate("rlp items", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let is_item_start = meta.query_advice(d.is_item_start, Rotation::cur());
+            let is_header = meta.query_advice(d.is_header, Rotation::cur());
+            let is_header_prev = meta.query_advice(d.is_header, Rotation::prev());
+            let classes = d
+                .classes
+                .map(|column| meta.query_advice(column, Rotation::cur()));
+            let [is_short_string, is_long_string, is_short_list, is_long_list] = classes.clone();
+            let counter = meta.query_advice(d.counter, Rotation::cur());
+            let counter_prev = meta.query_advice(d.counter, Rotation::prev());
+            let length = meta.query_advice(d.length, Rotation::cur());
+            let length_prev = meta.query_advice(d.length, Rotation::prev());
+            let counter_is_zero = d.counter_is_zero.expr();
+            let is_container = is_container(meta);
+
+            for (name, column) in [
+                ("is_item_start is boolean", d.is_item_start),
+                ("is_item_end is boolean", d.is_item_end),
+                ("is_header is boolean", d.is_header),
+            ]
+            .into_iter()
+            .chain(d.classes.map(|column| ("class flag is boolean", column)))
+            {
+                cb.require_boolean(name, meta.query_advice(column, Rotation::cur()));
+            }
+            cb.require_boolean("a byte has at most one class", sum::expr(classes.clone()));
+
+            // The first byte determines how the item is encoded
+            cb.condition(is_item_start.clone(), |cb| {
+                cb.require_equal(
+                    "an item has a header unless it's a single byte",
+                    is_header.clone(),
+                    sum::expr(classes),
+                );
+                cb.require_equal(
+                    "counter is the number of bytes of the length of a long item",
+                    counter.clone(),
+                    is_long_string * (byte.clone() - 0xb7.expr())
+                        + is_long_list * (byte.clone() - 0xf7.expr()),
+                );
+                cb.require_equal(
+                    "the length of a short item is in its first byte",
+                    length.clone(),
+                    is_short_string * (byte.clone() - 0x80.expr())
+                        + is_short_list * (byte.clone() - 0xc0.expr())
+                        + not::expr(is_header.clone()),
+                );
+            });
+
+            cb.condition(not::expr(is_item_start.clone()), |cb| {
+                cb.require_equal(
+                    "the header continues until its counter is 0",
+                    is_header.clone(),
+                    is_header_prev.clone() * not::expr(d.counter_is_zero_prev(meta)),
+                );
+            });
+            cb.condition(not::expr(is_item_start.clone()) * is_header.clone(), |cb| {
+                cb.require_equal(
+                    "counter decreases by 1 in the length bytes",
+                    counter.clone(),
+                    counter_prev.clone() - 1.expr(),
+                );
+                cb.require_equal(
+                    "length accumulates the length bytes",
+                    length.clone(),
+                    length_prev.clone() * 256.expr() + byte,
+                );
+            });
+            cb.condition(
+                not::expr(is_item_start) * not::expr(is_header.clone()),
+                |cb| {
+                    cb.require_equal(
+                        "counter counts down the payload bytes from the length",
+                        counter,
+                        select::expr(is_header_prev, length_prev.clone(), counter_prev) - 1.expr(),
+                    );
+                    cb.require_equal(
+                        "length is the same for all the payload bytes",
+                        length,
+                        length_prev,
+                    );
+                },
+            );
+
+            cb.require_equal(
+                "an item ends with its header if it's a container or empty, or with its last \
+                 payload byte",
+                meta.query_advice(d.is_item_end, Rotation::cur()),
+                is_header.clone()
+                    * counter_is_zero.clone()
+                    * (is_container.clone() + not::expr(is_container) * d.length_is_zero.expr())
+                    + not::expr(is_header) * counter_is_zero,
+            );
+
+            cb.gate(q_enable(meta))
+        });
+
+        meta.lookup_any("rlp byte class", |meta| {
+            let q_enable = q_enable(meta);
+            vec![
+                (
+                    q_enable.clone() * meta.query_advice(byte, Rotation::cur()),
+                    meta.query_fixed(byte_table.byte, Rotation::cur()),
+                ),
+                (
+                    q_enable * d.class(meta),
+                    meta.query_fixed(byte_table.class, Rotation::cur()),
+                ),
+            ]
+        });
+
+        config
+    }
+
+    /// Class of the byte, as the value of its [`RlpByteClass`]
+    pub(crate) fn class(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        sum::expr(
+            RlpByteClass::HEADER_CLASSES
+                .iter()
+                .zip(self.classes)
+                .map(|(class, column)| {
+                    (*class as u64).expr() * meta.query_advice(column, Rotation::cur())
+                }),
+        )
+    }
+
+    /// Whether the byte is the first byte of a list
+    pub(crate) fn is_list(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        meta.query_advice(self.classes[2], Rotation::cur())
+            + meta.query_advice(self.classes[3], Rotation::cur())
+    }
+
+    /// Whether the counter is 0 at the previous row
+    pub(crate) fn counter_is_zero_prev(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        self.counter_is_zero
+            .expr_at(meta, self.counter, Rotation::prev())
+    }
+
+    /// Assign the decoder columns at `offset`
+    pub(crate) fn assign(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        row: &RlpDecoderRow,
+    ) -> Result<(), Error> {
+        let class = RlpByteClass::from(row.byte);
+        for (name, column, value) in
+            [
+                (
+                    "is_item_start",
+                    self.is_item_start,
+                    row.is_item_start as u64,
+                ),
+                ("is_item_end", self.is_item_end, row.is_item_end as u64),
+                ("is_header", self.is_header, row.is_header as u64),
+                ("counter", self.counter, row.counter),
+                ("length", self.length, row.length),
+            ]
+            .into_iter()
+            .chain(RlpByteClass::HEADER_CLASSES.iter().zip(self.classes).map(
+                |(header_class, column)| ("class flag", column, (class == *header_class) as u64),
+            ))
+        {
+            region.assign_advice(|| name, column, offset, || Value::known(F::from(value)))?;
+        }
+        IsZeroChip::construct(self.counter_is_zero.clone()).assign(
+            region,
+            offset,
+            Value::known(F::from(row.counter)),
+        )?;
+        IsZeroChip::construct(self.length_is_zero.clone()).assign(
+            region,
+            offset,
+            Value::known(F::from(row.length)),
+        )
+    }
+}
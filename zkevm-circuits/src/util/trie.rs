@@ -0,0 +1,1216 @@
+//! Proof of the root of the trie of an ordered list of values, keyed by the
+//! RLP encoding of their index, like the transactions, receipts and
+//! withdrawals tries of a block.
+//!
+//! The gadget assigns one byte per row: 32 root rows with the root of the
+//! trie, followed by the RLP encoded nodes of the path of every value, in the
+//! order of the list.  Like in the MPT circuit, the hash of every node is
+//! looked up in the keccak table and referenced by its parent, the first node
+//! of every path is the root, and the value of the last node of each path is
+//! accumulated in `value_rlc`, so that the circuit using the gadget can look it
+//! up at the end of the path.  The root of a trie without values is the empty
+//! root.
+//!
+//! The gadget verifies that:
+//!
+//! - Every node is decoded as an RLP list of strings with the decoder of
+//!   [`crate::util::rlp`], so that the items and the list end where their
+//!   headers say.  A branch has 17 items, which are empty or 32 bytes hashes,
+//!   and an extension or a leaf has 2 items, the first of which is its hex
+//!   prefix encoded path.
+//! - Every path follows the key of its value: the nibbles of the branch
+//!   children and of the extension and leaf paths are accumulated, and looked
+//!   up at the end of the path, which is a leaf, in a fixed table of the key of
+//!   every id.  A node is referenced by the child of its parent in the position
+//!   of the next key nibble for a branch, or by the child of an extension.
+//! - No value is left out: every hash in the branches of the paths is the hash
+//!   of a node of a path, so that every leaf of the trie is the end of a path.
+//!
+//! Values must be at least 29 bytes, like signed txs and receipts, so that
+//! every node is at least 32 bytes and is referenced by its hash: embedded
+//! nodes are not supported.
+
+use crate::{
+    evm_circuit::util::{constraint_builder::BaseConstraintBuilder, rlc},
+    table::KeccakTable,
+    util::{
+        keccak,
+        rlp::{RlpByteClass, RlpByteTable, RlpDecoderConfig, RlpDecoderRow},
+        Challenges,
+    },
+};
+use bus_mapping::mpt::{Trie, EMPTY_ROOT};
+use eth_types::{Field, ToLittleEndian, ToWord, Word};
+use ethers_core::utils::rlp;
+use gadgets::util::{and, not, select, sum, Expr};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase, VirtualCells,
+    },
+    poly::Rotation,
+};
+use std::{array, marker::PhantomData};
+
+/// Number of rows used for the root at the beginning of the trie rows.
+pub const ROOT_ROWS: usize = 32;
+
+const MAX_DEGREE: usize = 9;
+
+/// Number of items of a branch node
+const BRANCH_ITEMS: usize = 17;
+
+/// Cells of the root of the trie
+#[derive(Clone, Debug)]
+pub(crate) struct ListTrieCells<F: Field> {
+    /// First 16 bytes of the root, as a number
+    pub(crate) root_hi: AssignedCell<F, F>,
+    /// Last 16 bytes of the root, as a number
+    pub(crate) root_lo: AssignedCell<F, F>,
+    /// Root, as the RLC of its big endian bytes with the EVM word challenge
+    pub(crate) root_rlc: AssignedCell<F, F>,
+    /// Offset of the ids of the paths
+    pub(crate) id_offset: AssignedCell<F, F>,
+}
+
+/// Config of the trie of a list of values
+#[derive(Clone, Debug)]
+pub(crate) struct ListTrieConfig<F: Field> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_last: Column<Fixed>,
+    q_root: Column<Fixed>,
+    q_root_hi: Column<Fixed>,
+    /// Table of the keys of the ids: (id, key, number of nibbles of the key),
+    /// with the key of the value at index `id - 1` as a big endian number in
+    /// the row `id`.
+    key_table: [Column<Fixed>; 3],
+
+    byte: Column<Advice>,
+    /// Position of the value of the path in the list, starting at 1
+    pub(crate) id: Column<Advice>,
+    /// Offset of the ids in the ids of the circuit using the gadget, or any
+    /// other key of the list in that circuit, the same in all the rows
+    pub(crate) id_offset: Column<Advice>,
+    is_padding: Column<Advice>,
+    is_node_start: Column<Advice>,
+    is_node_end: Column<Advice>,
+    is_last_node: Column<Advice>,
+    is_branch: Column<Advice>,
+    node_len: Column<Advice>,
+    root_hi: Column<Advice>,
+    root_lo: Column<Advice>,
+
+    // RLP decoding of the nodes, whose items start at the start of the node
+    rlp: RlpDecoderConfig<F>,
+    is_list: Column<Advice>,
+    payload_remaining: Column<Advice>,
+    item_count: Column<Advice>,
+
+    // Paths
+    is_child: Column<Advice>,
+    child_count: Column<Advice>,
+    is_flag: Column<Advice>,
+    is_odd: Column<Advice>,
+    nibble: Column<Advice>,
+    is_nibble: Column<Advice>,
+    is_key_byte: Column<Advice>,
+    key_acc: Column<Advice>,
+    key_len: Column<Advice>,
+    is_hash_end: Column<Advice>,
+    is_value: Column<Advice>,
+    /// Length of the value of the path
+    pub(crate) value_len: Column<Advice>,
+
+    root_rlc: Column<Advice>,
+    node_rlc: Column<Advice>,
+    node_hash: Column<Advice>,
+    item_rlc: Column<Advice>,
+    ref_rlc: Column<Advice>,
+    /// RLC of the value of the path with the keccak input challenge
+    pub(crate) value_rlc: Column<Advice>,
+
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> ListTrieConfig<F> {
+    /// Configure the trie rows.  The bytes are decoded and range checked with
+    /// `byte_table`, which must be loaded by the caller.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        byte_table: &RlpByteTable,
+        keccak_table: &KeccakTable,
+        challenges: &Challenges<Expression<F>>,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_root = meta.fixed_column();
+        let is_padding = meta.advice_column();
+        let byte = meta.advice_column();
+        let is_list = meta.advice_column();
+
+        let node_row = |meta: &mut VirtualCells<'_, F>| {
+            and::expr([
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_fixed(q_root, Rotation::cur())),
+                not::expr(meta.query_advice(is_padding, Rotation::cur())),
+            ])
+        };
+        let rlp = RlpDecoderConfig::configure(meta, node_row, byte, byte_table, |meta| {
+            meta.query_advice(is_list, Rotation::cur())
+        });
+
+        let config = Self {
+            q_enable,
+            q_first: meta.fixed_column(),
+            q_last: meta.fixed_column(),
+            q_root,
+            q_root_hi: meta.fixed_column(),
+            key_table: array::from_fn(|_| meta.fixed_column()),
+            byte,
+            id: meta.advice_column(),
+            id_offset: meta.advice_column(),
+            is_padding,
+            is_node_start: meta.advice_column(),
+            is_node_end: meta.advice_column(),
+            is_last_node: meta.advice_column(),
+            is_branch: meta.advice_column(),
+            node_len: meta.advice_column(),
+            root_hi: meta.advice_column(),
+            root_lo: meta.advice_column(),
+            rlp,
+            is_list,
+            payload_remaining: meta.advice_column(),
+            item_count: meta.advice_column(),
+            is_child: meta.advice_column(),
+            child_count: meta.advice_column(),
+            is_flag: meta.advice_column(),
+            is_odd: meta.advice_column(),
+            nibble: meta.advice_column(),
+            is_nibble: meta.advice_column(),
+            is_key_byte: meta.advice_column(),
+            key_acc: meta.advice_column(),
+            key_len: meta.advice_column(),
+            is_hash_end: meta.advice_column(),
+            is_value: meta.advice_column(),
+            value_len: meta.advice_column(),
+            root_rlc: meta.advice_column_in(SecondPhase),
+            node_rlc: meta.advice_column_in(SecondPhase),
+            node_hash: meta.advice_column_in(SecondPhase),
+            item_rlc: meta.advice_column_in(SecondPhase),
+            ref_rlc: meta.advice_column_in(SecondPhase),
+            value_rlc: meta.advice_column_in(SecondPhase),
+            _marker: PhantomData,
+        };
+        for column in [
+            config.id_offset,
+            config.root_hi,
+            config.root_lo,
+            config.root_rlc,
+        ] {
+            meta.enable_equality(column);
+        }
+        config.configure_gates(meta, challenges);
+
+        let t = &config;
+        meta.lookup_any("trie byte is in u8 range", |meta| {
+            vec![(
+                meta.query_fixed(t.q_enable, Rotation::cur())
+                    * meta.query_advice(t.byte, Rotation::cur()),
+                meta.query_fixed(byte_table.byte, Rotation::cur()),
+            )]
+        });
+        for (name, is_upper) in [
+            ("nibble is not negative", false),
+            ("nibble is not above 15", true),
+        ] {
+            meta.lookup_any(name, |meta| {
+                let enable = meta.query_fixed(t.q_enable, Rotation::cur())
+                    * meta.query_advice(t.is_nibble, Rotation::cur());
+                let nibble = meta.query_advice(t.nibble, Rotation::cur());
+                let value = if is_upper { 15.expr() - nibble } else { nibble };
+                vec![(
+                    enable * value,
+                    meta.query_fixed(byte_table.byte, Rotation::cur()),
+                )]
+            });
+        }
+        meta.lookup_any("the key of the path is the key of its id", |meta| {
+            let enable = t.path_end(meta);
+            [t.id, t.key_acc, t.key_len]
+                .into_iter()
+                .zip(t.key_table)
+                .map(|(column, table_column)| {
+                    (
+                        enable.clone() * meta.query_advice(column, Rotation::cur()),
+                        meta.query_fixed(table_column, Rotation::cur()),
+                    )
+                })
+                .collect()
+        });
+        meta.lookup_any("the hashes in the branches are hashes of nodes", |meta| {
+            let q_enable = meta.query_fixed(t.q_enable, Rotation::cur());
+            vec![(
+                q_enable.clone()
+                    * meta.query_advice(t.is_hash_end, Rotation::cur())
+                    * meta.query_advice(t.item_rlc, Rotation::cur()),
+                q_enable
+                    * meta.query_advice(t.is_node_start, Rotation::cur())
+                    * meta.query_advice(t.node_hash, Rotation::cur()),
+            )]
+        });
+        meta.lookup_any(
+            "keccak256_table_lookup(node_rlc, node_len, node_hash)",
+            |meta| {
+                let enable = and::expr([
+                    meta.query_fixed(t.q_enable, Rotation::cur()),
+                    meta.query_advice(t.is_node_end, Rotation::cur()),
+                ]);
+
+                let mut constraints = vec![(
+                    enable.clone(),
+                    meta.query_advice(keccak_table.is_enabled, Rotation::cur()),
+                )];
+                for (circuit_column, table_column) in
+                    keccak_table.match_columns(t.node_rlc, t.node_len, t.node_hash)
+                {
+                    constraints.push((
+                        enable.clone() * meta.query_advice(circuit_column, Rotation::cur()),
+                        meta.query_advice(table_column, Rotation::cur()),
+                    ))
+                }
+                constraints
+            },
+        );
+
+        config
+    }
+
+    /// Enabled in the last byte of the path of a value
+    pub(crate) fn path_end(&self, meta: &mut VirtualCells<'_, F>) -> Expression<F> {
+        and::expr([
+            meta.query_fixed(self.q_enable, Rotation::cur()),
+            meta.query_advice(self.is_node_end, Rotation::cur()),
+            meta.query_advice(self.is_last_node, Rotation::cur()),
+        ])
+    }
+
+    /// Flags that are disabled in the root and padding rows
+    fn node_flags(&self) -> [Column<Advice>; 9] {
+        [
+            self.is_node_start,
+            self.is_node_end,
+            self.is_branch,
+            self.rlp.is_item_start,
+            self.rlp.is_item_end,
+            self.is_child,
+            self.is_nibble,
+            self.is_hash_end,
+            self.is_value,
+        ]
+    }
+
+    fn configure_gates(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        challenges: &Challenges<Expression<F>>,
+    ) {
+        let t = self;
+        let empty_root = split_root::<F>(EMPTY_ROOT.to_word());
+        let node_row = |meta: &mut VirtualCells<'_, F>| {
+            and::expr([
+                meta.query_fixed(t.q_enable, Rotation::cur()),
+                not::expr(meta.query_fixed(t.q_root, Rotation::cur())),
+                not::expr(meta.query_advice(t.is_padding, Rotation::cur())),
+            ])
+        };
+        let cur_prev = |meta: &mut VirtualCells<'_, F>, column| {
+            (
+                meta.query_advice(column, Rotation::cur()),
+                meta.query_advice(column, Rotation::prev()),
+            )
+        };
+        // First byte of the payload of an item
+        let is_first_data = |meta: &mut VirtualCells<'_, F>| {
+            let is_item_start = meta.query_advice(t.rlp.is_item_start, Rotation::cur());
+            not::expr(meta.query_advice(t.rlp.is_header, Rotation::cur()))
+                * (is_item_start.clone()
+                    + not::expr(is_item_start)
+                        * meta.query_advice(t.rlp.is_header, Rotation::prev()))
+        };
+
+        meta.create_gate("trie flags are boolean", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+            for column in [
+                t.is_padding,
+                t.is_node_start,
+                t.is_node_end,
+                t.is_last_node,
+                t.is_branch,
+                t.is_list,
+                t.is_child,
+                t.is_odd,
+            ] {
+                cb.require_boolean(
+                    "trie flag is boolean",
+                    meta.query_advice(column, Rotation::cur()),
+                );
+            }
+            cb.gate(meta.query_fixed(t.q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("id_offset is the same in all the rows", |meta| {
+            let (id_offset, id_offset_prev) = cur_prev(meta, t.id_offset);
+            vec![
+                meta.query_fixed(t.q_enable, Rotation::cur())
+                    * not::expr(meta.query_fixed(t.q_first, Rotation::cur()))
+                    * (id_offset - id_offset_prev),
+            ]
+        });
+
+        meta.create_gate("root rows", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(t.byte, Rotation::cur());
+            let q_first = meta.query_fixed(t.q_first, Rotation::cur());
+            let q_root_hi = meta.query_fixed(t.q_root_hi, Rotation::cur());
+            let root_hi = meta.query_advice(t.root_hi, Rotation::cur());
+            let root_lo = meta.query_advice(t.root_lo, Rotation::cur());
+            let root_rlc = meta.query_advice(t.root_rlc, Rotation::cur());
+
+            for column in [t.id, t.is_padding].into_iter().chain(t.node_flags()) {
+                cb.require_zero(
+                    "root rows are not node rows",
+                    meta.query_advice(column, Rotation::cur()),
+                );
+            }
+            cb.condition(q_first.clone(), |cb| {
+                cb.require_equal("root_hi = byte", root_hi.clone(), byte.clone());
+                cb.require_zero("root_lo = 0", root_lo.clone());
+                cb.require_equal("root_rlc = byte", root_rlc.clone(), byte.clone());
+            });
+            cb.condition(not::expr(q_first), |cb| {
+                let root_hi_prev = meta.query_advice(t.root_hi, Rotation::prev());
+                let root_lo_prev = meta.query_advice(t.root_lo, Rotation::prev());
+                cb.require_equal(
+                    "root_hi accumulates the first 16 root bytes",
+                    root_hi,
+                    select::expr(
+                        q_root_hi.clone(),
+                        root_hi_prev.clone() * 256.expr() + byte.clone(),
+                        root_hi_prev,
+                    ),
+                );
+                cb.require_equal(
+                    "root_lo accumulates the last 16 root bytes",
+                    root_lo,
+                    select::expr(
+                        q_root_hi,
+                        root_lo_prev.clone(),
+                        root_lo_prev * 256.expr() + byte.clone(),
+                    ),
+                );
+                cb.require_equal(
+                    "root_rlc accumulates the root bytes",
+                    root_rlc,
+                    meta.query_advice(t.root_rlc, Rotation::prev()) * challenges.evm_word() + byte,
+                );
+            });
+
+            cb.gate(meta.query_fixed(t.q_root, Rotation::cur()))
+        });
+
+        meta.create_gate("trie padding", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_root_prev = meta.query_fixed(t.q_root, Rotation::prev());
+            let is_padding_prev = meta.query_advice(t.is_padding, Rotation::prev());
+            let is_padding = meta.query_advice(t.is_padding, Rotation::cur());
+
+            for column in [t.root_hi, t.root_lo, t.root_rlc] {
+                cb.require_equal(
+                    "the root is kept after the root rows",
+                    meta.query_advice(column, Rotation::cur()),
+                    meta.query_advice(column, Rotation::prev()),
+                );
+            }
+            cb.require_zero(
+                "padding is not followed by a node",
+                is_padding_prev.clone() * not::expr(is_padding.clone()),
+            );
+            cb.condition(is_padding.clone() - is_padding_prev, |cb| {
+                cb.require_equal(
+                    "padding follows the root rows or the end of the last path",
+                    q_root_prev.clone()
+                        + meta.query_advice(t.is_node_end, Rotation::prev())
+                            * meta.query_advice(t.is_last_node, Rotation::prev()),
+                    1.expr(),
+                );
+            });
+            cb.condition(q_root_prev * is_padding.clone(), |cb| {
+                for (i, column) in [t.root_hi, t.root_lo].into_iter().enumerate() {
+                    cb.require_equal(
+                        "the root of a trie without values is the empty root",
+                        meta.query_advice(column, Rotation::cur()),
+                        Expression::Constant(empty_root[i]),
+                    );
+                }
+            });
+            cb.condition(is_padding, |cb| {
+                for column in t.node_flags() {
+                    cb.require_zero(
+                        "flags are disabled in padding rows",
+                        meta.query_advice(column, Rotation::cur()),
+                    );
+                }
+            });
+
+            cb.gate(and::expr([
+                meta.query_fixed(t.q_enable, Rotation::cur()),
+                not::expr(meta.query_fixed(t.q_root, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("trie last row", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+            cb.require_equal(
+                "the last row is padding",
+                meta.query_advice(t.is_padding, Rotation::cur()),
+                1.expr(),
+            );
+            cb.gate(meta.query_fixed(t.q_last, Rotation::cur()))
+        });
+
+        meta.create_gate("node rows", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(t.byte, Rotation::cur());
+            let q_root_prev = meta.query_fixed(t.q_root, Rotation::prev());
+            let is_node_end_prev = meta.query_advice(t.is_node_end, Rotation::prev());
+            let is_node_start = meta.query_advice(t.is_node_start, Rotation::cur());
+            let is_value = meta.query_advice(t.is_value, Rotation::cur());
+            let is_path_start = q_root_prev.clone()
+                + is_node_end_prev.clone() * meta.query_advice(t.is_last_node, Rotation::prev());
+            let (id, id_prev) = cur_prev(meta, t.id);
+            let (node_len, node_len_prev) = cur_prev(meta, t.node_len);
+            let (node_rlc, node_rlc_prev) = cur_prev(meta, t.node_rlc);
+            let (node_hash, node_hash_prev) = cur_prev(meta, t.node_hash);
+            let (value_len, value_len_prev) = cur_prev(meta, t.value_len);
+            let (value_rlc, value_rlc_prev) = cur_prev(meta, t.value_rlc);
+            let (key_acc, key_acc_prev) = cur_prev(meta, t.key_acc);
+            let (key_len, key_len_prev) = cur_prev(meta, t.key_len);
+
+            cb.require_equal(
+                "a node starts after the root rows or after the end of a node",
+                is_node_start.clone(),
+                q_root_prev + is_node_end_prev,
+            );
+
+            cb.condition(is_node_start.clone(), |cb| {
+                cb.require_equal("node_len = 1", node_len.clone(), 1.expr());
+                cb.require_equal("node_rlc = byte", node_rlc.clone(), byte.clone());
+                cb.require_equal("value_len = is_value", value_len.clone(), is_value.clone());
+                cb.require_equal(
+                    "value_rlc = is_value * byte",
+                    value_rlc.clone(),
+                    is_value.clone() * byte.clone(),
+                );
+                cb.require_equal(
+                    "key_acc is reset at the start of a path",
+                    key_acc.clone(),
+                    not::expr(is_path_start.clone()) * key_acc_prev.clone(),
+                );
+                cb.require_equal(
+                    "key_len is reset at the start of a path",
+                    key_len.clone(),
+                    not::expr(is_path_start.clone()) * key_len_prev.clone(),
+                );
+            });
+            cb.condition(is_node_start.clone() * is_path_start.clone(), |cb| {
+                cb.require_equal(
+                    "paths are in the order of the list",
+                    id.clone(),
+                    id_prev.clone() + 1.expr(),
+                );
+                cb.require_equal(
+                    "the first node hash is the root",
+                    node_hash.clone(),
+                    meta.query_advice(t.root_rlc, Rotation::cur()),
+                );
+            });
+            cb.condition(is_node_start.clone() * not::expr(is_path_start), |cb| {
+                cb.require_equal("id is the same for a path", id.clone(), id_prev.clone());
+                cb.require_equal(
+                    "the node hash is referenced by the previous node",
+                    node_hash.clone(),
+                    meta.query_advice(t.ref_rlc, Rotation::prev()),
+                );
+            });
+
+            cb.condition(not::expr(is_node_start), |cb| {
+                cb.require_equal("id is the same for a path", id, id_prev);
+                cb.require_equal(
+                    "node_len increases by 1",
+                    node_len,
+                    node_len_prev + 1.expr(),
+                );
+                cb.require_equal(
+                    "node_rlc accumulates the node bytes",
+                    node_rlc,
+                    node_rlc_prev * challenges.keccak_input() + byte.clone(),
+                );
+                cb.require_equal(
+                    "node_hash is the same for all the bytes of a node",
+                    node_hash,
+                    node_hash_prev,
+                );
+                for column in [t.is_last_node, t.is_branch] {
+                    cb.require_equal(
+                        "the kind of a node is the same for all its bytes",
+                        meta.query_advice(column, Rotation::cur()),
+                        meta.query_advice(column, Rotation::prev()),
+                    );
+                }
+                cb.require_equal(
+                    "value_len counts the value bytes",
+                    value_len,
+                    value_len_prev + is_value.clone(),
+                );
+                cb.require_equal(
+                    "value_rlc accumulates the value bytes",
+                    value_rlc,
+                    value_rlc_prev
+                        * select::expr(is_value.clone(), challenges.keccak_input(), 1.expr())
+                        + is_value * byte.clone(),
+                );
+
+                let is_nibble = meta.query_advice(t.is_nibble, Rotation::cur());
+                let is_key_byte = meta.query_advice(t.is_key_byte, Rotation::cur());
+                cb.require_equal(
+                    "key_acc accumulates the nibbles and the bytes of the key",
+                    key_acc,
+                    key_acc_prev
+                        * (1.expr()
+                            + 15.expr() * is_nibble.clone()
+                            + 255.expr() * is_key_byte.clone())
+                        + is_nibble.clone() * meta.query_advice(t.nibble, Rotation::cur())
+                        + is_key_byte.clone() * byte,
+                );
+                cb.require_equal(
+                    "key_len counts the nibbles of the key",
+                    key_len,
+                    key_len_prev + is_nibble + 2.expr() * is_key_byte,
+                );
+            });
+
+            cb.gate(node_row(meta))
+        });
+
+        meta.create_gate("node items", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_node_start = meta.query_advice(t.is_node_start, Rotation::cur());
+            let is_item_start = meta.query_advice(t.rlp.is_item_start, Rotation::cur());
+            let is_item_end_prev = meta.query_advice(t.rlp.is_item_end, Rotation::prev());
+            let (is_list, is_list_prev) = cur_prev(meta, t.is_list);
+            let length = meta.query_advice(t.rlp.length, Rotation::cur());
+            let (payload_remaining, payload_remaining_prev) = cur_prev(meta, t.payload_remaining);
+            let (is_child, is_child_prev) = cur_prev(meta, t.is_child);
+
+            cb.require_equal(
+                "an item starts at the start of a node or after the end of the previous item",
+                is_item_start.clone(),
+                select::expr(is_node_start.clone(), 1.expr(), is_item_end_prev),
+            );
+            cb.require_equal(
+                "payload_remaining is the length of the list minus the bytes of its items",
+                payload_remaining,
+                select::expr(is_list.clone(), length, payload_remaining_prev - 1.expr()),
+            );
+
+            cb.condition(is_item_start.clone(), |cb| {
+                cb.require_equal(
+                    "a node is a list, and its items are strings",
+                    is_list.clone(),
+                    is_node_start,
+                );
+                cb.require_equal(
+                    "is_list is the class of the first byte of a list",
+                    is_list.clone(),
+                    t.rlp.is_list(meta),
+                );
+            });
+            cb.condition(not::expr(is_item_start), |cb| {
+                cb.require_equal("is_list is the same in the header", is_list, is_list_prev);
+                cb.require_equal("is_child is the same for an item", is_child, is_child_prev);
+            });
+
+            cb.gate(node_row(meta))
+        });
+
+        meta.create_gate("node kinds and paths", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let byte = meta.query_advice(t.byte, Rotation::cur());
+            let is_node_start = meta.query_advice(t.is_node_start, Rotation::cur());
+            let is_item_start = meta.query_advice(t.rlp.is_item_start, Rotation::cur());
+            let is_header = meta.query_advice(t.rlp.is_header, Rotation::cur());
+            let is_last_node = meta.query_advice(t.is_last_node, Rotation::cur());
+            let is_branch = meta.query_advice(t.is_branch, Rotation::cur());
+            let is_child = meta.query_advice(t.is_child, Rotation::cur());
+            let is_flag = meta.query_advice(t.is_flag, Rotation::cur());
+            let is_odd = meta.query_advice(t.is_odd, Rotation::cur());
+            let nibble = meta.query_advice(t.nibble, Rotation::cur());
+            let is_short_string = meta.query_advice(t.rlp.classes[0], Rotation::cur());
+            let length = meta.query_advice(t.rlp.length, Rotation::cur());
+            let (item_count, item_count_prev) = cur_prev(meta, t.item_count);
+            let (child_count, child_count_prev) = cur_prev(meta, t.child_count);
+            // The first item of an extension or a leaf
+            let is_path = not::expr(is_branch.clone()) * (2.expr() - item_count.clone());
+
+            cb.condition(is_node_start.clone(), |cb| {
+                cb.require_zero("item_count = 0", item_count.clone());
+                cb.require_zero("child_count = 0", child_count.clone());
+                cb.require_zero("the list header is not a child", is_child.clone());
+            });
+            cb.condition(not::expr(is_node_start.clone()), |cb| {
+                cb.require_equal(
+                    "item_count counts the items of the node",
+                    item_count.clone(),
+                    item_count_prev + is_item_start.clone(),
+                );
+                cb.require_equal(
+                    "child_count counts the children of the node",
+                    child_count.clone(),
+                    child_count_prev + is_child.clone() * is_item_start.clone(),
+                );
+            });
+            cb.condition(is_item_start.clone() * not::expr(is_node_start), |cb| {
+                cb.require_zero(
+                    "the child of an extension is its second item, and a leaf has no child",
+                    not::expr(is_branch.clone())
+                        * (is_child.clone()
+                            - not::expr(is_last_node.clone()) * (item_count.clone() - 1.expr())),
+                );
+                cb.require_zero(
+                    "the items of a branch are short strings",
+                    is_branch.clone() * not::expr(is_short_string.clone()),
+                );
+                cb.require_zero(
+                    "the items of a branch are empty or hashes",
+                    is_branch.clone() * length.clone() * (length.clone() - 32.expr()),
+                );
+                cb.require_zero(
+                    "a child is a short string",
+                    is_child.clone() * not::expr(is_short_string),
+                );
+                cb.require_zero("a child is a hash", is_child.clone() * (length - 32.expr()));
+                cb.require_zero(
+                    "the nibble of a branch child is its position",
+                    is_branch.clone()
+                        * is_child.clone()
+                        * (nibble.clone() - (item_count.clone() - 1.expr())),
+                );
+            });
+            cb.condition(meta.query_advice(t.is_node_end, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "a node ends with an item",
+                    meta.query_advice(t.rlp.is_item_end, Rotation::cur()),
+                    1.expr(),
+                );
+                cb.require_zero(
+                    "a node does not end with its list header",
+                    meta.query_advice(t.is_list, Rotation::cur()),
+                );
+                cb.require_zero(
+                    "a node ends with its list",
+                    meta.query_advice(t.payload_remaining, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "a branch has 17 items, and an extension or a leaf 2",
+                    item_count.clone(),
+                    2.expr() + (BRANCH_ITEMS - 2).expr() * is_branch.clone(),
+                );
+                cb.require_equal(
+                    "a node has one child, except the leaf that ends the path",
+                    child_count,
+                    not::expr(is_last_node.clone()),
+                );
+                cb.require_zero(
+                    "the last node is not a branch",
+                    is_branch.clone() * is_last_node.clone(),
+                );
+            });
+
+            cb.require_equal(
+                "the flag is the first byte of the path of an extension or a leaf",
+                is_flag.clone(),
+                is_path.clone() * is_first_data(meta),
+            );
+            cb.condition(is_flag.clone(), |cb| {
+                cb.require_equal(
+                    "the flag is 0x0 or 0x1 for an extension, 0x2 or 0x3 for a leaf",
+                    byte,
+                    16.expr() * (2.expr() * is_last_node.clone() + is_odd.clone()) + nibble.clone(),
+                );
+                cb.require_zero(
+                    "the flag of an even path has no nibble",
+                    not::expr(is_odd.clone()) * nibble,
+                );
+            });
+            cb.require_equal(
+                "the nibbles of the key are the branch children and the first nibble of odd paths",
+                meta.query_advice(t.is_nibble, Rotation::cur()),
+                is_branch.clone() * is_child.clone() * is_item_start + is_flag.clone() * is_odd,
+            );
+            cb.require_equal(
+                "the bytes of the key are the bytes of the paths after the flag",
+                meta.query_advice(t.is_key_byte, Rotation::cur()),
+                is_path * not::expr(is_header.clone()) * not::expr(is_flag),
+            );
+            cb.require_equal(
+                "the value is the payload of the second item of the leaf",
+                meta.query_advice(t.is_value, Rotation::cur()),
+                is_last_node * (item_count - 1.expr()) * not::expr(is_header.clone()),
+            );
+            cb.require_equal(
+                "the hashes of a branch end with its items",
+                meta.query_advice(t.is_hash_end, Rotation::cur()),
+                is_branch
+                    * meta.query_advice(t.rlp.is_item_end, Rotation::cur())
+                    * not::expr(is_header),
+            );
+
+            cb.gate(node_row(meta))
+        });
+
+        meta.create_gate("hash references", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_header = meta.query_advice(t.rlp.is_header, Rotation::cur());
+            let (item_rlc, item_rlc_prev) = cur_prev(meta, t.item_rlc);
+            let (ref_rlc, ref_rlc_prev) = cur_prev(meta, t.ref_rlc);
+
+            cb.require_equal(
+                "item_rlc accumulates the payload of an item",
+                item_rlc.clone(),
+                not::expr(is_header.clone())
+                    * (meta.query_advice(t.byte, Rotation::cur())
+                        + not::expr(is_first_data(meta)) * item_rlc_prev * challenges.evm_word()),
+            );
+            cb.require_equal(
+                "ref_rlc is the hash of the child of the node",
+                ref_rlc,
+                not::expr(meta.query_advice(t.is_node_start, Rotation::cur())) * ref_rlc_prev
+                    + meta.query_advice(t.is_child, Rotation::cur())
+                        * meta.query_advice(t.rlp.is_item_end, Rotation::cur())
+                        * not::expr(is_header)
+                        * item_rlc,
+            );
+
+            cb.gate(node_row(meta))
+        });
+    }
+
+    /// Assign the trie rows of `values` in their own region, followed by a
+    /// padding row, and return the cells of the root and of `id_offset`.  The
+    /// ids of the paths are offset by `id_offset` in the circuit using the
+    /// gadget.
+    pub(crate) fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        name: &str,
+        values: &[Vec<u8>],
+        id_offset: usize,
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<ListTrieCells<F>, Error> {
+        let t = self;
+        let rows = trie_rows(values);
+        let n_rows = rows.len() + 1;
+
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                let keccak_input = challenges.keccak_input();
+                let evm_word = challenges.evm_word();
+
+                let mut root = [0u128; 2];
+                let mut root_rlc = Value::known(F::zero());
+                let mut node_rlc = Value::known(F::zero());
+                let mut item_rlc = Value::known(F::zero());
+                let mut ref_rlc = Value::known(F::zero());
+                let mut value_rlc = Value::known(F::zero());
+                let mut root_cells = Vec::new();
+
+                for offset in 0..n_rows {
+                    let is_root = offset < ROOT_ROWS;
+                    for (name, column, value) in [
+                        ("q_enable", t.q_enable, true),
+                        ("q_first", t.q_first, offset == 0),
+                        ("q_last", t.q_last, offset == n_rows - 1),
+                        ("q_root", t.q_root, is_root),
+                        ("q_root_hi", t.q_root_hi, offset < ROOT_ROWS / 2),
+                    ] {
+                        region.assign_fixed(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(F::from(value as u64)),
+                        )?;
+                    }
+                    let (key, key_len) = match offset.checked_sub(1) {
+                        Some(index) => list_key_value(index),
+                        None => (0, 0),
+                    };
+                    for (column, value) in t.key_table.into_iter().zip([
+                        F::from(offset as u64),
+                        F::from_u128(key),
+                        F::from(key_len as u64),
+                    ]) {
+                        region.assign_fixed(
+                            || "key table",
+                            column,
+                            offset,
+                            || Value::known(value),
+                        )?;
+                    }
+
+                    let is_padding = offset >= rows.len();
+                    let row = rows.get(offset).cloned().unwrap_or_default();
+                    let byte = Value::known(F::from(row.rlp.byte as u64));
+
+                    if is_root {
+                        let half = if offset < ROOT_ROWS / 2 { 0 } else { 1 };
+                        root[half] = root[half] * 256 + row.rlp.byte as u128;
+                        root_rlc = root_rlc * evm_word + byte;
+                    }
+                    node_rlc = if row.is_node_start {
+                        byte
+                    } else {
+                        node_rlc * keccak_input + byte
+                    };
+                    item_rlc = if row.rlp.is_header {
+                        Value::known(F::zero())
+                    } else if row.is_first_data {
+                        byte
+                    } else {
+                        item_rlc * evm_word + byte
+                    };
+                    if row.is_node_start {
+                        ref_rlc = Value::known(F::zero());
+                        value_rlc = Value::known(F::zero());
+                    }
+                    if row.is_child && row.rlp.is_item_end && !row.rlp.is_header {
+                        ref_rlc = item_rlc;
+                    }
+                    if row.is_value {
+                        value_rlc = value_rlc * keccak_input + byte;
+                    }
+                    let node_hash = evm_word
+                        .map(|randomness| rlc::value(&row.node_hash.to_le_bytes(), randomness));
+
+                    let (node_rlc, node_hash, item_rlc, ref_rlc, value_rlc) =
+                        if is_root || is_padding {
+                            let zero = Value::known(F::zero());
+                            (zero, zero, zero, zero, zero)
+                        } else {
+                            (node_rlc, node_hash, item_rlc, ref_rlc, value_rlc)
+                        };
+
+                    for (name, column, value) in [
+                        ("byte", t.byte, row.rlp.byte as u64),
+                        ("id", t.id, row.id as u64),
+                        ("is_padding", t.is_padding, is_padding as u64),
+                        ("is_node_start", t.is_node_start, row.is_node_start as u64),
+                        ("is_node_end", t.is_node_end, row.is_node_end as u64),
+                        ("is_last_node", t.is_last_node, row.is_last_node as u64),
+                        ("is_branch", t.is_branch, row.is_branch as u64),
+                        ("node_len", t.node_len, row.node_len as u64),
+                        ("is_list", t.is_list, row.is_list as u64),
+                        (
+                            "payload_remaining",
+                            t.payload_remaining,
+                            row.payload_remaining,
+                        ),
+                        ("item_count", t.item_count, row.item_count as u64),
+                        ("is_child", t.is_child, row.is_child as u64),
+                        ("child_count", t.child_count, row.child_count as u64),
+                        ("is_flag", t.is_flag, row.is_flag as u64),
+                        ("is_odd", t.is_odd, row.is_odd as u64),
+                        ("nibble", t.nibble, row.nibble as u64),
+                        ("is_nibble", t.is_nibble, row.is_nibble as u64),
+                        ("is_key_byte", t.is_key_byte, row.is_key_byte as u64),
+                        ("key_len", t.key_len, row.key_len as u64),
+                        ("is_hash_end", t.is_hash_end, row.is_hash_end as u64),
+                        ("is_value", t.is_value, row.is_value as u64),
+                        ("value_len", t.value_len, row.value_len as u64),
+                    ] {
+                        region.assign_advice(
+                            || name,
+                            column,
+                            offset,
+                            || Value::known(F::from(value)),
+                        )?;
+                    }
+                    let id_offset_cell = region.assign_advice(
+                        || "id_offset",
+                        t.id_offset,
+                        offset,
+                        || Value::known(F::from(id_offset as u64)),
+                    )?;
+                    t.rlp.assign(&mut region, offset, &row.rlp)?;
+                    region.assign_advice(
+                        || "key_acc",
+                        t.key_acc,
+                        offset,
+                        || Value::known(F::from_u128(row.key_acc)),
+                    )?;
+                    for (name, column, value) in [
+                        ("node_rlc", t.node_rlc, node_rlc),
+                        ("node_hash", t.node_hash, node_hash),
+                        ("item_rlc", t.item_rlc, item_rlc),
+                        ("ref_rlc", t.ref_rlc, ref_rlc),
+                        ("value_rlc", t.value_rlc, value_rlc),
+                    ] {
+                        region.assign_advice(|| name, column, offset, || value)?;
+                    }
+                    let cells = [
+                        (t.root_hi, Value::known(F::from_u128(root[0]))),
+                        (t.root_lo, Value::known(F::from_u128(root[1]))),
+                        (t.root_rlc, root_rlc),
+                    ]
+                    .into_iter()
+                    .map(|(column, value)| {
+                        region.assign_advice(|| "root", column, offset, || value)
+                    })
+                    .chain([Ok(id_offset_cell)])
+                    .collect::<Result<Vec<_>, Error>>()?;
+                    if offset == ROOT_ROWS - 1 {
+                        root_cells = cells;
+                    }
+                }
+
+                let mut root_cells = root_cells.into_iter();
+                let mut next = || root_cells.next().expect("the trie has root rows");
+                Ok(ListTrieCells {
+                    root_hi: next(),
+                    root_lo: next(),
+                    root_rlc: next(),
+                    id_offset: next(),
+                })
+            },
+        )
+    }
+}
+
+/// Witness of a row of the trie.
+#[derive(Clone, Debug, Default)]
+struct TrieRow {
+    /// Decoding of the byte in its node
+    rlp: RlpDecoderRow,
+    id: usize,
+    is_node_start: bool,
+    is_node_end: bool,
+    is_last_node: bool,
+    is_branch: bool,
+    node_len: usize,
+    node_hash: Word,
+    is_list: bool,
+    payload_remaining: u64,
+    item_count: usize,
+    /// First byte of the payload of an item
+    is_first_data: bool,
+    is_child: bool,
+    child_count: usize,
+    is_flag: bool,
+    is_odd: bool,
+    nibble: u8,
+    is_nibble: bool,
+    is_key_byte: bool,
+    key_acc: u128,
+    key_len: usize,
+    is_hash_end: bool,
+    is_value: bool,
+    value_len: usize,
+}
+
+/// Key of the value at `index` in the trie of a list.
+pub fn list_key(index: usize) -> Vec<u8> {
+    rlp::encode(&(index as u64)).to_vec()
+}
+
+/// Key of the value at `index` as a big endian number, and its number of
+/// nibbles.
+fn list_key_value(index: usize) -> (u128, usize) {
+    let key = list_key(index);
+    let value = key
+        .iter()
+        .fold(0, |value, byte| value * 256 + *byte as u128);
+    (value, 2 * key.len())
+}
+
+/// Trie of a list of values, keyed by the RLP encoding of their index.
+pub fn list_trie(values: &[Vec<u8>]) -> Trie {
+    let mut trie = Trie::default();
+    for (index, value) in values.iter().enumerate() {
+        trie.insert(&list_key(index), value.clone())
+            .expect("list trie is complete");
+    }
+    trie
+}
+
+/// Nodes of the path of every value in the trie of a list, in the order of
+/// the values.
+fn list_trie_paths(trie: &Trie, len: usize) -> impl Iterator<Item = Vec<Vec<u8>>> + '_ {
+    (0..len).map(|index| trie.proof(&list_key(index)).expect("list trie is complete"))
+}
+
+/// Inputs to the keccak table required to prove the trie of `values`: the
+/// nodes of the path of every value.
+pub fn list_trie_keccak_inputs(values: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let trie = list_trie(values);
+    list_trie_paths(&trie, values.len()).flatten().collect()
+}
+
+/// Number of rows of the trie of `values`, including the padding row.
+pub(crate) fn list_trie_num_rows(values: &[Vec<u8>]) -> usize {
+    trie_rows(values).len() + 1
+}
+
+/// Generate the rows of the trie: the root rows, followed by the path of
+/// every value.
+fn trie_rows(values: &[Vec<u8>]) -> Vec<TrieRow> {
+    let trie = list_trie(values);
+    let mut rows: Vec<TrieRow> = trie
+        .root()
+        .to_fixed_bytes()
+        .into_iter()
+        .map(|byte| TrieRow {
+            rlp: RlpDecoderRow {
+                byte,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .collect();
+
+    for (index, path) in list_trie_paths(&trie, values.len()).enumerate() {
+        let key: Vec<u8> = list_key(index)
+            .into_iter()
+            .flat_map(|byte| [byte >> 4, byte & 0xf])
+            .collect();
+        let mut key_acc = 0;
+        let mut key_len = 0;
+        for (i, node) in path.iter().enumerate() {
+            let is_last_node = i == path.len() - 1;
+            let node_hash = keccak(node);
+            let node_rows = decode_node(node);
+            let is_branch =
+                node_rows.last().expect("nodes are not empty").item_count == BRANCH_ITEMS;
+            // Position of the child of a branch
+            let branch_child = if is_branch {
+                *key.get(key_len).expect("the path of the key continues") as usize + 1
+            } else {
+                0
+            };
+            let mut child_count = 0;
+            let mut value_len = 0;
+            let mut is_child = false;
+            for (j, mut row) in node_rows.into_iter().enumerate() {
+                row.id = index + 1;
+                row.is_node_start = j == 0;
+                row.is_node_end = j == node.len() - 1;
+                row.is_last_node = is_last_node;
+                row.is_branch = is_branch;
+                row.node_len = j + 1;
+                row.node_hash = node_hash;
+
+                if row.is_node_start {
+                    is_child = false;
+                } else if row.rlp.is_item_start {
+                    is_child = if is_branch {
+                        row.item_count == branch_child
+                    } else {
+                        !is_last_node && row.item_count == 2
+                    };
+                }
+                row.is_child = is_child;
+                child_count += (row.is_child && row.rlp.is_item_start) as usize;
+                row.child_count = child_count;
+
+                let is_path = !is_branch && row.item_count == 1;
+                row.is_flag = is_path && row.is_first_data;
+                if row.is_flag {
+                    row.is_odd = row.rlp.byte & 0x10 != 0;
+                    row.nibble = if row.is_odd { row.rlp.byte & 0xf } else { 0 };
+                }
+                if is_branch && row.is_child && row.rlp.is_item_start {
+                    row.nibble = (row.item_count - 1) as u8;
+                }
+                row.is_nibble = (is_branch && row.is_child && row.rlp.is_item_start)
+                    || (row.is_flag && row.is_odd);
+                row.is_key_byte = is_path && !row.rlp.is_header && !row.is_flag;
+                if row.is_nibble {
+                    key_acc = key_acc * 16 + row.nibble as u128;
+                    key_len += 1;
+                }
+                if row.is_key_byte {
+                    key_acc = key_acc * 256 + row.rlp.byte as u128;
+                    key_len += 2;
+                }
+                row.key_acc = key_acc;
+                row.key_len = key_len;
+
+                row.is_hash_end = is_branch && row.rlp.is_item_end && !row.rlp.is_header;
+                row.is_value = is_last_node && row.item_count == 2 && !row.rlp.is_header;
+                value_len += row.is_value as usize;
+                row.value_len = value_len;
+                rows.push(row);
+            }
+        }
+        debug_assert_eq!((key_acc, key_len), list_key_value(index));
+    }
+    rows
+}
+
+/// Decode the RLP encoding of a node, which is a list of strings, and return
+/// its rows with the decoding witness.
+fn decode_node(node: &[u8]) -> Vec<TrieRow> {
+    let mut rows: Vec<TrieRow> = Vec::with_capacity(node.len());
+    for (j, &byte) in node.iter().enumerate() {
+        let prev = rows.last().cloned().unwrap_or_default();
+        let is_item_start = j == 0 || prev.rlp.is_item_end;
+        let is_list = if is_item_start { j == 0 } else { prev.is_list };
+        if is_item_start {
+            assert_eq!(
+                is_list,
+                matches!(
+                    RlpByteClass::from(byte),
+                    RlpByteClass::ShortList | RlpByteClass::LongList
+                ),
+                "trie nodes are lists of strings"
+            );
+        }
+        let rlp = RlpDecoderRow::new(byte, is_item_start, is_list, &prev.rlp);
+        rows.push(TrieRow {
+            rlp,
+            is_list,
+            payload_remaining: if is_list {
+                rlp.length
+            } else {
+                prev.payload_remaining - 1
+            },
+            item_count: prev.item_count + (is_item_start && j != 0) as usize,
+            is_first_data: !rlp.is_header && (is_item_start || prev.rlp.is_header),
+            ..Default::default()
+        });
+    }
+    rows
+}
+
+/// Split a root in its hi and lo 128 bit halves.
+pub(crate) fn split_root<F: Field>(root: Word) -> [F; 2] {
+    [
+        F::from_u128((root >> 128).low_u128()),
+        F::from_u128(root.low_u128()),
+    ]
+}
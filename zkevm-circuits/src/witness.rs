@@ -3,15 +3,24 @@
 //! used to generate witnesses for circuits.
 
 mod block;
-pub use block::{block_convert, Block, BlockContext};
+pub use block::{block_convert, Block, BlockContext, BlockContexts};
 mod bytecode;
 pub use bytecode::Bytecode;
+mod chunk;
+pub use chunk::{block_convert_chunks, ChunkContext};
 mod call;
 pub use call::Call;
 mod mpt;
-pub use mpt::{MptUpdate, MptUpdateRow, MptUpdates};
+pub(crate) use mpt::Key as MptKey;
+pub use mpt::{MptUpdate, MptUpdateProof, MptUpdateRow, MptUpdates};
+mod receipt;
+pub(crate) use receipt::bloom_bit_index;
+pub use receipt::{
+    accrue_bloom, logs_bloom, receipts_from_rws, receipts_trie, Log, Receipt, BLOOM_BYTES,
+};
 mod rw;
-pub use rw::{Rw, RwMap, RwRow};
+pub(crate) use rw::RW_RECORD_LEN;
+pub use rw::{rw_commitment, Rw, RwMap, RwRow};
 mod step;
 pub use step::ExecStep;
 mod tx;
@@ -1,24 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{evm_circuit::util::rlc, table::BlockContextFieldTag};
+use crate::{
+    evm_circuit::util::rlc, pi_circuit::public_data_convert,
+    receipt_circuit::receipts_keccak_inputs, table::BlockContextFieldTag,
+};
 use bus_mapping::{
     circuit_input_builder::{self, CircuitsParams, CopyEvent, ExpEvent},
     Error,
 };
-use eth_types::{Address, Field, ToLittleEndian, ToScalar, Word};
+use eth_types::{geth_types::Withdrawal, Address, Field, ToLittleEndian, ToScalar, Word, H256};
 use halo2_proofs::circuit::Value;
+use serde::{Deserialize, Serialize};
 
-use super::{step::step_convert, tx::tx_convert, Bytecode, ExecStep, RwMap, Transaction};
+use super::{
+    receipt::receipts_from_rws, step::step_convert, tx::tx_convert, Bytecode, ChunkContext,
+    ExecStep, MptUpdates, Receipt, RwMap, Transaction,
+};
 
 // TODO: Remove fields that are duplicated in`eth_block`
 /// Block is the struct used by all circuits, which contains all the needed
 /// data for witness generation.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(bound = "F: Field")]
 pub struct Block<F> {
     /// The randomness for random linear combination
+    #[serde(with = "serde_field")]
     pub randomness: F,
     /// Transactions in the block
     pub txs: Vec<Transaction>,
+    /// Withdrawals in the blocks of the batch, applied after the transactions
+    pub withdrawals: Vec<Withdrawal>,
+    /// Numbers of the blocks of the withdrawals, aligned with `withdrawals`
+    pub withdrawal_blocks: Vec<u64>,
+    /// Withdrawal steps, one per withdrawal, between the last transaction and
+    /// the first EndBlock step.
+    pub withdrawal_steps: Vec<ExecStep>,
     /// EndBlock step that is repeated after the last transaction and before
     /// reaching the last EVM row.
     pub end_block_not_last: ExecStep,
@@ -26,10 +42,14 @@ pub struct Block<F> {
     pub end_block_last: ExecStep,
     /// Read write events in the RwTable
     pub rws: RwMap,
+    /// Updates to the state trie, proved by the MPT circuit
+    pub mpt_updates: MptUpdates,
+    /// Receipts of the transactions, built from the TxReceipt and TxLog rows
+    pub receipts: Vec<Receipt>,
     /// Bytecode used in the block
     pub bytecodes: HashMap<Word, Bytecode>,
-    /// The block context
-    pub context: BlockContext,
+    /// The contexts of the blocks of the batch
+    pub context: BlockContexts,
     /// Copy events for the copy circuit's table.
     pub copy_events: Vec<CopyEvent>,
     /// Exponentiation traces for the exponentiation circuit's table.
@@ -49,11 +69,53 @@ pub struct Block<F> {
     pub prev_state_root: Word, // TODO: Make this H256
     /// Keccak inputs
     pub keccak_inputs: Vec<Vec<u8>>,
-    /// Original Block from geth
+    /// Original Block from geth, the last one of the batch
+    #[serde(with = "eth_types::geth_types::json_in_binary")]
     pub eth_block: eth_types::Block<eth_types::Transaction>,
+    /// Original Blocks from geth that precede `eth_block` in the batch
+    #[serde(with = "eth_types::geth_types::json_in_binary")]
+    pub prev_eth_blocks: Vec<eth_types::Block<eth_types::Transaction>>,
+}
+
+/// Serde of a field element as its little-endian representation
+mod serde_field {
+    use eth_types::Field;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<F: Field, S: Serializer>(
+        value: &F,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.to_repr().serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, F: Field, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<F, D::Error> {
+        let repr = <[u8; 32]>::deserialize(deserializer)?;
+        Option::from(F::from_repr(repr)).ok_or_else(|| de::Error::custom("invalid field element"))
+    }
 }
 
 impl<F: Field> Block<F> {
+    /// Returns the transactions from geth of all the blocks of the batch, with
+    /// the number of their block.
+    pub fn eth_txs(&self) -> Vec<eth_types::Transaction> {
+        self.prev_eth_blocks
+            .iter()
+            .chain(std::iter::once(&self.eth_block))
+            .flat_map(|eth_block| {
+                eth_block
+                    .transactions
+                    .iter()
+                    .map(move |tx| eth_types::Transaction {
+                        block_number: eth_block.number,
+                        ..tx.clone()
+                    })
+            })
+            .collect()
+    }
+
     /// For each tx, for each step, print the rwc at the beginning of the step,
     /// and all the rw operations of the step.
     pub(crate) fn debug_print_txs_steps_rw_ops(&self) {
@@ -69,7 +131,7 @@ impl<F: Field> Block<F> {
     }
 }
 /// Block context for execution
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct BlockContext {
     /// The address of the miner for the block
     pub coinbase: Address,
@@ -85,6 +147,9 @@ pub struct BlockContext {
     pub base_fee: Word,
     /// The hash of previous blocks
     pub history_hashes: Vec<Word>,
+    /// The RLP encoded headers of previous blocks, aligned with
+    /// `history_hashes`
+    pub history_headers: Vec<Vec<u8>>,
     /// The chain id
     pub chain_id: Word,
 }
@@ -93,64 +158,70 @@ impl BlockContext {
     /// Assignments for block table
     pub fn table_assignments<F: Field>(&self, randomness: Value<F>) -> Vec<[Value<F>; 3]> {
         [
-            vec![
-                [
-                    Value::known(F::from(BlockContextFieldTag::Coinbase as u64)),
-                    Value::known(F::zero()),
-                    Value::known(self.coinbase.to_scalar().unwrap()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::Timestamp as u64)),
-                    Value::known(F::zero()),
-                    Value::known(self.timestamp.to_scalar().unwrap()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::Number as u64)),
-                    Value::known(F::zero()),
-                    Value::known(self.number.to_scalar().unwrap()),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::Difficulty as u64)),
-                    Value::known(F::zero()),
-                    randomness
-                        .map(|randomness| rlc::value(&self.difficulty.to_le_bytes(), randomness)),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::GasLimit as u64)),
-                    Value::known(F::zero()),
-                    Value::known(F::from(self.gas_limit)),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::BaseFee as u64)),
-                    Value::known(F::zero()),
-                    randomness
-                        .map(|randomness| rlc::value(&self.base_fee.to_le_bytes(), randomness)),
-                ],
-                [
-                    Value::known(F::from(BlockContextFieldTag::ChainId as u64)),
-                    Value::known(F::zero()),
-                    randomness
-                        .map(|randomness| rlc::value(&self.chain_id.to_le_bytes(), randomness)),
-                ],
-            ],
-            {
-                let len_history = self.history_hashes.len();
-                self.history_hashes
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, hash)| {
-                        [
-                            Value::known(F::from(BlockContextFieldTag::BlockHash as u64)),
-                            Value::known((self.number - len_history + idx).to_scalar().unwrap()),
-                            randomness
-                                .map(|randomness| rlc::value(&hash.to_le_bytes(), randomness)),
-                        ]
-                    })
-                    .collect()
-            },
+            self.header_assignments(randomness),
+            vec![[
+                Value::known(F::from(BlockContextFieldTag::ChainId as u64)),
+                Value::known(F::zero()),
+                randomness.map(|randomness| rlc::value(&self.chain_id.to_le_bytes(), randomness)),
+            ]],
+            self.numbered_history_hashes()
+                .map(|(number, hash)| block_hash_assignment(number, hash, randomness))
+                .collect(),
         ]
         .concat()
     }
+
+    /// Assignments for the header fields in the block table, indexed by the
+    /// number of the block.
+    fn header_assignments<F: Field>(&self, randomness: Value<F>) -> Vec<[Value<F>; 3]> {
+        let number = Value::known(self.number.to_scalar().unwrap());
+        vec![
+            [
+                Value::known(F::from(BlockContextFieldTag::Coinbase as u64)),
+                number,
+                Value::known(self.coinbase.to_scalar().unwrap()),
+            ],
+            [
+                Value::known(F::from(BlockContextFieldTag::Timestamp as u64)),
+                number,
+                Value::known(self.timestamp.to_scalar().unwrap()),
+            ],
+            [
+                Value::known(F::from(BlockContextFieldTag::Number as u64)),
+                number,
+                Value::known(self.number.to_scalar().unwrap()),
+            ],
+            [
+                Value::known(F::from(BlockContextFieldTag::Difficulty as u64)),
+                number,
+                randomness.map(|randomness| rlc::value(&self.difficulty.to_le_bytes(), randomness)),
+            ],
+            [
+                Value::known(F::from(BlockContextFieldTag::GasLimit as u64)),
+                number,
+                Value::known(F::from(self.gas_limit)),
+            ],
+            [
+                Value::known(F::from(BlockContextFieldTag::BaseFee as u64)),
+                number,
+                randomness.map(|randomness| rlc::value(&self.base_fee.to_le_bytes(), randomness)),
+            ],
+        ]
+    }
+
+    /// Returns the history hashes with the numbers of their blocks
+    fn numbered_history_hashes(&self) -> impl Iterator<Item = (u64, Word)> + '_ {
+        let first_number = self.number.low_u64() - self.history_hashes.len() as u64;
+        (first_number..).zip(self.history_hashes.iter().copied())
+    }
+}
+
+fn block_hash_assignment<F: Field>(number: u64, hash: Word, randomness: Value<F>) -> [Value<F>; 3] {
+    [
+        Value::known(F::from(BlockContextFieldTag::BlockHash as u64)),
+        Value::known(F::from(number)),
+        randomness.map(|randomness| rlc::value(&hash.to_le_bytes(), randomness)),
+    ]
 }
 
 impl From<&circuit_input_builder::Block> for BlockContext {
@@ -163,26 +234,133 @@ impl From<&circuit_input_builder::Block> for BlockContext {
             difficulty: block.difficulty,
             base_fee: block.base_fee,
             history_hashes: block.history_hashes.clone(),
+            history_headers: block.history_headers.clone(),
             chain_id: block.chain_id,
         }
     }
 }
 
+impl From<&circuit_input_builder::BlockHead> for BlockContext {
+    fn from(head: &circuit_input_builder::BlockHead) -> Self {
+        Self {
+            coinbase: head.coinbase,
+            gas_limit: head.gas_limit,
+            number: head.number,
+            timestamp: head.timestamp,
+            difficulty: head.difficulty,
+            base_fee: head.base_fee,
+            history_hashes: head.history_hashes.clone(),
+            history_headers: Vec::new(),
+            chain_id: head.chain_id,
+        }
+    }
+}
+
+/// Block contexts of the blocks of a batch, by number
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BlockContexts {
+    /// The contexts of the blocks, by number
+    pub ctxs: BTreeMap<u64, BlockContext>,
+    /// The chunk of the last block that is proved
+    pub chunk: ChunkContext,
+}
+
+impl BlockContexts {
+    /// Returns the context of the last block of the batch
+    pub fn last(&self) -> &BlockContext {
+        self.ctxs
+            .values()
+            .next_back()
+            .expect("batch without blocks")
+    }
+
+    /// Returns the context of the block with `number`
+    pub fn get(&self, number: u64) -> &BlockContext {
+        &self.ctxs[&number]
+    }
+
+    /// Assignments for block table.  The rows of the last block come first,
+    /// followed by the header fields of the previous blocks of the batch and
+    /// the fields of the chunk.  The PI circuit proves the rows of every
+    /// block from its header, so the hashes of the blocks before the history
+    /// of the last block, which an earlier block of the batch could read with
+    /// BLOCKHASH, are not in the table.
+    pub fn table_assignments<F: Field>(&self, randomness: Value<F>) -> Vec<[Value<F>; 3]> {
+        let last = match self.ctxs.values().next_back() {
+            Some(last) => last,
+            None => return Vec::new(),
+        };
+        last.table_assignments(randomness)
+            .into_iter()
+            .chain(
+                self.ctxs
+                    .range(..last.number.low_u64())
+                    .flat_map(|(_, ctx)| ctx.header_assignments(randomness)),
+            )
+            .chain(self.chunk.table_assignments())
+            .collect()
+    }
+}
+
+impl From<BlockContext> for BlockContexts {
+    fn from(ctx: BlockContext) -> Self {
+        Self {
+            ctxs: BTreeMap::from([(ctx.number.low_u64(), ctx)]),
+            chunk: ChunkContext::default(),
+        }
+    }
+}
+
+impl From<&circuit_input_builder::Block> for BlockContexts {
+    fn from(block: &circuit_input_builder::Block) -> Self {
+        let mut ctxs: BTreeMap<u64, BlockContext> = block
+            .headers
+            .iter()
+            .map(|(number, head)| (*number, head.into()))
+            .collect();
+        // The last block also knows the headers of its history
+        ctxs.insert(block.number.low_u64(), block.into());
+        Self {
+            ctxs,
+            chunk: ChunkContext::default(),
+        }
+    }
+}
+
 /// Convert a block struct in bus-mapping to a witness block used in circuits
 pub fn block_convert<F: Field>(
     block: &circuit_input_builder::Block,
     code_db: &bus_mapping::state_db::CodeDB,
 ) -> Result<Block<F>, Error> {
-    Ok(Block {
+    let rws = RwMap::from(&block.container);
+    let mpt_updates = MptUpdates::from_rws(&rws.table_assignments(), &block.state_trie)?;
+    let mut keccak_inputs = circuit_input_builder::keccak_inputs(block, code_db)?;
+    keccak_inputs.extend(mpt_updates.keccak_inputs());
+    let txs: Vec<Transaction> = block
+        .txs()
+        .iter()
+        .enumerate()
+        .map(|(idx, tx)| tx_convert(tx, idx + 1, block.chain_id.as_u64()))
+        .collect();
+    let receipts = receipts_from_rws(&rws, &txs);
+    keccak_inputs.extend(receipts_keccak_inputs(&receipts));
+    let mut context = BlockContexts::from(block);
+    context.chunk.rw_commitment = rws.commitment(H256::zero());
+    let mut witness_block = Block {
         // randomness: F::from(0x100), // Special value to reveal elements after RLC
         randomness: F::from(0xcafeu64),
-        context: block.into(),
-        rws: RwMap::from(&block.container),
-        txs: block
-            .txs()
+        context,
+        rws,
+        mpt_updates,
+        receipts,
+        txs,
+        withdrawals: block.withdrawals.clone(),
+        withdrawal_blocks: block.withdrawal_blocks.clone(),
+        withdrawal_steps: block
+            .block_steps
+            .withdrawals
             .iter()
-            .enumerate()
-            .map(|(idx, tx)| tx_convert(tx, idx + 1))
+            .map(step_convert)
             .collect(),
         end_block_not_last: step_convert(&block.block_steps.end_block_not_last),
         end_block_last: step_convert(&block.block_steps.end_block_last),
@@ -201,7 +379,84 @@ pub fn block_convert<F: Field>(
         evm_circuit_pad_to: <usize>::default(),
         exp_circuit_pad_to: <usize>::default(),
         prev_state_root: block.prev_state_root,
-        keccak_inputs: circuit_input_builder::keccak_inputs(block, code_db)?,
+        keccak_inputs,
         eth_block: block.eth_block.clone(),
-    })
+        prev_eth_blocks: block
+            .headers
+            .range(..block.number.low_u64())
+            .map(|(_, head)| head.eth_block.clone())
+            .collect(),
+    };
+    // The headers hashed by the PI circuit
+    let pi_keccak_inputs = public_data_convert(&witness_block).keccak_inputs();
+    witness_block.keccak_inputs.extend(pi_keccak_inputs);
+    Ok(witness_block)
+}
+
+#[cfg(test)]
+mod block_tests {
+    use super::*;
+    use bus_mapping::{
+        mock::BlockData,
+        serialization::{from_reader, to_writer, Format},
+    };
+    use eth_types::{bytecode, geth_types::GethData};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use mock::test_ctx::{helpers::*, TestContext};
+
+    #[test]
+    fn witness_block_roundtrip() {
+        let code = bytecode! {
+            PUSH1(0x20)
+            PUSH1(0x00)
+            PUSH1(0x00)
+            CALLDATACOPY
+            PUSH1(0x03)
+            PUSH1(0x02)
+            EXP
+            PUSH1(0x00)
+            SSTORE
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .input(vec![0xab; 0x20].into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let mut witness_block = block_convert::<Fr>(&builder.block, &builder.code_db).unwrap();
+        witness_block.randomness = Fr::from(0x100);
+
+        let expected = serde_json::to_value(&witness_block).unwrap();
+        for format in [Format::Json, Format::Binary] {
+            let mut bytes = Vec::new();
+            to_writer(&mut bytes, format, &witness_block).unwrap();
+            let decoded: Block<Fr> = from_reader(bytes.as_slice(), format).unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), expected);
+            assert_eq!(decoded.randomness, witness_block.randomness);
+            // The updates are looked up by the key rebuilt from each of them
+            assert_eq!(decoded.mpt_updates.len(), witness_block.mpt_updates.len());
+            assert_eq!(
+                decoded.mpt_updates.new_root(),
+                witness_block.mpt_updates.new_root()
+            );
+            for rw in witness_block.rws.table_assignments() {
+                assert_eq!(
+                    serde_json::to_value(decoded.mpt_updates.get(&rw)).unwrap(),
+                    serde_json::to_value(witness_block.mpt_updates.get(&rw)).unwrap()
+                );
+            }
+        }
+    }
 }
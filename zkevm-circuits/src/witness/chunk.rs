@@ -0,0 +1,299 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    pi_circuit::public_data_convert,
+    receipt_circuit::receipts_keccak_inputs,
+    table::{BlockContextFieldTag, CallContextFieldTag, RwTableTag, TxReceiptFieldTag},
+};
+use bus_mapping::{
+    circuit_input_builder::{self, CopyDataType, NumberOrHash},
+    state_db::CodeDB,
+    Error,
+};
+use eth_types::{Field, ToWord, Word, H256};
+use halo2_proofs::circuit::Value;
+use serde::{Deserialize, Serialize};
+
+use super::{block_convert, Block, ExecStep, MptUpdates, Rw, RwMap};
+
+/// Position of a witness in the chunks of its block, which is assigned to the
+/// block table for the EVM circuit and linked to the other chunks by the
+/// public inputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkContext {
+    /// Index of the chunk in the block, starting at 0
+    pub index: usize,
+    /// Number of chunks of the block
+    pub total: usize,
+    /// Rw counter of the first operation of the chunk
+    pub rw_counter_start: usize,
+    /// Id of the first tx of the chunk
+    pub first_tx_id: usize,
+    /// State root after the whole block
+    pub block_state_root: Word,
+    /// Commitment to the rw operations of the previous chunks
+    pub prev_rw_commitment: H256,
+    /// Digest of the rw operations of the previous chunk, from which
+    /// `prev_rw_commitment` is computed, see [`RwMap::commitment`]
+    pub prev_rw_digest: H256,
+    /// Rw operation of the previous chunks with the highest rw counter, from
+    /// which `prev_rw_commitment` is computed
+    pub prev_last_rw: Option<Rw>,
+    /// Commitment to the rw operations of the chunk, chained to
+    /// `prev_rw_commitment`
+    pub rw_commitment: H256,
+}
+
+impl Default for ChunkContext {
+    /// Context of a block proved in a single chunk
+    fn default() -> Self {
+        Self {
+            index: 0,
+            total: 1,
+            rw_counter_start: 1,
+            first_tx_id: 1,
+            block_state_root: Word::zero(),
+            prev_rw_commitment: H256::zero(),
+            prev_rw_digest: H256::zero(),
+            prev_last_rw: None,
+            rw_commitment: H256::zero(),
+        }
+    }
+}
+
+impl ChunkContext {
+    /// Returns whether the chunk is the last one of the block
+    pub fn is_last(&self) -> bool {
+        self.index + 1 == self.total
+    }
+
+    /// Assignments for block table
+    pub fn table_assignments<F: Field>(&self) -> Vec<[Value<F>; 3]> {
+        [
+            (BlockContextFieldTag::RwCounterStart, self.rw_counter_start),
+            (BlockContextFieldTag::FirstTxId, self.first_tx_id),
+            (BlockContextFieldTag::IsLastChunk, self.is_last() as usize),
+        ]
+        .map(|(tag, value)| {
+            [
+                Value::known(F::from(tag as u64)),
+                Value::known(F::zero()),
+                Value::known(F::from(value as u64)),
+            ]
+        })
+        .to_vec()
+    }
+}
+
+/// Convert a block struct in bus-mapping to the witness blocks of its chunks
+/// (see [`circuit_input_builder::Block::chunks`]), in order.  Every chunk
+/// keeps all the txs of the block in its tx table, but only the ones of the
+/// chunk are executed.  A chunk that isn't the last one ends with the
+/// EndBlock reads of its last tx, which are not part of the block.
+pub fn block_convert_chunks<F: Field>(
+    block: &circuit_input_builder::Block,
+    code_db: &CodeDB,
+) -> Result<Vec<Block<F>>, Error> {
+    let chunks = block.chunks(code_db)?;
+    let full = block_convert::<F>(block, code_db)?;
+    if chunks.len() == 1 {
+        return Ok(vec![full]);
+    }
+
+    let block_state_root = full.mpt_updates.new_root();
+    let mut state_trie = block.state_trie.clone();
+    let mut prev_rw_commitment = H256::zero();
+    let mut prev_rw_digest = H256::zero();
+    let mut prev_last_rw = None;
+    let mut witness_chunks = Vec::with_capacity(chunks.len());
+    for chunk in chunks.iter() {
+        let is_last = chunk.index + 1 == chunks.len();
+        // The last chunk also contains the withdrawals and EndBlock
+        let rwcs = if is_last {
+            chunk.rw_counter_start.0..usize::MAX
+        } else {
+            chunk.rw_counter_start.0..chunk.rw_counter_end.0
+        };
+        let (mut rws, indices) = chunk_rws(&full.rws, &rwcs);
+        let remap = |step: &ExecStep| ExecStep {
+            rw_indices: step
+                .rw_indices
+                .iter()
+                .filter_map(|index| indices.get(index).copied())
+                .collect(),
+            ..step.clone()
+        };
+        let mpt_updates =
+            MptUpdates::from_rws_with_trie(&rws.table_assignments(), &mut state_trie)?;
+
+        let mut txs = full.txs.clone();
+        for tx in txs.iter_mut() {
+            if chunk.txs.contains(&(tx.id - 1)) {
+                tx.steps = tx.steps.iter().map(remap).collect();
+            } else {
+                tx.steps.clear();
+            }
+        }
+        let last_tx = &txs[chunk.txs.end - 1];
+
+        let (withdrawal_steps, end_block_not_last, mut end_block_last) = if is_last {
+            (
+                full.withdrawal_steps.iter().map(remap).collect(),
+                remap(&full.end_block_not_last),
+                remap(&full.end_block_last),
+            )
+        } else {
+            // EndBlock reads the id and the cumulative gas used of the last tx
+            // of the chunk, without withdrawals before it.
+            let rw_counter = chunk.rw_counter_end.0;
+            let cumulative_gas_used = full
+                .receipts
+                .iter()
+                .find(|receipt| receipt.tx_id == last_tx.id)
+                .expect("receipt of the last tx of the chunk")
+                .cumulative_gas_used;
+            let end_block = ExecStep {
+                rw_counter,
+                program_counter: 1,
+                rw_indices: Vec::new(),
+                ..full.end_block_not_last.clone()
+            };
+            let mut end_block_last = end_block.clone();
+            end_block_last.rw_indices = [
+                Rw::CallContext {
+                    rw_counter,
+                    is_write: false,
+                    call_id: last_tx.calls[0].id,
+                    field_tag: CallContextFieldTag::TxId,
+                    value: Word::from(last_tx.id),
+                },
+                Rw::TxReceipt {
+                    rw_counter: rw_counter + 1,
+                    is_write: false,
+                    tx_id: last_tx.id,
+                    field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                    value: cumulative_gas_used,
+                },
+            ]
+            .into_iter()
+            .map(|row| push_row(&mut rws, row))
+            .collect();
+            (Vec::new(), end_block, end_block_last)
+        };
+        // The EndBlock reads are part of the commitment, so that the next
+        // chunk can check where it starts from its last row.
+        let rw_digest = rws.digest(prev_rw_commitment);
+        let last_rw = rws.last_row();
+        let rw_commitment = rws.commitment(prev_rw_commitment);
+        // The Start rows of the chunk, see EndBlock
+        let total_rws: usize = rws.0.values().map(|rows| rows.len()).sum();
+        for rw_counter in [1, block.circuits_params.max_rws - total_rws] {
+            let index = push_row(&mut rws, Rw::Start { rw_counter });
+            end_block_last.rw_indices.push(index);
+        }
+
+        let mut used_bytecodes: Vec<Word> = txs[chunk.txs.clone()]
+            .iter()
+            .flat_map(|tx| tx.calls.iter().map(|call| call.code_hash))
+            .collect();
+        let copy_events: Vec<_> = full
+            .copy_events
+            .iter()
+            .filter(|event| rwcs.contains(&event.rw_counter_start.0))
+            .cloned()
+            .collect();
+        for event in copy_events.iter() {
+            for (ty, id) in [
+                (event.src_type, &event.src_id),
+                (event.dst_type, &event.dst_id),
+            ] {
+                if let (CopyDataType::Bytecode, NumberOrHash::Hash(hash)) = (ty, id) {
+                    used_bytecodes.push(hash.to_word());
+                }
+            }
+        }
+
+        let receipts: Vec<_> = full
+            .receipts
+            .iter()
+            .filter(|receipt| chunk.txs.contains(&(receipt.tx_id - 1)))
+            .cloned()
+            .collect();
+        let mut keccak_inputs = circuit_input_builder::keccak_inputs(block, code_db)?;
+        keccak_inputs.extend(mpt_updates.keccak_inputs());
+        keccak_inputs.extend(receipts_keccak_inputs(&receipts));
+
+        let mut context = full.context.clone();
+        context.chunk = ChunkContext {
+            index: chunk.index,
+            total: chunks.len(),
+            rw_counter_start: chunk.rw_counter_start.0,
+            first_tx_id: chunk.txs.start + 1,
+            block_state_root,
+            prev_rw_commitment,
+            prev_rw_digest,
+            prev_last_rw,
+            rw_commitment,
+        };
+        let mut witness_chunk = Block {
+            txs,
+            withdrawal_steps,
+            end_block_not_last,
+            end_block_last,
+            rws,
+            prev_state_root: mpt_updates.old_root(),
+            mpt_updates,
+            receipts,
+            bytecodes: full
+                .bytecodes
+                .iter()
+                .filter(|(hash, _)| used_bytecodes.contains(hash))
+                .map(|(hash, bytecode)| (*hash, bytecode.clone()))
+                .collect(),
+            context,
+            copy_events,
+            exp_events: full
+                .exp_events
+                .iter()
+                .filter(|event| rwcs.contains(&event.identifier))
+                .cloned()
+                .collect(),
+            keccak_inputs,
+            ..full.clone()
+        };
+        // The headers hashed by the PI circuit
+        let pi_keccak_inputs = public_data_convert(&witness_chunk).keccak_inputs();
+        witness_chunk.keccak_inputs.extend(pi_keccak_inputs);
+
+        prev_rw_commitment = rw_commitment;
+        prev_rw_digest = rw_digest;
+        prev_last_rw = last_rw;
+        witness_chunks.push(witness_chunk);
+    }
+    Ok(witness_chunks)
+}
+
+/// Rows of `rws` with rw counters in `rwcs`, excluding the Start ones, with
+/// the map from their indexes in `rws` to their indexes in the chunk.
+fn chunk_rws(
+    rws: &RwMap,
+    rwcs: &Range<usize>,
+) -> (RwMap, HashMap<(RwTableTag, usize), (RwTableTag, usize)>) {
+    let mut chunk_rws = RwMap::default();
+    let mut indices = HashMap::new();
+    for (tag, rows) in rws.0.iter() {
+        for (index, row) in rows.iter().enumerate() {
+            if !matches!(row, Rw::Start { .. }) && rwcs.contains(&row.rw_counter()) {
+                indices.insert((*tag, index), push_row(&mut chunk_rws, *row));
+            }
+        }
+    }
+    (chunk_rws, indices)
+}
+
+/// Append `row` to the rows of its tag and return its index.
+fn push_row(rws: &mut RwMap, row: Rw) -> (RwTableTag, usize) {
+    let rows = rws.0.entry(row.tag()).or_default();
+    rows.push(row);
+    (row.tag(), rows.len() - 1)
+}
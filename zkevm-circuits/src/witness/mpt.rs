@@ -1,24 +1,63 @@
 use crate::evm_circuit::util::rlc;
 use crate::evm_circuit::witness::Rw;
+use crate::mpt_circuit::update_keccak_inputs;
 use crate::table::{AccountFieldTag, ProofType};
-use eth_types::{Address, Field, ToLittleEndian, ToScalar, Word};
+use bus_mapping::{error::TrieError, mpt::StateTrie, operation::AccountField};
+use eth_types::{Address, Field, ToLittleEndian, ToScalar, ToWord, Word};
 use halo2_proofs::circuit::Value;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 /// An MPT update whose validity is proved by the MptCircuit
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MptUpdate {
-    key: Key,
-    old_value: Word,
-    new_value: Word,
-    old_root: Word,
-    new_root: Word,
+    pub(crate) key: Key,
+    pub(crate) old_value: Word,
+    pub(crate) new_value: Word,
+    pub(crate) old_root: Word,
+    pub(crate) new_root: Word,
+    pub(crate) proof: MptUpdateProof,
+}
+
+/// The trie nodes that prove an [`MptUpdate`].  Each list contains the RLP
+/// encoded nodes in the path from the root to the key, before and after the
+/// update.  For storage updates the path goes through the account trie down
+/// to the account leaf, followed by the path in the storage trie of the
+/// account.  An empty trie is represented by the empty node `0x80`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MptUpdateProof {
+    /// Nodes in the path of the key in the trie with the old root
+    pub old_nodes: Vec<Vec<u8>>,
+    /// Nodes in the path of the key in the trie with the new root
+    pub new_nodes: Vec<Vec<u8>>,
+}
+
+/// The empty trie node, whose hash is the root of an empty trie.
+const EMPTY_NODE: u8 = 0x80;
+
+impl MptUpdateProof {
+    /// The old and new paths, where the path of an empty trie is the empty
+    /// node.
+    pub(crate) fn paths(&self) -> [Vec<Vec<u8>>; 2] {
+        [&self.old_nodes, &self.new_nodes].map(|nodes| {
+            if nodes.is_empty() {
+                vec![vec![EMPTY_NODE]]
+            } else {
+                nodes.clone()
+            }
+        })
+    }
 }
 
 impl MptUpdate {
-    fn proof_type<F: Field>(&self) -> F {
-        let proof_type = match self.key {
+    pub(crate) fn proof_type<F: Field>(&self) -> F {
+        F::from(self.proof_type_tag() as u64)
+    }
+
+    /// The type of the proof of the update in the MptTable
+    pub(crate) fn proof_type_tag(&self) -> ProofType {
+        match self.key {
             Key::AccountStorage { .. } => {
                 if self.old_value.is_zero() && self.new_value.is_zero() {
                     ProofType::StorageDoesNotExist
@@ -27,18 +66,42 @@ impl MptUpdate {
                 }
             }
             Key::Account { field_tag, .. } => field_tag.into(),
-        };
-        F::from(proof_type as u64)
+        }
     }
 }
 
 /// All the MPT updates in the MptCircuit, accessible by their key
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct MptUpdates {
     old_root: Word,
+    #[serde(with = "serde_updates")]
     updates: BTreeMap<Key, MptUpdate>,
 }
 
+/// Serde of the updates as a list, since their keys can't be the keys of a
+/// JSON object.
+mod serde_updates {
+    use super::{Key, MptUpdate};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub(super) fn serialize<S: Serializer>(
+        updates: &BTreeMap<Key, MptUpdate>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(updates.values())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Key, MptUpdate>, D::Error> {
+        Ok(Vec::<MptUpdate>::deserialize(deserializer)?
+            .into_iter()
+            .map(|update| (update.key, update))
+            .collect())
+    }
+}
+
 /// The field element encoding of an MPT update, which is used by the MptTable
 #[derive(Debug, Clone, Copy)]
 pub struct MptUpdateRow<F>(pub(crate) [F; 7]);
@@ -49,7 +112,117 @@ impl MptUpdates {
     }
 
     pub(crate) fn get(&self, row: &Rw) -> Option<MptUpdate> {
-        key(row).map(|key| *self.updates.get(&key).expect("missing key in mpt updates"))
+        key(row).map(|key| {
+            self.updates
+                .get(&key)
+                .expect("missing key in mpt updates")
+                .clone()
+        })
+    }
+
+    /// The root of the trie after applying all the updates
+    pub(crate) fn new_root(&self) -> Word {
+        self.updates
+            .values()
+            .last()
+            .map_or(self.old_root, |update| update.new_root)
+    }
+
+    /// Iterate over the updates, in the order in which they are applied
+    pub(crate) fn updates(&self) -> impl Iterator<Item = &MptUpdate> {
+        self.updates.values()
+    }
+
+    /// Number of updates
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    /// Returns true if there are no updates
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    /// The keys and the trie nodes of all the update proofs, including the
+    /// drift nodes, which are hashed in the MptCircuit
+    pub fn keccak_inputs(&self) -> Vec<Vec<u8>> {
+        self.updates
+            .values()
+            .flat_map(update_keccak_inputs)
+            .collect()
+    }
+
+    /// Build the updates from a list of updates applied in the order of
+    /// their keys, starting from the trie with root `old_root`.
+    pub(crate) fn from_updates(
+        old_root: Word,
+        updates: impl IntoIterator<Item = MptUpdate>,
+    ) -> Self {
+        MptUpdates {
+            old_root,
+            updates: updates
+                .into_iter()
+                .map(|update| (update.key.set_exists(), update))
+                .collect(),
+        }
+    }
+
+    /// Build the updates of the `rows` of the RwTable by applying them, in the
+    /// order of their keys, to the state trie of the previous block.
+    pub(crate) fn from_rws(rows: &[Rw], state_trie: &StateTrie) -> Result<Self, TrieError> {
+        Self::from_rws_with_trie(rows, &mut state_trie.clone())
+    }
+
+    /// Updates of the rows applied to `trie`, which is left in the state that
+    /// follows them, so that the updates of the next chunk of a block can be
+    /// applied to it.
+    pub(crate) fn from_rws_with_trie(rows: &[Rw], trie: &mut StateTrie) -> Result<Self, TrieError> {
+        let transitions: BTreeMap<_, _> = rows
+            .iter()
+            .group_by(|row| key(row))
+            .into_iter()
+            .filter_map(|(key, rows)| key.map(|key| (key, rows)))
+            .map(|(key, mut rows)| {
+                let first = rows.next().unwrap();
+                let last = rows.last().unwrap_or(first);
+                (key, (value_prev(first), value(last)))
+            })
+            .collect();
+
+        let old_root = trie.root().to_word();
+        let updates = transitions
+            .into_iter()
+            .map(|(key, (old_value, new_value))| {
+                let update = match key {
+                    Key::Account { address, field_tag } => {
+                        let field = match field_tag {
+                            AccountFieldTag::Nonce => AccountField::Nonce,
+                            AccountFieldTag::Balance => AccountField::Balance,
+                            AccountFieldTag::CodeHash => AccountField::CodeHash,
+                            AccountFieldTag::NonExisting => unreachable!(),
+                        };
+                        trie.set_account_field(&address, field, new_value)?
+                    }
+                    Key::AccountStorage {
+                        address,
+                        storage_key,
+                        ..
+                    } => trie.set_storage(&address, &storage_key, new_value)?,
+                };
+                Ok(MptUpdate {
+                    key: key.set_non_exists(old_value, new_value),
+                    old_value,
+                    new_value,
+                    old_root: update.old_root.to_word(),
+                    new_root: update.new_root.to_word(),
+                    proof: MptUpdateProof {
+                        old_nodes: update.old_nodes,
+                        new_nodes: update.new_nodes,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, TrieError>>()?;
+        Ok(Self::from_updates(old_root, updates))
     }
 
     pub(crate) fn mock_from(rows: &[Rw]) -> Self {
@@ -73,6 +246,7 @@ impl MptUpdates {
                         new_root: Word::from(i as u64 + 1) + mock_old_root,
                         old_value: value_prev(first),
                         new_value: value(last),
+                        proof: MptUpdateProof::default(),
                     },
                 )
             })
@@ -89,28 +263,34 @@ impl MptUpdates {
     ) -> Vec<MptUpdateRow<Value<F>>> {
         self.updates
             .values()
-            .map(|update| {
-                let (new_root, old_root) = randomness
-                    .map(|randomness| update.root_assignments(randomness))
-                    .unzip();
-                let (new_value, old_value) = randomness
-                    .map(|randomness| update.value_assignments(randomness))
-                    .unzip();
-                MptUpdateRow([
-                    Value::known(update.key.address()),
-                    randomness.map(|randomness| update.key.storage_key(randomness)),
-                    Value::known(update.proof_type()),
-                    new_root,
-                    old_root,
-                    new_value,
-                    old_value,
-                ])
-            })
+            .map(|update| update.table_assignment(randomness))
             .collect()
     }
 }
 
 impl MptUpdate {
+    /// The row of the MptTable that corresponds to the update
+    pub(crate) fn table_assignment<F: Field>(
+        &self,
+        randomness: Value<F>,
+    ) -> MptUpdateRow<Value<F>> {
+        let (new_root, old_root) = randomness
+            .map(|randomness| self.root_assignments(randomness))
+            .unzip();
+        let (new_value, old_value) = randomness
+            .map(|randomness| self.value_assignments(randomness))
+            .unzip();
+        MptUpdateRow([
+            Value::known(self.key.address()),
+            randomness.map(|randomness| self.key.storage_key(randomness)),
+            Value::known(self.proof_type()),
+            new_root,
+            old_root,
+            new_value,
+            old_value,
+        ])
+    }
+
     pub(crate) fn value_assignments<F: Field>(&self, word_randomness: F) -> (F, F) {
         let assign = |x: Word| match self.key {
             Key::Account {
@@ -131,18 +311,20 @@ impl MptUpdate {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug, Copy, PartialOrd, Ord)]
-enum Key {
-    Account {
-        address: Address,
-        field_tag: AccountFieldTag,
-    },
+// The order of the keys is the order of their rows in the RwTable, which is
+// the order in which the updates are applied to the state trie.
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Copy, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) enum Key {
     AccountStorage {
         tx_id: usize,
         address: Address,
         storage_key: Word,
         exists: bool,
     },
+    Account {
+        address: Address,
+        field_tag: AccountFieldTag,
+    },
 }
 
 impl Key {
@@ -178,6 +360,31 @@ impl Key {
             self
         }
     }
+    // Inverse of `set_non_exists`: return the key of the rw rows of the update,
+    // which is the key used to index it in MptUpdates.
+    fn set_exists(self) -> Self {
+        match self {
+            Key::Account {
+                address,
+                field_tag: AccountFieldTag::NonExisting,
+            } => Key::Account {
+                address,
+                field_tag: AccountFieldTag::CodeHash,
+            },
+            Key::AccountStorage {
+                tx_id,
+                address,
+                storage_key,
+                ..
+            } => Key::AccountStorage {
+                tx_id,
+                address,
+                storage_key,
+                exists: true,
+            },
+            _ => self,
+        }
+    }
     fn address<F: Field>(&self) -> F {
         match self {
             Self::Account { address, .. } | Self::AccountStorage { address, .. } => {
@@ -0,0 +1,188 @@
+use bus_mapping::mpt::Trie;
+use eth_types::{geth_types::TxType, Address, ToAddress, ToBigEndian, Word, H256};
+use ethers_core::{
+    types::Bloom,
+    utils::{keccak256, rlp::RlpStream},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    table::{RwTableTag, TxLogFieldTag, TxReceiptFieldTag},
+    util::trie::list_trie,
+};
+
+use super::{Rw, RwMap, Transaction};
+
+/// Number of bytes of a bloom filter
+pub const BLOOM_BYTES: usize = 256;
+
+/// Log emitted by a transaction
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Log {
+    /// Address of the contract that emitted the log
+    pub address: Address,
+    /// Topics of the log
+    pub topics: Vec<Word>,
+    /// Data of the log
+    pub data: Vec<u8>,
+}
+
+/// Receipt of a transaction in a witness block, built from the TxReceipt and
+/// TxLog rows of the RwTable.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// The transaction identifier in the block
+    pub tx_id: usize,
+    /// The type of the transaction envelope
+    pub tx_type: TxType,
+    /// 1 if the transaction succeeded, 0 otherwise
+    pub status: u64,
+    /// Gas used by the transaction and all the previous ones in the block
+    pub cumulative_gas_used: u64,
+    /// Logs emitted by the transaction, which only include the logs of the
+    /// calls that were not reverted
+    pub logs: Vec<Log>,
+}
+
+impl Receipt {
+    /// Bloom filter of the logs of the receipt
+    pub fn bloom(&self) -> Bloom {
+        let mut bloom = Bloom::zero();
+        for log in &self.logs {
+            accrue_bloom(&mut bloom, log.address.as_bytes());
+            for topic in &log.topics {
+                accrue_bloom(&mut bloom, &topic.to_be_bytes());
+            }
+        }
+        bloom
+    }
+
+    /// Encoding of the receipt as stored in the receipts trie: the RLP list
+    /// `[status, cumulative_gas_used, bloom, logs]`, preceded by the type of
+    /// the transaction for typed transactions (EIP-2718).
+    pub fn rlp(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append(&self.status)
+            .append(&self.cumulative_gas_used)
+            .append(&self.bloom().as_bytes());
+        stream.begin_list(self.logs.len());
+        for log in &self.logs {
+            stream.begin_list(3).append(&log.address);
+            stream.begin_list(log.topics.len());
+            for topic in &log.topics {
+                stream.append(&H256::from_uint(topic));
+            }
+            stream.append(&log.data);
+        }
+        let mut encoding = match self.tx_type {
+            TxType::Legacy => Vec::new(),
+            tx_type => vec![tx_type as u8],
+        };
+        encoding.extend_from_slice(&stream.out());
+        encoding
+    }
+}
+
+/// Set in `bloom` the 3 bits selected by the hash of `input`.
+pub fn accrue_bloom(bloom: &mut Bloom, input: &[u8]) {
+    let hash = keccak256(input);
+    for i in [0, 2, 4] {
+        let bit = bloom_bit_index(hash[i], hash[i + 1]);
+        bloom.0[BLOOM_BYTES - 1 - bit / 8] |= 1 << (bit % 8);
+    }
+}
+
+/// Index of the bloom bit selected by a pair of hash bytes
+pub(crate) fn bloom_bit_index(hi: u8, lo: u8) -> usize {
+    (usize::from(hi & 7) << 8) | usize::from(lo)
+}
+
+/// Build the receipts of `txs` from the TxReceipt and TxLog rows of `rws`.
+/// The transactions are identified by their position in the block, starting
+/// at 1.
+pub fn receipts_from_rws(rws: &RwMap, txs: &[Transaction]) -> Vec<Receipt> {
+    let mut receipts: Vec<Receipt> = txs
+        .iter()
+        .map(|tx| Receipt {
+            tx_id: tx.id,
+            tx_type: tx.tx_type,
+            ..Default::default()
+        })
+        .collect();
+    for rw in rws.0.get(&RwTableTag::TxReceipt).into_iter().flatten() {
+        if let Rw::TxReceipt {
+            is_write: true,
+            tx_id,
+            field_tag,
+            value,
+            ..
+        } = *rw
+        {
+            let receipt = &mut receipts[tx_id - 1];
+            match field_tag {
+                TxReceiptFieldTag::PostStateOrStatus => receipt.status = value,
+                TxReceiptFieldTag::CumulativeGasUsed => receipt.cumulative_gas_used = value,
+                TxReceiptFieldTag::LogLength => receipt.logs.resize(value as usize, Log::default()),
+            }
+        }
+    }
+
+    for rw in rws.0.get(&RwTableTag::TxLog).into_iter().flatten() {
+        if let Rw::TxLog {
+            tx_id,
+            log_id,
+            field_tag,
+            index,
+            value,
+            ..
+        } = *rw
+        {
+            let logs = &mut receipts[tx_id - 1].logs;
+            // Log ids start at 1
+            let log_index = log_id as usize - 1;
+            if logs.len() <= log_index {
+                logs.resize(log_index + 1, Log::default());
+            }
+            let log = &mut logs[log_index];
+            match field_tag {
+                TxLogFieldTag::Address => log.address = value.to_address(),
+                TxLogFieldTag::Topic => {
+                    if log.topics.len() <= index {
+                        log.topics.resize(index + 1, Word::zero());
+                    }
+                    log.topics[index] = value;
+                }
+                TxLogFieldTag::Data => {
+                    if log.data.len() <= index {
+                        log.data.resize(index + 1, 0);
+                    }
+                    log.data[index] = value.low_u64() as u8;
+                }
+                TxLogFieldTag::TopicLength => {
+                    log.topics.resize(value.as_usize(), Word::zero());
+                }
+                TxLogFieldTag::DataLength => log.data.resize(value.as_usize(), 0),
+            }
+        }
+    }
+
+    receipts
+}
+
+/// Receipts trie of a block, keyed by the RLP encoding of the index of the
+/// transactions.
+pub fn receipts_trie(receipts: &[Receipt]) -> Trie {
+    list_trie(&receipts.iter().map(Receipt::rlp).collect::<Vec<_>>())
+}
+
+/// Bloom filter of the logs of all the receipts of a block
+pub fn logs_bloom(receipts: &[Receipt]) -> Bloom {
+    let mut bloom = Bloom::zero();
+    for receipt in receipts {
+        for (byte, receipt_byte) in bloom.0.iter_mut().zip(receipt.bloom().0) {
+            *byte |= receipt_byte;
+        }
+    }
+    bloom
+}
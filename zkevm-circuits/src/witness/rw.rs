@@ -2,9 +2,13 @@
 use std::collections::HashMap;
 
 use bus_mapping::operation::{self, AccountField, CallContextField, TxLogField, TxReceiptField};
-use eth_types::{Address, Field, ToAddress, ToLittleEndian, ToScalar, Word, U256};
+use eth_types::{
+    Address, Field, ToAddress, ToBigEndian, ToLittleEndian, ToScalar, Word, H256, U256,
+};
+use ethers_core::utils::keccak256;
 use halo2_proofs::circuit::Value;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::evm_circuit::util::rlc;
 use crate::table::{
@@ -13,7 +17,7 @@ use crate::table::{
 use crate::util::build_tx_log_address;
 
 /// Rw constainer for a witness block
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct RwMap(pub HashMap<RwTableTag, Vec<Rw>>);
 
 impl std::ops::Index<(RwTableTag, usize)> for RwMap {
@@ -24,8 +28,9 @@ impl std::ops::Index<(RwTableTag, usize)> for RwMap {
     }
 }
 impl RwMap {
-    /// Check rw_counter is continuous and starting from 1
-    pub fn check_rw_counter_sanity(&self) {
+    /// Check rw_counter is continuous and starting from 1, or from the first
+    /// rw_counter of the chunk when the block is split in chunks.
+    pub fn check_rw_counter_sanity(&self, rw_counter_start: usize) {
         for (idx, rw_counter) in self
             .0
             .iter()
@@ -35,7 +40,7 @@ impl RwMap {
             .sorted()
             .enumerate()
         {
-            debug_assert_eq!(idx, rw_counter - 1);
+            debug_assert_eq!(idx, rw_counter - rw_counter_start);
         }
     }
     /// Calculates the number of Rw::Start rows needed.
@@ -67,6 +72,43 @@ impl RwMap {
         let padding = (1..=padding_length).map(|rw_counter| Rw::Start { rw_counter });
         (padding.chain(rows.into_iter()).collect(), padding_length)
     }
+    /// Digest of the rows of the rw table of a chunk of a block, chained to
+    /// the commitment of the previous chunks: the keccak hash of
+    /// `prev_commitment` followed by the encoding of every row but the Start
+    /// ones, in the order of their rw counters.
+    pub fn digest(&self, prev_commitment: H256) -> H256 {
+        let rows_bytes = self
+            .0
+            .values()
+            .flatten()
+            .filter(|rw| !matches!(rw, Rw::Start { .. }))
+            .sorted_by_key(|rw| rw.rw_counter())
+            .flat_map(|rw| rw.to_be_bytes());
+        let bytes: Vec<u8> = prev_commitment
+            .as_bytes()
+            .iter()
+            .copied()
+            .chain(rows_bytes)
+            .collect();
+        H256(keccak256(bytes))
+    }
+    /// Row with the highest rw counter, excluding the Start ones
+    pub fn last_row(&self) -> Option<Rw> {
+        self.0
+            .values()
+            .flatten()
+            .filter(|rw| !matches!(rw, Rw::Start { .. }))
+            .max_by_key(|rw| rw.rw_counter())
+            .copied()
+    }
+    /// Commitment to the rows of the rw table of a chunk of a block, chained to
+    /// the commitment of the previous chunks: the keccak hash of the encoding
+    /// of the last row (zero if there are no rows) followed by the digest of
+    /// the rows.  The next chunk opens the last row from the commitment to
+    /// check where it starts.
+    pub fn commitment(&self, prev_commitment: H256) -> H256 {
+        rw_commitment(self.last_row().as_ref(), self.digest(prev_commitment))
+    }
     /// Build Rws for assignment
     pub fn table_assignments(&self) -> Vec<Rw> {
         let mut rows: Vec<Rw> = self.0.values().flatten().cloned().collect();
@@ -84,9 +126,23 @@ impl RwMap {
     }
 }
 
+/// Number of bytes of the encoding of a row (see [`Rw::to_be_bytes`])
+pub(crate) const RW_RECORD_LEN: usize = 174;
+
+/// Commitment to the rows of a chunk whose row with the highest rw counter is
+/// `last_row` and whose digest is `digest` (see [`RwMap::commitment`]).
+pub fn rw_commitment(last_row: Option<&Rw>, digest: H256) -> H256 {
+    let bytes: Vec<u8> = last_row
+        .map_or_else(|| vec![0; RW_RECORD_LEN], |rw| rw.to_be_bytes())
+        .into_iter()
+        .chain(digest.as_bytes().iter().copied())
+        .collect();
+    H256(keccak256(bytes))
+}
+
 /// Read-write records in execution. Rws are used for connecting evm circuit and
 /// state circuits.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Rw {
     /// Start
     Start { rw_counter: usize },
@@ -192,6 +248,16 @@ pub enum Rw {
         field_tag: TxReceiptFieldTag,
         value: u64,
     },
+    /// TransientStorage
+    TransientStorage {
+        rw_counter: usize,
+        is_write: bool,
+        tx_id: usize,
+        account_address: Address,
+        storage_key: Word,
+        value: Word,
+        value_prev: Word,
+    },
 }
 
 /// Rw table row assignment
@@ -210,7 +276,7 @@ pub struct RwRow<F> {
     pub(crate) aux2: F,
 }
 
-impl<F: Field> RwRow<F> {
+impl<F: Copy> RwRow<F> {
     pub(crate) fn values(&self) -> [F; 11] {
         [
             self.rw_counter,
@@ -226,6 +292,9 @@ impl<F: Field> RwRow<F> {
             self.aux2,
         ]
     }
+}
+
+impl<F: Field> RwRow<F> {
     pub(crate) fn rlc(&self, randomness: F) -> F {
         let values = self.values();
         values
@@ -298,6 +367,15 @@ impl Rw {
         }
     }
 
+    pub(crate) fn transient_storage_value_pair(&self) -> (Word, Word) {
+        match self {
+            Self::TransientStorage {
+                value, value_prev, ..
+            } => (*value, *value_prev),
+            _ => unreachable!(),
+        }
+    }
+
     pub(crate) fn call_context_value(&self) -> Word {
         match self {
             Self::CallContext { value, .. } => *value,
@@ -382,6 +460,78 @@ impl Rw {
         }
     }
 
+    /// Value, previous value and committed value of the row as words, which
+    /// are 0 when the row doesn't have them.
+    fn value_words(&self) -> [Word; 3] {
+        match self {
+            Self::Start { .. } => [Word::zero(); 3],
+            Self::TxAccessListAccount {
+                is_warm,
+                is_warm_prev,
+                ..
+            }
+            | Self::TxAccessListAccountStorage {
+                is_warm,
+                is_warm_prev,
+                ..
+            } => [
+                Word::from(*is_warm as u64),
+                Word::from(*is_warm_prev as u64),
+                Word::zero(),
+            ],
+            Self::AccountDestructed {
+                is_destructed,
+                is_destructed_prev,
+                ..
+            } => [
+                Word::from(*is_destructed as u64),
+                Word::from(*is_destructed_prev as u64),
+                Word::zero(),
+            ],
+            Self::TxRefund {
+                value, value_prev, ..
+            } => [Word::from(*value), Word::from(*value_prev), Word::zero()],
+            Self::Account {
+                value, value_prev, ..
+            }
+            | Self::TransientStorage {
+                value, value_prev, ..
+            } => [*value, *value_prev, Word::zero()],
+            Self::AccountStorage {
+                value,
+                value_prev,
+                committed_value,
+                ..
+            } => [*value, *value_prev, *committed_value],
+            Self::CallContext { value, .. }
+            | Self::Stack { value, .. }
+            | Self::TxLog { value, .. } => [*value, Word::zero(), Word::zero()],
+            Self::Memory { byte, .. } => [Word::from(*byte), Word::zero(), Word::zero()],
+            Self::TxReceipt { value, .. } => [Word::from(*value), Word::zero(), Word::zero()],
+        }
+    }
+
+    /// Big endian encoding of the keys and the values of the row, in
+    /// [`RW_RECORD_LEN`] bytes
+    pub(crate) fn to_be_bytes(&self) -> Vec<u8> {
+        [
+            (self.rw_counter() as u64).to_be_bytes().to_vec(),
+            vec![self.is_write() as u8, self.tag() as u8],
+            (self.id().unwrap_or_default() as u64)
+                .to_be_bytes()
+                .to_vec(),
+            self.address().unwrap_or_default().as_bytes().to_vec(),
+            self.field_tag().unwrap_or_default().to_be_bytes().to_vec(),
+            self.storage_key()
+                .unwrap_or_default()
+                .to_be_bytes()
+                .to_vec(),
+        ]
+        .into_iter()
+        .chain(self.value_words().map(|word| word.to_be_bytes().to_vec()))
+        .concat()
+    }
+
     pub(crate) fn rw_counter(&self) -> usize {
         match self {
             Self::Start { rw_counter }
@@ -395,7 +545,8 @@ impl Rw {
             | Self::AccountDestructed { rw_counter, .. }
             | Self::CallContext { rw_counter, .. }
             | Self::TxLog { rw_counter, .. }
-            | Self::TxReceipt { rw_counter, .. } => *rw_counter,
+            | Self::TxReceipt { rw_counter, .. }
+            | Self::TransientStorage { rw_counter, .. } => *rw_counter,
         }
     }
 
@@ -412,7 +563,8 @@ impl Rw {
             | Self::AccountDestructed { is_write, .. }
             | Self::CallContext { is_write, .. }
             | Self::TxLog { is_write, .. }
-            | Self::TxReceipt { is_write, .. } => *is_write,
+            | Self::TxReceipt { is_write, .. }
+            | Self::TransientStorage { is_write, .. } => *is_write,
         }
     }
 
@@ -430,6 +582,7 @@ impl Rw {
             Self::CallContext { .. } => RwTableTag::CallContext,
             Self::TxLog { .. } => RwTableTag::TxLog,
             Self::TxReceipt { .. } => RwTableTag::TxReceipt,
+            Self::TransientStorage { .. } => RwTableTag::TransientStorage,
         }
     }
 
@@ -440,7 +593,8 @@ impl Rw {
             | Self::TxAccessListAccountStorage { tx_id, .. }
             | Self::TxRefund { tx_id, .. }
             | Self::TxLog { tx_id, .. }
-            | Self::TxReceipt { tx_id, .. } => Some(*tx_id),
+            | Self::TxReceipt { tx_id, .. }
+            | Self::TransientStorage { tx_id, .. } => Some(*tx_id),
             Self::CallContext { call_id, .. }
             | Self::Stack { call_id, .. }
             | Self::Memory { call_id, .. } => Some(*call_id),
@@ -464,6 +618,9 @@ impl Rw {
             }
             | Self::AccountDestructed {
                 account_address, ..
+            }
+            | Self::TransientStorage {
+                account_address, ..
             } => Some(*account_address),
             Self::Memory { memory_address, .. } => Some(U256::from(*memory_address).to_address()),
             Self::Stack { stack_pointer, .. } => {
@@ -498,14 +655,16 @@ impl Rw {
             | Self::TxAccessListAccountStorage { .. }
             | Self::TxRefund { .. }
             | Self::TxLog { .. }
-            | Self::AccountDestructed { .. } => None,
+            | Self::AccountDestructed { .. }
+            | Self::TransientStorage { .. } => None,
         }
     }
 
     pub(crate) fn storage_key(&self) -> Option<Word> {
         match self {
             Self::AccountStorage { storage_key, .. }
-            | Self::TxAccessListAccountStorage { storage_key, .. } => Some(*storage_key),
+            | Self::TxAccessListAccountStorage { storage_key, .. }
+            | Self::TransientStorage { storage_key, .. } => Some(*storage_key),
             Self::Start { .. }
             | Self::CallContext { .. }
             | Self::Stack { .. }
@@ -542,9 +701,9 @@ impl Rw {
                 }
                 AccountFieldTag::Nonce | AccountFieldTag::NonExisting => value.to_scalar().unwrap(),
             },
-            Self::AccountStorage { value, .. } | Self::Stack { value, .. } => {
-                rlc::value(&value.to_le_bytes(), randomness)
-            }
+            Self::AccountStorage { value, .. }
+            | Self::Stack { value, .. }
+            | Self::TransientStorage { value, .. } => rlc::value(&value.to_le_bytes(), randomness),
 
             Self::TxLog {
                 field_tag, value, ..
@@ -561,6 +720,27 @@ impl Rw {
         }
     }
 
+    /// Returns whether the value and the previous value of the row are
+    /// assigned as an RLC of their bytes, like in [`Self::value_assignment`],
+    /// instead of as a number.
+    pub(crate) fn is_word_value(&self) -> bool {
+        match self {
+            Self::CallContext { field_tag, .. } => matches!(
+                field_tag,
+                CallContextFieldTag::CodeHash | CallContextFieldTag::Value
+            ),
+            Self::Account { field_tag, .. } => matches!(
+                field_tag,
+                AccountFieldTag::CodeHash | AccountFieldTag::Balance
+            ),
+            Self::AccountStorage { .. } | Self::Stack { .. } | Self::TransientStorage { .. } => {
+                true
+            }
+            Self::TxLog { field_tag, .. } => matches!(field_tag, TxLogFieldTag::Topic),
+            _ => false,
+        }
+    }
+
     pub(crate) fn value_prev_assignment<F: Field>(&self, randomness: F) -> Option<F> {
         match self {
             Self::Account {
@@ -575,7 +755,7 @@ impl Rw {
                     value_prev.to_scalar().unwrap()
                 }
             }),
-            Self::AccountStorage { value_prev, .. } => {
+            Self::AccountStorage { value_prev, .. } | Self::TransientStorage { value_prev, .. } => {
                 Some(rlc::value(&value_prev.to_le_bytes(), randomness))
             }
             Self::TxAccessListAccount { is_warm_prev, .. }
@@ -664,6 +844,22 @@ impl From<&operation::OperationContainer> for RwMap {
                 })
                 .collect(),
         );
+        rws.insert(
+            RwTableTag::TransientStorage,
+            container
+                .transient_storage
+                .iter()
+                .map(|op| Rw::TransientStorage {
+                    rw_counter: op.rwc().into(),
+                    is_write: op.rw().is_write(),
+                    tx_id: op.op().tx_id,
+                    account_address: op.op().address,
+                    storage_key: op.op().key,
+                    value: op.op().value,
+                    value_prev: op.op().value_prev,
+                })
+                .collect(),
+        );
         rws.insert(
             RwTableTag::Account,
             container
@@ -758,6 +954,7 @@ impl From<&operation::OperationContainer> for RwMap {
                         CallContextField::ReversibleWriteCounter => {
                             CallContextFieldTag::ReversibleWriteCounter
                         }
+                        CallContextField::IsInvalidTx => CallContextFieldTag::IsInvalidTx,
                     },
                     value: op.op().value,
                 })
@@ -807,6 +1004,8 @@ impl From<&operation::OperationContainer> for RwMap {
                         TxLogField::Address => TxLogFieldTag::Address,
                         TxLogField::Topic => TxLogFieldTag::Topic,
                         TxLogField::Data => TxLogFieldTag::Data,
+                        TxLogField::TopicLength => TxLogFieldTag::TopicLength,
+                        TxLogField::DataLength => TxLogFieldTag::DataLength,
                     },
                     index: op.op().index,
                     value: op.op().value,
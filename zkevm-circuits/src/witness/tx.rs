@@ -1,22 +1,31 @@
 use bus_mapping::circuit_input_builder;
-use eth_types::{Address, Field, ToLittleEndian, ToScalar, ToWord, Word};
+use eth_types::{geth_types::TxType, Address, Field, ToLittleEndian, ToScalar, ToWord, Word};
 use halo2_proofs::circuit::Value;
+use serde::{Deserialize, Serialize};
 
 use crate::{evm_circuit::util::rlc, table::TxContextFieldTag, util::Challenges};
 
 use super::{step::step_convert, Call, ExecStep};
 
 /// Transaction in a witness block
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
     /// The transaction identifier in the block
     pub id: usize,
+    /// The type of the transaction envelope
+    pub tx_type: TxType,
+    /// The chain id
+    pub chain_id: u64,
     /// The sender account nonce of the transaction
     pub nonce: u64,
     /// The gas limit of the transaction
     pub gas: u64,
     /// The gas price
     pub gas_price: Word,
+    /// The max fee per gas
+    pub max_fee_per_gas: Word,
+    /// The max priority fee per gas
+    pub max_priority_fee_per_gas: Word,
     /// The caller address
     pub caller_address: Address,
     /// The callee address
@@ -31,6 +40,14 @@ pub struct Transaction {
     pub call_data_length: usize,
     /// The gas cost for transaction call data
     pub call_data_gas_cost: u64,
+    /// The number of addresses in the access list
+    pub access_list_addresses_len: u64,
+    /// The number of storage keys in the access list
+    pub access_list_storage_keys_len: u64,
+    /// The number of the block of the batch that contains the transaction
+    pub block_number: u64,
+    /// Whether the transaction is invalid and skipped
+    pub is_invalid: bool,
     /// The calls made in the transaction
     pub calls: Vec<Call>,
     /// The steps executioned in the transaction
@@ -103,6 +120,54 @@ impl Transaction {
                     Value::known(F::zero()),
                     Value::known(F::from(self.call_data_gas_cost)),
                 ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::TxType as u64)),
+                    Value::known(F::zero()),
+                    Value::known(F::from(self.tx_type as u64)),
+                ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::ChainId as u64)),
+                    Value::known(F::zero()),
+                    challenges.evm_word().map(|challenge| {
+                        rlc::value(&Word::from(self.chain_id).to_le_bytes(), challenge)
+                    }),
+                ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::MaxFeePerGas as u64)),
+                    Value::known(F::zero()),
+                    challenges.evm_word().map(|challenge| {
+                        rlc::value(&self.max_fee_per_gas.to_le_bytes(), challenge)
+                    }),
+                ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::MaxPriorityFeePerGas as u64)),
+                    Value::known(F::zero()),
+                    challenges.evm_word().map(|challenge| {
+                        rlc::value(&self.max_priority_fee_per_gas.to_le_bytes(), challenge)
+                    }),
+                ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::AccessListAddressesLen as u64)),
+                    Value::known(F::zero()),
+                    Value::known(F::from(self.access_list_addresses_len)),
+                ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::AccessListStorageKeysLen as u64)),
+                    Value::known(F::zero()),
+                    Value::known(F::from(self.access_list_storage_keys_len)),
+                ],
+                [
+                    Value::known(F::from(self.id as u64)),
+                    Value::known(F::from(TxContextFieldTag::BlockNumber as u64)),
+                    Value::known(F::zero()),
+                    Value::known(F::from(self.block_number)),
+                ],
             ],
             self.call_data
                 .iter()
@@ -121,12 +186,20 @@ impl Transaction {
     }
 }
 
-pub(super) fn tx_convert(tx: &circuit_input_builder::Transaction, id: usize) -> Transaction {
+pub(super) fn tx_convert(
+    tx: &circuit_input_builder::Transaction,
+    id: usize,
+    chain_id: u64,
+) -> Transaction {
     Transaction {
         id,
+        tx_type: tx.tx_type,
+        chain_id,
         nonce: tx.nonce,
         gas: tx.gas,
         gas_price: tx.gas_price,
+        max_fee_per_gas: tx.max_fee_per_gas,
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
         caller_address: tx.from,
         callee_address: tx.to,
         is_create: tx.is_create(),
@@ -137,6 +210,15 @@ pub(super) fn tx_convert(tx: &circuit_input_builder::Transaction, id: usize) ->
             .input
             .iter()
             .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 }),
+        access_list_addresses_len: tx.access_list.0.len() as u64,
+        access_list_storage_keys_len: tx
+            .access_list
+            .0
+            .iter()
+            .map(|item| item.storage_keys.len() as u64)
+            .sum(),
+        block_number: tx.block_num,
+        is_invalid: tx.is_invalid,
         calls: tx
             .calls()
             .iter()